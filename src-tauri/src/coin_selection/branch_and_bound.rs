@@ -0,0 +1,203 @@
+//! Implements the Branch-and-Bound coin selection algorithm of Bitcoin Core,
+//! as described in [An Evaluation of Coin Selection Strategies](https://murch.one/wp-content/uploads/2016/11/erhardt2016coinselection.pdf).
+//! The algorithm searches for a selection of UTXOs that pays the target without a change
+//! output. The excess of such a selection is added to the fee, so it must not exceed the
+//! cost of creating and later spending a change output. If there is no such selection,
+//! the UTXOs are selected by Random-Improve.
+use super::error::CoinSelectionError;
+use super::fee_estimation;
+use super::frozen::FrozenUTXOs;
+use super::random_improve;
+use super::CoinSelection;
+use super::MAX_INPUTS_PER_TX;
+use crate::transactions::script::ScriptType;
+use crate::transactions::utxo::UTXOBox;
+
+static MAX_TRIES: usize = 100000; // the number of visited branches, as in Bitcoin Core
+
+/// Returns a selection of UTXOs without change according to the Branch-and-Bound
+/// coin selection algorithm. Falls back to Random-Improve if there is no such selection.
+/// Frozen UTXOs are never selected.
+/// The fee is estimated for outputs of the specified script types.
+pub fn select_coins(
+    boxed_utxos: Vec<UTXOBox>,
+    frozen: &FrozenUTXOs,
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) -> Result<CoinSelection, CoinSelectionError> {
+    let boxed_utxos = frozen.unfrozen_utxos(boxed_utxos);
+    if let Some(selected_utxos) = search(&boxed_utxos, output_types, target, fee_per_byte) {
+        return Ok(CoinSelection {
+            selected_utxos,
+            change: None,
+        });
+    }
+    random_improve::select_coins(boxed_utxos, frozen, output_types, target, fee_per_byte)
+}
+
+/// Returns the cost of creating a change output and spending it later.
+/// The change output pays to the script type of the first UTXO.
+pub fn cost_of_change(boxed_utxos: &[UTXOBox], fee_per_byte: u64) -> u64 {
+    let change_type = boxed_utxos
+        .first()
+        .map(fee_estimation::utxo_script_type)
+        .unwrap_or(ScriptType::P2PKH);
+    let size =
+        fee_estimation::output_size(&change_type) + fee_estimation::input_vsize(&change_type);
+    size as u64 * fee_per_byte
+}
+
+/// Returns the selection of UTXOs whose value exceeds the target plus the fee by the
+/// smallest amount that is at most the cost of change, or `None` if there is no such
+/// selection. The search visits at most `MAX_TRIES` branches.
+fn search(
+    boxed_utxos: &[UTXOBox],
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) -> Option<Vec<UTXOBox>> {
+    // the effective value of a UTXO is its value minus the fee for spending it
+    let mut candidates: Vec<(u64, &UTXOBox)> = boxed_utxos
+        .iter()
+        .filter_map(|utxo_box| {
+            fee_estimation::effective_value(utxo_box, fee_per_byte).map(|value| (value, utxo_box))
+        })
+        .collect();
+    // the largest UTXOs are tried first, so the search reaches the target quickly
+    candidates.sort_by_key(|(value, _)| std::cmp::Reverse(*value));
+    let values: Vec<u64> = candidates.iter().map(|(value, _)| *value).collect();
+
+    let cost_of_change = cost_of_change(boxed_utxos, fee_per_byte);
+    // the effective target includes the fee of the transaction without inputs
+    let effective_target =
+        target + fee_estimation::estimate_fee_for_scripts(&[], output_types, fee_per_byte);
+    let mut search = Search {
+        values: &values,
+        target: effective_target,
+        upper_bound: effective_target + cost_of_change,
+        tries: 0,
+        selected: Vec::new(),
+        best: None,
+    };
+    search.visit(0, 0, values.iter().sum());
+    let (_, best) = search.best?;
+    let selected_utxos: Vec<UTXOBox> = best
+        .into_iter()
+        .map(|index| candidates[index].1.clone())
+        .collect();
+
+    // the effective values overestimate the fee of each input, but the fee of the
+    // whole transaction is estimated again to be sure that it is paid
+    let input_types = fee_estimation::utxo_script_types(&selected_utxos);
+    let fee = fee_estimation::estimate_fee_for_scripts(&input_types, output_types, fee_per_byte);
+    let selected_amount: u64 = selected_utxos
+        .iter()
+        .map(|utxo_box| utxo_box.utxo.value)
+        .sum();
+    match selected_amount.checked_sub(target + fee) {
+        Some(excess) if excess <= cost_of_change => Some(selected_utxos),
+        _ => None,
+    }
+}
+
+struct Search<'a> {
+    values: &'a [u64], // the effective values in descending order
+    target: u64,
+    upper_bound: u64,
+    tries: usize,
+    selected: Vec<usize>,
+    best: Option<(u64, Vec<usize>)>, // the excess and the indices of the best selection
+}
+
+impl<'a> Search<'a> {
+    /// Visits the branch in which the values before the specified index are decided.
+    /// The available value is the sum of the undecided values.
+    fn visit(&mut self, index: usize, value: u64, available: u64) {
+        self.tries += 1;
+        if self.tries > MAX_TRIES || matches!(self.best, Some((0, _))) {
+            return;
+        }
+        if value > self.upper_bound || value + available < self.target {
+            return;
+        }
+        if value >= self.target {
+            let excess = value - self.target;
+            if !matches!(&self.best, Some((best, _)) if *best <= excess) {
+                self.best = Some((excess, self.selected.clone()));
+            }
+            return;
+        }
+        if index == self.values.len() || self.selected.len() >= MAX_INPUTS_PER_TX {
+            return;
+        }
+        let current = self.values[index];
+        self.selected.push(index);
+        self.visit(index + 1, value + current, available - current);
+        self.selected.pop();
+
+        // omitting a value and including an equal one leads to the same selection
+        let mut next = index + 1;
+        let mut available = available - current;
+        while next < self.values.len() && self.values[next] == current {
+            available -= self.values[next];
+            next += 1;
+        }
+        self.visit(next, value, available);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cost_of_change, select_coins};
+    use crate::coin_selection::{fee_estimation, frozen::FrozenUTXOs};
+    use crate::test_utils::boxed_utxos;
+    use crate::transactions::script::ScriptType;
+
+    #[test]
+    fn test_branch_and_bound() {
+        let output_types = [ScriptType::P2PKH];
+        let frozen = FrozenUTXOs::default();
+        let boxed_utxos = boxed_utxos(&[70000, 40000, 25000, 11000, 9000]);
+        // the UTXOs of 40000 and 11000 pay the target and the fee of two inputs exactly
+        let fee = fee_estimation::estimate_fee(2, 1, 2);
+        let selection =
+            select_coins(boxed_utxos.clone(), &frozen, &output_types, 51000 - fee, 2).unwrap();
+        assert_eq!(selection.change, None);
+        let mut values: Vec<u64> = selection
+            .selected_utxos
+            .iter()
+            .map(|utxo_box| utxo_box.utxo.value)
+            .collect();
+        values.sort();
+        assert_eq!(values, vec![11000, 40000]);
+
+        // an excess below the cost of change is added to the fee
+        let excess = cost_of_change(&boxed_utxos, 2) / 2;
+        let selection = select_coins(
+            boxed_utxos.clone(),
+            &frozen,
+            &output_types,
+            51000 - fee - excess,
+            2,
+        )
+        .unwrap();
+        assert_eq!(selection.change, None);
+        let selected_amount: u64 = selection
+            .selected_utxos
+            .iter()
+            .map(|utxo_box| utxo_box.utxo.value)
+            .sum();
+        assert!(selected_amount <= 51000 + excess);
+
+        // there is no changeless selection, so a change output is created
+        let boxed_utxos = self::boxed_utxos(&[100000]);
+        let selection =
+            select_coins(boxed_utxos.clone(), &frozen, &output_types, 10000, 2).unwrap();
+        assert_eq!(selection.selected_utxos.len(), 1);
+        assert!(selection.change.is_some());
+
+        let err = select_coins(boxed_utxos, &frozen, &output_types, 100000, 2).unwrap_err();
+        assert_eq!(err.to_string(), "balance_insufficient");
+    }
+}
@@ -0,0 +1,118 @@
+//! Implements the minimum number of confirmations that a UTXO needs before it is
+//! selected to fund a transaction. An unconfirmed UTXO can disappear if the transaction
+//! that created it is replaced. The wallet replaces its own transactions only on request,
+//! so the change of these transactions can have a different minimum than UTXOs that were
+//! received from others.
+use crate::transactions::utxo::{UTXOBox, UTXO};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmationPolicy {
+    #[serde(default)]
+    pub min_confirmations: u32, // for UTXOs that were received from others
+    #[serde(default)]
+    pub min_change_confirmations: u32,
+}
+
+impl ConfirmationPolicy {
+    /// Returns true if a UTXO needs at least one confirmation, i.e., if the
+    /// height of the most recent block is needed to filter the UTXOs.
+    pub fn requires_confirmations(&self) -> bool {
+        self.min_confirmations > 0 || self.min_change_confirmations > 0
+    }
+
+    /// Returns the UTXOs that have enough confirmations to be selected. A UTXO that
+    /// pays to one of the change addresses needs the minimum number of confirmations
+    /// of change.
+    pub fn eligible_utxos(
+        &self,
+        boxed_utxos: Vec<UTXOBox>,
+        change_addresses: &[String],
+        tip_height: u32,
+    ) -> Vec<UTXOBox> {
+        boxed_utxos
+            .into_iter()
+            .filter(|utxo_box| {
+                let change = change_addresses.contains(&utxo_box.output.scriptpubkey_address);
+                self.is_eligible(&utxo_box.utxo, change, tip_height)
+            })
+            .collect()
+    }
+
+    /// Returns true if the UTXO has enough confirmations to be selected.
+    pub fn is_eligible(&self, utxo: &UTXO, change: bool, tip_height: u32) -> bool {
+        let min_confirmations = if change {
+            self.min_change_confirmations
+        } else {
+            self.min_confirmations
+        };
+        confirmations(utxo, tip_height) >= min_confirmations
+    }
+}
+
+/// Returns the number of confirmations of the UTXO, given the height of the most
+/// recent block. An unconfirmed UTXO has no confirmations.
+pub fn confirmations(utxo: &UTXO, tip_height: u32) -> u32 {
+    match utxo.status.block_height {
+        Some(block_height) if utxo.status.confirmed => {
+            (tip_height as u64 + 1).saturating_sub(block_height) as u32
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{confirmations, ConfirmationPolicy};
+    use crate::test_utils::seeded_boxed_utxos;
+    use crate::transactions::utxo::UTXOBox;
+
+    // the UTXOs of each seed are received by the same address
+    fn boxed_utxos(utxos: &[(u8, Option<u64>)]) -> Vec<UTXOBox> {
+        let values: Vec<(u8, u64)> = utxos.iter().map(|(seed, _)| (*seed, 10000)).collect();
+        let mut boxed_utxos = seeded_boxed_utxos(&values);
+        for (utxo_box, (_, block_height)) in boxed_utxos.iter_mut().zip(utxos) {
+            utxo_box.utxo.status.confirmed = block_height.is_some();
+            utxo_box.utxo.status.block_height = *block_height;
+        }
+        boxed_utxos
+    }
+
+    fn vouts(boxed_utxos: &[UTXOBox]) -> Vec<u32> {
+        boxed_utxos
+            .iter()
+            .map(|utxo_box| utxo_box.utxo.vout)
+            .collect()
+    }
+
+    #[test]
+    fn test_confirmations() {
+        // the second seed receives the change
+        let boxed_utxos = boxed_utxos(&[(1, None), (2, None), (1, Some(100)), (2, Some(98))]);
+        let change_addresses = vec![boxed_utxos[1].output.scriptpubkey_address.clone()];
+        assert_eq!(confirmations(&boxed_utxos[0].utxo, 100), 0);
+        assert_eq!(confirmations(&boxed_utxos[2].utxo, 100), 1);
+        assert_eq!(confirmations(&boxed_utxos[3].utxo, 100), 3);
+
+        let policy = ConfirmationPolicy::default();
+        assert!(!policy.requires_confirmations());
+        let eligible = policy.eligible_utxos(boxed_utxos.clone(), &change_addresses, 100);
+        assert_eq!(vouts(&eligible), vec![0, 1, 2, 3]);
+
+        // unconfirmed change can be spent, unconfirmed payments cannot
+        let policy = ConfirmationPolicy {
+            min_confirmations: 1,
+            min_change_confirmations: 0,
+        };
+        assert!(policy.requires_confirmations());
+        let eligible = policy.eligible_utxos(boxed_utxos.clone(), &change_addresses, 100);
+        assert_eq!(vouts(&eligible), vec![1, 2, 3]);
+
+        let policy = ConfirmationPolicy {
+            min_confirmations: 2,
+            min_change_confirmations: 3,
+        };
+        let eligible = policy.eligible_utxos(boxed_utxos, &change_addresses, 100);
+        assert_eq!(vouts(&eligible), vec![3]);
+    }
+}
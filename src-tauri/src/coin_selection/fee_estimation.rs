@@ -1,17 +1,59 @@
 //! Estimates the value of the transaction fee based
 //! on the transaction size in bytes and the current
 //! recommended fee per byte.
+//! The size of SegWit inputs is given in virtual bytes,
+//! i.e., the witness is discounted by the witness scale factor.
+use crate::transactions::script::ScriptType;
+use crate::transactions::utxo::UTXOBox;
 use crate::utils::varint;
+use std::str::FromStr;
 
 static TX_INPUT_SIZE: u32 = 147; // in bytes
 static TX_OUTPUT_SIZE: u32 = 34; // in bytes
+static WITNESS_SCALE_FACTOR: u32 = 4;
+static SEGWIT_OVERHEAD_WEIGHT: u32 = 2; // marker and flag
+static SEGWIT_INPUT_SIZE: u32 = 41; // outpoint, empty script signature and sequence
+static P2SH_P2WPKH_INPUT_SIZE: u32 = 64; // the script signature pushes the redeem script
+static P2WPKH_WITNESS_WEIGHT: u32 = 108; // item count, signature and public key
+static P2TR_WITNESS_WEIGHT: u32 = 66; // item count and Schnorr signature (key path)
 
 /// Returns the estimated fee of the transaction.
+/// All inputs and outputs are assumed to be P2PKH.
 pub fn estimate_fee(num_inputs: u32, num_outputs: u32, fee_per_byte: u64) -> u64 {
     (estimate_transaction_size(num_inputs, num_outputs) as u64) * fee_per_byte
 }
 
+/// Returns the estimated fee of a transaction that spends outputs of the input
+/// script types to outputs of the output script types.
+pub fn estimate_fee_for_scripts(
+    input_types: &[ScriptType],
+    output_types: &[ScriptType],
+    fee_per_byte: u64,
+) -> u64 {
+    (estimate_vsize(input_types, output_types) as u64) * fee_per_byte
+}
+
+/// Returns the estimated fee of a transaction that spends outputs of the input script
+/// types to outputs of the output script types and to a change output. The change
+/// output pays to the script type of the first input.
+pub fn estimate_fee_with_change(
+    input_types: &[ScriptType],
+    output_types: &[ScriptType],
+    fee_per_byte: u64,
+) -> u64 {
+    let mut output_types = output_types.to_vec();
+    output_types.push(*input_types.first().unwrap_or(&ScriptType::P2PKH));
+    estimate_fee_for_scripts(input_types, &output_types, fee_per_byte)
+}
+
+/// Returns the size of an OP_RETURN output that carries data of the specified length.
+pub fn op_return_output_size(data_len: usize) -> u32 {
+    let push_size = if data_len > 75 { 2 } else { 1 }; // OP_PUSHDATA1 for longer data
+    8 + 1 + 1 + push_size + data_len as u32 // value, script length, OP_RETURN, push, data
+}
+
 /// Returns the estimated size of the transaction in bytes.
+/// All inputs and outputs are assumed to be P2PKH.
 pub fn estimate_transaction_size(num_inputs: u32, num_outputs: u32) -> u32 {
     let mut size = 4; // version
                       // the cast from usize (vector length) to u32 cannot overflow
@@ -23,3 +65,108 @@ pub fn estimate_transaction_size(num_inputs: u32, num_outputs: u32) -> u32 {
     size += 4; // locktime
     size
 }
+
+/// Returns the estimated virtual size of a transaction that spends outputs of the
+/// input script types to outputs of the output script types.
+pub fn estimate_vsize(input_types: &[ScriptType], output_types: &[ScriptType]) -> u32 {
+    let mut size = 4 + 4; // version and locktime
+    size += varint::encode(input_types.len() as u64).len() as u32;
+    size += varint::encode(output_types.len() as u64).len() as u32;
+    size += output_types.iter().map(output_size).sum::<u32>();
+    let mut weight = size * WITNESS_SCALE_FACTOR;
+    weight += input_types.iter().map(input_weight).sum::<u32>();
+    if input_types.iter().any(is_segwit) {
+        weight += SEGWIT_OVERHEAD_WEIGHT;
+    }
+    weight.div_ceil(WITNESS_SCALE_FACTOR)
+}
+
+/// Returns the estimated weight of an input that spends an output of the script type.
+/// P2SH outputs are assumed to be P2SH-P2WPKH, the only P2SH script of the wallet.
+/// Other inputs, e.g., multisig inputs, are estimated like P2PKH, which overestimates them.
+pub fn input_weight(script_type: &ScriptType) -> u32 {
+    match script_type {
+        ScriptType::P2WPKH => SEGWIT_INPUT_SIZE * WITNESS_SCALE_FACTOR + P2WPKH_WITNESS_WEIGHT,
+        ScriptType::P2SH => P2SH_P2WPKH_INPUT_SIZE * WITNESS_SCALE_FACTOR + P2WPKH_WITNESS_WEIGHT,
+        ScriptType::P2TR => SEGWIT_INPUT_SIZE * WITNESS_SCALE_FACTOR + P2TR_WITNESS_WEIGHT,
+        _ => TX_INPUT_SIZE * WITNESS_SCALE_FACTOR,
+    }
+}
+
+/// Returns the virtual size of an input that spends an output of the script type.
+pub fn input_vsize(script_type: &ScriptType) -> u32 {
+    input_weight(script_type).div_ceil(WITNESS_SCALE_FACTOR)
+}
+
+/// Returns the value of the UTXO minus the fee for spending it,
+/// or `None` if spending the UTXO costs more than its value.
+pub fn effective_value(utxo_box: &UTXOBox, fee_per_byte: u64) -> Option<u64> {
+    let input_fee = input_vsize(&utxo_script_type(utxo_box)) as u64 * fee_per_byte;
+    match utxo_box.utxo.value.checked_sub(input_fee) {
+        Some(value) if value > 0 => Some(value),
+        _ => None,
+    }
+}
+
+/// Returns the size of an output that pays to the script type.
+pub fn output_size(script_type: &ScriptType) -> u32 {
+    // value (8 bytes), script length (1 byte) and script
+    match script_type {
+        ScriptType::P2PKH => TX_OUTPUT_SIZE,
+        ScriptType::P2SH => 8 + 1 + 23,
+        ScriptType::P2WPKH => 8 + 1 + 22,
+        ScriptType::P2WSH | ScriptType::P2TR => 8 + 1 + 34,
+        ScriptType::OpReturn => op_return_output_size(0),
+    }
+}
+
+/// Returns the script type of the output that the UTXO spends.
+/// Unknown script types are estimated like P2PKH.
+pub fn utxo_script_type(utxo_box: &UTXOBox) -> ScriptType {
+    ScriptType::from_str(&utxo_box.output.scriptpubkey_type).unwrap_or(ScriptType::P2PKH)
+}
+
+/// Returns the script types of the outputs that the UTXOs spend.
+pub fn utxo_script_types(boxed_utxos: &[UTXOBox]) -> Vec<ScriptType> {
+    boxed_utxos.iter().map(utxo_script_type).collect()
+}
+
+fn is_segwit(script_type: &ScriptType) -> bool {
+    matches!(
+        script_type,
+        ScriptType::P2SH | ScriptType::P2WPKH | ScriptType::P2WSH | ScriptType::P2TR
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_fee_with_change, estimate_transaction_size, estimate_vsize};
+    use crate::transactions::script::ScriptType;
+
+    #[test]
+    fn test_estimate_vsize() {
+        // P2PKH transactions have no witness
+        let vsize = estimate_vsize(&[ScriptType::P2PKH; 2], &[ScriptType::P2PKH; 2]);
+        assert_eq!(vsize, estimate_transaction_size(2, 2));
+        // the common P2WPKH transaction with one input and two outputs has 141 vbytes
+        let vsize = estimate_vsize(&[ScriptType::P2WPKH], &[ScriptType::P2WPKH; 2]);
+        assert_eq!(vsize, 141);
+        // the common P2SH-P2WPKH transaction with one input and two outputs has 166 vbytes
+        let vsize = estimate_vsize(&[ScriptType::P2SH], &[ScriptType::P2SH; 2]);
+        assert_eq!(vsize, 166);
+        // the common P2TR transaction with one input and two outputs has 154 vbytes
+        let vsize = estimate_vsize(&[ScriptType::P2TR], &[ScriptType::P2TR; 2]);
+        assert_eq!(vsize, 154);
+        // SegWit inputs are much smaller than P2PKH inputs
+        assert!(
+            estimate_vsize(&[ScriptType::P2WPKH; 10], &[ScriptType::P2PKH])
+                < estimate_transaction_size(10, 1) / 2
+        );
+
+        // the change output pays to the script type of the first input
+        assert_eq!(
+            estimate_fee_with_change(&[ScriptType::P2TR], &[ScriptType::P2WPKH], 2),
+            2 * estimate_vsize(&[ScriptType::P2TR], &[ScriptType::P2WPKH, ScriptType::P2TR]) as u64
+        );
+    }
+}
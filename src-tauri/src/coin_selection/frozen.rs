@@ -0,0 +1,97 @@
+//! Implements frozen UTXOs. A frozen UTXO is never selected to fund a transaction
+//! and does not count towards the spendable balance until it is unfrozen.
+//! The frozen UTXOs are stored next to the key file of the wallet.
+use crate::transactions::utxo::UTXOBox;
+use crate::utils::file;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct FrozenUTXOs {
+    outpoints: Vec<(String, u32)>,
+}
+
+impl FrozenUTXOs {
+    /// Freezes the UTXO with the specified transaction ID and output index.
+    /// Returns false if the UTXO was already frozen.
+    pub fn freeze(&mut self, txid: &str, vout: u32) -> bool {
+        if self.is_frozen(txid, vout) {
+            return false;
+        }
+        self.outpoints.push((txid.to_string(), vout));
+        true
+    }
+
+    /// Unfreezes the UTXO with the specified transaction ID and output index.
+    /// Returns false if the UTXO was not frozen.
+    pub fn unfreeze(&mut self, txid: &str, vout: u32) -> bool {
+        let count = self.outpoints.len();
+        self.outpoints
+            .retain(|(frozen_txid, frozen_vout)| frozen_txid != txid || *frozen_vout != vout);
+        self.outpoints.len() != count
+    }
+
+    /// Returns true if the UTXO with the specified transaction ID and output index is frozen.
+    pub fn is_frozen(&self, txid: &str, vout: u32) -> bool {
+        self.outpoints
+            .iter()
+            .any(|(frozen_txid, frozen_vout)| frozen_txid == txid && *frozen_vout == vout)
+    }
+
+    /// Returns the UTXOs that are not frozen.
+    pub fn unfrozen_utxos(&self, boxed_utxos: Vec<UTXOBox>) -> Vec<UTXOBox> {
+        boxed_utxos
+            .into_iter()
+            .filter(|utxo_box| !self.is_frozen(&utxo_box.utxo.txid, utxo_box.utxo.vout))
+            .collect()
+    }
+
+    /// Saves the frozen UTXOs to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let frozen_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &frozen_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the frozen UTXOs from the specified path.
+    /// Returns no frozen UTXOs if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<FrozenUTXOs, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(FrozenUTXOs::default());
+        }
+        let frozen_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&frozen_encoded)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrozenUTXOs;
+    use std::fs;
+
+    #[test]
+    fn test_frozen_utxos() {
+        let path = std::env::temp_dir().join("ghorbu_test_frozen_utxos");
+        let _ = fs::remove_file(&path);
+        let mut frozen = FrozenUTXOs::load(&path).unwrap();
+        assert!(!frozen.is_frozen("aa", 0));
+
+        assert!(frozen.freeze("aa", 0));
+        assert!(!frozen.freeze("aa", 0));
+        assert!(frozen.freeze("bb", 1));
+        frozen.save(&path).unwrap();
+
+        let mut frozen = FrozenUTXOs::load(&path).unwrap();
+        assert!(frozen.is_frozen("aa", 0));
+        assert!(!frozen.is_frozen("aa", 1));
+        assert!(frozen.is_frozen("bb", 1));
+        assert!(frozen.unfreeze("aa", 0));
+        assert!(!frozen.unfreeze("aa", 0));
+        assert!(!frozen.is_frozen("aa", 0));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+//! Implements the knapsack coin selection algorithm that Bitcoin Core used before
+//! Branch-and-Bound. UTXOs that are smaller than the target plus the minimum change are
+//! combined by a stochastic approximation of the best subset. The smallest UTXO that
+//! covers the target on its own is selected instead if the subset is not closer to the target.
+use super::error::CoinSelectionError;
+use super::fee_estimation;
+use super::frozen::FrozenUTXOs;
+use super::MAX_INPUTS_PER_TX;
+use super::{create_selection_with_change, CoinSelection};
+use crate::transactions::script::ScriptType;
+use crate::transactions::transaction::DUST_LIMIT;
+use crate::transactions::utxo::UTXOBox;
+use rand::Rng;
+
+static ITERATIONS: usize = 1000; // the number of random subsets, as in Bitcoin Core
+static MIN_CHANGE: u64 = DUST_LIMIT;
+
+/// Returns a selection of UTXOs according to the knapsack coin selection algorithm.
+/// Frozen UTXOs are never selected.
+/// The fee is estimated for outputs of the specified script types.
+pub fn select_coins(
+    boxed_utxos: Vec<UTXOBox>,
+    frozen: &FrozenUTXOs,
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) -> Result<CoinSelection, CoinSelectionError> {
+    let boxed_utxos = frozen.unfrozen_utxos(boxed_utxos);
+    // the UTXOs are compared by their effective values, so the target only
+    // includes the fee of the transaction without inputs
+    let mut candidates: Vec<(u64, UTXOBox)> = boxed_utxos
+        .into_iter()
+        .filter_map(|utxo_box| {
+            fee_estimation::effective_value(&utxo_box, fee_per_byte).map(|value| (value, utxo_box))
+        })
+        .collect();
+    let change_type = candidates
+        .first()
+        .map(|(_, utxo_box)| fee_estimation::utxo_script_type(utxo_box))
+        .unwrap_or(ScriptType::P2PKH);
+    let mut output_types_with_change = output_types.to_vec();
+    output_types_with_change.push(change_type);
+    let effective_target = target
+        + fee_estimation::estimate_fee_for_scripts(&[], &output_types_with_change, fee_per_byte);
+
+    // a UTXO that matches the target exactly is selected on its own
+    if let Some(index) = candidates
+        .iter()
+        .position(|(value, _)| *value == effective_target)
+    {
+        let (_, utxo_box) = candidates.swap_remove(index);
+        return create_selection_with_change(vec![utxo_box], output_types, target, fee_per_byte);
+    }
+
+    let mut lower: Vec<(u64, UTXOBox)> = Vec::new();
+    let mut lowest_larger: Option<(u64, UTXOBox)> = None;
+    for (value, utxo_box) in candidates {
+        if value < effective_target + MIN_CHANGE {
+            lower.push((value, utxo_box));
+        } else if !matches!(&lowest_larger, Some((lowest, _)) if *lowest <= value) {
+            lowest_larger = Some((value, utxo_box));
+        }
+    }
+    let lower_total: u64 = lower.iter().map(|(value, _)| *value).sum();
+    if lower_total < effective_target || lower.len() > MAX_INPUTS_PER_TX {
+        return match lowest_larger {
+            Some((_, utxo_box)) => {
+                create_selection_with_change(vec![utxo_box], output_types, target, fee_per_byte)
+            }
+            None => Err(CoinSelectionError::new("balance_insufficient".to_string())),
+        };
+    }
+    // the largest UTXOs are tried first
+    lower.sort_by_key(|(value, _)| std::cmp::Reverse(*value));
+    let values: Vec<u64> = lower.iter().map(|(value, _)| *value).collect();
+    let (mut best, mut best_total) = approximate_best_subset(&values, effective_target);
+    // a subset that leaves change should leave at least the minimum change
+    if best_total != effective_target && lower_total >= effective_target + MIN_CHANGE {
+        (best, best_total) = approximate_best_subset(&values, effective_target + MIN_CHANGE);
+    }
+    let selected_utxos = match lowest_larger {
+        Some((value, utxo_box)) if best_total != effective_target && value <= best_total => {
+            vec![utxo_box]
+        }
+        _ => best
+            .iter()
+            .zip(lower)
+            .filter(|(included, _)| **included)
+            .map(|(_, (_, utxo_box))| utxo_box)
+            .collect(),
+    };
+    create_selection_with_change(selected_utxos, output_types, target, fee_per_byte)
+}
+
+/// Returns the subset of the values whose sum is closest to the target without falling
+/// below it, and the sum. The subsets are chosen randomly in two passes: the first pass
+/// includes each value with a probability of one half and the second pass includes the
+/// remaining values until the target is reached.
+fn approximate_best_subset(values: &[u64], target: u64) -> (Vec<bool>, u64) {
+    let mut best = vec![true; values.len()];
+    let mut best_total: u64 = values.iter().sum();
+    let mut rng = rand::thread_rng();
+    for _ in 0..ITERATIONS {
+        if best_total == target {
+            break;
+        }
+        let mut included = vec![false; values.len()];
+        let mut total = 0;
+        let mut reached = false;
+        for pass in 0..2 {
+            if reached {
+                break;
+            }
+            for index in 0..values.len() {
+                let include = if pass == 0 {
+                    rng.gen_bool(0.5)
+                } else {
+                    !included[index]
+                };
+                if !include {
+                    continue;
+                }
+                total += values[index];
+                included[index] = true;
+                if total >= target {
+                    reached = true;
+                    if total < best_total {
+                        best_total = total;
+                        best = included.clone();
+                    }
+                    // the value is removed again to search for a smaller subset
+                    total -= values[index];
+                    included[index] = false;
+                }
+            }
+        }
+    }
+    (best, best_total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::select_coins;
+    use crate::coin_selection::{fee_estimation, frozen::FrozenUTXOs};
+    use crate::test_utils::boxed_utxos;
+    use crate::transactions::script::ScriptType;
+    use crate::transactions::utxo::UTXOBox;
+
+    fn selected_values(selected_utxos: &[UTXOBox]) -> Vec<u64> {
+        let mut values: Vec<u64> = selected_utxos
+            .iter()
+            .map(|utxo_box| utxo_box.utxo.value)
+            .collect();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn test_knapsack() {
+        let output_types = [ScriptType::P2PKH];
+        let frozen = FrozenUTXOs::default();
+        // the fee of a transaction with two inputs and two outputs, one of which is change
+        let fee = fee_estimation::estimate_fee(2, 2, 1);
+        let boxed_utxos = boxed_utxos(&[5000, 7000, 12000, 100000]);
+        // the subset of 5000 and 7000 matches the target exactly
+        let selection =
+            select_coins(boxed_utxos.clone(), &frozen, &output_types, 12000 - fee, 1).unwrap();
+        assert_eq!(selected_values(&selection.selected_utxos).len(), 2);
+        assert_eq!(selection.change, None);
+
+        // the smallest UTXO that covers the target is preferred to a larger subset
+        let selection =
+            select_coins(boxed_utxos.clone(), &frozen, &output_types, 30000, 1).unwrap();
+        assert_eq!(selected_values(&selection.selected_utxos), vec![100000]);
+        assert!(selection.change.unwrap() > 60000);
+
+        // all smaller UTXOs are needed
+        let boxed_utxos = self::boxed_utxos(&[5000, 7000, 12000]);
+        let selection =
+            select_coins(boxed_utxos.clone(), &frozen, &output_types, 20000, 1).unwrap();
+        assert_eq!(
+            selected_values(&selection.selected_utxos),
+            vec![5000, 7000, 12000]
+        );
+
+        let mut frozen = FrozenUTXOs::default();
+        frozen.freeze(&boxed_utxos[2].utxo.txid, 2);
+        let err = select_coins(boxed_utxos, &frozen, &output_types, 20000, 1).unwrap_err();
+        assert_eq!(err.to_string(), "balance_insufficient");
+    }
+}
@@ -1,55 +1,244 @@
 //! Implements the Largest-First coin selection algorithm,
 //! as specified in [CIP-2](https://cips.cardano.org/cips/cip2/).
+//! The selection is post-processed to reduce the change: inputs that are not needed
+//! are removed and inputs are replaced by smaller UTXOs that still cover the target.
 use super::error::CoinSelectionError;
 use super::fee_estimation;
-use super::CoinSelection;
+use super::frozen::FrozenUTXOs;
 use super::MAX_INPUTS_PER_TX;
+use super::{create_selection_with_change, CoinSelection};
+use crate::transactions::script::ScriptType;
 use crate::transactions::utxo::UTXOBox;
 
 /// Returns a selection of UTXOs according to the Largest-First coin
-/// selection algorithm.
+/// selection algorithm. Frozen UTXOs are never selected.
+/// The fee is estimated for outputs of the specified script types.
 pub fn select_coins(
-    mut boxed_utxos: Vec<UTXOBox>,
-    num_outputs: u32,
+    boxed_utxos: Vec<UTXOBox>,
+    frozen: &FrozenUTXOs,
+    output_types: &[ScriptType],
     target: u64,
     fee_per_byte: u64,
 ) -> Result<CoinSelection, CoinSelectionError> {
+    let mut boxed_utxos = frozen.unfrozen_utxos(boxed_utxos);
     // sort UTXOs descendingly with respect to the value
     boxed_utxos.sort_by_key(|utxo_box| -(utxo_box.utxo.value as i128));
 
     let mut selected_utxos = Vec::new();
-    let mut selected_amount = 0;
-    while !boxed_utxos.is_empty() {
-        let utxo = boxed_utxos.remove(0);
-        selected_amount += utxo.utxo.value;
-        selected_utxos.push(utxo);
-
+    while selected_utxos.is_empty()
+        || !covers(
+            total_value(&selected_utxos),
+            &fee_estimation::utxo_script_types(&selected_utxos),
+            output_types,
+            target,
+            fee_per_byte,
+        )
+    {
+        if boxed_utxos.is_empty() {
+            return Err(CoinSelectionError::new("balance_insufficient".to_string()));
+        }
+        selected_utxos.push(boxed_utxos.remove(0));
         if selected_utxos.len() > MAX_INPUTS_PER_TX {
             return Err(CoinSelectionError::new(
                 "max_input_count_exceeded".to_string(),
             ));
         }
-        // add output for potential change output (overestimate fee)
-        let fee = fee_estimation::estimate_fee(
-            selected_utxos.len() as u32,
-            num_outputs + 1,
-            fee_per_byte,
-        );
-        let target_plus_fee = target + fee;
-        if selected_amount < target_plus_fee {
-            continue;
-        } else if selected_amount > target_plus_fee {
-            let change = Some(selected_amount - target_plus_fee);
-            return Ok(CoinSelection {
-                selected_utxos,
-                change,
-            });
+    }
+    trim_inputs(&mut selected_utxos, output_types, target, fee_per_byte);
+    replace_inputs(
+        &mut selected_utxos,
+        &mut boxed_utxos,
+        output_types,
+        target,
+        fee_per_byte,
+    );
+    create_selection_with_change(selected_utxos, output_types, target, fee_per_byte)
+}
+
+/// Removes the inputs that are not needed to cover the target and the fee.
+/// The inputs are in descending order, so the largest unnecessary input is removed first.
+fn trim_inputs(
+    selected_utxos: &mut Vec<UTXOBox>,
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) {
+    let mut index = 0;
+    while index < selected_utxos.len() && selected_utxos.len() > 1 {
+        let amount = total_value(selected_utxos) - selected_utxos[index].utxo.value;
+        let mut input_types = fee_estimation::utxo_script_types(selected_utxos);
+        input_types.remove(index);
+        if covers(amount, &input_types, output_types, target, fee_per_byte) {
+            selected_utxos.remove(index);
         } else {
-            return Ok(CoinSelection {
-                selected_utxos,
-                change: None,
-            });
+            index += 1;
+        }
+    }
+}
+
+/// Replaces each input by the smallest unselected UTXO that is smaller than the input
+/// and still covers the target and the fee. The unselected UTXOs are in descending order.
+fn replace_inputs(
+    selected_utxos: &mut [UTXOBox],
+    boxed_utxos: &mut [UTXOBox],
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) {
+    for index in 0..selected_utxos.len() {
+        let value = selected_utxos[index].utxo.value;
+        let amount = total_value(selected_utxos) - value;
+        let mut input_types = fee_estimation::utxo_script_types(selected_utxos);
+        // the smallest UTXOs are tried first
+        let replacement = boxed_utxos.iter().rposition(|utxo_box| {
+            if utxo_box.utxo.value >= value || amount + utxo_box.utxo.value < target {
+                return false;
+            }
+            input_types[index] = fee_estimation::utxo_script_type(utxo_box);
+            covers(
+                amount + utxo_box.utxo.value,
+                &input_types,
+                output_types,
+                target,
+                fee_per_byte,
+            )
+        });
+        if let Some(position) = replacement {
+            std::mem::swap(&mut selected_utxos[index], &mut boxed_utxos[position]);
+            boxed_utxos.sort_by_key(|utxo_box| -(utxo_box.utxo.value as i128));
+        }
+    }
+}
+
+/// Returns true if the amount covers the target and the fee of a transaction without change.
+fn covers(
+    amount: u64,
+    input_types: &[ScriptType],
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) -> bool {
+    let fee = fee_estimation::estimate_fee_for_scripts(input_types, output_types, fee_per_byte);
+    amount >= target + fee
+}
+
+fn total_value(boxed_utxos: &[UTXOBox]) -> u64 {
+    boxed_utxos.iter().map(|utxo_box| utxo_box.utxo.value).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::select_coins;
+    use crate::coin_selection::{fee_estimation, frozen::FrozenUTXOs, random_improve};
+    use crate::test_utils::boxed_utxos;
+    use crate::transactions::script::ScriptType;
+    use crate::transactions::transaction::DUST_LIMIT;
+    use crate::transactions::utxo::UTXOBox;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn selected_values(selected_utxos: &[UTXOBox]) -> Vec<u64> {
+        let mut values: Vec<u64> = selected_utxos
+            .iter()
+            .map(|utxo_box| utxo_box.utxo.value)
+            .collect();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn test_largest_first() {
+        let output_types = [ScriptType::P2PKH];
+        let frozen = FrozenUTXOs::default();
+        // the largest UTXO is replaced by the smallest UTXO that covers the target
+        let boxed_utxos = boxed_utxos(&[100000, 60000, 50000, 1000]);
+        let selection =
+            select_coins(boxed_utxos.clone(), &frozen, &output_types, 105000, 1).unwrap();
+        assert_eq!(
+            selected_values(&selection.selected_utxos),
+            vec![50000, 60000]
+        );
+
+        // a remainder below the dust limit is added to the fee
+        let fee = fee_estimation::estimate_fee(2, 1, 1);
+        let selection = select_coins(
+            boxed_utxos.clone(),
+            &frozen,
+            &output_types,
+            150000 - fee - DUST_LIMIT / 2,
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            selected_values(&selection.selected_utxos),
+            vec![50000, 100000]
+        );
+        assert_eq!(selection.change, None);
+
+        let err = select_coins(boxed_utxos, &frozen, &output_types, 300000, 1).unwrap_err();
+        assert_eq!(err.to_string(), "balance_insufficient");
+    }
+
+    #[test]
+    fn test_largest_first_properties() {
+        let output_types = [ScriptType::P2PKH];
+        let frozen = FrozenUTXOs::default();
+        // the seed is fixed, so a failure can be reproduced
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let count = rng.gen_range(1..20);
+            let values: Vec<u64> = (0..count).map(|_| rng.gen_range(1000..100000)).collect();
+            let boxed_utxos = boxed_utxos(&values);
+            let target = rng.gen_range(DUST_LIMIT..500000);
+            let fee_per_byte = rng.gen_range(1..20);
+
+            let largest_first = select_coins(
+                boxed_utxos.clone(),
+                &frozen,
+                &output_types,
+                target,
+                fee_per_byte,
+            );
+            let random_improve = random_improve::select_coins(
+                boxed_utxos,
+                &frozen,
+                &output_types,
+                target,
+                fee_per_byte,
+            );
+            let selection = match (largest_first, random_improve) {
+                (Ok(selection), Ok(random_improve)) => {
+                    // the largest UTXOs cover the target with the fewest inputs
+                    assert!(selection.selected_utxos.len() <= random_improve.selected_utxos.len());
+                    selection
+                }
+                (Err(_), Err(_)) => continue,
+                (Ok(selection), Err(_)) => selection,
+                (Err(err), Ok(_)) => panic!("largest-first failed: {}", err),
+            };
+
+            // the selection covers the target and the fee and the change is not dust
+            let selected_amount: u64 = selection
+                .selected_utxos
+                .iter()
+                .map(|utxo_box| utxo_box.utxo.value)
+                .sum();
+            let num_inputs = selection.selected_utxos.len() as u32;
+            match selection.change {
+                Some(change) => {
+                    assert!(change >= DUST_LIMIT);
+                    let fee = fee_estimation::estimate_fee(num_inputs, 2, fee_per_byte);
+                    assert_eq!(selected_amount, target + fee + change);
+                }
+                None => {
+                    let fee = fee_estimation::estimate_fee(num_inputs, 1, fee_per_byte);
+                    assert!(selected_amount >= target + fee);
+                }
+            }
+            // no input can be removed
+            for utxo_box in selection.selected_utxos.iter() {
+                let fee = fee_estimation::estimate_fee(num_inputs - 1, 1, fee_per_byte);
+                assert!(selected_amount - utxo_box.utxo.value < target + fee);
+            }
         }
     }
-    Err(CoinSelectionError::new("balance_insufficient".to_string()))
 }
@@ -0,0 +1,118 @@
+//! Implements manual coin selection (coin control). The UTXOs to spend are
+//! specified by their outpoints instead of being chosen by an algorithm.
+use super::error::CoinSelectionError;
+use super::frozen::FrozenUTXOs;
+use super::MAX_INPUTS_PER_TX;
+use super::{create_selection_with_change, CoinSelection};
+use crate::transactions::script::ScriptType;
+use crate::transactions::utxo::UTXOBox;
+
+/// Returns the UTXOs with the specified outpoints (transaction ID and output index).
+/// All selected UTXOs are spent, even if a subset would cover the target.
+/// A remainder below the dust limit is added to the fee instead of creating change.
+/// Returns an error if a specified UTXO is frozen.
+pub fn select_coins(
+    boxed_utxos: Vec<UTXOBox>,
+    frozen: &FrozenUTXOs,
+    outpoints: &[(String, u32)],
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) -> Result<CoinSelection, CoinSelectionError> {
+    let mut selected_utxos: Vec<UTXOBox> = Vec::new();
+    for (txid, vout) in outpoints {
+        if frozen.is_frozen(txid, *vout) {
+            return Err(CoinSelectionError::new("utxo_frozen_error".to_string()));
+        }
+        let is_outpoint =
+            |utxo_box: &UTXOBox| utxo_box.utxo.txid == *txid && utxo_box.utxo.vout == *vout;
+        if selected_utxos.iter().any(is_outpoint) {
+            continue;
+        }
+        match boxed_utxos.iter().find(|utxo_box| is_outpoint(utxo_box)) {
+            Some(utxo_box) => selected_utxos.push(utxo_box.clone()),
+            None => {
+                return Err(CoinSelectionError::new(
+                    "utxo_unavailable_error".to_string(),
+                ))
+            }
+        }
+    }
+    if selected_utxos.len() > MAX_INPUTS_PER_TX {
+        return Err(CoinSelectionError::new(
+            "max_input_count_exceeded".to_string(),
+        ));
+    }
+    create_selection_with_change(selected_utxos, output_types, target, fee_per_byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::select_coins;
+    use crate::coin_selection::{fee_estimation, frozen::FrozenUTXOs};
+    use crate::test_utils::boxed_utxos;
+    use crate::transactions::script::ScriptType;
+
+    #[test]
+    fn test_manual_selection() {
+        let txid = String::from("d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a");
+        let boxed_utxos = boxed_utxos(&[50000, 20000, 10000]);
+        let mut frozen = FrozenUTXOs::default();
+        let output_types = [ScriptType::P2PKH];
+        // the largest UTXO would suffice, but the specified UTXOs are spent
+        let outpoints = vec![(txid.clone(), 2), (txid.clone(), 1), (txid.clone(), 2)];
+        let selection = select_coins(
+            boxed_utxos.clone(),
+            &frozen,
+            &outpoints,
+            &output_types,
+            5000,
+            1,
+        )
+        .unwrap();
+        assert_eq!(selection.selected_utxos.len(), 2);
+        assert_eq!(selection.selected_utxos[0].utxo.vout, 2);
+        assert_eq!(selection.selected_utxos[1].utxo.vout, 1);
+        let fee = fee_estimation::estimate_fee(2, 2, 1);
+        assert_eq!(selection.change, Some(30000 - 5000 - fee));
+
+        // the remainder is below the dust limit
+        let fee = fee_estimation::estimate_fee(1, 1, 1);
+        let target = 10000 - fee - 100;
+        let selection = select_coins(
+            boxed_utxos.clone(),
+            &frozen,
+            &[(txid.clone(), 2)],
+            &output_types,
+            target,
+            1,
+        )
+        .unwrap();
+        assert_eq!(selection.change, None);
+
+        let err = select_coins(
+            boxed_utxos.clone(),
+            &frozen,
+            &[(txid.clone(), 2)],
+            &output_types,
+            10000,
+            1,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "balance_insufficient");
+        let err = select_coins(
+            boxed_utxos.clone(),
+            &frozen,
+            &[(txid.clone(), 3)],
+            &output_types,
+            1000,
+            1,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "utxo_unavailable_error");
+        frozen.freeze(&txid, 0);
+        let err =
+            select_coins(boxed_utxos, &frozen, &[(txid, 0)], &output_types, 1000, 1).unwrap_err();
+        assert_eq!(err.to_string(), "utxo_frozen_error");
+    }
+}
@@ -1,15 +1,88 @@
 //! Implements the coin selection strategy.
+pub mod branch_and_bound;
+pub mod confirmations;
 pub mod error;
 pub mod fee_estimation;
+pub mod frozen;
+pub mod knapsack;
 pub mod largest_first;
+pub mod manual;
+pub mod privacy;
 pub mod random_improve;
+pub mod reservation;
+pub mod strategy;
+pub mod sweep;
+use crate::transactions::script::ScriptType;
+use crate::transactions::transaction::DUST_LIMIT;
 use crate::transactions::utxo::UTXOBox;
+use error::CoinSelectionError;
+use frozen::FrozenUTXOs;
 use serde::{Deserialize, Serialize};
 
-static MAX_INPUTS_PER_TX: usize = 2048; // this value is not set in stone
+pub static MAX_INPUTS_PER_TX: usize = 2048; // this value is not set in stone
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CoinSelection {
     pub selected_utxos: Vec<UTXOBox>,
     pub change: Option<u64>,
 }
+
+/// Returns the selection of the UTXOs with the change that remains after paying the
+/// target and the fee. A remainder below the dust limit is added to the fee.
+/// Returns an error if the UTXOs do not cover the target and the fee.
+pub fn create_selection_with_change(
+    selected_utxos: Vec<UTXOBox>,
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) -> Result<CoinSelection, CoinSelectionError> {
+    let input_types = fee_estimation::utxo_script_types(&selected_utxos);
+    let selected_amount: u64 = selected_utxos
+        .iter()
+        .map(|utxo_box| utxo_box.utxo.value)
+        .sum();
+    let fee = fee_estimation::estimate_fee_for_scripts(&input_types, output_types, fee_per_byte);
+    if selected_amount < target + fee {
+        return Err(CoinSelectionError::new("balance_insufficient".to_string()));
+    }
+    let fee_with_change =
+        fee_estimation::estimate_fee_with_change(&input_types, output_types, fee_per_byte);
+    let change = match selected_amount.checked_sub(target + fee_with_change) {
+        Some(change) if change >= DUST_LIMIT => Some(change),
+        _ => None,
+    };
+    Ok(CoinSelection {
+        selected_utxos,
+        change,
+    })
+}
+
+/// Returns the selection of the single UTXO that covers the target and the fee without
+/// change, or `None` if there is no such UTXO. The excess of the UTXO must be below the
+/// dust limit, since it is added to the fee. If several UTXOs qualify, the UTXO with the
+/// smallest excess is selected. Frozen UTXOs are never selected.
+pub fn select_exact_match(
+    boxed_utxos: &[UTXOBox],
+    frozen: &FrozenUTXOs,
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) -> Option<CoinSelection> {
+    boxed_utxos
+        .iter()
+        .filter(|utxo_box| !frozen.is_frozen(&utxo_box.utxo.txid, utxo_box.utxo.vout))
+        .filter_map(|utxo_box| {
+            let input_types = [fee_estimation::utxo_script_type(utxo_box)];
+            let fee =
+                fee_estimation::estimate_fee_for_scripts(&input_types, output_types, fee_per_byte);
+            match utxo_box.utxo.value.checked_sub(target + fee) {
+                Some(excess) if excess < DUST_LIMIT => Some((excess, utxo_box)),
+                _ => None,
+            }
+        })
+        .min_by_key(|(excess, _)| *excess)
+        .map(|(_, utxo_box)| CoinSelection {
+            selected_utxos: vec![utxo_box.clone()],
+            change: None,
+        })
+}
@@ -0,0 +1,119 @@
+//! Implements privacy-aware coin selection. Spending UTXOs of different addresses in the
+//! same transaction reveals that the addresses belong to the same wallet. Therefore, the
+//! UTXOs are selected per address: all UTXOs of an address are spent together and as few
+//! addresses as possible are combined.
+use super::error::CoinSelectionError;
+use super::frozen::FrozenUTXOs;
+use super::{create_selection_with_change, CoinSelection, MAX_INPUTS_PER_TX};
+use crate::transactions::script::ScriptType;
+use crate::transactions::utxo::UTXOBox;
+use std::cmp::Reverse;
+
+/// Returns a selection of UTXOs that links as few addresses as possible.
+/// If the UTXOs of a single address cover the target and the fee, the address with
+/// the smallest balance among those is spent. Otherwise, the addresses with the largest
+/// balances are combined. Frozen UTXOs are never selected.
+/// The fee is estimated for outputs of the specified script types.
+pub fn select_coins(
+    boxed_utxos: Vec<UTXOBox>,
+    frozen: &FrozenUTXOs,
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) -> Result<CoinSelection, CoinSelectionError> {
+    let mut groups = group_by_address(frozen.unfrozen_utxos(boxed_utxos));
+    let group_value =
+        |group: &Vec<UTXOBox>| -> u64 { group.iter().map(|utxo_box| utxo_box.utxo.value).sum() };
+
+    let covering_group = groups
+        .iter()
+        .filter(|group| group.len() <= MAX_INPUTS_PER_TX)
+        .filter_map(|group| {
+            create_selection_with_change(group.clone(), output_types, target, fee_per_byte).ok()
+        })
+        .min_by_key(|selection| group_value(&selection.selected_utxos));
+    if let Some(selection) = covering_group {
+        return Ok(selection);
+    }
+
+    groups.sort_by_key(|group| Reverse(group_value(group)));
+    let mut selected_utxos: Vec<UTXOBox> = Vec::new();
+    for group in groups {
+        if selected_utxos.len() + group.len() > MAX_INPUTS_PER_TX {
+            continue;
+        }
+        selected_utxos.extend(group);
+        let selection = create_selection_with_change(
+            selected_utxos.clone(),
+            output_types,
+            target,
+            fee_per_byte,
+        );
+        if selection.is_ok() {
+            return selection;
+        }
+    }
+    Err(CoinSelectionError::new("balance_insufficient".to_string()))
+}
+
+/// Returns the UTXOs grouped by the address of their outputs.
+/// The groups are in the order in which their addresses first occur.
+fn group_by_address(boxed_utxos: Vec<UTXOBox>) -> Vec<Vec<UTXOBox>> {
+    let mut groups: Vec<Vec<UTXOBox>> = Vec::new();
+    for utxo_box in boxed_utxos {
+        let address = &utxo_box.output.scriptpubkey_address;
+        match groups
+            .iter_mut()
+            .find(|group| group[0].output.scriptpubkey_address == *address)
+        {
+            Some(group) => group.push(utxo_box),
+            None => groups.push(vec![utxo_box]),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::select_coins;
+    use crate::coin_selection::frozen::FrozenUTXOs;
+    use crate::test_utils::seeded_boxed_utxos;
+    use crate::transactions::script::ScriptType;
+    use crate::transactions::utxo::UTXOBox;
+
+    fn selected_vouts(boxed_utxos: &[UTXOBox]) -> Vec<u32> {
+        let mut vouts: Vec<u32> = boxed_utxos
+            .iter()
+            .map(|utxo_box| utxo_box.utxo.vout)
+            .collect();
+        vouts.sort();
+        vouts
+    }
+
+    #[test]
+    fn test_privacy() {
+        let boxed_utxos =
+            seeded_boxed_utxos(&[(1, 30000), (2, 40000), (3, 10000), (1, 25000), (3, 5000)]);
+        let frozen = FrozenUTXOs::default();
+        let output_types = [ScriptType::P2PKH];
+
+        // only the first address covers the target, so both of its UTXOs are spent
+        let selection =
+            select_coins(boxed_utxos.clone(), &frozen, &output_types, 50000, 1).unwrap();
+        assert_eq!(selected_vouts(&selection.selected_utxos), vec![0, 3]);
+
+        // the address with the smallest balance that covers the target is spent
+        let selection =
+            select_coins(boxed_utxos.clone(), &frozen, &output_types, 20000, 1).unwrap();
+        assert_eq!(selected_vouts(&selection.selected_utxos), vec![1]);
+        assert!(selection.change.is_some());
+
+        // no address covers the target, so the largest balances are combined
+        let selection =
+            select_coins(boxed_utxos.clone(), &frozen, &output_types, 80000, 1).unwrap();
+        assert_eq!(selected_vouts(&selection.selected_utxos), vec![0, 1, 3]);
+
+        let err = select_coins(boxed_utxos, &frozen, &output_types, 200000, 1).unwrap_err();
+        assert_eq!(err.to_string(), "balance_insufficient");
+    }
+}
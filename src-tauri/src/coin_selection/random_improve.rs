@@ -2,37 +2,48 @@
 //! as specified in [CIP-2](https://cips.cardano.org/cips/cip2/).
 use super::error::CoinSelectionError;
 use super::fee_estimation;
+use super::frozen::FrozenUTXOs;
 use super::largest_first;
 use super::CoinSelection;
 use super::MAX_INPUTS_PER_TX;
+use crate::transactions::script::ScriptType;
 use crate::transactions::utxo::UTXOBox;
 use rand::Rng;
 use std::collections::HashMap;
 
 /// Returns a selection of UTXOs according to the Random-Improve coin
-/// selection algorithm.
+/// selection algorithm. Frozen UTXOs are never selected.
+/// The fee is estimated for outputs of the specified script types.
 pub fn select_coins(
-    mut boxed_utxos: Vec<UTXOBox>,
-    num_outputs: u32,
+    boxed_utxos: Vec<UTXOBox>,
+    frozen: &FrozenUTXOs,
+    output_types: &[ScriptType],
     target: u64,
     fee_per_byte: u64,
 ) -> Result<CoinSelection, CoinSelectionError> {
+    let mut boxed_utxos = frozen.unfrozen_utxos(boxed_utxos);
     // Phase 1: Random selection
     let mut selected_utxos = Vec::new();
     let mut selected_amount = 0;
     let mut target_plus_fee = target;
     while !boxed_utxos.is_empty() {
         if selected_utxos.len() > MAX_INPUTS_PER_TX {
-            return largest_first::select_coins(boxed_utxos, num_outputs, target, fee_per_byte);
+            return largest_first::select_coins(
+                boxed_utxos,
+                frozen,
+                output_types,
+                target,
+                fee_per_byte,
+            );
         }
         let index: usize = rand::thread_rng().gen_range(0..boxed_utxos.len());
         let utxo = boxed_utxos.remove(index);
         selected_amount += utxo.utxo.value;
         selected_utxos.push(utxo);
         // add output for potential change output (overestimate fee)
-        let fee = fee_estimation::estimate_fee(
-            selected_utxos.len() as u32,
-            num_outputs + 1,
+        let fee = fee_estimation::estimate_fee_with_change(
+            &fee_estimation::utxo_script_types(&selected_utxos),
+            output_types,
             fee_per_byte,
         );
         target_plus_fee = target + fee;
@@ -41,7 +52,7 @@ pub fn select_coins(
         }
     }
     if selected_amount < target_plus_fee {
-        return Err(CoinSelectionError::new("Balance insufficient".to_string()));
+        return Err(CoinSelectionError::new("balance_insufficient".to_string()));
     }
 
     // Phase 2: Improvement
@@ -54,11 +65,10 @@ pub fn select_coins(
         let map_index = indices.get(index).unwrap();
         let utxo = boxed_utxos_map.get(map_index).unwrap();
 
-        let fee = fee_estimation::estimate_fee(
-            (selected_utxos.len() + 1) as u32,
-            num_outputs + 1,
-            fee_per_byte,
-        );
+        let mut input_types = fee_estimation::utxo_script_types(&selected_utxos);
+        input_types.push(fee_estimation::utxo_script_type(utxo));
+        let fee =
+            fee_estimation::estimate_fee_with_change(&input_types, output_types, fee_per_byte);
         target_plus_fee = (target + fee) as i128;
         let ideal = target_plus_fee * 2;
         let maximum = target_plus_fee * 3;
@@ -91,8 +101,9 @@ pub fn select_coins(
 
 #[cfg(test)]
 mod tests {
-    use crate::coin_selection::random_improve;
+    use crate::coin_selection::{frozen::FrozenUTXOs, random_improve};
     use crate::keys::{bip32::ExtendedPrivateKey, bip44::private_hierarchy::Keypair};
+    use crate::transactions::script::ScriptType;
     use crate::transactions::transaction::TransactionOutput;
     use crate::transactions::utxo::{UTXOBox, UTXOStatus, UTXO};
     use num_bigint::BigUint;
@@ -143,8 +154,23 @@ mod tests {
             output,
             keypair,
         };
-        let selected_coins = random_improve::select_coins(vec![utxo_box], 1, target, 8).unwrap();
+        let mut frozen = FrozenUTXOs::default();
+        let selected_coins = random_improve::select_coins(
+            vec![utxo_box.clone()],
+            &frozen,
+            &[ScriptType::P2PKH],
+            target,
+            8,
+        )
+        .unwrap();
         assert!(selected_coins.change.unwrap() < balance);
         assert!(selected_coins.change.unwrap() + target <= balance);
+
+        // frozen UTXOs are excluded
+        frozen.freeze(&utxo_box.utxo.txid, utxo_box.utxo.vout);
+        let err =
+            random_improve::select_coins(vec![utxo_box], &frozen, &[ScriptType::P2PKH], target, 8)
+                .unwrap_err();
+        assert_eq!(err.to_string(), "balance_insufficient");
     }
 }
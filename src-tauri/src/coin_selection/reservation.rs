@@ -0,0 +1,164 @@
+//! Implements reservations of UTXOs and change keys for sends that are in progress.
+//! A concurrent send skips reserved UTXOs and change indices, so it cannot
+//! spend the same coins or reuse the same change key.
+use super::error::CoinSelectionError;
+use crate::transactions::utxo::UTXOBox;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default)]
+pub struct ReservationTable {
+    utxos: HashMap<(String, u32), u64>,
+    change_keys: HashMap<(u32, u32, u32), u64>,
+    next_lease_id: u64,
+}
+
+/// Holds the reservations of a send. The reservations are released
+/// when the lease is dropped.
+#[derive(Debug)]
+pub struct Lease {
+    table: Arc<Mutex<ReservationTable>>,
+    id: u64,
+}
+
+impl ReservationTable {
+    /// Returns true if the specified UTXO is reserved.
+    pub fn is_utxo_reserved(&self, boxed_utxo: &UTXOBox) -> bool {
+        self.is_outpoint_reserved(&boxed_utxo.utxo.txid, boxed_utxo.utxo.vout)
+    }
+
+    /// Returns true if the UTXO with the specified transaction ID and output index is reserved.
+    pub fn is_outpoint_reserved(&self, txid: &str, vout: u32) -> bool {
+        self.utxos.contains_key(&(txid.to_string(), vout))
+    }
+
+    /// Returns the UTXOs that are not reserved.
+    pub fn unreserved_utxos(&self, boxed_utxos: Vec<UTXOBox>) -> Vec<UTXOBox> {
+        boxed_utxos
+            .into_iter()
+            .filter(|boxed_utxo| !self.is_utxo_reserved(boxed_utxo))
+            .collect()
+    }
+
+    /// Returns the first change index of the specified account that is
+    /// greater than or equal to `first_index` and not reserved.
+    pub fn next_change_index(
+        &self,
+        coin_type_index: u32,
+        account_index: u32,
+        first_index: u32,
+    ) -> u32 {
+        let mut index = first_index;
+        while self
+            .change_keys
+            .contains_key(&(coin_type_index, account_index, index))
+        {
+            index += 1;
+        }
+        index
+    }
+
+    /// Reserves the specified UTXOs and change key.
+    /// Returns the lease ID or an error if any of them is already reserved.
+    pub fn reserve(
+        &mut self,
+        boxed_utxos: &[UTXOBox],
+        change_key: Option<(u32, u32, u32)>,
+    ) -> Result<u64, CoinSelectionError> {
+        let outpoints: Vec<(String, u32)> = boxed_utxos
+            .iter()
+            .map(|boxed_utxo| (boxed_utxo.utxo.txid.clone(), boxed_utxo.utxo.vout))
+            .collect();
+        self.reserve_outpoints(&outpoints, change_key)
+    }
+
+    /// Reserves the UTXOs with the specified transaction IDs and output indices, e.g.,
+    /// the inputs of a journaled transaction, and the change key.
+    /// Returns the lease ID or an error if any of them is already reserved.
+    pub fn reserve_outpoints(
+        &mut self,
+        outpoints: &[(String, u32)],
+        change_key: Option<(u32, u32, u32)>,
+    ) -> Result<u64, CoinSelectionError> {
+        if outpoints
+            .iter()
+            .any(|(txid, vout)| self.is_outpoint_reserved(txid, *vout))
+        {
+            return Err(CoinSelectionError::new("utxo_reserved_error".to_string()));
+        }
+        if let Some(change_key) = change_key {
+            if self.change_keys.contains_key(&change_key) {
+                return Err(CoinSelectionError::new(
+                    "change_key_reserved_error".to_string(),
+                ));
+            }
+        }
+        let lease_id = self.next_lease_id;
+        self.next_lease_id += 1;
+        for outpoint in outpoints {
+            self.utxos.insert(outpoint.clone(), lease_id);
+        }
+        if let Some(change_key) = change_key {
+            self.change_keys.insert(change_key, lease_id);
+        }
+        Ok(lease_id)
+    }
+
+    /// Releases all reservations of the specified lease.
+    pub fn release(&mut self, lease_id: u64) {
+        self.utxos.retain(|_, id| *id != lease_id);
+        self.change_keys.retain(|_, id| *id != lease_id);
+    }
+}
+
+impl Lease {
+    /// Creates a lease for reservations that were made in the specified table.
+    pub fn new(table: Arc<Mutex<ReservationTable>>, id: u64) -> Lease {
+        Lease { table, id }
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.table.lock().unwrap().release(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lease, ReservationTable};
+    use crate::test_utils::{boxed_utxo, keypair};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_reserve_and_release() {
+        let table = Arc::new(Mutex::new(ReservationTable::default()));
+        let keypair = keypair(0);
+        let utxos = vec![
+            boxed_utxo(&keypair, "aa", 0, 1000),
+            boxed_utxo(&keypair, "aa", 1, 1000),
+        ];
+        let lease_id = table
+            .lock()
+            .unwrap()
+            .reserve(&utxos[..1], Some((1, 0, 3)))
+            .unwrap();
+        let lease = Lease::new(table.clone(), lease_id);
+        {
+            let mut table = table.lock().unwrap();
+            let unreserved = table.unreserved_utxos(utxos.clone());
+            assert_eq!(unreserved.len(), 1);
+            assert_eq!(unreserved[0].utxo.vout, 1);
+            assert_eq!(table.next_change_index(1, 0, 3), 4);
+            assert_eq!(table.next_change_index(1, 1, 3), 3);
+            assert!(table.reserve(&utxos[..1], None).is_err());
+            assert!(table.reserve(&utxos[1..], Some((1, 0, 3))).is_err());
+            let outpoints = [("aa".to_string(), 0)];
+            assert!(table.reserve_outpoints(&outpoints, None).is_err());
+        }
+        drop(lease);
+        let table = table.lock().unwrap();
+        assert_eq!(table.unreserved_utxos(utxos).len(), 2);
+        assert_eq!(table.next_change_index(1, 0, 3), 3);
+    }
+}
@@ -0,0 +1,212 @@
+//! Implements the selection of the coin selection algorithm. The strategy is a
+//! setting of the wallet and can be overridden for a single transaction.
+use super::error::CoinSelectionError;
+use super::frozen::FrozenUTXOs;
+use super::{
+    branch_and_bound, knapsack, largest_first, manual, privacy, random_improve, select_exact_match,
+    CoinSelection,
+};
+use crate::transactions::script::ScriptType;
+use crate::transactions::utxo::UTXOBox;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CoinSelectionStrategy {
+    RandomImprove,
+    LargestFirst,
+    #[default]
+    BranchAndBound,
+    Knapsack,
+    Privacy, // the UTXOs of as few addresses as possible are spent
+    Manual,  // the UTXOs are specified by their outpoints
+}
+
+/// Returns a selection of UTXOs according to the strategy. Specified outpoints are
+/// always selected manually, whatever the strategy. Returns an error if the strategy
+/// is manual and no outpoints are specified.
+/// A single UTXO that pays the target without change is selected before the strategy runs.
+pub fn select_coins(
+    strategy: CoinSelectionStrategy,
+    boxed_utxos: Vec<UTXOBox>,
+    frozen: &FrozenUTXOs,
+    outpoints: Option<&[(String, u32)]>,
+    output_types: &[ScriptType],
+    target: u64,
+    fee_per_byte: u64,
+) -> Result<CoinSelection, CoinSelectionError> {
+    if let Some(outpoints) = outpoints {
+        return manual::select_coins(
+            boxed_utxos,
+            frozen,
+            outpoints,
+            output_types,
+            target,
+            fee_per_byte,
+        );
+    }
+    if strategy != CoinSelectionStrategy::Manual {
+        if let Some(selection) =
+            select_exact_match(&boxed_utxos, frozen, output_types, target, fee_per_byte)
+        {
+            return Ok(selection);
+        }
+    }
+    let select_coins = match strategy {
+        CoinSelectionStrategy::RandomImprove => random_improve::select_coins,
+        CoinSelectionStrategy::LargestFirst => largest_first::select_coins,
+        CoinSelectionStrategy::BranchAndBound => branch_and_bound::select_coins,
+        CoinSelectionStrategy::Knapsack => knapsack::select_coins,
+        CoinSelectionStrategy::Privacy => privacy::select_coins,
+        CoinSelectionStrategy::Manual => {
+            return Err(CoinSelectionError::new(
+                "outpoints_missing_error".to_string(),
+            ))
+        }
+    };
+    select_coins(boxed_utxos, frozen, output_types, target, fee_per_byte)
+}
+
+impl FromStr for CoinSelectionStrategy {
+    type Err = CoinSelectionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random_improve" => Ok(CoinSelectionStrategy::RandomImprove),
+            "largest_first" => Ok(CoinSelectionStrategy::LargestFirst),
+            "branch_and_bound" => Ok(CoinSelectionStrategy::BranchAndBound),
+            "knapsack" => Ok(CoinSelectionStrategy::Knapsack),
+            "privacy" => Ok(CoinSelectionStrategy::Privacy),
+            "manual" => Ok(CoinSelectionStrategy::Manual),
+            _ => Err(CoinSelectionError::new(
+                "invalid_strategy_error".to_string(),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CoinSelectionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            CoinSelectionStrategy::RandomImprove => "random_improve",
+            CoinSelectionStrategy::LargestFirst => "largest_first",
+            CoinSelectionStrategy::BranchAndBound => "branch_and_bound",
+            CoinSelectionStrategy::Knapsack => "knapsack",
+            CoinSelectionStrategy::Privacy => "privacy",
+            CoinSelectionStrategy::Manual => "manual",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select_coins, CoinSelectionStrategy};
+    use crate::coin_selection::{fee_estimation, frozen::FrozenUTXOs};
+    use crate::test_utils::boxed_utxos;
+    use crate::transactions::script::ScriptType;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_strategy() {
+        let boxed_utxos = boxed_utxos(&[10000, 50000, 20000]);
+        let frozen = FrozenUTXOs::default();
+        let output_types = [ScriptType::P2PKH];
+        let selection = select_coins(
+            CoinSelectionStrategy::LargestFirst,
+            boxed_utxos.clone(),
+            &frozen,
+            None,
+            &output_types,
+            5000,
+            1,
+        )
+        .unwrap();
+        // the largest UTXO is replaced by the smallest UTXO that covers the target
+        assert_eq!(selection.selected_utxos.len(), 1);
+        assert_eq!(selection.selected_utxos[0].utxo.value, 10000);
+
+        // the outpoints override the strategy
+        let txid = boxed_utxos[0].utxo.txid.clone();
+        let outpoints = [(txid, 2)];
+        let selection = select_coins(
+            CoinSelectionStrategy::LargestFirst,
+            boxed_utxos.clone(),
+            &frozen,
+            Some(&outpoints),
+            &output_types,
+            5000,
+            1,
+        )
+        .unwrap();
+        assert_eq!(selection.selected_utxos[0].utxo.value, 20000);
+        let err = select_coins(
+            CoinSelectionStrategy::Manual,
+            boxed_utxos,
+            &frozen,
+            None,
+            &output_types,
+            5000,
+            1,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "outpoints_missing_error");
+
+        for strategy in [
+            "random_improve",
+            "branch_and_bound",
+            "knapsack",
+            "privacy",
+            "manual",
+        ] {
+            let parsed = CoinSelectionStrategy::from_str(strategy).unwrap();
+            assert_eq!(parsed.to_string(), strategy);
+        }
+        assert!(CoinSelectionStrategy::from_str("fifo").is_err());
+    }
+
+    #[test]
+    fn test_exact_match() {
+        let output_types = [ScriptType::P2PKH];
+        // the fee of a transaction with one input and one output
+        let fee = fee_estimation::estimate_fee(1, 1, 1);
+        let boxed_utxos = boxed_utxos(&[50000, 30000 + fee + 100, 100000]);
+        for strategy in [
+            CoinSelectionStrategy::RandomImprove,
+            CoinSelectionStrategy::LargestFirst,
+            CoinSelectionStrategy::Knapsack,
+        ] {
+            let selection = select_coins(
+                strategy,
+                boxed_utxos.clone(),
+                &FrozenUTXOs::default(),
+                None,
+                &output_types,
+                30000,
+                1,
+            )
+            .unwrap();
+            assert_eq!(selection.selected_utxos.len(), 1);
+            assert_eq!(selection.selected_utxos[0].utxo.vout, 1);
+            assert_eq!(selection.change, None);
+        }
+
+        // a frozen UTXO is not selected
+        let mut frozen = FrozenUTXOs::default();
+        frozen.freeze(&boxed_utxos[1].utxo.txid, 1);
+        let selection = select_coins(
+            CoinSelectionStrategy::LargestFirst,
+            boxed_utxos,
+            &frozen,
+            None,
+            &output_types,
+            30000,
+            1,
+        )
+        .unwrap();
+        assert_eq!(selection.selected_utxos[0].utxo.value, 50000);
+        assert!(selection.change.is_some());
+    }
+}
@@ -0,0 +1,156 @@
+//! Implements the selection of all UTXOs for a sweep.
+//! If the number of UTXOs exceeds the input limit of a transaction,
+//! the UTXOs are split into batches that are spent by separate transactions.
+use super::error::CoinSelectionError;
+use super::fee_estimation;
+use super::MAX_INPUTS_PER_TX;
+use crate::transactions::script::ScriptType;
+use crate::transactions::utxo::UTXOBox;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SweepBatch {
+    pub selected_utxos: Vec<UTXOBox>,
+    pub amount: u64, // the value of the single output after deducting the fee
+}
+
+/// The transactions of a sweep in batches. A batch that cannot be sent does not undo
+/// the batches that were sent before it, so their ids are returned together with the error.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SweepResult {
+    pub txids: Vec<String>,
+    pub error: Option<String>, // the error of the first batch that was not sent
+}
+
+/// Returns the batches of UTXOs that sweep all given UTXOs to a single output.
+/// Batches whose value does not cover their fee are skipped.
+pub fn select_batches(
+    boxed_utxos: Vec<UTXOBox>,
+    fee_per_byte: u64,
+) -> Result<Vec<SweepBatch>, CoinSelectionError> {
+    create_batches(boxed_utxos, None, fee_per_byte)
+}
+
+/// Returns the batches of UTXOs that sweep all given UTXOs to a single output
+/// of the specified script type, e.g., to migrate the UTXOs to SegWit.
+pub fn select_batches_to(
+    boxed_utxos: Vec<UTXOBox>,
+    output_type: ScriptType,
+    fee_per_byte: u64,
+) -> Result<Vec<SweepBatch>, CoinSelectionError> {
+    create_batches(boxed_utxos, Some(output_type), fee_per_byte)
+}
+
+/// Returns the batches of UTXOs. If no script type is specified, the single output
+/// is assumed to pay to the script type of the first input of its batch.
+fn create_batches(
+    mut boxed_utxos: Vec<UTXOBox>,
+    output_type: Option<ScriptType>,
+    fee_per_byte: u64,
+) -> Result<Vec<SweepBatch>, CoinSelectionError> {
+    // sort UTXOs descendingly with respect to the value, so that
+    // low value UTXOs end up in the last batch
+    boxed_utxos.sort_by_key(|utxo_box| -(utxo_box.utxo.value as i128));
+
+    let mut batches = Vec::new();
+    for chunk in boxed_utxos.chunks(MAX_INPUTS_PER_TX) {
+        let value: u64 = chunk.iter().map(|utxo_box| utxo_box.utxo.value).sum();
+        let input_types = fee_estimation::utxo_script_types(chunk);
+        let output_type = output_type.unwrap_or(input_types[0]);
+        let fee =
+            fee_estimation::estimate_fee_for_scripts(&input_types, &[output_type], fee_per_byte);
+        if value <= fee {
+            continue;
+        }
+        batches.push(SweepBatch {
+            selected_utxos: chunk.to_vec(),
+            amount: value - fee,
+        });
+    }
+    if batches.is_empty() {
+        return Err(CoinSelectionError::new("balance_insufficient".to_string()));
+    }
+    Ok(batches)
+}
+
+/// Returns a single batch that sweeps all given UTXOs to a single output.
+/// Returns an error if the UTXOs do not fit into a single transaction.
+pub fn select_single_batch(
+    boxed_utxos: Vec<UTXOBox>,
+    fee_per_byte: u64,
+) -> Result<SweepBatch, CoinSelectionError> {
+    if boxed_utxos.len() > MAX_INPUTS_PER_TX {
+        return Err(CoinSelectionError::new(
+            "max_input_count_exceeded".to_string(),
+        ));
+    }
+    let mut batches = select_batches(boxed_utxos, fee_per_byte)?;
+    Ok(batches.remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select_batches, select_batches_to, select_single_batch};
+    use crate::coin_selection::{fee_estimation, MAX_INPUTS_PER_TX};
+    use crate::keys::{bip32::ExtendedPrivateKey, bip44::private_hierarchy::Keypair};
+    use crate::transactions::script::ScriptType;
+    use crate::transactions::transaction::TransactionOutput;
+    use crate::transactions::utxo::{UTXOBox, UTXOStatus, UTXO};
+
+    #[test]
+    fn test_select_batches() {
+        let private_key = ExtendedPrivateKey::create_master_key([1u8; 32], true);
+        let public_key = private_key.derive_public_key();
+        let keypair = Keypair {
+            private_key,
+            public_key,
+        };
+        let boxed_utxos: Vec<UTXOBox> = (0..MAX_INPUTS_PER_TX + 2)
+            .map(|vout| UTXOBox {
+                utxo: UTXO {
+                    txid: String::from(
+                        "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+                    ),
+                    vout: vout as u32,
+                    value: 10000 + vout as u64,
+                    status: UTXOStatus {
+                        confirmed: true,
+                        block_height: None,
+                        block_hash: None,
+                        block_time: None,
+                    },
+                },
+                output: TransactionOutput {
+                    scriptpubkey: String::new(),
+                    scriptpubkey_asm: String::new(),
+                    scriptpubkey_type: String::from("p2pkh"),
+                    scriptpubkey_address: String::new(),
+                    value: 10000 + vout as u64,
+                },
+                keypair: keypair.clone(),
+            })
+            .collect();
+        let batches = select_batches(boxed_utxos.clone(), 8).unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].selected_utxos.len(), MAX_INPUTS_PER_TX);
+        assert_eq!(batches[1].selected_utxos.len(), 2);
+        assert_eq!(batches[1].selected_utxos[0].utxo.vout, 1);
+        let fee = fee_estimation::estimate_fee(2, 1, 8);
+        assert_eq!(batches[1].amount, 10000 + 10001 - fee);
+
+        // a P2WPKH output is smaller than a P2PKH output, so the fee is lower
+        let batches = select_batches_to(boxed_utxos.clone(), ScriptType::P2WPKH, 8).unwrap();
+        let input_types = vec![ScriptType::P2PKH; 2];
+        let segwit_fee =
+            fee_estimation::estimate_fee_for_scripts(&input_types, &[ScriptType::P2WPKH], 8);
+        assert!(segwit_fee < fee);
+        assert_eq!(batches[1].amount, 10000 + 10001 - segwit_fee);
+
+        // the fee exceeds the value of every batch
+        assert!(select_batches(boxed_utxos.clone(), 1000).is_err());
+
+        assert!(select_single_batch(boxed_utxos.clone(), 8).is_err());
+        let batch = select_single_batch(boxed_utxos[..2].to_vec(), 8).unwrap();
+        assert_eq!(batch.amount, 10000 + 10001 - fee);
+    }
+}
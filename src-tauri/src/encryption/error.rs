@@ -19,3 +19,20 @@ impl fmt::Debug for WrongPasswordError {
 }
 
 impl Error for WrongPasswordError {}
+
+/// This error occurs when the checksum of the ciphertext does not match.
+pub struct CorruptedFileError;
+
+impl fmt::Display for CorruptedFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CorruptedFileError")
+    }
+}
+
+impl fmt::Debug for CorruptedFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "CorruptedFileError")
+    }
+}
+
+impl Error for CorruptedFileError {}
@@ -1,29 +1,332 @@
+//! Encrypts the wallet file with AES-256-GCM. The key is derived from the
+//! password with Argon2. Every encryption uses a fresh random salt and nonce,
+//! which are stored in a header in front of the ciphertext together with the
+//! Argon2 parameters:
+//!
+//! ```text
+//! magic (4 bytes) | version (1 byte) | memory (4 bytes) | iterations (4 bytes)
+//!     | parallelism (4 bytes) | salt (16 bytes) | nonce (12 bytes) | verifier (32 bytes)
+//!     | ciphertext | checksum (32 bytes)
+//! ```
+//!
+//! The verifier is a hash of the key, which allows the password to be checked
+//! without decrypting the ciphertext. Files of version 3 have no verifier.
+//! The checksum is the SHA-256 hash of everything in front of it. It does not
+//! depend on the password, so a damaged file can be told apart from a wrong
+//! password. Files of version 2 have no checksum.
+//!
+//! Storing the parameters allows them to be strengthened over time. Files of
+//! version 1 have no parameters in the header and use the Argon2 defaults.
+//! Files without the header were written by earlier versions, which used a
+//! fixed salt and nonce. They can still be decrypted.
 use aes_gcm::aead::{Aead, NewAead};
 use aes_gcm::{Aes256Gcm, Key, Nonce};
 use argon2::{
     password_hash::{PasswordHasher, SaltString},
-    Argon2,
+    Algorithm, Argon2, Params, Version,
 };
+use bitcoin_hashes::{sha256, Hash, HashEngine};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use zeroize::Zeroize;
 
 pub mod error;
-use self::error::WrongPasswordError;
+use self::error::{CorruptedFileError, WrongPasswordError};
 
+static MAGIC: &[u8; 4] = b"GHRB";
+static VERSION_DEFAULT_PARAMS: u8 = 1;
+static VERSION_NO_CHECKSUM: u8 = 2;
+static VERSION_NO_VERIFIER: u8 = 3;
+static VERSION: u8 = 4;
+static PARAMS_LENGTH: usize = 12;
+static SALT_LENGTH: usize = 16;
+static NONCE_LENGTH: usize = 12;
+static KEY_LENGTH: usize = 32;
+static CHECKSUM_LENGTH: usize = 32;
+static VERIFIER_LENGTH: usize = 32;
+static VERIFIER_TAG: &[u8] = b"ghorbu-wallet/verifier";
+
+/// Argon2 parameters. The memory is specified in KiB.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfParams {
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SecurityLevel {
+    Low, // for devices with little RAM
+    Standard,
+    High,
+}
+
+impl SecurityLevel {
+    /// Returns the Argon2 parameters of the security level.
+    pub fn kdf_params(&self) -> KdfParams {
+        match self {
+            SecurityLevel::Low => KdfParams {
+                memory: Params::DEFAULT_M_COST,
+                iterations: Params::DEFAULT_T_COST,
+                parallelism: Params::DEFAULT_P_COST,
+            },
+            SecurityLevel::Standard => KdfParams {
+                memory: 19 * 1024,
+                iterations: 2,
+                parallelism: 1,
+            },
+            SecurityLevel::High => KdfParams {
+                memory: 64 * 1024,
+                iterations: 3,
+                parallelism: 4,
+            },
+        }
+    }
+
+    /// Returns the security level whose parameters are matched or exceeded
+    /// by the specified parameters.
+    pub fn from_kdf_params(params: &KdfParams) -> SecurityLevel {
+        [SecurityLevel::High, SecurityLevel::Standard]
+            .into_iter()
+            .find(|level| {
+                let level_params = level.kdf_params();
+                params.memory >= level_params.memory && params.iterations >= level_params.iterations
+            })
+            .unwrap_or(SecurityLevel::Low)
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        SecurityLevel::Standard.kdf_params()
+    }
+}
+
+/// Encrypts the plaintext with the default parameters.
+/// The plaintext and the password are wiped from memory.
 pub fn encrypt(plaintext: Vec<u8>, password: String) -> Vec<u8> {
-    let salt = SaltString::new("saltsaltsaltsaltsaltsalt").unwrap();
-    let argon2 = Argon2::default();
-    let password_hash = argon2
-        .hash_password(password.as_bytes(), &salt)
-        .unwrap()
-        .hash
+    encrypt_with_params(plaintext, password, &KdfParams::default())
+}
+
+/// Encrypts the plaintext with the specified parameters.
+/// The plaintext and the password are wiped from memory.
+pub fn encrypt_with_params(
+    mut plaintext: Vec<u8>,
+    mut password: String,
+    params: &KdfParams,
+) -> Vec<u8> {
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut nonce);
+    let mut key = derive_key(&password, &salt, params).expect("valid parameters");
+    password.zeroize();
+
+    let cipher = Aes256Gcm::new(Key::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
         .unwrap();
+    plaintext.zeroize();
+    let verifier = compute_verifier(&key);
+    key.zeroize();
+    let mut bytes = Vec::with_capacity(header_length(VERSION) + ciphertext.len() + CHECKSUM_LENGTH);
+    bytes.extend(MAGIC);
+    bytes.push(VERSION);
+    bytes.extend(params.memory.to_le_bytes());
+    bytes.extend(params.iterations.to_le_bytes());
+    bytes.extend(params.parallelism.to_le_bytes());
+    bytes.extend(&salt);
+    bytes.extend(&nonce);
+    bytes.extend(verifier);
+    bytes.extend(ciphertext);
+    let checksum = sha256::Hash::hash(&bytes);
+    bytes.extend(checksum.into_inner());
+    bytes
+}
 
-    let key = Key::from_slice(password_hash.as_bytes());
-    let cipher = Aes256Gcm::new(key);
-    let nonce = Nonce::from_slice(b"unique nonce");
-    cipher.encrypt(nonce, plaintext.as_ref()).unwrap()
+/// Decrypts the ciphertext. The password is wiped from memory.
+/// Returns a `CorruptedFileError` if the checksum does not match
+/// and a `WrongPasswordError` if the decryption fails.
+pub fn decrypt(ciphertext: Vec<u8>, mut password: String) -> Result<Vec<u8>, Box<dyn Error>> {
+    verify_checksum(&ciphertext)?;
+    let (version, params) = match parse_header(&ciphertext) {
+        Some(header) => header,
+        None => return Ok(decrypt_legacy(ciphertext, password)?),
+    };
+    let salt_start = salt_start(version);
+    let nonce_start = salt_start + SALT_LENGTH;
+    let salt = &ciphertext[salt_start..nonce_start];
+    let nonce = &ciphertext[nonce_start..nonce_start + NONCE_LENGTH];
+    let key = derive_key(&password, salt, &params);
+    password.zeroize();
+    let mut key = match key {
+        Some(key) => key,
+        None => return Err(Box::new(WrongPasswordError {})),
+    };
+
+    let cipher = Aes256Gcm::new(Key::from_slice(&key));
+    let plaintext = cipher.decrypt(
+        Nonce::from_slice(nonce),
+        &ciphertext[header_length(version)..ciphertext.len() - checksum_length(version)],
+    );
+    key.zeroize();
+    match plaintext {
+        Ok(plaintext) => Ok(plaintext),
+        Err(_) => Err(Box::new(WrongPasswordError {})),
+    }
+}
+
+/// Checks the password against the verifier in the header without decrypting
+/// the ciphertext. Ciphertexts without a verifier are decrypted instead.
+/// The password is wiped from memory.
+pub fn check_password(ciphertext: &[u8], mut password: String) -> Result<(), Box<dyn Error>> {
+    verify_checksum(ciphertext)?;
+    let params = match parse_header(ciphertext) {
+        Some((version, params)) if version == VERSION => params,
+        _ => {
+            let mut plaintext = decrypt(ciphertext.to_vec(), password)?;
+            plaintext.zeroize();
+            return Ok(());
+        }
+    };
+    let salt_start = salt_start(VERSION);
+    let verifier_start = salt_start + SALT_LENGTH + NONCE_LENGTH;
+    let salt = &ciphertext[salt_start..salt_start + SALT_LENGTH];
+    let verifier = &ciphertext[verifier_start..verifier_start + VERIFIER_LENGTH];
+    let key = derive_key(&password, salt, &params);
+    password.zeroize();
+    let mut key = match key {
+        Some(key) => key,
+        None => return Err(Box::new(WrongPasswordError {})),
+    };
+    let matches = compute_verifier(&key) == verifier;
+    key.zeroize();
+    if !matches {
+        return Err(Box::new(WrongPasswordError {}));
+    }
+    Ok(())
+}
+
+/// Checks that the ciphertext was not damaged. The password is not needed.
+/// Files that were written without a checksum cannot be checked and are accepted.
+pub fn verify_checksum(ciphertext: &[u8]) -> Result<(), CorruptedFileError> {
+    if ciphertext.len() <= MAGIC.len() || &ciphertext[..MAGIC.len()] != MAGIC {
+        return Ok(());
+    }
+    let version = ciphertext[MAGIC.len()];
+    if version > VERSION {
+        return Err(CorruptedFileError {});
+    }
+    if version <= VERSION_NO_CHECKSUM {
+        return Ok(());
+    }
+    if ciphertext.len() < header_length(version) + CHECKSUM_LENGTH {
+        return Err(CorruptedFileError {});
+    }
+    let (data, checksum) = ciphertext.split_at(ciphertext.len() - CHECKSUM_LENGTH);
+    if sha256::Hash::hash(data).into_inner() != checksum {
+        return Err(CorruptedFileError {});
+    }
+    Ok(())
+}
+
+/// Returns the Argon2 parameters that are stored in the header of the ciphertext.
+/// Returns `None` if the ciphertext was written by a version that did not store them.
+pub fn read_kdf_params(ciphertext: &[u8]) -> Option<KdfParams> {
+    match parse_header(ciphertext) {
+        Some((version, params)) if version != VERSION_DEFAULT_PARAMS => Some(params),
+        _ => None,
+    }
+}
+
+/// Returns the version and the Argon2 parameters of the header.
+/// Returns `None` if the ciphertext has no header.
+fn parse_header(ciphertext: &[u8]) -> Option<(u8, KdfParams)> {
+    if ciphertext.len() <= MAGIC.len() || &ciphertext[..MAGIC.len()] != MAGIC {
+        return None;
+    }
+    let version = ciphertext[MAGIC.len()];
+    if version < VERSION_DEFAULT_PARAMS
+        || version > VERSION
+        || ciphertext.len() < header_length(version) + checksum_length(version)
+    {
+        return None;
+    }
+    if version == VERSION_DEFAULT_PARAMS {
+        return Some((version, SecurityLevel::Low.kdf_params()));
+    }
+    let params = &ciphertext[MAGIC.len() + 1..MAGIC.len() + 1 + PARAMS_LENGTH];
+    let params = KdfParams {
+        memory: u32::from_le_bytes(params[..4].try_into().unwrap()),
+        iterations: u32::from_le_bytes(params[4..8].try_into().unwrap()),
+        parallelism: u32::from_le_bytes(params[8..].try_into().unwrap()),
+    };
+    Some((version, params))
+}
+
+/// Returns the position of the salt in the header.
+fn salt_start(version: u8) -> usize {
+    let params_length = if version == VERSION_DEFAULT_PARAMS {
+        0
+    } else {
+        PARAMS_LENGTH
+    };
+    MAGIC.len() + 1 + params_length
+}
+
+/// Returns the length of the header in bytes.
+fn header_length(version: u8) -> usize {
+    let verifier_length = if version <= VERSION_NO_VERIFIER {
+        0
+    } else {
+        VERIFIER_LENGTH
+    };
+    salt_start(version) + SALT_LENGTH + NONCE_LENGTH + verifier_length
+}
+
+/// Returns the length of the checksum at the end of the ciphertext in bytes.
+fn checksum_length(version: u8) -> usize {
+    if version <= VERSION_NO_CHECKSUM {
+        0
+    } else {
+        CHECKSUM_LENGTH
+    }
+}
+
+/// Returns the hash of the key that is stored in the header.
+fn compute_verifier(key: &[u8]) -> [u8; VERIFIER_LENGTH] {
+    let mut engine = sha256::Hash::engine();
+    engine.input(VERIFIER_TAG);
+    engine.input(key);
+    sha256::Hash::from_engine(engine).into_inner()
 }
 
-pub fn decrypt(ciphertext: Vec<u8>, password: String) -> Result<Vec<u8>, WrongPasswordError> {
+/// Derives the encryption key from the password and the salt.
+/// Returns `None` if the parameters are invalid.
+fn derive_key(password: &str, salt: &[u8], params: &KdfParams) -> Option<[u8; KEY_LENGTH]> {
+    let params = Params::new(
+        params.memory,
+        params.iterations,
+        params.parallelism,
+        Some(KEY_LENGTH),
+    )
+    .ok()?;
+    let mut key = [0u8; KEY_LENGTH];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .ok()?;
+    Some(key)
+}
+
+/// Decrypts a file that was written without a header.
+fn decrypt_legacy(
+    ciphertext: Vec<u8>,
+    mut password: String,
+) -> Result<Vec<u8>, WrongPasswordError> {
     let salt = SaltString::new("saltsaltsaltsaltsaltsalt").unwrap();
     let argon2 = Argon2::default();
     let password_hash = argon2
@@ -31,6 +334,7 @@ pub fn decrypt(ciphertext: Vec<u8>, password: String) -> Result<Vec<u8>, WrongPa
         .unwrap()
         .hash
         .unwrap();
+    password.zeroize();
     let key = Key::from_slice(password_hash.as_bytes());
     let cipher = Aes256Gcm::new(key);
     let nonce = Nonce::from_slice(b"unique nonce");
@@ -43,7 +347,17 @@ pub fn decrypt(ciphertext: Vec<u8>, password: String) -> Result<Vec<u8>, WrongPa
 
 #[cfg(test)]
 mod tests {
-    use crate::encryption::{decrypt, encrypt};
+    use crate::encryption::error::{CorruptedFileError, WrongPasswordError};
+    use crate::encryption::{
+        check_password, decrypt, encrypt, encrypt_with_params, read_kdf_params, verify_checksum,
+        KdfParams, SecurityLevel,
+    };
+    use aes_gcm::aead::{Aead, NewAead};
+    use aes_gcm::{Aes256Gcm, Key, Nonce};
+    use argon2::{
+        password_hash::{PasswordHasher, SaltString},
+        Argon2,
+    };
 
     #[test]
     fn test_encrypt_decrypt_success() {
@@ -54,4 +368,117 @@ mod tests {
         let plaintext_dec = String::from_utf8(plaintext_dec).unwrap();
         assert_eq!(plaintext, plaintext_dec);
     }
+
+    #[test]
+    fn test_encrypt_fresh_salt_and_nonce() {
+        let password = "password1234".to_string();
+        let ciphertext1 = encrypt("secret message".into(), password.clone());
+        let ciphertext2 = encrypt("secret message".into(), password.clone());
+        assert_ne!(ciphertext1, ciphertext2);
+        assert!(decrypt(ciphertext1, "password1235".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_legacy() {
+        // encrypt the way earlier versions did
+        let password = "password1234".to_string();
+        let salt = SaltString::new("saltsaltsaltsaltsaltsalt").unwrap();
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .unwrap()
+            .hash
+            .unwrap();
+        let cipher = Aes256Gcm::new(Key::from_slice(password_hash.as_bytes()));
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(b"unique nonce"),
+                b"secret message".as_ref(),
+            )
+            .unwrap();
+        let plaintext_dec = decrypt(ciphertext, password).unwrap();
+        assert_eq!(plaintext_dec, b"secret message");
+    }
+
+    #[test]
+    fn test_encrypt_with_params() {
+        let password = "password1234".to_string();
+        let params = KdfParams {
+            memory: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let ciphertext = encrypt_with_params("secret message".into(), password.clone(), &params);
+        assert_eq!(read_kdf_params(&ciphertext), Some(params));
+        assert_eq!(SecurityLevel::from_kdf_params(&params), SecurityLevel::Low);
+        let plaintext_dec = decrypt(ciphertext, password).unwrap();
+        assert_eq!(plaintext_dec, b"secret message");
+
+        let ciphertext = encrypt("secret message".into(), "password1234".to_string());
+        let params = read_kdf_params(&ciphertext).unwrap();
+        assert_eq!(
+            SecurityLevel::from_kdf_params(&params),
+            SecurityLevel::Standard
+        );
+    }
+
+    #[test]
+    fn test_decrypt_version_1() {
+        // version 1 files use the Argon2 defaults and have no parameters in the header
+        let password = "password1234".to_string();
+        let salt = [7u8; 16];
+        let nonce = [9u8; 12];
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(password.as_bytes(), &salt, &mut key)
+            .unwrap();
+        let cipher = Aes256Gcm::new(Key::from_slice(&key));
+        let mut ciphertext = b"GHRB\x01".to_vec();
+        ciphertext.extend(salt);
+        ciphertext.extend(nonce);
+        ciphertext.extend(
+            cipher
+                .encrypt(Nonce::from_slice(&nonce), b"secret message".as_ref())
+                .unwrap(),
+        );
+        assert_eq!(read_kdf_params(&ciphertext), None);
+        // without a verifier the password is checked by decrypting
+        assert!(check_password(&ciphertext, password.clone()).is_ok());
+        assert!(check_password(&ciphertext, "password1235".to_string()).is_err());
+        let plaintext_dec = decrypt(ciphertext, password).unwrap();
+        assert_eq!(plaintext_dec, b"secret message");
+    }
+
+    #[test]
+    fn test_corrupted_file() {
+        let password = "password1234".to_string();
+        let ciphertext = encrypt("secret message".into(), password.clone());
+        assert!(verify_checksum(&ciphertext).is_ok());
+        let err = decrypt(ciphertext.clone(), "password1235".to_string()).unwrap_err();
+        assert!(err.is::<WrongPasswordError>());
+
+        let mut corrupted = ciphertext.clone();
+        let last = corrupted.len() - 40;
+        corrupted[last] ^= 1;
+        assert!(verify_checksum(&corrupted).is_err());
+        let err = decrypt(corrupted, password.clone()).unwrap_err();
+        assert!(err.is::<CorruptedFileError>());
+
+        let truncated = ciphertext[..ciphertext.len() / 2].to_vec();
+        let err = decrypt(truncated, password).unwrap_err();
+        assert!(err.is::<CorruptedFileError>());
+    }
+
+    #[test]
+    fn test_check_password() {
+        let password = "password1234".to_string();
+        let ciphertext = encrypt("secret message".into(), password.clone());
+        assert!(check_password(&ciphertext, password.clone()).is_ok());
+        let err = check_password(&ciphertext, "password1235".to_string()).unwrap_err();
+        assert!(err.is::<WrongPasswordError>());
+
+        let mut corrupted = ciphertext.clone();
+        corrupted[10] ^= 1;
+        let err = check_password(&corrupted, password).unwrap_err();
+        assert!(err.is::<CorruptedFileError>());
+    }
 }
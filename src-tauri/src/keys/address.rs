@@ -1,21 +1,52 @@
-//! Implements a Base58Check address.
+//! Implements Base58Check addresses of P2PKH and P2SH outputs and
+//! Bech32 addresses of native SegWit outputs as described in
+//! [BIP-173](https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki).
+//! Taproot addresses use the Bech32m checksum of
+//! [BIP-350](https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki).
+//! Bech32 addresses of the testnet coin type are encoded for the selected test network.
 use crate::keys::bip32::ExtendedPublicKey;
 use crate::keys::error::ParseAddressError;
-use crate::utils::{base58, hex};
+use crate::keys::network::{self, TestNetwork};
+use crate::keys::taproot;
+use crate::transactions::script::{self, ScriptType};
+use crate::utils::{
+    base58,
+    bech32::{self, Variant},
+    hex,
+};
 use bitcoin_hashes::{ripemd160, sha256, Hash};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
+static PREFIX_P2PKH: u8 = 0x00;
+static PREFIX_P2PKH_TESTNET: u8 = 0x6f;
+static PREFIX_P2SH: u8 = 0x05;
+static PREFIX_P2SH_TESTNET: u8 = 0xc4;
+static HRP: &'static str = "bc";
+static WITNESS_VERSION_TAPROOT: u8 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AddressType {
+    P2PKH,
+    P2SH,
+    P2WPKH,
+    P2WSH,
+    P2TR,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Address {
     pub testnet: bool,
-    pub hash160: [u8; 20],
+    pub address_type: AddressType,
+    pub hash: Vec<u8>, // hash of the public key or of the script, the output key for Taproot
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SimpleAddress {
     pub address: String,
-    pub used: bool,
+    pub path: String,       // derivation path of the key
+    pub index: u32,         // index of the key in its chain
+    pub used: Option<bool>, // `None` if the backend was not queried
 }
 
 impl Address {
@@ -25,36 +56,151 @@ impl Address {
         let hash160 = ripemd160::Hash::hash(&sha_256);
         Address {
             testnet: public_key.testnet,
-            hash160: hash160.into_inner(),
+            address_type: AddressType::P2PKH,
+            hash: hash160.to_vec(),
         }
     }
 
-    /// Returns the address in hex format.
-    pub fn get_h160(&self) -> String {
-        hex::bytes_to_hex(&self.hash160)
+    /// Creates the P2SH address of the redeem script.
+    pub fn create_p2sh(redeem_script: &[u8], testnet: bool) -> Address {
+        let hash160 = ripemd160::Hash::hash(&sha256::Hash::hash(redeem_script));
+        Address {
+            testnet,
+            address_type: AddressType::P2SH,
+            hash: hash160.to_vec(),
+        }
     }
-}
 
-impl ToString for Address {
-    fn to_string(&self) -> String {
-        let mut hash160_prefix = Vec::with_capacity(25);
+    /// Creates the P2WPKH address of the public key.
+    pub fn create_p2wpkh(public_key: &ExtendedPublicKey) -> Address {
+        Address {
+            address_type: AddressType::P2WPKH,
+            ..Address::create(public_key)
+        }
+    }
+
+    /// Creates the P2SH-P2WPKH address of the public key, i.e., the P2SH address of
+    /// a redeem script that pays to the P2WPKH script of the key.
+    pub fn create_p2sh_p2wpkh(public_key: &ExtendedPublicKey) -> Address {
+        let pubkey_hash = Address::create(public_key).get_hash();
+        let redeem_script = script::p2sh_p2wpkh_redeem_script(&pubkey_hash);
+        Address::create_p2sh(
+            &hex::hex_to_bytes(&redeem_script).unwrap(),
+            public_key.testnet,
+        )
+    }
+
+    /// Creates the P2WSH address of the witness script.
+    pub fn create_p2wsh(witness_script: &[u8], testnet: bool) -> Address {
+        Address {
+            testnet,
+            address_type: AddressType::P2WSH,
+            hash: sha256::Hash::hash(witness_script).to_vec(),
+        }
+    }
+
+    /// Creates the P2TR address of the public key without a script tree.
+    pub fn create_p2tr(public_key: &ExtendedPublicKey) -> Address {
+        Address {
+            testnet: public_key.testnet,
+            address_type: AddressType::P2TR,
+            hash: taproot::public_output_key(public_key).serialize().to_vec(),
+        }
+    }
+
+    /// Returns true if the address is encoded in Bech32 or Bech32m.
+    pub fn is_segwit(&self) -> bool {
+        self.address_type == AddressType::P2WPKH
+            || self.address_type == AddressType::P2WSH
+            || self.address_type == AddressType::P2TR
+    }
+
+    /// Returns the witness version of a SegWit address.
+    fn witness_version(&self) -> u8 {
+        if self.address_type == AddressType::P2TR {
+            WITNESS_VERSION_TAPROOT
+        } else {
+            0
+        }
+    }
+
+    /// Returns the encoding of a SegWit address with the specified witness version.
+    /// Version 0 uses Bech32 and later versions Bech32m.
+    fn variant(witness_version: u8) -> Variant {
+        if witness_version == 0 {
+            Variant::Bech32
+        } else {
+            Variant::Bech32m
+        }
+    }
+
+    /// Returns the Base58Check prefix of the address.
+    fn prefix(&self) -> u8 {
+        match (self.address_type, self.testnet) {
+            (AddressType::P2SH, false) => PREFIX_P2SH,
+            (AddressType::P2SH, true) => PREFIX_P2SH_TESTNET,
+            (_, false) => PREFIX_P2PKH,
+            (_, true) => PREFIX_P2PKH_TESTNET,
+        }
+    }
+
+    /// Returns the hash in hex format.
+    pub fn get_hash(&self) -> String {
+        hex::bytes_to_hex(&self.hash)
+    }
+
+    /// Returns the BIP-44 coin type index of the network the address belongs to.
+    pub fn coin_type_index(&self) -> u32 {
         if self.testnet {
-            hash160_prefix.push(0x6f);
+            1
         } else {
-            hash160_prefix.push(0x00);
+            0
         }
-        hash160_prefix.extend(&self.hash160);
-        let checksum = sha256::Hash::hash(&hash160_prefix);
-        let checksum = sha256::Hash::hash(&checksum);
-        hash160_prefix.extend(&checksum[..4]);
-        base58::encode(&hash160_prefix)
     }
-}
 
-impl FromStr for Address {
-    type Err = ParseAddressError;
+    /// Returns the address that the scriptPubKey pays to, or `None`
+    /// if the script does not match any of the supported templates.
+    pub fn from_script_pub_key(bytes: &[u8], testnet: bool) -> Option<Address> {
+        let (address_type, hash) = match ScriptType::from_script_pub_key(bytes)? {
+            ScriptType::P2PKH => (AddressType::P2PKH, &bytes[3..23]),
+            ScriptType::P2SH => (AddressType::P2SH, &bytes[2..22]),
+            ScriptType::P2WPKH => (AddressType::P2WPKH, &bytes[2..]),
+            ScriptType::P2WSH => (AddressType::P2WSH, &bytes[2..]),
+            ScriptType::P2TR => (AddressType::P2TR, &bytes[2..]),
+            ScriptType::OpReturn => return None, // the output has no address
+        };
+        Some(Address {
+            testnet,
+            address_type,
+            hash: hash.to_vec(),
+        })
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    /// Returns the type of the scriptPubKey that pays to the address.
+    pub fn script_type(&self) -> ScriptType {
+        match self.address_type {
+            AddressType::P2PKH => ScriptType::P2PKH,
+            AddressType::P2SH => ScriptType::P2SH,
+            AddressType::P2WPKH => ScriptType::P2WPKH,
+            AddressType::P2WSH => ScriptType::P2WSH,
+            AddressType::P2TR => ScriptType::P2TR,
+        }
+    }
+
+    /// Returns the scriptPubKey that pays to the address in assembly format.
+    pub fn script_pub_key(&self) -> String {
+        let hash = self.get_hash();
+        match self.address_type {
+            AddressType::P2PKH => script::p2pkh_script_pub_key(&hash),
+            AddressType::P2SH => script::p2sh_script_pub_key(&hash),
+            AddressType::P2WPKH => script::p2wpkh_script_pub_key(&hash),
+            AddressType::P2WSH => script::p2wsh_script_pub_key(&hash),
+            AddressType::P2TR => script::p2tr_script_pub_key(&hash),
+        }
+    }
+
+    /// Parses a Base58Check address.
+    fn from_base58(s: &str) -> Result<Address, ParseAddressError> {
         let bytes = base58::decode(s);
         if let None = bytes {
             return Err(ParseAddressError::new("Invalid character"));
@@ -63,10 +209,18 @@ impl FromStr for Address {
         if bytes.len() != 25 {
             return Err(ParseAddressError::new("Invalid length"));
         }
-        let prefix = &bytes[0];
-        if *prefix != 0x00 && *prefix != 0x6f {
+        let prefix = bytes[0];
+        let (address_type, testnet) = if prefix == PREFIX_P2PKH {
+            (AddressType::P2PKH, false)
+        } else if prefix == PREFIX_P2PKH_TESTNET {
+            (AddressType::P2PKH, true)
+        } else if prefix == PREFIX_P2SH {
+            (AddressType::P2SH, false)
+        } else if prefix == PREFIX_P2SH_TESTNET {
+            (AddressType::P2SH, true)
+        } else {
             return Err(ParseAddressError::new("Invalid prefix"));
-        }
+        };
         let hash = &bytes[1..bytes.len() - 4];
         let checksum_target = &bytes[bytes.len() - 4..];
         let checksum = sha256::Hash::hash(&bytes[..bytes.len() - 4]);
@@ -75,16 +229,106 @@ impl FromStr for Address {
             return Err(ParseAddressError::new("Checksum failed"));
         }
         Ok(Address {
-            testnet: *prefix == 0x6f,
-            hash160: hash.try_into().unwrap(),
+            testnet,
+            address_type,
+            hash: hash.to_vec(),
+        })
+    }
+
+    /// Parses a Bech32 address of a native SegWit output or a Bech32m address
+    /// of a Taproot output.
+    fn from_bech32(s: &str) -> Result<Address, ParseAddressError> {
+        let (hrp, data, variant) = match bech32::decode(s) {
+            Some(decoded) => decoded,
+            None => return Err(ParseAddressError::new("Invalid encoding")),
+        };
+        let testnet = if hrp == HRP {
+            false
+        } else if TestNetwork::from_hrp(&hrp).is_some() {
+            true
+        } else {
+            return Err(ParseAddressError::new("Invalid prefix"));
+        };
+        if data.is_empty() {
+            return Err(ParseAddressError::new("Invalid length"));
+        }
+        let version = data[0];
+        let program = match bech32::convert_bits(&data[1..], 5, 8, false) {
+            Some(program) => program,
+            None => return Err(ParseAddressError::new("Invalid padding")),
+        };
+        if variant != Address::variant(version) {
+            return Err(ParseAddressError::new("Checksum failed"));
+        }
+        let address_type = match (version, program.len()) {
+            (0, 20) => AddressType::P2WPKH,
+            (0, 32) => AddressType::P2WSH,
+            (0, _) => return Err(ParseAddressError::new("Invalid length")),
+            (1, 32) => AddressType::P2TR,
+            _ => return Err(ParseAddressError::new("Unsupported witness version")),
+        };
+        Ok(Address {
+            testnet,
+            address_type,
+            hash: program,
         })
     }
 }
 
+impl Address {
+    /// Encodes the address. A testnet address is encoded for the specified test network.
+    pub fn encode(&self, test_network: TestNetwork) -> String {
+        if self.is_segwit() {
+            let hrp = if self.testnet {
+                test_network.hrp()
+            } else {
+                HRP
+            };
+            let version = self.witness_version();
+            let mut data = vec![version];
+            data.extend(bech32::convert_bits(&self.hash, 8, 5, true).unwrap());
+            return bech32::encode(hrp, &data, Address::variant(version)).unwrap();
+        }
+        let mut hash160_prefix = Vec::with_capacity(25);
+        hash160_prefix.push(self.prefix());
+        hash160_prefix.extend(&self.hash);
+        let checksum = sha256::Hash::hash(&hash160_prefix);
+        let checksum = sha256::Hash::hash(&checksum);
+        hash160_prefix.extend(&checksum[..4]);
+        base58::encode(&hash160_prefix)
+    }
+}
+
+impl ToString for Address {
+    fn to_string(&self) -> String {
+        self.encode(network::selected_test_network())
+    }
+}
+
+impl FromStr for Address {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lowercase = s.to_lowercase();
+        if lowercase.starts_with(&format!("{}1", HRP))
+            || lowercase.starts_with(&format!("{}1", TestNetwork::Testnet.hrp()))
+            || lowercase.starts_with(&format!("{}1", TestNetwork::Regtest.hrp()))
+        {
+            Address::from_bech32(s)
+        } else {
+            Address::from_base58(s)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::keys::address::Address;
-    use crate::keys::bip32::ExtendedPublicKey;
+    use crate::keys::address::{Address, AddressType};
+    use crate::keys::bip32::{DerivationPath, ExtendedPrivateKey, ExtendedPublicKey};
+    use crate::keys::bip39;
+    use crate::keys::network::TestNetwork;
+    use crate::transactions::script::{self, ScriptType};
+    use crate::utils::{bech32, hex};
     use std::str::FromStr;
 
     #[test]
@@ -102,4 +346,215 @@ mod tests {
         let address = Address::from_str(address_str).unwrap();
         assert_eq!(address.to_string(), address_str.to_string());
     }
+
+    #[test]
+    fn test_p2sh_address() {
+        let address_str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+        let address = Address::from_str(address_str).unwrap();
+        assert_eq!(address.address_type, AddressType::P2SH);
+        assert!(!address.testnet);
+        assert_eq!(address.to_string(), address_str);
+
+        let address = Address::from_str("2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc").unwrap();
+        assert_eq!(address.address_type, AddressType::P2SH);
+        assert!(address.testnet);
+
+        // P2SH-P2WPKH example of BIP-143
+        let redeem_script =
+            hex::hex_to_bytes("001479091972186c449eb1ded22b78e40d009bdf0089").unwrap();
+        let address = Address::create_p2sh(&redeem_script, false);
+        assert_eq!(
+            address.get_hash(),
+            "4733f37cf4db86fbc2efed2500b4f4e49f312023"
+        );
+    }
+
+    #[test]
+    fn test_p2sh_p2wpkh_address() {
+        // first receiving address of the test vector of BIP-49
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = bip39::generate_seed(mnemonic.split(' ').collect(), "");
+        let path = DerivationPath::from_str("m/49'/1'/0'/0/0").unwrap();
+        let public_key = ExtendedPrivateKey::create_master_key(seed, true)
+            .derive_path(&path)
+            .unwrap()
+            .derive_public_key();
+        let address = Address::create_p2sh_p2wpkh(&public_key);
+        assert_eq!(address.address_type, AddressType::P2SH);
+        assert_eq!(address.to_string(), "2Mww8dCYPUpKHofjgcXcBCEGmniw9CoaiD2");
+    }
+
+    #[test]
+    fn test_segwit_address() {
+        // examples of BIP-173
+        let address = Address::from_str("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4").unwrap();
+        assert_eq!(address.address_type, AddressType::P2WPKH);
+        assert!(!address.testnet);
+        assert_eq!(
+            address.get_hash(),
+            "751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+        assert_eq!(
+            address.to_string(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+
+        let address_str = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7";
+        let address = Address::from_str(address_str).unwrap();
+        assert_eq!(address.address_type, AddressType::P2WSH);
+        assert!(address.testnet);
+        assert_eq!(
+            address.get_hash(),
+            "1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"
+        );
+        assert_eq!(address.to_string(), address_str);
+
+        // the same script on regtest
+        let regtest_address_str = address.encode(TestNetwork::Regtest);
+        assert!(regtest_address_str.starts_with("bcrt1q"));
+        let regtest_address = Address::from_str(&regtest_address_str).unwrap();
+        assert!(regtest_address.testnet);
+        assert_eq!(regtest_address.hash, address.hash);
+        assert_eq!(regtest_address.encode(TestNetwork::Testnet), address_str);
+        // signet addresses are the same as testnet addresses
+        assert_eq!(address.encode(TestNetwork::Signet), address_str);
+
+        // invalid checksum
+        assert!(Address::from_str(
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k8"
+        )
+        .is_err());
+        // mixed case
+        assert!(Address::from_str(
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sL5k7"
+        )
+        .is_err());
+        // invalid program length
+        assert!(Address::from_str("bc1rw5uspcuh").is_err());
+        // invalid padding
+        assert!(Address::from_str("tb1pw508d6qejxtdg4y5r3zarqfsj6c3").is_err());
+        // version 0 with a Bech32m checksum
+        let address_str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh";
+        assert!(bech32::decode(address_str).is_some());
+        assert!(Address::from_str(address_str).is_err());
+
+        let public_key_b58 = "xpub6AHA9hZDN11k2ijHMeS5QqHx2KP9aMBRhTDqANMnwVtdyw2TDYRmF8PjpvwUFcL1Et8Hj59S3gTSMcUQ5gAqTz3Wd8EsMTmF3DChhqPQBnU";
+        let public_key = ExtendedPublicKey::import_key_from_base58_check(public_key_b58).unwrap();
+        let address = Address::create_p2wpkh(&public_key);
+        assert_eq!(address.hash, Address::create(&public_key).hash);
+        assert!(address.to_string().starts_with("bc1q"));
+    }
+
+    #[test]
+    fn test_taproot_address() {
+        // first receiving address of BIP-86
+        let address_str = "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr";
+        let address = Address::from_str(address_str).unwrap();
+        assert_eq!(address.address_type, AddressType::P2TR);
+        assert!(!address.testnet);
+        assert_eq!(
+            address.get_hash(),
+            "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+        );
+        assert_eq!(address.to_string(), address_str);
+
+        // version 1 with a Bech32 checksum
+        let address_str = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd";
+        assert!(bech32::decode(address_str).is_some());
+        assert!(Address::from_str(address_str).is_err());
+        // unknown witness version
+        assert!(Address::from_str("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs").is_err());
+    }
+
+    #[test]
+    fn test_network_detection() {
+        let mainnet = [
+            "1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD",
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+        ];
+        for address_str in mainnet {
+            let address = Address::from_str(address_str).unwrap();
+            assert_eq!(address.coin_type_index(), 0);
+        }
+        let testnet = [
+            "2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc",
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+        ];
+        for address_str in testnet {
+            let address = Address::from_str(address_str).unwrap();
+            assert_eq!(address.coin_type_index(), 1);
+        }
+    }
+
+    #[test]
+    fn test_from_script_pub_key() {
+        let addresses = [
+            "1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD",
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+        ];
+        for address_str in addresses {
+            let address = Address::from_str(address_str).unwrap();
+            let bytes = script::serialize(&address.script_pub_key()).unwrap();
+            let parsed = Address::from_script_pub_key(&bytes[1..], false).unwrap();
+            assert_eq!(parsed.to_string(), address_str);
+        }
+        let bytes = hex::hex_to_bytes("6a0401020304").unwrap();
+        assert!(Address::from_script_pub_key(&bytes, false).is_none());
+    }
+
+    #[test]
+    fn test_script_pub_key() {
+        let address = Address::from_str("1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD").unwrap();
+        assert_eq!(address.script_type(), ScriptType::P2PKH);
+        assert_eq!(
+            address.script_pub_key(),
+            format!(
+                "OP_DUP OP_HASH160 OP_PUSHBYTES_20 {} OP_EQUALVERIFY OP_CHECKSIG",
+                address.get_hash()
+            )
+        );
+
+        let address = Address::from_str("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy").unwrap();
+        assert_eq!(address.script_type(), ScriptType::P2SH);
+        assert_eq!(
+            address.script_pub_key(),
+            "OP_HASH160 OP_PUSHBYTES_20 b472a266d0bd89c13706a4132ccfb16f7c3b9fcb OP_EQUAL"
+        );
+
+        // examples of BIP-173 and BIP-86
+        let address = Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        assert_eq!(address.script_type(), ScriptType::P2WPKH);
+        assert_eq!(
+            script::serialize(&address.script_pub_key()).unwrap(),
+            hex::hex_to_bytes("160014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap()
+        );
+
+        let address =
+            Address::from_str("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7")
+                .unwrap();
+        assert_eq!(address.script_type(), ScriptType::P2WSH);
+        assert_eq!(
+            script::serialize(&address.script_pub_key()).unwrap(),
+            hex::hex_to_bytes(
+                "2200201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"
+            )
+            .unwrap()
+        );
+
+        let address =
+            Address::from_str("bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr")
+                .unwrap();
+        assert_eq!(address.script_type(), ScriptType::P2TR);
+        assert_eq!(
+            script::serialize(&address.script_pub_key()).unwrap(),
+            hex::hex_to_bytes(
+                "225120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+            )
+            .unwrap()
+        );
+    }
 }
@@ -1,12 +1,15 @@
 //! Implements [BIP-32](https://en.bitcoin.it/wiki/BIP_0032).
 //! This BIP describes hierarchical deterministic wallets (or "HD Wallets").
 use crate::keys::address::Address;
-use crate::keys::error::{ChildKeyDeriveError, ImportKeyError};
+use crate::keys::error::{ChildKeyDeriveError, ImportKeyError, ParseDerivationPathError};
 use crate::utils::base58;
 use bitcoin_hashes::{hmac, ripemd160, sha256, sha512, Hash, HashEngine};
 use num_bigint::BigUint;
 use secp256k1::{PublicKey, Secp256k1, SecretKey};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroize;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ExtendedPrivateKey {
@@ -28,11 +31,30 @@ pub struct ExtendedPublicKey {
     pub key_data: Vec<u8>, // must be 33 bytes
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ChildIndex {
+    pub index: u32, // without the hardened offset
+    pub hardened: bool,
+}
+
+/// A derivation path such as `m/44'/0'/5'/0/17`, relative to the master key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DerivationPath {
+    pub indices: Vec<ChildIndex>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DerivedKey {
+    pub path: String,
+    pub public_key: String, // Base58Check
+    pub address: String,
+}
+
 impl ExtendedPrivateKey {
     /// Creates a private master key (extended format) from the given seed.
-    pub fn create_master_key(seed: [u8; 64], testnet: bool) -> ExtendedPrivateKey {
+    pub fn create_master_key<S: AsRef<[u8]>>(seed: S, testnet: bool) -> ExtendedPrivateKey {
         let mut engine = hmac::HmacEngine::<sha512::Hash>::new(b"Bitcoin seed");
-        engine.input(&seed);
+        engine.input(seed.as_ref());
         let seed_hash = hmac::Hmac::from_engine(engine).into_inner();
         let (private_key, chain_code) = seed_hash.split_at(seed_hash.len() / 2);
 
@@ -59,6 +81,9 @@ impl ExtendedPrivateKey {
             return Err(ImportKeyError::new("Invalid character"));
         }
         let bytes = bytes.unwrap();
+        if bytes.len() != 82 {
+            return Err(ImportKeyError::new("Invalid length"));
+        }
         let version = &bytes[..4];
         if version != [0x04, 0x88, 0xAD, 0xE4] && version != [0x04, 0x35, 0x83, 0x94] {
             return Err(ImportKeyError::new("Invalid version bytes"));
@@ -181,6 +206,26 @@ impl ExtendedPrivateKey {
     pub fn derive_public_key(&self) -> ExtendedPublicKey {
         ExtendedPublicKey::derive_public_key(&self)
     }
+
+    /// Derives the descendant key at the specified path.
+    pub fn derive_path(
+        &self,
+        path: &DerivationPath,
+    ) -> Result<ExtendedPrivateKey, ChildKeyDeriveError> {
+        let mut key = self.clone();
+        for child_index in &path.indices {
+            key = key.derive_child_key(child_index.index, child_index.hardened)?;
+        }
+        Ok(key)
+    }
+}
+
+impl Drop for ExtendedPrivateKey {
+    /// Wipes the secret key material from memory.
+    fn drop(&mut self) {
+        self.key_data.zeroize();
+        self.chain_code.zeroize();
+    }
 }
 
 impl ExtendedPublicKey {
@@ -209,6 +254,9 @@ impl ExtendedPublicKey {
             return Err(ImportKeyError::new("Invalid character"));
         }
         let bytes = bytes.unwrap();
+        if bytes.len() != 82 {
+            return Err(ImportKeyError::new("Invalid length"));
+        }
         let version = &bytes[..4];
         if version != [0x04, 0x88, 0xB2, 0x1E] && version != [0x04, 0x35, 0x87, 0xCF] {
             return Err(ImportKeyError::new("Invalid version bytes"));
@@ -317,11 +365,80 @@ impl ExtendedPublicKey {
     pub fn get_address(&self) -> Address {
         Address::create(self)
     }
+
+    /// Returns the addresses that the wallet receives to with the public key,
+    /// i.e., the P2PKH address followed by the P2WPKH address.
+    pub fn get_addresses(&self) -> Vec<Address> {
+        vec![Address::create(self), Address::create_p2wpkh(self)]
+    }
+
+    /// Derives the descendant key at the specified path.
+    /// Returns an error if the path contains hardened indices.
+    pub fn derive_path(
+        &self,
+        path: &DerivationPath,
+    ) -> Result<ExtendedPublicKey, ChildKeyDeriveError> {
+        let mut key = self.clone();
+        for child_index in &path.indices {
+            if child_index.hardened {
+                return Err(ChildKeyDeriveError::new(
+                    "Hardened child of a public key requested",
+                ));
+            }
+            key = key.derive_child_key(child_index.index)?;
+        }
+        Ok(key)
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = ParseDerivationPathError;
+
+    /// Parses a derivation path. Hardened indices are marked with `'` or `h`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut components = s.trim().split('/');
+        if components.next() != Some("m") {
+            return Err(ParseDerivationPathError::new("Path must start with m"));
+        }
+        let mut indices = Vec::new();
+        for component in components {
+            let (digits, hardened) = match component.strip_suffix(&['\'', 'h'][..]) {
+                Some(digits) => (digits, true),
+                None => (component, false),
+            };
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(ParseDerivationPathError::new("Invalid child index"));
+            }
+            let index = match digits.parse::<u32>() {
+                Ok(index) if index < 2u32.pow(31) => index,
+                _ => return Err(ParseDerivationPathError::new("Child index out of bounds")),
+            };
+            indices.push(ChildIndex { index, hardened });
+        }
+        if indices.len() > 0xFF {
+            return Err(ParseDerivationPathError::new("Maximum depth exceeded"));
+        }
+        Ok(DerivationPath { indices })
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for child_index in &self.indices {
+            write!(f, "/{}", child_index.index)?;
+            if child_index.hardened {
+                write!(f, "'")?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::keys::bip32::{ExtendedPrivateKey, ExtendedPublicKey};
+    use crate::keys::bip32::{DerivationPath, ExtendedPrivateKey, ExtendedPublicKey};
+    use std::str::FromStr;
 
     #[test]
     fn test_create_master_key_private() {
@@ -481,4 +598,47 @@ mod tests {
         let target = String::from("mxoePtNPAGTUCe7814kjGx9zHddaERxu4r");
         assert_eq!(public_key.get_address().to_string(), target);
     }
+
+    #[test]
+    fn test_parse_derivation_path() {
+        let path = DerivationPath::from_str("m/44'/0h/5'/0/17").unwrap();
+        assert_eq!(path.indices.len(), 5);
+        assert!(path.indices[1].hardened);
+        assert!(!path.indices[4].hardened);
+        assert_eq!(path.indices[4].index, 17);
+        assert_eq!(path.to_string(), "m/44'/0'/5'/0/17");
+        assert!(DerivationPath::from_str("m").unwrap().indices.is_empty());
+
+        assert!(DerivationPath::from_str("44'/0'").is_err());
+        assert!(DerivationPath::from_str("m/").is_err());
+        assert!(DerivationPath::from_str("m/-1").is_err());
+        assert!(DerivationPath::from_str("m/0''").is_err());
+        assert!(DerivationPath::from_str("m/2147483648").is_err());
+    }
+
+    #[test]
+    fn test_derive_path() {
+        // test vector 1 from BIP-32
+        let private_key_b58 = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let path = DerivationPath::from_str("m/0'/1/2'/2/1000000000").unwrap();
+        let target = "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76";
+        assert_eq!(
+            private_key.derive_path(&path).unwrap().to_base58_check(),
+            target
+        );
+
+        let public_key = private_key.derive_public_key();
+        assert!(public_key.derive_path(&path).is_err());
+        let path = DerivationPath::from_str("m/0/1").unwrap();
+        assert_eq!(
+            public_key.derive_path(&path).unwrap().to_base58_check(),
+            private_key
+                .derive_path(&path)
+                .unwrap()
+                .derive_public_key()
+                .to_base58_check()
+        );
+    }
 }
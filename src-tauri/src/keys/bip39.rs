@@ -55,6 +55,33 @@ pub fn generate_mnemonic(entropy_length: usize, entropy: Option<&[u8]>) -> Vec<&
         .collect()
 }
 
+/// Returns true if all words of the mnemonic are in the word list
+/// and the checksum is valid.
+pub fn validate_mnemonic(mnemonic: &[&str]) -> bool {
+    if ![12, 15, 18, 21, 24].contains(&mnemonic.len()) {
+        return false;
+    }
+    let word_list = load_word_list();
+    let mut bits: BitVec<u8, Msb0> = BitVec::with_capacity(11 * mnemonic.len());
+    for word in mnemonic {
+        let index = match word_list.iter().position(|other| other == word) {
+            Some(index) => index as u16,
+            None => return false,
+        };
+        bits.extend_from_bitslice(&index.view_bits::<Msb0>()[5..]);
+    }
+    let entropy_length = 32 * bits.len() / 33;
+    let (entropy_bits, checksum_bits) = bits.split_at(entropy_length);
+    let entropy: Vec<u8> = entropy_bits
+        .chunks(8)
+        .map(|section| section.load_be::<u8>())
+        .collect();
+    let hash = sha256::Hash::hash(&entropy);
+    let hash_bytes: [u8; 32] = hash.into_inner();
+    let hash_bits = hash_bytes.view_bits::<Msb0>();
+    hash_bits[..checksum_bits.len()] == *checksum_bits
+}
+
 /// Generates the binary seed from the mnemonic.
 pub fn generate_seed(mnemonic: Vec<&str>, passphrase: &str) -> [u8; 64] {
     let mut seed = [0u8; 64];
@@ -64,7 +91,7 @@ pub fn generate_seed(mnemonic: Vec<&str>, passphrase: &str) -> [u8; 64] {
 
 #[cfg(test)]
 mod tests {
-    use super::{generate_mnemonic, generate_seed, load_word_list};
+    use super::{generate_mnemonic, generate_seed, load_word_list, validate_mnemonic};
 
     fn hex_to_bytes(s: &str) -> Option<Vec<u8>> {
         // taken from https://users.rust-lang.org/t/hex-string-to-vec-u8/51903/3
@@ -102,6 +129,26 @@ mod tests {
         assert_eq!(mnemonic, target_mnemonic);
     }
 
+    #[test]
+    fn test_validate_mnemonic() {
+        let mnemonic = generate_mnemonic(256, None);
+        assert!(validate_mnemonic(&mnemonic));
+        let mnemonic = vec![
+            "army", "van", "defense", "carry", "jealous", "true", "garbage", "claim", "echo",
+            "media", "make", "crunch",
+        ];
+        assert!(validate_mnemonic(&mnemonic));
+        // invalid checksum
+        let mut mnemonic_invalid = mnemonic.clone();
+        mnemonic_invalid[11] = "army";
+        assert!(!validate_mnemonic(&mnemonic_invalid));
+        // unknown word
+        let mut mnemonic_invalid = mnemonic.clone();
+        mnemonic_invalid[0] = "bitcoin";
+        assert!(!validate_mnemonic(&mnemonic_invalid));
+        assert!(!validate_mnemonic(&mnemonic[..11]));
+    }
+
     #[test]
     fn test_generate_mnemonic_256() {
         let bytes =
@@ -0,0 +1,396 @@
+//! Implements the layout of the decrypted key file. The key hierarchy is serialized with
+//! bincode, which encodes the fields of a struct by position, so adding a field changes the
+//! layout. The layout starts with a magic number and a version, so it can be changed without
+//! breaking existing files. Files of earlier versions are migrated to the current layout,
+//! which is written the next time the file is saved. The files of the first version have
+//! neither a magic number nor a version.
+use bincode::Options;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use super::private_hierarchy::{Account, Change, CoinType, MasterPrivateKey, Purpose};
+use crate::keys::bip32::ExtendedPrivateKey;
+use crate::keys::error::KeyFileError;
+
+static MAGIC: &[u8; 4] = b"GHKH";
+static VERSION: u8 = 3;
+
+/// The layout of the first version, i.e., the key hierarchy without the seed.
+#[derive(Deserialize)]
+struct LegacyMasterPrivateKey<A> {
+    private_key: ExtendedPrivateKey,
+    purpose: LegacyPurpose<A>,
+}
+
+/// The layout of the key hierarchy with the seed.
+#[derive(Deserialize)]
+struct LegacySeedMasterPrivateKey<A> {
+    seed: Vec<u8>,
+    private_key: ExtendedPrivateKey,
+    purpose: LegacyPurpose<A>,
+}
+
+#[derive(Deserialize)]
+struct LegacyPurpose<A> {
+    index: u32,
+    private_key: ExtendedPrivateKey,
+    coin_types: BTreeMap<u32, LegacyCoinType<A>>,
+}
+
+#[derive(Deserialize)]
+struct LegacyCoinType<A> {
+    index: u32,
+    name: String,
+    private_key: ExtendedPrivateKey,
+    accounts: BTreeMap<u32, A>,
+}
+
+/// The layout of an account of version 1, i.e., without a label and a creation date.
+#[derive(Deserialize)]
+struct LegacyAccount {
+    index: u32,
+    private_key: ExtendedPrivateKey,
+    external_chain: Change,
+    internal_chain: Change,
+}
+
+/// The layout of an account of version 2, i.e., without the archived flag.
+#[derive(Deserialize)]
+struct LegacyLabeledAccount {
+    index: u32,
+    label: String,
+    created_at: u64,
+    color: Option<String>,
+    private_key: ExtendedPrivateKey,
+    external_chain: Change,
+    internal_chain: Change,
+}
+
+impl From<LegacyAccount> for Account {
+    /// The creation date of the account is unknown, so it is set to zero.
+    fn from(account: LegacyAccount) -> Account {
+        Account {
+            index: account.index,
+            label: String::new(),
+            created_at: 0,
+            color: None,
+            archived: false,
+            private_key: account.private_key,
+            external_chain: account.external_chain,
+            internal_chain: account.internal_chain,
+        }
+    }
+}
+
+impl From<LegacyLabeledAccount> for Account {
+    fn from(account: LegacyLabeledAccount) -> Account {
+        Account {
+            index: account.index,
+            label: account.label,
+            created_at: account.created_at,
+            color: account.color,
+            archived: false,
+            private_key: account.private_key,
+            external_chain: account.external_chain,
+            internal_chain: account.internal_chain,
+        }
+    }
+}
+
+impl<A: Into<Account>> From<LegacyPurpose<A>> for Purpose {
+    fn from(purpose: LegacyPurpose<A>) -> Purpose {
+        Purpose {
+            index: purpose.index,
+            private_key: purpose.private_key,
+            coin_types: purpose
+                .coin_types
+                .into_iter()
+                .map(|(index, coin_type)| {
+                    let coin_type = CoinType {
+                        index: coin_type.index,
+                        name: coin_type.name,
+                        private_key: coin_type.private_key,
+                        accounts: coin_type
+                            .accounts
+                            .into_iter()
+                            .map(|(index, account)| (index, account.into()))
+                            .collect(),
+                    };
+                    (index, coin_type)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<A: Into<Account>> From<LegacyMasterPrivateKey<A>> for MasterPrivateKey {
+    /// The seed of the key hierarchy is unknown, so it is left empty.
+    fn from(master_key: LegacyMasterPrivateKey<A>) -> MasterPrivateKey {
+        MasterPrivateKey {
+            seed: Vec::new(),
+            private_key: master_key.private_key,
+            purpose: master_key.purpose.into(),
+        }
+    }
+}
+
+impl<A: Into<Account>> From<LegacySeedMasterPrivateKey<A>> for MasterPrivateKey {
+    fn from(master_key: LegacySeedMasterPrivateKey<A>) -> MasterPrivateKey {
+        MasterPrivateKey {
+            seed: master_key.seed,
+            private_key: master_key.private_key,
+            purpose: master_key.purpose.into(),
+        }
+    }
+}
+
+/// Returns the key hierarchy in the current layout.
+pub fn encode(master_key: &MasterPrivateKey) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    bytes.extend(bincode::serialize(master_key)?);
+    Ok(bytes)
+}
+
+/// Returns the key hierarchy of a decrypted key file.
+/// Files of earlier versions are migrated to the current layout.
+pub fn decode(bytes: &[u8]) -> Result<MasterPrivateKey, Box<dyn Error>> {
+    if !bytes.starts_with(MAGIC) {
+        let master_key: LegacyMasterPrivateKey<LegacyAccount> = deserialize_exact(bytes)?;
+        return Ok(master_key.into());
+    }
+    match bytes[MAGIC.len()..].split_first() {
+        Some((version, payload)) if *version == VERSION => Ok(deserialize_exact(payload)?),
+        Some((1, payload)) => {
+            let master_key: LegacySeedMasterPrivateKey<LegacyAccount> = deserialize_exact(payload)?;
+            Ok(master_key.into())
+        }
+        Some((2, payload)) => {
+            let master_key: LegacySeedMasterPrivateKey<LegacyLabeledAccount> =
+                deserialize_exact(payload)?;
+            Ok(master_key.into())
+        }
+        _ => Err(Box::new(KeyFileError::new("Unsupported version"))),
+    }
+}
+
+/// Deserializes a value that spans all bytes, so a layout that only matches a prefix
+/// of the bytes is rejected. The encoding is the one of `bincode::serialize`.
+fn deserialize_exact<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, bincode::Error> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(bytes.len() as u64)
+        .reject_trailing_bytes()
+        .deserialize(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+    use crate::keys::bip32::ExtendedPrivateKey;
+    use crate::keys::bip44::private_hierarchy::MasterPrivateKey;
+    use aes_gcm::aead::{Aead, NewAead};
+    use aes_gcm::{Aes256Gcm, Key, Nonce};
+    use argon2::{
+        password_hash::{PasswordHasher, SaltString},
+        Argon2,
+    };
+    use std::fs;
+
+    /// Returns the accounts of the key hierarchy in the layout of version 1, i.e.,
+    /// with only their keys. A map and a sequence of pairs are encoded the same way.
+    fn serialize_with_legacy_accounts<S: serde::Serialize>(
+        master_key: &MasterPrivateKey,
+        prefix: S,
+    ) -> Vec<u8> {
+        let purpose = &master_key.purpose;
+        let coin_types: Vec<_> = purpose
+            .coin_types
+            .iter()
+            .map(|(index, coin_type)| {
+                let accounts: Vec<_> = coin_type
+                    .accounts
+                    .iter()
+                    .map(|(index, account)| {
+                        (
+                            *index,
+                            (
+                                account.index,
+                                &account.private_key,
+                                &account.external_chain,
+                                &account.internal_chain,
+                            ),
+                        )
+                    })
+                    .collect();
+                (
+                    *index,
+                    (
+                        coin_type.index,
+                        &coin_type.name,
+                        &coin_type.private_key,
+                        accounts,
+                    ),
+                )
+            })
+            .collect();
+        bincode::serialize(&(
+            prefix,
+            &master_key.private_key,
+            (purpose.index, &purpose.private_key, coin_types),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_load_baseline_file() {
+        let private_key = ExtendedPrivateKey::create_master_key([7u8; 64], true);
+        let mut master_key = MasterPrivateKey::create_from_key(private_key);
+        master_key.add_account(1);
+        master_key.add_account(1);
+        master_key.new_receive_keypair(1, 1, None);
+        master_key.new_change_keypair(1, 0, None);
+
+        // the first version serialized the key hierarchy without the seed,
+        // and a unit is encoded as no bytes
+        let bytes = serialize_with_legacy_accounts(&master_key, ());
+
+        // encrypt the way the first version did
+        let password = "password1234".to_string();
+        let salt = SaltString::new("saltsaltsaltsaltsaltsalt").unwrap();
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .unwrap()
+            .hash
+            .unwrap();
+        let cipher = Aes256Gcm::new(Key::from_slice(password_hash.as_bytes()));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(b"unique nonce"), bytes.as_ref())
+            .unwrap();
+        let path = std::env::temp_dir().join("ghorbu_test_baseline_key_file");
+        fs::write(&path, ciphertext).unwrap();
+
+        let loaded = MasterPrivateKey::load(&path, password).unwrap();
+        fs::remove_file(path).unwrap();
+        assert!(loaded.seed.is_empty());
+        assert_eq!(loaded.private_key.key_data, master_key.private_key.key_data);
+        let accounts = &loaded.purpose.coin_types.get(&1).unwrap().accounts;
+        assert_eq!(accounts.len(), 2);
+        let account = accounts.get(&1).unwrap();
+        assert_eq!(account.label, "");
+        assert_eq!(account.created_at, 0);
+        assert!(!account.archived);
+        assert_eq!(account.external_chain.keys.len(), 1);
+        assert_eq!(accounts.get(&0).unwrap().internal_chain.keys.len(), 1);
+    }
+
+    #[test]
+    fn test_decode_version_1() {
+        let mut master_key = MasterPrivateKey::create_from_seed([5u8; 64]);
+        master_key.add_account(0);
+        let mut bytes = b"GHKH".to_vec();
+        bytes.push(1);
+        bytes.extend(serialize_with_legacy_accounts(
+            &master_key,
+            &master_key.seed,
+        ));
+
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.seed, master_key.seed);
+        let account = decoded
+            .purpose
+            .coin_types
+            .get(&0)
+            .unwrap()
+            .accounts
+            .get(&0)
+            .unwrap();
+        assert_eq!(account.label, "");
+    }
+
+    #[test]
+    fn test_decode_version_2() {
+        let mut master_key = MasterPrivateKey::create_from_seed([5u8; 64]);
+        master_key.add_account(0);
+        master_key.rename_account(0, 0, "Savings".to_string(), None);
+        let purpose = &master_key.purpose;
+        let coin_types: Vec<_> = purpose
+            .coin_types
+            .iter()
+            .map(|(index, coin_type)| {
+                let accounts: Vec<_> = coin_type
+                    .accounts
+                    .iter()
+                    .map(|(index, account)| {
+                        (
+                            *index,
+                            (
+                                account.index,
+                                &account.label,
+                                account.created_at,
+                                &account.color,
+                                &account.private_key,
+                                &account.external_chain,
+                                &account.internal_chain,
+                            ),
+                        )
+                    })
+                    .collect();
+                (
+                    *index,
+                    (
+                        coin_type.index,
+                        &coin_type.name,
+                        &coin_type.private_key,
+                        accounts,
+                    ),
+                )
+            })
+            .collect();
+        // the layout with labels, but without the archived flag
+        let mut bytes = b"GHKH".to_vec();
+        bytes.push(2);
+        bytes.extend(
+            bincode::serialize(&(
+                &master_key.seed,
+                &master_key.private_key,
+                (purpose.index, &purpose.private_key, coin_types),
+            ))
+            .unwrap(),
+        );
+
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.seed, master_key.seed);
+        let account = decoded
+            .purpose
+            .coin_types
+            .get(&0)
+            .unwrap()
+            .accounts
+            .get(&0)
+            .unwrap();
+        assert_eq!(account.label, "Savings");
+        assert!(!account.archived);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let mut master_key = MasterPrivateKey::create_from_seed([3u8; 64]);
+        master_key.add_account(0);
+        let bytes = encode(&master_key).unwrap();
+        assert!(bytes.starts_with(b"GHKH"));
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.seed, master_key.seed);
+        assert_eq!(
+            decoded.purpose.coin_types.get(&0).unwrap().accounts.len(),
+            1
+        );
+
+        let mut bytes = bytes;
+        bytes[4] = 4;
+        assert!(decode(&bytes).is_err());
+        assert!(decode(b"GHKH").is_err());
+        assert!(decode(b"garbage").is_err());
+    }
+}
@@ -1,8 +1,12 @@
 //! Implements a logical hierarchy for deterministic wallets as described
 //! in [BIP-44](https://en.bitcoin.it/wiki/BIP_0044).
+use crate::keys::address::Address;
+use crate::keys::bip32::ExtendedPublicKey;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 
+pub mod key_file;
+pub mod multisig;
 pub mod private_hierarchy;
 pub mod public_hierarchy;
 pub use private_hierarchy::{Keypair, MasterPrivateKey};
@@ -11,23 +15,42 @@ pub use public_hierarchy::MasterPublicKey;
 pub static BITCOIN_INDEX: u32 = 0;
 pub static BITCOIN_TESTNET_INDEX: u32 = 1;
 
+/// The purposes of the script types with a single key, i.e., P2PKH (BIP-44),
+/// P2SH-P2WPKH (BIP-49), P2WPKH (BIP-84) and P2TR (BIP-86).
+pub static SINGLE_KEY_PURPOSES: [u32; 4] = [44, 49, 84, 86];
+
 lazy_static! {
     static ref COIN_TYPE_NAMES: HashMap<u32, &'static str> = {
         let mut m = HashMap::new();
         m.insert(0, "Bitcoin");
-        m.insert(1, "Bitcoin Testnet");
+        m.insert(1, "Bitcoin Testnet"); // all test networks, see `network::TestNetwork`
         m
     };
 }
 
+/// Returns the addresses of the public key for the script type of the purpose.
+/// The keys of the wallet (BIP-44) have a P2PKH and a P2WPKH address.
+pub fn purpose_addresses(public_key: &ExtendedPublicKey, purpose_index: u32) -> Vec<Address> {
+    match purpose_index {
+        49 => vec![Address::create_p2sh_p2wpkh(public_key)],
+        84 => vec![Address::create_p2wpkh(public_key)],
+        86 => vec![Address::create_p2tr(public_key)],
+        _ => public_key.get_addresses(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::purpose_addresses;
+    use crate::encryption::KdfParams;
     use crate::keys::{
-        bip32::ExtendedPrivateKey,
+        address::{Address, AddressType},
+        bip32::{DerivationPath, ExtendedPrivateKey},
         bip44::{
             private_hierarchy::MasterPrivateKey, public_hierarchy::MasterPublicKey, BITCOIN_INDEX,
         },
     };
+    use std::str::FromStr;
 
     #[test]
     fn test_derive_private_and_public_key() {
@@ -46,4 +69,237 @@ mod tests {
             child_keypair.public_key.to_base58_check()
         );
     }
+
+    #[test]
+    fn test_save_public_key_to_file() {
+        let path = std::env::temp_dir().join("ghorbu_test_save_public_key");
+        let _ = std::fs::remove_file(&path);
+        assert!(MasterPublicKey::load_from_file(&path).is_err());
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        master_private_key.add_account(BITCOIN_INDEX);
+        let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+        master_public_key.save_to_file(&path).unwrap();
+        let loaded = MasterPublicKey::load_from_file(&path).unwrap();
+        assert_eq!(loaded.serialize(), master_public_key.serialize());
+        assert_eq!(
+            loaded.get_all_addresses(BITCOIN_INDEX, 0).len(),
+            master_public_key.get_all_addresses(BITCOIN_INDEX, 0).len()
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_rename_account() {
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        let account = master_private_key.add_account(BITCOIN_INDEX);
+        master_private_key.rename_account(
+            BITCOIN_INDEX,
+            account.index,
+            "Savings \"2022\"".to_string(),
+            Some("#ff0000".to_string()),
+        );
+        let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+        let overview: serde_json::Value =
+            serde_json::from_str(&master_public_key.jsonify(false)).unwrap();
+        let account_overview = &overview["purpose"]["coin_types"][0]["accounts"][0];
+        assert_eq!(account_overview["label"], "Savings \"2022\"");
+        assert_eq!(account_overview["color"], "#ff0000");
+        assert_eq!(account_overview["created_at"], account.created_at);
+    }
+
+    #[test]
+    fn test_archive_account() {
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        master_private_key.add_account(BITCOIN_INDEX);
+        let account = master_private_key.add_account(BITCOIN_INDEX);
+        master_private_key.set_account_archived(BITCOIN_INDEX, account.index, true);
+        let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+
+        let overview: serde_json::Value =
+            serde_json::from_str(&master_public_key.jsonify(false)).unwrap();
+        let accounts = overview["purpose"]["coin_types"][0]["accounts"]
+            .as_array()
+            .unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0]["index"], 0);
+
+        let overview: serde_json::Value =
+            serde_json::from_str(&master_public_key.jsonify(true)).unwrap();
+        let accounts = overview["purpose"]["coin_types"][0]["accounts"]
+            .as_array()
+            .unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[1]["archived"], true);
+    }
+
+    #[test]
+    fn test_create_from_secret() {
+        let mnemonic = "army van defense carry jealous true garbage claim echo media make crunch";
+        let master_private_key = MasterPrivateKey::create_from_secret(mnemonic, "").unwrap();
+        let seed = crate::keys::bip39::generate_seed(mnemonic.split(' ').collect(), "");
+        assert_eq!(master_private_key.seed, seed.to_vec());
+
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let master_private_key =
+            MasterPrivateKey::create_from_secret(&format!(" {}\n", private_key_b58), "").unwrap();
+        assert_eq!(
+            master_private_key.private_key.to_base58_check(),
+            private_key_b58
+        );
+
+        assert!(MasterPrivateKey::create_from_secret("army van defense", "").is_err());
+        assert!(MasterPrivateKey::create_from_secret("xprv9s21ZrQH143K", "").is_err());
+    }
+
+    #[test]
+    fn test_export_account_keys() {
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        let account = master_private_key.add_account(BITCOIN_INDEX);
+        let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+
+        let xprv = master_private_key
+            .get_account_private_key(BITCOIN_INDEX, account.index)
+            .unwrap();
+        let xpub = master_public_key
+            .get_account_public_key(BITCOIN_INDEX, account.index)
+            .unwrap();
+        assert!(xprv.to_base58_check().starts_with("xprv"));
+        assert!(xpub.to_base58_check().starts_with("xpub"));
+        assert_eq!(
+            xpub.to_base58_check(),
+            xprv.derive_public_key().to_base58_check()
+        );
+        assert!(master_public_key
+            .get_account_public_key(BITCOIN_INDEX, account.index + 1)
+            .is_none());
+    }
+
+    #[test]
+    fn test_get_all_receive_simple_addresses() {
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        master_private_key.add_account(BITCOIN_INDEX);
+        let account = master_private_key.add_account(BITCOIN_INDEX);
+        let mut master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+        master_public_key.new_receive_key(BITCOIN_INDEX, account.index);
+        master_public_key.new_receive_key(BITCOIN_INDEX, account.index);
+
+        let addresses =
+            master_public_key.get_all_receive_simple_addresses(BITCOIN_INDEX, account.index);
+        assert_eq!(addresses.len(), 2);
+        assert_eq!(addresses[0].index, 1);
+        assert_eq!(addresses[0].path, "m/44'/1'/0/1");
+        assert!(addresses[0].used.is_none());
+        // the path reproduces the address from the master key
+        let path = DerivationPath::from_str(&addresses[0].path).unwrap();
+        let key = master_private_key.private_key.derive_path(&path).unwrap();
+        assert_eq!(
+            key.derive_public_key().get_address().to_string(),
+            addresses[0].address
+        );
+    }
+
+    #[test]
+    fn test_purpose_addresses() {
+        let private_key = ExtendedPrivateKey::create_master_key([9u8; 64], true);
+        let master_private_key = MasterPrivateKey::create_from_key(private_key);
+        let mut segwit_private_key = master_private_key.for_purpose(84);
+        assert_eq!(segwit_private_key.purpose.index, 84);
+        segwit_private_key.add_account(BITCOIN_INDEX);
+        let keypair = segwit_private_key.new_receive_keypair(BITCOIN_INDEX, 0, None);
+        let path = DerivationPath::from_str("m/84'/0'/0/0").unwrap();
+        let key = master_private_key.private_key.derive_path(&path).unwrap();
+        assert_eq!(key.key_data, keypair.private_key.key_data);
+
+        let addresses = purpose_addresses(&keypair.public_key, 84);
+        assert_eq!(addresses.len(), 1);
+        assert_eq!(addresses[0].address_type, AddressType::P2WPKH);
+        assert_eq!(
+            purpose_addresses(&keypair.public_key, 86)[0].address_type,
+            AddressType::P2TR
+        );
+        // the keys of the wallet have a P2PKH and a P2WPKH address
+        let addresses = purpose_addresses(&keypair.public_key, 44);
+        assert_eq!(addresses[0].address_type, AddressType::P2PKH);
+        assert_eq!(addresses[1].address_type, AddressType::P2WPKH);
+    }
+
+    #[test]
+    fn test_find_keypair_with_path() {
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        master_private_key.add_account(BITCOIN_INDEX);
+        let account = master_private_key.add_account(BITCOIN_INDEX);
+        master_private_key.new_change_keypair(BITCOIN_INDEX, account.index, None);
+        let keypair = master_private_key.new_change_keypair(BITCOIN_INDEX, account.index, None);
+
+        let address = keypair.public_key.get_address();
+        let (found, path) = master_private_key.find_keypair_with_path(&address).unwrap();
+        assert_eq!(
+            found.public_key.to_base58_check(),
+            keypair.public_key.to_base58_check()
+        );
+        assert_eq!(path.to_string(), "m/44'/1'/1/1");
+        let key = master_private_key.private_key.derive_path(&path).unwrap();
+        assert_eq!(
+            key.derive_public_key().get_address().to_string(),
+            address.to_string()
+        );
+
+        // the P2WPKH address of the key belongs to the hierarchy as well
+        let address = Address::create_p2wpkh(&keypair.public_key);
+        let (_, segwit_path) = master_private_key.find_keypair_with_path(&address).unwrap();
+        assert_eq!(segwit_path.to_string(), path.to_string());
+        let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+        let address_paths = master_public_key.get_address_paths(BITCOIN_INDEX);
+        assert_eq!(address_paths[&address.to_string()].0, account.index);
+    }
+
+    #[test]
+    fn test_load_from_backup() {
+        let path = std::env::temp_dir().join("ghorbu_test_load_from_backup");
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        let params = KdfParams {
+            memory: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        master_private_key
+            .save_with_params(&path, "password".to_string(), &params)
+            .unwrap();
+        master_private_key.add_account(BITCOIN_INDEX);
+        master_private_key
+            .save_with_params(&path, "password".to_string(), &params)
+            .unwrap();
+
+        // a corrupted key file falls back to the previous version
+        std::fs::write(&path, b"corrupted").unwrap();
+        let restored = MasterPrivateKey::load(&path, "password".to_string()).unwrap();
+        assert!(restored.purpose.coin_types[&BITCOIN_INDEX]
+            .accounts
+            .is_empty());
+
+        MasterPrivateKey::discard_backups(&path).unwrap();
+        assert!(MasterPrivateKey::load(&path, "password".to_string()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,591 @@
+//! Implements multisig accounts whose outputs are locked by an m-of-n
+//! multisig script of the keys of several cosigners. The account keys are
+//! derived at `m/48'/coin_type'/account'/script_type'` as described in
+//! [BIP-48](https://github.com/bitcoin/bips/blob/master/bip-0048.mediawiki).
+//! Since the key file cannot be extended without breaking existing wallets,
+//! the multisig accounts are stored next to the key file of the wallet.
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{Keypair, MasterPrivateKey, BITCOIN_TESTNET_INDEX};
+use crate::keys::address::Address;
+use crate::keys::bip32::{ChildIndex, DerivationPath, ExtendedPublicKey};
+use crate::keys::error::MultisigError;
+use crate::transactions::psbt::KeySource;
+use crate::transactions::script;
+use crate::utils::{file, hex};
+
+static PURPOSE_INDEX: u32 = 48;
+static MAX_COSIGNERS: usize = 15; // the limit of standard P2SH scripts
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MultisigScriptType {
+    P2WSH,
+    P2SHP2WSH,
+}
+
+/// The extended public key of a cosigner together with its origin, i.e., the
+/// fingerprint of the cosigner's master key and the derivation path of the key.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Cosigner {
+    pub label: String,
+    pub fingerprint: [u8; 4],
+    pub path: DerivationPath,
+    pub public_key: ExtendedPublicKey,
+    pub local: bool, // true if the key is derived from the master key of this wallet
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MultisigAccount {
+    pub index: u32,
+    pub coin_type_index: u32,
+    pub label: String,
+    pub threshold: usize,
+    pub script_type: MultisigScriptType,
+    pub cosigners: Vec<Cosigner>,
+    pub external_index: u32, // number of receive addresses
+    pub internal_index: u32, // number of change addresses
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MultisigAddress {
+    pub address: String,
+    pub change: bool,
+    pub index: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct MultisigAccounts {
+    accounts: Vec<MultisigAccount>,
+}
+
+impl MultisigScriptType {
+    /// Returns the script type index of the derivation path.
+    pub fn script_index(&self) -> u32 {
+        match self {
+            MultisigScriptType::P2SHP2WSH => 1,
+            MultisigScriptType::P2WSH => 2,
+        }
+    }
+}
+
+impl FromStr for MultisigScriptType {
+    type Err = MultisigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "p2wsh" => Ok(MultisigScriptType::P2WSH),
+            "p2sh-p2wsh" => Ok(MultisigScriptType::P2SHP2WSH),
+            _ => Err(MultisigError::new("invalid_script_type_error")),
+        }
+    }
+}
+
+impl fmt::Display for MultisigScriptType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MultisigScriptType::P2WSH => write!(f, "p2wsh"),
+            MultisigScriptType::P2SHP2WSH => write!(f, "p2sh-p2wsh"),
+        }
+    }
+}
+
+impl Cosigner {
+    /// Parses the key of a cosigner in the format `[fingerprint/path]xpub`, e.g.,
+    /// `[d90c6a4f/48'/0'/0'/2']xpub...`. If the origin is missing, the key itself
+    /// is treated as the master key of the cosigner.
+    pub fn parse(label: String, key: &str) -> Result<Cosigner, MultisigError> {
+        let invalid = || MultisigError::new("invalid_cosigner_error");
+        let key = key.trim();
+        let (origin, key) = match key.strip_prefix('[') {
+            Some(rest) => match rest.split_once(']') {
+                Some((origin, key)) => (Some(origin), key),
+                None => return Err(invalid()),
+            },
+            None => (None, key),
+        };
+        let public_key = match ExtendedPublicKey::import_key_from_base58_check(key) {
+            Ok(public_key) => public_key,
+            Err(_) => return Err(invalid()),
+        };
+        let (fingerprint, path) = match origin {
+            Some(origin) => {
+                let (fingerprint, path) = match origin.split_once('/') {
+                    Some((fingerprint, path)) => (fingerprint, format!("m/{}", path)),
+                    None => (origin, String::from("m")),
+                };
+                let fingerprint = hex::hex_to_bytes(fingerprint)
+                    .and_then(|fingerprint| fingerprint.try_into().ok())
+                    .ok_or_else(invalid)?;
+                let path = DerivationPath::from_str(&path).map_err(|_| invalid())?;
+                if path.indices.len() != public_key.depth as usize {
+                    return Err(invalid());
+                }
+                (fingerprint, path)
+            }
+            None => (
+                public_key.get_fingerprint(),
+                DerivationPath { indices: vec![] },
+            ),
+        };
+        Ok(Cosigner {
+            label,
+            fingerprint,
+            path,
+            public_key,
+            local: false,
+        })
+    }
+
+    /// Derives the key of this wallet for the specified multisig account.
+    pub fn derive_local(
+        label: String,
+        master_private_key: &MasterPrivateKey,
+        coin_type_index: u32,
+        account_index: u32,
+        script_type: MultisigScriptType,
+    ) -> Cosigner {
+        let path = account_path(coin_type_index, account_index, script_type);
+        let private_key = master_private_key
+            .private_key
+            .derive_path(&path)
+            .expect("hardened derivation of a private key");
+        let mut public_key = private_key.derive_public_key();
+        public_key.testnet = coin_type_index == BITCOIN_TESTNET_INDEX;
+        Cosigner {
+            label,
+            fingerprint: master_private_key.private_key.get_fingerprint(),
+            path,
+            public_key,
+            local: true,
+        }
+    }
+
+    /// Returns the key in the format `[fingerprint/path]xpub`, which is shared with
+    /// the other cosigners.
+    pub fn to_key_expression(&self) -> String {
+        let path = self.path.to_string();
+        format!(
+            "[{}{}]{}",
+            hex::bytes_to_hex(&self.fingerprint),
+            &path[1..],
+            self.public_key.to_base58_check()
+        )
+    }
+
+    /// Returns the public key and its key source at the specified index of the
+    /// receive or change chain.
+    fn derive_key(&self, change: bool, index: u32) -> (Vec<u8>, KeySource) {
+        let chain_index = if change { 1 } else { 0 };
+        let public_key = self
+            .public_key
+            .derive_child_key(chain_index)
+            .and_then(|chain_key| chain_key.derive_child_key(index))
+            .expect("non-hardened derivation of a public key");
+        let mut path = self.path.clone();
+        path.indices.push(ChildIndex {
+            index: chain_index,
+            hardened: false,
+        });
+        path.indices.push(ChildIndex {
+            index,
+            hardened: false,
+        });
+        (
+            public_key.key_data,
+            KeySource {
+                fingerprint: self.fingerprint,
+                path,
+            },
+        )
+    }
+}
+
+impl MultisigAccount {
+    /// Returns true if the account belongs to the test network.
+    pub fn testnet(&self) -> bool {
+        self.coin_type_index == BITCOIN_TESTNET_INDEX
+    }
+
+    /// Returns the public keys of all cosigners at the specified index together
+    /// with their key sources.
+    pub fn key_sources(&self, change: bool, index: u32) -> Vec<(Vec<u8>, KeySource)> {
+        self.cosigners
+            .iter()
+            .map(|cosigner| cosigner.derive_key(change, index))
+            .collect()
+    }
+
+    /// Returns the multisig script at the specified index of the receive or change chain.
+    pub fn witness_script(&self, change: bool, index: u32) -> Vec<u8> {
+        let public_keys: Vec<Vec<u8>> = self
+            .key_sources(change, index)
+            .into_iter()
+            .map(|(public_key, _)| public_key)
+            .collect();
+        script::multisig_script(self.threshold, &public_keys).expect("validated threshold")
+    }
+
+    /// Returns the redeem script of a P2SH-P2WSH account or `None` for P2WSH accounts.
+    pub fn redeem_script(&self, change: bool, index: u32) -> Option<Vec<u8>> {
+        match self.script_type {
+            MultisigScriptType::P2WSH => None,
+            MultisigScriptType::P2SHP2WSH => Some(script::p2sh_p2wsh_redeem_script(
+                &self.witness_script(change, index),
+            )),
+        }
+    }
+
+    /// Returns the address at the specified index of the receive or change chain.
+    pub fn address(&self, change: bool, index: u32) -> Address {
+        match self.redeem_script(change, index) {
+            Some(redeem_script) => Address::create_p2sh(&redeem_script, self.testnet()),
+            None => Address::create_p2wsh(&self.witness_script(change, index), self.testnet()),
+        }
+    }
+
+    /// Returns the key pair of this wallet at the specified index of the receive or
+    /// change chain or `None` if the wallet is not a cosigner of the account.
+    pub fn local_keypair(
+        &self,
+        master_private_key: &MasterPrivateKey,
+        change: bool,
+        index: u32,
+    ) -> Option<Keypair> {
+        let cosigner = self.cosigners.iter().find(|cosigner| cosigner.local)?;
+        let (_, key_source) = cosigner.derive_key(change, index);
+        let private_key = master_private_key
+            .private_key
+            .derive_path(&key_source.path)
+            .ok()?;
+        Some(Keypair {
+            public_key: private_key.derive_public_key(),
+            private_key,
+        })
+    }
+
+    /// Creates a new receive address.
+    pub fn new_receive_address(&mut self) -> Address {
+        let address = self.address(false, self.external_index);
+        self.external_index += 1;
+        address
+    }
+
+    /// Creates a new change address.
+    pub fn new_change_address(&mut self) -> Address {
+        let address = self.address(true, self.internal_index);
+        self.internal_index += 1;
+        address
+    }
+
+    /// Returns all receive and change addresses that were created.
+    pub fn get_addresses(&self) -> Vec<MultisigAddress> {
+        let receive = (0..self.external_index).map(|index| (false, index));
+        let change = (0..self.internal_index).map(|index| (true, index));
+        receive
+            .chain(change)
+            .map(|(change, index)| MultisigAddress {
+                address: self.address(change, index).to_string(),
+                change,
+                index,
+            })
+            .collect()
+    }
+
+    /// Returns the chain and the index of the address
+    /// or `None` if the address does not belong to the account.
+    pub fn find_address(&self, address: &Address) -> Option<(bool, u32)> {
+        let address = address.to_string();
+        self.get_addresses()
+            .into_iter()
+            .find(|multisig_address| multisig_address.address == address)
+            .map(|multisig_address| (multisig_address.change, multisig_address.index))
+    }
+}
+
+impl MultisigAccounts {
+    /// Adds a multisig account with the specified cosigners.
+    /// Returns an error if the threshold is invalid, if a key is used twice or
+    /// if a key belongs to another network.
+    pub fn add(
+        &mut self,
+        coin_type_index: u32,
+        label: String,
+        threshold: usize,
+        script_type: MultisigScriptType,
+        cosigners: Vec<Cosigner>,
+    ) -> Result<&MultisigAccount, MultisigError> {
+        if cosigners.len() < 2 || cosigners.len() > MAX_COSIGNERS {
+            return Err(MultisigError::new("invalid_threshold_error"));
+        }
+        if threshold == 0 || threshold > cosigners.len() {
+            return Err(MultisigError::new("invalid_threshold_error"));
+        }
+        let testnet = coin_type_index == BITCOIN_TESTNET_INDEX;
+        if cosigners
+            .iter()
+            .any(|cosigner| cosigner.public_key.testnet != testnet)
+        {
+            return Err(MultisigError::new("invalid_cosigner_error"));
+        }
+        let mut keys: Vec<&Vec<u8>> = cosigners
+            .iter()
+            .map(|cosigner| &cosigner.public_key.key_data)
+            .collect();
+        keys.sort();
+        keys.dedup();
+        if keys.len() != cosigners.len() {
+            return Err(MultisigError::new("duplicate_cosigner_error"));
+        }
+        let index = self.next_index(coin_type_index);
+        self.accounts.push(MultisigAccount {
+            index,
+            coin_type_index,
+            label,
+            threshold,
+            script_type,
+            cosigners,
+            external_index: 0,
+            internal_index: 0,
+        });
+        Ok(self.accounts.last().unwrap())
+    }
+
+    /// Returns the index of the next multisig account of the coin type.
+    pub fn next_index(&self, coin_type_index: u32) -> u32 {
+        self.accounts
+            .iter()
+            .filter(|account| account.coin_type_index == coin_type_index)
+            .map(|account| account.index + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the specified multisig account.
+    pub fn get(&self, coin_type_index: u32, index: u32) -> Option<&MultisigAccount> {
+        self.accounts
+            .iter()
+            .find(|account| account.coin_type_index == coin_type_index && account.index == index)
+    }
+
+    /// Returns the specified multisig account.
+    pub fn get_mut(&mut self, coin_type_index: u32, index: u32) -> Option<&mut MultisigAccount> {
+        self.accounts
+            .iter_mut()
+            .find(|account| account.coin_type_index == coin_type_index && account.index == index)
+    }
+
+    /// Returns the multisig accounts of the coin type.
+    pub fn get_accounts(&self, coin_type_index: u32) -> Vec<MultisigAccount> {
+        self.accounts
+            .iter()
+            .filter(|account| account.coin_type_index == coin_type_index)
+            .cloned()
+            .collect()
+    }
+
+    /// Removes the specified multisig account.
+    /// Returns false if the account does not exist.
+    pub fn remove(&mut self, coin_type_index: u32, index: u32) -> bool {
+        let count = self.accounts.len();
+        self.accounts
+            .retain(|account| account.coin_type_index != coin_type_index || account.index != index);
+        self.accounts.len() != count
+    }
+
+    /// Saves the multisig accounts to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let accounts_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &accounts_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the multisig accounts from the specified path.
+    /// Returns no accounts if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<MultisigAccounts, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(MultisigAccounts::default());
+        }
+        let accounts_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&accounts_encoded)?)
+    }
+}
+
+/// Returns the BIP-48 derivation path of the account key.
+fn account_path(
+    coin_type_index: u32,
+    account_index: u32,
+    script_type: MultisigScriptType,
+) -> DerivationPath {
+    let indices = [
+        PURPOSE_INDEX,
+        coin_type_index,
+        account_index,
+        script_type.script_index(),
+    ]
+    .iter()
+    .map(|index| ChildIndex {
+        index: *index,
+        hardened: true,
+    })
+    .collect();
+    DerivationPath { indices }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cosigner, MultisigAccounts, MultisigScriptType};
+    use crate::keys::{
+        bip32::ExtendedPrivateKey,
+        bip44::{MasterPrivateKey, BITCOIN_INDEX},
+    };
+    use crate::transactions::script;
+    use std::fs;
+
+    fn cosigners(script_type: MultisigScriptType) -> Vec<Cosigner> {
+        (1..=3)
+            .map(|seed| {
+                let private_key = ExtendedPrivateKey::create_master_key([seed; 32], false);
+                let master_private_key = MasterPrivateKey::create_from_key(private_key);
+                Cosigner::derive_local(
+                    format!("Cosigner {}", seed),
+                    &master_private_key,
+                    BITCOIN_INDEX,
+                    0,
+                    script_type,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_cosigner() {
+        let cosigner = &cosigners(MultisigScriptType::P2WSH)[0];
+        let key = cosigner.to_key_expression();
+        assert!(key.contains("/48'/0'/0'/2']xpub"));
+        let parsed = Cosigner::parse("Alice".to_string(), &key).unwrap();
+        assert_eq!(parsed.fingerprint, cosigner.fingerprint);
+        assert_eq!(parsed.path, cosigner.path);
+        assert!(!parsed.local);
+
+        // without an origin, the key is treated as a master key
+        let xpub = cosigner.public_key.to_base58_check();
+        let parsed = Cosigner::parse("Alice".to_string(), &xpub).unwrap();
+        assert_eq!(parsed.fingerprint, cosigner.public_key.get_fingerprint());
+        assert!(parsed.path.indices.is_empty());
+
+        assert!(Cosigner::parse("Alice".to_string(), &key[1..]).is_err());
+        assert!(Cosigner::parse("Alice".to_string(), "[d90c6a4f/48'/0'/0']xpub").is_err());
+        // the path has to match the depth of the key
+        let wrong_depth = key.replace("/2']", "']");
+        assert!(Cosigner::parse("Alice".to_string(), &wrong_depth).is_err());
+    }
+
+    #[test]
+    fn test_multisig_addresses() {
+        let mut accounts = MultisigAccounts::default();
+        let account = accounts
+            .add(
+                BITCOIN_INDEX,
+                "Family".to_string(),
+                2,
+                MultisigScriptType::P2WSH,
+                cosigners(MultisigScriptType::P2WSH),
+            )
+            .unwrap()
+            .clone();
+        let witness_script = account.witness_script(false, 0);
+        let (threshold, public_keys) = script::parse_multisig(&witness_script).unwrap();
+        assert_eq!(threshold, 2);
+        assert_eq!(public_keys.len(), 3);
+        let address = account.address(false, 0).to_string();
+        assert!(address.starts_with("bc1q"));
+        assert_eq!(address.len(), 62);
+
+        let account = accounts
+            .add(
+                BITCOIN_INDEX,
+                "Family".to_string(),
+                2,
+                MultisigScriptType::P2SHP2WSH,
+                cosigners(MultisigScriptType::P2SHP2WSH),
+            )
+            .unwrap();
+        assert_eq!(account.index, 1);
+        assert!(account.address(false, 0).to_string().starts_with('3'));
+
+        let account = accounts.get_mut(BITCOIN_INDEX, 0).unwrap();
+        let receive_address = account.new_receive_address();
+        let change_address = account.new_change_address();
+        assert_eq!(receive_address.to_string(), address);
+        assert_eq!(account.find_address(&change_address), Some((true, 0)));
+        assert_eq!(account.get_addresses().len(), 2);
+    }
+
+    #[test]
+    fn test_add_multisig_account() {
+        let mut accounts = MultisigAccounts::default();
+        let cosigners = cosigners(MultisigScriptType::P2WSH);
+        for threshold in [0, 4] {
+            assert!(accounts
+                .add(
+                    BITCOIN_INDEX,
+                    "Family".to_string(),
+                    threshold,
+                    MultisigScriptType::P2WSH,
+                    cosigners.clone(),
+                )
+                .is_err());
+        }
+        let mut duplicate = cosigners.clone();
+        duplicate[2] = duplicate[0].clone();
+        assert_eq!(
+            accounts
+                .add(
+                    BITCOIN_INDEX,
+                    "Family".to_string(),
+                    2,
+                    MultisigScriptType::P2WSH,
+                    duplicate,
+                )
+                .unwrap_err()
+                .to_string(),
+            "duplicate_cosigner_error"
+        );
+        // the keys of mainnet cosigners cannot be used on the test network
+        assert!(accounts
+            .add(
+                1,
+                "Family".to_string(),
+                2,
+                MultisigScriptType::P2WSH,
+                cosigners.clone(),
+            )
+            .is_err());
+        assert!(accounts.get_accounts(BITCOIN_INDEX).is_empty());
+
+        let path = std::env::temp_dir().join("ghorbu_test_multisig_accounts");
+        let _ = fs::remove_file(&path);
+        accounts
+            .add(
+                BITCOIN_INDEX,
+                "Family".to_string(),
+                2,
+                MultisigScriptType::P2WSH,
+                cosigners,
+            )
+            .unwrap();
+        accounts.save(&path).unwrap();
+        let mut accounts = MultisigAccounts::load(&path).unwrap();
+        assert_eq!(accounts.get_accounts(BITCOIN_INDEX).len(), 1);
+        assert!(accounts.remove(BITCOIN_INDEX, 0));
+        assert!(!accounts.remove(BITCOIN_INDEX, 0));
+        fs::remove_file(path).unwrap();
+    }
+}
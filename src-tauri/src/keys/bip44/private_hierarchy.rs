@@ -1,20 +1,30 @@
 //! Implements a logical hierarchy for deterministic wallets as described
 //! in [BIP-44](https://en.bitcoin.it/wiki/BIP_0044).
-use bincode;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 use std::error::Error;
-use std::fs::File;
-use std::io::{Read, Write};
+use std::fs::{self, File};
+use std::io::Read;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
 
-use super::{BITCOIN_INDEX, BITCOIN_TESTNET_INDEX, COIN_TYPE_NAMES};
-use crate::encryption;
-use crate::keys::bip32::{ExtendedPrivateKey, ExtendedPublicKey};
+static BACKUP_COUNT: usize = 2; // number of previous versions of the key file
+
+use super::{key_file, BITCOIN_INDEX, BITCOIN_TESTNET_INDEX, COIN_TYPE_NAMES};
+use crate::encryption::{self, KdfParams};
+use crate::keys::address::Address;
+use crate::keys::bip32::{
+    ChildIndex, DerivationPath, DerivedKey, ExtendedPrivateKey, ExtendedPublicKey,
+};
+use crate::keys::bip39;
+use crate::keys::error::{ChildKeyDeriveError, ImportKeyError};
+use crate::utils::file;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MasterPrivateKey {
+    pub seed: Vec<u8>, // empty if the hierarchy was created from a key
     pub private_key: ExtendedPrivateKey,
     pub purpose: Purpose,
 }
@@ -37,6 +47,10 @@ pub struct CoinType {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Account {
     pub index: u32,
+    pub label: String,
+    pub created_at: u64, // UNIX timestamp in seconds
+    pub color: Option<String>,
+    pub archived: bool, // archived accounts are hidden, but their keys are kept
     pub private_key: ExtendedPrivateKey,
     pub external_chain: Change, // used for receiving payments
     pub internal_chain: Change, // used for change
@@ -60,26 +74,178 @@ impl MasterPrivateKey {
     pub fn create_from_key(private_key: ExtendedPrivateKey) -> MasterPrivateKey {
         let purpose = Purpose::create(&private_key);
         MasterPrivateKey {
+            seed: Vec::new(),
             private_key,
             purpose,
         }
     }
 
     /// Creates a private key hierarchy from the given seed.
-    pub fn create_from_seed(seed: [u8; 64]) -> MasterPrivateKey {
-        let private_key = ExtendedPrivateKey::create_master_key(seed, true);
+    pub fn create_from_seed<S: AsRef<[u8]>>(seed: S) -> MasterPrivateKey {
+        let private_key = ExtendedPrivateKey::create_master_key(&seed, true);
         let purpose = Purpose::create(&private_key);
         MasterPrivateKey {
+            seed: seed.as_ref().to_vec(),
             private_key,
             purpose,
         }
     }
 
+    /// Creates a private key hierarchy from a mnemonic or from a master private key
+    /// in the Base58Check format. The passphrase is only used for mnemonics.
+    pub fn create_from_secret(
+        secret: &str,
+        passphrase: &str,
+    ) -> Result<MasterPrivateKey, ImportKeyError> {
+        let secret = secret.trim();
+        if secret.starts_with("xprv") || secret.starts_with("tprv") {
+            let private_key = ExtendedPrivateKey::import_key_from_base58_check(secret)?;
+            if private_key.depth != 0 {
+                return Err(ImportKeyError::new("Not a master key"));
+            }
+            return Ok(MasterPrivateKey::create_from_key(private_key));
+        }
+        let mnemonic: Vec<&str> = secret.split_whitespace().collect();
+        if !bip39::validate_mnemonic(&mnemonic) {
+            return Err(ImportKeyError::new("Invalid mnemonic"));
+        }
+        let seed = bip39::generate_seed(mnemonic, passphrase);
+        Ok(MasterPrivateKey::create_from_seed(seed))
+    }
+
+    /// Returns the key hierarchy of the master key for the specified purpose,
+    /// e.g., 84 for P2WPKH addresses. The hierarchy has no accounts.
+    pub fn for_purpose(&self, purpose_index: u32) -> MasterPrivateKey {
+        MasterPrivateKey {
+            seed: self.seed.clone(),
+            private_key: self.private_key.clone(),
+            purpose: Purpose::create_with_index(&self.private_key, purpose_index),
+        }
+    }
+
     /// Adds an account for the specified coin type.
     pub fn add_account(&mut self, coin_type_index: u32) -> Account {
         self.purpose.add_account(coin_type_index)
     }
 
+    /// Adds the account with the specified index for the specified coin type.
+    /// If the account already exists, it is returned unchanged.
+    pub fn add_account_with_index(&mut self, coin_type_index: u32, account_index: u32) -> Account {
+        self.purpose
+            .add_account_with_index(coin_type_index, account_index)
+    }
+
+    /// Derives the specified account without adding it to the hierarchy.
+    pub fn derive_account(&self, coin_type_index: u32, account_index: u32) -> Account {
+        let coin_type = self.purpose.coin_types.get(&coin_type_index).unwrap();
+        coin_type.derive_account(account_index)
+    }
+
+    /// Sets the label and color of the specified account.
+    pub fn rename_account(
+        &mut self,
+        coin_type_index: u32,
+        account_index: u32,
+        label: String,
+        color: Option<String>,
+    ) {
+        let coin_type = self.purpose.coin_types.get_mut(&coin_type_index).unwrap();
+        let account = coin_type.accounts.get_mut(&account_index).unwrap();
+        account.label = label;
+        account.color = color;
+    }
+
+    /// Archives or restores the specified account.
+    pub fn set_account_archived(
+        &mut self,
+        coin_type_index: u32,
+        account_index: u32,
+        archived: bool,
+    ) {
+        let coin_type = self.purpose.coin_types.get_mut(&coin_type_index).unwrap();
+        let account = coin_type.accounts.get_mut(&account_index).unwrap();
+        account.archived = archived;
+    }
+
+    /// Returns the extended private key of the specified account.
+    pub fn get_account_private_key(
+        &self,
+        coin_type_index: u32,
+        account_index: u32,
+    ) -> Option<ExtendedPrivateKey> {
+        let coin_type = self.purpose.coin_types.get(&coin_type_index)?;
+        let account = coin_type.accounts.get(&account_index)?;
+        Some(account.private_key.clone())
+    }
+
+    /// Derives the key at the specified path from the master key. The path does
+    /// not have to follow BIP-44 and the key is not added to the hierarchy.
+    pub fn derive_key_at_path(
+        &self,
+        path: &DerivationPath,
+    ) -> Result<DerivedKey, ChildKeyDeriveError> {
+        let public_key = self.private_key.derive_path(path)?.derive_public_key();
+        Ok(DerivedKey {
+            path: path.to_string(),
+            public_key: public_key.to_base58_check(),
+            address: public_key.get_address().to_string(),
+        })
+    }
+
+    /// Returns the key pair of the specified address
+    /// or `None` if the address does not belong to the key hierarchy.
+    pub fn find_keypair(&self, address: &Address) -> Option<Keypair> {
+        self.find_keypair_with_path(address)
+            .map(|(keypair, _)| keypair)
+    }
+
+    /// Returns the key pair of the specified address together with its derivation path
+    /// or `None` if the address does not belong to the key hierarchy.
+    /// The accounts of all coin types are derived from the purpose key,
+    /// so the path does not contain a coin type level.
+    pub fn find_keypair_with_path(&self, address: &Address) -> Option<(Keypair, DerivationPath)> {
+        for account in self
+            .purpose
+            .coin_types
+            .values()
+            .flat_map(|coin_type| coin_type.accounts.values())
+        {
+            for chain in [&account.external_chain, &account.internal_chain] {
+                for (key_index, keypair) in &chain.keys {
+                    let found = keypair.public_key.get_addresses().iter().any(|other| {
+                        other.testnet == address.testnet
+                            && other.address_type == address.address_type
+                            && other.hash == address.hash
+                    });
+                    if found {
+                        let path = DerivationPath {
+                            indices: vec![
+                                ChildIndex {
+                                    index: self.purpose.index,
+                                    hardened: true,
+                                },
+                                ChildIndex {
+                                    index: account.index,
+                                    hardened: true,
+                                },
+                                ChildIndex {
+                                    index: chain.index,
+                                    hardened: false,
+                                },
+                                ChildIndex {
+                                    index: *key_index,
+                                    hardened: false,
+                                },
+                            ],
+                        };
+                        return Some((keypair.clone(), path));
+                    }
+                }
+            }
+        }
+        None
+    }
+
     /// Returns all key pairs for the specified account.
     pub fn get_all_keypairs(&self, coin_type_index: u32, account_index: u32) -> Vec<Keypair> {
         let receive_keypairs = self.get_all_receive_keypairs(coin_type_index, account_index);
@@ -133,6 +299,13 @@ impl MasterPrivateKey {
             .new_change_keypair(coin_type_index, account_index, key_index)
     }
 
+    /// Returns the index of the next change keypair for the specified account.
+    pub fn next_change_index(&self, coin_type_index: u32, account_index: u32) -> u32 {
+        let coin_type = self.purpose.coin_types.get(&coin_type_index).unwrap();
+        let account = coin_type.accounts.get(&account_index).unwrap();
+        account.internal_chain.next_index()
+    }
+
     /// Creates and returns a new keypair for receiving payments.
     pub fn new_receive_keypair(
         &mut self,
@@ -178,41 +351,128 @@ impl MasterPrivateKey {
         Some(keypair.private_key)
     }
 
-    /// Saves the key hierarchy to the specified path.
+    /// Saves the key hierarchy to the specified path. The Argon2 parameters of an
+    /// existing file are kept, otherwise the default parameters are used.
     pub fn save<P: AsRef<Path>>(&self, path: P, password: String) -> Result<(), Box<dyn Error>> {
         let path = path.as_ref();
-        let master_key_encoded: Vec<u8> = bincode::serialize(self)?;
-        let master_key_encrypted = encryption::encrypt(master_key_encoded, password);
-        let mut file = File::create(path)?;
-        file.write_all(&master_key_encrypted)?;
+        let params = MasterPrivateKey::read_kdf_params(path).unwrap_or_default();
+        self.save_with_params(path, password, &params)
+    }
+
+    /// Saves the key hierarchy to the specified path with the specified Argon2 parameters.
+    pub fn save_with_params<P: AsRef<Path>>(
+        &self,
+        path: P,
+        password: String,
+        params: &KdfParams,
+    ) -> Result<(), Box<dyn Error>> {
+        let master_key_encoded = key_file::encode(self)?;
+        let master_key_encrypted =
+            encryption::encrypt_with_params(master_key_encoded, password, params);
+        file::write_atomic_with_backups(path, &master_key_encrypted, BACKUP_COUNT)?;
         Ok(())
     }
 
+    /// Removes the previous versions of the key file at the specified path,
+    /// e.g., because they are encrypted with a password that is no longer valid.
+    pub fn discard_backups<P: AsRef<Path>>(path: P) -> Result<(), Box<dyn Error>> {
+        for backup_path in file::backup_paths(path.as_ref(), BACKUP_COUNT) {
+            if backup_path.exists() {
+                fs::remove_file(backup_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the Argon2 parameters of the key file at the specified path.
+    /// Returns `None` if the file does not exist or does not store the parameters.
+    pub fn read_kdf_params<P: AsRef<Path>>(path: P) -> Option<KdfParams> {
+        let bytes = fs::read(path).ok()?;
+        encryption::read_kdf_params(&bytes)
+    }
+
     /// Loads the key hierarchy from the specified path.
     pub fn load<P: AsRef<Path>>(
         path: P,
         password: String,
     ) -> Result<MasterPrivateKey, Box<dyn Error>> {
         let path = path.as_ref();
+        let err = match MasterPrivateKey::load_file(path, password.clone()) {
+            Ok(master_key) => return Ok(master_key),
+            Err(err) => err,
+        };
+        // the file might be corrupted, so the previous versions are tried
+        for backup_path in file::backup_paths(path, BACKUP_COUNT) {
+            if !backup_path.exists() {
+                break;
+            }
+            if let Ok(master_key) = MasterPrivateKey::load_file(&backup_path, password.clone()) {
+                return Ok(master_key);
+            }
+        }
+        Err(err)
+    }
+
+    /// Checks the password of the key file at the specified path
+    /// without decrypting and deserializing the key hierarchy.
+    pub fn check_password<P: AsRef<Path>>(path: P, password: String) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        let err = match MasterPrivateKey::check_password_file(path, password.clone()) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        // the same previous versions are tried as when loading
+        for backup_path in file::backup_paths(path, BACKUP_COUNT) {
+            if !backup_path.exists() {
+                break;
+            }
+            if MasterPrivateKey::check_password_file(&backup_path, password.clone()).is_ok() {
+                return Ok(());
+            }
+        }
+        Err(err)
+    }
+
+    /// Checks the password of the specified file without falling back to backups.
+    fn check_password_file(path: &Path, password: String) -> Result<(), Box<dyn Error>> {
+        let buffer = fs::read(path)?;
+        encryption::check_password(&buffer, password)
+    }
+
+    /// Loads the key hierarchy from the specified file without falling back to backups.
+    fn load_file(path: &Path, password: String) -> Result<MasterPrivateKey, Box<dyn Error>> {
         let mut file = File::open(path)?;
         let mut buffer = vec![];
         file.read_to_end(&mut buffer)?;
-        let buffer_decrypted = encryption::decrypt(buffer, password)?;
-        let master_key: MasterPrivateKey = bincode::deserialize(&buffer_decrypted[..])?;
-        Ok(master_key)
+        let mut buffer_decrypted = encryption::decrypt(buffer, password)?;
+        let master_key = key_file::decode(&buffer_decrypted[..]);
+        buffer_decrypted.zeroize();
+        master_key
+    }
+}
+
+impl Drop for MasterPrivateKey {
+    /// Wipes the seed from memory. The keys are wiped when they are dropped.
+    fn drop(&mut self) {
+        self.seed.zeroize();
     }
 }
 
 impl Purpose {
     /// Creates a new purpose.
     fn create(private_key: &ExtendedPrivateKey) -> Purpose {
-        let child_key = private_key.derive_child_key(44, true).unwrap();
+        Purpose::create_with_index(private_key, 44)
+    }
+
+    /// Creates a new purpose with the specified index.
+    fn create_with_index(private_key: &ExtendedPrivateKey, index: u32) -> Purpose {
+        let child_key = private_key.derive_child_key(index, true).unwrap();
         let coin_types = COIN_TYPE_NAMES
             .iter()
             .map(|(index, _)| (*index, CoinType::create(*index, &child_key)))
             .collect();
         Purpose {
-            index: 44,
+            index,
             private_key: child_key,
             coin_types,
         }
@@ -228,6 +488,16 @@ impl Purpose {
         coin_type.add_account()
     }
 
+    /// Adds the account with the specified index for the specified coin type.
+    fn add_account_with_index(&mut self, coin_type_index: u32, account_index: u32) -> Account {
+        assert!(coin_type_index == BITCOIN_INDEX || coin_type_index == BITCOIN_TESTNET_INDEX);
+        if !self.coin_types.contains_key(&coin_type_index) {
+            self.add_coin_type(coin_type_index);
+        }
+        let coin_type = self.coin_types.get_mut(&coin_type_index).unwrap();
+        coin_type.add_account_with_index(account_index)
+    }
+
     /// Creates and returns a new keypair for return transaction change.
     fn new_change_keypair(
         &mut self,
@@ -286,11 +556,24 @@ impl CoinType {
         } else {
             self.accounts.iter().max_by(|a, b| a.0.cmp(&b.0)).unwrap().0 + 1
         };
-        let child_key = self.private_key.derive_child_key(index, true).unwrap(); // use hardened derivation
-        let account = Account::create(index, &child_key);
+        self.add_account_with_index(index)
+    }
+
+    /// Adds the account with the specified index.
+    fn add_account_with_index(&mut self, index: u32) -> Account {
+        if let Some(account) = self.accounts.get(&index) {
+            return account.clone();
+        }
+        let account = self.derive_account(index);
         self.accounts.insert(index, account.clone());
         account
     }
+
+    /// Derives the account with the specified index.
+    fn derive_account(&self, index: u32) -> Account {
+        let child_key = self.private_key.derive_child_key(index, true).unwrap(); // use hardened derivation
+        Account::create(index, &child_key)
+    }
 }
 
 impl Account {
@@ -300,8 +583,16 @@ impl Account {
         let internal_chain_key = private_key.derive_child_key(1, false).unwrap();
         let external_chain = Change::create(0, external_chain_key);
         let internal_chain = Change::create(1, internal_chain_key);
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
         Account {
             index,
+            label: String::new(),
+            created_at,
+            color: None,
+            archived: false,
             private_key: private_key.clone(),
             external_chain,
             internal_chain,
@@ -334,13 +625,7 @@ impl Change {
     fn new_keypair(&mut self, index: Option<u32>) -> Keypair {
         let index = match index {
             Some(index) => index,
-            None => {
-                if self.keys.len() == 0 {
-                    0
-                } else {
-                    self.keys.iter().max_by(|a, b| a.0.cmp(&b.0)).unwrap().0 + 1
-                }
-            }
+            None => self.next_index(),
         };
         let child_private_key = self.private_key.derive_child_key(index, false).unwrap();
         let child_public_key = child_private_key.derive_public_key();
@@ -348,6 +633,15 @@ impl Change {
         self.keys.insert(index, keypair.clone());
         keypair
     }
+
+    /// Returns the index of the next keypair.
+    fn next_index(&self) -> u32 {
+        if self.keys.len() == 0 {
+            0
+        } else {
+            self.keys.iter().max_by(|a, b| a.0.cmp(&b.0)).unwrap().0 + 1
+        }
+    }
 }
 
 impl Keypair {
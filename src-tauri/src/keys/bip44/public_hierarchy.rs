@@ -2,14 +2,19 @@
 //! in [BIP-44](https://en.bitcoin.it/wiki/BIP_0044).
 use bincode;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use serde_json;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
 
 use super::{
     private_hierarchy::{Account, Change, CoinType, MasterPrivateKey, Purpose},
     BITCOIN_INDEX, BITCOIN_TESTNET_INDEX,
 };
-use crate::keys::address::Address;
-use crate::keys::bip32::ExtendedPublicKey;
+use crate::keys::address::{Address, SimpleAddress};
+use crate::keys::bip32::{ChildIndex, DerivationPath, ExtendedPublicKey};
+use crate::utils::file;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct MasterPublicKey {
@@ -35,6 +40,10 @@ pub struct PublicCoinType {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PublicAccount {
     pub index: u32,
+    pub label: String,
+    pub created_at: u64, // UNIX timestamp in seconds
+    pub color: Option<String>,
+    pub archived: bool,
     pub public_key: ExtendedPublicKey,
     pub external_chain: PublicChange, // used for receiving payments
     pub internal_chain: PublicChange, // used for change
@@ -79,7 +88,19 @@ impl MasterPublicKey {
         self.purpose.new_receive_key(coin_type_index, account_index)
     }
 
-    /// Returns all receive addresses for the specified account.
+    /// Returns the extended public key of the specified account.
+    pub fn get_account_public_key(
+        &self,
+        coin_type_index: u32,
+        account_index: u32,
+    ) -> Option<ExtendedPublicKey> {
+        let coin_type = self.purpose.coin_types.get(&coin_type_index)?;
+        let account = coin_type.accounts.get(&account_index)?;
+        Some(account.public_key.clone())
+    }
+
+    /// Returns all addresses for the specified account.
+    /// Every key has a P2PKH and a P2WPKH address.
     pub fn get_all_addresses(&self, coin_type_index: u32, account_index: u32) -> Vec<Address> {
         let receive_addresses = self.get_all_receive_addresses(coin_type_index, account_index);
         let change_addresses = self.get_all_change_addresses(coin_type_index, account_index);
@@ -101,11 +122,88 @@ impl MasterPublicKey {
             .external_chain
             .keys
             .iter()
-            .map(|(_, key)| key.get_address())
+            .rev()
+            .flat_map(|(_, key)| key.get_addresses())
+            .collect()
+    }
+
+    /// Returns all receive addresses for the specified account
+    /// together with the derivation paths of their keys.
+    pub fn get_all_receive_simple_addresses(
+        &self,
+        coin_type_index: u32,
+        account_index: u32,
+    ) -> Vec<SimpleAddress> {
+        let coin_type = self.purpose.coin_types.get(&coin_type_index).unwrap();
+        let account = coin_type.accounts.get(&account_index).unwrap();
+        account
+            .external_chain
+            .keys
+            .iter()
+            .map(|(index, key)| SimpleAddress {
+                address: key.get_address().to_string(),
+                path: self
+                    .get_derivation_path(account_index, account.external_chain.index, *index)
+                    .to_string(),
+                index: *index,
+                used: None,
+            })
             .rev()
             .collect()
     }
 
+    /// Returns the addresses of all accounts of the coin type, mapped to the index
+    /// of their account and the derivation path of their key.
+    pub fn get_address_paths(
+        &self,
+        coin_type_index: u32,
+    ) -> HashMap<String, (u32, DerivationPath)> {
+        let coin_type = self.purpose.coin_types.get(&coin_type_index).unwrap();
+        let mut paths = HashMap::new();
+        for (account_index, account) in coin_type.accounts.iter() {
+            for chain in [&account.external_chain, &account.internal_chain] {
+                for (index, key) in chain.keys.iter() {
+                    let path = self.get_derivation_path(*account_index, chain.index, *index);
+                    for address in key.get_addresses() {
+                        paths.insert(address.to_string(), (*account_index, path.clone()));
+                    }
+                }
+            }
+        }
+        paths
+    }
+
+    /// Returns the derivation path of the specified key.
+    /// The accounts of all coin types are derived from the purpose key,
+    /// so the path does not contain a coin type level.
+    pub fn get_derivation_path(
+        &self,
+        account_index: u32,
+        change_index: u32,
+        key_index: u32,
+    ) -> DerivationPath {
+        DerivationPath {
+            indices: vec![
+                ChildIndex {
+                    index: self.purpose.index,
+                    hardened: true,
+                },
+                ChildIndex {
+                    index: account_index,
+                    hardened: true,
+                },
+                ChildIndex {
+                    index: change_index,
+                    hardened: false,
+                },
+                ChildIndex {
+                    index: key_index,
+                    hardened: false,
+                },
+            ],
+        }
+    }
+
     /// Returns all change addresses for the specified account.
     pub fn get_all_change_addresses(
         &self,
@@ -118,8 +216,8 @@ impl MasterPublicKey {
             .internal_chain
             .keys
             .iter()
-            .map(|(_, key)| key.get_address())
             .rev()
+            .flat_map(|(_, key)| key.get_addresses())
             .collect()
     }
 
@@ -133,8 +231,26 @@ impl MasterPublicKey {
         bincode::deserialize(bytes).unwrap()
     }
 
-    pub fn jsonify(&self) -> String {
-        format!("{{\"purpose\": {}}}", self.purpose.jsonify())
+    /// Saves the key hierarchy to the specified path. The hierarchy contains
+    /// no private keys, so it is not encrypted.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        file::write_atomic(path, &self.serialize())?;
+        Ok(())
+    }
+
+    /// Loads the key hierarchy from the file at the specified path.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<MasterPublicKey, Box<dyn Error>> {
+        let bytes = fs::read(path)?;
+        Ok(bincode::deserialize(&bytes)?)
+    }
+
+    /// Returns the key hierarchy in JSON format.
+    /// Archived accounts are only included if `include_archived` is set.
+    pub fn jsonify(&self, include_archived: bool) -> String {
+        format!(
+            "{{\"purpose\": {}}}",
+            self.purpose.jsonify(include_archived)
+        )
     }
 }
 
@@ -180,11 +296,11 @@ impl PublicPurpose {
         coin_type.accounts.insert(account.index, public_account);
     }
 
-    fn jsonify(&self) -> String {
+    fn jsonify(&self, include_archived: bool) -> String {
         let coin_types: String = self
             .coin_types
             .iter()
-            .map(|(_, coin_type)| coin_type.jsonify())
+            .map(|(_, coin_type)| coin_type.jsonify(include_archived))
             .collect::<Vec<String>>()
             .join(",");
         format!(
@@ -209,10 +325,11 @@ impl PublicCoinType {
         }
     }
 
-    fn jsonify(&self) -> String {
+    fn jsonify(&self, include_archived: bool) -> String {
         let accounts: String = self
             .accounts
             .iter()
+            .filter(|(_, account)| include_archived || !account.archived)
             .map(|(_, account)| account.jsonify())
             .collect::<Vec<String>>()
             .join(",");
@@ -228,6 +345,10 @@ impl PublicAccount {
     fn create(account: &Account) -> PublicAccount {
         PublicAccount {
             index: account.index,
+            label: account.label.clone(),
+            created_at: account.created_at,
+            color: account.color.clone(),
+            archived: account.archived,
             public_key: account.private_key.derive_public_key(),
             external_chain: PublicChange::create(&account.external_chain),
             internal_chain: PublicChange::create(&account.internal_chain),
@@ -245,7 +366,15 @@ impl PublicAccount {
     }
 
     fn jsonify(&self) -> String {
-        format!("{{\"index\": {}}}", self.index)
+        // the label and color are user input and need to be escaped
+        format!(
+            "{{\"index\": {}, \"label\": {}, \"created_at\": {}, \"color\": {}, \"archived\": {}}}",
+            self.index,
+            serde_json::to_string(&self.label).unwrap(),
+            self.created_at,
+            serde_json::to_string(&self.color).unwrap(),
+            self.archived
+        )
     }
 }
 
@@ -0,0 +1,79 @@
+//! Implements [BIP-85](https://github.com/bitcoin/bips/blob/master/bip-0085.mediawiki).
+//! This BIP describes how to derive deterministic entropy from a master key,
+//! which is used to create child mnemonics (e.g. for a separate hot wallet).
+use bitcoin_hashes::{hmac, sha512, Hash, HashEngine};
+
+use crate::keys::bip32::ExtendedPrivateKey;
+use crate::keys::bip39;
+use crate::keys::error::ChildKeyDeriveError;
+
+static BIP85_PURPOSE: u32 = 83696968;
+static BIP39_APPLICATION: u32 = 39;
+static ENGLISH_LANGUAGE: u32 = 0;
+
+/// Derives 64 bytes of entropy from the key at the given path.
+/// All path elements use hardened derivation.
+pub fn derive_entropy(
+    master_key: &ExtendedPrivateKey,
+    path: &[u32],
+) -> Result<[u8; 64], ChildKeyDeriveError> {
+    let mut key = master_key.derive_child_key(BIP85_PURPOSE, true)?;
+    for index in path {
+        key = key.derive_child_key(*index, true)?;
+    }
+    let mut engine = hmac::HmacEngine::<sha512::Hash>::new(b"bip-entropy-from-k");
+    engine.input(&key.key_data);
+    Ok(hmac::Hmac::from_engine(engine).into_inner())
+}
+
+/// Derives the child mnemonic with the specified number of words and index.
+pub fn derive_mnemonic(
+    master_key: &ExtendedPrivateKey,
+    word_count: usize,
+    index: u32,
+) -> Result<Vec<&'static str>, ChildKeyDeriveError> {
+    let entropy_length = match word_count {
+        12 => 16,
+        18 => 24,
+        24 => 32,
+        _ => return Err(ChildKeyDeriveError::new("Invalid word count")),
+    };
+    let path = [
+        BIP39_APPLICATION,
+        ENGLISH_LANGUAGE,
+        word_count as u32,
+        index,
+    ];
+    let entropy = derive_entropy(master_key, &path)?;
+    Ok(bip39::generate_mnemonic(
+        entropy_length * 8,
+        Some(&entropy[..entropy_length]),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{derive_entropy, derive_mnemonic};
+    use crate::keys::bip32::ExtendedPrivateKey;
+    use crate::utils::hex;
+
+    static MASTER_KEY: &'static str = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+
+    #[test]
+    fn test_derive_entropy() {
+        let master_key = ExtendedPrivateKey::import_key_from_base58_check(MASTER_KEY).unwrap();
+        let entropy = derive_entropy(&master_key, &[0, 0]).unwrap();
+        let target = "efecfbccffea313214232d29e71563d941229afb4338c21f9517c41aaa0d16f0\
+                      0b83d2a09ef747e7a64e8e2bd5a14869e693da66ce94ac2da570ab7ee48618f7";
+        assert_eq!(hex::bytes_to_hex(&entropy), target);
+    }
+
+    #[test]
+    fn test_derive_mnemonic() {
+        let master_key = ExtendedPrivateKey::import_key_from_base58_check(MASTER_KEY).unwrap();
+        let mnemonic = derive_mnemonic(&master_key, 12, 0).unwrap();
+        let target = "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose";
+        assert_eq!(mnemonic.join(" "), target);
+        assert!(derive_mnemonic(&master_key, 13, 0).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 //! Defines errors for the key module.
+use std::error::Error as StdError;
 use std::fmt;
 use std::fmt::Formatter;
 
@@ -67,7 +68,34 @@ impl fmt::Debug for ChildKeyDeriveError {
     }
 }
 
-/// This error occurs when parsing a Base58Check address fails.
+impl StdError for ChildKeyDeriveError {}
+
+/// This error occurs when parsing a derivation path fails.
+pub struct ParseDerivationPathError {
+    message: String,
+}
+
+impl ParseDerivationPathError {
+    pub fn new(message: &str) -> ParseDerivationPathError {
+        ParseDerivationPathError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseDerivationPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for ParseDerivationPathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// This error occurs when parsing an address fails.
 pub struct ParseAddressError {
     message: String,
 }
@@ -91,3 +119,108 @@ impl fmt::Debug for ParseAddressError {
         write!(f, "{}", self.message)
     }
 }
+
+impl StdError for ParseAddressError {}
+
+/// This error occurs when splitting or combining SLIP-39 shares fails.
+pub struct Slip39Error {
+    message: String,
+}
+
+impl Slip39Error {
+    pub fn new(message: &str) -> Slip39Error {
+        Slip39Error {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Slip39Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for Slip39Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// This error occurs when a decrypted key file has an unknown layout.
+pub struct KeyFileError {
+    message: String,
+}
+
+impl KeyFileError {
+    pub fn new(message: &str) -> KeyFileError {
+        KeyFileError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for KeyFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for KeyFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for KeyFileError {}
+
+/// This error occurs when a message signature is malformed.
+pub struct InvalidSignatureError {
+    message: String,
+}
+
+impl InvalidSignatureError {
+    pub fn new(message: &str) -> InvalidSignatureError {
+        InvalidSignatureError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for InvalidSignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for InvalidSignatureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// This error occurs when a multisig account or one of its cosigners is invalid.
+/// The message is the error code that is returned to the frontend.
+pub struct MultisigError {
+    message: String,
+}
+
+impl MultisigError {
+    pub fn new(message: &str) -> MultisigError {
+        MultisigError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for MultisigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for MultisigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
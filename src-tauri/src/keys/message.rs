@@ -0,0 +1,440 @@
+//! Implements the signing of messages with the keys of the wallet, so that the
+//! ownership of an address can be proven, and the verification of such signatures.
+//! Signatures for P2PKH addresses are encoded as described in
+//! [BIP-137](https://github.com/bitcoin/bips/blob/master/bip-0137.mediawiki).
+//! Signatures for SegWit and Taproot addresses are simple proofs as described in
+//! [BIP-322](https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki),
+//! i.e., the witness of a virtual transaction that spends from the address.
+use bitcoin_hashes::{ripemd160, sha256, sha256d, Hash};
+use rand::RngCore;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId, Signature};
+use secp256k1::{schnorr, KeyPair, Message, Secp256k1, SecretKey, XOnlyPublicKey};
+
+use crate::keys::address::{Address, AddressType};
+use crate::keys::bip32::ExtendedPrivateKey;
+use crate::keys::error::InvalidSignatureError;
+use crate::keys::taproot::{tagged_hash, tweak_keypair, TAP_SIGHASH_TAG};
+use crate::utils::{base64, varint};
+
+static MESSAGE_PREFIX: &[u8] = b"\x18Bitcoin Signed Message:\n";
+static HEADER_P2PKH_UNCOMPRESSED: u8 = 27;
+static HEADER_P2PKH_COMPRESSED: u8 = 31; // 27 + 4 for compressed keys
+static HEADER_P2PKH_MAX: u8 = 34; // higher headers are used for SegWit addresses
+static BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+static SIGHASH_ALL: u8 = 0x01;
+static SIGHASH_DEFAULT: u8 = 0x00; // Taproot only
+static OP_RETURN: u8 = 0x6a;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WitnessScriptType {
+    P2WPKH,
+    P2TR,
+}
+
+/// Returns the hash of the message that is signed.
+pub fn message_hash(message: &str) -> [u8; 32] {
+    let mut data = MESSAGE_PREFIX.to_vec();
+    data.extend(varint::encode(message.len() as u64));
+    data.extend(message.as_bytes());
+    sha256d::Hash::hash(&data).into_inner()
+}
+
+/// Signs the message with the private key of a P2PKH address
+/// and returns the signature in Base64.
+pub fn sign_message(private_key: &ExtendedPrivateKey, message: &str) -> String {
+    let secp = Secp256k1::signing_only();
+    let secret_key = SecretKey::from_slice(&private_key.key_data).unwrap();
+    let msg = Message::from_slice(&message_hash(message)).unwrap();
+    let (recovery_id, sig) = secp
+        .sign_ecdsa_recoverable(&msg, &secret_key)
+        .serialize_compact();
+    let mut sig_bytes = Vec::with_capacity(65);
+    sig_bytes.push(HEADER_P2PKH_COMPRESSED + recovery_id.to_i32() as u8);
+    sig_bytes.extend(sig);
+    base64::encode(&sig_bytes)
+}
+
+/// Returns true if the signature of the message was created with the key of the
+/// specified P2PKH address. Returns an error if the signature is malformed.
+pub fn verify_message(
+    address: &Address,
+    message: &str,
+    signature: &str,
+) -> Result<bool, InvalidSignatureError> {
+    let sig_bytes = match base64::decode(signature.trim()) {
+        Some(sig_bytes) if sig_bytes.len() == 65 => sig_bytes,
+        _ => return Err(InvalidSignatureError::new("Invalid encoding")),
+    };
+    let header = sig_bytes[0];
+    if header < HEADER_P2PKH_UNCOMPRESSED || header > HEADER_P2PKH_MAX {
+        return Err(InvalidSignatureError::new("Unsupported header"));
+    }
+    let compressed = header >= HEADER_P2PKH_COMPRESSED;
+    let recovery_id = match RecoveryId::from_i32(((header - HEADER_P2PKH_UNCOMPRESSED) % 4) as i32)
+    {
+        Ok(recovery_id) => recovery_id,
+        Err(_) => return Err(InvalidSignatureError::new("Invalid recovery ID")),
+    };
+    let sig = match RecoverableSignature::from_compact(&sig_bytes[1..], recovery_id) {
+        Ok(sig) => sig,
+        Err(_) => return Err(InvalidSignatureError::new("Invalid signature")),
+    };
+    let secp = Secp256k1::verification_only();
+    let msg = Message::from_slice(&message_hash(message)).unwrap();
+    let public_key = match secp.recover_ecdsa(&msg, &sig) {
+        Ok(public_key) => public_key,
+        // no public key matches the signature
+        Err(_) => return Ok(false),
+    };
+    let public_key_bytes = if compressed {
+        public_key.serialize().to_vec()
+    } else {
+        public_key.serialize_uncompressed().to_vec()
+    };
+    let hash160 = ripemd160::Hash::hash(&sha256::Hash::hash(&public_key_bytes));
+    Ok(address.address_type == AddressType::P2PKH && hash160.to_vec() == address.hash)
+}
+
+/// Returns the BIP-322 hash of the message.
+pub fn bip322_message_hash(message: &str) -> [u8; 32] {
+    tagged_hash(BIP322_TAG, message.as_bytes())
+}
+
+/// Returns the scriptPubKey of the specified witness script type for the private key.
+/// For P2TR, the internal key is tweaked without a script tree.
+pub fn witness_script_pubkey(
+    private_key: &ExtendedPrivateKey,
+    script_type: WitnessScriptType,
+) -> Vec<u8> {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&private_key.key_data).unwrap();
+    match script_type {
+        WitnessScriptType::P2WPKH => {
+            let public_key = secret_key.public_key(&secp).serialize();
+            let mut script_pubkey = vec![0x00, 0x14];
+            script_pubkey.extend(hash160(&public_key));
+            script_pubkey
+        }
+        WitnessScriptType::P2TR => {
+            let keypair = tweak_keypair(&KeyPair::from_secret_key(&secp, &secret_key));
+            let (output_key, _) = XOnlyPublicKey::from_keypair(&keypair);
+            let mut script_pubkey = vec![0x51, 0x20];
+            script_pubkey.extend(output_key.serialize());
+            script_pubkey
+        }
+    }
+}
+
+/// Signs the message with the private key of a SegWit or Taproot address
+/// and returns the BIP-322 simple signature in Base64.
+pub fn sign_message_bip322(
+    private_key: &ExtendedPrivateKey,
+    script_type: WitnessScriptType,
+    message: &str,
+) -> String {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&private_key.key_data).unwrap();
+    let script_pubkey = witness_script_pubkey(private_key, script_type);
+    let to_spend_txid = to_spend_txid(&script_pubkey, message);
+    let witness = match script_type {
+        WitnessScriptType::P2WPKH => {
+            let public_key = secret_key.public_key(&secp).serialize();
+            let sighash = p2wpkh_sighash(&to_spend_txid, &hash160(&public_key));
+            let msg = Message::from_slice(&sighash).unwrap();
+            let mut sig = secp.sign_ecdsa(&msg, &secret_key).serialize_der().to_vec();
+            sig.push(SIGHASH_ALL);
+            vec![sig, public_key.to_vec()]
+        }
+        WitnessScriptType::P2TR => {
+            let keypair = tweak_keypair(&KeyPair::from_secret_key(&secp, &secret_key));
+            let sighash = p2tr_sighash(&to_spend_txid, &script_pubkey, SIGHASH_DEFAULT);
+            let msg = Message::from_slice(&sighash).unwrap();
+            let mut aux_rand = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut aux_rand);
+            let sig = secp.sign_schnorr_with_aux_rand(&msg, &keypair, &aux_rand);
+            vec![sig.as_ref().to_vec()]
+        }
+    };
+    base64::encode(&serialize_witness(&witness))
+}
+
+/// Returns true if the BIP-322 simple signature proves that the message was signed
+/// with the key of the specified P2WPKH or P2TR scriptPubKey.
+/// Returns an error if the signature is malformed or the script is not supported.
+pub fn verify_message_bip322(
+    script_pubkey: &[u8],
+    message: &str,
+    signature: &str,
+) -> Result<bool, InvalidSignatureError> {
+    let witness = match base64::decode(signature.trim()) {
+        Some(witness_bytes) => deserialize_witness(&witness_bytes)?,
+        None => return Err(InvalidSignatureError::new("Invalid encoding")),
+    };
+    let to_spend_txid = to_spend_txid(script_pubkey, message);
+    let secp = Secp256k1::verification_only();
+    if script_pubkey.len() == 22 && script_pubkey[..2] == [0x00, 0x14] {
+        if witness.len() != 2 {
+            return Err(InvalidSignatureError::new("Invalid witness"));
+        }
+        let (sig, public_key) = (&witness[0], &witness[1]);
+        if sig.last() != Some(&SIGHASH_ALL) {
+            return Err(InvalidSignatureError::new("Unsupported sighash type"));
+        }
+        let sig = match Signature::from_der(&sig[..sig.len() - 1]) {
+            Ok(sig) => sig,
+            Err(_) => return Err(InvalidSignatureError::new("Invalid signature")),
+        };
+        let public_key = match secp256k1::PublicKey::from_slice(public_key) {
+            Ok(public_key) => public_key,
+            Err(_) => return Err(InvalidSignatureError::new("Invalid public key")),
+        };
+        let public_key_hash = hash160(&public_key.serialize());
+        if public_key_hash[..] != script_pubkey[2..] {
+            return Ok(false);
+        }
+        let sighash = p2wpkh_sighash(&to_spend_txid, &public_key_hash);
+        let msg = Message::from_slice(&sighash).unwrap();
+        Ok(secp.verify_ecdsa(&msg, &sig, &public_key).is_ok())
+    } else if script_pubkey.len() == 34 && script_pubkey[..2] == [0x51, 0x20] {
+        if witness.len() != 1 {
+            return Err(InvalidSignatureError::new("Invalid witness"));
+        }
+        let sig = &witness[0];
+        let sighash_type = match sig.len() {
+            64 => SIGHASH_DEFAULT,
+            65 if sig[64] == SIGHASH_ALL => SIGHASH_ALL,
+            _ => return Err(InvalidSignatureError::new("Invalid signature")),
+        };
+        let sig = match schnorr::Signature::from_slice(&sig[..64]) {
+            Ok(sig) => sig,
+            Err(_) => return Err(InvalidSignatureError::new("Invalid signature")),
+        };
+        let output_key = match XOnlyPublicKey::from_slice(&script_pubkey[2..]) {
+            Ok(output_key) => output_key,
+            Err(_) => return Ok(false),
+        };
+        let sighash = p2tr_sighash(&to_spend_txid, script_pubkey, sighash_type);
+        let msg = Message::from_slice(&sighash).unwrap();
+        Ok(secp.verify_schnorr(&sig, &msg, &output_key).is_ok())
+    } else {
+        Err(InvalidSignatureError::new("Unsupported script"))
+    }
+}
+
+/// Computes RIPEMD160(SHA256(data)).
+fn hash160(data: &[u8]) -> [u8; 20] {
+    ripemd160::Hash::hash(&sha256::Hash::hash(data)).into_inner()
+}
+
+/// Returns the ID of the virtual transaction `to_spend` that commits to the message
+/// and pays to the specified scriptPubKey.
+fn to_spend_txid(script_pubkey: &[u8], message: &str) -> [u8; 32] {
+    let mut tx = Vec::new();
+    tx.extend(0u32.to_le_bytes()); // version
+    tx.push(0x01); // input count
+    tx.extend([0u8; 32]);
+    tx.extend(0xffffffffu32.to_le_bytes());
+    let mut script_sig = vec![0x00, 0x20]; // OP_0 PUSH32
+    script_sig.extend(bip322_message_hash(message));
+    tx.extend(varint::encode(script_sig.len() as u64));
+    tx.extend(script_sig);
+    tx.extend(0u32.to_le_bytes()); // sequence
+    tx.push(0x01); // output count
+    tx.extend(0u64.to_le_bytes());
+    tx.extend(varint::encode(script_pubkey.len() as u64));
+    tx.extend(script_pubkey);
+    tx.extend(0u32.to_le_bytes()); // locktime
+    sha256d::Hash::hash(&tx).into_inner()
+}
+
+/// Returns the serialized OP_RETURN output of the virtual transaction `to_sign`.
+fn to_sign_outputs() -> Vec<u8> {
+    let mut outputs = 0u64.to_le_bytes().to_vec();
+    outputs.push(0x01);
+    outputs.push(OP_RETURN);
+    outputs
+}
+
+/// Computes the BIP-143 signature hash of the input of the virtual transaction `to_sign`.
+fn p2wpkh_sighash(to_spend_txid: &[u8; 32], public_key_hash: &[u8; 20]) -> [u8; 32] {
+    let mut outpoint = to_spend_txid.to_vec();
+    outpoint.extend(0u32.to_le_bytes());
+    let sequence = 0u32.to_le_bytes();
+    let mut preimage = Vec::new();
+    preimage.extend(0u32.to_le_bytes()); // version
+    preimage.extend(sha256d::Hash::hash(&outpoint).into_inner());
+    preimage.extend(sha256d::Hash::hash(&sequence).into_inner());
+    preimage.extend(&outpoint);
+    preimage.extend([0x19, 0x76, 0xa9, 0x14]); // script code
+    preimage.extend(public_key_hash);
+    preimage.extend([0x88, 0xac]);
+    preimage.extend(0u64.to_le_bytes()); // amount
+    preimage.extend(sequence);
+    preimage.extend(sha256d::Hash::hash(&to_sign_outputs()).into_inner());
+    preimage.extend(0u32.to_le_bytes()); // locktime
+    preimage.extend((SIGHASH_ALL as u32).to_le_bytes());
+    sha256d::Hash::hash(&preimage).into_inner()
+}
+
+/// Computes the BIP-341 key path signature hash of the input of the virtual transaction `to_sign`.
+fn p2tr_sighash(to_spend_txid: &[u8; 32], script_pubkey: &[u8], sighash_type: u8) -> [u8; 32] {
+    let mut outpoint = to_spend_txid.to_vec();
+    outpoint.extend(0u32.to_le_bytes());
+    let mut script_pubkeys = varint::encode(script_pubkey.len() as u64);
+    script_pubkeys.extend(script_pubkey);
+    let mut preimage = vec![0x00]; // epoch
+    preimage.push(sighash_type);
+    preimage.extend(0u32.to_le_bytes()); // version
+    preimage.extend(0u32.to_le_bytes()); // locktime
+    preimage.extend(sha256::Hash::hash(&outpoint).into_inner());
+    preimage.extend(sha256::Hash::hash(&0u64.to_le_bytes()).into_inner()); // amounts
+    preimage.extend(sha256::Hash::hash(&script_pubkeys).into_inner());
+    preimage.extend(sha256::Hash::hash(&0u32.to_le_bytes()).into_inner()); // sequences
+    preimage.extend(sha256::Hash::hash(&to_sign_outputs()).into_inner());
+    preimage.push(0x00); // key path spend without annex
+    preimage.extend(0u32.to_le_bytes()); // input index
+    tagged_hash(TAP_SIGHASH_TAG, &preimage)
+}
+
+/// Serializes the witness stack.
+fn serialize_witness(witness: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = varint::encode(witness.len() as u64);
+    for item in witness {
+        bytes.extend(varint::encode(item.len() as u64));
+        bytes.extend(item);
+    }
+    bytes
+}
+
+/// Deserializes the witness stack.
+fn deserialize_witness(bytes: &[u8]) -> Result<Vec<Vec<u8>>, InvalidSignatureError> {
+    let invalid = || InvalidSignatureError::new("Invalid witness");
+    let (count, prefix_length) = varint::decode(bytes).ok_or_else(invalid)?;
+    let mut rest = &bytes[prefix_length..];
+    let mut witness = Vec::new();
+    for _ in 0..count {
+        let (length, prefix_length) = varint::decode(rest).ok_or_else(invalid)?;
+        let tail = &rest[prefix_length..];
+        if length > tail.len() as u64 {
+            return Err(invalid());
+        }
+        witness.push(tail[..length as usize].to_vec());
+        rest = &tail[length as usize..];
+    }
+    if !rest.is_empty() {
+        return Err(invalid());
+    }
+    Ok(witness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bip322_message_hash, sign_message, sign_message_bip322, verify_message,
+        verify_message_bip322, witness_script_pubkey, WitnessScriptType,
+    };
+    use crate::keys::address::Address;
+    use crate::keys::bip32::ExtendedPrivateKey;
+    use crate::utils::{base58, hex};
+    use std::str::FromStr;
+
+    static BIP322_WIF: &'static str = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k";
+
+    fn import_wif(wif: &str) -> ExtendedPrivateKey {
+        let wif_bytes = base58::decode(wif).unwrap();
+        ExtendedPrivateKey {
+            testnet: false,
+            depth: 0x00,
+            fingerprint: [0; 4],
+            child_number: [0; 4],
+            chain_code: [0; 32],
+            key_data: wif_bytes[1..33].try_into().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_sign_message() {
+        // test vector from https://github.com/bitcoinjs/bitcoinjs-message
+        let wif = "L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1";
+        let wif_bytes = base58::decode(wif).unwrap();
+        let private_key = ExtendedPrivateKey {
+            testnet: false,
+            depth: 0x00,
+            fingerprint: [0; 4],
+            child_number: [0; 4],
+            chain_code: [0; 32],
+            key_data: wif_bytes[1..33].try_into().unwrap(),
+        };
+        assert_eq!(
+            private_key.derive_public_key().get_address().to_string(),
+            "1F3sAm6ZtwLAUnj7d38pGFxtP3RVEvtsbV"
+        );
+        let signature = sign_message(&private_key, "This is an example of a signed message.");
+        assert_eq!(
+            signature,
+            "H9L5yLFjti0QTHhPyFrZCT1V/MMnBtXKmoiKDZ78NDBjERki6ZTQZdSMCtkgoNmp17By9ItJr8o7ChX0XxY91nk="
+        );
+    }
+
+    #[test]
+    fn test_verify_message() {
+        let address = Address::from_str("1F3sAm6ZtwLAUnj7d38pGFxtP3RVEvtsbV").unwrap();
+        let message = "This is an example of a signed message.";
+        let signature = "H9L5yLFjti0QTHhPyFrZCT1V/MMnBtXKmoiKDZ78NDBjERki6ZTQZdSMCtkgoNmp17By9ItJr8o7ChX0XxY91nk=";
+        assert!(verify_message(&address, message, signature).unwrap());
+        assert!(!verify_message(&address, "This is another message.", signature).unwrap());
+        let other_address = Address::from_str("mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1").unwrap();
+        assert!(!verify_message(&other_address, message, signature).unwrap());
+        assert!(verify_message(&address, message, "H9L5yLFjti0QTHhPyFrZCT1V").is_err());
+    }
+
+    #[test]
+    fn test_bip322_message_hash() {
+        // test vectors from BIP-322
+        assert_eq!(
+            hex::bytes_to_hex(&bip322_message_hash("")),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            hex::bytes_to_hex(&bip322_message_hash("Hello World")),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+    }
+
+    #[test]
+    fn test_bip322_p2wpkh() {
+        // test vectors from BIP-322
+        let private_key = import_wif(BIP322_WIF);
+        let script_pubkey = witness_script_pubkey(&private_key, WitnessScriptType::P2WPKH);
+        assert_eq!(
+            hex::bytes_to_hex(&script_pubkey),
+            "00142b05d564e6a7a33c087f16e0f730d1440123799d"
+        );
+        let signature = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        assert!(verify_message_bip322(&script_pubkey, "Hello World", signature).unwrap());
+        assert!(!verify_message_bip322(&script_pubkey, "Hello World!", signature).unwrap());
+
+        let signature = sign_message_bip322(&private_key, WitnessScriptType::P2WPKH, "");
+        assert!(verify_message_bip322(&script_pubkey, "", &signature).unwrap());
+        assert!(verify_message_bip322(&script_pubkey, "", "AkcwRAIg").is_err());
+    }
+
+    #[test]
+    fn test_bip322_p2tr() {
+        // test vectors from BIP-322
+        let private_key = import_wif(BIP322_WIF);
+        let script_pubkey = witness_script_pubkey(&private_key, WitnessScriptType::P2TR);
+        assert_eq!(
+            hex::bytes_to_hex(&script_pubkey),
+            "51200b34f2cc6f60d54e3fdc2d1dd053fcc393bd2db9acc8de4a7c3cc28a83d4d8e9"
+        );
+        let signature = "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==";
+        assert!(verify_message_bip322(&script_pubkey, "Hello World", signature).unwrap());
+        assert!(!verify_message_bip322(&script_pubkey, "Hello", signature).unwrap());
+
+        let signature = sign_message_bip322(&private_key, WitnessScriptType::P2TR, "Hello");
+        assert!(verify_message_bip322(&script_pubkey, "Hello", &signature).unwrap());
+        let p2wpkh_script_pubkey = witness_script_pubkey(&private_key, WitnessScriptType::P2WPKH);
+        assert!(verify_message_bip322(&p2wpkh_script_pubkey, "Hello", &signature).is_err());
+    }
+}
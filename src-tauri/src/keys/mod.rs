@@ -2,5 +2,10 @@ pub mod address;
 pub mod bip32;
 pub mod bip39;
 pub mod bip44;
+pub mod bip85;
 pub mod error;
+pub mod message;
+pub mod network;
 mod pbkdf2;
+pub mod slip39;
+pub mod taproot;
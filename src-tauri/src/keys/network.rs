@@ -0,0 +1,67 @@
+//! Defines the test networks that the testnet coin type can refer to. The test networks
+//! share the version bytes of extended keys (`tprv`/`tpub`), the coin type and the
+//! Base58Check prefixes of addresses. Signet also shares the human-readable part of
+//! Bech32 addresses with testnet, whereas regtest has its own. The test network is
+//! selected in the network settings and applies to all wallets.
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+static HRP_TESTNET: &'static str = "tb";
+static HRP_REGTEST: &'static str = "bcrt";
+static TESTNET_GENESIS_HASH: &'static str =
+    "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943";
+static SIGNET_GENESIS_HASH: &'static str =
+    "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6";
+static REGTEST_GENESIS_HASH: &'static str =
+    "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TestNetwork {
+    #[default]
+    Testnet,
+    Signet,
+    Regtest, // a local network, e.g., of `bitcoind -regtest`
+}
+
+impl TestNetwork {
+    /// Returns the human-readable part of Bech32 addresses.
+    pub fn hrp(&self) -> &'static str {
+        match self {
+            TestNetwork::Testnet | TestNetwork::Signet => HRP_TESTNET,
+            TestNetwork::Regtest => HRP_REGTEST,
+        }
+    }
+
+    /// Returns the hash of the genesis block, which identifies the network.
+    pub fn genesis_hash(&self) -> &'static str {
+        match self {
+            TestNetwork::Testnet => TESTNET_GENESIS_HASH,
+            TestNetwork::Signet => SIGNET_GENESIS_HASH,
+            TestNetwork::Regtest => REGTEST_GENESIS_HASH,
+        }
+    }
+
+    /// Returns the test network of the human-readable part of a Bech32 address.
+    /// The human-readable part of testnet and signet is the same, so testnet is returned.
+    pub fn from_hrp(hrp: &str) -> Option<TestNetwork> {
+        [TestNetwork::Testnet, TestNetwork::Regtest]
+            .into_iter()
+            .find(|network| network.hrp() == hrp)
+    }
+}
+
+lazy_static! {
+    static ref SELECTED_TEST_NETWORK: RwLock<TestNetwork> = RwLock::new(TestNetwork::default());
+}
+
+/// Returns the test network that the testnet coin type refers to.
+pub fn selected_test_network() -> TestNetwork {
+    *SELECTED_TEST_NETWORK.read().unwrap()
+}
+
+/// Selects the test network that the testnet coin type refers to.
+pub fn select_test_network(network: TestNetwork) {
+    *SELECTED_TEST_NETWORK.write().unwrap() = network;
+}
@@ -0,0 +1,599 @@
+//! Implements [SLIP-39](https://github.com/satoshilabs/slips/blob/master/slip-0039.md).
+//! This SLIP describes Shamir's Secret-Sharing for mnemonic codes, which
+//! splits a master secret into shares such that any `threshold` of them
+//! are sufficient to reconstruct it.
+use bitcoin_hashes::{hmac, sha256, Hash, HashEngine};
+use bitvec::prelude::*;
+use lazy_static::lazy_static;
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use std::collections::BTreeMap;
+
+use crate::keys::error::Slip39Error;
+
+static RADIX_BITS: usize = 10;
+static ID_LENGTH_BITS: usize = 15;
+static ITERATION_EXP_LENGTH_BITS: usize = 4;
+static METADATA_LENGTH_WORDS: usize = 7; // id, exponent, group and member fields, checksum
+static CHECKSUM_LENGTH_WORDS: usize = 3;
+static MIN_STRENGTH_BITS: usize = 128;
+static MAX_SHARE_COUNT: u8 = 16;
+static DIGEST_LENGTH_BYTES: usize = 4;
+static DIGEST_INDEX: u8 = 254;
+static SECRET_INDEX: u8 = 255;
+static BASE_ITERATION_COUNT: u32 = 10000;
+static ROUND_COUNT: u8 = 4;
+static DEFAULT_ITERATION_EXPONENT: u8 = 1;
+static CUSTOMIZATION_STRING: &'static str = "shamir";
+static CUSTOMIZATION_STRING_EXTENDABLE: &'static str = "shamir_extendable";
+static RS1024_GENERATOR: [u32; 10] = [
+    0xE0E040, 0x1C1C080, 0x3838100, 0x7070200, 0xE0E0009, 0x1C0C2412, 0x38086C24, 0x3090FC48,
+    0x21B1F890, 0x3F3F120,
+];
+
+lazy_static! {
+    static ref WORD_LIST: Vec<&'static str> = {
+        const WORDS: &str = include_str!("slip39_word_list.txt");
+        let mut word_list: Vec<&str> = WORDS.split("\n").collect();
+        word_list.remove(word_list.len() - 1); // remove empty string
+        word_list
+    };
+    // exponent and logarithm tables for GF(256) with the Rijndael polynomial
+    static ref GF256_TABLES: ([u8; 255], [u8; 256]) = {
+        let mut exp = [0u8; 255];
+        let mut log = [0u8; 256];
+        let mut poly: u16 = 1;
+        for (i, exp) in exp.iter_mut().enumerate() {
+            *exp = poly as u8;
+            log[poly as usize] = i as u8;
+            // multiply poly by the generator x + 1
+            poly = (poly << 1) ^ poly;
+            if poly & 0x100 != 0 {
+                poly ^= 0x11B;
+            }
+        }
+        (exp, log)
+    };
+}
+
+/// A single decoded share.
+struct Share {
+    identifier: u16,
+    extendable: bool,
+    iteration_exponent: u8,
+    group_index: u8,
+    group_threshold: u8,
+    group_count: u8,
+    member_index: u8,
+    member_threshold: u8,
+    value: Vec<u8>,
+}
+
+/// Splits the master secret into `share_count` mnemonics (single group),
+/// any `threshold` of which are sufficient to reconstruct the secret.
+pub fn generate_mnemonics(
+    master_secret: &[u8],
+    threshold: u8,
+    share_count: u8,
+    passphrase: &str,
+) -> Result<Vec<Vec<&'static str>>, Slip39Error> {
+    if master_secret.len() * 8 < MIN_STRENGTH_BITS || master_secret.len() % 2 == 1 {
+        return Err(Slip39Error::new("Invalid master secret length"));
+    }
+    if threshold == 0 || threshold > share_count || share_count > MAX_SHARE_COUNT {
+        return Err(Slip39Error::new("Invalid threshold or share count"));
+    }
+    if threshold == 1 && share_count > 1 {
+        return Err(Slip39Error::new("Use 1-of-1 sharing instead of 1-of-n"));
+    }
+    let mut rng = ChaCha20Rng::from_entropy();
+    let identifier: u16 = rng.gen_range(0..(1 << ID_LENGTH_BITS));
+    let extendable = true;
+    let encrypted_secret = encrypt(
+        master_secret,
+        passphrase,
+        DEFAULT_ITERATION_EXPONENT,
+        identifier,
+        extendable,
+    );
+    // a single group with a group threshold of one
+    let group_shares = split_secret(1, 1, &encrypted_secret, &mut rng)?;
+    let (group_index, group_secret) = &group_shares[0];
+    let member_shares = split_secret(threshold, share_count, group_secret, &mut rng)?;
+    Ok(member_shares
+        .into_iter()
+        .map(|(member_index, value)| {
+            Share {
+                identifier,
+                extendable,
+                iteration_exponent: DEFAULT_ITERATION_EXPONENT,
+                group_index: *group_index,
+                group_threshold: 1,
+                group_count: 1,
+                member_index,
+                member_threshold: threshold,
+                value,
+            }
+            .to_mnemonic()
+        })
+        .collect())
+}
+
+/// Reconstructs the master secret from the given mnemonics.
+pub fn combine_mnemonics(
+    mnemonics: &[Vec<&str>],
+    passphrase: &str,
+) -> Result<Vec<u8>, Slip39Error> {
+    if mnemonics.is_empty() {
+        return Err(Slip39Error::new("No mnemonics provided"));
+    }
+    let shares: Vec<Share> = mnemonics
+        .iter()
+        .map(|mnemonic| Share::from_mnemonic(mnemonic))
+        .collect::<Result<_, _>>()?;
+    let first = &shares[0];
+    for share in &shares {
+        if share.identifier != first.identifier
+            || share.extendable != first.extendable
+            || share.iteration_exponent != first.iteration_exponent
+        {
+            return Err(Slip39Error::new("Mnemonics belong to different secrets"));
+        }
+        if share.group_threshold != first.group_threshold || share.group_count != first.group_count
+        {
+            return Err(Slip39Error::new("Mismatching group parameters"));
+        }
+    }
+
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for share in &shares {
+        groups.entry(share.group_index).or_default().push(share);
+    }
+    if groups.len() != first.group_threshold as usize {
+        return Err(Slip39Error::new("Wrong number of groups"));
+    }
+    let mut group_shares = Vec::new();
+    for (group_index, members) in groups {
+        let member_threshold = members[0].member_threshold;
+        if members
+            .iter()
+            .any(|share| share.member_threshold != member_threshold)
+        {
+            return Err(Slip39Error::new("Mismatching member thresholds"));
+        }
+        let mut member_shares: Vec<(u8, Vec<u8>)> = Vec::new();
+        for share in members {
+            if member_shares
+                .iter()
+                .any(|(index, _)| *index == share.member_index)
+            {
+                return Err(Slip39Error::new("Duplicate member indices"));
+            }
+            member_shares.push((share.member_index, share.value.clone()));
+        }
+        if member_shares.len() != member_threshold as usize {
+            return Err(Slip39Error::new("Wrong number of mnemonics"));
+        }
+        let group_secret = recover_secret(member_threshold, &member_shares)?;
+        group_shares.push((group_index, group_secret));
+    }
+    let encrypted_secret = recover_secret(first.group_threshold, &group_shares)?;
+    Ok(decrypt(
+        &encrypted_secret,
+        passphrase,
+        first.iteration_exponent,
+        first.identifier,
+        first.extendable,
+    ))
+}
+
+impl Share {
+    /// Encodes the share as a mnemonic.
+    fn to_mnemonic(&self) -> Vec<&'static str> {
+        let mut bits: BitVec<u8, Msb0> = BitVec::new();
+        let id_exp = ((self.identifier as u32) << (ITERATION_EXP_LENGTH_BITS + 1))
+            | ((self.extendable as u32) << ITERATION_EXP_LENGTH_BITS)
+            | self.iteration_exponent as u32;
+        bits.extend_from_bitslice(&id_exp.to_be_bytes().view_bits::<Msb0>()[12..]);
+        for field in [
+            self.group_index,
+            self.group_threshold - 1,
+            self.group_count - 1,
+            self.member_index,
+            self.member_threshold - 1,
+        ] {
+            bits.extend_from_bitslice(&field.view_bits::<Msb0>()[4..]);
+        }
+        let value_bits = self.value.len() * 8;
+        let padding = (RADIX_BITS - value_bits % RADIX_BITS) % RADIX_BITS;
+        bits.resize(bits.len() + padding, false);
+        bits.extend_from_bitslice(self.value.view_bits::<Msb0>());
+
+        let mut indices: Vec<u16> = bits
+            .chunks(RADIX_BITS)
+            .map(|chunk| chunk.load_be::<u16>())
+            .collect();
+        indices.extend(rs1024_create_checksum(&indices, self.extendable));
+        indices
+            .into_iter()
+            .map(|index| WORD_LIST[index as usize])
+            .collect()
+    }
+
+    /// Decodes a share from a mnemonic.
+    fn from_mnemonic(mnemonic: &[&str]) -> Result<Share, Slip39Error> {
+        if mnemonic.len() < METADATA_LENGTH_WORDS + MIN_STRENGTH_BITS / RADIX_BITS + 1 {
+            return Err(Slip39Error::new("Invalid mnemonic length"));
+        }
+        let indices: Vec<u16> = mnemonic
+            .iter()
+            .map(|word| {
+                WORD_LIST
+                    .iter()
+                    .position(|w| *w == word.to_lowercase())
+                    .map(|index| index as u16)
+                    .ok_or(Slip39Error::new("Invalid word"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let padding = (RADIX_BITS * (indices.len() - METADATA_LENGTH_WORDS)) % 16;
+        if padding > 8 {
+            return Err(Slip39Error::new("Invalid mnemonic length"));
+        }
+        let extendable = (indices[1] >> ITERATION_EXP_LENGTH_BITS) & 1 == 1;
+        if !rs1024_verify_checksum(&indices, extendable) {
+            return Err(Slip39Error::new("Invalid checksum"));
+        }
+
+        let mut bits: BitVec<u8, Msb0> = BitVec::new();
+        indices[..indices.len() - CHECKSUM_LENGTH_WORDS]
+            .iter()
+            .for_each(|index| bits.extend_from_bitslice(&index.view_bits::<Msb0>()[6..]));
+        let id_exp = bits[..20].load_be::<u32>();
+        let field = |i: usize| bits[20 + 4 * i..24 + 4 * i].load_be::<u8>();
+        let (value_padding, value_bits) = bits[40..].split_at(padding);
+        if value_padding.any() {
+            return Err(Slip39Error::new("Invalid padding"));
+        }
+        let share = Share {
+            identifier: (id_exp >> (ITERATION_EXP_LENGTH_BITS + 1)) as u16,
+            extendable,
+            iteration_exponent: (id_exp & 0xF) as u8,
+            group_index: field(0),
+            group_threshold: field(1) + 1,
+            group_count: field(2) + 1,
+            member_index: field(3),
+            member_threshold: field(4) + 1,
+            value: value_bits
+                .chunks(8)
+                .map(|byte| byte.load_be::<u8>())
+                .collect(),
+        };
+        if share.group_count < share.group_threshold {
+            return Err(Slip39Error::new("Group threshold exceeds group count"));
+        }
+        Ok(share)
+    }
+}
+
+/// Computes the RS1024 checksum polynomial.
+fn rs1024_polymod(values: impl Iterator<Item = u32>) -> u32 {
+    let mut chk: u32 = 1;
+    for value in values {
+        let b = chk >> 20;
+        chk = ((chk & 0xFFFFF) << 10) ^ value;
+        for (i, generator) in RS1024_GENERATOR.iter().enumerate() {
+            if (b >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn customization_string(extendable: bool) -> &'static str {
+    if extendable {
+        CUSTOMIZATION_STRING_EXTENDABLE
+    } else {
+        CUSTOMIZATION_STRING
+    }
+}
+
+/// Returns the three checksum words for the given data words.
+fn rs1024_create_checksum(data: &[u16], extendable: bool) -> Vec<u16> {
+    let values = customization_string(extendable)
+        .bytes()
+        .map(|b| b as u32)
+        .chain(data.iter().map(|index| *index as u32))
+        .chain([0, 0, 0]);
+    let polymod = rs1024_polymod(values) ^ 1;
+    (0..CHECKSUM_LENGTH_WORDS)
+        .map(|i| ((polymod >> (10 * (2 - i))) & 1023) as u16)
+        .collect()
+}
+
+/// Verifies the checksum of the given words (including the checksum words).
+fn rs1024_verify_checksum(data: &[u16], extendable: bool) -> bool {
+    let values = customization_string(extendable)
+        .bytes()
+        .map(|b| b as u32)
+        .chain(data.iter().map(|index| *index as u32));
+    rs1024_polymod(values) == 1
+}
+
+/// Evaluates the polynomial defined by the shares at `x` using Lagrange interpolation.
+fn interpolate(shares: &[(u8, Vec<u8>)], x: u8) -> Vec<u8> {
+    if let Some((_, value)) = shares.iter().find(|(share_x, _)| *share_x == x) {
+        return value.clone();
+    }
+    let (exp, log) = &*GF256_TABLES;
+    let log_prod: u32 = shares
+        .iter()
+        .map(|(share_x, _)| log[(share_x ^ x) as usize] as u32)
+        .sum();
+    let mut result = vec![0u8; shares[0].1.len()];
+    for (share_x, value) in shares {
+        let log_denominator: u32 = shares
+            .iter()
+            .map(|(other_x, _)| log[(share_x ^ other_x) as usize] as u32)
+            .sum();
+        let log_basis = (log_prod + 255 * shares.len() as u32
+            - log[(share_x ^ x) as usize] as u32
+            - log_denominator)
+            % 255;
+        for (r, v) in result.iter_mut().zip(value) {
+            if *v != 0 {
+                *r ^= exp[((log[*v as usize] as u32 + log_basis) % 255) as usize];
+            }
+        }
+    }
+    result
+}
+
+/// Returns the first bytes of HMAC-SHA256(random_data, shared_secret).
+fn create_digest(random_data: &[u8], shared_secret: &[u8]) -> Vec<u8> {
+    let mut engine = hmac::HmacEngine::<sha256::Hash>::new(random_data);
+    engine.input(shared_secret);
+    hmac::Hmac::from_engine(engine).into_inner()[..DIGEST_LENGTH_BYTES].to_vec()
+}
+
+/// Splits the secret into `share_count` shares with the given threshold.
+fn split_secret(
+    threshold: u8,
+    share_count: u8,
+    shared_secret: &[u8],
+    rng: &mut ChaCha20Rng,
+) -> Result<Vec<(u8, Vec<u8>)>, Slip39Error> {
+    if threshold == 0 || threshold > share_count || share_count > MAX_SHARE_COUNT {
+        return Err(Slip39Error::new("Invalid threshold or share count"));
+    }
+    if threshold == 1 {
+        return Ok((0..share_count)
+            .map(|index| (index, shared_secret.to_vec()))
+            .collect());
+    }
+    let random_share_count = threshold - 2;
+    let mut shares: Vec<(u8, Vec<u8>)> = (0..random_share_count)
+        .map(|index| {
+            let mut value = vec![0u8; shared_secret.len()];
+            rng.fill_bytes(&mut value);
+            (index, value)
+        })
+        .collect();
+    let mut random_part = vec![0u8; shared_secret.len() - DIGEST_LENGTH_BYTES];
+    rng.fill_bytes(&mut random_part);
+    let mut digest = create_digest(&random_part, shared_secret);
+    digest.extend(random_part);
+
+    let mut base_shares = shares.clone();
+    base_shares.push((DIGEST_INDEX, digest));
+    base_shares.push((SECRET_INDEX, shared_secret.to_vec()));
+    for index in random_share_count..share_count {
+        shares.push((index, interpolate(&base_shares, index)));
+    }
+    Ok(shares)
+}
+
+/// Recovers the secret from the shares and checks its digest.
+fn recover_secret(threshold: u8, shares: &[(u8, Vec<u8>)]) -> Result<Vec<u8>, Slip39Error> {
+    if threshold == 1 {
+        return Ok(shares[0].1.clone());
+    }
+    let shared_secret = interpolate(shares, SECRET_INDEX);
+    let digest_share = interpolate(shares, DIGEST_INDEX);
+    let (digest, random_part) = digest_share.split_at(DIGEST_LENGTH_BYTES);
+    if digest != create_digest(random_part, &shared_secret) {
+        return Err(Slip39Error::new("Invalid digest of the shared secret"));
+    }
+    Ok(shared_secret)
+}
+
+/// Computes PBKDF2 with HMAC-SHA256 as the pseudorandom function.
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32, length: usize) -> Vec<u8> {
+    let prf = hmac::HmacEngine::<sha256::Hash>::new(password);
+    let mut output = Vec::with_capacity(length);
+    let mut block_index: u32 = 1;
+    while output.len() < length {
+        let mut engine = prf.clone();
+        engine.input(salt);
+        engine.input(&block_index.to_be_bytes());
+        let mut u = hmac::Hmac::from_engine(engine).into_inner();
+        let mut block = u;
+        for _ in 1..iterations {
+            let mut engine = prf.clone();
+            engine.input(&u);
+            u = hmac::Hmac::from_engine(engine).into_inner();
+            block.iter_mut().zip(u).for_each(|(b, u)| *b ^= u);
+        }
+        output.extend(block);
+        block_index += 1;
+    }
+    output.truncate(length);
+    output
+}
+
+/// The round function of the Feistel network.
+fn round_function(
+    round: u8,
+    passphrase: &str,
+    iteration_exponent: u8,
+    salt: &[u8],
+    r: &[u8],
+) -> Vec<u8> {
+    let mut password = vec![round];
+    password.extend(passphrase.as_bytes());
+    let mut round_salt = salt.to_vec();
+    round_salt.extend(r);
+    let iterations = (BASE_ITERATION_COUNT << iteration_exponent) / ROUND_COUNT as u32;
+    pbkdf2_sha256(&password, &round_salt, iterations, r.len())
+}
+
+fn feistel_salt(identifier: u16, extendable: bool) -> Vec<u8> {
+    if extendable {
+        return Vec::new();
+    }
+    let mut salt = CUSTOMIZATION_STRING.as_bytes().to_vec();
+    salt.extend(identifier.to_be_bytes());
+    salt
+}
+
+/// Runs the Feistel network over the secret with the rounds in the given order.
+fn feistel(
+    secret: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+    rounds: impl Iterator<Item = u8>,
+) -> Vec<u8> {
+    let salt = feistel_salt(identifier, extendable);
+    let (l, r) = secret.split_at(secret.len() / 2);
+    let (mut l, mut r) = (l.to_vec(), r.to_vec());
+    for round in rounds {
+        let f = round_function(round, passphrase, iteration_exponent, &salt, &r);
+        let new_r: Vec<u8> = l.iter().zip(f).map(|(l, f)| l ^ f).collect();
+        l = r;
+        r = new_r;
+    }
+    r.extend(l);
+    r
+}
+
+/// Encrypts the master secret with the passphrase.
+fn encrypt(
+    master_secret: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Vec<u8> {
+    feistel(
+        master_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        extendable,
+        0..ROUND_COUNT,
+    )
+}
+
+/// Decrypts the encrypted master secret with the passphrase.
+fn decrypt(
+    encrypted_secret: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Vec<u8> {
+    feistel(
+        encrypted_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        extendable,
+        (0..ROUND_COUNT).rev(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combine_mnemonics, generate_mnemonics};
+    use crate::utils::hex;
+
+    fn split_words(mnemonics: &[&'static str]) -> Vec<Vec<&'static str>> {
+        mnemonics
+            .iter()
+            .map(|mnemonic| mnemonic.split(" ").collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_combine_without_sharing() {
+        let mnemonics = split_words(&["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"]);
+        let secret = combine_mnemonics(&mnemonics, "TREZOR").unwrap();
+        assert_eq!(
+            hex::bytes_to_hex(&secret),
+            "bb54aac4b89dc868ba37d9cc21b2cece"
+        );
+    }
+
+    #[test]
+    fn test_combine_basic_sharing() {
+        let mnemonics = split_words(&[
+            "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+            "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+        ]);
+        let secret = combine_mnemonics(&mnemonics, "TREZOR").unwrap();
+        assert_eq!(
+            hex::bytes_to_hex(&secret),
+            "b43ceb7e57a0ea8766221624d01b0864"
+        );
+        assert!(combine_mnemonics(&mnemonics[..1], "TREZOR").is_err());
+    }
+
+    #[test]
+    fn test_combine_groups() {
+        let mnemonics = split_words(&[
+            "eraser senior decision roster beard treat identify grumpy salt index fake aviation theater cubic bike cause research dragon emphasis counter",
+            "eraser senior ceramic snake clay various huge numb argue hesitate auction category timber browser greatest hanger petition script leaf pickup",
+            "eraser senior ceramic shaft dynamic become junior wrist silver peasant force math alto coal amazing segment yelp velvet image paces",
+            "eraser senior ceramic round column hawk trust auction smug shame alive greatest sheriff living perfect corner chest sled fumes adequate",
+            "eraser senior decision smug corner ruin rescue cubic angel tackle skin skunk program roster trash rumor slush angel flea amazing",
+        ]);
+        let secret = combine_mnemonics(&mnemonics, "TREZOR").unwrap();
+        assert_eq!(
+            hex::bytes_to_hex(&secret),
+            "7c3397a292a5941682d7a4ae2d898d11"
+        );
+    }
+
+    #[test]
+    fn test_invalid_checksum() {
+        let mnemonics = split_words(&["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney"]);
+        assert!(combine_mnemonics(&mnemonics, "TREZOR").is_err());
+    }
+
+    #[test]
+    fn test_invalid_digest() {
+        let mnemonics = split_words(&[
+            "guilt walnut academic acid deliver remove equip listen vampire tactics nylon rhythm failure husband fatigue alive blind enemy teaspoon rebound",
+            "guilt walnut academic agency brave hamster hobo declare herd taste alpha slim criminal mild arcade formal romp branch pink ambition",
+        ]);
+        assert!(combine_mnemonics(&mnemonics, "TREZOR").is_err());
+    }
+
+    #[test]
+    fn test_generate_and_combine() {
+        let secret =
+            hex::hex_to_bytes("5b56c417303faa3fcba7e57400e120a0ca83ec5a4fc9ffba757fbe63fbd77a89")
+                .unwrap();
+        let mnemonics = generate_mnemonics(&secret, 3, 5, "passphrase").unwrap();
+        assert_eq!(mnemonics.len(), 5);
+        assert!(mnemonics.iter().all(|mnemonic| mnemonic.len() == 33));
+        let recovered = combine_mnemonics(&mnemonics[1..4], "passphrase").unwrap();
+        assert_eq!(recovered, secret);
+        let recovered = combine_mnemonics(&mnemonics[2..], "wrong passphrase").unwrap();
+        assert_ne!(recovered, secret);
+        assert!(combine_mnemonics(&mnemonics[..2], "passphrase").is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! Implements the tweaking of keys for Taproot outputs without a script tree
+//! and the tagged hashes of [BIP-340](https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki)
+//! and [BIP-341](https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki).
+use bitcoin_hashes::{sha256, Hash};
+use secp256k1::{KeyPair, Scalar, Secp256k1, SecretKey, XOnlyPublicKey};
+
+use crate::keys::bip32::{ExtendedPrivateKey, ExtendedPublicKey};
+
+pub static TAP_TWEAK_TAG: &[u8] = b"TapTweak";
+pub static TAP_SIGHASH_TAG: &[u8] = b"TapSighash";
+
+/// Computes a tagged hash as described in BIP-340.
+pub fn tagged_hash(tag: &[u8], data: &[u8]) -> [u8; 32] {
+    let tag_hash = sha256::Hash::hash(tag);
+    let mut preimage = tag_hash.to_vec();
+    preimage.extend(&tag_hash[..]);
+    preimage.extend(data);
+    sha256::Hash::hash(&preimage).into_inner()
+}
+
+/// Tweaks the key pair with its own public key, i.e., a Taproot output without scripts.
+pub fn tweak_keypair(keypair: &KeyPair) -> KeyPair {
+    let secp = Secp256k1::new();
+    let (internal_key, _) = XOnlyPublicKey::from_keypair(keypair);
+    let tweak = tagged_hash(TAP_TWEAK_TAG, &internal_key.serialize());
+    keypair
+        .add_xonly_tweak(&secp, &Scalar::from_be_bytes(tweak).unwrap())
+        .unwrap()
+}
+
+/// Returns the tweaked key pair of the private key.
+pub fn tweaked_keypair(private_key: &ExtendedPrivateKey) -> KeyPair {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&private_key.key_data).unwrap();
+    tweak_keypair(&KeyPair::from_secret_key(&secp, &secret_key))
+}
+
+/// Tweaks the internal key with itself, i.e., a Taproot output without scripts.
+pub fn tweak_public_key(internal_key: &XOnlyPublicKey) -> XOnlyPublicKey {
+    let secp = Secp256k1::verification_only();
+    let tweak = tagged_hash(TAP_TWEAK_TAG, &internal_key.serialize());
+    let (output_key, _) = internal_key
+        .add_tweak(&secp, &Scalar::from_be_bytes(tweak).unwrap())
+        .unwrap();
+    output_key
+}
+
+/// Returns the output key of the Taproot output of the public key.
+pub fn public_output_key(public_key: &ExtendedPublicKey) -> XOnlyPublicKey {
+    // the x-only key drops the first byte, which only encodes the parity
+    let internal_key = XOnlyPublicKey::from_slice(&public_key.key_data[1..]).unwrap();
+    tweak_public_key(&internal_key)
+}
+
+/// Returns the output key of the Taproot output of the private key.
+pub fn output_key(private_key: &ExtendedPrivateKey) -> XOnlyPublicKey {
+    let (output_key, _) = XOnlyPublicKey::from_keypair(&tweaked_keypair(private_key));
+    output_key
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::taproot::tweak_public_key;
+    use crate::utils::hex;
+    use secp256k1::XOnlyPublicKey;
+
+    #[test]
+    fn test_tweak_public_key() {
+        // first receiving address of BIP-86
+        let internal_key = XOnlyPublicKey::from_slice(
+            &hex::hex_to_bytes("cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            hex::bytes_to_hex(&tweak_public_key(&internal_key).serialize()),
+            "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+        );
+    }
+}
@@ -1,6 +1,11 @@
 pub mod coin_selection;
 pub mod encryption;
 pub mod keys;
+pub mod messages;
 pub mod networking;
+pub mod security;
+#[cfg(test)]
+pub mod test_utils;
 pub mod transactions;
 pub mod utils;
+pub mod wallets;
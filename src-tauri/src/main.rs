@@ -5,376 +5,4524 @@
 
 use dirs;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
     str::FromStr,
     sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tauri::State;
+use tauri::{State, Window};
+use zeroize::Zeroize;
 
-use app::coin_selection;
-use app::encryption::error::WrongPasswordError;
-use app::networking::{self, fee::Fees};
-use app::transactions::transaction::SimplifiedTransaction;
+use app::coin_selection::{
+    self,
+    confirmations::ConfirmationPolicy,
+    fee_estimation,
+    frozen::FrozenUTXOs,
+    reservation::{Lease, ReservationTable},
+    strategy::CoinSelectionStrategy,
+    sweep::SweepResult,
+};
+use app::encryption::{
+    self,
+    error::{CorruptedFileError, WrongPasswordError},
+    SecurityLevel,
+};
+use app::networking::{
+    self,
+    backend::{ChainBackend, ChainTip, SelectableBackend},
+    broadcast::BroadcastEndpoints,
+    discovery::{DiscoveredAccount, ScanProgress},
+    error::{
+        BroadcastError, OfflineError, RequestError, RequestErrorKind, SendTransactionError,
+        WrongNetworkError,
+    },
+    fee::{ConfirmationEstimate, Fees},
+    fee_fallback::FeeFallbacks,
+    health::EndpointHealth,
+    live::LiveEvent,
+    mempool::MempoolBackend,
+    monitor::EvictionWarning,
+    price::{self, PriceCache},
+    rate_limit::{self, RateLimit},
+    settings::{BackendSettings, NetworkSettings},
+    store::{ChainStore, StoredAccount},
+    sync::{SyncCache, SyncEvent, WatchedSnapshot},
+    tracking::{PendingTransaction, PendingTransactions},
+};
+use app::security::{AttemptStatus, PasswordAttempts};
+use app::transactions::journal::{BroadcastJournal, JournalEntry};
+use app::transactions::preview::TransactionPreview;
+use app::transactions::script::{ScriptType, MAX_OP_RETURN_SIZE};
+use app::transactions::send_request::SendRequest;
+use app::transactions::transaction::{self, SimplifiedTransaction, TransactionOutput, DUST_LIMIT};
+use app::transactions::utxo::{AccountUTXO, UTXOBox};
+use app::transactions::{
+    address_details::AddressDetails,
+    balance::{AccountBalance, SpendableUTXOs},
+    cpfp,
+    details::TransactionDetails,
+    export::{self, ExportFormat},
+    fee_adjustment,
+    payjoin::{PayjoinSender, PaymentResult},
+    payment_uri::PaymentUri,
+    psbt::Psbt,
+    rbf, send_max,
+};
+use app::wallets::{
+    backup::{WalletBackup, WalletStores},
+    contacts::{Contact, Contacts},
+    error::{SetupError, WalletError},
+    keychain,
+    labels::{Label, LabelType, Labels},
+    payment_requests::{PaymentRequestDetails, PaymentRequests},
+    settings::WalletSettings,
+    setup::WalletSetupSession,
+    watch_list::{WatchList, WatchedAddressBalance},
+    WalletManager, DEFAULT_WALLET,
+};
 use app::{
     keys::{
         address::{Address, SimpleAddress},
-        bip39,
-        bip44::{MasterPrivateKey, MasterPublicKey},
+        bip32::{DerivationPath, DerivedKey},
+        bip44::{
+            multisig::{
+                Cosigner, MultisigAccount, MultisigAccounts, MultisigAddress, MultisigScriptType,
+            },
+            Keypair, MasterPrivateKey, MasterPublicKey, SINGLE_KEY_PURPOSES,
+        },
+        bip85, message,
+        network::{self, TestNetwork},
+        slip39,
     },
+    messages,
     transactions::transaction::Transaction,
+    utils::{file, hex},
 };
 
-static KEY_PATH: &'static str = ".bitcoinwallet";
+static JOURNAL_EXTENSION: &'static str = "journal";
+static WATCH_LIST_EXTENSION: &'static str = "watch";
+static SCAN_PROGRESS_EXTENSION: &'static str = "scan";
+static MULTISIG_EXTENSION: &'static str = "multisig";
+static ATTEMPTS_EXTENSION: &'static str = "attempts";
+static PUBLIC_KEY_EXTENSION: &'static str = "pub";
+static FROZEN_EXTENSION: &'static str = "frozen";
+static LABELS_EXTENSION: &'static str = "labels";
+static CONTACTS_EXTENSION: &'static str = "contacts";
+static SETTINGS_EXTENSION: &'static str = "settings";
+static SYNC_CACHE_EXTENSION: &'static str = "sync";
+static STORE_EXTENSION: &'static str = "sqlite";
+static PENDING_EXTENSION: &'static str = "pending";
+static PAYMENT_REQUESTS_EXTENSION: &'static str = "requests";
+static NETWORK_SETTINGS_FILE: &'static str = ".bitcoinwallet_network";
+static PRICE_CACHE_FILE: &'static str = ".bitcoinwallet_prices";
 
 #[derive(Default)]
 struct Database(Arc<Mutex<HashMap<String, Vec<u8>>>>);
 
-/**
- * Key creation
- */
+#[derive(Default)]
+struct Reservations(Arc<Mutex<ReservationTable>>);
+
+#[derive(Default)]
+struct Setup(Mutex<Option<WalletSetupSession>>);
+
+struct Backend(Arc<SelectableBackend>);
+
+#[derive(Default)]
+struct PreparedTransactions(Mutex<PreparedTable>);
+
+#[derive(Default)]
+struct LiveUpdates(Mutex<Option<tauri::async_runtime::JoinHandle<()>>>);
+
+#[derive(Default)]
+struct SyncService(Mutex<Option<tauri::async_runtime::JoinHandle<()>>>);
+
+#[derive(Default)]
+struct PreparedTable {
+    next_id: u64,
+    transactions: HashMap<u64, PreparedTransaction>,
+}
+
+/// Returns the name of the open wallet.
+fn wallet_name(db: &Database) -> String {
+    match db.0.lock().unwrap().get("wallet_name") {
+        Some(name) => bincode::deserialize(&name[..]).unwrap(),
+        None => DEFAULT_WALLET.to_string(),
+    }
+}
+
+/// Returns the path of the key file of the open wallet.
+fn key_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.key_path(&wallet_name(db))
+}
+
+/// Returns the path of the broadcast journal of the open wallet.
+fn journal_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), JOURNAL_EXTENSION)
+}
+
+/// Returns the path of the watch list of the open wallet.
+fn watch_list_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), WATCH_LIST_EXTENSION)
+}
+
+/// Returns the path of the multisig accounts of the open wallet.
+fn multisig_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), MULTISIG_EXTENSION)
+}
+
+/// Returns the path of the account discovery progress of the open wallet.
+fn scan_progress_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), SCAN_PROGRESS_EXTENSION)
+}
+
+/// Returns the path of the failed password attempts of the open wallet.
+fn attempts_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), ATTEMPTS_EXTENSION)
+}
+
+/// Returns the path of the public key hierarchy of the open wallet.
+fn public_key_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), PUBLIC_KEY_EXTENSION)
+}
+
+/// Sets the public key hierarchy of the open wallet and saves it, so the wallet can be
+/// shown without the password after a restart. A failed save only affects the next
+/// start, so it is ignored.
+fn set_master_public_key(db: &Database, master_public_key: &MasterPublicKey) {
+    db.0.lock().unwrap().insert(
+        "master_public_key".to_string(),
+        master_public_key.serialize(),
+    );
+    let _ = master_public_key.save_to_file(public_key_path(db));
+}
+
+/// Loads the saved public key hierarchy of the open wallet.
+/// Returns false if the hierarchy was never saved.
+fn load_master_public_key(db: &Database) -> bool {
+    match MasterPublicKey::load_from_file(public_key_path(db)) {
+        Ok(master_public_key) => {
+            db.0.lock().unwrap().insert(
+                "master_public_key".to_string(),
+                master_public_key.serialize(),
+            );
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Returns the path of the frozen UTXOs of the open wallet.
+fn frozen_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), FROZEN_EXTENSION)
+}
+
+/// Returns the path of the labels of the open wallet.
+fn labels_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), LABELS_EXTENSION)
+}
+
+/// Returns the path of the contacts of the open wallet.
+fn contacts_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), CONTACTS_EXTENSION)
+}
+
+/// Returns the path of the store of the chain data of the open wallet.
+fn store_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), STORE_EXTENSION)
+}
+
+/// Returns the stored chain data of the account, or `None` if the account
+/// was never synchronized or the store cannot be read.
+fn stored_account(
+    db: &Database,
+    coin_type_index: u32,
+    account_index: u32,
+) -> Option<StoredAccount> {
+    let store = ChainStore::open(store_path(db)).ok()?;
+    store.load_account(coin_type_index, account_index).ok()?
+}
+
+/// Returns the path of the synchronized accounts of the open wallet.
+fn sync_cache_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), SYNC_CACHE_EXTENSION)
+}
+
+/// Returns the path of the transactions whose confirmations are tracked.
+fn pending_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), PENDING_EXTENSION)
+}
+
+/// Returns the path of the payment requests of the current wallet.
+fn payment_requests_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), PAYMENT_REQUESTS_EXTENSION)
+}
+
+/// Returns the path of the settings of the current wallet.
+fn settings_path(db: &Database) -> PathBuf {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    manager.auxiliary_path(&wallet_name(db), SETTINGS_EXTENSION)
+}
+
+/// Returns the path of the network settings, which are shared by all wallets.
+fn network_settings_path() -> PathBuf {
+    dirs::home_dir().unwrap().join(NETWORK_SETTINGS_FILE)
+}
+
+/// Returns the path of the cache of historical prices, which is shared by all wallets.
+fn price_cache_path() -> PathBuf {
+    dirs::home_dir().unwrap().join(PRICE_CACHE_FILE)
+}
+
+/// Sets the value of the confirmed transactions in the fiat currency at their block time.
+/// Testnet coins have no value, so only mainnet transactions are resolved. In the offline
+/// mode, only cached prices are used.
+async fn resolve_fiat_values(
+    coin_type_index: u32,
+    currency: &str,
+    simple_txs: &mut [SimplifiedTransaction],
+) -> Result<(), String> {
+    if !price::is_supported_currency(currency) {
+        return Err("invalid_currency_error".to_string());
+    }
+    if coin_type_index == 1 {
+        return Ok(());
+    }
+    let offline = match NetworkSettings::load(network_settings_path()) {
+        Ok(settings) => settings.offline,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let path = price_cache_path();
+    let mut cache = match PriceCache::load(&path) {
+        Ok(cache) => cache,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let api = MempoolBackend::default();
+    let mut api_available = !offline;
+    for simple_tx in simple_txs.iter_mut() {
+        let block_time = match simple_tx.block_time {
+            Some(block_time) => block_time,
+            None => continue,
+        };
+        let price = match cache.get(currency, block_time) {
+            Some(price) => Some(price),
+            None if api_available => {
+                match price::get_historical_price(&api, &mut cache, currency, block_time).await {
+                    Ok(price) => price,
+                    Err(_) => {
+                        // the remaining prices are not queried once the API failed
+                        api_available = false;
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        simple_tx.fiat_value = price.map(|price| price::fiat_value(simple_tx.value, price));
+    }
+    if cache.save(&path).is_err() {
+        return Err("io_error".to_string());
+    }
+    Ok(())
+}
+
+/// Returns the specified coin selection strategy or, if none is specified,
+/// the strategy of the wallet settings.
+fn resolve_strategy(
+    strategy: Option<String>,
+    db: &Database,
+) -> Result<CoinSelectionStrategy, String> {
+    if let Some(strategy) = strategy {
+        return CoinSelectionStrategy::from_str(&strategy).map_err(|err| err.to_string());
+    }
+    match WalletSettings::load(settings_path(db)) {
+        Ok(settings) => Ok(settings.coin_selection_strategy),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Returns the confirmation policy of the wallet settings.
+/// The specified minimums override the minimums of the settings.
+fn resolve_confirmation_policy(
+    min_confirmations: Option<u32>,
+    min_change_confirmations: Option<u32>,
+    db: &Database,
+) -> Result<ConfirmationPolicy, String> {
+    let mut policy = match WalletSettings::load(settings_path(db)) {
+        Ok(settings) => settings.confirmation_policy,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if let Some(min_confirmations) = min_confirmations {
+        policy.min_confirmations = min_confirmations;
+    }
+    if let Some(min_change_confirmations) = min_change_confirmations {
+        policy.min_change_confirmations = min_change_confirmations;
+    }
+    Ok(policy)
+}
+
+/// Returns the UTXOs that have enough confirmations according to the policy. The
+/// height of the most recent block is only requested if the policy requires confirmations.
+async fn eligible_utxos(
+    policy: ConfirmationPolicy,
+    boxed_utxos: Vec<UTXOBox>,
+    change_addresses: Vec<String>,
+    testnet: bool,
+    backend: &Backend,
+) -> Result<Vec<UTXOBox>, String> {
+    if !policy.requires_confirmations() {
+        return Ok(boxed_utxos);
+    }
+    let tip_height = match backend.0.get_tip_height(testnet).await {
+        Ok(tip_height) => tip_height,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    Ok(policy.eligible_utxos(boxed_utxos, &change_addresses, tip_height))
+}
+
+/// Returns the addresses of the change keys of the account.
+fn change_addresses(
+    master_private_key: &MasterPrivateKey,
+    coin_type_index: u32,
+    account_index: u32,
+) -> Vec<String> {
+    master_private_key
+        .get_all_change_keypairs(coin_type_index, account_index)
+        .iter()
+        .flat_map(|keypair| keypair.public_key.get_addresses())
+        .map(|address| address.to_string())
+        .collect()
+}
+
+/// Returns the current UNIX timestamp in seconds.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Loads the key hierarchy and records failed password attempts. Further attempts
+/// are delayed, and the wallet is wiped if its policy says so.
+fn load_with_attempt_limit(
+    key_path: &Path,
+    attempts_path: &Path,
+    password: String,
+) -> Result<MasterPrivateKey, String> {
+    with_attempt_limit(key_path, attempts_path, || {
+        MasterPrivateKey::load(key_path, password)
+    })
+}
+
+/// Loads the key hierarchy of the open wallet. Every command that checks the password
+/// loads the key hierarchy this way, so none of them can be used to guess the password.
+fn load_master_private_key(db: &Database, password: String) -> Result<MasterPrivateKey, String> {
+    load_with_attempt_limit(&key_path(db), &attempts_path(db), password)
+}
+
+/// Runs the password check `unlock` and records failed password attempts
+/// the same way as `load_with_attempt_limit`.
+fn with_attempt_limit<T, F>(key_path: &Path, attempts_path: &Path, unlock: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, Box<dyn Error>>,
+{
+    let mut attempts = match PasswordAttempts::load(attempts_path) {
+        Ok(attempts) => attempts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let now = unix_now();
+    if attempts.retry_after(now) > 0 {
+        return Err("too_many_attempts_error".to_string());
+    }
+    match unlock() {
+        Ok(unlocked) => {
+            attempts.record_success();
+            if attempts.save(attempts_path).is_err() {
+                return Err("io_error".to_string());
+            }
+            Ok(unlocked)
+        }
+        Err(err) if err.is::<WrongPasswordError>() => {
+            attempts.record_failure(now);
+            if attempts.should_wipe() {
+                if fs::remove_file(key_path).is_err()
+                    || MasterPrivateKey::discard_backups(key_path).is_err()
+                    || fs::remove_file(attempts_path).is_err()
+                {
+                    return Err("io_error".to_string());
+                }
+                // the public key hierarchy would still reveal the addresses of the wallet
+                let mut public_key_path = key_path.as_os_str().to_owned();
+                public_key_path.push(".");
+                public_key_path.push(PUBLIC_KEY_EXTENSION);
+                let _ = fs::remove_file(public_key_path);
+                return Err("wallet_wiped_error".to_string());
+            }
+            if attempts.save(attempts_path).is_err() {
+                return Err("io_error".to_string());
+            }
+            Err("wrong_password_error".to_string())
+        }
+        Err(err) => Err(load_error_to_string(err)),
+    }
+}
+
+/// Maps an error that occurred while loading the key hierarchy
+/// to the error string expected by the frontend.
+fn load_error_to_string(err: Box<dyn Error>) -> String {
+    if err.is::<std::io::Error>() {
+        "io_error".to_string()
+    } else if err.is::<WrongPasswordError>() {
+        "wrong_password_error".to_string()
+    } else if err.is::<CorruptedFileError>() {
+        "corrupted_file_error".to_string()
+    } else {
+        "other_error".to_string()
+    }
+}
+
+/**
+ * Key creation
+ */
+/// Starts a new wallet setup session and returns its mnemonic.
+/// A previous session is discarded.
+#[tauri::command]
+fn generate_mnemonic(setup: State<'_, Setup>) -> String {
+    let session = WalletSetupSession::start();
+    let mnemonic = session.get_mnemonic();
+    *setup.0.lock().unwrap() = Some(session);
+    mnemonic
+}
+
+#[tauri::command]
+fn confirm_backup(setup: State<'_, Setup>) -> Result<(), String> {
+    with_setup_session(&setup, |session| session.confirm_backup())
+}
+
+#[tauri::command]
+fn send_passphrase(passphrase: String, setup: State<'_, Setup>) -> Result<(), String> {
+    with_setup_session(&setup, |session| session.set_passphrase(passphrase))
+}
+
+#[tauri::command]
+fn create_master_key(
+    password: String,
+    setup: State<'_, Setup>,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let session = match setup.0.lock().unwrap().take() {
+        Some(session) => session,
+        None => return Err(SetupError::NoSession.to_string()),
+    };
+    let mut seed = match session.finalize() {
+        Ok(seed) => seed,
+        Err(err) => return Err(err.to_string()),
+    };
+    let master_private_key = MasterPrivateKey::create_from_seed(seed);
+    seed.zeroize();
+    let path = key_path(&db);
+    if master_private_key.save(path, password).is_err() {
+        return Err("io_error".to_string());
+    }
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(())
+}
+
+/// Applies a step to the current setup session. Expired sessions are discarded.
+fn with_setup_session<F>(setup: &Setup, step: F) -> Result<(), String>
+where
+    F: FnOnce(&mut WalletSetupSession) -> Result<(), SetupError>,
+{
+    let mut setup = setup.0.lock().unwrap();
+    let session = match setup.as_mut() {
+        Some(session) => session,
+        None => return Err(SetupError::NoSession.to_string()),
+    };
+    if session.is_expired() {
+        *setup = None;
+        return Err(SetupError::Expired.to_string());
+    }
+    step(session).map_err(|err| err.to_string())
+}
+
+/**
+ * Backup
+ */
+#[tauri::command]
+fn generate_seed_shares(
+    threshold: u8,
+    share_count: u8,
+    passphrase: String,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<Vec<String>, String> {
+    let master_private_key = load_master_private_key(&db, password)?;
+    if master_private_key.seed.is_empty() {
+        return Err("seed_unavailable_error".to_string());
+    }
+    match slip39::generate_mnemonics(
+        &master_private_key.seed,
+        threshold,
+        share_count,
+        &passphrase,
+    ) {
+        Ok(mnemonics) => Ok(mnemonics
+            .iter()
+            .map(|mnemonic| mnemonic.join(" "))
+            .collect()),
+        Err(_) => Err("invalid_share_parameters_error".to_string()),
+    }
+}
+
+/// Restores the wallet from SLIP-39 shares. An existing key file is not overwritten.
+#[tauri::command]
+fn restore_from_seed_shares(
+    shares: Vec<String>,
+    passphrase: String,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let path = key_path(&db);
+    if path.exists() {
+        return Err("wallet_exists_error".to_string());
+    }
+    let mnemonics: Vec<Vec<&str>> = shares
+        .iter()
+        .map(|share| share.split_whitespace().collect())
+        .collect();
+    let mut seed = match slip39::combine_mnemonics(&mnemonics, &passphrase) {
+        Ok(seed) => seed,
+        Err(_) => return Err("invalid_share_error".to_string()),
+    };
+    let master_private_key = MasterPrivateKey::create_from_seed(&seed);
+    seed.zeroize();
+    if master_private_key.save(path, password).is_err() {
+        return Err("io_error".to_string());
+    }
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(())
+}
+
+/// Loads the stores of the open wallet that are part of a backup.
+fn load_wallet_stores(db: &Database) -> Result<WalletStores, Box<dyn Error>> {
+    Ok(WalletStores {
+        watch_list: WatchList::load(watch_list_path(db))?,
+        labels: Labels::load(labels_path(db))?,
+        settings: WalletSettings::load(settings_path(db))?,
+        contacts: Contacts::load(contacts_path(db))?,
+        frozen: FrozenUTXOs::load(frozen_path(db))?,
+        payment_requests: PaymentRequests::load(payment_requests_path(db))?,
+        multisig_accounts: MultisigAccounts::load(multisig_path(db))?,
+    })
+}
+
+/// Saves the stores of a backup as the stores of the open wallet.
+fn save_wallet_stores(db: &Database, stores: &WalletStores) -> Result<(), Box<dyn Error>> {
+    stores.watch_list.save(watch_list_path(db))?;
+    stores.labels.save(labels_path(db))?;
+    stores.settings.save(settings_path(db))?;
+    stores.contacts.save(contacts_path(db))?;
+    stores.frozen.save(frozen_path(db))?;
+    stores.payment_requests.save(payment_requests_path(db))?;
+    stores.multisig_accounts.save(multisig_path(db))?;
+    Ok(())
+}
+
+/// Exports the key hierarchy and the stores of the open wallet, such as the labels,
+/// the contacts and the settings, to an archive that is encrypted with the wallet password.
+#[tauri::command]
+fn export_backup(path: String, password: String, db: State<'_, Database>) -> Result<(), String> {
+    let key_path = key_path(&db);
+    let master_private_key = load_master_private_key(&db, password.clone())?;
+    let stores = match load_wallet_stores(&db) {
+        Ok(stores) => stores,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let attempts = match PasswordAttempts::load(attempts_path(&db)) {
+        Ok(attempts) => attempts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let kdf_params = MasterPrivateKey::read_kdf_params(&key_path).unwrap_or_default();
+    let backup = WalletBackup::create(
+        master_private_key,
+        stores,
+        kdf_params,
+        attempts.get_wipe_after(),
+    );
+    match backup.save(path, password) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Restores the open wallet from a backup. An existing key file is not overwritten.
+#[tauri::command]
+fn import_backup(path: String, password: String, db: State<'_, Database>) -> Result<(), String> {
+    let key_path = key_path(&db);
+    if key_path.exists() {
+        return Err("wallet_exists_error".to_string());
+    }
+    let backup = match WalletBackup::load(path, password.clone()) {
+        Ok(backup) => backup,
+        Err(err) if err.is::<WalletError>() => return Err(err.to_string()),
+        Err(err) => return Err(load_error_to_string(err)),
+    };
+    if save_wallet_stores(&db, &backup.stores).is_err() {
+        return Err("io_error".to_string());
+    }
+    let mut attempts = PasswordAttempts::default();
+    attempts.set_wipe_after(backup.wipe_after);
+    if attempts.save(attempts_path(&db)).is_err() {
+        return Err("io_error".to_string());
+    }
+    let master_private_key = &backup.master_private_key;
+    if master_private_key
+        .save_with_params(key_path, password, &backup.kdf_params)
+        .is_err()
+    {
+        return Err("io_error".to_string());
+    }
+    let master_public_key = MasterPublicKey::create_from_key(master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(())
+}
+
+#[tauri::command]
+fn derive_child_mnemonic(
+    index: u32,
+    word_count: Option<usize>,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let master_private_key = load_master_private_key(&db, password)?;
+    match bip85::derive_mnemonic(
+        &master_private_key.private_key,
+        word_count.unwrap_or(24),
+        index,
+    ) {
+        Ok(mnemonic) => Ok(mnemonic.join(" ")),
+        Err(_) => Err("invalid_word_count_error".to_string()),
+    }
+}
+
+/**
+ * Messages
+ */
+#[tauri::command]
+fn get_message_catalog(locale: String) -> HashMap<String, String> {
+    messages::get_message_catalog(&locale)
+}
+
+/**
+ * Wallets
+ */
+#[tauri::command]
+fn list_wallets() -> Vec<String> {
+    WalletManager::new(dirs::home_dir().unwrap()).list_wallets()
+}
+
+#[tauri::command]
+fn create_wallet(name: String, db: State<'_, Database>) -> Result<(), String> {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    if let Err(err) = manager.create_wallet(&name) {
+        return Err(err.to_string());
+    }
+    // the key file is written by the subsequent key creation
+    select_wallet(&name, &db);
+    Ok(())
+}
+
+#[tauri::command]
+fn open_wallet(name: String, password: String, db: State<'_, Database>) -> Result<(), String> {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    if WalletManager::validate_name(&name).is_err() || !manager.exists(&name) {
+        return Err("wallet_not_found_error".to_string());
+    }
+    let master_private_key = load_with_attempt_limit(
+        &manager.key_path(&name),
+        &manager.auxiliary_path(&name, ATTEMPTS_EXTENSION),
+        password,
+    )?;
+    select_wallet(&name, &db);
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(())
+}
+
+/// Opens the specified wallet without the password, using its saved public key hierarchy.
+/// The balances and the transactions can be shown, but spending requires the password.
+#[tauri::command]
+fn open_wallet_watch_only(name: String, db: State<'_, Database>) -> Result<(), String> {
+    let manager = WalletManager::new(dirs::home_dir().unwrap());
+    if WalletManager::validate_name(&name).is_err() || !manager.exists(&name) {
+        return Err("wallet_not_found_error".to_string());
+    }
+    select_wallet(&name, &db);
+    if !load_master_public_key(&db) {
+        return Err("public_key_unavailable_error".to_string());
+    }
+    Ok(())
+}
+
+/// Returns true if the public key hierarchy of the open wallet is loaded,
+/// e.g., from the saved hierarchy at startup.
+#[tauri::command]
+fn is_master_public_key_loaded(db: State<'_, Database>) -> bool {
+    db.0.lock().unwrap().contains_key("master_public_key")
+}
+
+#[tauri::command]
+fn get_wallet_name(db: State<'_, Database>) -> String {
+    wallet_name(&db)
+}
+
+/// Makes the specified wallet the open wallet and
+/// discards the state of the previously open wallet.
+fn select_wallet(name: &str, db: &Database) {
+    let mut db = db.0.lock().unwrap();
+    db.clear();
+    db.insert("wallet_name".to_string(), bincode::serialize(name).unwrap());
+}
+
+/**
+ * Keys and transaction
+ */
+#[tauri::command]
+fn does_master_key_exist(db: State<'_, Database>) -> bool {
+    key_path(&db).exists()
+}
+
+#[tauri::command]
+fn load_master_key(password: String, db: State<'_, Database>) -> Result<(), String> {
+    let master_private_key = load_master_private_key(&db, password)?;
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(())
+}
+
+/// Checks the password of the open wallet without loading the key hierarchy,
+/// so that a wrong password is reported quickly.
+#[tauri::command]
+fn check_password(password: String, db: State<'_, Database>) -> Result<(), String> {
+    let path = key_path(&db);
+    with_attempt_limit(&path, &attempts_path(&db), || {
+        MasterPrivateKey::check_password(&path, password)
+    })
+}
+
+/// Checks the key file of the open wallet. A damaged file is reported as
+/// `corrupted_file_error` and a wrong password as `wrong_password_error`.
+#[tauri::command]
+fn verify_wallet_file(password: String, db: State<'_, Database>) -> Result<(), String> {
+    let path = key_path(&db);
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if encryption::verify_checksum(&bytes).is_err() {
+        return Err("corrupted_file_error".to_string());
+    }
+    load_master_private_key(&db, password)?;
+    Ok(())
+}
+
+/// Returns the number of failed password attempts, the number of remaining
+/// attempts before the wallet is wiped and the time until the next attempt.
+#[tauri::command]
+fn get_password_attempt_status(db: State<'_, Database>) -> Result<AttemptStatus, String> {
+    match PasswordAttempts::load(attempts_path(&db)) {
+        Ok(attempts) => Ok(attempts.status(unix_now())),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Sets the number of failed password attempts after which the wallet is wiped.
+/// The wallet is never wiped if no number is specified.
+#[tauri::command]
+fn set_wipe_policy(
+    wipe_after: Option<u32>,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    load_master_private_key(&db, password)?;
+    let attempts_path = attempts_path(&db);
+    let mut attempts = match PasswordAttempts::load(&attempts_path) {
+        Ok(attempts) => attempts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    attempts.set_wipe_after(wipe_after);
+    match attempts.save(&attempts_path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+#[tauri::command]
+fn change_password(
+    old_password: String,
+    new_password: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let path = key_path(&db);
+    let master_private_key = load_master_private_key(&db, old_password)?;
+    // the key hierarchy is encrypted with a fresh salt and replaces the file atomically
+    if master_private_key
+        .save(&path, new_password.clone())
+        .is_err()
+    {
+        return Err("io_error".to_string());
+    }
+    // the old password must not unlock the previous versions of the file
+    if MasterPrivateKey::discard_backups(&path).is_err() {
+        return Err("io_error".to_string());
+    }
+    let name = wallet_name(&db);
+    if keychain::get_password(&name).is_some()
+        && keychain::remember_password(&name, &new_password).is_err()
+    {
+        return Err("keychain_error".to_string());
+    }
+    Ok(())
+}
+
+/// Stores the password in the keychain of the operating system,
+/// so that it does not have to be entered for every transaction.
+#[tauri::command]
+fn remember_password(password: String, db: State<'_, Database>) -> Result<(), String> {
+    load_master_private_key(&db, password.clone())?;
+    match keychain::remember_password(&wallet_name(&db), &password) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("keychain_error".to_string()),
+    }
+}
+
+#[tauri::command]
+fn forget_password(db: State<'_, Database>) -> Result<(), String> {
+    match keychain::forget_password(&wallet_name(&db)) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("keychain_error".to_string()),
+    }
+}
+
+#[tauri::command]
+fn is_password_remembered(db: State<'_, Database>) -> bool {
+    keychain::get_password(&wallet_name(&db)).is_some()
+}
+
+/// Returns the entered password or, if none was entered,
+/// the password that is stored in the keychain.
+fn resolve_password(password: Option<String>, db: &Database) -> String {
+    match password {
+        Some(password) if !password.is_empty() => password,
+        _ => keychain::get_password(&wallet_name(db)).unwrap_or_default(),
+    }
+}
+
+/// Returns the security level of the key derivation of the wallet file.
+#[tauri::command]
+fn get_security_level(db: State<'_, Database>) -> SecurityLevel {
+    match MasterPrivateKey::read_kdf_params(key_path(&db)) {
+        Some(params) => SecurityLevel::from_kdf_params(&params),
+        None => SecurityLevel::Low,
+    }
+}
+
+/// Re-encrypts the wallet file with the parameters of the specified security level.
+#[tauri::command]
+fn set_security_level(
+    level: SecurityLevel,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let path = key_path(&db);
+    let master_private_key = load_master_private_key(&db, password.clone())?;
+    if master_private_key
+        .save_with_params(path, password, &level.kdf_params())
+        .is_err()
+    {
+        return Err("io_error".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_accounts_overview(include_archived: Option<bool>, db: State<'_, Database>) -> String {
+    if !db.0.lock().unwrap().contains_key("master_public_key") {
+        return "".to_string();
+    }
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    master_pub_key.jsonify(include_archived.unwrap_or(false))
+}
+
+/// Returns the balance of the account broken down into the confirmed balance, the
+/// unconfirmed incoming payments, the unconfirmed change and the spendable balance.
+#[tauri::command]
+async fn get_account_balance(
+    coin_type_index: u32,
+    account_index: u32,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<AccountBalance, String> {
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let frozen = match FrozenUTXOs::load(frozen_path(&db)) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if let Ok(cache) = SyncCache::load(sync_cache_path(&db)) {
+        if let Some(snapshot) = cache.get_fresh(coin_type_index, account_index, unix_now()) {
+            return Ok(AccountBalance::create(
+                &snapshot.utxos,
+                &snapshot.transactions,
+                &frozen,
+            ));
+        }
+    }
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
+    let address_paths = master_pub_key.get_address_paths(coin_type_index);
+    // the transactions are needed to tell unconfirmed change from incoming payments
+    match networking::sync::sync_account(
+        backend.0.as_ref(),
+        addresses.clone(),
+        &address_paths,
+        unix_now(),
+    )
+    .await
+    {
+        Ok((snapshot, _)) => Ok(AccountBalance::create(
+            &snapshot.utxos,
+            &snapshot.transactions,
+            &frozen,
+        )),
+        // the stored balance is served if the backend cannot be queried, e.g., offline
+        Err(err) => match stored_account(&db, coin_type_index, account_index) {
+            Some(account) => {
+                let simple_txs = networking::transaction::simplify_transactions(
+                    account.transactions,
+                    &addresses,
+                    &address_paths,
+                );
+                Ok(AccountBalance::create(&account.utxos, &simple_txs, &frozen))
+            }
+            None => Err(backend_error_to_string(err.as_ref())),
+        },
+    }
+}
+
+/// Returns the details of an address of the wallet, i.e., its balance, its transactions,
+/// its first and last use and the derivation path of its key.
+#[tauri::command]
+async fn get_address_details(
+    address: String,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<AddressDetails, String> {
+    let address = match Address::from_str(&address) {
+        Ok(address) => address,
+        Err(_) => return Err("invalid_address_error".to_string()),
+    };
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let address_paths = master_pub_key.get_address_paths(address.coin_type_index());
+    let (account_index, path) = match address_paths.get(&address.to_string()) {
+        Some(account_path) => account_path.clone(),
+        None => return Err("address_not_found_error".to_string()),
+    };
+    let utxos = match backend.0.get_address_utxos(&address).await {
+        Ok(utxos) => utxos,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    let txs = match backend.0.get_address_transactions(&address).await {
+        Ok(txs) => txs,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    Ok(AddressDetails::create(
+        &address.to_string(),
+        account_index,
+        &path,
+        &utxos,
+        &txs,
+    ))
+}
+
+/// Returns the UTXOs of the account, so the coins to spend can be chosen manually.
+#[tauri::command]
+async fn list_utxos(
+    coin_type_index: u32,
+    account_index: u32,
+    db: State<'_, Database>,
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<Vec<AccountUTXO>, String> {
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
+    let address_utxos =
+        match networking::utxo::get_addresses_received_utxos(backend.0.as_ref(), addresses).await {
+            Ok(address_utxos) => address_utxos,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+    let frozen = match FrozenUTXOs::load(frozen_path(&db)) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let labels = match Labels::load(labels_path(&db)) {
+        Ok(labels) => labels,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let reservation_table = reservations.0.lock().unwrap();
+    let utxos = address_utxos
+        .into_iter()
+        .map(|(address, utxo)| AccountUTXO {
+            reserved: reservation_table.is_outpoint_reserved(&utxo.txid, utxo.vout),
+            frozen: frozen.is_frozen(&utxo.txid, utxo.vout),
+            label: labels
+                .get(LabelType::Output, &format!("{}:{}", utxo.txid, utxo.vout))
+                .map(|label| label.to_string()),
+            txid: utxo.txid,
+            vout: utxo.vout,
+            value: utxo.value,
+            address: address.to_string(),
+            confirmed: utxo.status.confirmed,
+            block_height: utxo.status.block_height,
+        })
+        .collect();
+    Ok(utxos)
+}
+
+/// Freezes the specified UTXO, so it is not spent until it is unfrozen.
+#[tauri::command]
+fn freeze_utxo(txid: String, vout: u32, db: State<'_, Database>) -> Result<(), String> {
+    let path = frozen_path(&db);
+    let mut frozen = match FrozenUTXOs::load(&path) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    frozen.freeze(&txid, vout);
+    match frozen.save(&path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Returns the coin selection strategy of the current wallet.
+#[tauri::command]
+fn get_coin_selection_strategy(db: State<'_, Database>) -> Result<String, String> {
+    resolve_strategy(None, &db).map(|strategy| strategy.to_string())
+}
+
+/// Sets the coin selection strategy of the current wallet.
+#[tauri::command]
+fn set_coin_selection_strategy(strategy: String, db: State<'_, Database>) -> Result<(), String> {
+    let strategy = resolve_strategy(Some(strategy), &db)?;
+    let path = settings_path(&db);
+    let mut settings = match WalletSettings::load(&path) {
+        Ok(settings) => settings,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    settings.coin_selection_strategy = strategy;
+    match settings.save(&path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Returns the minimum numbers of confirmations of the current wallet.
+#[tauri::command]
+fn get_confirmation_policy(db: State<'_, Database>) -> Result<ConfirmationPolicy, String> {
+    resolve_confirmation_policy(None, None, &db)
+}
+
+/// Sets the minimum number of confirmations of received UTXOs and of change
+/// before they are selected to fund a transaction.
+#[tauri::command]
+fn set_confirmation_policy(
+    min_confirmations: u32,
+    min_change_confirmations: u32,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let path = settings_path(&db);
+    let mut settings = match WalletSettings::load(&path) {
+        Ok(settings) => settings,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    settings.confirmation_policy = ConfirmationPolicy {
+        min_confirmations,
+        min_change_confirmations,
+    };
+    match settings.save(&path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Returns the network settings.
+#[tauri::command]
+fn get_network_settings() -> Result<NetworkSettings, String> {
+    match NetworkSettings::load(network_settings_path()) {
+        Ok(settings) => Ok(settings),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Selects the backend that the wallet queries, i.e., mempool.space, a compatible API
+/// or an Electrum server.
+/// The backend is used immediately and saved in the network settings.
+#[tauri::command]
+fn set_backend_settings(
+    backend_settings: BackendSettings,
+    backend: State<'_, Backend>,
+) -> Result<(), String> {
+    let path = network_settings_path();
+    let mut settings = match NetworkSettings::load(&path) {
+        Ok(settings) => settings,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    settings.backend = backend_settings;
+    if settings.validate().is_err() {
+        return Err("invalid_server_url_error".to_string());
+    }
+    if settings.save(&path).is_err() {
+        return Err("io_error".to_string());
+    }
+    backend
+        .0
+        .select(networking::backend::create_backend(&settings));
+    Ok(())
+}
+
+/// Selects the test network that the testnet coin type refers to, i.e., testnet, signet
+/// or a local regtest network.
+/// The test network is used immediately and saved in the network settings.
+#[tauri::command]
+fn set_test_network(test_network: TestNetwork, backend: State<'_, Backend>) -> Result<(), String> {
+    let path = network_settings_path();
+    let mut settings = match NetworkSettings::load(&path) {
+        Ok(settings) => settings,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    settings.test_network = test_network;
+    if settings.save(&path).is_err() {
+        return Err("io_error".to_string());
+    }
+    network::select_test_network(test_network);
+    backend
+        .0
+        .select(networking::backend::create_backend(&settings));
+    Ok(())
+}
+
+/// Sets the API URLs that transactions are broadcast to in addition to the backend,
+/// e.g., Blockstream or the Esplora instance of a node.
+/// The endpoints are used immediately and saved in the network settings.
+#[tauri::command]
+fn set_broadcast_endpoints(
+    broadcast_endpoints: BroadcastEndpoints,
+    backend: State<'_, Backend>,
+) -> Result<(), String> {
+    let path = network_settings_path();
+    let mut settings = match NetworkSettings::load(&path) {
+        Ok(settings) => settings,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    settings.broadcast_endpoints = broadcast_endpoints;
+    if settings.validate().is_err() {
+        return Err("invalid_server_url_error".to_string());
+    }
+    if settings.save(&path).is_err() {
+        return Err("io_error".to_string());
+    }
+    backend
+        .0
+        .select(networking::backend::create_backend(&settings));
+    Ok(())
+}
+
+/// Sets the sources of the recommended fees that are queried if the backend cannot
+/// provide them, i.e., Esplora APIs or Bitcoin Core nodes.
+/// The sources are used immediately and saved in the network settings.
+#[tauri::command]
+fn set_fee_fallbacks(
+    fee_fallbacks: FeeFallbacks,
+    backend: State<'_, Backend>,
+) -> Result<(), String> {
+    let path = network_settings_path();
+    let mut settings = match NetworkSettings::load(&path) {
+        Ok(settings) => settings,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    settings.fee_fallbacks = fee_fallbacks;
+    if settings.validate().is_err() {
+        return Err("invalid_server_url_error".to_string());
+    }
+    if settings.save(&path).is_err() {
+        return Err("io_error".to_string());
+    }
+    backend
+        .0
+        .select(networking::backend::create_backend(&settings));
+    Ok(())
+}
+
+/// Enables or disables the offline mode. In the offline mode, the backend is never
+/// queried, so the wallet can be used as the signer of an air-gapped machine.
+/// The mode is used immediately and saved in the network settings.
+#[tauri::command]
+fn set_offline_mode(offline: bool, backend: State<'_, Backend>) -> Result<(), String> {
+    let path = network_settings_path();
+    let mut settings = match NetworkSettings::load(&path) {
+        Ok(settings) => settings,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    settings.offline = offline;
+    if settings.save(&path).is_err() {
+        return Err("io_error".to_string());
+    }
+    backend
+        .0
+        .select(networking::backend::create_backend(&settings));
+    Ok(())
+}
+
+/// Sets the limit of the requests to the backend, so public APIs are not queried
+/// excessively when a large wallet is synchronized.
+/// The limit is used immediately and saved in the network settings.
+#[tauri::command]
+fn set_rate_limit(rate_limit: RateLimit) -> Result<(), String> {
+    if !rate_limit.is_valid() {
+        return Err("invalid_rate_limit_error".to_string());
+    }
+    let path = network_settings_path();
+    let mut settings = match NetworkSettings::load(&path) {
+        Ok(settings) => settings,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    settings.rate_limit = rate_limit;
+    if settings.save(&path).is_err() {
+        return Err("io_error".to_string());
+    }
+    rate_limit::set_rate_limit(rate_limit);
+    Ok(())
+}
+
+/// Tests whether the backend of the settings can be reached, without selecting it.
+/// Returns the height of the most recent block of the network.
+#[tauri::command]
+async fn test_backend_connection(
+    backend_settings: BackendSettings,
+    testnet: bool,
+) -> Result<u32, String> {
+    let mut settings = NetworkSettings::load(network_settings_path()).unwrap_or_default();
+    settings.backend = backend_settings;
+    if settings.validate().is_err() {
+        return Err("invalid_server_url_error".to_string());
+    }
+    let backend = networking::backend::create_settings_backend(&settings);
+    match backend.get_tip_height(testnet).await {
+        Ok(tip_height) => Ok(tip_height),
+        Err(_) => Err("connection_error".to_string()),
+    }
+}
+
+/// Unfreezes the specified UTXO, so it can be spent again.
+#[tauri::command]
+fn unfreeze_utxo(txid: String, vout: u32, db: State<'_, Database>) -> Result<(), String> {
+    let path = frozen_path(&db);
+    let mut frozen = match FrozenUTXOs::load(&path) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    frozen.unfreeze(&txid, vout);
+    match frozen.save(&path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Returns the balance of the account without the frozen UTXOs.
+#[tauri::command]
+async fn get_spendable_balance(
+    coin_type_index: u32,
+    account_index: u32,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<u64, String> {
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
+    let frozen = match FrozenUTXOs::load(frozen_path(&db)) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let utxos = match networking::utxo::get_addresses_utxos(backend.0.as_ref(), addresses).await {
+        Ok(utxos) => utxos,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    Ok(utxos
+        .iter()
+        .filter(|utxo| !frozen.is_frozen(&utxo.txid, utxo.vout))
+        .map(|utxo| utxo.value)
+        .sum())
+}
+
+/// Returns the transactions of the account. If a fiat currency is specified, the value
+/// of each confirmed mainnet transaction at its block time is resolved in the currency.
+/// Values that cannot be resolved, e.g., because the price API is unavailable, are omitted.
+#[tauri::command]
+async fn get_simple_transactions(
+    coin_type_index: u32,
+    account_index: u32,
+    fiat_currency: Option<String>,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<Vec<SimplifiedTransaction>, String> {
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
+    let address_paths = master_pub_key.get_address_paths(coin_type_index);
+    let labels = match Labels::load(labels_path(&db)) {
+        Ok(labels) => labels,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let cached = SyncCache::load(sync_cache_path(&db))
+        .ok()
+        .and_then(|cache| {
+            cache
+                .get_fresh(coin_type_index, account_index, unix_now())
+                .map(|snapshot| snapshot.transactions.clone())
+        });
+    let res = match cached {
+        Some(simple_txs) => Ok(simple_txs),
+        None => {
+            networking::transaction::get_addresses_simple_transactions(
+                backend.0.as_ref(),
+                addresses.clone(),
+                &address_paths,
+            )
+            .await
+        }
+    };
+    // the stored transactions are served if the backend cannot be queried, e.g., offline,
+    // and the error is not kept across the resolution of the fiat values
+    let mut simple_txs = match res.ok() {
+        Some(simple_txs) => simple_txs,
+        None => match stored_account(&db, coin_type_index, account_index) {
+            Some(account) => networking::transaction::simplify_transactions(
+                account.transactions,
+                &addresses,
+                &address_paths,
+            ),
+            None => return Err("io_error".to_string()),
+        },
+    };
+    let contacts = match Contacts::load(contacts_path(&db)) {
+        Ok(contacts) => contacts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let contact_addresses = contacts.get_address_map();
+    // the confirmations are omitted if the height of the most recent block is unknown
+    let tip_height = backend.0.get_tip_height(coin_type_index == 1).await.ok();
+    for simple_tx in simple_txs.iter_mut() {
+        if let Some(tip_height) = tip_height {
+            simple_tx.set_confirmations(tip_height);
+        }
+        simple_tx.memo = labels
+            .get(LabelType::Tx, &simple_tx.txid)
+            .map(|memo| memo.to_string());
+        simple_tx.contact = simple_tx
+            .counterparties
+            .iter()
+            .find_map(|address| contact_addresses.get(address))
+            .map(|contact| contact.name.clone());
+    }
+    if let Some(currency) = fiat_currency {
+        resolve_fiat_values(coin_type_index, &currency, &mut simple_txs).await?;
+    }
+    Ok(simple_txs)
+}
+
+/// Exports the transaction history of the account to the file in the format, "csv" or
+/// "json". If a fiat currency is specified, the fiat values of the transactions are exported.
+#[tauri::command]
+async fn export_transactions(
+    coin_type_index: u32,
+    account_index: u32,
+    format: String,
+    path: String,
+    fiat_currency: Option<String>,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<(), String> {
+    let format = match ExportFormat::from_str(&format) {
+        Ok(format) => format,
+        Err(_) => return Err("invalid_export_format_error".to_string()),
+    };
+    let simple_txs = get_simple_transactions(
+        coin_type_index,
+        account_index,
+        fiat_currency.clone(),
+        db,
+        backend,
+    )
+    .await?;
+    let exported = export::export(&simple_txs, format, fiat_currency.as_deref());
+    match file::write_atomic(path, exported.as_bytes()) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Returns the details of the transaction with the specified ID, i.e., the decoded
+/// transaction where the inputs and outputs that belong to the wallet are marked.
+#[tauri::command]
+async fn get_transaction_details(
+    coin_type_index: u32,
+    txid: String,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<TransactionDetails, String> {
+    let testnet = coin_type_index == 1;
+    let tx = match backend.0.get_transaction(&txid, testnet).await {
+        Ok(tx) => tx,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    // the confirmations are omitted if the height of the most recent block is unknown
+    let tip_height = backend.0.get_tip_height(testnet).await.ok();
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let address_paths = master_pub_key.get_address_paths(coin_type_index);
+    let labels = match Labels::load(labels_path(&db)) {
+        Ok(labels) => labels,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let label = labels
+        .get(LabelType::Tx, &txid)
+        .map(|label| label.to_string());
+    Ok(TransactionDetails::create(
+        &tx,
+        &address_paths,
+        tip_height,
+        label,
+    ))
+}
+
+/// Checks if unconfirmed transactions of the account pay less than the minimum fee
+/// of the mempool. For every such transaction, a warning event is emitted.
+#[tauri::command]
+async fn check_pending_transactions(
+    coin_type_index: u32,
+    account_index: u32,
+    window: Window,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<Vec<EvictionWarning>, String> {
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
+    let res = networking::monitor::monitor_pending_transactions(
+        backend.0.as_ref(),
+        coin_type_index,
+        addresses,
+    )
+    .await;
+    let warnings = match res {
+        Ok(warnings) => warnings,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    for warning in &warnings {
+        let _ = window.emit("mempool_eviction_warning", warning.clone());
+    }
+    Ok(warnings)
+}
+
+/// Starts pushing live updates of the account to the frontend as `live_update` events,
+/// i.e., new blocks and the transactions of its addresses. The live updates of the
+/// previously watched account are stopped. After the backend is changed, the live
+/// updates are started again to watch the new backend.
+#[tauri::command]
+fn start_live_updates(
+    coin_type_index: u32,
+    account_index: u32,
+    window: Window,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+    live_updates: State<'_, LiveUpdates>,
+) -> Result<(), String> {
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
+    let backend = backend.0.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let on_event = move |event: LiveEvent| {
+            let _ = window.emit("live_update", event);
+        };
+        networking::live::run_live_updates(
+            backend.as_ref(),
+            addresses,
+            coin_type_index == 1,
+            &on_event,
+        )
+        .await;
+    });
+    if let Some(previous) = live_updates.0.lock().unwrap().replace(task) {
+        previous.abort();
+    }
+    Ok(())
+}
+
+/// Starts the background synchronization of the accounts of the open wallet.
+/// The accounts are synchronized periodically and a `sync_completed` event is emitted
+/// for every synchronized account. The balance and the transactions of an account are
+/// served from the cache while it is fresh. The confirmations of broadcast transactions
+/// are tracked as well (see `track_confirmations`).
+#[tauri::command]
+fn start_sync(
+    window: Window,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+    sync_service: State<'_, SyncService>,
+) {
+    let db = Database(db.0.clone());
+    let backend = backend.0.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        while sync_accounts(&db, backend.as_ref(), &window).await {
+            let interval = Duration::from_secs(networking::sync::SYNC_INTERVAL_SECS);
+            tokio::time::sleep(interval).await;
+        }
+    });
+    if let Some(previous) = sync_service.0.lock().unwrap().replace(task) {
+        previous.abort();
+    }
+}
+
+/// Stops the background synchronization.
+#[tauri::command]
+fn stop_sync(sync_service: State<'_, SyncService>) {
+    if let Some(task) = sync_service.0.lock().unwrap().take() {
+        task.abort();
+    }
+}
+
+/// Returns the UNIX timestamp of the last synchronization of the account,
+/// or `None` if the account was not synchronized yet.
+#[tauri::command]
+fn get_sync_status(
+    coin_type_index: u32,
+    account_index: u32,
+    db: State<'_, Database>,
+) -> Result<Option<u64>, String> {
+    match SyncCache::load(sync_cache_path(&db)) {
+        Ok(cache) => Ok(cache
+            .get(coin_type_index, account_index)
+            .map(|snapshot| snapshot.synced_at)),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Synchronizes all accounts of the open wallet that are not archived, and its watched
+/// addresses. Accounts that cannot be synchronized keep their previous snapshot.
+/// Returns false if no wallet is open, so the synchronization stops.
+async fn sync_accounts(db: &Database, backend: &dyn ChainBackend, window: &Window) -> bool {
+    let master_pub_key = match db.0.lock().unwrap().get("master_public_key") {
+        Some(master_pub_key) => MasterPublicKey::load(&master_pub_key[..]),
+        None => return false,
+    };
+    let accounts: Vec<(u32, u32)> = master_pub_key
+        .purpose
+        .coin_types
+        .iter()
+        .flat_map(|(coin_type_index, coin_type)| {
+            coin_type
+                .accounts
+                .iter()
+                .filter(|(_, account)| !account.archived)
+                .map(move |(account_index, _)| (*coin_type_index, *account_index))
+        })
+        .collect();
+    let path = sync_cache_path(db);
+    let mut cache = SyncCache::load(&path).unwrap_or_default();
+    for (coin_type_index, account_index) in accounts {
+        let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
+        let address_paths = master_pub_key.get_address_paths(coin_type_index);
+        let (snapshot, txs) =
+            match networking::sync::sync_account(backend, addresses, &address_paths, unix_now())
+                .await
+            {
+                Ok(synced) => synced,
+                Err(_) => continue,
+            };
+        let synced_at = snapshot.synced_at;
+        // the store is a cache of the chain data, so a failure does not stop the synchronization
+        if let Ok(mut store) = ChainStore::open(store_path(db)) {
+            let _ = store.save_account(
+                coin_type_index,
+                account_index,
+                &txs,
+                &snapshot.utxos,
+                synced_at,
+            );
+        }
+        cache.insert(coin_type_index, account_index, snapshot);
+        if cache.save(&path).is_ok() {
+            let _ = window.emit(
+                "sync_completed",
+                SyncEvent {
+                    coin_type_index,
+                    account_index,
+                    synced_at,
+                },
+            );
+        }
+    }
+    for coin_type_index in [0, 1] {
+        match watched_addresses(coin_type_index, db) {
+            Ok(addresses) if !addresses.is_empty() => {
+                let _ = sync_watched_addresses(coin_type_index, addresses, db, backend).await;
+            }
+            _ => continue,
+        }
+    }
+    track_confirmations(db, backend, window).await;
+    true
+}
+
+/// Checks the confirmations of the tracked transactions and emits a
+/// `transaction_confirmed` event for every milestone that a transaction reached.
+async fn track_confirmations(db: &Database, backend: &dyn ChainBackend, window: &Window) {
+    let path = pending_path(db);
+    let mut pending = match PendingTransactions::load(&path) {
+        Ok(pending) => pending,
+        Err(_) => return,
+    };
+    if pending.list().is_empty() {
+        return;
+    }
+    let events = match networking::tracking::check_confirmations(backend, &mut pending).await {
+        Ok(events) => events,
+        Err(_) => return,
+    };
+    // the events are emitted once the milestones are persisted, so they are not repeated
+    if pending.save(&path).is_ok() {
+        for event in events {
+            let _ = window.emit("transaction_confirmed", event);
+        }
+    }
+}
+
+/// Returns the broadcast transactions whose confirmations are tracked, i.e., that are
+/// not confirmed by 6 blocks yet.
+#[tauri::command]
+fn get_pending_transactions(db: State<'_, Database>) -> Result<Vec<PendingTransaction>, String> {
+    match PendingTransactions::load(pending_path(&db)) {
+        Ok(pending) => Ok(pending.list().to_vec()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Stops pushing live updates to the frontend.
+#[tauri::command]
+fn stop_live_updates(live_updates: State<'_, LiveUpdates>) {
+    if let Some(task) = live_updates.0.lock().unwrap().take() {
+        task.abort();
+    }
+}
+
+#[tauri::command]
+async fn get_recommended_fees(
+    coin_type_index: u32,
+    backend: State<'_, Backend>,
+) -> Result<Fees, String> {
+    match backend.0.get_recommended_fees(coin_type_index).await {
+        Ok(fees) => Ok(fees),
+        Err(err) => Err(backend_error_to_string(err.as_ref())),
+    }
+}
+
+/// Checks the backend and the broadcast endpoints of the network. Every API is pinged
+/// and its latency is measured. An API that serves the chain of another network than
+/// the coin type is reported with a `wrong_network_error`.
+#[tauri::command]
+async fn check_backend_health(
+    coin_type_index: u32,
+    backend: State<'_, Backend>,
+) -> Result<Vec<EndpointHealth>, String> {
+    let testnet = coin_type_index == 1;
+    let settings = match NetworkSettings::load(network_settings_path()) {
+        Ok(settings) => settings,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let endpoints = &settings.broadcast_endpoints;
+    let urls = if testnet {
+        &endpoints.testnet_urls
+    } else {
+        &endpoints.urls
+    };
+    let mut apis: Vec<(String, Arc<dyn ChainBackend>)> =
+        vec![("backend".to_string(), backend.0.current())];
+    for url in urls {
+        if let Ok(url) = networking::mempool::parse_api_url(url) {
+            let api = MempoolBackend::new(Some(url.clone()), Some(url.clone()));
+            apis.push((url, Arc::new(api)));
+        }
+    }
+    let mut health = Vec::new();
+    for (endpoint, api) in apis {
+        let res = networking::health::probe(api.as_ref(), testnet).await;
+        health.push(match res {
+            Ok(probe) => EndpointHealth {
+                endpoint,
+                latency_ms: Some(probe.latency_ms),
+                tip_height: Some(probe.tip_height),
+                error: None,
+            },
+            Err(err) if err.is::<WrongNetworkError>() => EndpointHealth {
+                endpoint,
+                latency_ms: None,
+                tip_height: None,
+                error: Some("wrong_network_error".to_string()),
+            },
+            Err(err) => EndpointHealth {
+                endpoint,
+                latency_ms: None,
+                tip_height: None,
+                error: Some(backend_error_to_string(err.as_ref())),
+            },
+        });
+    }
+    Ok(health)
+}
+
+/// Returns the height, the hash and the timestamp of the most recent block of the network.
+#[tauri::command]
+async fn get_chain_tip(
+    coin_type_index: u32,
+    backend: State<'_, Backend>,
+) -> Result<ChainTip, String> {
+    match backend.0.get_chain_tip(coin_type_index == 1).await {
+        Ok(tip) => Ok(tip),
+        Err(err) => Err(backend_error_to_string(err.as_ref())),
+    }
+}
+
+/// Estimates the confirmation time of a transaction with the fee rate in sat/vB
+/// from the fee histogram of the mempool. The estimate also reports the congestion
+/// of the mempool, i.e., the vsize of all unconfirmed transactions.
+#[tauri::command]
+async fn estimate_confirmation_time(
+    coin_type_index: u32,
+    fee_rate: f64,
+    backend: State<'_, Backend>,
+) -> Result<ConfirmationEstimate, String> {
+    let histogram = match backend.0.get_fee_histogram(coin_type_index == 1).await {
+        Ok(histogram) => histogram,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    Ok(networking::fee::estimate_confirmation(&histogram, fee_rate))
+}
+
+/**
+ * Account and address
+ */
+#[tauri::command]
+fn create_new_account(
+    coin_type_index: u32,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let path = key_path(&db);
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    master_private_key.add_account(coin_type_index);
+    master_private_key.save(path, password).unwrap();
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(())
+}
+
+#[tauri::command]
+fn rename_account(
+    coin_type_index: u32,
+    account_index: u32,
+    label: String,
+    color: Option<String>,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let path = key_path(&db);
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    master_private_key.rename_account(coin_type_index, account_index, label, color);
+    if master_private_key.save(path, password).is_err() {
+        return Err("io_error".to_string());
+    }
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(())
+}
+
+#[tauri::command]
+fn archive_account(
+    coin_type_index: u32,
+    account_index: u32,
+    archived: bool,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let path = key_path(&db);
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    master_private_key.set_account_archived(coin_type_index, account_index, archived);
+    if master_private_key.save(path, password).is_err() {
+        return Err("io_error".to_string());
+    }
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(())
+}
+
+#[tauri::command]
+fn export_account_xpub(
+    coin_type_index: u32,
+    account_index: u32,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let master_pub_key = match db.0.lock().unwrap().get("master_public_key") {
+        Some(master_pub_key) => MasterPublicKey::load(&master_pub_key[..]),
+        None => return Err("other_error".to_string()),
+    };
+    match master_pub_key.get_account_public_key(coin_type_index, account_index) {
+        Some(public_key) => Ok(public_key.to_base58_check()),
+        None => Err("account_not_found_error".to_string()),
+    }
+}
+
+/// Exports the extended private key of an account. Anyone who knows this key
+/// can spend the funds of the account, so the user has to confirm the export.
+#[tauri::command]
+fn export_account_xprv(
+    coin_type_index: u32,
+    account_index: u32,
+    confirmed: bool,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    if !confirmed {
+        return Err("confirmation_required_error".to_string());
+    }
+    let master_private_key = load_master_private_key(&db, password)?;
+    match master_private_key.get_account_private_key(coin_type_index, account_index) {
+        Some(private_key) => Ok(private_key.to_base58_check()),
+        None => Err("account_not_found_error".to_string()),
+    }
+}
+
+/// Derives the key at an arbitrary path, e.g., to compare addresses
+/// with wallets that use a non-standard layout.
+#[tauri::command]
+fn derive_key_at_path(
+    path: String,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<DerivedKey, String> {
+    let derivation_path = match DerivationPath::from_str(&path) {
+        Ok(derivation_path) => derivation_path,
+        Err(_) => return Err("invalid_derivation_path_error".to_string()),
+    };
+    let master_private_key = load_master_private_key(&db, password)?;
+    match master_private_key.derive_key_at_path(&derivation_path) {
+        Ok(derived_key) => Ok(derived_key),
+        Err(_) => Err("invalid_derivation_path_error".to_string()),
+    }
+}
+
+#[tauri::command]
+async fn discover_accounts(
+    coin_type_index: u32,
+    password: String,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<Vec<DiscoveredAccount>, String> {
+    let path = key_path(&db);
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    // resume an interrupted discovery
+    let scan_path = scan_progress_path(&db);
+    let mut progress = match ScanProgress::load(&scan_path) {
+        Ok(Some(progress)) if progress.coin_type_index == coin_type_index => progress,
+        Ok(_) => ScanProgress::new(coin_type_index),
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let res = networking::discovery::resume_discovery(
+        backend.0.as_ref(),
+        &master_private_key,
+        &mut progress,
+        &mut |progress| progress.save(&scan_path),
+    )
+    .await;
+    let accounts = match res {
+        Ok(accounts) => accounts,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    networking::discovery::add_discovered_accounts(
+        &mut master_private_key,
+        coin_type_index,
+        &accounts,
+    );
+    if master_private_key.save(path, password).is_err() {
+        return Err("io_error".to_string());
+    }
+    let _ = std::fs::remove_file(scan_path);
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(accounts)
+}
+
+#[tauri::command]
+fn get_new_receive_address(
+    coin_type_index: u32,
+    account_index: u32,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let address = new_receive_address(coin_type_index, account_index, password, &db)?;
+    Ok(address.to_string())
+}
+
+/// Derives the next receive address of the account and saves the key file.
+fn new_receive_address(
+    coin_type_index: u32,
+    account_index: u32,
+    password: String,
+    db: &Database,
+) -> Result<Address, String> {
+    let path = key_path(db);
+    let mut master_private_key = load_master_private_key(db, password.clone())?;
+    let keypair = master_private_key.new_receive_keypair(coin_type_index, account_index, None);
+    master_private_key.save(path, password).unwrap();
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(db, &master_public_key);
+    Ok(keypair.public_key.get_address())
+}
+
+#[tauri::command]
+fn get_all_receive_addresses(
+    coin_type_index: u32,
+    account_index: u32,
+    db: State<'_, Database>,
+) -> Vec<SimpleAddress> {
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    master_pub_key.get_all_receive_simple_addresses(coin_type_index, account_index)
+}
+
+#[tauri::command]
+async fn get_all_receive_addresses_marked(
+    coin_type_index: u32,
+    account_index: u32,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<Vec<SimpleAddress>, String> {
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let addresses = master_pub_key.get_all_receive_simple_addresses(coin_type_index, account_index);
+    // addresses that are known to be used are not queried again
+    let used_addresses = match ChainStore::open(store_path(&db)) {
+        Ok(store) => store
+            .used_addresses(coin_type_index, account_index)
+            .unwrap_or_default(),
+        Err(_) => HashSet::new(),
+    };
+    let unknown: Vec<SimpleAddress> = addresses
+        .iter()
+        .filter(|simple_address| !used_addresses.contains(&simple_address.address))
+        .cloned()
+        .collect();
+    let marked =
+        match networking::transaction::mark_addresses_as_used(backend.0.as_ref(), unknown).await {
+            Ok(marked) => marked,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+    let newly_used: Vec<String> = marked
+        .iter()
+        .filter(|simple_address| simple_address.used == Some(true))
+        .map(|simple_address| simple_address.address.clone())
+        .collect();
+    if !newly_used.is_empty() {
+        if let Ok(mut store) = ChainStore::open(store_path(&db)) {
+            let _ = store.mark_used(coin_type_index, account_index, &newly_used);
+        }
+    }
+    let mut marked = marked.into_iter();
+    Ok(addresses
+        .into_iter()
+        .map(|mut simple_address| {
+            if used_addresses.contains(&simple_address.address) {
+                simple_address.used = Some(true);
+                simple_address
+            } else {
+                marked.next().unwrap() // in the order of the queried addresses
+            }
+        })
+        .collect())
+}
+
+#[tauri::command]
+fn sign_message(
+    address: String,
+    message: String,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let address = match Address::from_str(&address) {
+        Ok(address) => address,
+        Err(_) => return Err("invalid_address_error".to_string()),
+    };
+    let master_private_key = load_master_private_key(&db, password)?;
+    match master_private_key.find_keypair(&address) {
+        Some(keypair) => Ok(message::sign_message(&keypair.private_key, &message)),
+        None => Err("address_not_found_error".to_string()),
+    }
+}
+
+#[tauri::command]
+fn verify_message(address: String, message: String, signature: String) -> Result<bool, String> {
+    let address = match Address::from_str(&address) {
+        Ok(address) => address,
+        Err(_) => return Err("invalid_address_error".to_string()),
+    };
+    match message::verify_message(&address, &message, &signature) {
+        Ok(valid) => Ok(valid),
+        Err(_) => Err("invalid_signature_error".to_string()),
+    }
+}
+
+#[tauri::command]
+fn validate_address(address: String, coin_type_index: u32) -> Result<(), String> {
+    match Address::from_str(&address) {
+        Ok(address) => {
+            if coin_type_index != address.coin_type_index() {
+                return Err("wrong_address_type_error".to_string());
+            }
+            Ok(())
+        }
+        Err(_) => Err("invalid_address_error".to_string()),
+    }
+}
+
+/// Parses a BIP-21 payment URI, e.g., from a scanned QR code, to prefill the send form.
+/// Returns an error if the address belongs to the other network.
+#[tauri::command]
+fn parse_payment_uri(uri: String, coin_type_index: u32) -> Result<PaymentUri, String> {
+    let (payment_uri, address) = match PaymentUri::parse(&uri) {
+        Ok(parsed) => parsed,
+        Err(err) => return Err(err.to_string()),
+    };
+    if coin_type_index != address.coin_type_index() {
+        return Err("wrong_address_type_error".to_string());
+    }
+    Ok(payment_uri)
+}
+
+/**
+ * Watch list
+ */
+#[tauri::command]
+fn add_watched_address(
+    address: String,
+    label: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let path = watch_list_path(&db);
+    let mut watch_list = match WatchList::load(&path) {
+        Ok(watch_list) => watch_list,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if let Err(err) = watch_list.add(&address, label) {
+        return Err(err.to_string());
+    }
+    match watch_list.save(path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+#[tauri::command]
+fn remove_watched_address(address: String, db: State<'_, Database>) -> Result<(), String> {
+    let path = watch_list_path(&db);
+    let mut watch_list = match WatchList::load(&path) {
+        Ok(watch_list) => watch_list,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if watch_list.remove(&address) && watch_list.save(path).is_err() {
+        return Err("io_error".to_string());
+    }
+    Ok(())
+}
+
+/**
+ * Contacts
+ */
+/// Adds a contact with an address or an extended public key and returns its ID.
+#[tauri::command]
+fn add_contact(
+    name: String,
+    address: String,
+    notes: String,
+    db: State<'_, Database>,
+) -> Result<u64, String> {
+    let path = contacts_path(&db);
+    let mut contacts = match Contacts::load(&path) {
+        Ok(contacts) => contacts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let id = match contacts.add(&name, &address, &notes) {
+        Ok(id) => id,
+        Err(err) => return Err(err.to_string()),
+    };
+    match contacts.save(path) {
+        Ok(()) => Ok(id),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+#[tauri::command]
+fn update_contact(
+    id: u64,
+    name: String,
+    address: String,
+    notes: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let path = contacts_path(&db);
+    let mut contacts = match Contacts::load(&path) {
+        Ok(contacts) => contacts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if let Err(err) = contacts.update(id, &name, &address, &notes) {
+        return Err(err.to_string());
+    }
+    match contacts.save(path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+#[tauri::command]
+fn remove_contact(id: u64, db: State<'_, Database>) -> Result<(), String> {
+    let path = contacts_path(&db);
+    let mut contacts = match Contacts::load(&path) {
+        Ok(contacts) => contacts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if contacts.remove(id) && contacts.save(path).is_err() {
+        return Err("io_error".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_contacts(db: State<'_, Database>) -> Result<Vec<Contact>, String> {
+    match Contacts::load(contacts_path(&db)) {
+        Ok(contacts) => Ok(contacts.list().to_vec()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Returns the address to pay the contact, so it can be used in the send flow.
+/// A contact with an extended public key gets a fresh address for every payment.
+/// Returns an error if the contact belongs to the other network.
+#[tauri::command]
+fn get_contact_address(
+    coin_type_index: u32,
+    id: u64,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let path = contacts_path(&db);
+    let mut contacts = match Contacts::load(&path) {
+        Ok(contacts) => contacts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if let Some(contact) = contacts.list().iter().find(|contact| contact.id == id) {
+        if contact.testnet != (coin_type_index == 1) {
+            return Err("invalid_address_error".to_string());
+        }
+    }
+    let address = match contacts.next_address(id) {
+        Ok(address) => address,
+        Err(err) => return Err(err.to_string()),
+    };
+    match contacts.save(path) {
+        Ok(()) => Ok(address),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Returns the contact that the address belongs to, so the send flow can show
+/// the recipient of an address that was entered or scanned.
+#[tauri::command]
+fn resolve_contact(address: String, db: State<'_, Database>) -> Result<Option<Contact>, String> {
+    match Contacts::load(contacts_path(&db)) {
+        Ok(contacts) => Ok(contacts.resolve(address.trim()).cloned()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/**
+ * Payment requests
+ */
+/// Creates a payment request for the amount (in satoshis) at a fresh receive address
+/// of the account. Any amount is requested if there is none.
+#[tauri::command]
+fn create_payment_request(
+    coin_type_index: u32,
+    account_index: u32,
+    amount: Option<u64>,
+    label: Option<String>,
+    password: String,
+    db: State<'_, Database>,
+) -> Result<PaymentRequestDetails, String> {
+    let path = payment_requests_path(&db);
+    let mut requests = match PaymentRequests::load(&path) {
+        Ok(requests) => requests,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    // the amount is checked before an address is used up
+    if amount == Some(0) {
+        return Err("invalid_amount_error".to_string());
+    }
+    let address = new_receive_address(coin_type_index, account_index, password, &db)?;
+    let request = match requests.add(
+        coin_type_index,
+        account_index,
+        &address.to_string(),
+        amount,
+        label.as_deref(),
+        unix_now(),
+    ) {
+        Ok(request) => request,
+        Err(err) => return Err(err.to_string()),
+    };
+    match requests.save(path) {
+        Ok(()) => Ok(request.details()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Returns the payment requests with the amounts that their addresses received.
+/// If the backend cannot be queried, the amounts that were received before are returned.
+#[tauri::command]
+async fn get_payment_requests(
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<Vec<PaymentRequestDetails>, String> {
+    let path = payment_requests_path(&db);
+    let mut requests = match PaymentRequests::load(&path) {
+        Ok(requests) => requests,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let addresses: Vec<Address> = requests
+        .list()
+        .iter()
+        .filter_map(|request| Address::from_str(&request.address).ok())
+        .collect();
+    let txs = networking::backend::query_addresses(&addresses, |address| {
+        backend.0.get_address_transactions(address)
+    })
+    .await
+    .ok();
+    if let Some(txs) = txs {
+        let txs: Vec<Transaction> = txs.into_iter().flatten().collect();
+        for request in requests.list_mut() {
+            request.track(&txs);
+        }
+        if requests.save(path).is_err() {
+            return Err("io_error".to_string());
+        }
+    }
+    Ok(requests
+        .list()
+        .iter()
+        .map(|request| request.details())
+        .collect())
+}
+
+#[tauri::command]
+fn remove_payment_request(id: u64, db: State<'_, Database>) -> Result<(), String> {
+    let path = payment_requests_path(&db);
+    let mut requests = match PaymentRequests::load(&path) {
+        Ok(requests) => requests,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if requests.remove(id) && requests.save(path).is_err() {
+        return Err("io_error".to_string());
+    }
+    Ok(())
+}
+
+/**
+ * Labels
+ */
+/// Sets the label of an address ("addr"), a transaction ("tx") or an output ("output").
+/// The reference of an output is its outpoint "txid:vout". An empty label removes the label.
+#[tauri::command]
+fn set_label(
+    label_type: String,
+    reference: String,
+    label: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let label_type = match LabelType::from_str(&label_type) {
+        Ok(label_type) => label_type,
+        Err(err) => return Err(err.to_string()),
+    };
+    let path = labels_path(&db);
+    let mut labels = match Labels::load(&path) {
+        Ok(labels) => labels,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if let Err(err) = labels.set(label_type, &reference, &label) {
+        return Err(err.to_string());
+    }
+    match labels.save(path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+#[tauri::command]
+fn get_labels(db: State<'_, Database>) -> Result<Vec<Label>, String> {
+    match Labels::load(labels_path(&db)) {
+        Ok(labels) => Ok(labels.get_labels()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Exports the labels to the specified file in the BIP-329 format.
+#[tauri::command]
+fn export_labels(path: String, db: State<'_, Database>) -> Result<(), String> {
+    let labels = match Labels::load(labels_path(&db)) {
+        Ok(labels) => labels,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    match file::write_atomic(path, labels.export_bip329().as_bytes()) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Imports labels in the BIP-329 format from the specified file.
+/// Returns the number of imported labels.
+#[tauri::command]
+fn import_labels(path: String, db: State<'_, Database>) -> Result<usize, String> {
+    let jsonl = match fs::read_to_string(path) {
+        Ok(jsonl) => jsonl,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let labels_path = labels_path(&db);
+    let mut labels = match Labels::load(&labels_path) {
+        Ok(labels) => labels,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let count = match labels.import_bip329(&jsonl) {
+        Ok(count) => count,
+        Err(err) => return Err(err.to_string()),
+    };
+    match labels.save(labels_path) {
+        Ok(()) => Ok(count),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+#[tauri::command]
+async fn get_watched_addresses(
+    coin_type_index: u32,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<Vec<WatchedAddressBalance>, String> {
+    let watched_addresses = match WatchList::load(watch_list_path(&db)) {
+        Ok(watch_list) => watch_list.get_addresses(coin_type_index == 1),
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let snapshot = watched_snapshot(coin_type_index, &db, backend.0.as_ref()).await?;
+    Ok(watched_addresses
+        .into_iter()
+        .map(|watched_address| {
+            let address = watched_address.get_address().to_string();
+            WatchedAddressBalance {
+                balance: snapshot.balances.get(&address).copied().unwrap_or(0),
+                watched_address,
+            }
+        })
+        .collect())
+}
+
+#[tauri::command]
+async fn get_watched_transactions(
+    coin_type_index: u32,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<Vec<SimplifiedTransaction>, String> {
+    let testnet = coin_type_index == 1;
+    let mut simple_txs = watched_snapshot(coin_type_index, &db, backend.0.as_ref())
+        .await?
+        .transactions;
+    if let Ok(tip_height) = backend.0.get_tip_height(testnet).await {
+        for simple_tx in simple_txs.iter_mut() {
+            simple_tx.set_confirmations(tip_height);
+        }
+    }
+    Ok(simple_txs)
+}
+
+/// Returns the snapshot of the watched addresses of the coin type. It is served from the
+/// cache while it is fresh, otherwise the watched addresses are synchronized.
+async fn watched_snapshot(
+    coin_type_index: u32,
+    db: &Database,
+    backend: &dyn ChainBackend,
+) -> Result<WatchedSnapshot, String> {
+    let addresses = watched_addresses(coin_type_index, db)?;
+    if let Ok(cache) = SyncCache::load(sync_cache_path(db)) {
+        if let Some(snapshot) = cache.get_fresh_watched(coin_type_index, &addresses, unix_now()) {
+            return Ok(snapshot.clone());
+        }
+    }
+    sync_watched_addresses(coin_type_index, addresses, db, backend).await
+}
+
+/// Synchronizes the watched addresses of the coin type and caches their snapshot.
+async fn sync_watched_addresses(
+    coin_type_index: u32,
+    addresses: Vec<Address>,
+    db: &Database,
+    backend: &dyn ChainBackend,
+) -> Result<WatchedSnapshot, String> {
+    let snapshot =
+        match networking::sync::sync_watched_addresses(backend, addresses, unix_now()).await {
+            Ok(snapshot) => snapshot,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+    let path = sync_cache_path(db);
+    // the snapshot is a cache of the chain data, so a failure to save it is ignored
+    let mut cache = SyncCache::load(&path).unwrap_or_default();
+    cache.insert_watched(coin_type_index, snapshot.clone());
+    let _ = cache.save(&path);
+    Ok(snapshot)
+}
+
+/// Returns the watched addresses of the coin type.
+fn watched_addresses(coin_type_index: u32, db: &Database) -> Result<Vec<Address>, String> {
+    match WatchList::load(watch_list_path(db)) {
+        Ok(watch_list) => Ok(watch_list
+            .get_addresses(coin_type_index == 1)
+            .iter()
+            .map(|watched_address| watched_address.get_address())
+            .collect()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/**
+ * Send transaction
+ */
+/// Returns the payload of an OP_RETURN output, which is either hex encoded or
+/// UTF-8 text. Returns an error if the payload exceeds the size that is relayed.
+fn parse_op_return_data(data: &str, hex_encoded: bool) -> Result<Vec<u8>, String> {
+    let bytes = if hex_encoded {
+        match hex::hex_to_bytes(data) {
+            Some(bytes) => bytes,
+            None => return Err("invalid_data_error".to_string()),
+        }
+    } else {
+        data.as_bytes().to_vec()
+    };
+    if bytes.len() > MAX_OP_RETURN_SIZE {
+        return Err("invalid_data_error".to_string());
+    }
+    Ok(bytes)
+}
+
+/// Parses the recipients of a transaction. Each address has to belong to the
+/// network of the coin type and each amount has to exceed the dust limit.
+fn parse_targets(
+    targets: &[(String, u64)],
+    coin_type_index: u32,
+) -> Result<Vec<(Address, u64)>, String> {
+    if targets.is_empty() {
+        return Err("recipient_missing_error".to_string());
+    }
+    let mut parsed_targets = Vec::new();
+    for (address, amount) in targets {
+        validate_address(address.clone(), coin_type_index)?;
+        if *amount < DUST_LIMIT {
+            return Err("amount_below_dust_error".to_string());
+        }
+        parsed_targets.push((Address::from_str(address).unwrap(), *amount));
+    }
+    Ok(parsed_targets)
+}
+
+/// Returns the locktime of a new transaction. Unless a locktime is specified,
+/// it is set to the height of the most recent block to discourage fee sniping.
+async fn resolve_locktime(
+    locktime: Option<u32>,
+    testnet: bool,
+    backend: &Backend,
+) -> Result<u32, String> {
+    if let Some(locktime) = locktime {
+        return Ok(locktime);
+    }
+    match backend.0.get_tip_height(testnet).await {
+        Ok(tip_height) => Ok(transaction::anti_fee_sniping_locktime(tip_height)),
+        Err(err) => Err(backend_error_to_string(err.as_ref())),
+    }
+}
+
+/// A signed transaction that is broadcast once its preview is confirmed.
+/// The spent UTXOs and the change key remain reserved until it is broadcast or discarded.
+struct PreparedTransaction {
+    coin_type_index: u32,
+    account_index: u32,
+    transaction: Transaction,
+    change_index: Option<u32>,
+    change_address: Option<String>,
+    total_amount: u64,
+    request_key: String,
+    memo: Option<String>,
+    selected_utxos: Vec<UTXOBox>, // empty if the transaction was taken from the broadcast journal
+    _lease: Lease,
+}
+
+/// Builds and signs the transaction that sends the amounts to the addresses of the targets.
+/// If a previous attempt already signed this payment, the journaled transaction is returned
+/// instead of constructing a new one. Its inputs and change key are reserved as well.
+async fn prepare_send(
+    request: SendRequest,
+    master_private_key: &mut MasterPrivateKey,
+    db: &Database,
+    reservations: &Reservations,
+    backend: &Backend,
+) -> Result<PreparedTransaction, String> {
+    let request_key = BroadcastJournal::request_key(&request);
+    let SendRequest {
+        coin_type_index,
+        account_index,
+        targets,
+        fee,
+        rbf,
+        locktime,
+        data,
+        data_hex,
+        outpoints,
+        strategy,
+        min_confirmations,
+        min_change_confirmations,
+        memo,
+    } = request;
+    let testnet = coin_type_index == 1;
+    let parsed_targets = parse_targets(&targets, coin_type_index)?;
+    let amount: u64 = targets.iter().map(|(_, amount)| amount).sum();
+    let data = match data {
+        Some(data) => Some(parse_op_return_data(&data, data_hex.unwrap_or(false))?),
+        None => None,
+    };
+    let journal = match BroadcastJournal::load(journal_path(db)) {
+        Ok(journal) => journal,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if let Some(entry) = journal.get(&request_key).cloned() {
+        let outpoints: Vec<(String, u32)> = entry
+            .transaction
+            .vin
+            .iter()
+            .map(|tx_in| (tx_in.txid.clone(), tx_in.vout))
+            .collect();
+        let change_key = entry
+            .change_index
+            .map(|index| (coin_type_index, account_index, index));
+        let lease_id = match reservations
+            .0
+            .lock()
+            .unwrap()
+            .reserve_outpoints(&outpoints, change_key)
+        {
+            Ok(lease_id) => lease_id,
+            Err(err) => return Err(err.to_string()),
+        };
+        let lease = Lease::new(reservations.0.clone(), lease_id);
+        let change_address = entry.change_index.map(|index| {
+            master_private_key
+                .new_change_keypair(coin_type_index, account_index, Some(index))
+                .public_key
+                .get_address()
+                .to_string()
+        });
+        return Ok(PreparedTransaction {
+            coin_type_index,
+            account_index,
+            transaction: entry.transaction,
+            change_index: entry.change_index,
+            change_address,
+            total_amount: entry.total_amount,
+            request_key,
+            memo,
+            selected_utxos: Vec::new(),
+            _lease: lease,
+        });
+    }
+
+    let locktime = resolve_locktime(locktime, testnet, backend).await?;
+    let keypairs = master_private_key.get_all_keypairs(coin_type_index, account_index);
+    let utxos = match networking::utxo::get_keypairs_boxed_utxos(backend.0.as_ref(), keypairs).await
+    {
+        Ok(utxos) => utxos,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    // the specified outpoints have to satisfy the confirmation policy as well
+    let policy = resolve_confirmation_policy(min_confirmations, min_change_confirmations, db)?;
+    let change_addresses = change_addresses(master_private_key, coin_type_index, account_index);
+    let utxos = eligible_utxos(policy, utxos, change_addresses, testnet, backend).await?;
+    let frozen = match FrozenUTXOs::load(frozen_path(db)) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let output_types: Vec<ScriptType> = parsed_targets
+        .iter()
+        .map(|(address, _)| address.script_type())
+        .collect();
+    let mut targets = parsed_targets;
+    let mut change_index = None;
+    let mut change_address = None;
+    // the OP_RETURN output carries no value, but its size increases the fee
+    let data_fee = match &data {
+        Some(data) => fee_estimation::op_return_output_size(data.len()) as u64 * fee,
+        None => 0,
+    };
+    let strategy = resolve_strategy(strategy, db)?;
+    // the coins and the change key are selected and reserved under the same lock,
+    // so a concurrent send can neither spend the coins nor reuse the change key
+    let (selected_coins, lease_id) = {
+        let mut reservation_table = reservations.0.lock().unwrap();
+        // a specified outpoint that is reserved by another send is unavailable
+        let utxos = reservation_table.unreserved_utxos(utxos);
+        let res = match &outpoints {
+            Some(outpoints) => coin_selection::manual::select_coins(
+                utxos,
+                &frozen,
+                outpoints,
+                &output_types,
+                amount + data_fee,
+                fee,
+            ),
+            None => coin_selection::strategy::select_coins(
+                strategy,
+                utxos,
+                &frozen,
+                None,
+                &output_types,
+                amount + data_fee,
+                fee,
+            ),
+        };
+        let selected_coins = match res {
+            Ok(selected_coins) => selected_coins,
+            Err(err) => return Err(err.to_string()),
+        };
+        let mut change_key = None;
+        if let Some(change) = selected_coins.change {
+            let first_index = master_private_key.next_change_index(coin_type_index, account_index);
+            let index =
+                reservation_table.next_change_index(coin_type_index, account_index, first_index);
+            let change_keypair =
+                master_private_key.new_change_keypair(coin_type_index, account_index, Some(index));
+            change_index = Some(index);
+            change_key = Some((coin_type_index, account_index, index));
+            change_address = Some(change_keypair.public_key.get_address().to_string());
+            targets.push((change_keypair.public_key.get_address(), change));
+        }
+        match reservation_table.reserve(&selected_coins.selected_utxos, change_key) {
+            Ok(lease_id) => (selected_coins, lease_id),
+            Err(err) => return Err(err.to_string()),
+        }
+    };
+    // the reservations are released when the lease is dropped
+    let lease = Lease::new(reservations.0.clone(), lease_id);
+    let selected_utxos = selected_coins.selected_utxos;
+    // the change output follows the outputs of the recipients
+    let change_output = selected_coins.change.map(|_| targets.len() - 1);
+    let mut tx =
+        match Transaction::create_with_options(&selected_utxos, targets, None, Some(locktime)) {
+            Ok(tx) => tx,
+            Err(_) => return Err("create_tx_error".to_string()),
+        };
+    if let Some(data) = &data {
+        tx.vout
+            .push(TransactionOutput::create_op_return(data).unwrap()); // already validated
+    }
+    if rbf.unwrap_or(true) {
+        tx.signal_rbf();
+    }
+    tx.sign_all_inputs(&selected_utxos);
+    tx.txid = tx.compute_txid();
+    let mut total_amount: u64 = selected_utxos.iter().map(|utxo| utxo.utxo.value).sum();
+    if let Some(change_output) = change_output {
+        // the fee was estimated before signing, so the change absorbs the deviation
+        fee_adjustment::adjust_change(&mut tx, &selected_utxos, change_output, fee);
+        total_amount += tx.vout[change_output].value;
+    }
+    Ok(PreparedTransaction {
+        coin_type_index,
+        account_index,
+        transaction: tx,
+        change_index,
+        change_address,
+        total_amount,
+        request_key,
+        memo,
+        selected_utxos,
+        _lease: lease,
+    })
+}
+
+/// Broadcasts a prepared transaction. The transaction is persisted in the broadcast
+/// journal before it is broadcast. A journaled transaction that already reached the
+/// backend is not broadcast again. The memo is stored as the label of the transaction.
+async fn broadcast_prepared(
+    prepared: PreparedTransaction,
+    password: String,
+    db: &Database,
+    backend: &Backend,
+) -> Result<u64, String> {
+    let testnet = prepared.coin_type_index == 1;
+    let journal_path = journal_path(db);
+    let request_key = prepared.request_key.clone();
+    // persist the signed transaction before broadcasting it, unless it was journaled before
+    let journaled = match BroadcastJournal::update(&journal_path, |journal| {
+        if journal.get(&request_key).is_some() {
+            return true;
+        }
+        journal.insert(
+            request_key.clone(),
+            JournalEntry {
+                transaction: prepared.transaction.clone(),
+                change_index: prepared.change_index,
+                total_amount: prepared.total_amount,
+            },
+        );
+        false
+    }) {
+        Ok(journaled) => journaled,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let broadcasted = journaled
+        && match backend
+            .0
+            .is_transaction_known(&prepared.transaction.txid, testnet)
+            .await
+        {
+            Ok(known) => known,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+    if !broadcasted {
+        let res = backend
+            .0
+            .send_transaction(prepared.transaction.clone(), testnet)
+            .await;
+        if let Err(err) = res {
+            return Err(broadcast_error_to_string(err, &journal_path, &request_key));
+        }
+    }
+    // successfully broadcasted transaction
+    complete_send(
+        prepared.coin_type_index,
+        prepared.account_index,
+        prepared.change_index,
+        password,
+        &request_key,
+        db,
+    )?;
+    if let Some(memo) = &prepared.memo {
+        // the transaction was sent, so a memo that cannot be saved is not reported
+        let path = labels_path(db);
+        if let Ok(mut labels) = Labels::load(&path) {
+            if labels
+                .set(LabelType::Tx, &prepared.transaction.txid, memo)
+                .is_ok()
+            {
+                let _ = labels.save(path);
+            }
+        }
+    }
+    Ok(prepared.total_amount)
+}
+
+/// Sends the amounts to the addresses of the targets in a single transaction.
+/// The transaction signals that it can be replaced by a transaction with a
+/// higher fee unless `rbf` is false.
+/// If `data` is specified, an OP_RETURN output with the data is attached.
+/// If `outpoints` are specified, exactly these UTXOs are spent (coin control).
+/// Otherwise, the coins are selected by the specified strategy or the strategy of the settings.
+/// The minimum numbers of confirmations of the UTXOs default to those of the settings.
+/// The optional memo is stored locally and returned with the transaction.
+/// The locktime defaults to the current block height (anti-fee-sniping).
+#[tauri::command]
+async fn send_transaction(
+    request: SendRequest,
+    password: Option<String>,
+    db: State<'_, Database>,
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<u64, String> {
+    let password = resolve_password(password, &db);
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    let prepared = prepare_send(
+        request,
+        &mut master_private_key,
+        &db,
+        &reservations,
+        &backend,
+    )
+    .await?;
+    broadcast_prepared(prepared, password, &db, &backend).await
+}
+
+/// Pays the payment URI. If the URI contains a payjoin endpoint, the receiver is asked to
+/// add its inputs to the transaction (BIP-78). The original transaction is broadcast if
+/// the payjoin fails. The amount of the URI is paid unless an amount is specified.
+/// Returns the total amount like `send_transaction` and the reason why the payjoin failed.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+async fn send_payment_uri(
+    coin_type_index: u32,
+    account_index: u32,
+    uri: String,
+    amount: Option<u64>,
+    fee: u64,
+    memo: Option<String>,
+    password: Option<String>,
+    db: State<'_, Database>,
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<PaymentResult, String> {
+    let payment_uri = parse_payment_uri(uri, coin_type_index)?;
+    let amount = match amount.or(payment_uri.amount) {
+        Some(amount) => amount,
+        None => return Err("invalid_amount_error".to_string()),
+    };
+    let password = resolve_password(password, &db);
+    let request = SendRequest {
+        coin_type_index,
+        account_index,
+        targets: vec![(payment_uri.address.clone(), amount)],
+        fee,
+        memo,
+        ..SendRequest::default()
+    };
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    let mut prepared = prepare_send(
+        request,
+        &mut master_private_key,
+        &db,
+        &reservations,
+        &backend,
+    )
+    .await?;
+    let mut payjoin_error = None;
+    // a journaled transaction may have been broadcast already, so it is not replaced
+    let journaled = prepared.selected_utxos.is_empty();
+    if let Some(endpoint) = payment_uri.payjoin_endpoint.as_ref().filter(|_| !journaled) {
+        let payjoin_tx = negotiate_payjoin(
+            &prepared,
+            &payment_uri.address,
+            endpoint,
+            fee,
+            &master_private_key,
+        )
+        .await;
+        match payjoin_tx {
+            Ok(payjoin_tx) => {
+                // the change pays for the inputs of the receiver
+                let change = |tx: &Transaction| -> u64 {
+                    tx.vout
+                        .iter()
+                        .filter(|tx_out| {
+                            Some(&tx_out.scriptpubkey_address) == prepared.change_address.as_ref()
+                        })
+                        .map(|tx_out| tx_out.value)
+                        .sum()
+                };
+                prepared.total_amount =
+                    prepared.total_amount - change(&prepared.transaction) + change(&payjoin_tx);
+                prepared.transaction = payjoin_tx;
+            }
+            Err(err) => payjoin_error = Some(err),
+        }
+    }
+    let total_amount = broadcast_prepared(prepared, password, &db, &backend).await?;
+    Ok(PaymentResult {
+        total_amount,
+        payjoin_error,
+    })
+}
+
+/// Sends the signed original transaction to the payjoin endpoint of the receiver and
+/// returns the signed payjoin transaction. Returns an error if the transaction cannot be
+/// a payjoin, the receiver does not answer or its proposal is invalid.
+async fn negotiate_payjoin(
+    prepared: &PreparedTransaction,
+    address: &str,
+    endpoint: &str,
+    fee: u64,
+    master_private_key: &MasterPrivateKey,
+) -> Result<Transaction, String> {
+    let tx = &prepared.transaction;
+    let payee_output = match tx
+        .vout
+        .iter()
+        .position(|tx_out| tx_out.scriptpubkey_address == address)
+    {
+        Some(payee_output) => payee_output,
+        None => return Err("payjoin_unsupported_error".to_string()),
+    };
+    let fee_output = prepared.change_address.as_ref().and_then(|change_address| {
+        tx.vout
+            .iter()
+            .position(|tx_out| &tx_out.scriptpubkey_address == change_address)
+    });
+    let sender = PayjoinSender::new(
+        tx,
+        &prepared.selected_utxos,
+        master_private_key,
+        payee_output,
+        fee_output,
+        fee,
+    )
+    .map_err(|err| err.to_string())?;
+    let original_psbt = sender
+        .original_psbt(master_private_key)
+        .map_err(|err| err.to_string())?;
+    let proposal =
+        match networking::payjoin::request_proposal(endpoint, &sender.query(), original_psbt).await
+        {
+            Ok(proposal) => proposal,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+    let testnet = prepared.coin_type_index == 1;
+    sender
+        .process_proposal(&proposal, testnet, master_private_key)
+        .map_err(|err| err.to_string())
+}
+
+/// Builds and signs the transaction of `send_transaction` without broadcasting it.
+/// Returns a preview with the actual size and fee. The transaction is broadcast
+/// with `broadcast_prepared_transaction` using the ID of the preview.
+#[tauri::command]
+async fn prepare_transaction(
+    request: SendRequest,
+    password: Option<String>,
+    db: State<'_, Database>,
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+    prepared_transactions: State<'_, PreparedTransactions>,
+) -> Result<TransactionPreview, String> {
+    let password = resolve_password(password, &db);
+    let mut master_private_key = load_master_private_key(&db, password)?;
+    let prepared = prepare_send(
+        request,
+        &mut master_private_key,
+        &db,
+        &reservations,
+        &backend,
+    )
+    .await?;
+    let mut table = prepared_transactions.0.lock().unwrap();
+    let id = table.next_id;
+    table.next_id += 1;
+    let preview = TransactionPreview::create(
+        id,
+        &prepared.transaction,
+        prepared.change_address.as_deref(),
+    );
+    table.transactions.insert(id, prepared);
+    Ok(preview)
+}
+
+/// Broadcasts the transaction of the preview with the specified ID.
+/// Returns the total amount like `send_transaction`.
+#[tauri::command]
+async fn broadcast_prepared_transaction(
+    id: u64,
+    password: Option<String>,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+    prepared_transactions: State<'_, PreparedTransactions>,
+) -> Result<u64, String> {
+    let prepared = match prepared_transactions
+        .0
+        .lock()
+        .unwrap()
+        .transactions
+        .remove(&id)
+    {
+        Some(prepared) => prepared,
+        None => return Err("prepared_tx_missing_error".to_string()),
+    };
+    let password = resolve_password(password, &db);
+    broadcast_prepared(prepared, password, &db, &backend).await
+}
+
+/// Discards the transaction of the preview with the specified ID
+/// and releases its reservations.
+#[tauri::command]
+fn discard_prepared_transaction(
+    id: u64,
+    prepared_transactions: State<'_, PreparedTransactions>,
+) -> Result<(), String> {
+    match prepared_transactions
+        .0
+        .lock()
+        .unwrap()
+        .transactions
+        .remove(&id)
+    {
+        Some(_) => Ok(()),
+        None => Err("prepared_tx_missing_error".to_string()),
+    }
+}
+
+/// Sends the entire balance of the account to the address in a single transaction
+/// without a change output. The fee is computed from the size of the signed transaction.
+/// Returns the amount that the address receives.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+async fn send_max(
+    coin_type_index: u32,
+    account_index: u32,
+    address: String,
+    fee: u64,
+    rbf: Option<bool>,
+    locktime: Option<u32>,
+    password: Option<String>,
+    db: State<'_, Database>,
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<u64, String> {
+    let password = resolve_password(password, &db);
+    let master_private_key = load_master_private_key(&db, password.clone())?;
+    let testnet = coin_type_index == 1;
+    validate_address(address.clone(), coin_type_index)?;
+    let target_address = Address::from_str(&address).unwrap();
+    let journal_path = journal_path(&db);
+    let journal = match BroadcastJournal::load(&journal_path) {
+        Ok(journal) => journal,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    // the amount is not known in advance, so a zero amount marks the request
+    let request_key = BroadcastJournal::request_key(&SendRequest {
+        coin_type_index,
+        account_index,
+        targets: vec![(address, 0)],
+        fee,
+        rbf,
+        locktime,
+        ..SendRequest::default()
+    });
+    if let Some(entry) = journal.get(&request_key).cloned() {
+        let txid = entry.transaction.txid.clone();
+        let broadcasted = match backend.0.is_transaction_known(&txid, testnet).await {
+            Ok(known) => known,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+        if !broadcasted {
+            let res = backend.0.send_transaction(entry.transaction, testnet).await;
+            if let Err(err) = res {
+                return Err(broadcast_error_to_string(err, &journal_path, &request_key));
+            }
+        }
+        complete_send(
+            coin_type_index,
+            account_index,
+            None,
+            password,
+            &request_key,
+            &db,
+        )?;
+        return Ok(entry.total_amount);
+    }
+
+    let locktime = resolve_locktime(locktime, testnet, &backend).await?;
+    let keypairs = master_private_key.get_all_keypairs(coin_type_index, account_index);
+    let utxos = match networking::utxo::get_keypairs_boxed_utxos(backend.0.as_ref(), keypairs).await
+    {
+        Ok(utxos) => utxos,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let policy = resolve_confirmation_policy(None, None, &db)?;
+    let change_addresses = change_addresses(&master_private_key, coin_type_index, account_index);
+    let utxos = eligible_utxos(policy, utxos, change_addresses, testnet, &backend).await?;
+    let frozen = match FrozenUTXOs::load(frozen_path(&db)) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let (selected_utxos, lease_id) = {
+        let mut reservation_table = reservations.0.lock().unwrap();
+        let utxos = reservation_table.unreserved_utxos(frozen.unfrozen_utxos(utxos));
+        match reservation_table.reserve(&utxos, None) {
+            Ok(lease_id) => (utxos, lease_id),
+            Err(err) => return Err(err.to_string()),
+        }
+    };
+    // the reservations are released when the lease is dropped
+    let _lease = Lease::new(reservations.0.clone(), lease_id);
+    let tx = match send_max::create_send_max_transaction(
+        &selected_utxos,
+        &target_address,
+        fee,
+        Some(locktime),
+        rbf.unwrap_or(true),
+    ) {
+        Ok(tx) => tx,
+        Err(err) => return Err(err.to_string()),
+    };
+    let total_amount = tx.vout[0].value;
+
+    // persist the signed transaction before broadcasting it
+    let journaled = BroadcastJournal::update(&journal_path, |journal| {
+        journal.insert(
+            request_key.clone(),
+            JournalEntry {
+                transaction: tx.clone(),
+                change_index: None,
+                total_amount,
+            },
+        )
+    })
+    .is_ok();
+    if !journaled {
+        return Err("io_error".to_string());
+    }
+    if let Err(err) = backend.0.send_transaction(tx, testnet).await {
+        return Err(broadcast_error_to_string(err, &journal_path, &request_key));
+    }
+    complete_send(
+        coin_type_index,
+        account_index,
+        None,
+        password,
+        &request_key,
+        &db,
+    )?;
+    Ok(total_amount)
+}
+
+/// Returns the maximum amount that can be sent at the specified fee per byte, i.e., the
+/// spendable balance minus the fee. The UTXOs are the ones of the spendable balance that
+/// are not reserved and have the confirmations of the policy. Only the master public key
+/// is used, so the amount is known for watch-only wallets as well. The fee depends on the
+/// script type of the target address, so the address of the account is assumed if no
+/// address is specified.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+async fn get_max_spendable(
+    coin_type_index: u32,
+    account_index: u32,
+    fee: u64,
+    address: Option<String>,
+    min_confirmations: Option<u32>,
+    min_change_confirmations: Option<u32>,
+    db: State<'_, Database>,
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<u64, String> {
+    let master_pub_key =
+        db.0.lock()
+            .unwrap()
+            .get("master_public_key")
+            .unwrap()
+            .clone();
+    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
+    let testnet = coin_type_index == 1;
+    let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
+    let target_address = match address {
+        Some(address) => {
+            validate_address(address.clone(), coin_type_index)?;
+            Address::from_str(&address).unwrap()
+        }
+        None => match addresses.first() {
+            Some(address) => address.clone(),
+            None => return Ok(0),
+        },
+    };
+    let address_utxos =
+        match networking::utxo::get_addresses_received_utxos(backend.0.as_ref(), addresses.clone())
+            .await
+        {
+            Ok(address_utxos) => address_utxos,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+    // the transactions tell unconfirmed change from incoming payments, as in the balance
+    let address_paths = master_pub_key.get_address_paths(coin_type_index);
+    let simple_txs = match networking::transaction::get_addresses_simple_transactions(
+        backend.0.as_ref(),
+        addresses.clone(),
+        &address_paths,
+    )
+    .await
+    {
+        Ok(simple_txs) => simple_txs,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    let policy = resolve_confirmation_policy(min_confirmations, min_change_confirmations, &db)?;
+    let tip_height = if policy.requires_confirmations() {
+        match backend.0.get_tip_height(testnet).await {
+            Ok(tip_height) => tip_height,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        }
+    } else {
+        0
+    };
+    let frozen = match FrozenUTXOs::load(frozen_path(&db)) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let spendable_utxos = SpendableUTXOs::new(&simple_txs, &frozen);
+    let change_addresses: Vec<String> = master_pub_key
+        .get_all_change_addresses(coin_type_index, account_index)
+        .iter()
+        .map(|address| address.to_string())
+        .collect();
+    let utxos: Vec<(u64, ScriptType)> = {
+        let reservation_table = reservations.0.lock().unwrap();
+        address_utxos
+            .iter()
+            .filter(|(_, utxo)| spendable_utxos.contains(utxo))
+            .filter(|(_, utxo)| !reservation_table.is_outpoint_reserved(&utxo.txid, utxo.vout))
+            .filter(|(address, utxo)| {
+                let change = change_addresses.contains(&address.to_string());
+                policy.is_eligible(utxo, change, tip_height)
+            })
+            .map(|(address, utxo)| (utxo.value, address.script_type()))
+            .collect()
+    };
+    match send_max::max_spendable_amount(&utxos, &target_address, fee) {
+        Ok(amount) => Ok(amount),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Replaces an unconfirmed transaction of the account with a transaction that pays
+/// the specified fee per byte. The additional fee is deducted from the change.
+/// The replacement is persisted in the broadcast journal before it is broadcast,
+/// so a retry broadcasts the same replacement. Returns the transaction ID of the replacement.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+async fn bump_fee(
+    coin_type_index: u32,
+    account_index: u32,
+    txid: String,
+    fee: u64,
+    password: Option<String>,
+    db: State<'_, Database>,
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<String, String> {
+    let password = resolve_password(password, &db);
+    let master_private_key = load_master_private_key(&db, password)?;
+    let testnet = coin_type_index == 1;
+    let journal_path = journal_path(&db);
+    let journal = match BroadcastJournal::load(&journal_path) {
+        Ok(journal) => journal,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let request_key = BroadcastJournal::replacement_key(&txid, fee);
+    let entry = journal.get(&request_key).cloned();
+    let journaled = entry.is_some();
+    let replacement = match entry {
+        Some(entry) => entry.transaction,
+        None => {
+            let tx = match backend.0.get_transaction(&txid, testnet).await {
+                Ok(tx) => tx,
+                Err(err) => return Err(backend_error_to_string(err.as_ref())),
+            };
+            let keypairs = master_private_key.get_all_keypairs(coin_type_index, account_index);
+            let change_keypairs =
+                master_private_key.get_all_change_keypairs(coin_type_index, account_index);
+            match rbf::bump_fee(&tx, &keypairs, &change_keypairs, fee) {
+                Ok(replacement) => replacement,
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+    };
+    // the replacement spends the same coins, so a concurrent send must not select them
+    let outpoints: Vec<(String, u32)> = replacement
+        .vin
+        .iter()
+        .map(|tx_in| (tx_in.txid.clone(), tx_in.vout))
+        .collect();
+    let lease_id = match reservations
+        .0
+        .lock()
+        .unwrap()
+        .reserve_outpoints(&outpoints, None)
+    {
+        Ok(lease_id) => lease_id,
+        Err(err) => return Err(err.to_string()),
+    };
+    let _lease = Lease::new(reservations.0.clone(), lease_id);
+
+    // persist the signed replacement before broadcasting it
+    if !journaled {
+        let inserted = BroadcastJournal::update(&journal_path, |journal| {
+            journal.insert(
+                request_key.clone(),
+                JournalEntry {
+                    transaction: replacement.clone(),
+                    change_index: None,
+                    total_amount: 0, // the amounts of the replaced transaction are kept
+                },
+            )
+        });
+        if inserted.is_err() {
+            return Err("io_error".to_string());
+        }
+    }
+    let replacement_txid = replacement.txid.clone();
+    let broadcasted = journaled
+        && match backend
+            .0
+            .is_transaction_known(&replacement_txid, testnet)
+            .await
+        {
+            Ok(known) => known,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+    if !broadcasted {
+        if let Err(err) = backend.0.send_transaction(replacement, testnet).await {
+            return Err(broadcast_error_to_string(err, &journal_path, &request_key));
+        }
+    }
+    // if this fails, a retry finds the replacement on the backend and completes again
+    let _ = BroadcastJournal::update(&journal_path, |journal| journal.remove(&request_key));
+    track_transaction(&db, coin_type_index, &replacement_txid);
+    Ok(replacement_txid)
+}
+
+/// Accelerates an unconfirmed transaction by spending its outputs that belong to
+/// the account to a new change address. The child pays a fee, such that both
+/// transactions together pay the specified fee per byte.
+/// Returns the transaction ID of the child.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+async fn cpfp(
+    coin_type_index: u32,
+    account_index: u32,
+    txid: String,
+    fee: u64,
+    password: Option<String>,
+    db: State<'_, Database>,
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<String, String> {
+    let password = resolve_password(password, &db);
+    let path = key_path(&db);
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    let testnet = coin_type_index == 1;
+    let parent = match backend.0.get_transaction(&txid, testnet).await {
+        Ok(parent) => parent,
+        Err(err) => return Err(backend_error_to_string(err.as_ref())),
+    };
+    let keypairs = master_private_key.get_all_keypairs(coin_type_index, account_index);
+    let (child, lease_id) = {
+        let mut reservation_table = reservations.0.lock().unwrap();
+        let utxos = reservation_table.unreserved_utxos(cpfp::spendable_outputs(&parent, &keypairs));
+        let first_index = master_private_key.next_change_index(coin_type_index, account_index);
+        let index =
+            reservation_table.next_change_index(coin_type_index, account_index, first_index);
+        let change_keypair =
+            master_private_key.new_change_keypair(coin_type_index, account_index, Some(index));
+        // all outputs are spent, since they are swept to a single output
+        let child = match cpfp::create_child(
+            &parent,
+            utxos.clone(),
+            change_keypair.public_key.get_address(),
+            fee,
+        ) {
+            Ok(child) => child,
+            Err(err) => return Err(err.to_string()),
+        };
+        let change_key = Some((coin_type_index, account_index, index));
+        match reservation_table.reserve(&utxos, change_key) {
+            Ok(lease_id) => (child, lease_id),
+            Err(err) => return Err(err.to_string()),
+        }
+    };
+    let _lease = Lease::new(reservations.0.clone(), lease_id);
+
+    // the change key is saved before broadcasting, so the funds
+    // are never sent to a key that is missing from the key file
+    if master_private_key.save(path, password).is_err() {
+        return Err("io_error".to_string());
+    }
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    let child_txid = child.txid.clone();
+    if backend.0.send_transaction(child, testnet).await.is_err() {
+        return Err("send_tx_error".to_string());
+    }
+    track_transaction(&db, coin_type_index, &child_txid);
+    Ok(child_txid)
+}
+
+/// Creates an unsigned transaction that sends the amount to the address and returns
+/// it as a Base64-encoded PSBT, so it can be signed externally.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+async fn create_psbt(
+    coin_type_index: u32,
+    account_index: u32,
+    address: String,
+    amount: u64,
+    fee: u64,
+    rbf: Option<bool>,
+    locktime: Option<u32>,
+    password: Option<String>,
+    db: State<'_, Database>,
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<String, String> {
+    let password = resolve_password(password, &db);
+    let path = key_path(&db);
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    let testnet = coin_type_index == 1;
+    let address = match Address::from_str(&address) {
+        Ok(address) => address,
+        Err(_) => return Err("invalid_address_error".to_string()),
+    };
+    if coin_type_index != address.coin_type_index() {
+        return Err("wrong_address_type_error".to_string());
+    }
+    let locktime = resolve_locktime(locktime, testnet, &backend).await?;
+    let keypairs = master_private_key.get_all_keypairs(coin_type_index, account_index);
+    let utxos = match networking::utxo::get_keypairs_boxed_utxos(backend.0.as_ref(), keypairs).await
+    {
+        Ok(utxos) => utxos,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let policy = resolve_confirmation_policy(None, None, &db)?;
+    let change_addresses = change_addresses(&master_private_key, coin_type_index, account_index);
+    let utxos = eligible_utxos(policy, utxos, change_addresses, testnet, &backend).await?;
+    let frozen = match FrozenUTXOs::load(frozen_path(&db)) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let output_types = [address.script_type()];
+    let mut targets = vec![(address, amount)];
+    let strategy = resolve_strategy(None, &db)?;
+    let (selected_utxos, lease_id) = {
+        let mut reservation_table = reservations.0.lock().unwrap();
+        let utxos = reservation_table.unreserved_utxos(utxos);
+        let selected_coins = match coin_selection::strategy::select_coins(
+            strategy,
+            utxos,
+            &frozen,
+            None,
+            &output_types,
+            amount,
+            fee,
+        ) {
+            Ok(selected_coins) => selected_coins,
+            Err(err) => return Err(err.to_string()),
+        };
+        let mut change_key = None;
+        if let Some(change) = selected_coins.change {
+            let first_index = master_private_key.next_change_index(coin_type_index, account_index);
+            let index =
+                reservation_table.next_change_index(coin_type_index, account_index, first_index);
+            let change_keypair =
+                master_private_key.new_change_keypair(coin_type_index, account_index, Some(index));
+            targets.push((change_keypair.public_key.get_address(), change));
+            change_key = Some((coin_type_index, account_index, index));
+        }
+        match reservation_table.reserve(&selected_coins.selected_utxos, change_key) {
+            Ok(lease_id) => (selected_coins.selected_utxos, lease_id),
+            Err(err) => return Err(err.to_string()),
+        }
+    };
+    // the reservations are released when the lease is dropped
+    let _lease = Lease::new(reservations.0.clone(), lease_id);
+    let mut tx =
+        match Transaction::create_with_options(&selected_utxos, targets, None, Some(locktime)) {
+            Ok(tx) => tx,
+            Err(_) => return Err("create_tx_error".to_string()),
+        };
+    if rbf.unwrap_or(true) {
+        tx.signal_rbf();
+    }
+    tx.txid = tx.compute_txid();
+    let mut psbt = Psbt::create(&tx, &selected_utxos, &master_private_key);
+    // signers need the previous transactions to verify the amounts of the inputs
+    let mut prev_txids: Vec<String> = tx.vin.iter().map(|tx_in| tx_in.txid.clone()).collect();
+    prev_txids.sort();
+    prev_txids.dedup();
+    for txid in prev_txids {
+        let prev_tx = match backend.0.get_transaction(&txid, testnet).await {
+            Ok(prev_tx) => prev_tx,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+        psbt.add_previous_transaction(&prev_tx);
+    }
+
+    // the change key is saved, so the change is recognized once the PSBT is broadcast
+    if master_private_key.save(path, password).is_err() {
+        return Err("io_error".to_string());
+    }
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+    Ok(psbt.to_base64())
+}
+
+/// Adds the signatures of the wallet to a Base64-encoded PSBT and returns the
+/// updated PSBT. The inputs are matched against the wallet's keys by their
+/// BIP-32 derivation paths.
+#[tauri::command]
+fn sign_psbt(
+    coin_type_index: u32,
+    psbt: String,
+    password: Option<String>,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let password = resolve_password(password, &db);
+    let master_private_key = load_master_private_key(&db, password)?;
+    let testnet = coin_type_index == 1;
+    let mut psbt = match Psbt::from_base64(&psbt, testnet) {
+        Ok(psbt) => psbt,
+        Err(err) => return Err(err.to_string()),
+    };
+    if let Err(err) = psbt.sign(&master_private_key) {
+        return Err(err.to_string());
+    }
+    Ok(psbt.to_base64())
+}
+
+/// Merges Base64-encoded PSBTs of the same transaction, e.g., the PSBTs
+/// that were signed by different cosigners.
+#[tauri::command]
+fn merge_psbts(coin_type_index: u32, psbts: Vec<String>) -> Result<String, String> {
+    let testnet = coin_type_index == 1;
+    let mut merged: Option<Psbt> = None;
+    for psbt in psbts {
+        let psbt = match Psbt::from_base64(&psbt, testnet) {
+            Ok(psbt) => psbt,
+            Err(err) => return Err(err.to_string()),
+        };
+        match merged.as_mut() {
+            Some(merged) => {
+                if let Err(err) = merged.merge(&psbt) {
+                    return Err(err.to_string());
+                }
+            }
+            None => merged = Some(psbt),
+        }
+    }
+    match merged {
+        Some(merged) => Ok(merged.to_base64()),
+        None => Err("invalid_psbt_error".to_string()),
+    }
+}
+
+/// Finalizes a fully signed Base64-encoded PSBT and broadcasts its transaction.
+/// Returns the transaction ID.
+#[tauri::command]
+async fn broadcast_psbt(
+    coin_type_index: u32,
+    psbt: String,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<String, String> {
+    let testnet = coin_type_index == 1;
+    let mut psbt = match Psbt::from_base64(&psbt, testnet) {
+        Ok(psbt) => psbt,
+        Err(err) => return Err(err.to_string()),
+    };
+    if let Err(err) = psbt.finalize() {
+        return Err(err.to_string());
+    }
+    let tx = match psbt.extract_transaction() {
+        Ok(tx) => tx,
+        Err(err) => return Err(err.to_string()),
+    };
+    let txid = tx.txid.clone();
+    if backend.0.send_transaction(tx, testnet).await.is_err() {
+        return Err("send_tx_error".to_string());
+    }
+    track_transaction(&db, coin_type_index, &txid);
+    Ok(txid)
+}
+
+/// Exports a Base64-encoded PSBT to the specified file in the binary format of BIP-174,
+/// so it can be transferred to an air-gapped signer.
+#[tauri::command]
+fn export_psbt(coin_type_index: u32, psbt: String, path: String) -> Result<(), String> {
+    let testnet = coin_type_index == 1;
+    let psbt = match Psbt::from_base64(&psbt, testnet) {
+        Ok(psbt) => psbt,
+        Err(err) => return Err(err.to_string()),
+    };
+    match file::write_atomic(path, &psbt.serialize()) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
+}
+
+/// Imports a PSBT from the specified file and returns it Base64-encoded.
+/// The file contains either the binary PSBT or the Base64-encoded PSBT.
+#[tauri::command]
+fn import_psbt(coin_type_index: u32, path: String) -> Result<String, String> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let testnet = coin_type_index == 1;
+    let psbt = match Psbt::from_bytes(&bytes, testnet) {
+        Ok(psbt) => Ok(psbt),
+        Err(_) => match String::from_utf8(bytes) {
+            Ok(psbt) => Psbt::from_base64(&psbt, testnet),
+            Err(_) => return Err("invalid_psbt_error".to_string()),
+        },
+    };
+    match psbt {
+        Ok(psbt) => Ok(psbt.to_base64()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Finalizes a fully signed Base64-encoded PSBT and returns its transaction in hex,
+/// so an air-gapped signer can pass it to an online instance for the broadcast.
 #[tauri::command]
-fn generate_mnemonic(db: State<'_, Database>) -> String {
-    let mnemonic = bip39::generate_mnemonic(256, None);
-    let mnemonic_encoded: Vec<u8> = bincode::serialize(&mnemonic).unwrap();
-    db.0.lock()
-        .unwrap()
-        .insert("mnemonic".to_string(), mnemonic_encoded.clone());
-    mnemonic.join(" ")
+fn finalize_psbt(coin_type_index: u32, psbt: String) -> Result<String, String> {
+    let testnet = coin_type_index == 1;
+    let mut psbt = match Psbt::from_base64(&psbt, testnet) {
+        Ok(psbt) => psbt,
+        Err(err) => return Err(err.to_string()),
+    };
+    if let Err(err) = psbt.finalize() {
+        return Err(err.to_string());
+    }
+    match psbt.extract_transaction() {
+        Ok(tx) => Ok(tx.serialize_hex()),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
+/// Exports a signed transaction in hex to the specified file.
 #[tauri::command]
-fn send_passphrase(passphrase: String, db: State<'_, Database>) {
-    let passphrase_encoded: Vec<u8> = bincode::serialize(&passphrase).unwrap();
-    db.0.lock()
-        .unwrap()
-        .insert("passphrase".to_string(), passphrase_encoded);
+fn export_raw_transaction(coin_type_index: u32, hex: String, path: String) -> Result<(), String> {
+    let tx = match Transaction::from_hex(hex.trim(), coin_type_index == 1) {
+        Ok(tx) => tx,
+        Err(_) => return Err("invalid_tx_error".to_string()),
+    };
+    match file::write_atomic(path, tx.serialize_hex().as_bytes()) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
+    }
 }
 
+/// Imports a signed transaction in hex from the specified file.
 #[tauri::command]
-fn create_master_key(password: String, db: State<'_, Database>) {
-    let passphrase_decoded = db.0.lock().unwrap().remove("passphrase").unwrap();
-    let mnemonic_decoded = db.0.lock().unwrap().remove("mnemonic").unwrap();
-    let passphrase: String = bincode::deserialize(&passphrase_decoded[..]).unwrap();
-    let mnemonic: Vec<&str> = bincode::deserialize(&mnemonic_decoded[..]).unwrap();
-    let seed = bip39::generate_seed(mnemonic, &passphrase);
-    let master_private_key = MasterPrivateKey::create_from_seed(seed);
-    let path = dirs::home_dir().unwrap().join(KEY_PATH);
-    master_private_key.save(path, password).unwrap();
-    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
-    db.0.lock().unwrap().insert(
-        "master_public_key".to_string(),
-        master_public_key.serialize(),
-    );
+fn import_raw_transaction(coin_type_index: u32, path: String) -> Result<String, String> {
+    let hex = match fs::read_to_string(path) {
+        Ok(hex) => hex,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    match Transaction::from_hex(hex.trim(), coin_type_index == 1) {
+        Ok(tx) => Ok(tx.serialize_hex()),
+        Err(_) => Err("invalid_tx_error".to_string()),
+    }
 }
 
-/**
- * Keys and transaction
- */
+/// Decodes a signed transaction in hex and checks it before the broadcast, so the
+/// transaction can be reviewed. Returns the decoded transaction.
 #[tauri::command]
-fn does_master_key_exist() -> bool {
-    dirs::home_dir().unwrap().join(KEY_PATH).exists()
+fn decode_raw_transaction(coin_type_index: u32, hex: String) -> Result<Transaction, String> {
+    let tx = match Transaction::from_hex(hex.trim(), coin_type_index == 1) {
+        Ok(tx) => tx,
+        Err(_) => return Err("invalid_tx_error".to_string()),
+    };
+    match tx.check_sanity() {
+        Ok(()) => Ok(tx),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
+/// Broadcasts a transaction in hex that was signed elsewhere, e.g., by a hardware wallet
+/// or an air-gapped signer. The transaction is decoded and checked before it is sent to
+/// the backend. Returns the transaction ID.
 #[tauri::command]
-fn load_master_key(password: String, db: State<'_, Database>) -> Result<(), String> {
-    let path = dirs::home_dir().unwrap().join(KEY_PATH);
-    let master_private_key = MasterPrivateKey::load(path, password);
-    match master_private_key {
-        Ok(master_private_key) => {
-            let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
-            db.0.lock().unwrap().insert(
-                "master_public_key".to_string(),
-                master_public_key.serialize(),
-            );
-            Ok(())
-        }
-        Err(e) => {
-            if e.is::<std::io::Error>() {
-                Err("io_error".to_string())
-            } else if e.is::<WrongPasswordError>() {
-                Err("wrong_password_error".to_string())
-            } else {
-                Err("other_error".to_string())
-            }
+async fn broadcast_raw_transaction(
+    coin_type_index: u32,
+    hex: String,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<String, String> {
+    let tx = decode_raw_transaction(coin_type_index, hex)?;
+    let testnet = coin_type_index == 1;
+    let txid = tx.txid.clone();
+    if let Err(err) = backend.0.send_transaction(tx, testnet).await {
+        if err.is::<OfflineError>() {
+            return Err("offline_error".to_string());
         }
+        return Err("send_tx_error".to_string());
     }
+    track_transaction(&db, coin_type_index, &txid);
+    Ok(txid)
 }
 
+/*
+ * Multisig accounts
+ */
+/// Returns the key of this wallet for the next multisig account in the format
+/// `[fingerprint/path]xpub`, which is shared with the other cosigners.
 #[tauri::command]
-fn get_accounts_overview(db: State<'_, Database>) -> String {
-    if !db.0.lock().unwrap().contains_key("master_public_key") {
-        return "".to_string();
-    }
-    let master_pub_key =
-        db.0.lock()
-            .unwrap()
-            .get("master_public_key")
-            .unwrap()
-            .clone();
-    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
-    master_pub_key.jsonify()
+fn get_multisig_cosigner_key(
+    coin_type_index: u32,
+    script_type: String,
+    password: Option<String>,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let script_type = match MultisigScriptType::from_str(&script_type) {
+        Ok(script_type) => script_type,
+        Err(err) => return Err(err.to_string()),
+    };
+    let password = resolve_password(password, &db);
+    let master_private_key = load_master_private_key(&db, password)?;
+    let accounts = match MultisigAccounts::load(multisig_path(&db)) {
+        Ok(accounts) => accounts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let cosigner = Cosigner::derive_local(
+        wallet_name(&db),
+        &master_private_key,
+        coin_type_index,
+        accounts.next_index(coin_type_index),
+        script_type,
+    );
+    Ok(cosigner.to_key_expression())
 }
 
+/// Creates a multisig account with the keys of the other cosigners and the key
+/// of this wallet. The cosigners are specified as pairs of label and key.
 #[tauri::command]
-async fn get_account_balance(
+fn create_multisig_account(
     coin_type_index: u32,
-    account_index: u32,
+    label: String,
+    threshold: usize,
+    script_type: String,
+    cosigners: Vec<(String, String)>,
+    password: Option<String>,
     db: State<'_, Database>,
-) -> Result<u64, String> {
-    let master_pub_key =
-        db.0.lock()
-            .unwrap()
-            .get("master_public_key")
-            .unwrap()
-            .clone();
-    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
-    let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
-    match networking::utxo::get_account_balance(addresses).await {
-        Ok(balance) => Ok(balance),
+) -> Result<MultisigAccount, String> {
+    let script_type = match MultisigScriptType::from_str(&script_type) {
+        Ok(script_type) => script_type,
+        Err(err) => return Err(err.to_string()),
+    };
+    let password = resolve_password(password, &db);
+    let master_private_key = load_master_private_key(&db, password)?;
+    let path = multisig_path(&db);
+    let mut accounts = match MultisigAccounts::load(&path) {
+        Ok(accounts) => accounts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let mut account_cosigners = vec![Cosigner::derive_local(
+        wallet_name(&db),
+        &master_private_key,
+        coin_type_index,
+        accounts.next_index(coin_type_index),
+        script_type,
+    )];
+    for (cosigner_label, key) in cosigners {
+        match Cosigner::parse(cosigner_label, &key) {
+            Ok(cosigner) => account_cosigners.push(cosigner),
+            Err(err) => return Err(err.to_string()),
+        }
+    }
+    let account = match accounts.add(
+        coin_type_index,
+        label,
+        threshold,
+        script_type,
+        account_cosigners,
+    ) {
+        Ok(account) => account.clone(),
+        Err(err) => return Err(err.to_string()),
+    };
+    match accounts.save(path) {
+        Ok(()) => Ok(account),
         Err(_) => Err("io_error".to_string()),
     }
 }
 
 #[tauri::command]
-async fn get_simple_transactions(
+fn get_multisig_accounts(
     coin_type_index: u32,
-    account_index: u32,
     db: State<'_, Database>,
-) -> Result<Vec<SimplifiedTransaction>, String> {
-    let master_pub_key =
-        db.0.lock()
-            .unwrap()
-            .get("master_public_key")
-            .unwrap()
-            .clone();
-    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
-    let addresses = master_pub_key.get_all_addresses(coin_type_index, account_index);
-    match networking::transaction::get_addresses_simple_transactions(addresses).await {
-        Ok(simple_txs) => Ok(simple_txs),
+) -> Result<Vec<MultisigAccount>, String> {
+    match MultisigAccounts::load(multisig_path(&db)) {
+        Ok(accounts) => Ok(accounts.get_accounts(coin_type_index)),
         Err(_) => Err("io_error".to_string()),
     }
 }
 
 #[tauri::command]
-async fn get_recommended_fees(coin_type_index: u32) -> Result<Fees, String> {
-    match networking::fee::get_recommended_fees(coin_type_index).await {
-        Ok(fees) => Ok(fees),
-        Err(err) => Err(err.to_string()),
+fn get_multisig_addresses(
+    coin_type_index: u32,
+    account_index: u32,
+    db: State<'_, Database>,
+) -> Result<Vec<MultisigAddress>, String> {
+    let accounts = match MultisigAccounts::load(multisig_path(&db)) {
+        Ok(accounts) => accounts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    match accounts.get(coin_type_index, account_index) {
+        Some(account) => Ok(account.get_addresses()),
+        None => Err("account_not_found_error".to_string()),
     }
 }
 
-/**
- * Account and address
- */
 #[tauri::command]
-fn create_new_account(
+fn rename_cosigner(
     coin_type_index: u32,
-    password: String,
+    account_index: u32,
+    cosigner_index: usize,
+    label: String,
     db: State<'_, Database>,
 ) -> Result<(), String> {
-    let path = dirs::home_dir().unwrap().join(KEY_PATH);
-    let master_private_key = MasterPrivateKey::load(path.clone(), password.clone());
-    match master_private_key {
-        Ok(mut master_private_key) => {
-            master_private_key.add_account(coin_type_index);
-            master_private_key.save(path, password).unwrap();
-            let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
-            db.0.lock().unwrap().insert(
-                "master_public_key".to_string(),
-                master_public_key.serialize(),
-            );
-            Ok(())
-        }
-        Err(e) => {
-            if e.is::<std::io::Error>() {
-                Err("io_error".to_string())
-            } else if e.is::<WrongPasswordError>() {
-                Err("wrong_password_error".to_string())
-            } else {
-                Err("other_error".to_string())
-            }
-        }
+    let path = multisig_path(&db);
+    let mut accounts = match MultisigAccounts::load(&path) {
+        Ok(accounts) => accounts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let cosigner = accounts
+        .get_mut(coin_type_index, account_index)
+        .and_then(|account| account.cosigners.get_mut(cosigner_index));
+    match cosigner {
+        Some(cosigner) => cosigner.label = label,
+        None => return Err("account_not_found_error".to_string()),
+    }
+    match accounts.save(path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
     }
 }
 
 #[tauri::command]
-fn get_new_receive_address(
+fn remove_multisig_account(
     coin_type_index: u32,
     account_index: u32,
-    password: String,
     db: State<'_, Database>,
-) -> Result<String, String> {
-    let path = dirs::home_dir().unwrap().join(KEY_PATH);
-    let master_private_key = MasterPrivateKey::load(path.clone(), password.clone());
-    match master_private_key {
-        Ok(mut master_private_key) => {
-            let keypair =
-                master_private_key.new_receive_keypair(coin_type_index, account_index, None);
-            master_private_key.save(path, password).unwrap();
-            let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
-            db.0.lock().unwrap().insert(
-                "master_public_key".to_string(),
-                master_public_key.serialize(),
-            );
-            let address = keypair.public_key.get_address();
-            Ok(address.to_string())
-        }
-        Err(e) => {
-            if e.is::<std::io::Error>() {
-                Err("io_error".to_string())
-            } else if e.is::<WrongPasswordError>() {
-                Err("wrong_password_error".to_string())
-            } else {
-                Err("other_error".to_string())
-            }
-        }
+) -> Result<(), String> {
+    let path = multisig_path(&db);
+    let mut accounts = match MultisigAccounts::load(&path) {
+        Ok(accounts) => accounts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    if !accounts.remove(coin_type_index, account_index) {
+        return Err("account_not_found_error".to_string());
+    }
+    match accounts.save(path) {
+        Ok(()) => Ok(()),
+        Err(_) => Err("io_error".to_string()),
     }
 }
 
 #[tauri::command]
-fn get_all_receive_addresses(
+fn new_multisig_receive_address(
     coin_type_index: u32,
     account_index: u32,
     db: State<'_, Database>,
-) -> Vec<String> {
-    let master_pub_key =
-        db.0.lock()
-            .unwrap()
-            .get("master_public_key")
-            .unwrap()
-            .clone();
-    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
-    master_pub_key
-        .get_all_receive_addresses(coin_type_index, account_index)
-        .iter()
-        .map(|address| address.to_string())
-        .collect()
+) -> Result<String, String> {
+    let path = multisig_path(&db);
+    let mut accounts = match MultisigAccounts::load(&path) {
+        Ok(accounts) => accounts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let address = match accounts.get_mut(coin_type_index, account_index) {
+        Some(account) => account.new_receive_address(),
+        None => return Err("account_not_found_error".to_string()),
+    };
+    match accounts.save(path) {
+        Ok(()) => Ok(address.to_string()),
+        Err(_) => Err("io_error".to_string()),
+    }
 }
 
+/// Creates an unsigned transaction that sends the amount from the multisig account
+/// to the address and returns it as a Base64-encoded PSBT, which is signed by the
+/// cosigners with `sign_psbt` and broadcast with `broadcast_psbt`.
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
-async fn get_all_receive_addresses_marked(
+async fn create_multisig_psbt(
     coin_type_index: u32,
     account_index: u32,
+    address: String,
+    amount: u64,
+    fee: u64,
+    rbf: Option<bool>,
+    password: Option<String>,
     db: State<'_, Database>,
-) -> Result<Vec<SimpleAddress>, String> {
-    let master_pub_key =
-        db.0.lock()
-            .unwrap()
-            .get("master_public_key")
-            .unwrap()
-            .clone();
-    let master_pub_key = MasterPublicKey::load(&master_pub_key[..]);
-    let addresses = master_pub_key.get_all_receive_addresses(coin_type_index, account_index);
-    let simple_addr = networking::transaction::mark_addresses_as_used(addresses).await;
-    match simple_addr {
-        Ok(simple_addr) => Ok(simple_addr),
-        Err(err) => Err(err.to_string()),
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<String, String> {
+    let password = resolve_password(password, &db);
+    let master_private_key = load_master_private_key(&db, password)?;
+    let path = multisig_path(&db);
+    let mut accounts = match MultisigAccounts::load(&path) {
+        Ok(accounts) => accounts,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let account = match accounts.get_mut(coin_type_index, account_index) {
+        Some(account) => account,
+        None => return Err("account_not_found_error".to_string()),
+    };
+    let utxos = match networking::utxo::get_multisig_boxed_utxos(
+        backend.0.as_ref(),
+        account,
+        &master_private_key,
+    )
+    .await
+    {
+        Ok(utxos) => utxos,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let policy = resolve_confirmation_policy(None, None, &db)?;
+    let change_addresses: Vec<String> = account
+        .get_addresses()
+        .into_iter()
+        .filter(|multisig_address| multisig_address.change)
+        .map(|multisig_address| multisig_address.address)
+        .collect();
+    let testnet = coin_type_index == 1;
+    let utxos = eligible_utxos(policy, utxos, change_addresses, testnet, &backend).await?;
+    let frozen = match FrozenUTXOs::load(frozen_path(&db)) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
+    };
+    let address = match Address::from_str(&address) {
+        Ok(address) => address,
+        Err(_) => return Err("invalid_address_error".to_string()),
+    };
+    let output_types = [address.script_type()];
+    let mut targets = vec![(address, amount)];
+    let strategy = resolve_strategy(None, &db)?;
+    let selected_utxos = {
+        let reservation_table = reservations.0.lock().unwrap();
+        let utxos = reservation_table.unreserved_utxos(utxos);
+        let selected_coins = match coin_selection::strategy::select_coins(
+            strategy,
+            utxos,
+            &frozen,
+            None,
+            &output_types,
+            amount,
+            fee,
+        ) {
+            Ok(selected_coins) => selected_coins,
+            Err(err) => return Err(err.to_string()),
+        };
+        if let Some(change) = selected_coins.change {
+            targets.push((account.new_change_address(), change));
+        }
+        selected_coins.selected_utxos
+    };
+    let mut tx = Transaction::create_unsigned(&selected_utxos, targets);
+    if rbf.unwrap_or(true) {
+        tx.signal_rbf();
     }
-}
+    tx.txid = tx.compute_txid();
+    let psbt = Psbt::create_multisig(&tx, &selected_utxos, account);
 
-#[tauri::command]
-fn validate_address(address: String, coin_type_index: u32) -> Result<(), String> {
-    match Address::from_str(&address) {
-        Ok(address) => {
-            let addr_coin_index = if address.testnet { 1 } else { 0 };
-            if coin_type_index != addr_coin_index {
-                return Err("Wrong address type".to_string());
-            }
-            Ok(())
-        }
-        Err(err) => Err(err.to_string()),
+    // the change address is saved, so the change is recognized once the PSBT is broadcast
+    if accounts.save(path).is_err() {
+        return Err("io_error".to_string());
     }
+    Ok(psbt.to_base64())
 }
 
-/**
- * Send transaction
- */
+/// Sends all funds of the source account to a new receive address of the target account.
+/// The script type of the receive address is P2PKH unless P2WPKH is specified, which
+/// migrates the funds to SegWit. Frozen and reserved UTXOs are not spent. If the UTXOs
+/// exceed the input limit of a transaction, they are sent in batches. A batch that
+/// cannot be sent stops the merge and its error is returned with the sent transactions.
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
-async fn send_transaction(
+async fn merge_accounts(
     coin_type_index: u32,
-    account_index: u32,
-    address: String,
-    amount: u64,
+    source_account_index: u32,
+    target_account_index: u32,
+    script_type: Option<String>,
     fee: u64,
     password: String,
     db: State<'_, Database>,
-) -> Result<u64, String> {
-    let path = dirs::home_dir().unwrap().join(KEY_PATH);
-    let mut master_private_key = match MasterPrivateKey::load(path.clone(), password.clone()) {
-        Ok(master_private_key) => master_private_key,
-        Err(err) => {
-            if err.is::<std::io::Error>() {
-                return Err("io_error".to_string());
-            } else if err.is::<WrongPasswordError>() {
-                return Err("wrong_password_error".to_string());
-            } else {
-                return Err("other_error".to_string());
-            }
-        }
+    reservations: State<'_, Reservations>,
+    backend: State<'_, Backend>,
+) -> Result<SweepResult, String> {
+    if source_account_index == target_account_index {
+        return Err("same_account_error".to_string());
+    }
+    // the wallet receives to the P2PKH and the P2WPKH address of its keys
+    let script_type = match script_type.as_deref().map(ScriptType::from_str) {
+        None => ScriptType::P2PKH,
+        Some(Ok(script_type @ (ScriptType::P2PKH | ScriptType::P2WPKH))) => script_type,
+        Some(_) => return Err("invalid_script_type_error".to_string()),
     };
-    let keypairs = master_private_key.get_all_keypairs(coin_type_index, account_index);
-    let utxos = match networking::utxo::get_keypairs_boxed_utxos(keypairs).await {
+    let path = key_path(&db);
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    let testnet = coin_type_index == 1;
+    let keypairs = master_private_key.get_all_keypairs(coin_type_index, source_account_index);
+    let utxos = match networking::utxo::get_keypairs_boxed_utxos(backend.0.as_ref(), keypairs).await
+    {
         Ok(utxos) => utxos,
         Err(_) => return Err("io_error".to_string()),
     };
-    let selected_coins = match coin_selection::random_improve::select_coins(utxos, 1, amount, fee) {
-        Ok(selected_coins) => selected_coins,
-        Err(err) => return Err(err.to_string()),
+    let policy = resolve_confirmation_policy(None, None, &db)?;
+    let change_addresses =
+        change_addresses(&master_private_key, coin_type_index, source_account_index);
+    let utxos = eligible_utxos(policy, utxos, change_addresses, testnet, &backend).await?;
+    let frozen = match FrozenUTXOs::load(frozen_path(&db)) {
+        Ok(frozen) => frozen,
+        Err(_) => return Err("io_error".to_string()),
     };
-    let address = Address::from_str(&address).unwrap(); // already validated
-    let mut targets = vec![(address, amount)];
-    if let Some(change) = selected_coins.change {
-        let change_keypair =
-            master_private_key.new_change_keypair(coin_type_index, account_index, None);
-        targets.push((change_keypair.public_key.get_address(), change));
+    let (batches, lease_id) = {
+        let mut reservation_table = reservations.0.lock().unwrap();
+        let utxos = reservation_table.unreserved_utxos(frozen.unfrozen_utxos(utxos));
+        let batches = match coin_selection::sweep::select_batches_to(utxos, script_type, fee) {
+            Ok(batches) => batches,
+            Err(err) => return Err(err.to_string()),
+        };
+        let selected_utxos: Vec<UTXOBox> = batches
+            .iter()
+            .flat_map(|batch| batch.selected_utxos.clone())
+            .collect();
+        match reservation_table.reserve(&selected_utxos, None) {
+            Ok(lease_id) => (batches, lease_id),
+            Err(err) => return Err(err.to_string()),
+        }
+    };
+    let _lease = Lease::new(reservations.0.clone(), lease_id);
+
+    // the receive key is saved before broadcasting, so the swept funds
+    // are never sent to a key that is missing from the key file
+    let keypair =
+        master_private_key.new_receive_keypair(coin_type_index, target_account_index, None);
+    if master_private_key.save(path, password).is_err() {
+        return Err("io_error".to_string());
     }
-    let selected_utxos = selected_coins.selected_utxos;
-    let tx = Transaction::create(&selected_utxos, targets);
-    match tx {
-        Ok(mut tx) => {
-            tx.sign_all_inputs(&selected_utxos);
-            match networking::transaction::send_transaction(tx, coin_type_index == 1).await {
-                Ok(()) => {
-                    // successfully broadcasted transaction
-                    master_private_key.save(path, password).unwrap();
-                    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
-                    db.0.lock().unwrap().insert(
-                        "master_public_key".to_string(),
-                        master_public_key.serialize(),
-                    );
-                    let mut total_amount: u64 =
-                        selected_utxos.iter().map(|utxo| utxo.utxo.value).sum();
-                    if let Some(change) = selected_coins.change {
-                        total_amount += change;
-                    }
-                    Ok(total_amount)
-                }
-                Err(_) => Err("send_tx_error".to_string()),
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+
+    let address = match script_type {
+        ScriptType::P2WPKH => Address::create_p2wpkh(&keypair.public_key),
+        _ => keypair.public_key.get_address(),
+    };
+    let mut result = SweepResult::default();
+    for batch in batches {
+        let targets = vec![(address.clone(), batch.amount)];
+        let mut tx = match Transaction::create(&batch.selected_utxos, targets) {
+            Ok(tx) => tx,
+            Err(_) => {
+                result.error = Some("create_tx_error".to_string());
+                break;
             }
+        };
+        tx.sign_all_inputs(&batch.selected_utxos);
+        let txid = tx.compute_txid();
+        if backend.0.send_transaction(tx, testnet).await.is_err() {
+            result.error = Some("send_tx_error".to_string());
+            break;
+        }
+        track_transaction(&db, coin_type_index, &txid);
+        result.txids.push(txid);
+    }
+    // the error is only reported with the result if a batch was sent
+    match result.error {
+        Some(err) if result.txids.is_empty() => Err(err),
+        _ => Ok(result),
+    }
+}
+
+/// Sends all funds of an external wallet (e.g. a paper wallet) to the specified account
+/// in a single transaction. The external wallet is given as a mnemonic or as a master
+/// private key. Its accounts are discovered with the gap limit for the purposes of all
+/// script types with a single key, i.e., P2PKH (44), P2SH-P2WPKH (49), P2WPKH (84)
+/// and P2TR (86).
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+async fn sweep_external_wallet(
+    secret: String,
+    passphrase: Option<String>,
+    coin_type_index: u32,
+    target_account_index: u32,
+    fee: u64,
+    password: String,
+    db: State<'_, Database>,
+    backend: State<'_, Backend>,
+) -> Result<String, String> {
+    let external_private_key =
+        match MasterPrivateKey::create_from_secret(&secret, &passphrase.unwrap_or_default()) {
+            Ok(external_private_key) => external_private_key,
+            Err(_) => return Err("invalid_secret_error".to_string()),
+        };
+    let testnet = coin_type_index == 1;
+    let mut utxos = Vec::new();
+    for purpose_index in SINGLE_KEY_PURPOSES {
+        let mut purpose_private_key = external_private_key.for_purpose(purpose_index);
+        let accounts = match networking::discovery::discover_accounts(
+            backend.0.as_ref(),
+            &purpose_private_key,
+            coin_type_index,
+        )
+        .await
+        {
+            Ok(accounts) => accounts,
+            Err(err) => return Err(backend_error_to_string(err.as_ref())),
+        };
+        networking::discovery::add_discovered_accounts(
+            &mut purpose_private_key,
+            coin_type_index,
+            &accounts,
+        );
+        let keypairs: Vec<Keypair> = accounts
+            .iter()
+            .flat_map(|account| {
+                purpose_private_key.get_all_keypairs(coin_type_index, account.index)
+            })
+            .collect();
+        match networking::utxo::get_purpose_keypairs_boxed_utxos(
+            backend.0.as_ref(),
+            keypairs,
+            purpose_index,
+        )
+        .await
+        {
+            Ok(purpose_utxos) => utxos.extend(purpose_utxos),
+            Err(_) => return Err("io_error".to_string()),
         }
-        Err(_) => Err("create_tx_error".to_string()),
     }
+    let batch = match coin_selection::sweep::select_single_batch(utxos, fee) {
+        Ok(batch) => batch,
+        Err(err) => return Err(err.to_string()),
+    };
+
+    // the receive key is saved before broadcasting, so the swept funds
+    // are never sent to a key that is missing from the key file
+    let path = key_path(&db);
+    let mut master_private_key = load_master_private_key(&db, password.clone())?;
+    let keypair =
+        master_private_key.new_receive_keypair(coin_type_index, target_account_index, None);
+    if master_private_key.save(path, password).is_err() {
+        return Err("io_error".to_string());
+    }
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(&db, &master_public_key);
+
+    let targets = vec![(keypair.public_key.get_address(), batch.amount)];
+    let mut tx = match Transaction::create(&batch.selected_utxos, targets) {
+        Ok(tx) => tx,
+        Err(_) => return Err("create_tx_error".to_string()),
+    };
+    tx.sign_all_inputs(&batch.selected_utxos);
+    let txid = tx.compute_txid();
+    if backend.0.send_transaction(tx, testnet).await.is_err() {
+        return Err("send_tx_error".to_string());
+    }
+    track_transaction(&db, coin_type_index, &txid);
+    Ok(txid)
+}
+
+/// Starts tracking the confirmations of a broadcast transaction.
+/// The transaction was sent, so a failure to persist it is not reported.
+fn track_transaction(db: &Database, coin_type_index: u32, txid: &str) {
+    let path = pending_path(db);
+    if let Ok(mut pending) = PendingTransactions::load(&path) {
+        pending.insert(txid, coin_type_index, unix_now());
+        let _ = pending.save(path);
+    }
+}
+
+/// Adds the change key to the key hierarchy after a successful broadcast
+/// and removes the transaction from the broadcast journal.
+fn complete_send(
+    coin_type_index: u32,
+    account_index: u32,
+    change_index: Option<u32>,
+    password: String,
+    request_key: &str,
+    db: &Database,
+) -> Result<(), String> {
+    let path = key_path(db);
+    // the key hierarchy is reloaded, so the change keys of concurrent sends are kept
+    let mut master_private_key = load_master_private_key(db, password.clone())?;
+    if let Some(change_index) = change_index {
+        master_private_key.new_change_keypair(coin_type_index, account_index, Some(change_index));
+    }
+    if master_private_key.save(path, password).is_err() {
+        // the journal entry is kept, so a retry will not pay twice
+        return Err("io_error".to_string());
+    }
+    let master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+    set_master_public_key(db, &master_public_key);
+    // if this fails, a retry finds the transaction on the backend and completes again
+    let removed = BroadcastJournal::update(journal_path(db), |journal| journal.remove(request_key));
+    if let Ok(Some(entry)) = removed {
+        track_transaction(db, coin_type_index, &entry.transaction.txid);
+    }
+    Ok(())
+}
+
+/// Maps an error of the backend to the error string expected by the frontend.
+/// Requests that failed because of a transient problem are reported separately,
+/// so the frontend can ask the user to try again later.
+fn backend_error_to_string(err: &(dyn Error + 'static)) -> String {
+    if err.is::<OfflineError>() {
+        return "offline_error".to_string();
+    }
+    let code = match err.downcast_ref::<RequestError>().map(|err| &err.kind) {
+        Some(RequestErrorKind::Timeout) => "timeout_error",
+        Some(RequestErrorKind::RateLimited) => "rate_limited_error",
+        Some(RequestErrorKind::Unavailable) => "server_unavailable_error",
+        _ => "io_error",
+    };
+    code.to_string()
+}
+
+/// Maps a broadcast error to the error string expected by the frontend.
+/// If the backend, or every broadcast endpoint, rejected the transaction, it is
+/// removed from the journal because it can never be broadcast. Otherwise the
+/// transaction may have reached the backend and is kept for a retry.
+fn broadcast_error_to_string(
+    err: Box<dyn Error>,
+    journal_path: &Path,
+    request_key: &str,
+) -> String {
+    let rejected = match err.downcast_ref::<BroadcastError>() {
+        Some(err) => err.is_rejected(),
+        None => err.is::<SendTransactionError>(),
+    };
+    if rejected {
+        let _ = BroadcastJournal::update(journal_path, |journal| journal.remove(request_key));
+    }
+    "send_tx_error".to_string()
 }
 
 fn main() {
+    let network_settings = NetworkSettings::load(network_settings_path()).unwrap_or_default();
+    network::select_test_network(network_settings.test_network);
+    rate_limit::set_rate_limit(network_settings.rate_limit);
+    // the saved public key hierarchy shows the default wallet before it is unlocked
+    let database = Database(Default::default());
+    load_master_public_key(&database);
     tauri::Builder::default()
-        .manage(Database(Default::default()))
+        .manage(database)
+        .manage(Reservations(Default::default()))
+        .manage(Setup(Default::default()))
+        .manage(Backend(Arc::new(SelectableBackend::new(
+            networking::backend::create_backend(&network_settings),
+        ))))
+        .manage(PreparedTransactions(Default::default()))
+        .manage(LiveUpdates(Default::default()))
+        .manage(SyncService(Default::default()))
         .invoke_handler(tauri::generate_handler![
             generate_mnemonic,
+            confirm_backup,
             send_passphrase,
             create_master_key,
+            generate_seed_shares,
+            restore_from_seed_shares,
+            export_backup,
+            import_backup,
+            derive_child_mnemonic,
+            get_message_catalog,
+            list_wallets,
+            create_wallet,
+            open_wallet,
+            open_wallet_watch_only,
+            is_master_public_key_loaded,
+            get_wallet_name,
             does_master_key_exist,
             load_master_key,
+            check_password,
+            verify_wallet_file,
+            get_password_attempt_status,
+            set_wipe_policy,
+            change_password,
+            get_security_level,
+            remember_password,
+            forget_password,
+            is_password_remembered,
+            set_security_level,
             get_accounts_overview,
             create_new_account,
+            rename_account,
+            archive_account,
+            export_account_xpub,
+            export_account_xprv,
+            derive_key_at_path,
+            discover_accounts,
             get_new_receive_address,
             get_all_receive_addresses,
             get_all_receive_addresses_marked,
             get_account_balance,
+            get_address_details,
+            get_max_spendable,
+            list_utxos,
+            freeze_utxo,
+            unfreeze_utxo,
+            get_coin_selection_strategy,
+            set_coin_selection_strategy,
+            get_confirmation_policy,
+            set_confirmation_policy,
+            get_network_settings,
+            set_test_network,
+            set_rate_limit,
+            set_broadcast_endpoints,
+            set_fee_fallbacks,
+            set_backend_settings,
+            test_backend_connection,
+            start_live_updates,
+            stop_live_updates,
+            start_sync,
+            stop_sync,
+            get_sync_status,
+            get_pending_transactions,
+            get_spendable_balance,
             get_simple_transactions,
+            export_transactions,
+            get_transaction_details,
+            sign_message,
+            verify_message,
             validate_address,
+            parse_payment_uri,
             get_recommended_fees,
+            estimate_confirmation_time,
+            get_chain_tip,
+            check_backend_health,
+            set_offline_mode,
+            check_pending_transactions,
+            add_watched_address,
+            remove_watched_address,
+            add_contact,
+            update_contact,
+            remove_contact,
+            get_contacts,
+            get_contact_address,
+            resolve_contact,
+            create_payment_request,
+            get_payment_requests,
+            remove_payment_request,
+            get_watched_addresses,
+            set_label,
+            get_labels,
+            export_labels,
+            import_labels,
+            get_watched_transactions,
             send_transaction,
+            send_payment_uri,
+            prepare_transaction,
+            broadcast_prepared_transaction,
+            discard_prepared_transaction,
+            send_max,
+            bump_fee,
+            cpfp,
+            create_psbt,
+            sign_psbt,
+            merge_psbts,
+            broadcast_psbt,
+            export_psbt,
+            import_psbt,
+            finalize_psbt,
+            export_raw_transaction,
+            import_raw_transaction,
+            decode_raw_transaction,
+            broadcast_raw_transaction,
+            get_multisig_cosigner_key,
+            create_multisig_account,
+            get_multisig_accounts,
+            get_multisig_addresses,
+            rename_cosigner,
+            remove_multisig_account,
+            new_multisig_receive_address,
+            create_multisig_psbt,
+            merge_accounts,
+            sweep_external_wallet,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
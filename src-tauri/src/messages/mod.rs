@@ -0,0 +1,604 @@
+//! Defines the messages for the error codes that are returned to the frontend.
+//! The frontend looks up the message for a code in the catalog of the user's
+//! locale instead of matching on English text.
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+pub static DEFAULT_LOCALE: &'static str = "en";
+
+static ENGLISH: &[(&'static str, &'static str)] = &[
+    (
+        "io_error",
+        "Unable to access the wallet file or the server.",
+    ),
+    ("wrong_password_error", "Wrong password."),
+    (
+        "corrupted_file_error",
+        "The wallet file is damaged. Restore it from a backup.",
+    ),
+    (
+        "too_many_attempts_error",
+        "Too many failed attempts. Please wait before trying again.",
+    ),
+    (
+        "wallet_wiped_error",
+        "The wallet was deleted after too many failed attempts.",
+    ),
+    (
+        "keychain_error",
+        "The password could not be accessed in the keychain.",
+    ),
+    ("other_error", "An error occurred."),
+    ("send_tx_error", "Sending the transaction failed."),
+    ("create_tx_error", "Creating the transaction failed."),
+    ("balance_insufficient", "Not enough funds available."),
+    (
+        "max_input_count_exceeded",
+        "Maximum number of inputs exceeded.",
+    ),
+    (
+        "utxo_reserved_error",
+        "The coins are already being spent by another transaction.",
+    ),
+    (
+        "change_key_reserved_error",
+        "The change address is already being used by another transaction.",
+    ),
+    (
+        "utxo_unavailable_error",
+        "A selected coin is not available.",
+    ),
+    ("utxo_frozen_error", "A selected coin is frozen."),
+    (
+        "invalid_strategy_error",
+        "The coin selection strategy is unknown.",
+    ),
+    (
+        "outpoints_missing_error",
+        "Manual coin selection requires the coins to spend.",
+    ),
+    ("invalid_server_url_error", "The server URL is invalid."),
+    ("connection_error", "Unable to connect to the server."),
+    (
+        "invalid_rate_limit_error",
+        "The rate limit must allow at least one request per second.",
+    ),
+    (
+        "wrong_network_error",
+        "The server belongs to a different network than the selected one.",
+    ),
+    (
+        "offline_error",
+        "The wallet is in the offline mode and cannot connect to the network.",
+    ),
+    ("invalid_tx_error", "The transaction is invalid."),
+    ("invalid_currency_error", "The currency is not supported."),
+    (
+        "empty_tx_error",
+        "The transaction has no inputs or no outputs.",
+    ),
+    ("unsigned_tx_error", "The transaction is not signed."),
+    (
+        "duplicate_input_error",
+        "The transaction spends the same output more than once.",
+    ),
+    (
+        "dust_output_error",
+        "The transaction has an output below the dust limit.",
+    ),
+    (
+        "tx_too_large_error",
+        "The transaction is too large to be relayed.",
+    ),
+    (
+        "timeout_error",
+        "The server did not respond in time. Try again later.",
+    ),
+    (
+        "rate_limited_error",
+        "The server received too many requests. Try again later.",
+    ),
+    (
+        "server_unavailable_error",
+        "The server is unavailable. Try again later.",
+    ),
+    (
+        "prepared_tx_missing_error",
+        "The transaction was already broadcast or discarded.",
+    ),
+    (
+        "seed_unavailable_error",
+        "The seed of this wallet is not available.",
+    ),
+    ("invalid_share_error", "The recovery shares are invalid."),
+    (
+        "invalid_share_parameters_error",
+        "The threshold or the number of shares is invalid.",
+    ),
+    (
+        "invalid_word_count_error",
+        "The number of words is invalid.",
+    ),
+    ("invalid_address_error", "Invalid address."),
+    (
+        "invalid_payment_uri_error",
+        "The payment request is invalid.",
+    ),
+    (
+        "unsupported_payment_uri_error",
+        "The payment request requires a feature that is not supported.",
+    ),
+    ("invalid_signature_error", "Invalid signature."),
+    ("invalid_derivation_path_error", "Invalid derivation path."),
+    ("wrong_address_type_error", "Wrong address type."),
+    (
+        "invalid_wallet_name_error",
+        "The wallet name may only contain letters, digits, dashes and underscores.",
+    ),
+    (
+        "wallet_exists_error",
+        "A wallet with this name already exists.",
+    ),
+    ("wallet_not_found_error", "The wallet does not exist."),
+    ("contact_not_found_error", "The contact does not exist."),
+    (
+        "contact_exists_error",
+        "A contact with this address exists already.",
+    ),
+    ("invalid_contact_name_error", "The contact needs a name."),
+    (
+        "public_key_unavailable_error",
+        "The wallet has to be unlocked with the password once.",
+    ),
+    ("invalid_backup_error", "The file is not a valid backup."),
+    ("account_not_found_error", "The account does not exist."),
+    (
+        "same_account_error",
+        "The source and the target account have to be different.",
+    ),
+    (
+        "setup_session_missing_error",
+        "The wallet setup has not been started.",
+    ),
+    (
+        "setup_session_expired_error",
+        "The wallet setup has expired. Please start again.",
+    ),
+    (
+        "setup_step_out_of_order_error",
+        "The steps of the wallet setup have to be completed in order.",
+    ),
+    (
+        "address_not_found_error",
+        "The address does not belong to this wallet.",
+    ),
+    (
+        "address_watched_error",
+        "This address is already being watched.",
+    ),
+    (
+        "invalid_label_error",
+        "The label type or reference is invalid.",
+    ),
+    (
+        "invalid_labels_error",
+        "The file does not contain labels in the BIP-329 format.",
+    ),
+    (
+        "invalid_secret_error",
+        "The recovery phrase or the private key is invalid.",
+    ),
+    (
+        "confirmation_required_error",
+        "Please confirm that you want to reveal the private key.",
+    ),
+    (
+        "tx_confirmed_error",
+        "The transaction is already confirmed.",
+    ),
+    (
+        "not_replaceable_error",
+        "The transaction cannot be replaced because it does not signal replace-by-fee.",
+    ),
+    (
+        "foreign_input_error",
+        "The transaction spends coins that do not belong to this account.",
+    ),
+    (
+        "change_output_missing_error",
+        "The transaction has no change from which the higher fee can be paid.",
+    ),
+    (
+        "spendable_output_missing_error",
+        "The transaction has no output that belongs to this account.",
+    ),
+    (
+        "invalid_psbt_error",
+        "The partially signed transaction (PSBT) is invalid.",
+    ),
+    (
+        "psbt_key_missing_error",
+        "The partially signed transaction (PSBT) has no input that can be signed by this wallet.",
+    ),
+    (
+        "psbt_mismatch_error",
+        "The partially signed transactions (PSBTs) do not belong to the same transaction.",
+    ),
+    (
+        "psbt_incomplete_error",
+        "The partially signed transaction (PSBT) is missing signatures.",
+    ),
+    (
+        "sighash_single_error",
+        "An input signed with SIGHASH_SINGLE needs an output with the same index.",
+    ),
+    (
+        "payjoin_unsupported_error",
+        "The transaction cannot be sent as a payjoin, so it was sent without the inputs of the receiver.",
+    ),
+    (
+        "invalid_payjoin_proposal_error",
+        "The receiver proposed an invalid payjoin, so the transaction was sent without its inputs.",
+    ),
+    (
+        "invalid_cosigner_error",
+        "The key of the cosigner is invalid or belongs to another network.",
+    ),
+    (
+        "duplicate_cosigner_error",
+        "The key of a cosigner is used more than once.",
+    ),
+    (
+        "invalid_threshold_error",
+        "The number of required signatures or the number of cosigners is invalid.",
+    ),
+    ("invalid_script_type_error", "Invalid script type."),
+    (
+        "invalid_data_error",
+        "The data has to be valid and must not exceed 80 bytes.",
+    ),
+    ("recipient_missing_error", "Please specify a recipient."),
+    (
+        "amount_below_dust_error",
+        "The amount is too small to be sent.",
+    ),
+    (
+        "invalid_amount_error",
+        "The amount has to be greater than zero and must not exceed 21 million bitcoin.",
+    ),
+    (
+        "invalid_export_format_error",
+        "The transactions can only be exported as CSV or JSON.",
+    ),
+];
+
+static GERMAN: &[(&'static str, &'static str)] = &[
+    (
+        "io_error",
+        "Auf die Wallet-Datei oder den Server kann nicht zugegriffen werden.",
+    ),
+    ("wrong_password_error", "Falsches Passwort."),
+    (
+        "corrupted_file_error",
+        "Die Wallet-Datei ist beschädigt. Stellen Sie sie aus einer Sicherung wieder her.",
+    ),
+    (
+        "too_many_attempts_error",
+        "Zu viele Fehlversuche. Bitte warten Sie, bevor Sie es erneut versuchen.",
+    ),
+    (
+        "wallet_wiped_error",
+        "Die Wallet wurde nach zu vielen Fehlversuchen gelöscht.",
+    ),
+    (
+        "keychain_error",
+        "Auf das Passwort im Schlüsselbund konnte nicht zugegriffen werden.",
+    ),
+    ("other_error", "Ein Fehler ist aufgetreten."),
+    (
+        "send_tx_error",
+        "Die Transaktion konnte nicht gesendet werden.",
+    ),
+    (
+        "create_tx_error",
+        "Die Transaktion konnte nicht erstellt werden.",
+    ),
+    ("balance_insufficient", "Das Guthaben reicht nicht aus."),
+    (
+        "max_input_count_exceeded",
+        "Die maximale Anzahl an Inputs wurde überschritten.",
+    ),
+    (
+        "utxo_reserved_error",
+        "Die Coins werden bereits von einer anderen Transaktion ausgegeben.",
+    ),
+    (
+        "change_key_reserved_error",
+        "Die Wechselgeldadresse wird bereits von einer anderen Transaktion verwendet.",
+    ),
+    (
+        "utxo_unavailable_error",
+        "Ein ausgewählter Coin ist nicht verfügbar.",
+    ),
+    (
+        "utxo_frozen_error",
+        "Ein ausgewählter Coin ist eingefroren.",
+    ),
+    (
+        "invalid_strategy_error",
+        "Die Strategie zur Coin-Auswahl ist unbekannt.",
+    ),
+    (
+        "outpoints_missing_error",
+        "Die manuelle Coin-Auswahl erfordert die auszugebenden Coins.",
+    ),
+    ("invalid_server_url_error", "Die Server-URL ist ungültig."),
+    ("connection_error", "Verbindung zum Server fehlgeschlagen."),
+    (
+        "invalid_rate_limit_error",
+        "Das Limit muss mindestens eine Anfrage pro Sekunde erlauben.",
+    ),
+    (
+        "wrong_network_error",
+        "Der Server gehört zu einem anderen Netzwerk als dem ausgewählten.",
+    ),
+    (
+        "offline_error",
+        "Die Wallet ist im Offline-Modus und kann keine Verbindung zum Netzwerk herstellen.",
+    ),
+    ("invalid_tx_error", "Die Transaktion ist ungültig."),
+    ("invalid_currency_error", "Die Währung wird nicht unterstützt."),
+    (
+        "empty_tx_error",
+        "Die Transaktion hat keine Inputs oder keine Outputs.",
+    ),
+    ("unsigned_tx_error", "Die Transaktion ist nicht signiert."),
+    (
+        "duplicate_input_error",
+        "Die Transaktion gibt denselben Output mehrfach aus.",
+    ),
+    (
+        "dust_output_error",
+        "Die Transaktion hat einen Output unterhalb der Dust-Grenze.",
+    ),
+    (
+        "tx_too_large_error",
+        "Die Transaktion ist zu groß, um weitergeleitet zu werden.",
+    ),
+    (
+        "timeout_error",
+        "Der Server hat nicht rechtzeitig geantwortet. Bitte versuchen Sie es später erneut.",
+    ),
+    (
+        "rate_limited_error",
+        "Der Server hat zu viele Anfragen erhalten. Bitte versuchen Sie es später erneut.",
+    ),
+    (
+        "server_unavailable_error",
+        "Der Server ist nicht erreichbar. Bitte versuchen Sie es später erneut.",
+    ),
+    (
+        "prepared_tx_missing_error",
+        "Die Transaktion wurde bereits gesendet oder verworfen.",
+    ),
+    (
+        "seed_unavailable_error",
+        "Der Seed dieser Wallet ist nicht verfügbar.",
+    ),
+    (
+        "invalid_share_error",
+        "Die Wiederherstellungsanteile sind ungültig.",
+    ),
+    (
+        "invalid_share_parameters_error",
+        "Der Schwellenwert oder die Anzahl der Anteile ist ungültig.",
+    ),
+    (
+        "invalid_word_count_error",
+        "Die Anzahl der Wörter ist ungültig.",
+    ),
+    ("invalid_address_error", "Ungültige Adresse."),
+    (
+        "invalid_payment_uri_error",
+        "Die Zahlungsanforderung ist ungültig.",
+    ),
+    (
+        "unsupported_payment_uri_error",
+        "Die Zahlungsanforderung erfordert eine Funktion, die nicht unterstützt wird.",
+    ),
+    ("invalid_signature_error", "Ungültige Signatur."),
+    (
+        "invalid_derivation_path_error",
+        "Ungültiger Ableitungspfad.",
+    ),
+    ("wrong_address_type_error", "Falscher Adresstyp."),
+    (
+        "invalid_wallet_name_error",
+        "Der Name der Wallet darf nur Buchstaben, Ziffern, Binde- und Unterstriche enthalten.",
+    ),
+    (
+        "wallet_exists_error",
+        "Eine Wallet mit diesem Namen existiert bereits.",
+    ),
+    ("wallet_not_found_error", "Die Wallet existiert nicht."),
+    ("contact_not_found_error", "Der Kontakt existiert nicht."),
+    (
+        "contact_exists_error",
+        "Ein Kontakt mit dieser Adresse existiert bereits.",
+    ),
+    ("invalid_contact_name_error", "Der Kontakt benötigt einen Namen."),
+    (
+        "public_key_unavailable_error",
+        "Die Wallet muss einmal mit dem Passwort entsperrt werden.",
+    ),
+    (
+        "invalid_backup_error",
+        "Die Datei ist kein gültiges Backup.",
+    ),
+    ("account_not_found_error", "Das Konto existiert nicht."),
+    (
+        "same_account_error",
+        "Das Quell- und das Zielkonto müssen verschieden sein.",
+    ),
+    (
+        "setup_session_missing_error",
+        "Die Einrichtung der Wallet wurde nicht gestartet.",
+    ),
+    (
+        "setup_session_expired_error",
+        "Die Einrichtung der Wallet ist abgelaufen. Bitte beginnen Sie erneut.",
+    ),
+    (
+        "setup_step_out_of_order_error",
+        "Die Schritte der Einrichtung müssen der Reihe nach ausgeführt werden.",
+    ),
+    (
+        "address_not_found_error",
+        "Die Adresse gehört nicht zu dieser Wallet.",
+    ),
+    (
+        "address_watched_error",
+        "Diese Adresse wird bereits beobachtet.",
+    ),
+    (
+        "invalid_label_error",
+        "Der Typ oder die Referenz des Labels ist ungültig.",
+    ),
+    (
+        "invalid_labels_error",
+        "Die Datei enthält keine Labels im BIP-329-Format.",
+    ),
+    (
+        "invalid_secret_error",
+        "Die Wiederherstellungsphrase oder der private Schlüssel ist ungültig.",
+    ),
+    (
+        "confirmation_required_error",
+        "Bitte bestätigen Sie, dass der private Schlüssel angezeigt werden soll.",
+    ),
+    (
+        "tx_confirmed_error",
+        "Die Transaktion ist bereits bestätigt.",
+    ),
+    (
+        "not_replaceable_error",
+        "Die Transaktion kann nicht ersetzt werden, da sie kein Replace-by-Fee signalisiert.",
+    ),
+    (
+        "foreign_input_error",
+        "Die Transaktion gibt Coins aus, die nicht zu diesem Konto gehören.",
+    ),
+    (
+        "change_output_missing_error",
+        "Die Transaktion hat kein Wechselgeld, aus dem die höhere Gebühr bezahlt werden kann.",
+    ),
+    (
+        "spendable_output_missing_error",
+        "Die Transaktion hat keinen Output, der zu diesem Konto gehört.",
+    ),
+    (
+        "invalid_psbt_error",
+        "Die teilweise signierte Transaktion (PSBT) ist ungültig.",
+    ),
+    (
+        "psbt_key_missing_error",
+        "Die teilweise signierte Transaktion (PSBT) hat keinen Input, den diese Wallet signieren kann.",
+    ),
+    (
+        "psbt_mismatch_error",
+        "Die teilweise signierten Transaktionen (PSBTs) gehören nicht zur selben Transaktion.",
+    ),
+    (
+        "psbt_incomplete_error",
+        "Der teilweise signierten Transaktion (PSBT) fehlen Signaturen.",
+    ),
+    (
+        "sighash_single_error",
+        "Ein mit SIGHASH_SINGLE signierter Input benötigt einen Output mit demselben Index.",
+    ),
+    (
+        "payjoin_unsupported_error",
+        "Die Transaktion kann nicht als Payjoin gesendet werden und wurde daher ohne die Inputs des Empfängers gesendet.",
+    ),
+    (
+        "invalid_payjoin_proposal_error",
+        "Der Empfänger hat einen ungültigen Payjoin vorgeschlagen, daher wurde die Transaktion ohne seine Inputs gesendet.",
+    ),
+    (
+        "invalid_cosigner_error",
+        "Der Schlüssel des Mitunterzeichners ist ungültig oder gehört zu einem anderen Netzwerk.",
+    ),
+    (
+        "duplicate_cosigner_error",
+        "Der Schlüssel eines Mitunterzeichners wird mehrfach verwendet.",
+    ),
+    (
+        "invalid_threshold_error",
+        "Die Anzahl der erforderlichen Signaturen oder der Mitunterzeichner ist ungültig.",
+    ),
+    ("invalid_script_type_error", "Ungültiger Skripttyp."),
+    (
+        "invalid_data_error",
+        "Die Daten müssen gültig sein und dürfen 80 Bytes nicht überschreiten.",
+    ),
+    ("recipient_missing_error", "Bitte geben Sie einen Empfänger an."),
+    (
+        "amount_below_dust_error",
+        "Der Betrag ist zu klein, um gesendet zu werden.",
+    ),
+    (
+        "invalid_amount_error",
+        "Der Betrag muss größer als null sein und darf 21 Millionen Bitcoin nicht überschreiten.",
+    ),
+    (
+        "invalid_export_format_error",
+        "Die Transaktionen können nur als CSV oder JSON exportiert werden.",
+    ),
+];
+
+lazy_static! {
+    static ref CATALOGS: HashMap<&'static str, &'static [(&'static str, &'static str)]> = {
+        let mut m = HashMap::new();
+        m.insert("en", ENGLISH);
+        m.insert("de", GERMAN);
+        m
+    };
+}
+
+/// Returns the message catalog for the specified locale (e.g. `de` or `de-CH`).
+/// Codes that are not translated fall back to the English message.
+pub fn get_message_catalog(locale: &str) -> HashMap<String, String> {
+    let language = locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or(DEFAULT_LOCALE)
+        .to_lowercase();
+    let mut catalog: HashMap<String, String> = ENGLISH
+        .iter()
+        .map(|(code, message)| (code.to_string(), message.to_string()))
+        .collect();
+    if let Some(messages) = CATALOGS.get(language.as_str()) {
+        for (code, message) in messages.iter() {
+            catalog.insert(code.to_string(), message.to_string());
+        }
+    }
+    catalog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_message_catalog, ENGLISH, GERMAN};
+
+    #[test]
+    fn test_get_message_catalog() {
+        let catalog = get_message_catalog("de-CH");
+        assert_eq!(catalog["wrong_password_error"], "Falsches Passwort.");
+        let catalog = get_message_catalog("fr");
+        assert_eq!(catalog["wrong_password_error"], "Wrong password.");
+        // every locale translates every code
+        for (code, _) in ENGLISH {
+            assert!(GERMAN.iter().any(|(other, _)| other == code));
+        }
+        assert_eq!(ENGLISH.len(), GERMAN.len());
+    }
+}
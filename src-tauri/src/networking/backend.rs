@@ -0,0 +1,299 @@
+//! Defines the interface between the wallet and the blockchain.
+//! By default, the [mempool.space API](https://mempool.space/docs/api/rest) is used.
+//! A self-hosted mempool or Esplora instance can be selected in the network settings.
+//! An Electrum server can be selected in the network settings instead.
+//! If the environment variable `GHORBU_FIXTURES` points to a directory,
+//! the recorded fixtures in that directory are served instead (see `fixtures`).
+use super::broadcast::BroadcastBackend;
+use super::electrum::{ElectrumBackend, ElectrumServer};
+use super::error::LiveUpdatesUnsupportedError;
+use super::fee::{FeeHistogram, Fees};
+use super::fee_fallback::FeeFallbackBackend;
+use super::fixtures::FixtureBackend;
+use super::live::LiveEvent;
+use super::mempool::{default_test_api_url, parse_api_url, MempoolBackend};
+use super::offline::OfflineBackend;
+use super::settings::{BackendSettings, NetworkSettings};
+use crate::keys::address::Address;
+use crate::keys::network::TestNetwork;
+use crate::transactions::transaction::Transaction;
+use crate::transactions::utxo::UTXO;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+pub static FIXTURES_ENV_VAR: &'static str = "GHORBU_FIXTURES";
+
+/// The maximum number of queries that are sent concurrently
+/// when the addresses of an account are queried.
+pub static MAX_CONCURRENT_QUERIES: usize = 8;
+
+/// The most recent block of the chain.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChainTip {
+    pub height: u32,
+    pub hash: String,
+    pub timestamp: u64, // UNIX timestamp of the block header in seconds
+}
+
+#[async_trait]
+pub trait ChainBackend: Send + Sync {
+    /// Returns all UTXOs for the specified address.
+    async fn get_address_utxos(&self, address: &Address) -> Result<Vec<UTXO>, Box<dyn Error>>;
+
+    /// Returns all transactions for the specified address.
+    async fn get_address_transactions(
+        &self,
+        address: &Address,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>>;
+
+    /// Returns the transaction for the specified transaction ID.
+    async fn get_transaction(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<Transaction, Box<dyn Error>>;
+
+    /// Returns true if the backend knows the transaction with the specified
+    /// transaction ID, i.e., it is either in the mempool or in a block.
+    async fn is_transaction_known(&self, txid: &str, testnet: bool)
+        -> Result<bool, Box<dyn Error>>;
+
+    /// Broadcasts a signed transaction.
+    async fn send_transaction(&self, tx: Transaction, testnet: bool) -> Result<(), Box<dyn Error>>;
+
+    /// Returns the current recommended fees.
+    async fn get_recommended_fees(&self, coin_type_index: u32) -> Result<Fees, Box<dyn Error>>;
+
+    /// Returns the height of the most recent block.
+    async fn get_tip_height(&self, testnet: bool) -> Result<u32, Box<dyn Error>>;
+
+    /// Returns the height, the hash and the timestamp of the most recent block.
+    async fn get_chain_tip(&self, testnet: bool) -> Result<ChainTip, Box<dyn Error>>;
+
+    /// Returns the fee histogram of the mempool.
+    async fn get_fee_histogram(&self, testnet: bool) -> Result<FeeHistogram, Box<dyn Error>>;
+
+    /// Returns the hash of the block at the specified height.
+    async fn get_block_hash(&self, height: u32, testnet: bool) -> Result<String, Box<dyn Error>>;
+
+    /// Watches the addresses and the chain and passes the live events to the callback
+    /// until the connection to the backend is lost.
+    /// By default, the backend does not support live updates.
+    async fn watch(
+        &self,
+        _addresses: Vec<Address>,
+        _testnet: bool,
+        _on_event: &(dyn Fn(LiveEvent) + Send + Sync),
+    ) -> Result<(), Box<dyn Error>> {
+        Err(Box::new(LiveUpdatesUnsupportedError {}))
+    }
+}
+
+/// Delegates to the selected backend, so the backend can be
+/// changed in the settings while the wallet is running.
+pub struct SelectableBackend {
+    backend: RwLock<Arc<dyn ChainBackend>>,
+}
+
+impl SelectableBackend {
+    pub fn new(backend: Arc<dyn ChainBackend>) -> SelectableBackend {
+        SelectableBackend {
+            backend: RwLock::new(backend),
+        }
+    }
+
+    /// Selects the backend that answers all following queries.
+    pub fn select(&self, backend: Arc<dyn ChainBackend>) {
+        *self.backend.write().unwrap() = backend;
+    }
+
+    /// Returns the selected backend.
+    pub fn current(&self) -> Arc<dyn ChainBackend> {
+        self.backend.read().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl ChainBackend for SelectableBackend {
+    async fn get_address_utxos(&self, address: &Address) -> Result<Vec<UTXO>, Box<dyn Error>> {
+        self.current().get_address_utxos(address).await
+    }
+
+    async fn get_address_transactions(
+        &self,
+        address: &Address,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        self.current().get_address_transactions(address).await
+    }
+
+    async fn get_transaction(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        self.current().get_transaction(txid, testnet).await
+    }
+
+    async fn is_transaction_known(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.current().is_transaction_known(txid, testnet).await
+    }
+
+    async fn send_transaction(&self, tx: Transaction, testnet: bool) -> Result<(), Box<dyn Error>> {
+        self.current().send_transaction(tx, testnet).await
+    }
+
+    async fn get_recommended_fees(&self, coin_type_index: u32) -> Result<Fees, Box<dyn Error>> {
+        self.current().get_recommended_fees(coin_type_index).await
+    }
+
+    async fn get_tip_height(&self, testnet: bool) -> Result<u32, Box<dyn Error>> {
+        self.current().get_tip_height(testnet).await
+    }
+
+    async fn get_chain_tip(&self, testnet: bool) -> Result<ChainTip, Box<dyn Error>> {
+        self.current().get_chain_tip(testnet).await
+    }
+
+    async fn get_fee_histogram(&self, testnet: bool) -> Result<FeeHistogram, Box<dyn Error>> {
+        self.current().get_fee_histogram(testnet).await
+    }
+
+    async fn get_block_hash(&self, height: u32, testnet: bool) -> Result<String, Box<dyn Error>> {
+        self.current().get_block_hash(height, testnet).await
+    }
+
+    async fn watch(
+        &self,
+        addresses: Vec<Address>,
+        testnet: bool,
+        on_event: &(dyn Fn(LiveEvent) + Send + Sync),
+    ) -> Result<(), Box<dyn Error>> {
+        self.current().watch(addresses, testnet, on_event).await
+    }
+}
+
+/// Runs the query for each address with at most `MAX_CONCURRENT_QUERIES` queries in flight.
+/// The results are returned in the order of the addresses. The first error is returned.
+pub async fn query_addresses<'a, T, F, Fut>(
+    addresses: &'a [Address],
+    query: F,
+) -> Result<Vec<T>, Box<dyn Error>>
+where
+    F: Fn(&'a Address) -> Fut,
+    Fut: Future<Output = Result<T, Box<dyn Error>>> + 'a,
+{
+    // the queries are lazy, so only the buffered ones are in flight
+    let queries: Vec<Fut> = addresses.iter().map(query).collect();
+    let mut queries = stream::iter(queries.into_iter().enumerate())
+        .map(|(index, query)| async move { (index, query.await) })
+        .buffer_unordered(MAX_CONCURRENT_QUERIES);
+    let mut results = Vec::with_capacity(addresses.len());
+    while let Some((index, res)) = queries.next().await {
+        results.push((index, res?));
+    }
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+/// Returns the backend that is selected by the settings.
+/// The fixtures of the environment take precedence over the settings.
+pub fn create_backend(settings: &NetworkSettings) -> Arc<dyn ChainBackend> {
+    match env::var(FIXTURES_ENV_VAR) {
+        Ok(path) if !path.is_empty() => return Arc::new(FixtureBackend::new(path)),
+        _ => {}
+    }
+    create_settings_backend(settings)
+}
+
+/// Returns the backend that is described by the settings. Transactions are also
+/// broadcast through the broadcast endpoints of the settings and the fee fallbacks
+/// of the settings are queried if the backend cannot provide the fees.
+/// In the offline mode, a backend that cannot be queried is returned.
+pub fn create_settings_backend(settings: &NetworkSettings) -> Arc<dyn ChainBackend> {
+    if settings.offline {
+        return Arc::new(OfflineBackend);
+    }
+    let backend = create_chain_backend(&settings.backend, settings.test_network);
+    let backend = Arc::new(FeeFallbackBackend::new(backend, &settings.fee_fallbacks));
+    if settings.broadcast_endpoints.is_empty() {
+        return backend;
+    }
+    Arc::new(BroadcastBackend::new(
+        backend,
+        &settings.broadcast_endpoints,
+    ))
+}
+
+/// Returns the backend that is described by the backend settings.
+/// Invalid URLs are ignored, since the settings are validated when they are saved.
+fn create_chain_backend(
+    settings: &BackendSettings,
+    test_network: TestNetwork,
+) -> Arc<dyn ChainBackend> {
+    match settings {
+        BackendSettings::Mempool { url, testnet_url } => {
+            let api_url =
+                |url: &Option<String>| url.as_ref().and_then(|url| parse_api_url(url).ok());
+            let testnet_api_url =
+                api_url(testnet_url).unwrap_or_else(|| default_test_api_url(test_network));
+            Arc::new(MempoolBackend::new(api_url(url), Some(testnet_api_url)))
+        }
+        BackendSettings::Electrum { url, testnet_url } => {
+            let server =
+                |url: &Option<String>| url.as_ref().and_then(|url| ElectrumServer::parse(url).ok());
+            Arc::new(ElectrumBackend::new(server(url), server(testnet_url)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{query_addresses, MAX_CONCURRENT_QUERIES};
+    use crate::keys::address::Address;
+    use std::error::Error;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_query_addresses() {
+        let address = Address::from_str("mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1").unwrap();
+        let addresses = vec![address; 3 * MAX_CONCURRENT_QUERIES];
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let next = AtomicUsize::new(0);
+        let results = query_addresses(&addresses, |_| async {
+            let index = next.fetch_add(1, Ordering::SeqCst);
+            let count = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(count, Ordering::SeqCst);
+            // later queries finish first
+            let delay = (3 * MAX_CONCURRENT_QUERIES - index) as u64;
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok::<usize, Box<dyn Error>>(index)
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            results,
+            (0..3 * MAX_CONCURRENT_QUERIES).collect::<Vec<usize>>()
+        );
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), MAX_CONCURRENT_QUERIES);
+
+        let err = query_addresses(&addresses, |_| async {
+            Err::<usize, Box<dyn Error>>("unavailable".into())
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "unavailable");
+    }
+}
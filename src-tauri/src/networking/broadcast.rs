@@ -0,0 +1,262 @@
+//! Implements the broadcast of transactions through multiple endpoints. A transaction
+//! is sent to the selected backend and to additional APIs that are compatible with
+//! the mempool.space API, e.g., Blockstream or the Esplora instance of a node.
+//! The broadcast succeeds if any endpoint accepts the transaction, so a single
+//! unavailable or censoring endpoint does not prevent the payment.
+use super::backend::{ChainBackend, ChainTip};
+use super::error::{
+    BroadcastError, EndpointFailure, RequestError, RequestErrorKind, SendTransactionError,
+};
+use super::fee::{FeeHistogram, Fees};
+use super::live::LiveEvent;
+use super::mempool::{parse_api_url, MempoolBackend, BLOCKSTREAM_API, BLOCKSTREAM_TESTNET_API};
+use crate::keys::address::Address;
+use crate::transactions::transaction::Transaction;
+use crate::transactions::utxo::UTXO;
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::Arc;
+
+// the name of the selected backend in the failures of a broadcast
+static BACKEND_ENDPOINT: &'static str = "backend";
+
+/// The API URLs that transactions are broadcast to in addition to the selected backend.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BroadcastEndpoints {
+    #[serde(default)]
+    pub urls: Vec<String>,
+    #[serde(default)]
+    pub testnet_urls: Vec<String>,
+}
+
+impl Default for BroadcastEndpoints {
+    fn default() -> Self {
+        BroadcastEndpoints {
+            urls: vec![BLOCKSTREAM_API.to_string()],
+            testnet_urls: vec![BLOCKSTREAM_TESTNET_API.to_string()],
+        }
+    }
+}
+
+impl BroadcastEndpoints {
+    /// Returns true if there are no additional endpoints.
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty() && self.testnet_urls.is_empty()
+    }
+}
+
+struct Endpoint {
+    url: String,
+    testnet: bool,
+    backend: MempoolBackend,
+}
+
+/// Broadcasts transactions through the selected backend and the additional endpoints.
+/// All other queries are answered by the selected backend.
+pub struct BroadcastBackend {
+    backend: Arc<dyn ChainBackend>,
+    endpoints: Vec<Endpoint>,
+}
+
+impl BroadcastBackend {
+    /// Creates a backend that broadcasts through the backend and the endpoints.
+    /// Invalid URLs are ignored, since the settings are validated when they are saved.
+    pub fn new(backend: Arc<dyn ChainBackend>, endpoints: &BroadcastEndpoints) -> BroadcastBackend {
+        let networks = [(&endpoints.urls, false), (&endpoints.testnet_urls, true)];
+        let endpoints = networks
+            .into_iter()
+            .flat_map(|(urls, testnet)| urls.iter().map(move |url| (url, testnet)))
+            .filter_map(|(url, testnet)| parse_api_url(url).ok().map(|url| (url, testnet)))
+            .map(|(url, testnet)| Endpoint {
+                backend: MempoolBackend::new(Some(url.clone()), Some(url.clone())),
+                url,
+                testnet,
+            })
+            .collect();
+        BroadcastBackend { backend, endpoints }
+    }
+}
+
+#[async_trait]
+impl ChainBackend for BroadcastBackend {
+    async fn get_address_utxos(&self, address: &Address) -> Result<Vec<UTXO>, Box<dyn Error>> {
+        self.backend.get_address_utxos(address).await
+    }
+
+    async fn get_address_transactions(
+        &self,
+        address: &Address,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        self.backend.get_address_transactions(address).await
+    }
+
+    async fn get_transaction(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        self.backend.get_transaction(txid, testnet).await
+    }
+
+    async fn is_transaction_known(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.backend.is_transaction_known(txid, testnet).await
+    }
+
+    /// Sends the transaction to all endpoints of the network concurrently.
+    /// Returns as soon as an endpoint accepts the transaction. Otherwise, a
+    /// `BroadcastError` with the failure of every endpoint is returned.
+    async fn send_transaction(&self, tx: Transaction, testnet: bool) -> Result<(), Box<dyn Error>> {
+        let mut broadcasts: FuturesUnordered<_> = self
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.testnet == testnet)
+            .map(|endpoint| {
+                (
+                    endpoint.url.as_str(),
+                    endpoint.backend.send_transaction(tx.clone(), testnet),
+                )
+            })
+            .chain([(
+                BACKEND_ENDPOINT,
+                self.backend.send_transaction(tx.clone(), testnet),
+            )])
+            .map(|(endpoint, broadcast)| async move { (endpoint, broadcast.await) })
+            .collect();
+        let mut failures = Vec::new();
+        while let Some((endpoint, res)) = broadcasts.next().await {
+            match res {
+                Ok(()) => return Ok(()),
+                Err(err) => failures.push(EndpointFailure {
+                    endpoint: endpoint.to_string(),
+                    error: err.to_string(),
+                    rejected: is_rejection(err.as_ref()),
+                }),
+            }
+        }
+        Err(Box::new(BroadcastError { failures }))
+    }
+
+    async fn get_recommended_fees(&self, coin_type_index: u32) -> Result<Fees, Box<dyn Error>> {
+        self.backend.get_recommended_fees(coin_type_index).await
+    }
+
+    async fn get_tip_height(&self, testnet: bool) -> Result<u32, Box<dyn Error>> {
+        self.backend.get_tip_height(testnet).await
+    }
+
+    async fn get_chain_tip(&self, testnet: bool) -> Result<ChainTip, Box<dyn Error>> {
+        self.backend.get_chain_tip(testnet).await
+    }
+
+    async fn get_fee_histogram(&self, testnet: bool) -> Result<FeeHistogram, Box<dyn Error>> {
+        self.backend.get_fee_histogram(testnet).await
+    }
+
+    async fn get_block_hash(&self, height: u32, testnet: bool) -> Result<String, Box<dyn Error>> {
+        self.backend.get_block_hash(height, testnet).await
+    }
+
+    async fn watch(
+        &self,
+        addresses: Vec<Address>,
+        testnet: bool,
+        on_event: &(dyn Fn(LiveEvent) + Send + Sync),
+    ) -> Result<(), Box<dyn Error>> {
+        self.backend.watch(addresses, testnet, on_event).await
+    }
+}
+
+/// Returns true if the endpoint received the transaction and rejected it.
+fn is_rejection(err: &(dyn Error + 'static)) -> bool {
+    if err.is::<SendTransactionError>() {
+        return true;
+    }
+    match err.downcast_ref::<RequestError>() {
+        Some(err) => matches!(err.kind, RequestErrorKind::Rejected(_)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BroadcastBackend, BroadcastEndpoints};
+    use crate::networking::backend::ChainBackend;
+    use crate::networking::error::BroadcastError;
+    use crate::networking::mempool::MempoolBackend;
+    use crate::transactions::transaction::Transaction;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves an API that answers every request with the status and the body.
+    async fn serve(status: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 4096];
+                let _ = stream.read(&mut buf).await.unwrap();
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://127.0.0.1:{}/api", port)
+    }
+
+    #[tokio::test]
+    async fn test_send_transaction() {
+        let tx: Transaction = serde_json::from_value(json!({
+            "txid": format!("{:064x}", 1),
+            "version": 2,
+            "locktime": 0,
+            "vin": [],
+            "vout": []
+        }))
+        .unwrap();
+        let rejecting = serve("400 Bad Request", "bad-txns-vin-empty").await;
+        let accepting = serve("200 OK", "0000").await;
+        let backend = || Arc::new(MempoolBackend::new(None, Some(rejecting.clone())));
+
+        // an endpoint accepts the transaction
+        let endpoints = BroadcastEndpoints {
+            urls: Vec::new(),
+            testnet_urls: vec![accepting.clone()],
+        };
+        let broadcast_backend = BroadcastBackend::new(backend(), &endpoints);
+        broadcast_backend
+            .send_transaction(tx.clone(), true)
+            .await
+            .unwrap();
+
+        // every endpoint rejects the transaction
+        let endpoints = BroadcastEndpoints {
+            urls: vec![accepting.clone()],
+            testnet_urls: vec![rejecting.clone()],
+        };
+        let broadcast_backend = BroadcastBackend::new(backend(), &endpoints);
+        let err = broadcast_backend
+            .send_transaction(tx, true)
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<BroadcastError>().unwrap();
+        assert_eq!(err.failures.len(), 2);
+        assert!(err
+            .failures
+            .iter()
+            .any(|failure| failure.endpoint == rejecting));
+        assert!(err.is_rejected());
+    }
+}
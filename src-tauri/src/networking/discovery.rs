@@ -0,0 +1,350 @@
+//! Implements account discovery as described in
+//! [BIP-44](https://github.com/bitcoin/bips/blob/master/bip-0044.mediawiki#account-discovery).
+//! Accounts are scanned in order and the discovery stops at the first account
+//! that has no transactions on its external chain. The progress of the
+//! discovery can be persisted, so an interrupted discovery does not have
+//! to start over.
+use super::backend::ChainBackend;
+use crate::keys::bip32::ExtendedPublicKey;
+use crate::keys::bip44::{self, MasterPrivateKey};
+use crate::utils::file;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Called with the progress of a discovery whenever it changes, e.g., to persist it.
+type Checkpoint<'a> = dyn FnMut(&ScanProgress) -> Result<(), Box<dyn Error>> + Send + 'a;
+
+/// The number of consecutive unused addresses after which a chain is
+/// considered to be fully scanned.
+pub static GAP_LIMIT: u32 = 20;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredAccount {
+    pub index: u32,
+    pub last_receive_index: Option<u32>,
+    pub last_change_index: Option<u32>,
+}
+
+/// The progress of the scan of a single chain.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ChainProgress {
+    pub next_index: u32,
+    pub gap: u32,
+    pub last_used_index: Option<u32>,
+}
+
+/// The progress of an account discovery. The progress is persisted after
+/// every scanned address, so an interrupted discovery can be resumed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScanProgress {
+    pub coin_type_index: u32,
+    pub accounts: Vec<DiscoveredAccount>, // accounts that were fully scanned
+    pub account_index: u32,               // the account that is being scanned
+    pub external_chain: ChainProgress,
+    pub internal_chain: ChainProgress,
+}
+
+impl ChainProgress {
+    /// Returns true if `GAP_LIMIT` consecutive unused keys were found.
+    pub fn is_complete(&self) -> bool {
+        self.gap >= GAP_LIMIT
+    }
+}
+
+impl ScanProgress {
+    /// Creates the progress of a discovery that has not started yet.
+    pub fn new(coin_type_index: u32) -> ScanProgress {
+        ScanProgress {
+            coin_type_index,
+            accounts: Vec::new(),
+            account_index: 0,
+            external_chain: ChainProgress::default(),
+            internal_chain: ChainProgress::default(),
+        }
+    }
+
+    /// Saves the progress to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let progress_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &progress_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the progress from the specified path.
+    /// Returns `None` if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<ScanProgress>, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let progress_encoded = fs::read(path)?;
+        Ok(Some(serde_json::from_slice(&progress_encoded)?))
+    }
+}
+
+/// Checks the next key on the specified chain and updates the progress.
+/// A key is used if one of its addresses for the purpose has transactions.
+async fn scan_next_key(
+    backend: &dyn ChainBackend,
+    chain_key: &ExtendedPublicKey,
+    purpose_index: u32,
+    progress: &mut ChainProgress,
+) -> Result<(), Box<dyn Error>> {
+    let key = chain_key.derive_child_key(progress.next_index)?;
+    let mut used = false;
+    for address in bip44::purpose_addresses(&key, purpose_index) {
+        if !backend.get_address_transactions(&address).await?.is_empty() {
+            used = true;
+            break;
+        }
+    }
+    if !used {
+        progress.gap += 1;
+    } else {
+        progress.last_used_index = Some(progress.next_index);
+        progress.gap = 0;
+    }
+    progress.next_index += 1;
+    Ok(())
+}
+
+/// Returns the index of the last used key on the specified chain or `None`
+/// if no key was used. The scan stops after `GAP_LIMIT` consecutive unused keys.
+pub async fn scan_chain(
+    backend: &dyn ChainBackend,
+    chain_key: &ExtendedPublicKey,
+    purpose_index: u32,
+) -> Result<Option<u32>, Box<dyn Error>> {
+    let mut progress = ChainProgress::default();
+    while !progress.is_complete() {
+        scan_next_key(backend, chain_key, purpose_index, &mut progress).await?;
+    }
+    Ok(progress.last_used_index)
+}
+
+/// Returns all used accounts for the specified coin type. The addresses of the keys
+/// are the ones of the purpose of the key hierarchy.
+pub async fn discover_accounts(
+    backend: &dyn ChainBackend,
+    master_private_key: &MasterPrivateKey,
+    coin_type_index: u32,
+) -> Result<Vec<DiscoveredAccount>, Box<dyn Error>> {
+    let mut progress = ScanProgress::new(coin_type_index);
+    resume_discovery(backend, master_private_key, &mut progress, &mut |_| Ok(())).await
+}
+
+/// Continues the discovery from the specified progress and returns all used accounts.
+/// The checkpoint is called whenever the progress changes.
+pub async fn resume_discovery(
+    backend: &dyn ChainBackend,
+    master_private_key: &MasterPrivateKey,
+    progress: &mut ScanProgress,
+    checkpoint: &mut Checkpoint<'_>,
+) -> Result<Vec<DiscoveredAccount>, Box<dyn Error>> {
+    let purpose_index = master_private_key.purpose.index;
+    loop {
+        let account =
+            master_private_key.derive_account(progress.coin_type_index, progress.account_index);
+        let external_chain_key = account.external_chain.private_key.derive_public_key();
+        while !progress.external_chain.is_complete() {
+            scan_next_key(
+                backend,
+                &external_chain_key,
+                purpose_index,
+                &mut progress.external_chain,
+            )
+            .await?;
+            checkpoint(progress)?;
+        }
+        if progress.external_chain.last_used_index.is_none() {
+            break;
+        }
+        let internal_chain_key = account.internal_chain.private_key.derive_public_key();
+        while !progress.internal_chain.is_complete() {
+            scan_next_key(
+                backend,
+                &internal_chain_key,
+                purpose_index,
+                &mut progress.internal_chain,
+            )
+            .await?;
+            checkpoint(progress)?;
+        }
+        progress.accounts.push(DiscoveredAccount {
+            index: progress.account_index,
+            last_receive_index: progress.external_chain.last_used_index,
+            last_change_index: progress.internal_chain.last_used_index,
+        });
+        progress.account_index += 1;
+        progress.external_chain = ChainProgress::default();
+        progress.internal_chain = ChainProgress::default();
+        checkpoint(progress)?;
+    }
+    Ok(progress.accounts.clone())
+}
+
+/// Adds the discovered accounts and all keys up to the last used key
+/// of each chain to the key hierarchy.
+pub fn add_discovered_accounts(
+    master_private_key: &mut MasterPrivateKey,
+    coin_type_index: u32,
+    accounts: &[DiscoveredAccount],
+) {
+    for account in accounts {
+        master_private_key.add_account_with_index(coin_type_index, account.index);
+        if let Some(last_receive_index) = account.last_receive_index {
+            for key_index in 0..=last_receive_index {
+                master_private_key.new_receive_keypair(
+                    coin_type_index,
+                    account.index,
+                    Some(key_index),
+                );
+            }
+        }
+        if let Some(last_change_index) = account.last_change_index {
+            for key_index in 0..=last_change_index {
+                master_private_key.new_change_keypair(
+                    coin_type_index,
+                    account.index,
+                    Some(key_index),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_discovered_accounts, discover_accounts, resume_discovery, DiscoveredAccount,
+        ScanProgress,
+    };
+    use crate::keys::{
+        bip32::ExtendedPrivateKey,
+        bip44::{MasterPrivateKey, MasterPublicKey, BITCOIN_INDEX, BITCOIN_TESTNET_INDEX},
+    };
+    use crate::networking::fixtures::FixtureBackend;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_add_discovered_accounts() {
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        let accounts = vec![
+            DiscoveredAccount {
+                index: 0,
+                last_receive_index: Some(2),
+                last_change_index: None,
+            },
+            DiscoveredAccount {
+                index: 1,
+                last_receive_index: Some(0),
+                last_change_index: Some(4),
+            },
+        ];
+        add_discovered_accounts(&mut master_private_key, BITCOIN_TESTNET_INDEX, &accounts);
+
+        let receive_keypairs =
+            master_private_key.get_all_receive_keypairs(BITCOIN_TESTNET_INDEX, 0);
+        let change_keypairs = master_private_key.get_all_change_keypairs(BITCOIN_TESTNET_INDEX, 0);
+        assert_eq!(receive_keypairs.len(), 3);
+        assert_eq!(change_keypairs.len(), 0);
+        let receive_keypairs =
+            master_private_key.get_all_receive_keypairs(BITCOIN_TESTNET_INDEX, 1);
+        let change_keypairs = master_private_key.get_all_change_keypairs(BITCOIN_TESTNET_INDEX, 1);
+        assert_eq!(receive_keypairs.len(), 1);
+        assert_eq!(change_keypairs.len(), 5);
+
+        // the discovered keys match the keys derived by the public hierarchy
+        let account = master_private_key.derive_account(BITCOIN_TESTNET_INDEX, 0);
+        let mut master_public_key = MasterPublicKey::create_from_key(&master_private_key);
+        let next_key = master_public_key.new_receive_key(BITCOIN_TESTNET_INDEX, 0);
+        let target = account
+            .external_chain
+            .private_key
+            .derive_child_key(3, false)
+            .unwrap()
+            .derive_public_key();
+        assert_eq!(next_key.to_base58_check(), target.to_base58_check());
+    }
+
+    #[tokio::test]
+    async fn test_resume_discovery() {
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let master_private_key = MasterPrivateKey::create_from_key(private_key);
+
+        // the third receive address of the first account was used
+        let root = std::env::temp_dir().join("ghorbu_fixtures_test_resume_discovery");
+        let account = master_private_key.derive_account(BITCOIN_INDEX, 0);
+        let address = account
+            .external_chain
+            .private_key
+            .derive_child_key(2, false)
+            .unwrap()
+            .derive_public_key()
+            .get_address();
+        let address_dir = root
+            .join("mainnet")
+            .join("address")
+            .join(address.to_string());
+        fs::create_dir_all(&address_dir).unwrap();
+        let tx = json!({
+            "txid": "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+            "version": 1,
+            "locktime": 0,
+            "vin": [],
+            "vout": [],
+        });
+        fs::write(address_dir.join("txs.json"), json!([tx]).to_string()).unwrap();
+        let backend = FixtureBackend::new(&root);
+
+        // interrupt the discovery after a few keys
+        let path = root.join("scan");
+        let mut progress = ScanProgress::new(BITCOIN_INDEX);
+        let mut count = 0;
+        let res = resume_discovery(
+            &backend,
+            &master_private_key,
+            &mut progress,
+            &mut |progress| {
+                count += 1;
+                if count > 10 {
+                    return Err("interrupted".into());
+                }
+                progress.save(&path)
+            },
+        )
+        .await;
+        assert!(res.is_err());
+
+        let mut progress = ScanProgress::load(&path).unwrap().unwrap();
+        assert_eq!(progress.external_chain.next_index, 10);
+        assert_eq!(progress.external_chain.last_used_index, Some(2));
+        let accounts = resume_discovery(
+            &backend,
+            &master_private_key,
+            &mut progress,
+            &mut |progress| progress.save(&path),
+        )
+        .await
+        .unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].last_receive_index, Some(2));
+        assert_eq!(accounts[0].last_change_index, None);
+
+        let accounts_full = discover_accounts(&backend, &master_private_key, BITCOIN_INDEX)
+            .await
+            .unwrap();
+        assert_eq!(accounts_full.len(), accounts.len());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,795 @@
+//! Implements a backend that queries an Electrum server, e.g., a personal
+//! [ElectrumX](https://electrumx-spesmilo.readthedocs.io/en/latest/protocol.html)
+//! or Fulcrum server. The server is reached over TCP or TLS and speaks JSON-RPC
+//! with one message per line. An address is identified by the hash of its
+//! scriptPubKey, the script hash.
+use super::backend::{ChainBackend, ChainTip};
+use super::error::{ElectrumError, SendTransactionError};
+use super::fee::{sat_per_vbyte, FeeHistogram, Fees, CONFIRMATION_TARGETS};
+use super::live::LiveEvent;
+use super::rate_limit;
+use crate::keys::address::Address;
+use crate::transactions::script;
+use crate::transactions::transaction::{Transaction, TransactionStatus};
+use crate::transactions::utxo::{UTXOStatus, UTXO};
+use crate::utils::{hex, varint};
+use async_trait::async_trait;
+use bitcoin_hashes::{sha256, Hash};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{self, json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex as AsyncMutex;
+use tokio_native_tls::{native_tls, TlsConnector};
+
+pub static TCP_SCHEME: &'static str = "tcp://";
+pub static SSL_SCHEME: &'static str = "ssl://";
+static TCP_PORT: u16 = 50001;
+static SSL_PORT: u16 = 50002;
+static CLIENT_NAME: &'static str = "ghorbu-wallet";
+static PROTOCOL_VERSION: &'static str = "1.4";
+static REQUEST_TIMEOUT_SECS: u64 = 30;
+// the interval in which the notifications of the subscriptions are read
+static WATCH_INTERVAL_SECS: u64 = 10;
+static BLOCK_HEADER_SIZE: usize = 80;
+
+/// The address of an Electrum server, e.g., `ssl://electrum.example.com:50002`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElectrumServer {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+}
+
+impl ElectrumServer {
+    /// Parses the URL of an Electrum server. The scheme is either `tcp://` or `ssl://`
+    /// and the port defaults to 50001 or 50002, respectively.
+    pub fn parse(url: &str) -> Result<ElectrumServer, ElectrumError> {
+        let (tls, rest) = if let Some(rest) = url.strip_prefix(TCP_SCHEME) {
+            (false, rest)
+        } else if let Some(rest) = url.strip_prefix(SSL_SCHEME) {
+            (true, rest)
+        } else {
+            return Err(ElectrumError::new("Invalid scheme"));
+        };
+        let rest = rest.trim_end_matches('/');
+        let (host, port) = match rest.rsplit_once(':') {
+            Some((host, port)) => match port.parse() {
+                Ok(port) => (host, port),
+                Err(_) => return Err(ElectrumError::new("Invalid port")),
+            },
+            None => (rest, if tls { SSL_PORT } else { TCP_PORT }),
+        };
+        if host.is_empty() || host.contains('/') {
+            return Err(ElectrumError::new("Invalid host"));
+        }
+        Ok(ElectrumServer {
+            host: host.to_string(),
+            port,
+            tls,
+        })
+    }
+}
+
+trait AsyncStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncStream for T {}
+
+type Connection = BufReader<Box<dyn AsyncStream>>;
+
+#[derive(Deserialize)]
+struct HistoryEntry {
+    tx_hash: String,
+    height: i64, // zero or negative if the transaction is unconfirmed
+}
+
+#[derive(Deserialize)]
+struct UnspentEntry {
+    tx_hash: String,
+    tx_pos: u32,
+    height: i64,
+    value: u64,
+}
+
+/// A JSON-RPC client of an Electrum server. The connection is established by the
+/// first request and established again after it fails.
+pub struct ElectrumClient {
+    server: ElectrumServer,
+    connection: AsyncMutex<Option<Connection>>,
+    next_id: AtomicU64,
+    statuses: Mutex<HashMap<String, Option<String>>>, // the notified statuses by script hash
+    raw_transactions: Mutex<HashMap<String, String>>,
+}
+
+impl ElectrumClient {
+    /// Creates a client of the specified server without connecting to it.
+    pub fn new(server: ElectrumServer) -> ElectrumClient {
+        ElectrumClient {
+            server,
+            connection: AsyncMutex::new(None),
+            next_id: AtomicU64::new(0),
+            statuses: Mutex::new(HashMap::new()),
+            raw_transactions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Calls the method with the parameters and returns the result.
+    /// Returns an `ElectrumError` if the server rejects the request.
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+        rate_limit::acquire().await;
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            *connection = Some(self.connect().await?);
+        }
+        let result = self
+            .request(connection.as_mut().unwrap(), method, params)
+            .await;
+        if let Err(err) = &result {
+            // the connection is established again by the next request
+            if !err.is::<ElectrumError>() {
+                *connection = None;
+            }
+        }
+        result
+    }
+
+    /// Subscribes to the status of the address and returns the current status, i.e.,
+    /// a hash of its history, or `None` if the address has no history.
+    /// The server notifies changes of the status between the responses to later
+    /// requests. They are returned by `take_status_changes`.
+    pub async fn subscribe(&self, address: &Address) -> Result<Option<String>, Box<dyn Error>> {
+        let result = self
+            .call(
+                "blockchain.scripthash.subscribe",
+                json!([address_script_hash(address)]),
+            )
+            .await?;
+        parse(result)
+    }
+
+    /// Returns the statuses that were notified since the last call by script hash.
+    pub fn take_status_changes(&self) -> HashMap<String, Option<String>> {
+        std::mem::take(&mut *self.statuses.lock().unwrap())
+    }
+
+    /// Connects to the server and negotiates the protocol version.
+    async fn connect(&self) -> Result<Connection, Box<dyn Error>> {
+        let stream = TcpStream::connect((self.server.host.as_str(), self.server.port)).await?;
+        let stream: Box<dyn AsyncStream> = if self.server.tls {
+            let connector = TlsConnector::from(native_tls::TlsConnector::new()?);
+            Box::new(connector.connect(&self.server.host, stream).await?)
+        } else {
+            Box::new(stream)
+        };
+        let mut connection = BufReader::new(stream);
+        self.request(
+            &mut connection,
+            "server.version",
+            json!([CLIENT_NAME, PROTOCOL_VERSION]),
+        )
+        .await?;
+        Ok(connection)
+    }
+
+    /// Sends the request and reads messages until the response arrives.
+    /// Notifications that arrive before the response are recorded.
+    async fn request(
+        &self,
+        connection: &mut Connection,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, Box<dyn Error>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let timeout = Duration::from_secs(REQUEST_TIMEOUT_SECS);
+        let line = format!("{}\n", request);
+        tokio::time::timeout(timeout, connection.get_mut().write_all(line.as_bytes())).await??;
+        loop {
+            let mut line = String::new();
+            if tokio::time::timeout(timeout, connection.read_line(&mut line)).await?? == 0 {
+                return Err(Box::new(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed",
+                )));
+            }
+            let message: Value = serde_json::from_str(&line)?;
+            if message["id"].as_u64() == Some(id) {
+                if !message["error"].is_null() {
+                    let error = &message["error"];
+                    let error_message = error["message"].as_str().unwrap_or("Request failed");
+                    return Err(Box::new(ElectrumError::new(error_message)));
+                }
+                return Ok(message["result"].clone());
+            }
+            if message["method"] == "blockchain.scripthash.subscribe" {
+                if let Some(script_hash) = message["params"][0].as_str() {
+                    let status = message["params"][1].as_str().map(String::from);
+                    self.statuses
+                        .lock()
+                        .unwrap()
+                        .insert(script_hash.to_string(), status);
+                }
+            }
+        }
+    }
+
+    /// Returns the serialized transaction in hex format.
+    /// Transactions do not change, so they are cached.
+    async fn raw_transaction(&self, txid: &str) -> Result<String, Box<dyn Error>> {
+        let cached = self.raw_transactions.lock().unwrap().get(txid).cloned();
+        if let Some(raw_transaction) = cached {
+            return Ok(raw_transaction);
+        }
+        let raw_transaction: String = parse(
+            self.call("blockchain.transaction.get", json!([txid]))
+                .await?,
+        )?;
+        self.raw_transactions
+            .lock()
+            .unwrap()
+            .insert(txid.to_string(), raw_transaction.clone());
+        Ok(raw_transaction)
+    }
+
+    /// Returns the transaction with the outputs that its inputs spend and its fee.
+    /// The status of the transaction is not set.
+    async fn transaction(&self, txid: &str, testnet: bool) -> Result<Transaction, Box<dyn Error>> {
+        let mut tx = Transaction::from_hex(&self.raw_transaction(txid).await?, testnet)?;
+        for tx_in in tx.vin.iter_mut().filter(|tx_in| !tx_in.is_coinbase) {
+            let parent = Transaction::from_hex(&self.raw_transaction(&tx_in.txid).await?, testnet)?;
+            match parent.vout.get(tx_in.vout as usize) {
+                Some(prevout) => tx_in.prevout = prevout.clone(),
+                None => return Err(Box::new(ElectrumError::new("Invalid prevout"))),
+            }
+        }
+        if tx.vin.iter().all(|tx_in| !tx_in.is_coinbase) {
+            let input_value: u64 = tx.vin.iter().map(|tx_in| tx_in.prevout.value).sum();
+            let output_value: u64 = tx.vout.iter().map(|tx_out| tx_out.value).sum();
+            tx.fee = input_value.checked_sub(output_value);
+        }
+        Ok(tx)
+    }
+
+    /// Returns the history of the script hash.
+    async fn history(&self, script_hash: &str) -> Result<Vec<HistoryEntry>, Box<dyn Error>> {
+        parse(
+            self.call("blockchain.scripthash.get_history", json!([script_hash]))
+                .await?,
+        )
+    }
+
+    /// Returns the status of a transaction in the block with the specified height.
+    async fn transaction_status(&self, height: i64) -> Result<TransactionStatus, Box<dyn Error>> {
+        if height <= 0 {
+            return Ok(TransactionStatus {
+                confirmed: false,
+                block_height: None,
+                block_hash: None,
+                block_time: None,
+            });
+        }
+        let header: String = parse(
+            self.call("blockchain.block.header", json!([height]))
+                .await?,
+        )?;
+        let (block_hash, block_time) = parse_block_header(&header)?;
+        Ok(TransactionStatus {
+            confirmed: true,
+            block_height: Some(height as u64),
+            block_hash: Some(block_hash),
+            block_time: Some(block_time),
+        })
+    }
+
+    /// Returns the recommended fee per byte for a confirmation within the specified
+    /// number of blocks, or `None` if the server cannot estimate it.
+    async fn estimate_fee(&self, blocks: u32) -> Result<Option<u32>, Box<dyn Error>> {
+        let fee: f64 = parse(self.call("blockchain.estimatefee", json!([blocks])).await?)?;
+        if fee <= 0.0 {
+            return Ok(None);
+        }
+        Ok(Some(sat_per_vbyte(fee)))
+    }
+}
+
+/// Queries an Electrum server for each network. A network without a server
+/// cannot be queried.
+pub struct ElectrumBackend {
+    mainnet: Option<ElectrumClient>,
+    testnet: Option<ElectrumClient>,
+}
+
+impl ElectrumBackend {
+    /// Creates a backend that queries the specified servers.
+    pub fn new(
+        mainnet: Option<ElectrumServer>,
+        testnet: Option<ElectrumServer>,
+    ) -> ElectrumBackend {
+        ElectrumBackend {
+            mainnet: mainnet.map(ElectrumClient::new),
+            testnet: testnet.map(ElectrumClient::new),
+        }
+    }
+
+    /// Returns the client of the server for the network.
+    pub fn client(&self, testnet: bool) -> Result<&ElectrumClient, Box<dyn Error>> {
+        let client = if testnet {
+            self.testnet.as_ref()
+        } else {
+            self.mainnet.as_ref()
+        };
+        match client {
+            Some(client) => Ok(client),
+            None => Err(Box::new(ElectrumError::new("No server for the network"))),
+        }
+    }
+}
+
+#[async_trait]
+impl ChainBackend for ElectrumBackend {
+    async fn get_address_utxos(&self, address: &Address) -> Result<Vec<UTXO>, Box<dyn Error>> {
+        let client = self.client(address.testnet)?;
+        let unspent: Vec<UnspentEntry> = parse(
+            client
+                .call(
+                    "blockchain.scripthash.listunspent",
+                    json!([address_script_hash(address)]),
+                )
+                .await?,
+        )?;
+        Ok(unspent
+            .into_iter()
+            .map(|entry| UTXO {
+                txid: entry.tx_hash,
+                vout: entry.tx_pos,
+                value: entry.value,
+                status: UTXOStatus {
+                    confirmed: entry.height > 0,
+                    block_height: if entry.height > 0 {
+                        Some(entry.height as u64)
+                    } else {
+                        None
+                    },
+                    block_hash: None,
+                    block_time: None,
+                },
+            })
+            .collect())
+    }
+
+    async fn get_address_transactions(
+        &self,
+        address: &Address,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let client = self.client(address.testnet)?;
+        let history = client.history(&address_script_hash(address)).await?;
+        let mut txs = Vec::new();
+        // the history starts with the oldest transaction
+        for entry in history.into_iter().rev() {
+            let mut tx = client.transaction(&entry.tx_hash, address.testnet).await?;
+            tx.status = Some(client.transaction_status(entry.height).await?);
+            txs.push(tx);
+        }
+        Ok(txs)
+    }
+
+    async fn get_transaction(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let client = self.client(testnet)?;
+        let mut tx = client.transaction(txid, testnet).await?;
+        // the height of the transaction is found in the history of an address that it pays
+        // to or spends from, since the server has no index of transactions by ID
+        let script_pub_key = tx
+            .vout
+            .iter()
+            .find(|tx_out| !tx_out.scriptpubkey_address.is_empty())
+            .or_else(|| tx.vin.first().map(|tx_in| &tx_in.prevout))
+            .map(|tx_out| tx_out.scriptpubkey.clone());
+        let mut height = 0;
+        if let Some(script_pub_key) = script_pub_key.and_then(|s| hex::hex_to_bytes(&s)) {
+            let history = client.history(&script_hash(&script_pub_key)).await?;
+            if let Some(entry) = history.iter().find(|entry| entry.tx_hash == txid) {
+                height = entry.height;
+            }
+        }
+        tx.status = Some(client.transaction_status(height).await?);
+        Ok(tx)
+    }
+
+    async fn is_transaction_known(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<bool, Box<dyn Error>> {
+        let client = self.client(testnet)?;
+        match client.raw_transaction(txid).await {
+            Ok(_) => Ok(true),
+            Err(err) if err.is::<ElectrumError>() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn send_transaction(&self, tx: Transaction, testnet: bool) -> Result<(), Box<dyn Error>> {
+        let client = self.client(testnet)?;
+        match client
+            .call(
+                "blockchain.transaction.broadcast",
+                json!([tx.serialize_hex()]),
+            )
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) if err.is::<ElectrumError>() => Err(Box::new(SendTransactionError {})),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn get_recommended_fees(&self, coin_type_index: u32) -> Result<Fees, Box<dyn Error>> {
+        let client = self.client(coin_type_index == 1)?;
+        let relay_fee: f64 = parse(client.call("blockchain.relayfee", json!([])).await?)?;
+        let minimum_fee = sat_per_vbyte(relay_fee).max(1);
+        let mut estimates = [None; 4];
+        for (estimate, blocks) in estimates.iter_mut().zip(CONFIRMATION_TARGETS) {
+            *estimate = client.estimate_fee(blocks).await?;
+        }
+        Ok(Fees::from_estimates(minimum_fee, &estimates))
+    }
+
+    async fn get_tip_height(&self, testnet: bool) -> Result<u32, Box<dyn Error>> {
+        let client = self.client(testnet)?;
+        let tip = client
+            .call("blockchain.headers.subscribe", json!([]))
+            .await?;
+        match tip["height"].as_u64() {
+            Some(height) => Ok(height as u32),
+            None => Err(Box::new(ElectrumError::new("Invalid tip"))),
+        }
+    }
+
+    async fn get_chain_tip(&self, testnet: bool) -> Result<ChainTip, Box<dyn Error>> {
+        let client = self.client(testnet)?;
+        let tip = client
+            .call("blockchain.headers.subscribe", json!([]))
+            .await?;
+        match (tip["height"].as_u64(), tip["hex"].as_str()) {
+            (Some(height), Some(header)) => {
+                let (hash, timestamp) = parse_block_header(header)?;
+                Ok(ChainTip {
+                    height: height as u32,
+                    hash,
+                    timestamp,
+                })
+            }
+            _ => Err(Box::new(ElectrumError::new("Invalid tip"))),
+        }
+    }
+
+    async fn get_fee_histogram(&self, testnet: bool) -> Result<FeeHistogram, Box<dyn Error>> {
+        let client = self.client(testnet)?;
+        parse(client.call("mempool.get_fee_histogram", json!([])).await?)
+    }
+
+    async fn get_block_hash(&self, height: u32, testnet: bool) -> Result<String, Box<dyn Error>> {
+        let client = self.client(testnet)?;
+        let header: String = parse(
+            client
+                .call("blockchain.block.header", json!([height]))
+                .await?,
+        )?;
+        let (hash, _) = parse_block_header(&header)?;
+        Ok(hash)
+    }
+
+    /// Subscribes to the addresses with a separate connection, whose notifications are
+    /// read in an interval. The history of an address with a changed status is compared
+    /// with the known history to find the new and the confirmed transactions.
+    async fn watch(
+        &self,
+        addresses: Vec<Address>,
+        testnet: bool,
+        on_event: &(dyn Fn(LiveEvent) + Send + Sync),
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.client(testnet)?;
+        let watcher = ElectrumClient::new(client.server.clone());
+        // the heights of the known transactions by script hash
+        let mut histories: HashMap<String, (String, HashMap<String, i64>)> = HashMap::new();
+        for address in addresses {
+            watcher.subscribe(&address).await?;
+            let script_hash = address_script_hash(&address);
+            let history = client.history(&script_hash).await?;
+            let heights = history
+                .into_iter()
+                .map(|entry| (entry.tx_hash, entry.height))
+                .collect();
+            histories.insert(script_hash, (address.to_string(), heights));
+        }
+        let mut tip_height = None;
+        loop {
+            // the subscription to the headers also reads the pending notifications
+            let tip = watcher
+                .call("blockchain.headers.subscribe", json!([]))
+                .await?;
+            let height = tip["height"].as_u64();
+            if let (Some(previous), Some(height)) = (tip_height, height) {
+                if height != previous {
+                    on_event(LiveEvent::NewBlock { height });
+                }
+            }
+            tip_height = height.or(tip_height);
+            for script_hash in watcher.take_status_changes().into_keys() {
+                let (address, heights) = match histories.get_mut(&script_hash) {
+                    Some(history) => history,
+                    None => continue,
+                };
+                let history = client.history(&script_hash).await?;
+                for entry in history {
+                    let confirmed = entry.height > 0;
+                    let known_confirmed = heights.get(&entry.tx_hash).map(|height| *height > 0);
+                    if known_confirmed == Some(true) || known_confirmed == Some(confirmed) {
+                        continue;
+                    }
+                    let tx = client.transaction(&entry.tx_hash, testnet).await?;
+                    on_event(LiveEvent::address_transaction(address, &tx, confirmed));
+                    heights.insert(entry.tx_hash, entry.height);
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(WATCH_INTERVAL_SECS)).await;
+        }
+    }
+}
+
+/// Returns the script hash of the scriptPubKey, i.e., its SHA-256 hash in reversed
+/// byte order and hex format.
+pub fn script_hash(script_pub_key: &[u8]) -> String {
+    let hash = sha256::Hash::hash(script_pub_key);
+    let hash_bytes: Vec<u8> = hash.into_inner().into_iter().rev().collect();
+    hex::bytes_to_hex(&hash_bytes)
+}
+
+/// Returns the script hash of the scriptPubKey that pays to the address.
+pub fn address_script_hash(address: &Address) -> String {
+    let bytes = script::serialize(&address.script_pub_key()).unwrap();
+    let (_, prefix_length) = varint::decode(&bytes).unwrap();
+    script_hash(&bytes[prefix_length..])
+}
+
+/// Returns the hash and the timestamp of the block with the serialized header.
+fn parse_block_header(header: &str) -> Result<(String, u64), ElectrumError> {
+    let bytes = match hex::hex_to_bytes(header) {
+        Some(bytes) if bytes.len() == BLOCK_HEADER_SIZE => bytes,
+        _ => return Err(ElectrumError::new("Invalid block header")),
+    };
+    let hash = sha256::Hash::hash(&sha256::Hash::hash(&bytes));
+    let hash_bytes: Vec<u8> = hash.into_inner().into_iter().rev().collect();
+    let time = u32::from_le_bytes(bytes[68..72].try_into().unwrap());
+    Ok((hex::bytes_to_hex(&hash_bytes), time as u64))
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{address_script_hash, parse_block_header, ElectrumBackend, ElectrumServer};
+    use crate::keys::address::Address;
+    use crate::keys::bip44::private_hierarchy::Keypair;
+    use crate::networking::backend::ChainBackend;
+    use crate::test_utils::keypair;
+    use crate::transactions::transaction::{Transaction, TransactionOutput};
+    use crate::transactions::utxo::{UTXOBox, UTXOStatus, UTXO};
+    use serde_json::{json, Value};
+    use std::str::FromStr;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    static GENESIS_HEADER: &'static str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+
+    /// Returns a signed transaction that spends the output of a fake transaction
+    /// with the specified value to the targets.
+    fn signed_transaction(
+        keypair: &Keypair,
+        txid: &str,
+        value: u64,
+        targets: Vec<(Address, u64)>,
+    ) -> Transaction {
+        let boxed_utxos = vec![UTXOBox {
+            utxo: UTXO {
+                txid: txid.to_string(),
+                vout: 0,
+                value,
+                status: UTXOStatus {
+                    confirmed: true,
+                    block_height: None,
+                    block_hash: None,
+                    block_time: None,
+                },
+            },
+            output: TransactionOutput::create(&keypair.public_key.get_address(), value),
+            keypair: keypair.clone(),
+        }];
+        let mut tx = Transaction::create(&boxed_utxos, targets).unwrap();
+        tx.sign_all_inputs(&boxed_utxos);
+        tx.txid = tx.compute_txid();
+        tx
+    }
+
+    /// Serves the responses of the handler to a single connection and returns the URL.
+    /// The handler returns the messages that precede the response and the response.
+    async fn serve<F>(handler: F) -> String
+    where
+        F: Fn(&str, &Value) -> Vec<Value> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let mut messages = handler(request["method"].as_str().unwrap(), &request["params"]);
+                let response = messages.last_mut().unwrap();
+                response["id"] = request["id"].clone();
+                for message in messages {
+                    writer
+                        .write_all(format!("{}\n", message).as_bytes())
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+        format!("tcp://127.0.0.1:{}", port)
+    }
+
+    #[test]
+    fn test_parse_server() {
+        let server = ElectrumServer::parse("ssl://electrum.example.com:50102").unwrap();
+        assert_eq!(server.host, "electrum.example.com");
+        assert_eq!(server.port, 50102);
+        assert!(server.tls);
+        let server = ElectrumServer::parse("tcp://127.0.0.1").unwrap();
+        assert_eq!(server.port, 50001);
+        assert!(!server.tls);
+        assert!(ElectrumServer::parse("https://electrum.example.com").is_err());
+        assert!(ElectrumServer::parse("ssl://electrum.example.com:port").is_err());
+        assert!(ElectrumServer::parse("tcp://").is_err());
+    }
+
+    #[test]
+    fn test_script_hash() {
+        // the example of the Electrum protocol
+        let address = Address::from_str("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").unwrap();
+        assert_eq!(
+            address_script_hash(&address),
+            "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161"
+        );
+        let (block_hash, block_time) = parse_block_header(GENESIS_HEADER).unwrap();
+        assert_eq!(
+            block_hash,
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(block_time, 1231006505);
+    }
+
+    #[tokio::test]
+    async fn test_electrum_backend() {
+        let keypair = keypair(1);
+        let address = keypair.public_key.get_address();
+        let recipient = self::keypair(2).public_key.get_address();
+        let parent = signed_transaction(
+            &keypair,
+            "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+            100000,
+            vec![(address.clone(), 99000)],
+        );
+        let tx = signed_transaction(
+            &keypair,
+            &parent.txid,
+            99000,
+            vec![(recipient, 60000), (address.clone(), 38000)],
+        );
+        let script_hash = address_script_hash(&address);
+
+        let raw_transactions = [
+            (parent.txid.clone(), parent.serialize_hex()),
+            (tx.txid.clone(), tx.serialize_hex()),
+        ];
+        let (txid, notified_script_hash) = (tx.txid.clone(), script_hash.clone());
+        let url = serve(move |method, params| {
+            let result = match method {
+                "server.version" => json!(["mock", "1.4"]),
+                "blockchain.scripthash.listunspent" => {
+                    json!([{"tx_hash": txid, "tx_pos": 1, "height": 0, "value": 38000}])
+                }
+                "blockchain.scripthash.get_history" => {
+                    json!([{"tx_hash": txid, "height": 2345000}])
+                }
+                "blockchain.transaction.get" => {
+                    match raw_transactions
+                        .iter()
+                        .find(|(txid, _)| params[0] == txid.as_str())
+                    {
+                        Some((_, raw_transaction)) => json!(raw_transaction),
+                        None => return vec![json!({"error": {"code": 2, "message": "unknown"}})],
+                    }
+                }
+                "blockchain.block.header" => json!(GENESIS_HEADER),
+                "blockchain.headers.subscribe" => {
+                    // the status of the address changes before the response
+                    let notification = json!({
+                        "method": "blockchain.scripthash.subscribe",
+                        "params": [notified_script_hash, "changed"]
+                    });
+                    let response = json!({"result": {"height": 2345678, "hex": GENESIS_HEADER}});
+                    return vec![notification, response];
+                }
+                "blockchain.relayfee" => json!(0.00001),
+                "blockchain.estimatefee" => match params[0].as_u64().unwrap() {
+                    1 => json!(0.0002),
+                    3 => json!(0.0001),
+                    144 => json!(0.00002),
+                    _ => json!(-1),
+                },
+                "blockchain.transaction.broadcast" => json!(txid),
+                _ => panic!("unexpected method {}", method),
+            };
+            vec![json!({ "result": result })]
+        })
+        .await;
+
+        let backend = ElectrumBackend::new(None, Some(ElectrumServer::parse(&url).unwrap()));
+        assert!(backend.get_tip_height(false).await.is_err());
+
+        let utxos = backend.get_address_utxos(&address).await.unwrap();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].txid, tx.txid);
+        assert!(!utxos[0].status.confirmed);
+
+        assert_eq!(backend.get_tip_height(true).await.unwrap(), 2345678);
+        let statuses = backend.client(true).unwrap().take_status_changes();
+        assert_eq!(statuses[&script_hash], Some("changed".to_string()));
+        let tip = backend.get_chain_tip(true).await.unwrap();
+        assert_eq!(tip.height, 2345678);
+        assert_eq!(tip.timestamp, 1231006505);
+
+        let fetched_tx = backend.get_transaction(&tx.txid, true).await.unwrap();
+        assert_eq!(fetched_tx.vin[0].prevout.value, 99000);
+        assert_eq!(fetched_tx.fee, Some(1000));
+        let status = fetched_tx.status.unwrap();
+        assert_eq!(status.block_height, Some(2345000));
+        assert_eq!(status.block_time, Some(1231006505));
+
+        let txs = backend.get_address_transactions(&address).await.unwrap();
+        assert_eq!(txs.len(), 1);
+        assert!(txs[0].status.as_ref().unwrap().confirmed);
+
+        assert!(backend.is_transaction_known(&tx.txid, true).await.unwrap());
+        assert!(!backend
+            .is_transaction_known(
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                true
+            )
+            .await
+            .unwrap());
+
+        let fees = backend.get_recommended_fees(1).await.unwrap();
+        assert_eq!(fees.fastest_fee, 20);
+        assert_eq!(fees.half_hour_fee, 10);
+        assert_eq!(fees.hour_fee, 2);
+        assert_eq!(fees.economy_fee, 2);
+        assert_eq!(fees.minimum_fee, 1);
+
+        backend.send_transaction(tx, true).await.unwrap();
+    }
+}
@@ -20,3 +20,225 @@ impl fmt::Debug for SendTransactionError {
 }
 
 impl StdError for SendTransactionError {}
+
+/// This error occurs when live updates are requested from
+/// a backend that does not support them.
+pub struct LiveUpdatesUnsupportedError;
+
+impl fmt::Display for LiveUpdatesUnsupportedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Live updates are not supported")
+    }
+}
+
+impl fmt::Debug for LiveUpdatesUnsupportedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Live updates are not supported")
+    }
+}
+
+impl StdError for LiveUpdatesUnsupportedError {}
+
+/// This error occurs when no source can provide the recommended fees
+/// and no fees were received before.
+pub struct FeesUnavailableError;
+
+impl fmt::Display for FeesUnavailableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Fees are unavailable")
+    }
+}
+
+impl fmt::Debug for FeesUnavailableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Fees are unavailable")
+    }
+}
+
+impl StdError for FeesUnavailableError {}
+
+/// This error occurs when an API serves the chain of another network,
+/// e.g., a mainnet API that is configured for testnet.
+pub struct WrongNetworkError;
+
+impl fmt::Display for WrongNetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The API serves another network")
+    }
+}
+
+impl fmt::Debug for WrongNetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "The API serves another network")
+    }
+}
+
+impl StdError for WrongNetworkError {}
+
+/// This error occurs when the backend is queried in the offline mode.
+pub struct OfflineError;
+
+impl fmt::Display for OfflineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The wallet is in the offline mode")
+    }
+}
+
+impl fmt::Debug for OfflineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "The wallet is in the offline mode")
+    }
+}
+
+impl StdError for OfflineError {}
+
+/// This error occurs when the URL of an API is not an HTTP or HTTPS URL.
+pub struct InvalidApiUrlError;
+
+impl fmt::Display for InvalidApiUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid API URL")
+    }
+}
+
+impl fmt::Debug for InvalidApiUrlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid API URL")
+    }
+}
+
+impl StdError for InvalidApiUrlError {}
+
+/// The reason why an endpoint did not accept a broadcast transaction.
+#[derive(Debug, Clone)]
+pub struct EndpointFailure {
+    pub endpoint: String,
+    pub error: String,
+    pub rejected: bool, // the endpoint rejected the transaction, e.g., because it is invalid
+}
+
+/// This error occurs when no endpoint accepts a broadcast transaction.
+/// It contains the failure of every endpoint.
+pub struct BroadcastError {
+    pub failures: Vec<EndpointFailure>,
+}
+
+impl BroadcastError {
+    /// Returns true if every endpoint rejected the transaction, i.e., it cannot be broadcast.
+    pub fn is_rejected(&self) -> bool {
+        self.failures.iter().all(|failure| failure.rejected)
+    }
+}
+
+impl fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let failures: Vec<String> = self
+            .failures
+            .iter()
+            .map(|failure| format!("{}: {}", failure.endpoint, failure.error))
+            .collect();
+        write!(f, "Broadcast failed ({})", failures.join(", "))
+    }
+}
+
+impl fmt::Debug for BroadcastError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl StdError for BroadcastError {}
+
+/// This error occurs when the rate or the burst of the rate limit is zero.
+pub struct InvalidRateLimitError;
+
+impl fmt::Display for InvalidRateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid rate limit")
+    }
+}
+
+impl fmt::Debug for InvalidRateLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid rate limit")
+    }
+}
+
+impl StdError for InvalidRateLimitError {}
+
+/// This error occurs when an Electrum server rejects a request
+/// or its response cannot be parsed.
+pub struct ElectrumError {
+    message: String,
+}
+
+impl ElectrumError {
+    pub fn new(message: &str) -> ElectrumError {
+        ElectrumError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ElectrumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for ElectrumError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl StdError for ElectrumError {}
+
+/// The reason why a request to the backend failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestErrorKind {
+    Timeout,
+    RateLimited,
+    Unavailable,   // the backend cannot be reached or fails internally
+    Rejected(u16), // the status code of the response
+    InvalidResponse,
+}
+
+impl RequestErrorKind {
+    /// Returns true if the request can succeed when it is sent again.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            RequestErrorKind::Timeout
+                | RequestErrorKind::RateLimited
+                | RequestErrorKind::Unavailable
+        )
+    }
+}
+
+/// This error occurs when a request to the backend fails permanently
+/// or the retry budget is exhausted.
+pub struct RequestError {
+    pub kind: RequestErrorKind,
+    pub attempts: u32,
+}
+
+impl RequestError {
+    pub fn new(kind: RequestErrorKind, attempts: u32) -> RequestError {
+        RequestError { kind, attempts }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} after {} attempts", self.kind, self.attempts)
+    }
+}
+
+impl fmt::Debug for RequestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} after {} attempts", self.kind, self.attempts)
+    }
+}
+
+impl StdError for RequestError {}
@@ -1,11 +1,31 @@
-//! Implements the networking functionality for fees.
-//! The [mempool.space API](https://mempool.space/docs/api/rest) is used
-//! to interact with the blockchain.
-use super::{BITCOIN_API, BITCOIN_TESTNET_API};
+//! Defines the recommended fees that are returned by the backends and estimates
+//! the confirmation time of a fee rate from the fee histogram of the mempool.
 use serde::{Deserialize, Serialize};
-use serde_json;
+use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize, Debug)]
+// the confirmation targets of the fastest, half hour, hour and economy fees in blocks
+pub static CONFIRMATION_TARGETS: [u32; 4] = [1, 3, 6, 144];
+static SAT_PER_VBYTE_PER_BTC_PER_KVBYTE: f64 = 100000.0;
+// the maximum virtual size of a block in vbytes
+static BLOCK_VSIZE: u64 = 1000000;
+static BLOCK_INTERVAL_MINUTES: u32 = 10;
+
+/// The virtual sizes of the unconfirmed transactions by fee rate, i.e., pairs of a fee rate
+/// in sat/vB and the total vsize of the transactions that pay at least this fee rate and
+/// less than the fee rate of the previous pair. The pairs are in descending order.
+pub type FeeHistogram = Vec<(f64, u64)>;
+
+/// The expected confirmation time of a transaction with a given fee rate.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmationEstimate {
+    pub blocks: u32,
+    pub minutes: u32,
+    pub vsize_ahead: u64,   // of the transactions that pay at least the fee rate
+    pub mempool_vsize: u64, // of all unconfirmed transactions
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Fees {
     pub fastest_fee: u32,
@@ -13,21 +33,120 @@ pub struct Fees {
     pub hour_fee: u32,
     pub economy_fee: u32,
     pub minimum_fee: u32,
+    #[serde(default)]
+    pub stale: bool, // the fees were cached because no source could be queried
+}
+
+impl Fees {
+    /// Returns the fees for the estimates of the `CONFIRMATION_TARGETS`.
+    /// A fee that cannot be estimated is the fee of the next slower target
+    /// and no fee is lower than the fee of a slower target.
+    pub fn from_estimates(minimum_fee: u32, estimates: &[Option<u32>; 4]) -> Fees {
+        let economy_fee = estimates[3].unwrap_or(minimum_fee).max(minimum_fee);
+        let hour_fee = estimates[2].unwrap_or(economy_fee).max(economy_fee);
+        let half_hour_fee = estimates[1].unwrap_or(hour_fee).max(hour_fee);
+        let fastest_fee = estimates[0].unwrap_or(half_hour_fee).max(half_hour_fee);
+        Fees {
+            fastest_fee,
+            half_hour_fee,
+            hour_fee,
+            economy_fee,
+            minimum_fee,
+            stale: false,
+        }
+    }
+
+    /// Returns the fees for the fee estimates of an Esplora API, i.e., the fee rates
+    /// in sat/vB by confirmation target. The estimate of a target that is missing is the
+    /// estimate of the next larger target. The API does not report the minimum fee,
+    /// so the default minimum relay fee of 1 sat/vB is assumed.
+    pub fn from_esplora_estimates(estimates: &HashMap<String, f64>) -> Fees {
+        let mut targets: Vec<(u32, f64)> = estimates
+            .iter()
+            .filter_map(|(target, fee)| Some((target.parse().ok()?, *fee)))
+            .collect();
+        targets.sort_by_key(|(target, _)| *target);
+        let estimate = |blocks: u32| {
+            targets
+                .iter()
+                .find(|(target, _)| *target >= blocks)
+                .map(|(_, fee)| fee.ceil() as u32)
+        };
+        let estimates = CONFIRMATION_TARGETS.map(estimate);
+        Fees::from_estimates(1, &estimates)
+    }
+}
+
+/// Estimates the number of blocks until a transaction with the fee rate is confirmed,
+/// assuming that miners fill blocks with the transactions that pay the highest fee rates
+/// and that no transactions with higher fee rates arrive.
+pub fn estimate_confirmation(histogram: &[(f64, u64)], fee_rate: f64) -> ConfirmationEstimate {
+    let vsize_ahead: u64 = histogram
+        .iter()
+        .filter(|(bucket_fee_rate, _)| *bucket_fee_rate >= fee_rate)
+        .map(|(_, vsize)| vsize)
+        .sum();
+    let blocks = (vsize_ahead / BLOCK_VSIZE) as u32 + 1;
+    ConfirmationEstimate {
+        blocks,
+        minutes: blocks * BLOCK_INTERVAL_MINUTES,
+        vsize_ahead,
+        mempool_vsize: histogram.iter().map(|(_, vsize)| vsize).sum(),
+    }
+}
+
+/// Converts a fee in BTC per kilo-vbyte, as returned by Electrum servers and
+/// Bitcoin Core, to satoshis per vbyte.
+pub fn sat_per_vbyte(btc_per_kvbyte: f64) -> u32 {
+    (btc_per_kvbyte * SAT_PER_VBYTE_PER_BTC_PER_KVBYTE).ceil() as u32
 }
 
-/// Returns the current recommended fees.
-pub async fn get_recommended_fees(
-    coin_type_index: u32,
-) -> Result<Fees, Box<dyn std::error::Error>> {
-    let api_url = if coin_type_index == 1 {
-        BITCOIN_TESTNET_API
-    } else {
-        BITCOIN_API
-    };
-    let resp = reqwest::get(&format!("{}/v1/fees/recommended", api_url))
-        .await?
-        .text()
-        .await?;
-    let fees: Fees = serde_json::from_str(&resp)?;
-    Ok(fees)
+#[cfg(test)]
+mod tests {
+    use super::{estimate_confirmation, sat_per_vbyte, Fees};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_from_esplora_estimates() {
+        let estimates: HashMap<String, f64> = [("1", 20.5), ("2", 15.0), ("5", 8.2), ("144", 1.9)]
+            .into_iter()
+            .map(|(target, fee)| (target.to_string(), fee))
+            .collect();
+        let fees = Fees::from_esplora_estimates(&estimates);
+        assert_eq!(fees.fastest_fee, 21);
+        assert_eq!(fees.half_hour_fee, 9); // the estimate of 5 blocks
+        assert_eq!(fees.hour_fee, 2); // the estimate of 144 blocks
+        assert_eq!(fees.economy_fee, 2);
+        assert_eq!(fees.minimum_fee, 1);
+        assert!(!fees.stale);
+
+        let fees = Fees::from_esplora_estimates(&HashMap::new());
+        assert_eq!(fees.fastest_fee, 1);
+
+        assert_eq!(sat_per_vbyte(0.00001), 1);
+        assert_eq!(sat_per_vbyte(0.000123), 13);
+    }
+
+    #[test]
+    fn test_estimate_confirmation() {
+        let histogram = vec![
+            (50.0, 300000),
+            (20.0, 900000),
+            (10.0, 1500000),
+            (2.0, 2000000),
+            (1.0, 500000),
+        ];
+        let estimate = estimate_confirmation(&histogram, 60.0);
+        assert_eq!(estimate.blocks, 1);
+        assert_eq!(estimate.vsize_ahead, 0);
+        assert_eq!(estimate.mempool_vsize, 5200000);
+
+        let estimate = estimate_confirmation(&histogram, 20.0);
+        assert_eq!(estimate.blocks, 2);
+        assert_eq!(estimate.minutes, 20);
+        assert_eq!(estimate.vsize_ahead, 1200000);
+
+        assert_eq!(estimate_confirmation(&histogram, 1.5).blocks, 5);
+        assert_eq!(estimate_confirmation(&Vec::new(), 1.0).blocks, 1);
+    }
 }
@@ -0,0 +1,321 @@
+//! Implements fallback sources of the recommended fees. If the selected backend cannot
+//! provide the fees, the fee estimates of an Esplora API or of a Bitcoin Core node are
+//! used instead. If no source can be queried, the last fees that were received are
+//! returned and flagged as stale.
+use super::backend::{ChainBackend, ChainTip};
+use super::error::{FeesUnavailableError, RequestError};
+use super::fee::{sat_per_vbyte, FeeHistogram, Fees, CONFIRMATION_TARGETS};
+use super::live::LiveEvent;
+use super::mempool::{MempoolBackend, BLOCKSTREAM_API, BLOCKSTREAM_TESTNET_API};
+use super::rate_limit;
+use crate::keys::address::Address;
+use crate::transactions::transaction::Transaction;
+use crate::transactions::utxo::UTXO;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+static RPC_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeeSource {
+    // the `fee-estimates` endpoint of an Esplora API
+    Esplora {
+        url: String,
+        testnet: bool,
+    },
+    // the `estimatesmartfee` RPC of a Bitcoin Core node
+    Core {
+        url: String,
+        user: String,
+        password: String,
+        testnet: bool,
+    },
+}
+
+impl FeeSource {
+    /// Returns the URL of the source.
+    pub fn url(&self) -> &str {
+        match self {
+            FeeSource::Esplora { url, .. } | FeeSource::Core { url, .. } => url,
+        }
+    }
+
+    /// Returns true if the source provides the fees of the test network.
+    pub fn is_testnet(&self) -> bool {
+        match self {
+            FeeSource::Esplora { testnet, .. } | FeeSource::Core { testnet, .. } => *testnet,
+        }
+    }
+}
+
+/// The sources that are queried in order if the backend cannot provide the fees.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeeFallbacks {
+    #[serde(default)]
+    pub sources: Vec<FeeSource>,
+}
+
+impl Default for FeeFallbacks {
+    fn default() -> Self {
+        FeeFallbacks {
+            sources: vec![
+                FeeSource::Esplora {
+                    url: BLOCKSTREAM_API.to_string(),
+                    testnet: false,
+                },
+                FeeSource::Esplora {
+                    url: BLOCKSTREAM_TESTNET_API.to_string(),
+                    testnet: true,
+                },
+            ],
+        }
+    }
+}
+
+/// Queries the fallback sources if the backend cannot provide the recommended fees.
+/// All other queries are answered by the backend.
+pub struct FeeFallbackBackend {
+    backend: Arc<dyn ChainBackend>,
+    sources: Vec<FeeSource>,
+    client: reqwest::Client,
+    last_fees: Mutex<HashMap<bool, Fees>>, // the last fees that were received by network
+}
+
+impl FeeFallbackBackend {
+    /// Creates a backend that queries the fallback sources after the backend.
+    pub fn new(backend: Arc<dyn ChainBackend>, fallbacks: &FeeFallbacks) -> FeeFallbackBackend {
+        FeeFallbackBackend {
+            backend,
+            sources: fallbacks.sources.clone(),
+            client: reqwest::Client::new(),
+            last_fees: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Caches the fees of the network, so they can be returned if no source can be queried.
+    fn remember(&self, testnet: bool, fees: Fees) -> Fees {
+        self.last_fees.lock().unwrap().insert(testnet, fees.clone());
+        fees
+    }
+
+    /// Returns the recommended fees of the source.
+    async fn get_source_fees(&self, source: &FeeSource) -> Result<Fees, Box<dyn Error>> {
+        match source {
+            FeeSource::Esplora { url, testnet } => {
+                let api = MempoolBackend::new(Some(url.clone()), Some(url.clone()));
+                let estimates = api.get_fee_estimates(*testnet).await?;
+                Ok(Fees::from_esplora_estimates(&estimates))
+            }
+            FeeSource::Core {
+                url,
+                user,
+                password,
+                ..
+            } => {
+                let mempool_info = self
+                    .call(url, user, password, "getmempoolinfo", json!([]))
+                    .await?;
+                let minimum_fee = match mempool_info["mempoolminfee"].as_f64() {
+                    Some(fee) => sat_per_vbyte(fee).max(1),
+                    None => 1,
+                };
+                let mut estimates = [None; 4];
+                for (estimate, blocks) in estimates.iter_mut().zip(CONFIRMATION_TARGETS) {
+                    let result = self
+                        .call(url, user, password, "estimatesmartfee", json!([blocks]))
+                        .await?;
+                    // the node reports errors instead of a fee rate if it has too little data
+                    *estimate = result["feerate"].as_f64().map(sat_per_vbyte);
+                }
+                Ok(Fees::from_estimates(minimum_fee, &estimates))
+            }
+        }
+    }
+
+    /// Calls the JSON-RPC method of a Bitcoin Core node and returns the result.
+    async fn call(
+        &self,
+        url: &str,
+        user: &str,
+        password: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, Box<dyn Error>> {
+        rate_limit::acquire().await;
+        let request =
+            json!({"jsonrpc": "1.0", "id": "ghorbu-wallet", "method": method, "params": params});
+        let resp: Value = self
+            .client
+            .post(url)
+            .basic_auth(user, Some(password))
+            .json(&request)
+            .timeout(Duration::from_secs(RPC_TIMEOUT_SECS))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if !resp["error"].is_null() {
+            return Err(Box::new(FeesUnavailableError));
+        }
+        Ok(resp["result"].clone())
+    }
+}
+
+#[async_trait]
+impl ChainBackend for FeeFallbackBackend {
+    async fn get_address_utxos(&self, address: &Address) -> Result<Vec<UTXO>, Box<dyn Error>> {
+        self.backend.get_address_utxos(address).await
+    }
+
+    async fn get_address_transactions(
+        &self,
+        address: &Address,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        self.backend.get_address_transactions(address).await
+    }
+
+    async fn get_transaction(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        self.backend.get_transaction(txid, testnet).await
+    }
+
+    async fn is_transaction_known(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.backend.is_transaction_known(txid, testnet).await
+    }
+
+    async fn send_transaction(&self, tx: Transaction, testnet: bool) -> Result<(), Box<dyn Error>> {
+        self.backend.send_transaction(tx, testnet).await
+    }
+
+    /// Returns the fees of the backend, or of the first fallback source that provides them.
+    /// Otherwise, the last fees that were received are returned as stale fees. If there
+    /// are none, the error of the backend is returned if it was a request error.
+    async fn get_recommended_fees(&self, coin_type_index: u32) -> Result<Fees, Box<dyn Error>> {
+        let testnet = coin_type_index == 1;
+        // the error is not kept across the queries of the sources
+        let request_error = match self.backend.get_recommended_fees(coin_type_index).await {
+            Ok(fees) => return Ok(self.remember(testnet, fees)),
+            Err(err) => err
+                .downcast_ref::<RequestError>()
+                .map(|err| (err.kind.clone(), err.attempts)),
+        };
+        let sources = self
+            .sources
+            .iter()
+            .filter(|source| source.is_testnet() == testnet);
+        for source in sources {
+            if let Ok(fees) = self.get_source_fees(source).await {
+                return Ok(self.remember(testnet, fees));
+            }
+        }
+        if let Some(fees) = self.last_fees.lock().unwrap().get(&testnet) {
+            let mut fees = fees.clone();
+            fees.stale = true;
+            return Ok(fees);
+        }
+        match request_error {
+            Some((kind, attempts)) => Err(Box::new(RequestError::new(kind, attempts))),
+            None => Err(Box::new(FeesUnavailableError)),
+        }
+    }
+
+    async fn get_tip_height(&self, testnet: bool) -> Result<u32, Box<dyn Error>> {
+        self.backend.get_tip_height(testnet).await
+    }
+
+    async fn get_chain_tip(&self, testnet: bool) -> Result<ChainTip, Box<dyn Error>> {
+        self.backend.get_chain_tip(testnet).await
+    }
+
+    async fn get_fee_histogram(&self, testnet: bool) -> Result<FeeHistogram, Box<dyn Error>> {
+        self.backend.get_fee_histogram(testnet).await
+    }
+
+    async fn get_block_hash(&self, height: u32, testnet: bool) -> Result<String, Box<dyn Error>> {
+        self.backend.get_block_hash(height, testnet).await
+    }
+
+    async fn watch(
+        &self,
+        addresses: Vec<Address>,
+        testnet: bool,
+        on_event: &(dyn Fn(LiveEvent) + Send + Sync),
+    ) -> Result<(), Box<dyn Error>> {
+        self.backend.watch(addresses, testnet, on_event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FeeFallbackBackend, FeeFallbacks, FeeSource};
+    use crate::networking::backend::ChainBackend;
+    use crate::networking::mempool::MempoolBackend;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves an API that answers the requests with the responses in order.
+    /// The last response is repeated.
+    async fn serve(responses: Vec<(&'static str, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            for i in 0.. {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 4096];
+                let _ = stream.read(&mut buf).await.unwrap();
+                let (status, body) = responses[i.min(responses.len() - 1)];
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://127.0.0.1:{}/api", port)
+    }
+
+    #[tokio::test]
+    async fn test_get_recommended_fees() {
+        let backend_url = serve(vec![("404 Not Found", "")]).await;
+        let source_url = serve(vec![
+            ("200 OK", r#"{"1": 30.2, "3": 12.0, "6": 8.0, "144": 2.5}"#),
+            ("404 Not Found", ""),
+        ])
+        .await;
+        let fallbacks = FeeFallbacks {
+            sources: vec![FeeSource::Esplora {
+                url: source_url,
+                testnet: true,
+            }],
+        };
+        let backend = Arc::new(MempoolBackend::new(None, Some(backend_url)));
+        let fallback_backend = FeeFallbackBackend::new(backend, &fallbacks);
+
+        // the backend fails, so the source is queried
+        let fees = fallback_backend.get_recommended_fees(1).await.unwrap();
+        assert_eq!(fees.fastest_fee, 31);
+        assert_eq!(fees.economy_fee, 3);
+        assert!(!fees.stale);
+
+        // the source fails as well, so the last fees are returned
+        let fees = fallback_backend.get_recommended_fees(1).await.unwrap();
+        assert_eq!(fees.fastest_fee, 31);
+        assert!(fees.stale);
+    }
+}
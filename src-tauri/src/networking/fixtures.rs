@@ -0,0 +1,284 @@
+//! Implements an offline backend that serves recorded fixtures, so the
+//! wallet can be tested deterministically without querying mempool.space.
+//! The fixtures mirror the paths of the mempool.space API, e.g.:
+//!
+//! ```text
+//! <root>/testnet/address/<address>/utxo.json
+//! <root>/testnet/address/<address>/txs.json
+//! <root>/testnet/tx/<txid>.json
+//! <root>/testnet/fees.json
+//! <root>/testnet/fee_histogram.json
+//! <root>/testnet/chain_tip.json
+//! <root>/testnet/block-height/<height>.json
+//! <root>/testnet/tip_height.json
+//! ```
+//!
+//! Missing address fixtures are served as addresses without UTXOs and transactions.
+//! Broadcasted transactions are kept in memory and are known to the backend afterwards.
+use super::backend::{ChainBackend, ChainTip};
+use super::error::SendTransactionError;
+use super::fee::{FeeHistogram, Fees};
+use crate::keys::address::Address;
+use crate::transactions::transaction::Transaction;
+use crate::transactions::utxo::UTXO;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+pub struct FixtureBackend {
+    root: PathBuf,
+    broadcasted: Mutex<HashMap<String, Transaction>>,
+}
+
+impl FixtureBackend {
+    /// Creates a backend that serves the fixtures in the specified directory.
+    pub fn new<P: Into<PathBuf>>(root: P) -> FixtureBackend {
+        FixtureBackend {
+            root: root.into(),
+            broadcasted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns all transactions that were broadcasted.
+    pub fn get_broadcasted_transactions(&self) -> Vec<Transaction> {
+        self.broadcasted.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Returns the path of the fixture for the specified network.
+    fn fixture_path(&self, testnet: bool, path: &str) -> PathBuf {
+        let network = if testnet { "testnet" } else { "mainnet" };
+        self.root.join(network).join(path)
+    }
+
+    /// Reads and deserializes the specified fixture.
+    /// Returns `None` if the fixture does not exist.
+    fn read_fixture<T: DeserializeOwned>(
+        &self,
+        testnet: bool,
+        path: &str,
+    ) -> Result<Option<T>, Box<dyn Error>> {
+        let path = self.fixture_path(testnet, path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let fixture = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&fixture)?))
+    }
+}
+
+#[async_trait]
+impl ChainBackend for FixtureBackend {
+    async fn get_address_utxos(&self, address: &Address) -> Result<Vec<UTXO>, Box<dyn Error>> {
+        let path = format!("address/{}/utxo.json", address.to_string());
+        Ok(self
+            .read_fixture(address.testnet, &path)?
+            .unwrap_or_default())
+    }
+
+    async fn get_address_transactions(
+        &self,
+        address: &Address,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let path = format!("address/{}/txs.json", address.to_string());
+        Ok(self
+            .read_fixture(address.testnet, &path)?
+            .unwrap_or_default())
+    }
+
+    async fn get_transaction(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        if let Some(tx) = self.broadcasted.lock().unwrap().get(txid) {
+            return Ok(tx.clone());
+        }
+        match self.read_fixture(testnet, &format!("tx/{}.json", txid))? {
+            Some(tx) => Ok(tx),
+            None => Err(format!("Transaction not found: {}", txid).into()),
+        }
+    }
+
+    async fn is_transaction_known(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<bool, Box<dyn Error>> {
+        if self.broadcasted.lock().unwrap().contains_key(txid) {
+            return Ok(true);
+        }
+        Ok(self
+            .fixture_path(testnet, &format!("tx/{}.json", txid))
+            .exists())
+    }
+
+    async fn send_transaction(
+        &self,
+        tx: Transaction,
+        _testnet: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        if tx
+            .vin
+            .iter()
+            .any(|txin| txin.scriptsig_asm.is_empty() && txin.witness.is_none())
+        {
+            // the transaction is not signed
+            return Err(Box::new(SendTransactionError {}));
+        }
+        let txid = tx.compute_txid();
+        self.broadcasted.lock().unwrap().insert(txid, tx);
+        Ok(())
+    }
+
+    async fn get_recommended_fees(&self, coin_type_index: u32) -> Result<Fees, Box<dyn Error>> {
+        match self.read_fixture(coin_type_index == 1, "fees.json")? {
+            Some(fees) => Ok(fees),
+            None => Err("Fee fixture not found".into()),
+        }
+    }
+
+    async fn get_tip_height(&self, testnet: bool) -> Result<u32, Box<dyn Error>> {
+        match self.read_fixture(testnet, "tip_height.json")? {
+            Some(height) => Ok(height),
+            None => Err("Tip height fixture not found".into()),
+        }
+    }
+
+    async fn get_chain_tip(&self, testnet: bool) -> Result<ChainTip, Box<dyn Error>> {
+        match self.read_fixture(testnet, "chain_tip.json")? {
+            Some(tip) => Ok(tip),
+            None => Err("Chain tip fixture not found".into()),
+        }
+    }
+
+    async fn get_fee_histogram(&self, testnet: bool) -> Result<FeeHistogram, Box<dyn Error>> {
+        match self.read_fixture(testnet, "fee_histogram.json")? {
+            Some(histogram) => Ok(histogram),
+            None => Err("Fee histogram fixture not found".into()),
+        }
+    }
+
+    async fn get_block_hash(&self, height: u32, testnet: bool) -> Result<String, Box<dyn Error>> {
+        let path = format!("block-height/{}.json", height);
+        match self.read_fixture(testnet, &path)? {
+            Some(hash) => Ok(hash),
+            None => Err("Block hash fixture not found".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixtureBackend;
+    use crate::coin_selection::{frozen::FrozenUTXOs, random_improve};
+    use crate::keys::{bip32::ExtendedPrivateKey, bip44::Keypair};
+    use crate::networking::{backend::ChainBackend, utxo};
+    use crate::transactions::{script::ScriptType, transaction::Transaction};
+    use num_bigint::BigUint;
+    use serde_json::json;
+    use std::fs;
+    use std::str::FromStr;
+
+    static ADDRESS: &'static str = "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1";
+    static TXID: &'static str = "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a";
+
+    #[tokio::test]
+    async fn test_send_flow() {
+        let root = std::env::temp_dir().join("ghorbu_fixtures_test_send_flow");
+        let address_dir = root.join("testnet").join("address").join(ADDRESS);
+        fs::create_dir_all(&address_dir).unwrap();
+        fs::create_dir_all(root.join("testnet").join("tx")).unwrap();
+        let status = json!({"confirmed": true, "block_height": 2345000});
+        let utxos = json!([{"txid": TXID, "vout": 0, "value": 10000, "status": status}]);
+        fs::write(address_dir.join("utxo.json"), utxos.to_string()).unwrap();
+        let tx = json!({
+            "txid": TXID,
+            "version": 1,
+            "locktime": 0,
+            "vin": [],
+            "vout": [{
+                "scriptpubkey": "76a9146bd18c889da9d66610354ccdc4676f055bae298088ac",
+                "scriptpubkey_asm": "OP_DUP OP_HASH160 OP_PUSHBYTES_20 6bd18c889da9d66610354ccdc4676f055bae2980 OP_EQUALVERIFY OP_CHECKSIG",
+                "scriptpubkey_type": "p2pkh",
+                "scriptpubkey_address": ADDRESS,
+                "value": 10000
+            }],
+            "status": status
+        });
+        fs::write(
+            root.join("testnet")
+                .join("tx")
+                .join(format!("{}.json", TXID)),
+            tx.to_string(),
+        )
+        .unwrap();
+
+        let secret_num = BigUint::from_str(
+            "54471658843786062176644521799104358682409094809685530415586086977504002449585",
+        )
+        .unwrap();
+        let private_key = ExtendedPrivateKey {
+            testnet: true,
+            depth: 0x00,
+            fingerprint: [0; 4],
+            child_number: [0; 4],
+            chain_code: [0; 32],
+            key_data: secret_num.to_bytes_be().try_into().unwrap(),
+        };
+        let public_key = private_key.derive_public_key();
+        let keypair = Keypair {
+            private_key,
+            public_key,
+        };
+        let address = keypair.public_key.get_address();
+        assert_eq!(address.to_string(), ADDRESS);
+
+        fs::write(root.join("testnet").join("tip_height.json"), "2345678").unwrap();
+
+        let backend = FixtureBackend::new(&root);
+        assert_eq!(backend.get_tip_height(true).await.unwrap(), 2345678);
+        assert!(backend.get_tip_height(false).await.is_err());
+        let balance = utxo::get_account_balance(&backend, vec![address.clone()])
+            .await
+            .unwrap();
+        assert_eq!(balance, 10000);
+        let boxed_utxos = utxo::get_keypairs_boxed_utxos(&backend, vec![keypair.clone()])
+            .await
+            .unwrap();
+        assert_eq!(boxed_utxos.len(), 1);
+
+        let selected_coins = random_improve::select_coins(
+            boxed_utxos,
+            &FrozenUTXOs::default(),
+            &[ScriptType::P2PKH],
+            1000,
+            1,
+        )
+        .unwrap();
+        let targets = vec![
+            (address.clone(), 1000),
+            (address, selected_coins.change.unwrap()),
+        ];
+        let mut tx = Transaction::create(&selected_coins.selected_utxos, targets).unwrap();
+        let txid = tx.compute_txid();
+        // unsigned transactions are rejected
+        assert!(backend.send_transaction(tx.clone(), true).await.is_err());
+        tx.sign_all_inputs(&selected_coins.selected_utxos);
+        let txid_signed = tx.compute_txid();
+        assert_ne!(txid, txid_signed);
+        backend.send_transaction(tx, true).await.unwrap();
+        assert!(backend
+            .is_transaction_known(&txid_signed, true)
+            .await
+            .unwrap());
+        assert!(!backend.is_transaction_known(&txid, true).await.unwrap());
+        assert_eq!(backend.get_broadcasted_transactions().len(), 1);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+//! Checks the health of the backend and the other configured APIs. An API is healthy
+//! if it can be reached and serves the chain of the selected network. The chain is
+//! identified by the hash of its genesis block, so an API of the wrong network is
+//! detected before it returns confusing results.
+use super::backend::ChainBackend;
+use super::error::WrongNetworkError;
+use crate::keys::network;
+use serde::Serialize;
+use std::error::Error;
+use std::time::Instant;
+
+static BITCOIN_GENESIS_HASH: &'static str =
+    "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+
+/// The health of an API.
+#[derive(Serialize, Debug, Clone)]
+pub struct EndpointHealth {
+    pub endpoint: String,
+    pub latency_ms: Option<u64>, // of the request of the chain tip
+    pub tip_height: Option<u32>,
+    pub error: Option<String>, // the error code if the API is unhealthy
+}
+
+/// The result of a successful health check.
+pub struct HealthProbe {
+    pub latency_ms: u64,
+    pub tip_height: u32,
+}
+
+/// Returns the hash of the genesis block of the network.
+/// The test network is the selected test network.
+pub fn genesis_hash(testnet: bool) -> &'static str {
+    if testnet {
+        network::selected_test_network().genesis_hash()
+    } else {
+        BITCOIN_GENESIS_HASH
+    }
+}
+
+/// Measures the latency of the backend and verifies that it serves the chain of the network.
+/// Returns a `WrongNetworkError` if the backend serves another chain.
+pub async fn probe(
+    backend: &dyn ChainBackend,
+    testnet: bool,
+) -> Result<HealthProbe, Box<dyn Error>> {
+    let start = Instant::now();
+    let tip_height = backend.get_tip_height(testnet).await?;
+    let latency_ms = start.elapsed().as_millis() as u64;
+    let hash = backend.get_block_hash(0, testnet).await?;
+    if hash != genesis_hash(testnet) {
+        return Err(Box::new(WrongNetworkError));
+    }
+    Ok(HealthProbe {
+        latency_ms,
+        tip_height,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::probe;
+    use crate::networking::error::WrongNetworkError;
+    use crate::networking::fixtures::FixtureBackend;
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_probe() {
+        let root = std::env::temp_dir().join("ghorbu_test_probe");
+        for (network, genesis_hash) in [
+            (
+                "mainnet",
+                "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+            ),
+            // a mainnet API that is configured for testnet
+            (
+                "testnet",
+                "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+            ),
+        ] {
+            fs::create_dir_all(root.join(network).join("block-height")).unwrap();
+            fs::write(root.join(network).join("tip_height.json"), "800000").unwrap();
+            fs::write(
+                root.join(network).join("block-height").join("0.json"),
+                format!("\"{}\"", genesis_hash),
+            )
+            .unwrap();
+        }
+        let backend = FixtureBackend::new(&root);
+        assert_eq!(probe(&backend, false).await.unwrap().tip_height, 800000);
+        let err = probe(&backend, true).await.err().unwrap();
+        assert!(err.is::<WrongNetworkError>());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+//! Implements live updates of the wallet. The backend pushes new blocks and the
+//! transactions of the watched addresses, so the frontend does not need to refresh
+//! manually. The mempool.space backend uses its WebSocket API and the Electrum backend
+//! uses the subscriptions of the server.
+use super::backend::ChainBackend;
+use super::error::LiveUpdatesUnsupportedError;
+use crate::keys::address::Address;
+use crate::transactions::transaction::Transaction;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+// the delay before the backend is watched again after the connection is lost
+static RECONNECT_DELAY_SECS: u64 = 10;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    NewBlock {
+        height: u64,
+    },
+    // a transaction of a watched address entered the mempool or was confirmed
+    AddressTransaction {
+        address: String,
+        txid: String,
+        confirmed: bool,
+        received: u64, // the amount that is paid to the address
+    },
+}
+
+impl LiveEvent {
+    /// Returns the event for the transaction of the watched address.
+    pub fn address_transaction(address: &str, tx: &Transaction, confirmed: bool) -> LiveEvent {
+        let received = tx
+            .vout
+            .iter()
+            .filter(|output| output.scriptpubkey_address == address)
+            .map(|output| output.value)
+            .sum();
+        LiveEvent::AddressTransaction {
+            address: address.to_string(),
+            txid: tx.txid.clone(),
+            confirmed,
+            received,
+        }
+    }
+}
+
+/// Watches the addresses with the backend and passes the live events to the callback.
+/// If the connection is lost, the backend is watched again after a delay.
+/// Returns if the backend does not support live updates.
+pub async fn run_live_updates(
+    backend: &dyn ChainBackend,
+    addresses: Vec<Address>,
+    testnet: bool,
+    on_event: &(dyn Fn(LiveEvent) + Send + Sync),
+) {
+    loop {
+        let unsupported = match backend.watch(addresses.clone(), testnet, on_event).await {
+            Ok(()) => false,
+            Err(err) => err.is::<LiveUpdatesUnsupportedError>(),
+        };
+        if unsupported {
+            return;
+        }
+        tokio::time::sleep(Duration::from_secs(RECONNECT_DELAY_SECS)).await;
+    }
+}
+
+/// Returns the live events of a message of the mempool.space WebSocket API.
+/// Blocks and the transactions of tracked addresses are reported, other messages are ignored.
+pub fn parse_mempool_message(message: &Value) -> Vec<LiveEvent> {
+    let mut events = Vec::new();
+    if let Some(height) = message["block"]["height"].as_u64() {
+        events.push(LiveEvent::NewBlock { height });
+    }
+    if let Some(addresses) = message["multi-address-transactions"].as_object() {
+        for (address, txs) in addresses {
+            for (key, confirmed) in [("mempool", false), ("confirmed", true)] {
+                let txs: Vec<Transaction> = match serde_json::from_value(txs[key].clone()) {
+                    Ok(txs) => txs,
+                    Err(_) => continue, // missing or invalid, ignore transactions
+                };
+                for tx in txs {
+                    events.push(LiveEvent::address_transaction(address, &tx, confirmed));
+                }
+            }
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_mempool_message, LiveEvent};
+    use serde_json::json;
+
+    static ADDRESS: &'static str = "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1";
+    static TXID: &'static str = "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a";
+
+    #[test]
+    fn test_parse_mempool_message() {
+        let message = json!({"block": {"id": "00", "height": 2345679}, "conversions": {}});
+        assert_eq!(
+            parse_mempool_message(&message),
+            vec![LiveEvent::NewBlock { height: 2345679 }]
+        );
+
+        let output = |address: &str, value: u64| {
+            json!({
+                "scriptpubkey": "",
+                "scriptpubkey_asm": "",
+                "scriptpubkey_type": "p2pkh",
+                "scriptpubkey_address": address,
+                "value": value
+            })
+        };
+        let tx = json!({
+            "txid": TXID,
+            "version": 2,
+            "locktime": 0,
+            "vin": [],
+            "vout": [output(ADDRESS, 10000), output("mkHS9ne12qx9pS9VojpwU5xtRd4T7X7ZUt", 500), output(ADDRESS, 2000)],
+            "status": {"confirmed": false}
+        });
+        let message = json!({
+            "multi-address-transactions": {
+                ADDRESS: {"mempool": [tx.clone()], "confirmed": [tx], "removed": []}
+            }
+        });
+        let expected = |confirmed: bool| LiveEvent::AddressTransaction {
+            address: ADDRESS.to_string(),
+            txid: TXID.to_string(),
+            confirmed,
+            received: 12000,
+        };
+        assert_eq!(
+            parse_mempool_message(&message),
+            vec![expected(false), expected(true)]
+        );
+
+        assert!(parse_mempool_message(&json!({"pong": true})).is_empty());
+    }
+}
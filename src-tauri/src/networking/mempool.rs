@@ -0,0 +1,478 @@
+//! Implements the backend that queries the
+//! [mempool.space API](https://mempool.space/docs/api/rest).
+//! Self-hosted mempool instances and Blockstream Esplora instances serve the same API,
+//! so their URLs can be set in the network settings instead.
+use super::backend::{ChainBackend, ChainTip};
+use super::error::{InvalidApiUrlError, RequestError, RequestErrorKind, SendTransactionError};
+use super::fee::{FeeHistogram, Fees};
+use super::live::{parse_mempool_message, LiveEvent};
+use super::rate_limit;
+use super::retry::{retry, RetryPolicy};
+use crate::keys::address::Address;
+use crate::keys::network::TestNetwork;
+use crate::transactions::transaction::Transaction;
+use crate::transactions::utxo::UTXO;
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use reqwest::StatusCode;
+use serde_json::{self, json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+static BITCOIN_API: &'static str = "https://mempool.space/api";
+static BITCOIN_TESTNET_API: &'static str = "https://mempool.space/testnet/api";
+static BITCOIN_SIGNET_API: &'static str = "https://mempool.space/signet/api";
+pub static BLOCKSTREAM_API: &'static str = "https://blockstream.info/api";
+pub static BLOCKSTREAM_TESTNET_API: &'static str = "https://blockstream.info/testnet/api";
+// the default port of the HTTP API of a local Esplora instance
+static BITCOIN_REGTEST_API: &'static str = "http://127.0.0.1:3002";
+// the number of confirmed transactions per page of the history of an address
+static CONFIRMED_TXS_PER_PAGE: usize = 25;
+// the interval of pings that keep the WebSocket connection alive
+static PING_INTERVAL_SECS: u64 = 30;
+
+/// Queries the mempool.space API or an API that is compatible with it.
+/// Requests that fail because of a transient problem are retried.
+pub struct MempoolBackend {
+    api_url: String,
+    testnet_api_url: String,
+    client: reqwest::Client,
+    retry_policy: RetryPolicy,
+}
+
+impl MempoolBackend {
+    /// Creates a backend that queries the specified API URLs.
+    /// mempool.space is queried for a network without a URL.
+    pub fn new(api_url: Option<String>, testnet_api_url: Option<String>) -> MempoolBackend {
+        MempoolBackend {
+            api_url: api_url.unwrap_or_else(|| BITCOIN_API.to_string()),
+            testnet_api_url: testnet_api_url.unwrap_or_else(|| BITCOIN_TESTNET_API.to_string()),
+            client: reqwest::Client::new(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets the policy for retrying failed requests.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> MempoolBackend {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Returns the URL of the API for the network.
+    fn api_url(&self, testnet: bool) -> &str {
+        if testnet {
+            &self.testnet_api_url
+        } else {
+            &self.api_url
+        }
+    }
+
+    /// Sends the request that is built by the closure and returns the status and the body
+    /// of the response. The request is retried if the backend cannot be reached,
+    /// limits the rate of requests, or fails internally. Every attempt is subject to
+    /// the client-side rate limit.
+    async fn send<F>(&self, build: F) -> Result<(StatusCode, String), RequestError>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        retry(&self.retry_policy, || async {
+            rate_limit::acquire().await;
+            let resp = build().send().await.map_err(request_error_kind)?;
+            let status = resp.status();
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                return Err(RequestErrorKind::RateLimited);
+            }
+            if status.is_server_error() {
+                return Err(RequestErrorKind::Unavailable);
+            }
+            let body = resp.text().await.map_err(request_error_kind)?;
+            Ok((status, body))
+        })
+        .await
+    }
+
+    /// Returns the body of the response to a GET request for the path of the API.
+    async fn get(&self, path: &str, testnet: bool) -> Result<String, RequestError> {
+        let url = format!("{}{}", self.api_url(testnet), path);
+        let (status, body) = self.send(|| self.client.get(&url)).await?;
+        if !status.is_success() {
+            return Err(RequestError::new(
+                RequestErrorKind::Rejected(status.as_u16()),
+                1,
+            ));
+        }
+        Ok(body)
+    }
+
+    /// Returns the fee estimates of the Esplora API, i.e., the fee rates in sat/vB
+    /// by confirmation target in blocks.
+    pub async fn get_fee_estimates(
+        &self,
+        testnet: bool,
+    ) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        let resp = self.get("/fee-estimates", testnet).await?;
+        Ok(serde_json::from_str(&resp)?)
+    }
+
+    /// Returns the price of a bitcoin in the fiat currency at the time, a UNIX timestamp
+    /// in seconds. The API returns the price that is closest to the time.
+    pub async fn get_historical_price(
+        &self,
+        currency: &str,
+        timestamp: u64,
+    ) -> Result<Option<f64>, Box<dyn Error>> {
+        let path = format!(
+            "/v1/historical-price?currency={}&timestamp={}",
+            currency, timestamp
+        );
+        let resp: Value = serde_json::from_str(&self.get(&path, false).await?)?;
+        Ok(resp["prices"][0][currency].as_f64())
+    }
+}
+
+/// Returns the default API URL of the test network.
+/// There is no public API for regtest, so a local Esplora instance is assumed.
+pub fn default_test_api_url(test_network: TestNetwork) -> String {
+    match test_network {
+        TestNetwork::Testnet => BITCOIN_TESTNET_API.to_string(),
+        TestNetwork::Signet => BITCOIN_SIGNET_API.to_string(),
+        TestNetwork::Regtest => BITCOIN_REGTEST_API.to_string(),
+    }
+}
+
+impl Default for MempoolBackend {
+    fn default() -> Self {
+        MempoolBackend::new(None, None)
+    }
+}
+
+/// Parses the URL of an API, e.g., `https://blockstream.info/api`.
+/// Only HTTP and HTTPS URLs with a host are accepted.
+/// The trailing slash is removed, since the paths of the queries are appended.
+pub fn parse_api_url(url: &str) -> Result<String, InvalidApiUrlError> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|_| InvalidApiUrlError {})?;
+    if !matches!(parsed.scheme(), "http" | "https")
+        || parsed.host_str().is_none()
+        || parsed.query().is_some()
+        || parsed.fragment().is_some()
+    {
+        return Err(InvalidApiUrlError {});
+    }
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+#[async_trait]
+impl ChainBackend for MempoolBackend {
+    async fn get_address_utxos(&self, address: &Address) -> Result<Vec<UTXO>, Box<dyn Error>> {
+        let resp = self
+            .get(
+                &format!("/address/{}/utxo", address.to_string()),
+                address.testnet,
+            )
+            .await?;
+        let utxos: Vec<UTXO> = serde_json::from_str(&resp)?;
+        Ok(utxos)
+    }
+
+    /// The first page of the history contains the unconfirmed transactions and the most
+    /// recent confirmed transactions. The following pages of confirmed transactions
+    /// are requested after the last confirmed transaction of the previous page.
+    async fn get_address_transactions(
+        &self,
+        address: &Address,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let resp = self
+            .get(
+                &format!("/address/{}/txs", address.to_string()),
+                address.testnet,
+            )
+            .await?;
+        let mut transactions: Vec<Transaction> = serde_json::from_str(&resp)?;
+        let mut page = transactions.clone();
+        while let Some(last_txid) = next_page_txid(&page) {
+            let resp = self
+                .get(
+                    &format!("/address/{}/txs/chain/{}", address.to_string(), last_txid),
+                    address.testnet,
+                )
+                .await?;
+            page = serde_json::from_str(&resp)?;
+            transactions.extend(page.iter().cloned());
+        }
+        Ok(transactions)
+    }
+
+    async fn get_transaction(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let resp = self.get(&format!("/tx/{}", txid), testnet).await?;
+        let tx: Transaction = serde_json::from_str(&resp)?;
+        Ok(tx)
+    }
+
+    async fn is_transaction_known(
+        &self,
+        txid: &str,
+        testnet: bool,
+    ) -> Result<bool, Box<dyn Error>> {
+        let url = format!("{}/tx/{}/status", self.api_url(testnet), txid);
+        let resp = self.send(|| self.client.get(&url)).await?;
+        match resp {
+            (StatusCode::NOT_FOUND, _) => Ok(false),
+            (status, _) if status.is_success() => Ok(true),
+            (status, _) => Err(Box::new(RequestError::new(
+                RequestErrorKind::Rejected(status.as_u16()),
+                1,
+            ))),
+        }
+    }
+
+    async fn send_transaction(&self, tx: Transaction, testnet: bool) -> Result<(), Box<dyn Error>> {
+        // broadcasting a transaction again is harmless, so the broadcast is retried as well
+        let url = format!("{}/tx", self.api_url(testnet));
+        let tx_hex = tx.serialize_hex();
+        let (status, body) = self
+            .send(|| self.client.post(&url).body(tx_hex.clone()))
+            .await?;
+        if !status.is_success() || body.contains("error") {
+            Err(Box::new(SendTransactionError {}))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn get_recommended_fees(&self, coin_type_index: u32) -> Result<Fees, Box<dyn Error>> {
+        let resp = self
+            .get("/v1/fees/recommended", coin_type_index == 1)
+            .await?;
+        let fees: Fees = serde_json::from_str(&resp)?;
+        Ok(fees)
+    }
+
+    async fn get_tip_height(&self, testnet: bool) -> Result<u32, Box<dyn Error>> {
+        let resp = self.get("/blocks/tip/height", testnet).await?;
+        Ok(resp.trim().parse()?)
+    }
+
+    async fn get_chain_tip(&self, testnet: bool) -> Result<ChainTip, Box<dyn Error>> {
+        let hash = self.get("/blocks/tip/hash", testnet).await?;
+        let resp = self
+            .get(&format!("/block/{}", hash.trim()), testnet)
+            .await?;
+        let block: Value = serde_json::from_str(&resp)?;
+        match (block["height"].as_u64(), block["timestamp"].as_u64()) {
+            (Some(height), Some(timestamp)) => Ok(ChainTip {
+                height: height as u32,
+                hash: hash.trim().to_string(),
+                timestamp,
+            }),
+            _ => Err(Box::new(RequestError::new(
+                RequestErrorKind::InvalidResponse,
+                1,
+            ))),
+        }
+    }
+
+    async fn get_fee_histogram(&self, testnet: bool) -> Result<FeeHistogram, Box<dyn Error>> {
+        let resp = self.get("/mempool", testnet).await?;
+        let mempool: Value = serde_json::from_str(&resp)?;
+        Ok(serde_json::from_value(mempool["fee_histogram"].clone())?)
+    }
+
+    async fn get_block_hash(&self, height: u32, testnet: bool) -> Result<String, Box<dyn Error>> {
+        let resp = self
+            .get(&format!("/block-height/{}", height), testnet)
+            .await?;
+        Ok(resp.trim().to_string())
+    }
+
+    /// Connects to the WebSocket API, which pushes new blocks and the transactions
+    /// of the tracked addresses.
+    async fn watch(
+        &self,
+        addresses: Vec<Address>,
+        testnet: bool,
+        on_event: &(dyn Fn(LiveEvent) + Send + Sync),
+    ) -> Result<(), Box<dyn Error>> {
+        let url = websocket_url(self.api_url(testnet));
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await?;
+        let addresses: Vec<String> = addresses
+            .iter()
+            .map(|address| address.to_string())
+            .collect();
+        let requests = [
+            json!({"action": "want", "data": ["blocks"]}),
+            json!({ "track-addresses": addresses }),
+        ];
+        for request in requests {
+            socket.send(Message::Text(request.to_string())).await?;
+        }
+        let ping_interval = Duration::from_secs(PING_INTERVAL_SECS);
+        loop {
+            let message = match tokio::time::timeout(ping_interval, socket.next()).await {
+                Ok(Some(message)) => message?,
+                Ok(None) => return Ok(()),
+                Err(_) => {
+                    let ping = json!({"action": "ping"});
+                    socket.send(Message::Text(ping.to_string())).await?;
+                    continue;
+                }
+            };
+            match message {
+                Message::Text(text) => {
+                    if let Ok(message) = serde_json::from_str::<Value>(&text) {
+                        for event in parse_mempool_message(&message) {
+                            on_event(event);
+                        }
+                    }
+                }
+                Message::Close(_) => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns the URL of the WebSocket API that belongs to the API URL.
+fn websocket_url(api_url: &str) -> String {
+    let url = if let Some(rest) = api_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = api_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        api_url.to_string()
+    };
+    format!("{}/v1/ws", url)
+}
+
+/// Returns the ID of the last confirmed transaction of the page if the page is full,
+/// i.e., if the history of the address may continue after the transaction.
+fn next_page_txid(page: &[Transaction]) -> Option<String> {
+    let confirmed: Vec<&Transaction> = page
+        .iter()
+        .filter(|tx| tx.status.as_ref().is_some_and(|status| status.confirmed))
+        .collect();
+    if confirmed.len() < CONFIRMED_TXS_PER_PAGE {
+        return None;
+    }
+    confirmed.last().map(|tx| tx.txid.clone())
+}
+
+/// Returns the reason why a request failed before a response was received.
+fn request_error_kind(err: reqwest::Error) -> RequestErrorKind {
+    if err.is_timeout() {
+        RequestErrorKind::Timeout
+    } else if err.is_decode() {
+        RequestErrorKind::InvalidResponse
+    } else {
+        RequestErrorKind::Unavailable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_api_url, websocket_url, MempoolBackend};
+    use crate::keys::address::Address;
+    use crate::networking::backend::ChainBackend;
+    use serde_json::{json, Value};
+    use std::str::FromStr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    static ADDRESS: &'static str = "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1";
+
+    fn transaction(index: usize, confirmed: bool) -> Value {
+        json!({
+            "txid": format!("{:064x}", index),
+            "version": 2,
+            "locktime": 0,
+            "vin": [],
+            "vout": [],
+            "status": {"confirmed": confirmed}
+        })
+    }
+
+    /// Serves the history of an address with the specified numbers of unconfirmed and
+    /// confirmed transactions. The confirmed transactions are numbered from 1 and
+    /// paginated like the mempool.space API does.
+    async fn serve_history(unconfirmed: usize, confirmed: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap().to_string();
+                let first = match path.rsplit_once("/txs/chain/") {
+                    Some((_, last_txid)) => usize::from_str_radix(last_txid, 16).unwrap() + 1,
+                    None => 1,
+                };
+                let mut page: Vec<Value> = Vec::new();
+                if first == 1 {
+                    page.extend((0..unconfirmed).map(|i| transaction(1000 + i, false)));
+                }
+                page.extend((first..=confirmed).take(25).map(|i| transaction(i, true)));
+                let body = Value::from(page).to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://127.0.0.1:{}/api", port)
+    }
+
+    #[tokio::test]
+    async fn test_get_address_transactions() {
+        let address = Address::from_str(ADDRESS).unwrap();
+        for (unconfirmed, confirmed) in [(0, 0), (3, 24), (2, 25), (0, 60)] {
+            let url = serve_history(unconfirmed, confirmed).await;
+            let backend = MempoolBackend::new(None, Some(url));
+            let txs = backend.get_address_transactions(&address).await.unwrap();
+            assert_eq!(txs.len(), unconfirmed + confirmed);
+            let confirmed_txids: Vec<String> = txs
+                .iter()
+                .filter(|tx| tx.status.as_ref().unwrap().confirmed)
+                .map(|tx| tx.txid.clone())
+                .collect();
+            let expected: Vec<String> = (1..=confirmed).map(|i| format!("{:064x}", i)).collect();
+            assert_eq!(confirmed_txids, expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_api_url() {
+        assert_eq!(
+            parse_api_url("https://blockstream.info/api/").unwrap(),
+            "https://blockstream.info/api"
+        );
+        assert_eq!(
+            parse_api_url(" http://192.168.1.10:3006/api ").unwrap(),
+            "http://192.168.1.10:3006/api"
+        );
+        assert!(parse_api_url("blockstream.info/api").is_err());
+        assert!(parse_api_url("ftp://blockstream.info/api").is_err());
+        assert!(parse_api_url("https://blockstream.info/api?network=testnet").is_err());
+
+        let backend = MempoolBackend::new(None, Some("http://localhost:3006/api".to_string()));
+        assert_eq!(backend.api_url(false), "https://mempool.space/api");
+        assert_eq!(backend.api_url(true), "http://localhost:3006/api");
+
+        assert_eq!(
+            websocket_url("https://mempool.space/testnet/api"),
+            "wss://mempool.space/testnet/api/v1/ws"
+        );
+        assert_eq!(
+            websocket_url("http://localhost:3006/api"),
+            "ws://localhost:3006/api/v1/ws"
+        );
+    }
+}
@@ -1,7 +1,23 @@
+pub mod backend;
+pub mod broadcast;
+pub mod discovery;
+pub mod electrum;
 pub mod error;
 pub mod fee;
+pub mod fee_fallback;
+pub mod fixtures;
+pub mod health;
+pub mod live;
+pub mod mempool;
+pub mod monitor;
+pub mod offline;
+pub mod payjoin;
+pub mod price;
+pub mod rate_limit;
+pub mod retry;
+pub mod settings;
+pub mod store;
+pub mod sync;
+pub mod tracking;
 pub mod transaction;
 pub mod utxo;
-
-static BITCOIN_API: &'static str = "https://mempool.space/api";
-static BITCOIN_TESTNET_API: &'static str = "https://mempool.space/testnet/api";
@@ -0,0 +1,173 @@
+//! Monitors unconfirmed transactions of the wallet. If the mempool of the backend
+//! is full, transactions that pay less than the minimum fee are purged from it.
+//! Such transactions are reported together with a recommendation on how to get
+//! them confirmed.
+use super::backend::ChainBackend;
+use super::transaction;
+use crate::keys::address::Address;
+use crate::transactions::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FeeBumpRecommendation {
+    ReplaceByFee,       // the transaction spends our coins and signals RBF
+    ChildPaysForParent, // the transaction has an output that we can spend
+    Rebroadcast,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EvictionWarning {
+    pub txid: String,
+    pub fee_rate: f64, // in sat/vB
+    pub minimum_fee: u32,
+    pub recommendation: FeeBumpRecommendation,
+}
+
+/// Returns the fee rate of the transaction in sat/vB.
+/// Returns `None` if the backend did not report the fee or the weight.
+pub fn get_fee_rate(tx: &Transaction) -> Option<f64> {
+    let fee = tx.fee?;
+    let weight = tx.weight?;
+    let vsize = weight.div_ceil(4);
+    if vsize == 0 {
+        return None;
+    }
+    Some(fee as f64 / vsize as f64)
+}
+
+/// Returns a warning for every unconfirmed transaction whose fee rate is below
+/// the minimum fee of the mempool.
+pub fn check_pending_transactions(
+    txs: &[Transaction],
+    addresses: &[Address],
+    minimum_fee: u32,
+) -> Vec<EvictionWarning> {
+    let address_set =
+        HashSet::<String>::from_iter(addresses.iter().map(|address| address.to_string()));
+    let mut seen_txids = HashSet::new();
+    let mut warnings = Vec::new();
+    for tx in txs {
+        let confirmed = match &tx.status {
+            Some(status) => status.confirmed,
+            None => false,
+        };
+        if confirmed || !seen_txids.insert(tx.txid.clone()) {
+            continue;
+        }
+        let fee_rate = match get_fee_rate(tx) {
+            Some(fee_rate) => fee_rate,
+            None => continue,
+        };
+        if fee_rate >= minimum_fee as f64 {
+            continue;
+        }
+        let spends_our_coins = tx
+            .vin
+            .iter()
+            .any(|txin| address_set.contains(&txin.prevout.scriptpubkey_address));
+        let signals_rbf = tx.signals_rbf();
+        let pays_us = tx
+            .vout
+            .iter()
+            .any(|txout| address_set.contains(&txout.scriptpubkey_address));
+        let recommendation = if spends_our_coins && signals_rbf {
+            FeeBumpRecommendation::ReplaceByFee
+        } else if pays_us {
+            FeeBumpRecommendation::ChildPaysForParent
+        } else {
+            FeeBumpRecommendation::Rebroadcast
+        };
+        warnings.push(EvictionWarning {
+            txid: tx.txid.clone(),
+            fee_rate,
+            minimum_fee,
+            recommendation,
+        });
+    }
+    warnings
+}
+
+/// Checks the unconfirmed transactions of the specified addresses against
+/// the current minimum fee of the mempool.
+pub async fn monitor_pending_transactions(
+    backend: &dyn ChainBackend,
+    coin_type_index: u32,
+    addresses: Vec<Address>,
+) -> Result<Vec<EvictionWarning>, Box<dyn Error>> {
+    let minimum_fee = backend
+        .get_recommended_fees(coin_type_index)
+        .await?
+        .minimum_fee;
+    let txs = transaction::get_addresses_transactions(backend, addresses.clone()).await?;
+    Ok(check_pending_transactions(&txs, &addresses, minimum_fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_pending_transactions, FeeBumpRecommendation};
+    use crate::keys::address::Address;
+    use crate::transactions::transaction::Transaction;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    static ADDRESS: &'static str = "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1";
+
+    fn create_transaction(txid: &str, fee: u64, sequence: u32, confirmed: bool) -> Transaction {
+        let txout = json!({
+            "scriptpubkey": "76a9146bd18c889da9d66610354ccdc4676f055bae298088ac",
+            "scriptpubkey_asm": "",
+            "scriptpubkey_type": "p2pkh",
+            "scriptpubkey_address": ADDRESS,
+            "value": 10000
+        });
+        let tx = json!({
+            "txid": txid,
+            "version": 1,
+            "locktime": 0,
+            "vin": [{
+                "txid": "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+                "vout": 0,
+                "prevout": txout,
+                "scriptsig": "",
+                "scriptsig_asm": "",
+                "witness": null,
+                "is_coinbase": false,
+                "sequence": sequence
+            }],
+            "vout": [],
+            "weight": 764,
+            "fee": fee,
+            "status": {"confirmed": confirmed}
+        });
+        serde_json::from_value(tx).unwrap()
+    }
+
+    #[test]
+    fn test_check_pending_transactions() {
+        let addresses = vec![Address::from_str(ADDRESS).unwrap()];
+        let txs = vec![
+            create_transaction("a", 191, 0xfffffffd, false), // 1 sat/vB
+            create_transaction("a", 191, 0xfffffffd, false), // duplicate
+            create_transaction("b", 191, 0xffffffff, false),
+            create_transaction("c", 955, 0xfffffffd, false), // 5 sat/vB
+            create_transaction("d", 191, 0xfffffffd, true),
+        ];
+        let warnings = check_pending_transactions(&txs, &addresses, 2);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].txid, "a");
+        assert_eq!(warnings[0].fee_rate, 1.0);
+        assert_eq!(
+            warnings[0].recommendation,
+            FeeBumpRecommendation::ReplaceByFee
+        );
+        assert_eq!(warnings[1].txid, "b");
+        assert_eq!(
+            warnings[1].recommendation,
+            FeeBumpRecommendation::Rebroadcast
+        );
+    }
+}
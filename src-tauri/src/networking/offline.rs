@@ -0,0 +1,108 @@
+//! Implements the offline mode of an air-gapped signer. The backend of the offline mode
+//! answers every query with an `OfflineError`, so the app never connects to the network.
+//! Transactions are exchanged with an online instance as PSBTs, which are signed offline
+//! and broadcast by the online instance as raw transactions.
+use super::backend::{ChainBackend, ChainTip};
+use super::error::OfflineError;
+use super::fee::{FeeHistogram, Fees};
+use super::live::LiveEvent;
+use crate::keys::address::Address;
+use crate::transactions::transaction::Transaction;
+use crate::transactions::utxo::UTXO;
+use async_trait::async_trait;
+use std::error::Error;
+
+/// A backend that cannot be queried.
+pub struct OfflineBackend;
+
+#[async_trait]
+impl ChainBackend for OfflineBackend {
+    async fn get_address_utxos(&self, _address: &Address) -> Result<Vec<UTXO>, Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn get_address_transactions(
+        &self,
+        _address: &Address,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn get_transaction(
+        &self,
+        _txid: &str,
+        _testnet: bool,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn is_transaction_known(
+        &self,
+        _txid: &str,
+        _testnet: bool,
+    ) -> Result<bool, Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn send_transaction(
+        &self,
+        _tx: Transaction,
+        _testnet: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn get_recommended_fees(&self, _coin_type_index: u32) -> Result<Fees, Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn get_tip_height(&self, _testnet: bool) -> Result<u32, Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn get_chain_tip(&self, _testnet: bool) -> Result<ChainTip, Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn get_fee_histogram(&self, _testnet: bool) -> Result<FeeHistogram, Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn get_block_hash(&self, _height: u32, _testnet: bool) -> Result<String, Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+
+    async fn watch(
+        &self,
+        _addresses: Vec<Address>,
+        _testnet: bool,
+        _on_event: &(dyn Fn(LiveEvent) + Send + Sync),
+    ) -> Result<(), Box<dyn Error>> {
+        Err(Box::new(OfflineError))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OfflineBackend;
+    use crate::networking::backend::{create_settings_backend, ChainBackend};
+    use crate::networking::error::OfflineError;
+    use crate::networking::settings::NetworkSettings;
+
+    #[tokio::test]
+    async fn test_offline_backend() {
+        assert!(OfflineBackend
+            .get_tip_height(false)
+            .await
+            .unwrap_err()
+            .is::<OfflineError>());
+
+        let settings = NetworkSettings {
+            offline: true,
+            ..NetworkSettings::default()
+        };
+        let backend = create_settings_backend(&settings);
+        let err = backend.get_recommended_fees(0).await.unwrap_err();
+        assert!(err.is::<OfflineError>());
+    }
+}
@@ -0,0 +1,43 @@
+//! Implements the request of a payjoin proposal from the endpoint of the receiver
+//! (see `transactions::payjoin`). The endpoint is taken from the `pj` parameter
+//! of the payment URI.
+use super::error::{RequestError, RequestErrorKind};
+use reqwest::header::CONTENT_TYPE;
+use std::error::Error;
+use std::time::Duration;
+
+// the receiver has to answer while the user waits for the payment to be sent
+static PAYJOIN_TIMEOUT_SECS: u64 = 30;
+
+/// Sends the original PSBT in Base64 to the payjoin endpoint with the query parameters
+/// and returns the proposal of the receiver in Base64.
+pub async fn request_proposal(
+    endpoint: &str,
+    query: &str,
+    original_psbt: String,
+) -> Result<String, Box<dyn Error>> {
+    let mut url = reqwest::Url::parse(endpoint)?;
+    // the endpoint may already contain query parameters of the receiver
+    let query = match url.query() {
+        Some(endpoint_query) if !endpoint_query.is_empty() => {
+            format!("{}&{}", endpoint_query, query)
+        }
+        _ => query.to_string(),
+    };
+    url.set_query(Some(&query));
+    let resp = reqwest::Client::new()
+        .post(url)
+        .header(CONTENT_TYPE, "text/plain")
+        .body(original_psbt)
+        .timeout(Duration::from_secs(PAYJOIN_TIMEOUT_SECS))
+        .send()
+        .await?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(Box::new(RequestError::new(
+            RequestErrorKind::Rejected(status.as_u16()),
+            1,
+        )));
+    }
+    Ok(resp.text().await?)
+}
@@ -0,0 +1,133 @@
+//! Resolves the historical fiat value of transactions for record-keeping, e.g., for tax
+//! purposes. The price of a bitcoin at the block time of a transaction is queried from
+//! the historical price API of mempool.space. Historical prices do not change, so they
+//! are cached locally by day and queried only once.
+use super::mempool::MempoolBackend;
+use crate::utils::file;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// the currencies that are supported by the historical price API
+pub static FIAT_CURRENCIES: [&'static str; 7] = ["USD", "EUR", "GBP", "CAD", "CHF", "AUD", "JPY"];
+static SECONDS_PER_DAY: u64 = 86400;
+static SATOSHIS_PER_BITCOIN: f64 = 100000000.0;
+
+/// Returns true if the historical prices of the currency are available.
+pub fn is_supported_currency(currency: &str) -> bool {
+    FIAT_CURRENCIES.contains(&currency)
+}
+
+/// Returns the value of the amount in satoshis at the price of a bitcoin,
+/// rounded to two decimal places.
+pub fn fiat_value(amount: u64, price: f64) -> f64 {
+    (amount as f64 / SATOSHIS_PER_BITCOIN * price * 100.0).round() / 100.0
+}
+
+/// The prices of a bitcoin by currency and day, i.e., the number of days since
+/// the UNIX epoch.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PriceCache {
+    #[serde(default)]
+    prices: HashMap<String, HashMap<u64, f64>>,
+}
+
+impl PriceCache {
+    /// Returns the cached price of the day of the timestamp.
+    pub fn get(&self, currency: &str, timestamp: u64) -> Option<f64> {
+        let day = timestamp / SECONDS_PER_DAY;
+        self.prices.get(currency)?.get(&day).copied()
+    }
+
+    /// Caches the price of the day of the timestamp.
+    pub fn insert(&mut self, currency: &str, timestamp: u64, price: f64) {
+        let day = timestamp / SECONDS_PER_DAY;
+        self.prices
+            .entry(currency.to_string())
+            .or_default()
+            .insert(day, price);
+    }
+
+    /// Saves the prices to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let prices_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &prices_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the prices from the specified path.
+    /// Returns no prices if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PriceCache, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(PriceCache::default());
+        }
+        let prices_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&prices_encoded)?)
+    }
+}
+
+/// Returns the price of a bitcoin in the currency on the day of the timestamp.
+/// The price is queried from the API unless it is cached already.
+/// Returns `None` if the API has no price for the day.
+pub async fn get_historical_price(
+    api: &MempoolBackend,
+    cache: &mut PriceCache,
+    currency: &str,
+    timestamp: u64,
+) -> Result<Option<f64>, Box<dyn Error>> {
+    if let Some(price) = cache.get(currency, timestamp) {
+        return Ok(Some(price));
+    }
+    // the start of the day, so all transactions of the day have the same price
+    let day_start = timestamp - timestamp % SECONDS_PER_DAY;
+    let price = api.get_historical_price(currency, day_start).await?;
+    if let Some(price) = price {
+        cache.insert(currency, timestamp, price);
+    }
+    Ok(price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fiat_value, get_historical_price, is_supported_currency, PriceCache};
+    use crate::networking::mempool::MempoolBackend;
+    use std::fs;
+
+    #[test]
+    fn test_price_cache() {
+        let path = std::env::temp_dir().join("ghorbu_test_price_cache");
+        let _ = fs::remove_file(&path);
+        let mut cache = PriceCache::load(&path).unwrap();
+        cache.insert("EUR", 1700000000, 33000.5);
+        cache.save(&path).unwrap();
+
+        let cache = PriceCache::load(&path).unwrap();
+        // the same day
+        assert_eq!(cache.get("EUR", 1700006399), Some(33000.5));
+        // the next day
+        assert_eq!(cache.get("EUR", 1700006400), None);
+        assert_eq!(cache.get("USD", 1700000000), None);
+        fs::remove_file(path).unwrap();
+
+        assert!(is_supported_currency("CHF"));
+        assert!(!is_supported_currency("chf"));
+        assert_eq!(fiat_value(150000, 33000.5), 49.5);
+        assert_eq!(fiat_value(1, 33000.0), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_historical_price_cached() {
+        // the API is not queried for a cached price
+        let api = MempoolBackend::new(Some("http://127.0.0.1:1/api".to_string()), None);
+        let mut cache = PriceCache::default();
+        cache.insert("USD", 1700000000, 36000.0);
+        let price = get_historical_price(&api, &mut cache, "USD", 1700001000)
+            .await
+            .unwrap();
+        assert_eq!(price, Some(36000.0));
+    }
+}
@@ -0,0 +1,127 @@
+//! Implements client-side rate limiting of the requests to the backend. All requests
+//! take a token from a single token bucket, so a large wallet that queries many addresses
+//! does not exceed the limits of public APIs. The limit is configured in the network
+//! settings and applies to all backends.
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests_per_second: u32, // the rate at which tokens are added to the bucket
+    pub burst: u32,               // the capacity of the bucket
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            requests_per_second: 10,
+            burst: 20,
+        }
+    }
+}
+
+impl RateLimit {
+    /// Returns true if the rate and the capacity of the bucket are positive.
+    pub fn is_valid(&self) -> bool {
+        self.requests_per_second > 0 && self.burst > 0
+    }
+}
+
+/// A token bucket. The tokens can become negative, i.e., a request reserves a token that
+/// is added in the future and waits until then. Hence, the requests are served in order.
+pub struct RateLimiter {
+    limit: Mutex<RateLimit>,
+    bucket: Mutex<(f64, Instant)>, // the tokens and the time they were last refilled
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter with a full bucket.
+    pub fn new(limit: RateLimit) -> RateLimiter {
+        RateLimiter {
+            limit: Mutex::new(limit),
+            bucket: Mutex::new((limit.burst as f64, Instant::now())),
+        }
+    }
+
+    /// Replaces the limit. The tokens are capped at the new capacity.
+    pub fn set_limit(&self, limit: RateLimit) {
+        *self.limit.lock().unwrap() = limit;
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.0 = bucket.0.min(limit.burst as f64);
+    }
+
+    /// Takes a token and returns the time to wait until it is available.
+    pub fn reserve(&self) -> Duration {
+        let limit = *self.limit.lock().unwrap();
+        let rate = limit.requests_per_second.max(1) as f64;
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.1).as_secs_f64();
+        bucket.0 = (bucket.0 + elapsed * rate).min(limit.burst as f64);
+        bucket.1 = now;
+        bucket.0 -= 1.0;
+        if bucket.0 >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.0 / rate)
+        }
+    }
+
+    /// Waits until a token is available.
+    pub async fn acquire(&self) {
+        let delay = self.reserve();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+lazy_static! {
+    static ref RATE_LIMITER: RateLimiter = RateLimiter::new(RateLimit::default());
+}
+
+/// Sets the limit of the requests to the backend.
+pub fn set_rate_limit(limit: RateLimit) {
+    RATE_LIMITER.set_limit(limit);
+}
+
+/// Waits until the next request to the backend can be sent.
+pub async fn acquire() {
+    RATE_LIMITER.acquire().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimit, RateLimiter};
+    use std::time::Duration;
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: 10,
+            burst: 2,
+        });
+        assert!(limiter.reserve().is_zero());
+        assert!(limiter.reserve().is_zero());
+        let delay = limiter.reserve();
+        assert!(delay > Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        // the requests wait in order
+        let delay = limiter.reserve();
+        assert!(delay > Duration::from_millis(150) && delay <= Duration::from_millis(200));
+
+        limiter.set_limit(RateLimit {
+            requests_per_second: 1000,
+            burst: 1,
+        });
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(limiter.reserve().is_zero());
+
+        assert!(!RateLimit {
+            requests_per_second: 0,
+            burst: 1
+        }
+        .is_valid());
+    }
+}
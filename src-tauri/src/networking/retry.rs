@@ -0,0 +1,143 @@
+//! Implements retries of requests to the backend. A request that fails because of a
+//! transient problem, e.g., a timeout or a rate limit, is retried with exponential backoff
+//! and jitter until the retry budget is exhausted. Requests that the backend rejects are
+//! not retried.
+use super::error::{RequestError, RequestErrorKind};
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32, // including the first attempt
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub timeout: Duration, // of a single attempt
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the specified retry, starting at 1.
+    /// The delay doubles with every retry up to the maximum delay. A random jitter of up
+    /// to half of the delay is subtracted, so concurrent requests do not retry in lockstep.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let delay = self
+            .initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        let jitter = rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 2);
+        delay - Duration::from_millis(jitter)
+    }
+}
+
+/// Sends the request until it succeeds, fails with an error that is not transient,
+/// or the retry budget of the policy is exhausted. Each attempt that exceeds the timeout
+/// of the policy is cancelled. The returned error contains the number of attempts.
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, mut request: F) -> Result<T, RequestError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, RequestErrorKind>>,
+{
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let kind = match tokio::time::timeout(policy.timeout, request()).await {
+            Ok(Ok(value)) => return Ok(value),
+            Ok(Err(kind)) => kind,
+            Err(_) => RequestErrorKind::Timeout,
+        };
+        if !kind.is_transient() || attempts >= policy.max_attempts {
+            return Err(RequestError::new(kind, attempts));
+        }
+        tokio::time::sleep(policy.delay(attempts)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{retry, RetryPolicy};
+    use crate::networking::error::RequestErrorKind;
+    use std::time::Duration;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+            timeout: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::default();
+        for retry in 1..40 {
+            let delay = policy.delay(retry);
+            assert!(delay <= policy.max_delay);
+            assert!(delay * 2 >= policy.initial_delay);
+        }
+        let delay = policy.delay(3);
+        assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        // succeeds after transient errors
+        let mut attempts = 0;
+        let res = retry(&policy(), || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt < 3 {
+                    Err(RequestErrorKind::RateLimited)
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(res.unwrap(), 3);
+
+        // the retry budget is exhausted
+        let err = retry(&policy(), || async {
+            Err::<(), _>(RequestErrorKind::Unavailable)
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind, RequestErrorKind::Unavailable);
+        assert_eq!(err.attempts, 3);
+
+        // rejected requests are not retried
+        let err = retry(&policy(), || async {
+            Err::<(), _>(RequestErrorKind::Rejected(400))
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind, RequestErrorKind::Rejected(400));
+        assert_eq!(err.attempts, 1);
+
+        // attempts that exceed the timeout are cancelled
+        let err = retry(&policy(), || async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind, RequestErrorKind::Timeout);
+        assert_eq!(err.attempts, 3);
+    }
+}
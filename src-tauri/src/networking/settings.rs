@@ -0,0 +1,164 @@
+//! Implements the network settings, i.e., the backend that the wallet queries.
+//! Unlike the settings of a wallet, the network settings are shared by all wallets.
+use super::broadcast::BroadcastEndpoints;
+use super::electrum::ElectrumServer;
+use super::error::InvalidRateLimitError;
+use super::fee_fallback::FeeFallbacks;
+use super::mempool;
+use super::rate_limit::RateLimit;
+use crate::keys::network::TestNetwork;
+use crate::utils::file;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackendSettings {
+    // mempool.space is queried for a network without an API URL
+    Mempool {
+        url: Option<String>,
+        testnet_url: Option<String>,
+    },
+    // a network without a server cannot be queried
+    Electrum {
+        url: Option<String>,
+        testnet_url: Option<String>,
+    },
+}
+
+impl Default for BackendSettings {
+    fn default() -> Self {
+        BackendSettings::Mempool {
+            url: None,
+            testnet_url: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NetworkSettings {
+    #[serde(default)]
+    pub backend: BackendSettings,
+    #[serde(default)]
+    pub test_network: TestNetwork, // the network of the testnet coin type
+    #[serde(default)]
+    pub rate_limit: RateLimit, // of the requests to the backend
+    #[serde(default)]
+    pub broadcast_endpoints: BroadcastEndpoints,
+    #[serde(default)]
+    pub fee_fallbacks: FeeFallbacks,
+    #[serde(default)]
+    pub offline: bool, // the backend is never queried, e.g., on an air-gapped signer
+}
+
+impl NetworkSettings {
+    /// Returns an error if a server URL, an API URL, a broadcast endpoint, a fee source
+    /// or the rate limit is invalid.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if !self.rate_limit.is_valid() {
+            return Err(Box::new(InvalidRateLimitError));
+        }
+        let endpoints = &self.broadcast_endpoints;
+        for url in endpoints.urls.iter().chain(endpoints.testnet_urls.iter()) {
+            mempool::parse_api_url(url)?;
+        }
+        for source in &self.fee_fallbacks.sources {
+            mempool::parse_api_url(source.url())?;
+        }
+        match &self.backend {
+            BackendSettings::Mempool { url, testnet_url } => {
+                for url in url.iter().chain(testnet_url.iter()) {
+                    mempool::parse_api_url(url)?;
+                }
+            }
+            BackendSettings::Electrum { url, testnet_url } => {
+                for url in url.iter().chain(testnet_url.iter()) {
+                    ElectrumServer::parse(url)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the settings to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let settings_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &settings_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the settings from the specified path.
+    /// Returns the default settings if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<NetworkSettings, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(NetworkSettings::default());
+        }
+        let settings_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&settings_encoded)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackendSettings, NetworkSettings};
+    use crate::keys::network::TestNetwork;
+    use crate::networking::rate_limit::RateLimit;
+    use std::fs;
+
+    #[test]
+    fn test_network_settings() {
+        let path = std::env::temp_dir().join("ghorbu_test_network_settings");
+        let _ = fs::remove_file(&path);
+        let mut settings = NetworkSettings::load(&path).unwrap();
+        assert_eq!(settings.backend, BackendSettings::default());
+        settings.backend = BackendSettings::Electrum {
+            url: Some("ssl://electrum.example.com".to_string()),
+            testnet_url: None,
+        };
+        assert!(settings.validate().is_ok());
+        settings.save(&path).unwrap();
+        let loaded = NetworkSettings::load(&path).unwrap();
+        assert_eq!(loaded.backend, settings.backend);
+        assert_eq!(loaded.test_network, TestNetwork::Testnet);
+        assert_eq!(loaded.rate_limit, RateLimit::default());
+        assert!(!loaded.offline);
+
+        settings.backend = BackendSettings::Electrum {
+            url: None,
+            testnet_url: Some("electrum.example.com:60002".to_string()),
+        };
+        assert!(settings.validate().is_err());
+
+        settings.backend = BackendSettings::Mempool {
+            url: Some("https://blockstream.info/api".to_string()),
+            testnet_url: Some("blockstream.info/testnet/api".to_string()),
+        };
+        assert!(settings.validate().is_err());
+
+        settings.backend = BackendSettings::default();
+        settings.rate_limit.burst = 0;
+        assert!(settings.validate().is_err());
+
+        settings.rate_limit = RateLimit::default();
+        settings
+            .broadcast_endpoints
+            .urls
+            .push("node.local/api".to_string());
+        assert!(settings.validate().is_err());
+        fs::remove_file(path).unwrap();
+
+        // settings that were saved before the API URLs existed
+        let loaded: NetworkSettings =
+            serde_json::from_str(r#"{"backend":{"type":"mempool"}}"#).unwrap();
+        assert_eq!(loaded.backend, BackendSettings::default());
+
+        let loaded: NetworkSettings =
+            serde_json::from_str(r#"{"backend":{"type":"mempool"},"test_network":"regtest"}"#)
+                .unwrap();
+        assert_eq!(loaded.test_network, TestNetwork::Regtest);
+    }
+}
@@ -0,0 +1,269 @@
+//! Implements the local store of the chain data of a wallet in an SQLite database.
+//! The transactions and the UTXOs of every synchronized account and the addresses that
+//! are known to be used are stored, so the history can be browsed without a connection
+//! and used addresses are not queried again after a restart.
+use crate::transactions::transaction::Transaction;
+use crate::transactions::utxo::UTXO;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json;
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::Path;
+
+static SCHEMA: &'static str = "
+    CREATE TABLE IF NOT EXISTS accounts (
+        coin_type_index INTEGER NOT NULL,
+        account_index INTEGER NOT NULL,
+        synced_at INTEGER NOT NULL,
+        PRIMARY KEY (coin_type_index, account_index)
+    );
+    CREATE TABLE IF NOT EXISTS transactions (
+        coin_type_index INTEGER NOT NULL,
+        account_index INTEGER NOT NULL,
+        txid TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (coin_type_index, account_index, txid)
+    );
+    CREATE TABLE IF NOT EXISTS utxos (
+        coin_type_index INTEGER NOT NULL,
+        account_index INTEGER NOT NULL,
+        txid TEXT NOT NULL,
+        vout INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (coin_type_index, account_index, txid, vout)
+    );
+    CREATE TABLE IF NOT EXISTS used_addresses (
+        coin_type_index INTEGER NOT NULL,
+        account_index INTEGER NOT NULL,
+        address TEXT NOT NULL,
+        PRIMARY KEY (coin_type_index, account_index, address)
+    );
+";
+
+/// The chain data of an account at the time of its last synchronization.
+#[derive(Debug, Clone)]
+pub struct StoredAccount {
+    pub transactions: Vec<Transaction>,
+    pub utxos: Vec<UTXO>,
+    pub synced_at: u64, // UNIX timestamp in seconds
+}
+
+/// The store of the chain data of a wallet.
+/// The connection is not shared between threads, so a store is opened where it is used.
+pub struct ChainStore {
+    connection: Connection,
+}
+
+impl ChainStore {
+    /// Opens the store at the specified path and creates it if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ChainStore, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(ChainStore { connection })
+    }
+
+    /// Replaces the transactions and the UTXOs of the account.
+    pub fn save_account(
+        &mut self,
+        coin_type_index: u32,
+        account_index: u32,
+        transactions: &[Transaction],
+        utxos: &[UTXO],
+        synced_at: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        let db_tx = self.connection.transaction()?;
+        let key = params![coin_type_index, account_index];
+        db_tx.execute(
+            "DELETE FROM transactions WHERE coin_type_index = ?1 AND account_index = ?2",
+            key,
+        )?;
+        db_tx.execute(
+            "DELETE FROM utxos WHERE coin_type_index = ?1 AND account_index = ?2",
+            key,
+        )?;
+        for tx in transactions {
+            db_tx.execute(
+                "INSERT OR REPLACE INTO transactions VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    coin_type_index,
+                    account_index,
+                    tx.txid,
+                    serde_json::to_string(tx)?
+                ],
+            )?;
+        }
+        for utxo in utxos {
+            db_tx.execute(
+                "INSERT OR REPLACE INTO utxos VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    coin_type_index,
+                    account_index,
+                    utxo.txid,
+                    utxo.vout,
+                    serde_json::to_string(utxo)?
+                ],
+            )?;
+        }
+        db_tx.execute(
+            "INSERT OR REPLACE INTO accounts VALUES (?1, ?2, ?3)",
+            params![coin_type_index, account_index, synced_at as i64],
+        )?;
+        db_tx.commit()?;
+        Ok(())
+    }
+
+    /// Returns the chain data of the account, or `None` if it was never saved.
+    pub fn load_account(
+        &self,
+        coin_type_index: u32,
+        account_index: u32,
+    ) -> Result<Option<StoredAccount>, Box<dyn Error>> {
+        let key = params![coin_type_index, account_index];
+        let synced_at: Option<i64> = self
+            .connection
+            .query_row(
+                "SELECT synced_at FROM accounts WHERE coin_type_index = ?1 AND account_index = ?2",
+                key,
+                |row| row.get(0),
+            )
+            .optional()?;
+        let synced_at = match synced_at {
+            Some(synced_at) => synced_at as u64,
+            None => return Ok(None),
+        };
+        let transactions = self.load_rows(
+            "SELECT data FROM transactions WHERE coin_type_index = ?1 AND account_index = ?2",
+            coin_type_index,
+            account_index,
+        )?;
+        let utxos = self.load_rows(
+            "SELECT data FROM utxos WHERE coin_type_index = ?1 AND account_index = ?2",
+            coin_type_index,
+            account_index,
+        )?;
+        Ok(Some(StoredAccount {
+            transactions,
+            utxos,
+            synced_at,
+        }))
+    }
+
+    /// Marks the addresses of the account as used. An address stays used once it
+    /// received a transaction, so it never needs to be queried again.
+    pub fn mark_used(
+        &mut self,
+        coin_type_index: u32,
+        account_index: u32,
+        addresses: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        let db_tx = self.connection.transaction()?;
+        for address in addresses {
+            db_tx.execute(
+                "INSERT OR IGNORE INTO used_addresses VALUES (?1, ?2, ?3)",
+                params![coin_type_index, account_index, address],
+            )?;
+        }
+        db_tx.commit()?;
+        Ok(())
+    }
+
+    /// Returns the addresses of the account that are known to be used.
+    pub fn used_addresses(
+        &self,
+        coin_type_index: u32,
+        account_index: u32,
+    ) -> Result<HashSet<String>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(
+            "SELECT address FROM used_addresses WHERE coin_type_index = ?1 AND account_index = ?2",
+        )?;
+        let rows = statement.query_map(params![coin_type_index, account_index], |row| {
+            row.get::<_, String>(0)
+        })?;
+        let mut addresses = HashSet::new();
+        for address in rows {
+            addresses.insert(address?);
+        }
+        Ok(addresses)
+    }
+
+    /// Returns the deserialized values of the `data` column of the rows of the account.
+    fn load_rows<T: serde::de::DeserializeOwned>(
+        &self,
+        query: &str,
+        coin_type_index: u32,
+        account_index: u32,
+    ) -> Result<Vec<T>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(query)?;
+        let rows = statement.query_map(params![coin_type_index, account_index], |row| {
+            row.get::<_, String>(0)
+        })?;
+        let mut values = Vec::new();
+        for data in rows {
+            values.push(serde_json::from_str(&data?)?);
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChainStore;
+    use crate::transactions::transaction::Transaction;
+    use crate::transactions::utxo::UTXO;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_chain_store() {
+        let path = std::env::temp_dir().join("ghorbu_test_chain_store");
+        let _ = fs::remove_file(&path);
+        let tx: Transaction = serde_json::from_value(json!({
+            "txid": format!("{:064x}", 1),
+            "version": 2,
+            "locktime": 0,
+            "vin": [],
+            "vout": [],
+            "status": {"confirmed": true, "block_height": 800000}
+        }))
+        .unwrap();
+        let utxo: UTXO = serde_json::from_value(json!({
+            "txid": format!("{:064x}", 1),
+            "vout": 0,
+            "value": 10000,
+            "status": {"confirmed": true}
+        }))
+        .unwrap();
+        let mut store = ChainStore::open(&path).unwrap();
+        assert!(store.load_account(0, 0).unwrap().is_none());
+        store
+            .save_account(
+                0,
+                0,
+                std::slice::from_ref(&tx),
+                std::slice::from_ref(&utxo),
+                1000,
+            )
+            .unwrap();
+        store
+            .mark_used(0, 0, &["address1".to_string(), "address2".to_string()])
+            .unwrap();
+        drop(store);
+
+        // the data is still there after the store was reopened
+        let mut store = ChainStore::open(&path).unwrap();
+        let account = store.load_account(0, 0).unwrap().unwrap();
+        assert_eq!(account.transactions[0].txid, tx.txid);
+        assert_eq!(account.utxos[0].value, 10000);
+        assert_eq!(account.synced_at, 1000);
+        assert!(store.load_account(0, 1).unwrap().is_none());
+        assert_eq!(store.used_addresses(0, 0).unwrap().len(), 2);
+        assert!(store.used_addresses(1, 0).unwrap().is_empty());
+
+        // the UTXO was spent
+        store.save_account(0, 0, &[tx], &[], 2000).unwrap();
+        let account = store.load_account(0, 0).unwrap().unwrap();
+        assert_eq!(account.transactions.len(), 1);
+        assert!(account.utxos.is_empty());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,240 @@
+//! Implements the background synchronization of the accounts. The UTXOs and the
+//! transactions of every account are refreshed periodically and cached in a file,
+//! so the balance and the transactions can be shown without querying the backend.
+use super::backend::{query_addresses, ChainBackend};
+use super::{transaction, utxo};
+use crate::keys::address::Address;
+use crate::keys::bip32::DerivationPath;
+use crate::transactions::transaction::{SimplifiedTransaction, Transaction};
+use crate::transactions::utxo::UTXO;
+use crate::utils::file;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+pub static SYNC_INTERVAL_SECS: u64 = 60;
+// older snapshots are not served, since the synchronization has probably stopped
+pub static MAX_SNAPSHOT_AGE_SECS: u64 = 120;
+
+/// The state of an account at the time of the synchronization.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountSnapshot {
+    pub balance: u64,
+    pub utxos: Vec<UTXO>,
+    pub transactions: Vec<SimplifiedTransaction>,
+    pub synced_at: u64, // UNIX timestamp in seconds
+}
+
+/// The state of the watched addresses of a coin type at the time of the synchronization.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchedSnapshot {
+    pub balances: HashMap<String, u64>, // by address
+    pub transactions: Vec<SimplifiedTransaction>,
+    pub synced_at: u64, // UNIX timestamp in seconds
+}
+
+/// Notifies the frontend that an account was synchronized.
+#[derive(Serialize, Debug, Clone)]
+pub struct SyncEvent {
+    pub coin_type_index: u32,
+    pub account_index: u32,
+    pub synced_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SyncCache {
+    #[serde(default)]
+    accounts: HashMap<String, AccountSnapshot>, // by coin type and account index
+    #[serde(default)]
+    watched: HashMap<u32, WatchedSnapshot>, // by coin type
+}
+
+impl SyncCache {
+    /// Returns the snapshot of the account.
+    pub fn get(&self, coin_type_index: u32, account_index: u32) -> Option<&AccountSnapshot> {
+        self.accounts
+            .get(&account_key(coin_type_index, account_index))
+    }
+
+    /// Returns the snapshot of the account if it is not older than `MAX_SNAPSHOT_AGE_SECS`.
+    pub fn get_fresh(
+        &self,
+        coin_type_index: u32,
+        account_index: u32,
+        now: u64,
+    ) -> Option<&AccountSnapshot> {
+        self.get(coin_type_index, account_index)
+            .filter(|snapshot| now.saturating_sub(snapshot.synced_at) <= MAX_SNAPSHOT_AGE_SECS)
+    }
+
+    /// Replaces the snapshot of the account.
+    pub fn insert(&mut self, coin_type_index: u32, account_index: u32, snapshot: AccountSnapshot) {
+        self.accounts
+            .insert(account_key(coin_type_index, account_index), snapshot);
+    }
+
+    /// Returns the snapshot of the watched addresses of the coin type if it is not older than
+    /// `MAX_SNAPSHOT_AGE_SECS` and contains all of the addresses, i.e., the watch list did not
+    /// grow since the synchronization.
+    pub fn get_fresh_watched(
+        &self,
+        coin_type_index: u32,
+        addresses: &[Address],
+        now: u64,
+    ) -> Option<&WatchedSnapshot> {
+        self.watched.get(&coin_type_index).filter(|snapshot| {
+            now.saturating_sub(snapshot.synced_at) <= MAX_SNAPSHOT_AGE_SECS
+                && addresses
+                    .iter()
+                    .all(|address| snapshot.balances.contains_key(&address.to_string()))
+        })
+    }
+
+    /// Replaces the snapshot of the watched addresses of the coin type.
+    pub fn insert_watched(&mut self, coin_type_index: u32, snapshot: WatchedSnapshot) {
+        self.watched.insert(coin_type_index, snapshot);
+    }
+
+    /// Saves the cache to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let cache_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &cache_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the cache from the specified path.
+    /// Returns an empty cache if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SyncCache, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(SyncCache::default());
+        }
+        let cache_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&cache_encoded)?)
+    }
+}
+
+fn account_key(coin_type_index: u32, account_index: u32) -> String {
+    format!("{}/{}", coin_type_index, account_index)
+}
+
+/// Queries the UTXOs and the transactions of the addresses of an account.
+/// The full transactions are returned as well, so they can be stored.
+pub async fn sync_account(
+    backend: &dyn ChainBackend,
+    addresses: Vec<Address>,
+    address_paths: &HashMap<String, (u32, DerivationPath)>,
+    now: u64,
+) -> Result<(AccountSnapshot, Vec<Transaction>), Box<dyn Error>> {
+    let utxos = utxo::get_addresses_utxos(backend, addresses.clone()).await?;
+    let txs = transaction::get_addresses_transactions(backend, addresses.clone()).await?;
+    let transactions = transaction::simplify_transactions(txs.clone(), &addresses, address_paths);
+    let snapshot = AccountSnapshot {
+        balance: utxos.iter().map(|utxo| utxo.value).sum(),
+        utxos,
+        transactions,
+        synced_at: now,
+    };
+    Ok((snapshot, txs))
+}
+
+/// Queries the balances and the transactions of addresses that do not belong to the wallet,
+/// i.e., the watched addresses. The addresses are queried concurrently.
+pub async fn sync_watched_addresses(
+    backend: &dyn ChainBackend,
+    addresses: Vec<Address>,
+    now: u64,
+) -> Result<WatchedSnapshot, Box<dyn Error>> {
+    let utxos = query_addresses(&addresses, |address| backend.get_address_utxos(address)).await?;
+    let balances = addresses
+        .iter()
+        .zip(utxos)
+        .map(|(address, utxos)| {
+            (
+                address.to_string(),
+                utxos.iter().map(|utxo| utxo.value).sum(),
+            )
+        })
+        .collect();
+    // the watched addresses do not belong to the wallet, so they have no derivation paths
+    let transactions =
+        transaction::get_addresses_simple_transactions(backend, addresses, &HashMap::new()).await?;
+    Ok(WatchedSnapshot {
+        balances,
+        transactions,
+        synced_at: now,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccountSnapshot, SyncCache, WatchedSnapshot, MAX_SNAPSHOT_AGE_SECS};
+    use crate::keys::address::Address;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_sync_cache() {
+        let path = std::env::temp_dir().join("ghorbu_test_sync_cache");
+        let _ = fs::remove_file(&path);
+        let mut cache = SyncCache::load(&path).unwrap();
+        assert!(cache.get(1, 0).is_none());
+        cache.insert(
+            1,
+            0,
+            AccountSnapshot {
+                balance: 10000,
+                utxos: Vec::new(),
+                transactions: Vec::new(),
+                synced_at: 1000,
+            },
+        );
+        cache.save(&path).unwrap();
+
+        let cache = SyncCache::load(&path).unwrap();
+        assert_eq!(cache.get(1, 0).unwrap().balance, 10000);
+        assert!(cache.get(0, 1).is_none());
+        assert!(cache
+            .get_fresh(1, 0, 1000 + MAX_SNAPSHOT_AGE_SECS)
+            .is_some());
+        assert!(cache
+            .get_fresh(1, 0, 1001 + MAX_SNAPSHOT_AGE_SECS)
+            .is_none());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_watched_snapshot() {
+        let address = Address::from_str("mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1").unwrap();
+        let other = Address::from_str("mkHS9ne12qx9pS9VojpwU5xtRd4T7X7ZUt").unwrap();
+        let mut cache = SyncCache::default();
+        assert!(cache.get_fresh_watched(1, &[], 1000).is_none());
+        cache.insert_watched(
+            1,
+            WatchedSnapshot {
+                balances: HashMap::from([(address.to_string(), 5000)]),
+                transactions: Vec::new(),
+                synced_at: 1000,
+            },
+        );
+        let addresses = [address];
+        assert_eq!(
+            cache
+                .get_fresh_watched(1, &addresses, 1000 + MAX_SNAPSHOT_AGE_SECS)
+                .unwrap()
+                .balances[&addresses[0].to_string()],
+            5000
+        );
+        assert!(cache
+            .get_fresh_watched(1, &addresses, 1001 + MAX_SNAPSHOT_AGE_SECS)
+            .is_none());
+        assert!(cache.get_fresh_watched(0, &addresses, 1000).is_none());
+        // an address that was added to the watch list after the synchronization
+        let addresses = [addresses[0].clone(), other];
+        assert!(cache.get_fresh_watched(1, &addresses, 1000).is_none());
+    }
+}
@@ -0,0 +1,169 @@
+//! Tracks the confirmations of broadcast transactions. The transaction IDs are
+//! persisted after the broadcast and checked against the backend until the transactions
+//! are confirmed by `CONFIRMATION_MILESTONES` blocks. An event is reported whenever a
+//! transaction reaches a milestone.
+use super::backend::ChainBackend;
+use crate::utils::file;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// the numbers of confirmations that are reported, transactions are tracked until the last one
+pub static CONFIRMATION_MILESTONES: [u32; 3] = [1, 3, 6];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PendingTransaction {
+    pub txid: String,
+    pub coin_type_index: u32,
+    pub broadcast_at: u64, // UNIX timestamp in seconds
+    pub confirmations: u32,
+}
+
+/// Notifies the frontend that a transaction reached a milestone.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmationEvent {
+    pub txid: String,
+    pub coin_type_index: u32,
+    pub confirmations: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PendingTransactions {
+    #[serde(default)]
+    transactions: Vec<PendingTransaction>,
+}
+
+impl PendingTransactions {
+    /// Returns the tracked transactions in the order they were broadcast.
+    pub fn list(&self) -> &[PendingTransaction] {
+        &self.transactions
+    }
+
+    /// Starts tracking the transaction. Transactions that are tracked already are ignored,
+    /// e.g., if a transaction is broadcast again.
+    pub fn insert(&mut self, txid: &str, coin_type_index: u32, now: u64) {
+        if self.transactions.iter().any(|tx| tx.txid == txid) {
+            return;
+        }
+        self.transactions.push(PendingTransaction {
+            txid: txid.to_string(),
+            coin_type_index,
+            broadcast_at: now,
+            confirmations: 0,
+        });
+    }
+
+    /// Updates the confirmations of the transaction and returns the events for the
+    /// milestones it reached. The transaction is no longer tracked once it reached
+    /// the last milestone.
+    pub fn update(&mut self, txid: &str, confirmations: u32) -> Vec<ConfirmationEvent> {
+        let tx = match self.transactions.iter_mut().find(|tx| tx.txid == txid) {
+            Some(tx) => tx,
+            None => return Vec::new(),
+        };
+        let events = CONFIRMATION_MILESTONES
+            .iter()
+            .filter(|milestone| tx.confirmations < **milestone && **milestone <= confirmations)
+            .map(|milestone| ConfirmationEvent {
+                txid: tx.txid.clone(),
+                coin_type_index: tx.coin_type_index,
+                confirmations: *milestone,
+            })
+            .collect();
+        // the confirmations can decrease after a reorganization of the chain
+        tx.confirmations = confirmations;
+        let last_milestone = CONFIRMATION_MILESTONES[CONFIRMATION_MILESTONES.len() - 1];
+        self.transactions
+            .retain(|tx| tx.confirmations < last_milestone);
+        events
+    }
+
+    /// Saves the tracked transactions to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let pending_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &pending_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the tracked transactions from the specified path.
+    /// Returns no transactions if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PendingTransactions, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(PendingTransactions::default());
+        }
+        let pending_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&pending_encoded)?)
+    }
+}
+
+/// Queries the confirmations of the tracked transactions and returns the events for
+/// the milestones they reached. Transactions that cannot be queried are checked again
+/// by the next call.
+pub async fn check_confirmations(
+    backend: &dyn ChainBackend,
+    pending: &mut PendingTransactions,
+) -> Result<Vec<ConfirmationEvent>, Box<dyn Error>> {
+    let mut events = Vec::new();
+    let mut tip_heights = HashMap::new();
+    let txs: Vec<(String, bool)> = pending
+        .list()
+        .iter()
+        .map(|tx| (tx.txid.clone(), tx.coin_type_index == 1))
+        .collect();
+    for (txid, testnet) in txs {
+        let tip_height = match tip_heights.entry(testnet) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => *entry.insert(backend.get_tip_height(testnet).await? as u64),
+        };
+        let block_height = match backend.get_transaction(&txid, testnet).await {
+            Ok(tx) => tx.status.and_then(|status| status.block_height),
+            Err(_) => continue,
+        };
+        let confirmations = match block_height {
+            Some(block_height) if block_height <= tip_height => tip_height - block_height + 1,
+            _ => 0,
+        };
+        events.extend(pending.update(&txid, confirmations as u32));
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PendingTransactions;
+    use std::fs;
+
+    static TXID: &'static str = "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a";
+
+    #[test]
+    fn test_pending_transactions() {
+        let path = std::env::temp_dir().join("ghorbu_test_pending_transactions");
+        let _ = fs::remove_file(&path);
+        let mut pending = PendingTransactions::load(&path).unwrap();
+        pending.insert(TXID, 1, 1000);
+        pending.insert(TXID, 1, 2000);
+        assert_eq!(pending.list().len(), 1);
+        assert_eq!(pending.list()[0].broadcast_at, 1000);
+        pending.save(&path).unwrap();
+
+        let mut pending = PendingTransactions::load(&path).unwrap();
+        assert!(pending.update(TXID, 0).is_empty());
+        let confirmations: Vec<u32> = pending
+            .update(TXID, 4)
+            .iter()
+            .map(|event| event.confirmations)
+            .collect();
+        assert_eq!(confirmations, vec![1, 3]);
+        assert!(pending.update(TXID, 5).is_empty());
+        assert_eq!(pending.list()[0].confirmations, 5);
+        assert_eq!(pending.update(TXID, 7)[0].confirmations, 6);
+        assert!(pending.list().is_empty());
+        assert!(pending.update(TXID, 8).is_empty());
+        fs::remove_file(path).unwrap();
+    }
+}
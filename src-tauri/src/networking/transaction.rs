@@ -1,67 +1,47 @@
 //! Implements the networking functionality for transactions.
-//! The [mempool.space API](https://mempool.space/docs/api/rest) is used
-//! to interact with the blockchain.
-use super::{error::SendTransactionError, BITCOIN_API, BITCOIN_TESTNET_API};
+//! The transactions are queried through the selected backend.
+use super::backend::{query_addresses, ChainBackend};
 use crate::keys::address::{Address, SimpleAddress};
+use crate::keys::bip32::DerivationPath;
 use crate::transactions::transaction::{SimplifiedTransaction, Transaction, TransactionType};
-use serde_json;
 use std::cmp::Ordering;
-use std::collections::HashSet;
-
-/// Returns the transaction for the specified transaction ID.
-pub async fn get_transaction(txid: &str, testnet: bool) -> Result<Transaction, String> {
-    let api_url = if testnet {
-        BITCOIN_TESTNET_API
-    } else {
-        BITCOIN_API
-    };
-    match reqwest::get(&format!("{}/tx/{}", api_url, txid)).await {
-        Ok(resp) => match resp.text().await {
-            Ok(resp) => match serde_json::from_str(&resp) {
-                Ok(tx) => Ok(tx),
-                Err(err) => Err(err.to_string()),
-            },
-            Err(err) => Err(err.to_string()),
-        },
-        Err(err) => Err(err.to_string()),
-    }
-}
-
-/// Returns all transactions for the specified address.
-pub async fn get_address_transactions(
-    address: &Address,
-) -> Result<Vec<Transaction>, Box<dyn std::error::Error>> {
-    let api_url = if address.testnet {
-        BITCOIN_TESTNET_API
-    } else {
-        BITCOIN_API
-    };
-    let resp = reqwest::get(&format!("{}/address/{}/txs", api_url, address.to_string()))
-        .await?
-        .text()
-        .await?;
-    let transactions: Vec<Transaction> = serde_json::from_str(&resp)?;
-    Ok(transactions)
-}
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 
 /// Returns all transactions for the specified addresses.
 pub async fn get_addresses_transactions(
+    backend: &dyn ChainBackend,
     addresses: Vec<Address>,
 ) -> Result<Vec<Transaction>, Box<dyn std::error::Error>> {
-    let mut txs = Vec::new();
-    for address in addresses {
-        txs.extend(get_address_transactions(&address).await?);
-    }
-    Ok(txs)
+    let txs = query_addresses(&addresses, |address| {
+        backend.get_address_transactions(address)
+    })
+    .await?;
+    Ok(txs.into_iter().flatten().collect())
 }
 
 /// Returns all transactions for the specified addresses.
+/// The addresses are mapped to the derivation paths of their keys, if they belong to the wallet.
 pub async fn get_addresses_simple_transactions(
+    backend: &dyn ChainBackend,
     addresses: Vec<Address>,
+    address_paths: &HashMap<String, (u32, DerivationPath)>,
 ) -> Result<Vec<SimplifiedTransaction>, Box<dyn std::error::Error>> {
+    let txs = get_addresses_transactions(backend, addresses.clone()).await?;
+    Ok(simplify_transactions(txs, &addresses, address_paths))
+}
+
+/// Returns the simplified transactions from the point of view of the addresses,
+/// the unconfirmed transactions first and the confirmed ones by descending block height.
+/// The derivation paths of the addresses that a transaction affects are taken from the map,
+/// which may be empty, e.g., for watched addresses.
+pub fn simplify_transactions(
+    mut txs: Vec<Transaction>,
+    addresses: &[Address],
+    address_paths: &HashMap<String, (u32, DerivationPath)>,
+) -> Vec<SimplifiedTransaction> {
     let address_set =
         HashSet::<String>::from_iter(addresses.iter().map(|address| address.to_string()));
-    let mut txs = get_addresses_transactions(addresses).await?;
     txs.sort_by(|a, b| {
         let a_status = a.status.clone().unwrap();
         let b_status = b.status.clone().unwrap();
@@ -85,76 +65,188 @@ pub async fn get_addresses_simple_transactions(
         if txs_set.contains(&tx.txid) {
             continue;
         }
-        txs_set.insert(tx.txid);
+        txs_set.insert(tx.txid.clone());
+        let mut affected = Vec::new();
         let mut incoming = true;
+        let mut senders = Vec::new();
         for txin in tx.vin {
             if address_set.contains(&txin.prevout.scriptpubkey_address) {
                 incoming = false;
-                break;
+                affected.push(txin.prevout.scriptpubkey_address);
+            } else if incoming {
+                senders.push(txin.prevout.scriptpubkey_address);
             }
         }
         let mut send_amount = 0;
         let mut received_amount = 0;
+        let mut recipients = Vec::new();
         for txout in tx.vout {
             if address_set.contains(&txout.scriptpubkey_address) {
                 // transaction output is send to wallet
                 received_amount += txout.value;
+                affected.push(txout.scriptpubkey_address);
             } else {
                 send_amount += txout.value;
+                recipients.push(txout.scriptpubkey_address);
             }
         }
-        let (transaction_type, value) = if !incoming && send_amount == 0 {
-            (TransactionType::Internal, 0)
+        let (transaction_type, value, mut counterparties) = if !incoming && send_amount == 0 {
+            (TransactionType::Internal, 0, Vec::new())
         } else if incoming {
-            (TransactionType::Incoming, received_amount)
+            (TransactionType::Incoming, received_amount, senders)
         } else {
-            (TransactionType::Outgoing, send_amount)
+            (TransactionType::Outgoing, send_amount, recipients)
         };
+        // outputs without an address, e.g., OP_RETURN outputs, have no counterparty
+        let mut seen = HashSet::new();
+        counterparties.retain(|address| !address.is_empty() && seen.insert(address.clone()));
+        let mut paths: Vec<String> = Vec::new();
+        for address in affected {
+            if let Some((_, path)) = address_paths.get(&address) {
+                let path = path.to_string();
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        let status = tx.status.unwrap();
         simple_txs.push(SimplifiedTransaction {
+            txid: tx.txid,
             transaction_type,
             value,
             fee: tx.fee.unwrap(),
-            confirmed: tx.status.unwrap().confirmed,
+            confirmed: status.confirmed,
+            memo: None,
+            block_time: status.block_time,
+            fiat_value: None,
+            counterparties,
+            contact: None,
+            block_height: status.block_height,
+            confirmations: None,
+            paths,
         });
     }
-    Ok(simple_txs)
+    simple_txs
 }
 
 /// Marks addresses as used if transactions exist.
 pub async fn mark_addresses_as_used(
-    addresses: Vec<Address>,
+    backend: &dyn ChainBackend,
+    mut addresses: Vec<SimpleAddress>,
 ) -> Result<Vec<SimpleAddress>, Box<dyn std::error::Error>> {
-    let mut simple_addresses = Vec::new();
-    for address in addresses {
-        let txs = get_address_transactions(&address).await?;
-        simple_addresses.push(SimpleAddress {
-            address: address.to_string(),
-            used: !txs.is_empty(),
-        });
+    for simple_address in addresses.iter_mut() {
+        let address = Address::from_str(&simple_address.address)?;
+        let txs = backend.get_address_transactions(&address).await?;
+        simple_address.used = Some(!txs.is_empty());
     }
-    Ok(simple_addresses)
+    Ok(addresses)
 }
 
-/// Send a raw transaction.
-pub async fn send_transaction(
-    tx: Transaction,
-    testnet: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let api_url = if testnet {
-        BITCOIN_TESTNET_API
-    } else {
-        BITCOIN_API
+#[cfg(test)]
+mod tests {
+    use super::simplify_transactions;
+    use crate::keys::{address::Address, bip32::DerivationPath};
+    use crate::transactions::transaction::{
+        Transaction, TransactionInput, TransactionOutput, TransactionStatus,
     };
-    let client = reqwest::Client::new();
-    let res = client
-        .post(&format!("{}/tx", api_url))
-        .body(tx.serialize_hex())
-        .send()
-        .await?;
-    let status = res.text().await?;
-    if status.contains("error") {
-        Err(Box::new(SendTransactionError {}))
-    } else {
-        Ok(())
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    fn output(address: &str, value: u64) -> TransactionOutput {
+        TransactionOutput {
+            scriptpubkey: String::new(),
+            scriptpubkey_asm: String::new(),
+            scriptpubkey_type: String::new(),
+            scriptpubkey_address: address.to_string(),
+            value,
+        }
+    }
+
+    fn transaction(
+        txid: &str,
+        inputs: &[&str],
+        outputs: Vec<TransactionOutput>,
+        block_height: Option<u64>,
+    ) -> Transaction {
+        Transaction {
+            txid: txid.to_string(),
+            version: 2,
+            locktime: 0,
+            vin: inputs
+                .iter()
+                .map(|address| TransactionInput {
+                    txid: String::new(),
+                    vout: 0,
+                    prevout: output(address, 100000),
+                    scriptsig: String::new(),
+                    scriptsig_asm: String::new(),
+                    witness: None,
+                    is_coinbase: false,
+                    sequence: 0xffffffff,
+                })
+                .collect(),
+            vout: outputs,
+            size: None,
+            weight: None,
+            fee: Some(1000),
+            status: Some(TransactionStatus {
+                confirmed: block_height.is_some(),
+                block_height,
+                block_hash: None,
+                block_time: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_simplify_transactions() {
+        let receive = "1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD";
+        let change = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        let other = "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1";
+        let addresses = vec![
+            Address::from_str(receive).unwrap(),
+            Address::from_str(change).unwrap(),
+        ];
+        let mut address_paths = HashMap::new();
+        address_paths.insert(
+            receive.to_string(),
+            (0, DerivationPath::from_str("m/84'/0'/0/0").unwrap()),
+        );
+        address_paths.insert(
+            change.to_string(),
+            (0, DerivationPath::from_str("m/84'/0'/1/0").unwrap()),
+        );
+        let txs = vec![
+            transaction("a", &[other], vec![output(receive, 100000)], Some(100)),
+            transaction(
+                "b",
+                &[receive],
+                vec![output(other, 60000), output(change, 39000)],
+                None,
+            ),
+        ];
+
+        let mut simple_txs = simplify_transactions(txs, &addresses, &address_paths);
+        assert_eq!(simple_txs[0].txid, "b");
+        assert_eq!(simple_txs[0].counterparties, vec![other.to_string()]);
+        assert_eq!(
+            simple_txs[0].paths,
+            vec!["m/84'/0'/0/0".to_string(), "m/84'/0'/1/0".to_string()]
+        );
+        assert_eq!(simple_txs[1].paths, vec!["m/84'/0'/0/0".to_string()]);
+        assert_eq!(simple_txs[1].block_height, Some(100));
+
+        for simple_tx in simple_txs.iter_mut() {
+            simple_tx.set_confirmations(105);
+        }
+        assert_eq!(simple_txs[0].confirmations, Some(0));
+        assert_eq!(simple_txs[1].confirmations, Some(6));
+
+        let simple_txs = simplify_transactions(
+            vec![transaction("c", &[other], vec![output(receive, 1)], None)],
+            &addresses,
+            &HashMap::new(),
+        );
+        assert!(simple_txs[0].paths.is_empty());
     }
 }
@@ -1,81 +1,38 @@
 //! Implements the networking functionality for UTXOs.
-//! The [mempool.space API](https://mempool.space/docs/api/rest) is used
-//! to interact with the blockchain.
-use super::transaction;
-use super::{BITCOIN_API, BITCOIN_TESTNET_API};
+//! The UTXOs are queried through the selected backend.
+use super::backend::{query_addresses, ChainBackend};
 use crate::keys::address::Address;
-use crate::keys::bip44::Keypair;
+use crate::keys::bip44::{self, multisig::MultisigAccount, Keypair, MasterPrivateKey};
 use crate::transactions::utxo::{UTXOBox, UTXO};
-use serde_json;
-
-/// Returns all UTXOs for the specified address.
-pub async fn _get_address_utxos(
-    address: &Address,
-) -> Result<Vec<UTXO>, Box<dyn std::error::Error>> {
-    let api_url = if address.testnet {
-        BITCOIN_TESTNET_API
-    } else {
-        BITCOIN_API
-    };
-    let resp = reqwest::get(&format!("{}/address/{}/utxo", api_url, address.to_string()))
-        .await?
-        .text()
-        .await?;
-    let utxos: Vec<UTXO> = serde_json::from_str(&resp)?;
-    Ok(utxos)
-}
-
-/// Returns all UTXOs for the specified address.
-pub async fn get_address_utxos(address: &Address) -> Result<Vec<UTXO>, String> {
-    let api_url = if address.testnet {
-        BITCOIN_TESTNET_API
-    } else {
-        BITCOIN_API
-    };
-    match reqwest::get(&format!("{}/address/{}/utxo", api_url, address.to_string())).await {
-        Ok(resp) => match resp.text().await {
-            Ok(resp) => match serde_json::from_str(&resp) {
-                Ok(utxo) => Ok(utxo),
-                Err(err) => Err(err.to_string()),
-            },
-            Err(err) => Err(err.to_string()),
-        },
-        Err(err) => Err(err.to_string()),
-    }
-}
-
-/// Returns all UTXOs for the specified address.
-pub fn get_address_utxos_blocking(
-    address: &Address,
-) -> Result<Vec<UTXO>, Box<dyn std::error::Error>> {
-    let api_url = if address.testnet {
-        BITCOIN_TESTNET_API
-    } else {
-        BITCOIN_API
-    };
-    let resp =
-        reqwest::blocking::get(&format!("{}/address/{}/utxo", api_url, address.to_string()))?
-            .text()?;
-    let utxos: Vec<UTXO> = serde_json::from_str(&resp)?;
-    Ok(utxos)
-}
 
 /// Returns all UTXOs for the specified addresses.
 pub async fn get_addresses_utxos(
+    backend: &dyn ChainBackend,
     addresses: Vec<Address>,
 ) -> Result<Vec<UTXO>, Box<dyn std::error::Error>> {
-    let mut utxos = Vec::new();
-    for address in addresses {
-        utxos.extend(get_address_utxos(&address).await?);
-    }
-    Ok(utxos)
+    let utxos = query_addresses(&addresses, |address| backend.get_address_utxos(address)).await?;
+    Ok(utxos.into_iter().flatten().collect())
+}
+
+/// Returns all UTXOs for the specified addresses together with the address that received them.
+pub async fn get_addresses_received_utxos(
+    backend: &dyn ChainBackend,
+    addresses: Vec<Address>,
+) -> Result<Vec<(Address, UTXO)>, Box<dyn std::error::Error>> {
+    let utxos = query_addresses(&addresses, |address| backend.get_address_utxos(address)).await?;
+    Ok(addresses
+        .iter()
+        .zip(utxos)
+        .flat_map(|(address, utxos)| utxos.into_iter().map(|utxo| (address.clone(), utxo)))
+        .collect())
 }
 
 /// Returns the balance for the specified addresses.
 pub async fn get_account_balance(
+    backend: &dyn ChainBackend,
     addresses: Vec<Address>,
 ) -> Result<u64, Box<dyn std::error::Error>> {
-    let balance = get_addresses_utxos(addresses)
+    let balance = get_addresses_utxos(backend, addresses)
         .await?
         .iter()
         .map(|utxo| utxo.value)
@@ -83,34 +40,109 @@ pub async fn get_account_balance(
     Ok(balance)
 }
 
-/// Returns all boxed UTXOs for the specified key pair.
-pub async fn get_keypair_boxed_utxos(keypair: &Keypair) -> Result<Vec<UTXOBox>, String> {
+/// Returns all boxed UTXOs for the specified key pair,
+/// i.e., the UTXOs of its P2PKH and its P2WPKH address.
+pub async fn get_keypair_boxed_utxos(
+    backend: &dyn ChainBackend,
+    keypair: &Keypair,
+) -> Result<Vec<UTXOBox>, String> {
+    get_purpose_keypair_boxed_utxos(backend, keypair, 44).await
+}
+
+/// Returns all boxed UTXOs of the addresses of the key pair for the specified purpose.
+async fn get_purpose_keypair_boxed_utxos(
+    backend: &dyn ChainBackend,
+    keypair: &Keypair,
+    purpose_index: u32,
+) -> Result<Vec<UTXOBox>, String> {
     let mut boxed_utxos = Vec::new();
-    match get_address_utxos(&keypair.public_key.get_address()).await {
-        Ok(utxos) => {
-            for utxo in utxos {
-                let tx =
-                    transaction::get_transaction(&utxo.txid, keypair.public_key.testnet).await?;
-                match tx.vout.get(utxo.vout as usize) {
-                    Some(output) => boxed_utxos.push(UTXOBox {
-                        utxo,
-                        output: output.clone(),
-                        keypair: keypair.clone(),
-                    }),
-                    None => continue, // invalid index, ignore UTXO
-                }
+    for address in bip44::purpose_addresses(&keypair.public_key, purpose_index) {
+        let utxos = backend
+            .get_address_utxos(&address)
+            .await
+            .map_err(|err| err.to_string())?;
+        for utxo in utxos {
+            let tx = backend
+                .get_transaction(&utxo.txid, keypair.public_key.testnet)
+                .await
+                .map_err(|err| err.to_string())?;
+            match tx.vout.get(utxo.vout as usize) {
+                Some(output) => boxed_utxos.push(UTXOBox {
+                    utxo,
+                    output: output.clone(),
+                    keypair: keypair.clone(),
+                }),
+                None => continue, // invalid index, ignore UTXO
             }
-            Ok(boxed_utxos)
         }
-        Err(err) => Err(err.to_string()),
     }
+    Ok(boxed_utxos)
 }
 
 /// Returns all boxed UTXOs for the specified key pairs.
-pub async fn get_keypairs_boxed_utxos(keypairs: Vec<Keypair>) -> Result<Vec<UTXOBox>, String> {
+pub async fn get_keypairs_boxed_utxos(
+    backend: &dyn ChainBackend,
+    keypairs: Vec<Keypair>,
+) -> Result<Vec<UTXOBox>, String> {
+    let mut boxed_utxos = Vec::new();
+    for keypair in keypairs {
+        boxed_utxos.extend(get_keypair_boxed_utxos(backend, &keypair).await?);
+    }
+    Ok(boxed_utxos)
+}
+
+/// Returns all boxed UTXOs of the addresses of the key pairs for the specified purpose,
+/// e.g., the P2TR addresses for BIP-86.
+pub async fn get_purpose_keypairs_boxed_utxos(
+    backend: &dyn ChainBackend,
+    keypairs: Vec<Keypair>,
+    purpose_index: u32,
+) -> Result<Vec<UTXOBox>, String> {
     let mut boxed_utxos = Vec::new();
     for keypair in keypairs {
-        boxed_utxos.extend(get_keypair_boxed_utxos(&keypair).await?);
+        boxed_utxos
+            .extend(get_purpose_keypair_boxed_utxos(backend, &keypair, purpose_index).await?);
+    }
+    Ok(boxed_utxos)
+}
+
+/// Returns all boxed UTXOs of the multisig account. The UTXOs are boxed with the
+/// key pairs of this wallet, which are only used for coin selection because the
+/// inputs are signed through a PSBT.
+pub async fn get_multisig_boxed_utxos(
+    backend: &dyn ChainBackend,
+    account: &MultisigAccount,
+    master_private_key: &MasterPrivateKey,
+) -> Result<Vec<UTXOBox>, String> {
+    let mut boxed_utxos = Vec::new();
+    for multisig_address in account.get_addresses() {
+        let keypair = match account.local_keypair(
+            master_private_key,
+            multisig_address.change,
+            multisig_address.index,
+        ) {
+            Some(keypair) => keypair,
+            None => return Err("account_not_found_error".to_string()),
+        };
+        let address = account.address(multisig_address.change, multisig_address.index);
+        let utxos = backend
+            .get_address_utxos(&address)
+            .await
+            .map_err(|err| err.to_string())?;
+        for utxo in utxos {
+            let tx = backend
+                .get_transaction(&utxo.txid, address.testnet)
+                .await
+                .map_err(|err| err.to_string())?;
+            match tx.vout.get(utxo.vout as usize) {
+                Some(output) => boxed_utxos.push(UTXOBox {
+                    utxo,
+                    output: output.clone(),
+                    keypair: keypair.clone(),
+                }),
+                None => continue, // invalid index, ignore UTXO
+            }
+        }
     }
     Ok(boxed_utxos)
 }
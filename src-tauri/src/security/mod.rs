@@ -0,0 +1,143 @@
+//! Protects the wallet file against brute-force attacks on the password.
+//! Failed password attempts are counted in a file next to the key file, so
+//! that the count survives a restart of the application. After a number of
+//! free attempts, every further attempt is delayed exponentially. Optionally,
+//! the wallet is wiped after a maximum number of failed attempts.
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::utils::file;
+
+static FREE_ATTEMPTS: u32 = 3;
+static BASE_DELAY: u64 = 1; // in seconds
+static MAX_DELAY: u64 = 60 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct PasswordAttempts {
+    failed_attempts: u32,
+    last_failure: u64, // UNIX timestamp in seconds
+    wipe_after: Option<u32>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttemptStatus {
+    pub failed_attempts: u32,
+    pub remaining_attempts: Option<u32>, // `None` if the wallet is never wiped
+    pub retry_after: u64,                // in seconds
+}
+
+impl PasswordAttempts {
+    /// Returns the number of seconds that have to pass after the last failure
+    /// before the next attempt is allowed.
+    pub fn delay(&self) -> u64 {
+        if self.failed_attempts <= FREE_ATTEMPTS {
+            return 0;
+        }
+        let exponent = (self.failed_attempts - FREE_ATTEMPTS).min(32);
+        BASE_DELAY
+            .saturating_mul(2u64.saturating_pow(exponent))
+            .min(MAX_DELAY)
+    }
+
+    /// Returns the number of seconds until the next attempt is allowed.
+    pub fn retry_after(&self, now: u64) -> u64 {
+        (self.last_failure + self.delay()).saturating_sub(now)
+    }
+
+    /// Records a failed attempt.
+    pub fn record_failure(&mut self, now: u64) {
+        self.failed_attempts += 1;
+        self.last_failure = now;
+    }
+
+    /// Resets the count after a successful attempt.
+    pub fn record_success(&mut self) {
+        self.failed_attempts = 0;
+        self.last_failure = 0;
+    }
+
+    /// Sets the number of failed attempts after which the wallet is wiped.
+    pub fn set_wipe_after(&mut self, wipe_after: Option<u32>) {
+        self.wipe_after = wipe_after;
+    }
+
+    /// Returns the number of failed attempts after which the wallet is wiped.
+    pub fn get_wipe_after(&self) -> Option<u32> {
+        self.wipe_after
+    }
+
+    /// Returns true if the maximum number of failed attempts was reached.
+    pub fn should_wipe(&self) -> bool {
+        match self.wipe_after {
+            Some(wipe_after) => self.failed_attempts >= wipe_after,
+            None => false,
+        }
+    }
+
+    /// Returns the status that is shown to the user.
+    pub fn status(&self, now: u64) -> AttemptStatus {
+        AttemptStatus {
+            failed_attempts: self.failed_attempts,
+            remaining_attempts: self
+                .wipe_after
+                .map(|wipe_after| wipe_after.saturating_sub(self.failed_attempts)),
+            retry_after: self.retry_after(now),
+        }
+    }
+
+    /// Saves the attempts to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let attempts_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &attempts_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the attempts from the specified path.
+    /// Returns no failed attempts if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PasswordAttempts, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(PasswordAttempts::default());
+        }
+        let attempts_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&attempts_encoded)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PasswordAttempts;
+
+    #[test]
+    fn test_password_attempts() {
+        let mut attempts = PasswordAttempts::default();
+        for _ in 0..3 {
+            attempts.record_failure(100);
+            assert_eq!(attempts.retry_after(100), 0);
+        }
+        attempts.record_failure(100);
+        assert_eq!(attempts.retry_after(100), 2);
+        attempts.record_failure(100);
+        assert_eq!(attempts.retry_after(100), 4);
+        assert_eq!(attempts.retry_after(103), 1);
+        assert_eq!(attempts.retry_after(104), 0);
+        for _ in 0..40 {
+            attempts.record_failure(100);
+        }
+        assert_eq!(attempts.retry_after(100), 60 * 60);
+        assert!(!attempts.should_wipe());
+        assert_eq!(attempts.status(100).remaining_attempts, None);
+
+        attempts.record_success();
+        assert_eq!(attempts.retry_after(100), 0);
+        attempts.set_wipe_after(Some(2));
+        attempts.record_failure(100);
+        assert_eq!(attempts.status(100).remaining_attempts, Some(1));
+        assert!(!attempts.should_wipe());
+        attempts.record_failure(100);
+        assert!(attempts.should_wipe());
+    }
+}
@@ -0,0 +1,76 @@
+//! Implements the fixtures that are shared by the tests.
+use crate::keys::{bip32::ExtendedPrivateKey, bip44::private_hierarchy::Keypair};
+use crate::transactions::transaction::TransactionOutput;
+use crate::transactions::utxo::{UTXOBox, UTXOStatus, UTXO};
+use crate::utils::hex;
+
+/// Returns the key pair of the testnet master key that is created from the seed byte.
+pub fn keypair(seed: u8) -> Keypair {
+    let private_key = ExtendedPrivateKey::create_master_key([seed; 32], true);
+    let public_key = private_key.derive_public_key();
+    Keypair {
+        private_key,
+        public_key,
+    }
+}
+
+/// Returns the key pair of the hex-encoded mainnet private key.
+pub fn keypair_from_hex(private_key: &str) -> Keypair {
+    let private_key = ExtendedPrivateKey {
+        testnet: false,
+        depth: 0x00,
+        fingerprint: [0; 4],
+        child_number: [0; 4],
+        chain_code: [0; 32],
+        key_data: hex::hex_to_bytes(private_key).unwrap().try_into().unwrap(),
+    };
+    let public_key = private_key.derive_public_key();
+    Keypair {
+        private_key,
+        public_key,
+    }
+}
+
+/// Returns a confirmed P2PKH UTXO of the key pair with the given outpoint and value.
+pub fn boxed_utxo(keypair: &Keypair, txid: &str, vout: u32, value: u64) -> UTXOBox {
+    UTXOBox {
+        utxo: UTXO {
+            txid: txid.to_string(),
+            vout,
+            value,
+            status: UTXOStatus {
+                confirmed: true,
+                block_height: None,
+                block_hash: None,
+                block_time: None,
+            },
+        },
+        output: TransactionOutput::create(&keypair.public_key.get_address(), value),
+        keypair: keypair.clone(),
+    }
+}
+
+/// Returns confirmed P2PKH UTXOs with the given values that belong to the same key.
+/// The UTXOs are the outputs of the same transaction in the order of the values.
+pub fn boxed_utxos(values: &[u64]) -> Vec<UTXOBox> {
+    let utxos: Vec<(u8, u64)> = values.iter().map(|value| (1, *value)).collect();
+    seeded_boxed_utxos(&utxos)
+}
+
+/// Returns confirmed P2PKH UTXOs with the given (seed, value) pairs. The UTXOs of
+/// each seed are received by the same address. The UTXOs are the outputs of the
+/// same transaction in the order of the pairs.
+pub fn seeded_boxed_utxos(utxos: &[(u8, u64)]) -> Vec<UTXOBox> {
+    utxos
+        .iter()
+        .enumerate()
+        .map(|(vout, (seed, value))| {
+            boxed_utxo(
+                &keypair(*seed),
+                "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+                vout as u32,
+                *value,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,170 @@
+//! Implements the details of an address of the wallet, i.e., its balance and its usage.
+//! An address that received more than one payment has been reused, which links the
+//! payments to the same owner, so the details can be used to audit address reuse.
+use super::transaction::Transaction;
+use super::utxo::UTXO;
+use crate::keys::bip32::DerivationPath;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AddressDetails {
+    pub address: String,
+    pub account_index: u32,
+    pub path: String, // derivation path of the key
+    pub balance: u64,
+    pub confirmed_balance: u64,
+    pub total_received: u64,
+    pub tx_count: u32,
+    pub receive_count: u32, // the transactions that pay to the address
+    pub reused: bool,
+    pub first_used: Option<u64>, // UNIX timestamp of the first confirmed transaction
+    pub last_used: Option<u64>,  // UNIX timestamp of the last confirmed transaction
+    pub pending: bool,           // true if a transaction of the address is unconfirmed
+}
+
+impl AddressDetails {
+    /// Creates the details of the address from its UTXOs and transactions.
+    /// A transaction that is passed twice is only counted once.
+    pub fn create(
+        address: &str,
+        account_index: u32,
+        path: &DerivationPath,
+        utxos: &[UTXO],
+        txs: &[Transaction],
+    ) -> AddressDetails {
+        let mut details = AddressDetails {
+            address: address.to_string(),
+            account_index,
+            path: path.to_string(),
+            balance: utxos.iter().map(|utxo| utxo.value).sum(),
+            confirmed_balance: utxos
+                .iter()
+                .filter(|utxo| utxo.status.confirmed)
+                .map(|utxo| utxo.value)
+                .sum(),
+            total_received: 0,
+            tx_count: 0,
+            receive_count: 0,
+            reused: false,
+            first_used: None,
+            last_used: None,
+            pending: false,
+        };
+        let mut txids = HashSet::new();
+        for tx in txs {
+            if !txids.insert(&tx.txid) {
+                continue;
+            }
+            details.tx_count += 1;
+            let received: u64 = tx
+                .vout
+                .iter()
+                .filter(|txout| txout.scriptpubkey_address == address)
+                .map(|txout| txout.value)
+                .sum();
+            if received > 0 {
+                details.total_received += received;
+                details.receive_count += 1;
+            }
+            let status = tx.status.as_ref();
+            match status.and_then(|status| status.block_time) {
+                Some(block_time) if status.is_some_and(|status| status.confirmed) => {
+                    details.first_used = Some(
+                        details
+                            .first_used
+                            .map_or(block_time, |first_used| first_used.min(block_time)),
+                    );
+                    details.last_used = Some(
+                        details
+                            .last_used
+                            .map_or(block_time, |last_used| last_used.max(block_time)),
+                    );
+                }
+                _ => details.pending = true,
+            }
+        }
+        details.reused = details.receive_count > 1;
+        details
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AddressDetails;
+    use crate::keys::bip32::DerivationPath;
+    use crate::transactions::transaction::{Transaction, TransactionOutput, TransactionStatus};
+    use crate::transactions::utxo::{UTXOStatus, UTXO};
+    use std::str::FromStr;
+
+    fn transaction(txid: &str, address: &str, block_time: Option<u64>) -> Transaction {
+        Transaction {
+            txid: txid.to_string(),
+            version: 2,
+            locktime: 0,
+            vin: Vec::new(),
+            vout: vec![TransactionOutput {
+                scriptpubkey: String::new(),
+                scriptpubkey_asm: String::new(),
+                scriptpubkey_type: String::new(),
+                scriptpubkey_address: address.to_string(),
+                value: 10000,
+            }],
+            size: None,
+            weight: None,
+            fee: Some(0),
+            status: Some(TransactionStatus {
+                confirmed: block_time.is_some(),
+                block_height: None,
+                block_hash: None,
+                block_time,
+            }),
+        }
+    }
+
+    fn utxo(txid: &str, confirmed: bool) -> UTXO {
+        UTXO {
+            txid: txid.to_string(),
+            vout: 0,
+            value: 10000,
+            status: UTXOStatus {
+                confirmed,
+                block_height: None,
+                block_hash: None,
+                block_time: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_address_details() {
+        let address = "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1";
+        let path = DerivationPath::from_str("m/44'/0'/0/2").unwrap();
+        let txs = vec![
+            transaction("a", address, Some(1700000000)),
+            transaction("b", address, Some(1600000000)),
+            transaction("b", address, Some(1600000000)),
+            transaction("c", address, None),
+            transaction("d", "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ2", Some(1800000000)),
+        ];
+        let utxos = vec![utxo("a", true), utxo("c", false)];
+
+        let details = AddressDetails::create(address, 0, &path, &utxos, &txs);
+        assert_eq!(details.path, "m/44'/0'/0/2");
+        assert_eq!(details.balance, 20000);
+        assert_eq!(details.confirmed_balance, 10000);
+        assert_eq!(details.tx_count, 4);
+        assert_eq!(details.receive_count, 3);
+        assert_eq!(details.total_received, 30000);
+        assert!(details.reused);
+        assert_eq!(details.first_used, Some(1600000000));
+        assert_eq!(details.last_used, Some(1800000000));
+        assert!(details.pending);
+
+        let details = AddressDetails::create(address, 0, &path, &[], &[]);
+        assert_eq!(details.tx_count, 0);
+        assert!(!details.reused);
+        assert_eq!(details.first_used, None);
+        assert!(!details.pending);
+    }
+}
@@ -0,0 +1,167 @@
+//! Implements the breakdown of the balance of an account by the status of its UTXOs.
+//! An unconfirmed UTXO is change if the transaction that created it spends UTXOs of
+//! the account, since such a transaction cannot be replaced by a third party.
+//! The change can be spent right away, while unconfirmed incoming payments should
+//! not be relied on until they are confirmed.
+use super::transaction::{SimplifiedTransaction, TransactionType};
+use super::utxo::UTXO;
+use crate::coin_selection::frozen::FrozenUTXOs;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountBalance {
+    pub total: u64,
+    pub confirmed: u64,
+    pub unconfirmed_incoming: u64,
+    pub unconfirmed_change: u64,
+    pub frozen: u64,
+    pub spendable: u64, // the confirmed UTXOs and the change that are not frozen
+}
+
+impl AccountBalance {
+    /// Creates the breakdown of the balance from the UTXOs of the account.
+    /// The transactions of the account tell unconfirmed change from incoming payments.
+    pub fn create(
+        utxos: &[UTXO],
+        simple_txs: &[SimplifiedTransaction],
+        frozen: &FrozenUTXOs,
+    ) -> AccountBalance {
+        let spendable_utxos = SpendableUTXOs::new(simple_txs, frozen);
+        let mut balance = AccountBalance::default();
+        for utxo in utxos {
+            balance.total += utxo.value;
+            if utxo.status.confirmed {
+                balance.confirmed += utxo.value;
+            } else if spendable_utxos.is_change(utxo) {
+                balance.unconfirmed_change += utxo.value;
+            } else {
+                balance.unconfirmed_incoming += utxo.value;
+            }
+            if frozen.is_frozen(&utxo.txid, utxo.vout) {
+                balance.frozen += utxo.value;
+            } else if spendable_utxos.contains(utxo) {
+                balance.spendable += utxo.value;
+            }
+        }
+        balance
+    }
+}
+
+/// Tells the UTXOs that count towards the spendable balance of an account, so the
+/// amount that can be sent is computed from the same UTXOs as the balance.
+pub struct SpendableUTXOs<'a> {
+    spending_txids: HashSet<&'a str>,
+    frozen: &'a FrozenUTXOs,
+}
+
+impl<'a> SpendableUTXOs<'a> {
+    pub fn new(
+        simple_txs: &'a [SimplifiedTransaction],
+        frozen: &'a FrozenUTXOs,
+    ) -> SpendableUTXOs<'a> {
+        let spending_txids = simple_txs
+            .iter()
+            .filter(|simple_tx| !matches!(simple_tx.transaction_type, TransactionType::Incoming))
+            .map(|simple_tx| simple_tx.txid.as_str())
+            .collect();
+        SpendableUTXOs {
+            spending_txids,
+            frozen,
+        }
+    }
+
+    /// Returns true if the UTXO is unconfirmed and was created by a transaction
+    /// that spends UTXOs of the account.
+    pub fn is_change(&self, utxo: &UTXO) -> bool {
+        !utxo.status.confirmed && self.spending_txids.contains(utxo.txid.as_str())
+    }
+
+    /// Returns true if the UTXO is confirmed or change and is not frozen.
+    pub fn contains(&self, utxo: &UTXO) -> bool {
+        (utxo.status.confirmed || self.is_change(utxo))
+            && !self.frozen.is_frozen(&utxo.txid, utxo.vout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccountBalance, SpendableUTXOs};
+    use crate::coin_selection::frozen::FrozenUTXOs;
+    use crate::transactions::transaction::{SimplifiedTransaction, TransactionType};
+    use crate::transactions::utxo::{UTXOStatus, UTXO};
+
+    fn utxo(txid: &str, value: u64, confirmed: bool) -> UTXO {
+        UTXO {
+            txid: txid.to_string(),
+            vout: 0,
+            value,
+            status: UTXOStatus {
+                confirmed,
+                block_height: None,
+                block_hash: None,
+                block_time: None,
+            },
+        }
+    }
+
+    fn simple_tx(txid: &str, transaction_type: TransactionType) -> SimplifiedTransaction {
+        SimplifiedTransaction {
+            txid: txid.to_string(),
+            transaction_type,
+            value: 0,
+            fee: 0,
+            confirmed: false,
+            memo: None,
+            block_time: None,
+            fiat_value: None,
+            counterparties: Vec::new(),
+            contact: None,
+            block_height: None,
+            confirmations: None,
+            paths: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_account_balance() {
+        let utxos = vec![
+            utxo("a", 100000, true),
+            utxo("b", 20000, false),
+            utxo("c", 30000, false),
+            utxo("d", 4000, true),
+        ];
+        let simple_txs = vec![
+            simple_tx("b", TransactionType::Incoming),
+            simple_tx("c", TransactionType::Outgoing),
+        ];
+        let mut frozen = FrozenUTXOs::default();
+        frozen.freeze("d", 0);
+
+        let balance = AccountBalance::create(&utxos, &simple_txs, &frozen);
+        assert_eq!(
+            balance,
+            AccountBalance {
+                total: 154000,
+                confirmed: 104000,
+                unconfirmed_incoming: 20000,
+                unconfirmed_change: 30000,
+                frozen: 4000,
+                spendable: 130000,
+            }
+        );
+
+        let spendable_utxos = SpendableUTXOs::new(&simple_txs, &frozen);
+        let spendable: u64 = utxos
+            .iter()
+            .filter(|utxo| spendable_utxos.contains(utxo))
+            .map(|utxo| utxo.value)
+            .sum();
+        assert_eq!(spendable, balance.spendable);
+
+        // without the transactions, every unconfirmed UTXO is treated as incoming
+        let balance = AccountBalance::create(&utxos, &[], &FrozenUTXOs::default());
+        assert_eq!(balance.unconfirmed_incoming, 50000);
+        assert_eq!(balance.spendable, 104000);
+    }
+}
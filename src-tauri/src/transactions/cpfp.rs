@@ -0,0 +1,217 @@
+//! Implements child-pays-for-parent. An unconfirmed transaction is accelerated
+//! by a child transaction that spends its outputs to the wallet. The fee of the
+//! child is chosen, such that both transactions together pay the target fee per byte.
+use super::error::FeeBumpError;
+use super::transaction::{Transaction, DUST_LIMIT};
+use super::utxo::{UTXOBox, UTXOStatus, UTXO};
+use crate::coin_selection::{fee_estimation, sweep};
+use crate::keys::address::Address;
+use crate::keys::bip44::Keypair;
+
+/// Returns the outputs of the transaction that pay to one of the key pairs.
+pub fn spendable_outputs(parent: &Transaction, keypairs: &[Keypair]) -> Vec<UTXOBox> {
+    let mut boxed_utxos = Vec::new();
+    for (vout, tx_out) in parent.vout.iter().enumerate() {
+        let keypair = keypairs.iter().find(|keypair| {
+            keypair.public_key.get_address().to_string() == tx_out.scriptpubkey_address
+        });
+        if let Some(keypair) = keypair {
+            boxed_utxos.push(UTXOBox {
+                utxo: UTXO {
+                    txid: parent.txid.clone(),
+                    vout: vout as u32,
+                    value: tx_out.value,
+                    status: UTXOStatus {
+                        confirmed: false,
+                        block_height: None,
+                        block_hash: None,
+                        block_time: None,
+                    },
+                },
+                output: tx_out.clone(),
+                keypair: keypair.clone(),
+            });
+        }
+    }
+    boxed_utxos
+}
+
+/// Returns the fee per byte that the child has to pay, such that the parent and
+/// the child together pay the target fee per byte. The child pays at least
+/// the target fee per byte itself.
+pub fn child_fee_per_byte(parent: &Transaction, num_child_inputs: u32, fee_per_byte: u64) -> u64 {
+    let parent_size = match parent.weight {
+        Some(weight) => weight.div_ceil(4) as u64,
+        None => parent.serialize().len() as u64,
+    };
+    let parent_fee = match parent.fee {
+        Some(fee) => fee,
+        None => {
+            let input_value: u64 = parent.vin.iter().map(|tx_in| tx_in.prevout.value).sum();
+            let output_value: u64 = parent.vout.iter().map(|tx_out| tx_out.value).sum();
+            input_value.saturating_sub(output_value)
+        }
+    };
+    let child_size = fee_estimation::estimate_transaction_size(num_child_inputs, 1) as u64;
+    let child_fee = (fee_per_byte * (parent_size + child_size)).saturating_sub(parent_fee);
+    // rounded up, so the package does not fall short of the target
+    let child_fee_per_byte = child_fee.div_ceil(child_size);
+    std::cmp::max(child_fee_per_byte, fee_per_byte)
+}
+
+/// Returns the signed child transaction that spends the specified outputs of the parent
+/// to the target address. The outputs are selected with `spendable_outputs`.
+pub fn create_child(
+    parent: &Transaction,
+    boxed_utxos: Vec<UTXOBox>,
+    target_address: Address,
+    fee_per_byte: u64,
+) -> Result<Transaction, FeeBumpError> {
+    if parent
+        .status
+        .as_ref()
+        .is_some_and(|status| status.confirmed)
+    {
+        return Err(FeeBumpError::new("tx_confirmed_error"));
+    }
+    if boxed_utxos.is_empty() {
+        return Err(FeeBumpError::new("spendable_output_missing_error"));
+    }
+    let child_fee_per_byte = child_fee_per_byte(parent, boxed_utxos.len() as u32, fee_per_byte);
+    let batch = match sweep::select_single_batch(boxed_utxos, child_fee_per_byte) {
+        Ok(batch) => batch,
+        Err(err) => return Err(FeeBumpError::new(&err.to_string())),
+    };
+    if batch.amount < DUST_LIMIT {
+        return Err(FeeBumpError::new("balance_insufficient"));
+    }
+    let targets = vec![(target_address, batch.amount)];
+    let mut child = match Transaction::create(&batch.selected_utxos, targets) {
+        Ok(child) => child,
+        Err(_) => return Err(FeeBumpError::new("create_tx_error")),
+    };
+    child.signal_rbf();
+    child.sign_all_inputs(&batch.selected_utxos);
+    child.txid = child.compute_txid();
+    Ok(child)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{child_fee_per_byte, create_child, spendable_outputs};
+    use crate::keys::bip44::private_hierarchy::Keypair;
+    use crate::test_utils::keypair_from_hex;
+    use crate::transactions::{
+        script,
+        transaction::{Transaction, TransactionInput, TransactionOutput, TransactionStatus},
+    };
+
+    fn p2pkh_output(keypair: &Keypair, value: u64) -> TransactionOutput {
+        let address = keypair.public_key.get_address();
+        TransactionOutput {
+            scriptpubkey: String::from(""),
+            scriptpubkey_asm: script::p2pkh_script_pub_key(&address.get_hash()),
+            scriptpubkey_type: String::from("p2pkh"),
+            scriptpubkey_address: address.to_string(),
+            value,
+        }
+    }
+
+    /// Returns a transaction of 250 vbytes that pays 250 sat in fees
+    /// and 50000 sat to each of the key pairs.
+    fn parent_tx(keypairs: &[Keypair]) -> Transaction {
+        Transaction {
+            txid: String::from("d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a"),
+            version: 1,
+            locktime: 0,
+            vin: vec![TransactionInput {
+                txid: String::from(
+                    "0dd7697dfc7f13978dcf44e7d82c0be4591dec423616510a46cdb6cfeddae02d",
+                ),
+                vout: 0,
+                prevout: TransactionOutput::default(),
+                scriptsig: String::from(""),
+                scriptsig_asm: String::from(""),
+                witness: None,
+                is_coinbase: false,
+                sequence: 0xffffffff,
+            }],
+            vout: keypairs
+                .iter()
+                .map(|keypair| p2pkh_output(keypair, 50000))
+                .collect(),
+            size: Some(250),
+            weight: Some(1000),
+            fee: Some(250),
+            status: Some(TransactionStatus {
+                confirmed: false,
+                block_height: None,
+                block_hash: None,
+                block_time: None,
+            }),
+        }
+    }
+
+    fn keypairs() -> Vec<Keypair> {
+        vec![
+            keypair_from_hex("eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf"),
+            keypair_from_hex("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9"),
+            keypair_from_hex("8e02b539b1500aa7c81cf3fed177448a546f19d2be416c0c61ff28e577d8d0cd"),
+        ]
+    }
+
+    #[test]
+    fn test_spendable_outputs() {
+        let keypairs = keypairs();
+        let parent = parent_tx(&keypairs[..2]);
+        let boxed_utxos = spendable_outputs(&parent, &keypairs[1..]);
+        assert_eq!(boxed_utxos.len(), 1);
+        assert_eq!(boxed_utxos[0].utxo.txid, parent.txid);
+        assert_eq!(boxed_utxos[0].utxo.vout, 1);
+        assert_eq!(boxed_utxos[0].utxo.value, 50000);
+    }
+
+    #[test]
+    fn test_child_fee_per_byte() {
+        let keypairs = keypairs();
+        let parent = parent_tx(&keypairs[..1]);
+        // the child of one input and one output has an estimated size of 191 bytes,
+        // so it pays 10 * (250 + 191) - 250 = 4160 sat
+        assert_eq!(child_fee_per_byte(&parent, 1, 10), 22);
+        // the parent already pays the target fee per byte
+        assert_eq!(child_fee_per_byte(&parent, 1, 1), 1);
+    }
+
+    #[test]
+    fn test_create_child() {
+        let keypairs = keypairs();
+        let parent = parent_tx(&keypairs[..2]);
+        let boxed_utxos = spendable_outputs(&parent, &keypairs);
+        let target_address = keypairs[2].public_key.get_address();
+        let child = create_child(&parent, boxed_utxos, target_address.clone(), 10).unwrap();
+        assert_eq!(child.vin.len(), 2);
+        assert_eq!(child.vin[0].txid, parent.txid);
+        assert!(child
+            .vin
+            .iter()
+            .all(|tx_in| !tx_in.scriptsig_asm.is_empty()));
+        assert!(child.signals_rbf());
+        assert_eq!(child.vout.len(), 1);
+        assert_eq!(
+            child.vout[0].scriptpubkey_address,
+            target_address.to_string()
+        );
+        // the child of two inputs has an estimated size of 338 bytes
+        let fee = 100000 - child.vout[0].value;
+        assert_eq!(fee, 338 * child_fee_per_byte(&parent, 2, 10));
+        assert!(fee + parent.fee.unwrap() >= 10 * (250 + 338));
+
+        // the outputs do not cover the fee
+        let boxed_utxos = spendable_outputs(&parent, &keypairs);
+        let err = create_child(&parent, boxed_utxos, target_address.clone(), 500).unwrap_err();
+        assert_eq!(err.to_string(), "balance_insufficient");
+        // no output pays to the wallet
+        let err = create_child(&parent, Vec::new(), target_address, 10).unwrap_err();
+        assert_eq!(err.to_string(), "spendable_output_missing_error");
+    }
+}
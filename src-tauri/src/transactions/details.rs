@@ -0,0 +1,248 @@
+//! Implements the details of a transaction, i.e., the decoded transaction together
+//! with the inputs and outputs that belong to the wallet. An input or output belongs
+//! to the wallet if its address was derived from the master public key.
+use super::transaction::Transaction;
+use crate::keys::bip32::DerivationPath;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DetailsInput {
+    pub txid: String,
+    pub vout: u32,
+    pub address: Option<String>, // None for coinbase inputs and scripts without an address
+    pub value: u64,
+    pub sequence: u32,
+    pub is_coinbase: bool,
+    pub is_mine: bool,
+    pub account_index: Option<u32>, // only if the input belongs to the wallet
+    pub path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DetailsOutput {
+    pub vout: u32,
+    pub address: Option<String>, // None for outputs without an address, e.g., OP_RETURN
+    pub value: u64,
+    pub script_type: String,
+    pub is_mine: bool,
+    pub account_index: Option<u32>, // only if the output belongs to the wallet
+    pub path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransactionDetails {
+    pub txid: String,
+    pub version: u32,
+    pub locktime: u32,
+    pub size: u32,
+    pub vsize: u32,
+    pub weight: u32,
+    pub fee: u64,
+    pub fee_rate: f64, // fee per vbyte
+    pub confirmed: bool,
+    pub block_height: Option<u64>,
+    pub block_hash: Option<String>,
+    pub block_time: Option<u64>,
+    pub confirmations: Option<u32>, // None if the height of the most recent block is unknown
+    pub inputs: Vec<DetailsInput>,
+    pub outputs: Vec<DetailsOutput>,
+    pub sent: u64,     // by the wallet, i.e., the value of its inputs
+    pub received: u64, // by the wallet, i.e., the value of its outputs
+    pub label: Option<String>,
+    pub hex: String,
+}
+
+impl TransactionDetails {
+    /// Creates the details of a transaction. The addresses of the wallet are mapped to
+    /// the index of their account and the derivation path of their key.
+    /// The number of confirmations is computed if the height of the most recent block is passed.
+    pub fn create(
+        tx: &Transaction,
+        address_paths: &HashMap<String, (u32, DerivationPath)>,
+        tip_height: Option<u32>,
+        label: Option<String>,
+    ) -> TransactionDetails {
+        let ownership = |address: &str| match address_paths.get(address) {
+            Some((account_index, path)) => (true, Some(*account_index), Some(path.to_string())),
+            None => (false, None, None),
+        };
+        let inputs: Vec<DetailsInput> = tx
+            .vin
+            .iter()
+            .map(|tx_in| {
+                let (is_mine, account_index, path) = ownership(&tx_in.prevout.scriptpubkey_address);
+                DetailsInput {
+                    txid: tx_in.txid.clone(),
+                    vout: tx_in.vout,
+                    address: match tx_in.prevout.scriptpubkey_address.as_str() {
+                        "" => None,
+                        address => Some(address.to_string()),
+                    },
+                    value: tx_in.prevout.value,
+                    sequence: tx_in.sequence,
+                    is_coinbase: tx_in.is_coinbase,
+                    is_mine,
+                    account_index,
+                    path,
+                }
+            })
+            .collect();
+        let outputs: Vec<DetailsOutput> = tx
+            .vout
+            .iter()
+            .enumerate()
+            .map(|(vout, tx_out)| {
+                let (is_mine, account_index, path) = ownership(&tx_out.scriptpubkey_address);
+                DetailsOutput {
+                    vout: vout as u32,
+                    address: match tx_out.scriptpubkey_address.as_str() {
+                        "" => None,
+                        address => Some(address.to_string()),
+                    },
+                    value: tx_out.value,
+                    script_type: tx_out.scriptpubkey_type.clone(),
+                    is_mine,
+                    account_index,
+                    path,
+                }
+            })
+            .collect();
+        let sent = inputs
+            .iter()
+            .filter(|input| input.is_mine)
+            .map(|input| input.value)
+            .sum();
+        let received = outputs
+            .iter()
+            .filter(|output| output.is_mine)
+            .map(|output| output.value)
+            .sum();
+        // the fee of the backend is used, since coinbase inputs have no value
+        let fee = tx.fee.unwrap_or_else(|| {
+            let input_value: u64 = inputs.iter().map(|input| input.value).sum();
+            let output_value: u64 = outputs.iter().map(|output| output.value).sum();
+            input_value.saturating_sub(output_value)
+        });
+        let size = tx.size.unwrap_or_else(|| tx.serialize().len() as u32);
+        let vsize = match tx.weight {
+            Some(weight) => weight.div_ceil(4),
+            None => tx.vsize(),
+        };
+        let weight = tx.weight.unwrap_or(vsize * 4);
+        let status = tx.status.as_ref();
+        let confirmed = status.is_some_and(|status| status.confirmed);
+        let block_height = status.and_then(|status| status.block_height);
+        let confirmations = tip_height.map(|tip_height| match block_height {
+            Some(block_height) if confirmed => {
+                (tip_height as u64 + 1).saturating_sub(block_height) as u32
+            }
+            _ => 0,
+        });
+        TransactionDetails {
+            txid: tx.txid.clone(),
+            version: tx.version,
+            locktime: tx.locktime,
+            size,
+            vsize,
+            weight,
+            fee,
+            fee_rate: fee as f64 / vsize as f64,
+            confirmed,
+            block_height,
+            block_hash: status.and_then(|status| status.block_hash.clone()),
+            block_time: status.and_then(|status| status.block_time),
+            confirmations,
+            inputs,
+            outputs,
+            sent,
+            received,
+            label,
+            hex: tx.serialize_hex(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TransactionDetails;
+    use crate::keys::bip32::DerivationPath;
+    use crate::transactions::transaction::{
+        Transaction, TransactionInput, TransactionOutput, TransactionStatus,
+    };
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    fn output(address: &str, value: u64) -> TransactionOutput {
+        TransactionOutput {
+            scriptpubkey: String::from("0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+            scriptpubkey_asm: String::new(),
+            scriptpubkey_type: String::from("v0_p2wpkh"),
+            scriptpubkey_address: address.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_create_details() {
+        let tx = Transaction {
+            txid: String::from("a"),
+            version: 2,
+            locktime: 0,
+            vin: vec![TransactionInput {
+                txid: String::from(
+                    "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+                ),
+                vout: 1,
+                prevout: output("mine_1", 100000),
+                scriptsig: String::new(),
+                scriptsig_asm: String::new(),
+                witness: None,
+                is_coinbase: false,
+                sequence: 0xfffffffd,
+            }],
+            vout: vec![output("theirs", 60000), output("mine_2", 38000)],
+            size: Some(222),
+            weight: Some(561),
+            fee: Some(2000),
+            status: Some(TransactionStatus {
+                confirmed: true,
+                block_height: Some(100),
+                block_hash: Some(String::from("c")),
+                block_time: Some(1700000000),
+            }),
+        };
+        let mut address_paths = HashMap::new();
+        address_paths.insert(
+            String::from("mine_1"),
+            (0, DerivationPath::from_str("m/84'/0'/0/3").unwrap()),
+        );
+        address_paths.insert(
+            String::from("mine_2"),
+            (1, DerivationPath::from_str("m/84'/1'/1/0").unwrap()),
+        );
+
+        let details =
+            TransactionDetails::create(&tx, &address_paths, Some(104), Some("Rent".to_string()));
+        assert_eq!(details.vsize, 141);
+        assert_eq!(details.size, 222);
+        assert_eq!(details.fee, 2000);
+        assert_eq!(details.confirmations, Some(5));
+        assert!(details.inputs[0].is_mine);
+        assert_eq!(details.inputs[0].account_index, Some(0));
+        assert_eq!(details.inputs[0].path, Some("m/84'/0'/0/3".to_string()));
+        assert!(!details.outputs[0].is_mine);
+        assert_eq!(details.outputs[0].path, None);
+        assert!(details.outputs[1].is_mine);
+        assert_eq!(details.outputs[1].vout, 1);
+        assert_eq!(details.outputs[1].account_index, Some(1));
+        assert_eq!(details.sent, 100000);
+        assert_eq!(details.received, 38000);
+        assert_eq!(details.label, Some("Rent".to_string()));
+        assert_eq!(details.hex, tx.serialize_hex());
+
+        let details = TransactionDetails::create(&tx, &HashMap::new(), None, None);
+        assert_eq!(details.confirmations, None);
+        assert!(details.outputs.iter().all(|output| !output.is_mine));
+    }
+}
@@ -17,6 +17,204 @@ impl fmt::Debug for ParseScriptTypeError {
     }
 }
 
+/// This error occurs when parsing the format of an export fails.
+pub struct ParseExportFormatError;
+
+impl fmt::Display for ParseExportFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ParseExportFormatError")
+    }
+}
+
+impl fmt::Debug for ParseExportFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ParseExportFormatError")
+    }
+}
+
+/// This error occurs when a serialized transaction cannot be parsed.
+pub struct ParseTransactionError {
+    message: String,
+}
+
+impl ParseTransactionError {
+    pub fn new(message: &str) -> ParseTransactionError {
+        ParseTransactionError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseTransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for ParseTransactionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ParseTransactionError {}
+
+/// This error occurs when a transaction fails the sanity checks before the broadcast.
+/// The message is the error code that is returned to the frontend.
+pub struct InvalidTransactionError {
+    message: String,
+}
+
+impl InvalidTransactionError {
+    pub fn new(message: &str) -> InvalidTransactionError {
+        InvalidTransactionError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for InvalidTransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for InvalidTransactionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// This error occurs when a payment URI cannot be parsed.
+/// The message is the error code that is returned to the frontend.
+pub struct PaymentUriError {
+    message: String,
+}
+
+impl PaymentUriError {
+    pub fn new(message: &str) -> PaymentUriError {
+        PaymentUriError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for PaymentUriError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for PaymentUriError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// This error occurs when a payjoin cannot be negotiated,
+/// e.g., because the proposal of the receiver is invalid.
+pub struct PayjoinError {
+    message: String,
+}
+
+impl PayjoinError {
+    pub fn new(message: &str) -> PayjoinError {
+        PayjoinError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for PayjoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for PayjoinError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// This error occurs when the fee of a transaction cannot be bumped.
+/// The message is the error code that is returned to the frontend.
+pub struct FeeBumpError {
+    message: String,
+}
+
+impl FeeBumpError {
+    pub fn new(message: &str) -> FeeBumpError {
+        FeeBumpError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FeeBumpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for FeeBumpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// This error occurs when a PSBT cannot be parsed, signed or merged.
+/// The message is the error code that is returned to the frontend.
+pub struct PsbtError {
+    message: String,
+}
+
+impl PsbtError {
+    pub fn new(message: &str) -> PsbtError {
+        PsbtError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for PsbtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for PsbtError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// This error occurs when a transaction input cannot be signed with the requested
+/// signature hash type. The message is the error code that is returned to the frontend.
+pub struct SigningError {
+    message: String,
+}
+
+impl SigningError {
+    pub fn new(message: &str) -> SigningError {
+        SigningError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SigningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for SigningError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
 /// This error occurs when a unsupported script type
 /// is encountered.
 pub struct UnsupportedScriptError {
@@ -0,0 +1,237 @@
+//! Implements the export of the transaction history of an account to CSV and JSON,
+//! e.g., for accounting tools. Every transaction is a record with its date, its ID,
+//! its type, the amount and the fee in bitcoin, its value in a fiat currency and its label.
+//! Outgoing amounts are negative.
+use super::error::ParseExportFormatError;
+use super::transaction::{SimplifiedTransaction, TransactionType};
+use serde::Serialize;
+use serde_json;
+use std::str::FromStr;
+
+static CSV_HEADER: &'static str = "date,txid,type,amount,fee,fiat_value,fiat_currency,label";
+static SATOSHIS_PER_BITCOIN: u64 = 100000000;
+static SECONDS_PER_DAY: u64 = 86400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = ParseExportFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(ParseExportFormatError {}),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TransactionRecord {
+    pub date: Option<String>, // of the block in UTC, if the transaction is confirmed
+    pub txid: String,
+    #[serde(rename = "type")]
+    pub transaction_type: String,
+    pub amount: String,      // in bitcoin
+    pub fee: Option<String>, // in bitcoin, only if the wallet paid the fee
+    pub fiat_value: Option<f64>,
+    pub fiat_currency: Option<String>,
+    pub label: Option<String>,
+}
+
+impl TransactionRecord {
+    /// Creates the record of the transaction. The fiat value of the transaction is in the
+    /// specified currency.
+    pub fn create(simple_tx: &SimplifiedTransaction, fiat_currency: Option<&str>) -> Self {
+        let (transaction_type, negative, fee) = match simple_tx.transaction_type {
+            TransactionType::Incoming => ("incoming", false, None),
+            TransactionType::Outgoing => ("outgoing", true, Some(simple_tx.fee)),
+            TransactionType::Internal => ("internal", false, Some(simple_tx.fee)),
+        };
+        let fiat_value = simple_tx
+            .fiat_value
+            .map(|fiat_value| if negative { -fiat_value } else { fiat_value });
+        TransactionRecord {
+            date: simple_tx.block_time.map(format_date),
+            txid: simple_tx.txid.clone(),
+            transaction_type: transaction_type.to_string(),
+            amount: format_bitcoin(simple_tx.value, negative),
+            fee: fee.map(|fee| format_bitcoin(fee, false)),
+            fiat_value,
+            fiat_currency: fiat_value.and(fiat_currency.map(|currency| currency.to_string())),
+            label: simple_tx.memo.clone(),
+        }
+    }
+}
+
+/// Returns the records of the transactions in the format.
+/// The transactions are exported in the order of the history.
+pub fn export(
+    simple_txs: &[SimplifiedTransaction],
+    format: ExportFormat,
+    fiat_currency: Option<&str>,
+) -> String {
+    let records: Vec<TransactionRecord> = simple_txs
+        .iter()
+        .map(|simple_tx| TransactionRecord::create(simple_tx, fiat_currency))
+        .collect();
+    match format {
+        ExportFormat::Csv => to_csv(&records),
+        ExportFormat::Json => serde_json::to_string_pretty(&records).unwrap(),
+    }
+}
+
+/// Returns the records as CSV with a header row.
+fn to_csv(records: &[TransactionRecord]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for record in records {
+        let fields = [
+            record.date.clone().unwrap_or_default(),
+            record.txid.clone(),
+            record.transaction_type.clone(),
+            record.amount.clone(),
+            record.fee.clone().unwrap_or_default(),
+            record
+                .fiat_value
+                .map(|fiat_value| format!("{:.2}", fiat_value))
+                .unwrap_or_default(),
+            record.fiat_currency.clone().unwrap_or_default(),
+            record.label.clone().unwrap_or_default(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| escape_csv(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quotes a field that contains a comma, a quote or a line break.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Formats an amount in satoshis in bitcoin with eight decimal places.
+fn format_bitcoin(amount: u64, negative: bool) -> String {
+    format!(
+        "{}{}.{:08}",
+        if negative && amount > 0 { "-" } else { "" },
+        amount / SATOSHIS_PER_BITCOIN,
+        amount % SATOSHIS_PER_BITCOIN
+    )
+}
+
+/// Formats a UNIX timestamp as a date and time in UTC in the ISO 8601 format.
+fn format_date(timestamp: u64) -> String {
+    let days = timestamp / SECONDS_PER_DAY;
+    let seconds = timestamp % SECONDS_PER_DAY;
+    // converts the days since 1970-01-01 to the date in the proleptic Gregorian calendar,
+    // with years that start in March, so the leap day is the last day of a year
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153; // starting with March
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export, format_date, ExportFormat};
+    use crate::transactions::transaction::{SimplifiedTransaction, TransactionType};
+    use std::str::FromStr;
+
+    fn simple_tx(
+        txid: &str,
+        transaction_type: TransactionType,
+        value: u64,
+        block_time: Option<u64>,
+        memo: Option<&str>,
+    ) -> SimplifiedTransaction {
+        SimplifiedTransaction {
+            txid: txid.to_string(),
+            transaction_type,
+            value,
+            fee: 1410,
+            confirmed: block_time.is_some(),
+            memo: memo.map(|memo| memo.to_string()),
+            block_time,
+            fiat_value: block_time.map(|_| 12.5),
+            counterparties: Vec::new(),
+            contact: None,
+            block_height: None,
+            confirmations: None,
+            paths: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let simple_txs = vec![
+            simple_tx(
+                "a",
+                TransactionType::Outgoing,
+                50000,
+                None,
+                Some("Rent, March"),
+            ),
+            simple_tx(
+                "b",
+                TransactionType::Incoming,
+                123456789,
+                Some(1700000000),
+                None,
+            ),
+        ];
+        let csv = export(&simple_txs, ExportFormat::Csv, Some("usd"));
+        assert_eq!(
+            csv,
+            "date,txid,type,amount,fee,fiat_value,fiat_currency,label\n\
+             ,a,outgoing,-0.00050000,0.00001410,,,\"Rent, March\"\n\
+             2023-11-14T22:13:20Z,b,incoming,1.23456789,,12.50,usd,\n"
+        );
+
+        let json = export(&simple_txs, ExportFormat::Json, Some("usd"));
+        let records: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(records[0]["type"], "outgoing");
+        assert_eq!(records[0]["fee"], "0.00001410");
+        assert_eq!(records[1]["date"], "2023-11-14T22:13:20Z");
+        assert_eq!(records[1]["fiat_value"], 12.5);
+        assert!(records[1]["fee"].is_null());
+
+        assert_eq!(ExportFormat::from_str("json").unwrap(), ExportFormat::Json);
+        assert!(ExportFormat::from_str("xlsx").is_err());
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_date(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_date(1231006505), "2009-01-03T18:15:05Z");
+    }
+}
@@ -0,0 +1,120 @@
+//! Adjusts the fee of a signed transaction to its actual size. The coin selection
+//! estimates the size of the transaction before it is signed, so the fee rate that
+//! the transaction pays can deviate from the requested fee rate. The deviation is
+//! absorbed by the change output.
+use super::transaction::{Transaction, DUST_LIMIT};
+use super::utxo::UTXOBox;
+
+static FEE_TOLERANCE_VBYTES: u64 = 2; // the length of a signature varies by a byte
+static MAX_ADJUSTMENTS: usize = 3;
+
+/// Adjusts the value of the change output with the specified index, such that the
+/// transaction pays the fee per byte for its virtual size. A fee that exceeds the
+/// required fee by at most the tolerance is kept. The transaction is signed again
+/// after each adjustment, since the signatures commit to the outputs.
+/// Returns true if the transaction was adjusted. The transaction is not adjusted
+/// if the change would fall below the dust limit.
+pub fn adjust_change(
+    tx: &mut Transaction,
+    boxed_utxos: &Vec<UTXOBox>,
+    change_index: usize,
+    fee_per_byte: u64,
+) -> bool {
+    let mut adjusted = false;
+    for _ in 0..MAX_ADJUSTMENTS {
+        let input_value: u64 = tx.vin.iter().map(|tx_in| tx_in.prevout.value).sum();
+        let output_value: u64 = tx.vout.iter().map(|tx_out| tx_out.value).sum();
+        let fee = input_value.saturating_sub(output_value);
+        let required_fee = tx.vsize() as u64 * fee_per_byte;
+        if fee >= required_fee && fee - required_fee <= FEE_TOLERANCE_VBYTES * fee_per_byte {
+            break;
+        }
+        let change = match (tx.vout[change_index].value + fee).checked_sub(required_fee) {
+            Some(change) if change >= DUST_LIMIT => change,
+            _ => break,
+        };
+        tx.vout[change_index].value = change;
+        tx.sign_all_inputs(boxed_utxos);
+        adjusted = true;
+    }
+    if adjusted {
+        tx.txid = tx.compute_txid();
+    }
+    adjusted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::adjust_change;
+    use crate::test_utils::keypair;
+    use crate::transactions::{
+        transaction::{Transaction, TransactionOutput},
+        utxo::{UTXOBox, UTXOStatus, UTXO},
+    };
+
+    fn signed_transaction(change: u64) -> (Transaction, Vec<UTXOBox>) {
+        let keypair = keypair(1);
+        let address = keypair.public_key.get_address();
+        let boxed_utxos = vec![UTXOBox {
+            utxo: UTXO {
+                txid: String::from(
+                    "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+                ),
+                vout: 1,
+                value: 100000,
+                status: UTXOStatus {
+                    confirmed: true,
+                    block_height: None,
+                    block_hash: None,
+                    block_time: None,
+                },
+            },
+            output: TransactionOutput::create(&address, 100000),
+            keypair: keypair.clone(),
+        }];
+        let targets = vec![
+            (self::keypair(2).public_key.get_address(), 60000),
+            (address, change),
+        ];
+        let mut tx = Transaction::create(&boxed_utxos, targets).unwrap();
+        tx.sign_all_inputs(&boxed_utxos);
+        tx.txid = tx.compute_txid();
+        (tx, boxed_utxos)
+    }
+
+    fn fee(tx: &Transaction) -> u64 {
+        let input_value: u64 = tx.vin.iter().map(|tx_in| tx_in.prevout.value).sum();
+        let output_value: u64 = tx.vout.iter().map(|tx_out| tx_out.value).sum();
+        input_value - output_value
+    }
+
+    #[test]
+    fn test_adjust_change() {
+        // the fee of 2000 overpays a fee rate of 1
+        let (mut tx, boxed_utxos) = signed_transaction(38000);
+        assert!(adjust_change(&mut tx, &boxed_utxos, 1, 1));
+        let vsize = tx.vsize() as u64;
+        assert!(fee(&tx) >= vsize && fee(&tx) <= vsize + 2);
+        assert_eq!(tx.vout[0].value, 60000);
+        assert_eq!(tx.txid, tx.compute_txid());
+
+        // the fee of 2000 underpays a fee rate of 20
+        let (mut tx, boxed_utxos) = signed_transaction(38000);
+        assert!(adjust_change(&mut tx, &boxed_utxos, 1, 20));
+        let vsize = tx.vsize() as u64;
+        assert!(fee(&tx) >= 20 * vsize && fee(&tx) <= 20 * (vsize + 2));
+        assert!(tx.vout[1].value < 38000);
+
+        // a fee within the tolerance is kept
+        let (tx, _) = signed_transaction(38000);
+        let (mut tx, boxed_utxos) = signed_transaction(40000 - 10 * tx.vsize() as u64 - 10);
+        let txid = tx.txid.clone();
+        assert!(!adjust_change(&mut tx, &boxed_utxos, 1, 10));
+        assert_eq!(tx.txid, txid);
+
+        // the change cannot fall below the dust limit
+        let (mut tx, boxed_utxos) = signed_transaction(38000);
+        assert!(!adjust_change(&mut tx, &boxed_utxos, 1, 200));
+        assert_eq!(tx.vout[1].value, 38000);
+    }
+}
@@ -0,0 +1,212 @@
+//! Implements a journal of signed transactions that are persisted before
+//! they are broadcast. If a send fails after the broadcast (e.g. while saving
+//! the key file), a retry finds the journaled transaction and rebroadcasts it
+//! instead of constructing a new one, which would pay the recipient twice.
+use bincode;
+use bitcoin_hashes::{sha256, Hash};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::send_request::SendRequest;
+use super::transaction::Transaction;
+use crate::utils::{file, hex};
+
+// sends run concurrently, so the journal is loaded, changed and saved under this lock
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub transaction: Transaction,
+    pub change_index: Option<u32>,
+    pub total_amount: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BroadcastJournal {
+    entries: HashMap<String, JournalEntry>,
+}
+
+impl BroadcastJournal {
+    /// Returns the key that identifies a send request. All options of the request are
+    /// part of it, so a changed request is not answered with the journaled transaction.
+    pub fn request_key(request: &SendRequest) -> String {
+        let request_encoded = bincode::serialize(request).unwrap();
+        hex::bytes_to_hex(&sha256::Hash::hash(&request_encoded))
+    }
+
+    /// Returns the key of the replacement that bumps the fee of the specified transaction
+    /// to the fee per byte. A replacement never shares its key with a send request.
+    pub fn replacement_key(txid: &str, fee: u64) -> String {
+        let replacement_encoded = bincode::serialize(&("replacement", txid, fee)).unwrap();
+        hex::bytes_to_hex(&sha256::Hash::hash(&replacement_encoded))
+    }
+
+    /// Returns the journaled transaction for the specified send request.
+    pub fn get(&self, request_key: &str) -> Option<&JournalEntry> {
+        self.entries.get(request_key)
+    }
+
+    /// Records a signed transaction for the specified send request.
+    pub fn insert(&mut self, request_key: String, entry: JournalEntry) {
+        self.entries.insert(request_key, entry);
+    }
+
+    /// Removes the journaled transaction for the specified send request.
+    pub fn remove(&mut self, request_key: &str) -> Option<JournalEntry> {
+        self.entries.remove(request_key)
+    }
+
+    /// Saves the journal to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let journal_encoded: Vec<u8> = bincode::serialize(self)?;
+        file::write_atomic(path, &journal_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the journal from the specified path, applies the update and saves it.
+    /// Concurrent updates are serialized, so an update does not overwrite the entries
+    /// that were recorded by another send in the meantime.
+    pub fn update<P: AsRef<Path>, R>(
+        path: P,
+        update: impl FnOnce(&mut BroadcastJournal) -> R,
+    ) -> Result<R, Box<dyn Error>> {
+        let _guard = UPDATE_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut journal = BroadcastJournal::load(&path)?;
+        let res = update(&mut journal);
+        journal.save(&path)?;
+        Ok(res)
+    }
+
+    /// Loads the journal from the specified path.
+    /// Returns an empty journal if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<BroadcastJournal, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(BroadcastJournal::default());
+        }
+        let buffer = fs::read(path)?;
+        let journal: BroadcastJournal = bincode::deserialize(&buffer[..])?;
+        Ok(journal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BroadcastJournal, JournalEntry};
+    use crate::transactions::send_request::SendRequest;
+    use crate::transactions::transaction::Transaction;
+    use std::fs;
+    use std::thread;
+
+    #[test]
+    fn test_request_key() {
+        let address = String::from("mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1");
+        let request = SendRequest {
+            coin_type_index: 1,
+            targets: vec![(address.clone(), 1000)],
+            fee: 8,
+            ..SendRequest::default()
+        };
+        let key = BroadcastJournal::request_key(&request);
+        assert_eq!(key, BroadcastJournal::request_key(&request));
+        assert_ne!(
+            key,
+            BroadcastJournal::request_key(&SendRequest {
+                targets: vec![(address.clone(), 1001)],
+                ..request.clone()
+            })
+        );
+        assert_ne!(
+            key,
+            BroadcastJournal::request_key(&SendRequest {
+                data: Some("memo".to_string()),
+                ..request.clone()
+            })
+        );
+        // the spent UTXOs are part of the request
+        let outpoints = vec![(String::from("ab").repeat(32), 0)];
+        let manual = BroadcastJournal::request_key(&SendRequest {
+            outpoints: Some(outpoints.clone()),
+            ..request.clone()
+        });
+        assert_ne!(key, manual);
+        assert_ne!(
+            manual,
+            BroadcastJournal::request_key(&SendRequest {
+                outpoints: Some(vec![(outpoints[0].0.clone(), 1)]),
+                ..request.clone()
+            })
+        );
+        // the order of the outputs is part of the request
+        let targets = vec![(address.clone(), 1000), (address.clone(), 2000)];
+        let reversed = vec![(address.clone(), 2000), (address, 1000)];
+        assert_ne!(
+            BroadcastJournal::request_key(&SendRequest {
+                targets,
+                ..request.clone()
+            }),
+            BroadcastJournal::request_key(&SendRequest {
+                targets: reversed,
+                ..request
+            })
+        );
+    }
+
+    #[test]
+    fn test_replacement_key() {
+        let txid = "ab".repeat(32);
+        let key = BroadcastJournal::replacement_key(&txid, 10);
+        assert_eq!(key, BroadcastJournal::replacement_key(&txid, 10));
+        assert_ne!(key, BroadcastJournal::replacement_key(&txid, 11));
+        assert_ne!(key, BroadcastJournal::replacement_key(&"cd".repeat(32), 10));
+    }
+
+    #[test]
+    fn test_concurrent_updates() {
+        let path = std::env::temp_dir().join("ghorbu_test_concurrent_journal_updates");
+        let _ = fs::remove_file(&path);
+        let handles: Vec<_> = (0..8)
+            .map(|index| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    BroadcastJournal::update(&path, |journal| {
+                        journal.insert(
+                            index.to_string(),
+                            JournalEntry {
+                                transaction: Transaction {
+                                    txid: index.to_string(),
+                                    version: 2,
+                                    locktime: 0,
+                                    vin: Vec::new(),
+                                    vout: Vec::new(),
+                                    size: None,
+                                    weight: None,
+                                    fee: None,
+                                    status: None,
+                                },
+                                change_index: None,
+                                total_amount: index,
+                            },
+                        )
+                    })
+                    .unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let journal = BroadcastJournal::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        // no update was lost
+        for index in 0..8u64 {
+            assert_eq!(journal.get(&index.to_string()).unwrap().total_amount, index);
+        }
+    }
+}
@@ -1,4 +1,18 @@
+pub mod address_details;
+pub mod balance;
+pub mod cpfp;
+pub mod details;
 pub mod error;
+pub mod export;
+pub mod fee_adjustment;
+pub mod journal;
+pub mod payjoin;
+pub mod payment_uri;
+pub mod preview;
+pub mod psbt;
+pub mod rbf;
 pub mod script;
+pub mod send_max;
+pub mod send_request;
 pub mod transaction;
 pub mod utxo;
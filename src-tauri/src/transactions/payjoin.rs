@@ -0,0 +1,373 @@
+//! Implements the sender of a payjoin as specified in
+//! [BIP-78](https://github.com/bitcoin/bips/blob/master/bip-0078.mediawiki).
+//! The signed original transaction is sent to the receiver, which adds its own inputs
+//! and returns a proposal. The proposal is checked against the original transaction,
+//! so the receiver can neither take more than the requested amount nor make the wallet
+//! pay more than the fee of the inputs that the receiver added. Output substitution
+//! is always disabled, so the payment cannot be redirected.
+use super::error::PayjoinError;
+use super::psbt::Psbt;
+use super::script::ScriptType;
+use super::transaction::{Transaction, TransactionOutput};
+use super::utxo::UTXOBox;
+use crate::coin_selection::fee_estimation;
+use crate::keys::bip44::MasterPrivateKey;
+use serde::{Deserialize, Serialize};
+
+static VERSION: u32 = 1;
+
+/// The payment of a payment URI. If the payjoin failed, the original transaction was
+/// sent instead and the error tells why.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PaymentResult {
+    pub total_amount: u64,
+    pub payjoin_error: Option<String>, // the reason why the payjoin was not sent
+}
+
+/// The original transaction of a payjoin and the parameters that limit the proposal.
+pub struct PayjoinSender {
+    original: Psbt, // unsigned, with the key sources of the inputs
+    original_fee: u64,
+    payee_output: usize,
+    fee_output: Option<usize>, // the output that pays the additional fee, i.e., the change
+    input_fee: u64,            // the fee of an input of the type of the wallet
+    fee_rate: u64,             // in sat/vB
+}
+
+impl PayjoinSender {
+    /// Creates the sender for the transaction that spends the UTXOs in the order of its
+    /// inputs. The payee output pays the receiver. Returns an error if an input is not
+    /// a SegWit input, because the receiver cannot add inputs of the same type otherwise.
+    pub fn new(
+        tx: &Transaction,
+        boxed_utxos: &[UTXOBox],
+        master_private_key: &MasterPrivateKey,
+        payee_output: usize,
+        fee_output: Option<usize>,
+        fee_rate: u64,
+    ) -> Result<PayjoinSender, PayjoinError> {
+        let script_types = fee_estimation::utxo_script_types(boxed_utxos);
+        if boxed_utxos.len() != tx.vin.len() || script_types.contains(&ScriptType::P2PKH) {
+            return Err(PayjoinError::new("payjoin_unsupported_error"));
+        }
+        if payee_output >= tx.vout.len() || fee_output.is_some_and(|index| index >= tx.vout.len()) {
+            return Err(PayjoinError::new("payjoin_unsupported_error"));
+        }
+        let input_value: u64 = boxed_utxos.iter().map(|utxo| utxo.utxo.value).sum();
+        let output_value: u64 = tx.vout.iter().map(|tx_out| tx_out.value).sum();
+        Ok(PayjoinSender {
+            original: Psbt::create(tx, boxed_utxos, master_private_key),
+            original_fee: input_value.saturating_sub(output_value),
+            payee_output,
+            fee_output,
+            input_fee: fee_estimation::input_vsize(&script_types[0]) as u64 * fee_rate,
+            fee_rate,
+        })
+    }
+
+    /// Returns the maximum fee that is deducted from the fee output, i.e., the fee of
+    /// one additional input of the type of the wallet.
+    fn max_additional_fee(&self) -> u64 {
+        match self.fee_output {
+            Some(_) => self.input_fee,
+            None => 0,
+        }
+    }
+
+    /// Returns the signed and finalized original PSBT in Base64, which is sent to the receiver.
+    pub fn original_psbt(
+        &self,
+        master_private_key: &MasterPrivateKey,
+    ) -> Result<String, PayjoinError> {
+        let mut psbt = self.original.clone();
+        let signed = psbt
+            .sign(master_private_key)
+            .and_then(|_| psbt.finalize())
+            .is_ok();
+        if !signed {
+            return Err(PayjoinError::new("payjoin_unsupported_error"));
+        }
+        Ok(psbt.to_base64())
+    }
+
+    /// Returns the query parameters of the request to the payjoin endpoint.
+    pub fn query(&self) -> String {
+        let mut params = vec![format!("v={}", VERSION)];
+        if let Some(fee_output) = self.fee_output {
+            params.push(format!("additionalfeeoutputindex={}", fee_output));
+            params.push(format!(
+                "maxadditionalfeecontribution={}",
+                self.max_additional_fee()
+            ));
+        }
+        params.push("disableoutputsubstitution=true".to_string());
+        params.push(format!("minfeerate={}", self.fee_rate));
+        params.join("&")
+    }
+
+    /// Checks the proposal of the receiver, signs the inputs of the wallet and returns
+    /// the payjoin transaction. Returns an error if the proposal is invalid, in which
+    /// case the original transaction should be broadcast instead.
+    pub fn process_proposal(
+        &self,
+        proposal: &str,
+        testnet: bool,
+        master_private_key: &MasterPrivateKey,
+    ) -> Result<Transaction, PayjoinError> {
+        let invalid = || PayjoinError::new("invalid_payjoin_proposal_error");
+        let mut psbt = Psbt::from_base64(proposal, testnet).map_err(|_| invalid())?;
+        let original_tx = &self.original.unsigned_tx;
+        let tx = psbt.unsigned_tx.clone();
+        if tx.version != original_tx.version || tx.locktime != original_tx.locktime {
+            return Err(invalid());
+        }
+        let mut found = vec![false; original_tx.vin.len()];
+        let mut added_inputs = 0;
+        for (index, tx_in) in tx.vin.iter().enumerate() {
+            let original_index = original_tx
+                .vin
+                .iter()
+                .position(|original| original.txid == tx_in.txid && original.vout == tx_in.vout);
+            let input = &mut psbt.inputs[index];
+            match original_index {
+                Some(original_index) => {
+                    // the receiver must remove the signatures of the wallet
+                    if found[original_index]
+                        || input.is_finalized()
+                        || tx_in.sequence != original_tx.vin[original_index].sequence
+                    {
+                        return Err(invalid());
+                    }
+                    found[original_index] = true;
+                    // the spent outputs and key sources are taken from the original
+                    *input = self.original.inputs[original_index].clone();
+                }
+                None => {
+                    // the inputs of the receiver have to be signed and of the same kind
+                    if !input.is_finalized()
+                        || input.witness_utxo.is_none()
+                        || tx_in.sequence != original_tx.vin[0].sequence
+                    {
+                        return Err(invalid());
+                    }
+                    // the wallet must never sign an input of the receiver
+                    input.bip32_derivation.clear();
+                    input.tap_bip32_derivation.clear();
+                    added_inputs += 1;
+                }
+            }
+        }
+        if found.contains(&false) || added_inputs == 0 {
+            return Err(invalid());
+        }
+
+        let mut contribution = 0;
+        for (index, original_output) in original_tx.vout.iter().enumerate() {
+            let output = match tx
+                .vout
+                .iter()
+                .find(|tx_out| script_pubkey(tx_out) == script_pubkey(original_output))
+            {
+                Some(output) => output,
+                None => return Err(invalid()),
+            };
+            if index == self.payee_output {
+                // the receiver may add the value of its inputs to its output
+                if output.value < original_output.value {
+                    return Err(invalid());
+                }
+            } else if Some(index) == self.fee_output {
+                if output.value > original_output.value {
+                    return Err(invalid());
+                }
+                contribution = original_output.value - output.value;
+            } else if output.value != original_output.value {
+                return Err(invalid());
+            }
+        }
+        if contribution > self.max_additional_fee() {
+            return Err(invalid());
+        }
+
+        psbt.sign(master_private_key).map_err(|_| invalid())?;
+        psbt.finalize().map_err(|_| invalid())?;
+        let payjoin_tx = psbt.extract_transaction().map_err(|_| invalid())?;
+        let fee = payjoin_tx.fee.unwrap();
+        // the contribution of the wallet only pays for the inputs of the receiver
+        if fee < self.original_fee + contribution || contribution > self.input_fee * added_inputs {
+            return Err(invalid());
+        }
+        // the signatures vary in size, so the fee rate may fall short by a byte per input
+        let tolerance = payjoin_tx.vin.len() as u64 * self.fee_rate;
+        if fee + tolerance < payjoin_tx.vsize() as u64 * self.fee_rate {
+            return Err(invalid());
+        }
+        Ok(payjoin_tx)
+    }
+}
+
+/// Returns the scriptPubKey of the output with its length prefix.
+fn script_pubkey(tx_out: &TransactionOutput) -> Vec<u8> {
+    tx_out.serialize()[8..].to_vec() // without the value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PayjoinSender;
+    use crate::keys::address::Address;
+    use crate::keys::bip32::ExtendedPrivateKey;
+    use crate::keys::bip44::{private_hierarchy::MasterPrivateKey, BITCOIN_INDEX};
+    use crate::transactions::psbt::{Psbt, PsbtInput};
+    use crate::transactions::script;
+    use crate::transactions::transaction::{Transaction, TransactionInput, TransactionOutput};
+    use crate::transactions::utxo::{UTXOBox, UTXOStatus, UTXO};
+    use std::str::FromStr;
+
+    static PAYEE: &'static str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn master_private_key() -> MasterPrivateKey {
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        let account = master_private_key.add_account(BITCOIN_INDEX);
+        master_private_key.new_receive_keypair(BITCOIN_INDEX, account.index, None);
+        master_private_key.new_change_keypair(BITCOIN_INDEX, account.index, None);
+        master_private_key
+    }
+
+    fn p2wpkh_output(hash: &str, value: u64) -> TransactionOutput {
+        TransactionOutput {
+            scriptpubkey: String::from(""),
+            scriptpubkey_asm: script::p2wpkh_script_pub_key(hash),
+            scriptpubkey_type: String::from("v0_p2wpkh"),
+            scriptpubkey_address: String::from(""),
+            value,
+        }
+    }
+
+    /// Returns a transaction that spends 100000 sat of the wallet, pays 50000 sat
+    /// to the payee and 49700 sat to the change, i.e., a fee of 2 sat/vB.
+    fn sender(master_private_key: &MasterPrivateKey) -> (PayjoinSender, Transaction) {
+        let keypair = master_private_key.get_all_receive_keypairs(BITCOIN_INDEX, 0)[0].clone();
+        let change_keypair =
+            master_private_key.get_all_change_keypairs(BITCOIN_INDEX, 0)[0].clone();
+        let output = p2wpkh_output(&keypair.public_key.get_address().get_hash(), 100000);
+        let boxed_utxos = vec![UTXOBox {
+            utxo: UTXO {
+                txid: String::from(
+                    "0dd7697dfc7f13978dcf44e7d82c0be4591dec423616510a46cdb6cfeddae02d",
+                ),
+                vout: 3,
+                value: output.value,
+                status: UTXOStatus {
+                    confirmed: true,
+                    block_height: None,
+                    block_hash: None,
+                    block_time: None,
+                },
+            },
+            output,
+            keypair,
+        }];
+        let targets = vec![
+            (Address::from_str(PAYEE).unwrap(), 50000),
+            (change_keypair.public_key.get_address(), 49700),
+        ];
+        let mut tx = Transaction::create(&boxed_utxos, targets).unwrap();
+        tx.signal_rbf();
+        let sender =
+            PayjoinSender::new(&tx, &boxed_utxos, master_private_key, 0, Some(1), 2).unwrap();
+        (sender, tx)
+    }
+
+    /// Returns the proposal of a receiver that adds an input of 30000 sat to the payee
+    /// output and deducts the contribution from the change. The proposal is modified
+    /// by the closure.
+    fn proposal<F: FnOnce(&mut Psbt)>(original: &str, contribution: u64, modify: F) -> String {
+        let mut psbt = Psbt::from_base64(original, false).unwrap();
+        let sequence = psbt.unsigned_tx.vin[0].sequence;
+        psbt.inputs[0] = PsbtInput::default();
+        psbt.unsigned_tx.vin.push(TransactionInput {
+            txid: String::from("d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a"),
+            vout: 0,
+            prevout: TransactionOutput::default(),
+            scriptsig: String::from(""),
+            scriptsig_asm: String::from(""),
+            witness: None,
+            is_coinbase: false,
+            sequence,
+        });
+        psbt.inputs.push(PsbtInput {
+            witness_utxo: Some(p2wpkh_output(&"07".repeat(20), 30000)),
+            final_script_witness: Some(vec![vec![1; 72], vec![2; 33]]),
+            ..PsbtInput::default()
+        });
+        psbt.unsigned_tx.vout[0].value += 30000;
+        psbt.unsigned_tx.vout[1].value -= contribution;
+        modify(&mut psbt);
+        psbt.to_base64()
+    }
+
+    #[test]
+    fn test_payjoin_sender() {
+        let master_private_key = master_private_key();
+        let (sender, tx) = sender(&master_private_key);
+        assert_eq!(
+            sender.query(),
+            "v=1&additionalfeeoutputindex=1&maxadditionalfeecontribution=136&disableoutputsubstitution=true&minfeerate=2"
+        );
+        let original = sender.original_psbt(&master_private_key).unwrap();
+        let original_tx = Psbt::from_base64(&original, false)
+            .unwrap()
+            .extract_transaction()
+            .unwrap();
+        assert_eq!(original_tx.fee, Some(300));
+
+        let payjoin_tx = sender
+            .process_proposal(
+                &proposal(&original, 136, |_| {}),
+                false,
+                &master_private_key,
+            )
+            .unwrap();
+        assert_eq!(payjoin_tx.vin.len(), 2);
+        assert!(payjoin_tx.vin[0].witness.is_some());
+        assert_eq!(payjoin_tx.vout[0].value, 80000);
+        assert_eq!(payjoin_tx.fee, Some(436));
+        assert_eq!(payjoin_tx.vin[0].sequence, tx.vin[0].sequence);
+
+        // the wallet pays at most for one input of the receiver
+        assert!(sender
+            .process_proposal(
+                &proposal(&original, 137, |_| {}),
+                false,
+                &master_private_key
+            )
+            .is_err());
+        // the payee output must not decrease
+        let decreased = proposal(&original, 0, |psbt| psbt.unsigned_tx.vout[0].value = 49000);
+        assert!(sender
+            .process_proposal(&decreased, false, &master_private_key)
+            .is_err());
+        // the inputs of the receiver have to be signed
+        let unsigned = proposal(&original, 0, |psbt| {
+            psbt.inputs[1].final_script_witness = None
+        });
+        assert!(sender
+            .process_proposal(&unsigned, false, &master_private_key)
+            .is_err());
+        // the inputs of the wallet have to be kept
+        let removed = proposal(&original, 0, |psbt| {
+            psbt.unsigned_tx.vin.remove(0);
+            psbt.inputs.remove(0);
+        });
+        assert!(sender
+            .process_proposal(&removed, false, &master_private_key)
+            .is_err());
+        // the original transaction is not a payjoin
+        assert!(sender
+            .process_proposal(&original, false, &master_private_key)
+            .is_err());
+    }
+}
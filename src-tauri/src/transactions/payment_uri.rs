@@ -0,0 +1,322 @@
+//! Implements payment URIs as described in
+//! [BIP-21](https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki),
+//! e.g., `bitcoin:bc1q...?amount=0.001&label=Alice`. The URIs are usually scanned
+//! from QR codes and prefill the send form. The payment requests of the wallet
+//! are shown as URIs, too.
+use super::error::PaymentUriError;
+use crate::keys::address::Address;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+static SCHEME: &'static str = "bitcoin:";
+// parameters with this prefix are required, so a URI with an unknown one is rejected
+static REQUIRED_PREFIX: &'static str = "req-";
+static SATOSHIS_PER_BITCOIN: u64 = 100000000;
+static MAX_DECIMALS: usize = 8;
+static MAX_AMOUNT: u64 = 21000000 * 100000000; // in satoshis
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PaymentUri {
+    pub address: String,
+    pub amount: Option<u64>, // in satoshis
+    pub label: Option<String>,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub payjoin_endpoint: Option<String>, // the URL of the receiver for a payjoin (BIP-78)
+}
+
+impl PaymentUri {
+    /// Parses a payment URI. The scheme is case-insensitive and the address is returned
+    /// in its canonical form, e.g., in lowercase if it is a Bech32 address in uppercase,
+    /// which makes QR codes smaller.
+    pub fn parse(uri: &str) -> Result<(PaymentUri, Address), PaymentUriError> {
+        let uri = uri.trim();
+        if uri.len() < SCHEME.len() || !uri[..SCHEME.len()].eq_ignore_ascii_case(SCHEME) {
+            return Err(PaymentUriError::new("invalid_payment_uri_error"));
+        }
+        let (address, query) = match uri[SCHEME.len()..].split_once('?') {
+            Some((address, query)) => (address, Some(query)),
+            None => (&uri[SCHEME.len()..], None),
+        };
+        let parsed_address = match Address::from_str(address) {
+            Ok(address) => address,
+            Err(_) => return Err(PaymentUriError::new("invalid_address_error")),
+        };
+        let mut payment_uri = PaymentUri {
+            address: parsed_address.to_string(),
+            amount: None,
+            label: None,
+            message: None,
+            payjoin_endpoint: None,
+        };
+        let params = query
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+            .filter(|param| !param.is_empty());
+        for param in params {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = match percent_decode(value) {
+                Some(value) => value,
+                None => return Err(PaymentUriError::new("invalid_payment_uri_error")),
+            };
+            match key {
+                "amount" => match parse_amount(&value) {
+                    Some(amount) => payment_uri.amount = Some(amount),
+                    None => return Err(PaymentUriError::new("invalid_payment_uri_error")),
+                },
+                "label" => payment_uri.label = Some(value),
+                "message" => payment_uri.message = Some(value),
+                // an invalid endpoint is ignored, so the payment is sent without a payjoin
+                "pj" if is_payjoin_endpoint(&value) => payment_uri.payjoin_endpoint = Some(value),
+                key if key.starts_with(REQUIRED_PREFIX) => {
+                    return Err(PaymentUriError::new("unsupported_payment_uri_error"))
+                }
+                _ => {}
+            }
+        }
+        Ok((payment_uri, parsed_address))
+    }
+
+    /// Returns the payment URI as a string. The parameters are percent-encoded.
+    pub fn to_uri(&self) -> String {
+        format!("{}{}{}", SCHEME, self.address, self.query())
+    }
+
+    /// Returns the payload of a QR code for the payment URI. The scheme and a Bech32
+    /// address are in uppercase, so they can be encoded in the alphanumeric mode,
+    /// which makes the QR code smaller.
+    pub fn to_qr_payload(&self) -> String {
+        let address = match Address::from_str(&self.address) {
+            Ok(address) if address.is_segwit() => self.address.to_uppercase(),
+            _ => self.address.clone(),
+        };
+        format!("{}{}{}", SCHEME.to_uppercase(), address, self.query())
+    }
+
+    /// Returns the query of the URI, including the question mark, or an empty string
+    /// if there are no parameters.
+    fn query(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            params.push(format!("amount={}", format_amount(amount)));
+        }
+        if let Some(label) = &self.label {
+            params.push(format!("label={}", percent_encode(label)));
+        }
+        if let Some(message) = &self.message {
+            params.push(format!("message={}", percent_encode(message)));
+        }
+        if let Some(endpoint) = &self.payjoin_endpoint {
+            params.push(format!("pj={}", percent_encode(endpoint)));
+        }
+        if params.is_empty() {
+            return String::new();
+        }
+        format!("?{}", params.join("&"))
+    }
+}
+
+/// Parses an amount in bitcoin with at most 8 decimal places and returns it in satoshis.
+/// Floating-point numbers are not used, so the amount is exact.
+fn parse_amount(s: &str) -> Option<u64> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() && fraction.is_empty() || fraction.len() > MAX_DECIMALS {
+        return None;
+    }
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if !is_digits(whole) || !is_digits(fraction) {
+        return None;
+    }
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let fraction: u64 = format!("{:0<8}", fraction).parse().ok()?;
+    let amount = whole
+        .checked_mul(SATOSHIS_PER_BITCOIN)?
+        .checked_add(fraction)?;
+    if amount > MAX_AMOUNT {
+        return None;
+    }
+    Some(amount)
+}
+
+/// Returns true if the URL can be used as a payjoin endpoint. BIP-78 requires HTTPS,
+/// unless the endpoint is an onion service.
+fn is_payjoin_endpoint(url: &str) -> bool {
+    match reqwest::Url::parse(url) {
+        Ok(url) => match url.scheme() {
+            "https" => url.host_str().is_some(),
+            "http" => url.host_str().is_some_and(|host| host.ends_with(".onion")),
+            _ => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Formats an amount in satoshis in bitcoin without trailing zeros, e.g., "20.3".
+fn format_amount(amount: u64) -> String {
+    let whole = amount / SATOSHIS_PER_BITCOIN;
+    let fraction = amount % SATOSHIS_PER_BITCOIN;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let fraction = format!("{:0>8}", fraction);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Encodes all characters of a parameter value except the unreserved characters.
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Decodes the percent-encoded characters of a parameter value.
+/// Returns `None` if an encoding is malformed or the result is not valid UTF-8.
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_amount, parse_amount, PaymentUri};
+
+    #[test]
+    fn test_parse_payment_uri() {
+        let (payment_uri, address) = PaymentUri::parse(
+            "bitcoin:1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD?amount=20.3&label=Luke-Jr&message=Donation%20for%20project%20xyz",
+        )
+        .unwrap();
+        assert_eq!(payment_uri.address, "1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD");
+        assert_eq!(payment_uri.amount, Some(2030000000));
+        assert_eq!(payment_uri.label, Some("Luke-Jr".to_string()));
+        assert_eq!(
+            payment_uri.message,
+            Some("Donation for project xyz".to_string())
+        );
+        assert!(!address.testnet);
+
+        // the scheme is case-insensitive and Bech32 addresses can be in uppercase
+        let (payment_uri, address) = PaymentUri::parse(
+            "BITCOIN:TB1QRP33G0Q5C5TXSP9ARYSRX4K6ZDKFS4NCE4XJ0GDCCCEFVPYSXF3Q0SL5K7",
+        )
+        .unwrap();
+        assert_eq!(
+            payment_uri.address,
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7"
+        );
+        assert_eq!(payment_uri.amount, None);
+        assert!(address.testnet);
+
+        // unknown parameters are ignored unless they are required
+        assert!(PaymentUri::parse(
+            "bitcoin:1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD?somethingyoudontunderstand=50"
+        )
+        .is_ok());
+        let err = PaymentUri::parse(
+            "bitcoin:1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD?req-somethingyoudontunderstand=50",
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "unsupported_payment_uri_error");
+
+        // the payjoin endpoint has to use HTTPS
+        let (payment_uri, _) = PaymentUri::parse(
+            "bitcoin:1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD?amount=0.01&pj=https://example.com/pj%3Fid%3D1",
+        )
+        .unwrap();
+        assert_eq!(
+            payment_uri.payjoin_endpoint,
+            Some("https://example.com/pj?id=1".to_string())
+        );
+        assert_eq!(
+            PaymentUri::parse(&payment_uri.to_uri()).unwrap().0,
+            payment_uri
+        );
+        let (payment_uri, _) =
+            PaymentUri::parse("bitcoin:1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD?pj=http://example.com/pj")
+                .unwrap();
+        assert_eq!(payment_uri.payjoin_endpoint, None);
+
+        assert!(PaymentUri::parse("1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD").is_err());
+        assert!(PaymentUri::parse("bitcoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245X").is_err());
+        assert!(PaymentUri::parse("bitcoin:1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD?amount=1e3").is_err());
+        assert!(PaymentUri::parse("bitcoin:1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD?label=%zz").is_err());
+    }
+
+    #[test]
+    fn test_payment_uri_to_uri() {
+        let payment_uri = PaymentUri {
+            address: "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7".to_string(),
+            amount: Some(2030000000),
+            label: Some("Rent & utilities".to_string()),
+            message: None,
+            payjoin_endpoint: None,
+        };
+        let uri = payment_uri.to_uri();
+        assert_eq!(
+            uri,
+            "bitcoin:tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7?amount=20.3&label=Rent%20%26%20utilities"
+        );
+        assert_eq!(PaymentUri::parse(&uri).unwrap().0, payment_uri);
+        let qr_payload = payment_uri.to_qr_payload();
+        assert!(qr_payload.starts_with(
+            "BITCOIN:TB1QRP33G0Q5C5TXSP9ARYSRX4K6ZDKFS4NCE4XJ0GDCCCEFVPYSXF3Q0SL5K7?"
+        ));
+        assert_eq!(PaymentUri::parse(&qr_payload).unwrap().0, payment_uri);
+
+        // Base58 addresses are case-sensitive
+        let payment_uri = PaymentUri {
+            address: "1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD".to_string(),
+            amount: None,
+            label: None,
+            message: None,
+            payjoin_endpoint: None,
+        };
+        assert_eq!(
+            payment_uri.to_qr_payload(),
+            "BITCOIN:1vFgGCtnBLEobbQMEbz13Vw6RF64H2SYD"
+        );
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(100000000), "1");
+        assert_eq!(format_amount(1), "0.00000001");
+        assert_eq!(format_amount(2030000000), "20.3");
+        assert_eq!(format_amount(0), "0");
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("1"), Some(100000000));
+        assert_eq!(parse_amount("0.00000001"), Some(1));
+        assert_eq!(parse_amount(".5"), Some(50000000));
+        assert_eq!(parse_amount("21000000"), Some(2100000000000000));
+        assert_eq!(parse_amount("21000000.00000001"), None);
+        assert_eq!(parse_amount("0.000000001"), None);
+        assert_eq!(parse_amount("-1"), None);
+        assert_eq!(parse_amount("."), None);
+        assert_eq!(parse_amount(""), None);
+    }
+}
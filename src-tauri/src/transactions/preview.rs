@@ -0,0 +1,147 @@
+//! Implements the preview of a signed transaction that has not been broadcast yet.
+//! The preview contains the actual size and fee of the transaction, so they can be
+//! confirmed before the transaction is broadcast.
+use super::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PreviewInput {
+    pub txid: String,
+    pub vout: u32,
+    pub address: String,
+    pub value: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PreviewOutput {
+    pub address: Option<String>, // None for outputs without an address, e.g., OP_RETURN
+    pub value: u64,
+    pub change: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransactionPreview {
+    pub id: u64,
+    pub txid: String,
+    pub inputs: Vec<PreviewInput>,
+    pub outputs: Vec<PreviewOutput>,
+    pub size: u32,
+    pub vsize: u32,
+    pub fee: u64,
+    pub fee_rate: f64, // fee per vbyte
+    pub change: Option<u64>,
+}
+
+impl TransactionPreview {
+    /// Creates the preview of a signed transaction. The inputs must contain the spent outputs.
+    /// The output that pays to the change address is marked as change.
+    pub fn create(id: u64, tx: &Transaction, change_address: Option<&str>) -> TransactionPreview {
+        let inputs: Vec<PreviewInput> = tx
+            .vin
+            .iter()
+            .map(|tx_in| PreviewInput {
+                txid: tx_in.txid.clone(),
+                vout: tx_in.vout,
+                address: tx_in.prevout.scriptpubkey_address.clone(),
+                value: tx_in.prevout.value,
+            })
+            .collect();
+        let outputs: Vec<PreviewOutput> = tx
+            .vout
+            .iter()
+            .map(|tx_out| PreviewOutput {
+                address: match tx_out.scriptpubkey_address.as_str() {
+                    "" => None,
+                    address => Some(address.to_string()),
+                },
+                value: tx_out.value,
+                change: change_address == Some(tx_out.scriptpubkey_address.as_str()),
+            })
+            .collect();
+        let input_value: u64 = inputs.iter().map(|input| input.value).sum();
+        let output_value: u64 = outputs.iter().map(|output| output.value).sum();
+        let fee = input_value.saturating_sub(output_value);
+        let vsize = tx.vsize();
+        let change = outputs
+            .iter()
+            .find(|output| output.change)
+            .map(|output| output.value);
+        TransactionPreview {
+            id,
+            txid: tx.compute_txid(),
+            inputs,
+            outputs,
+            size: tx.serialize().len() as u32,
+            vsize,
+            fee,
+            fee_rate: fee as f64 / vsize as f64,
+            change,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TransactionPreview;
+    use crate::test_utils::keypair;
+    use crate::transactions::{
+        transaction::{Transaction, TransactionOutput},
+        utxo::{UTXOBox, UTXOStatus, UTXO},
+    };
+
+    #[test]
+    fn test_create_preview() {
+        let keypair = keypair(1);
+        let address = keypair.public_key.get_address();
+        let boxed_utxos = vec![UTXOBox {
+            utxo: UTXO {
+                txid: String::from(
+                    "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+                ),
+                vout: 1,
+                value: 100000,
+                status: UTXOStatus {
+                    confirmed: true,
+                    block_height: None,
+                    block_hash: None,
+                    block_time: None,
+                },
+            },
+            output: TransactionOutput::create(&address, 100000),
+            keypair: keypair.clone(),
+        }];
+        let change_address = keypair.public_key.get_address();
+        let target_address = self::keypair(2).public_key.get_address();
+        let targets = vec![
+            (target_address.clone(), 60000),
+            (change_address.clone(), 38000),
+        ];
+        let mut tx = Transaction::create(&boxed_utxos, targets).unwrap();
+        tx.vout
+            .push(TransactionOutput::create_op_return(&[1, 2, 3]).unwrap());
+        tx.sign_all_inputs(&boxed_utxos);
+
+        let change_address = change_address.to_string();
+        let preview = TransactionPreview::create(7, &tx, Some(&change_address));
+        assert_eq!(preview.id, 7);
+        assert_eq!(preview.txid, tx.compute_txid());
+        assert_eq!(preview.inputs.len(), 1);
+        assert_eq!(preview.inputs[0].vout, 1);
+        assert_eq!(preview.inputs[0].value, 100000);
+        assert_eq!(preview.inputs[0].address, address.to_string());
+        assert_eq!(preview.outputs.len(), 3);
+        assert_eq!(preview.outputs[0].address, Some(target_address.to_string()));
+        assert!(!preview.outputs[0].change);
+        assert!(preview.outputs[1].change);
+        assert_eq!(preview.outputs[2].address, None);
+        assert_eq!(preview.change, Some(38000));
+        assert_eq!(preview.fee, 2000);
+        assert_eq!(preview.size, tx.serialize().len() as u32);
+        assert_eq!(preview.vsize, preview.size);
+        assert_eq!(preview.fee_rate, 2000.0 / preview.vsize as f64);
+
+        let preview = TransactionPreview::create(8, &tx, None);
+        assert!(preview.outputs.iter().all(|output| !output.change));
+        assert_eq!(preview.change, None);
+    }
+}
@@ -0,0 +1,1443 @@
+//! Implements partially signed Bitcoin transactions as specified in
+//! [BIP-174](https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki).
+//! A PSBT contains an unsigned transaction together with the information
+//! that a signer needs, so the transaction can be signed externally,
+//! e.g., by a hardware wallet or by the cosigner of a multisig wallet.
+use super::error::PsbtError;
+use super::script;
+use super::transaction::{SighashType, Transaction, TransactionInput, TransactionOutput};
+use super::utxo::{UTXOBox, UTXOStatus, UTXO};
+use crate::keys::address::Address;
+use crate::keys::bip32::{ChildIndex, DerivationPath};
+use crate::keys::bip44::multisig::MultisigAccount;
+use crate::keys::bip44::private_hierarchy::{Keypair, MasterPrivateKey};
+use crate::utils::{base64, hex, varint};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+static MAGIC: [u8; 5] = [0x70, 0x73, 0x62, 0x74, 0xff]; // "psbt" followed by 0xff
+static SEPARATOR: u8 = 0x00;
+static HARDENED_OFFSET: u32 = 0x80000000;
+
+static PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+
+static PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+static PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+static PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+static PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+static PSBT_IN_REDEEM_SCRIPT: u8 = 0x04;
+static PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+static PSBT_IN_BIP32_DERIVATION: u8 = 0x06;
+static PSBT_IN_FINAL_SCRIPTSIG: u8 = 0x07;
+static PSBT_IN_FINAL_SCRIPTWITNESS: u8 = 0x08;
+static PSBT_IN_TAP_KEY_SIG: u8 = 0x13;
+static PSBT_IN_TAP_BIP32_DERIVATION: u8 = 0x16;
+static PSBT_IN_TAP_INTERNAL_KEY: u8 = 0x17;
+
+static PSBT_OUT_REDEEM_SCRIPT: u8 = 0x00;
+static PSBT_OUT_WITNESS_SCRIPT: u8 = 0x01;
+static PSBT_OUT_BIP32_DERIVATION: u8 = 0x02;
+
+/// The key-value pairs of a map, in the order in which they are serialized.
+type KeyValuePairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+#[derive(Debug, Clone)]
+pub struct Psbt {
+    pub unsigned_tx: Transaction, // without script signatures and witnesses
+    pub inputs: Vec<PsbtInput>,
+    pub outputs: Vec<PsbtOutput>,
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>, // unknown global key-value pairs
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PsbtInput {
+    pub non_witness_utxo: Option<Transaction>, // the transaction that created the spent output
+    pub witness_utxo: Option<TransactionOutput>, // the spent output, for SegWit inputs only
+    pub partial_sigs: BTreeMap<Vec<u8>, Vec<u8>>, // public key -> signature
+    pub sighash_type: Option<u32>,
+    pub redeem_script: Option<Vec<u8>>,
+    pub witness_script: Option<Vec<u8>>,
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>, // public key -> key source
+    pub final_script_sig: Option<Vec<u8>>,
+    pub final_script_witness: Option<Vec<Vec<u8>>>,
+    pub tap_key_sig: Option<Vec<u8>>,
+    pub tap_bip32_derivation: BTreeMap<Vec<u8>, (Vec<Vec<u8>>, KeySource)>, // x-only public key -> (leaf hashes, key source)
+    pub tap_internal_key: Option<Vec<u8>>,
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PsbtOutput {
+    pub redeem_script: Option<Vec<u8>>,
+    pub witness_script: Option<Vec<u8>>,
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>, // public key -> key source
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+/// The fingerprint of the master key and the derivation path of a key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySource {
+    pub fingerprint: [u8; 4],
+    pub path: DerivationPath,
+}
+
+impl KeySource {
+    /// Returns the fingerprint followed by the little-endian child indices.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = self.fingerprint.to_vec();
+        for child_index in &self.path.indices {
+            let index = if child_index.hardened {
+                child_index.index + HARDENED_OFFSET
+            } else {
+                child_index.index
+            };
+            bytes.extend(&index.to_le_bytes()[..4]);
+        }
+        bytes
+    }
+
+    /// Parses a serialized key source.
+    pub fn from_bytes(bytes: &[u8]) -> Option<KeySource> {
+        if bytes.len() < 4 || bytes.len() % 4 != 0 {
+            return None;
+        }
+        let indices = bytes[4..]
+            .chunks(4)
+            .map(|chunk| {
+                let index = u32::from_le_bytes(chunk.try_into().unwrap());
+                ChildIndex {
+                    index: index % HARDENED_OFFSET,
+                    hardened: index >= HARDENED_OFFSET,
+                }
+            })
+            .collect();
+        Some(KeySource {
+            fingerprint: bytes[..4].try_into().unwrap(),
+            path: DerivationPath { indices },
+        })
+    }
+}
+
+impl Psbt {
+    /// Creates a PSBT for the unsigned transaction that spends the specified UTXOs in
+    /// the order of its inputs. The derivation paths of the keys are looked up in the
+    /// key hierarchy, such that a signer can derive the keys from the master key.
+    /// The previous transactions of legacy inputs have to be added with
+    /// `add_previous_transaction`.
+    pub fn create(
+        tx: &Transaction,
+        boxed_utxos: &[UTXOBox],
+        master_private_key: &MasterPrivateKey,
+    ) -> Psbt {
+        let fingerprint = master_private_key.private_key.get_fingerprint();
+        let key_source = |address: &Address| {
+            master_private_key
+                .find_keypair_with_path(address)
+                .map(|(keypair, path)| (keypair, KeySource { fingerprint, path }))
+        };
+        let mut unsigned_tx = tx.clone();
+        let mut inputs = Vec::new();
+        for (tx_in, utxo_box) in unsigned_tx.vin.iter_mut().zip(boxed_utxos.iter()) {
+            let mut input = PsbtInput::default();
+            let output = &utxo_box.output;
+            let public_key = utxo_box.keypair.public_key.key_data.to_vec();
+            let key_source = key_source(&utxo_box.keypair.public_key.get_address());
+            if output.scriptpubkey_type == script::P2TR_TYPE {
+                // the internal key is the public key without the parity byte
+                let internal_key = public_key[1..].to_vec();
+                if let Some((_, key_source)) = key_source {
+                    // the key is not used in any leaf script, so the list of leaf hashes is empty
+                    input
+                        .tap_bip32_derivation
+                        .insert(internal_key.clone(), (Vec::new(), key_source));
+                }
+                input.tap_internal_key = Some(internal_key);
+            } else if let Some((_, key_source)) = key_source {
+                input.bip32_derivation.insert(public_key, key_source);
+            }
+            if output.scriptpubkey_type == script::P2SH_TYPE {
+                // the script signature of a P2SH-P2WPKH input is the redeem script
+                input.redeem_script = hex::hex_to_bytes(&tx_in.scriptsig_asm);
+            }
+            if output.scriptpubkey_type != script::P2PKH_TYPE {
+                input.witness_utxo = Some(output.clone());
+            }
+            tx_in.scriptsig = String::from("");
+            tx_in.scriptsig_asm = String::from("");
+            tx_in.witness = None;
+            inputs.push(input);
+        }
+        let mut outputs = Vec::new();
+        for tx_out in &unsigned_tx.vout {
+            let mut output = PsbtOutput::default();
+            // outputs to the wallet, i.e., change outputs, are marked for the signer
+            if let Ok(address) = Address::from_str(&tx_out.scriptpubkey_address) {
+                if let Some((keypair, key_source)) = key_source(&address) {
+                    output
+                        .bip32_derivation
+                        .insert(keypair.public_key.key_data.to_vec(), key_source);
+                }
+            }
+            outputs.push(output);
+        }
+        Psbt {
+            unsigned_tx,
+            inputs,
+            outputs,
+            unknown: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a PSBT for the unsigned transaction that spends outputs of the multisig
+    /// account in the order of its inputs. The inputs contain the multisig scripts and
+    /// the key sources of all cosigners, such that each cosigner can sign with its key.
+    pub fn create_multisig(
+        tx: &Transaction,
+        boxed_utxos: &[UTXOBox],
+        account: &MultisigAccount,
+    ) -> Psbt {
+        let mut unsigned_tx = tx.clone();
+        let mut inputs = Vec::new();
+        for (tx_in, utxo_box) in unsigned_tx.vin.iter_mut().zip(boxed_utxos.iter()) {
+            let mut input = PsbtInput {
+                witness_utxo: Some(utxo_box.output.clone()),
+                ..PsbtInput::default()
+            };
+            let address = Address::from_str(&utxo_box.output.scriptpubkey_address);
+            if let Some((change, index)) = address
+                .ok()
+                .and_then(|address| account.find_address(&address))
+            {
+                input.witness_script = Some(account.witness_script(change, index));
+                input.redeem_script = account.redeem_script(change, index);
+                input.bip32_derivation = account.key_sources(change, index).into_iter().collect();
+            }
+            tx_in.scriptsig = String::from("");
+            tx_in.scriptsig_asm = String::from("");
+            tx_in.witness = None;
+            inputs.push(input);
+        }
+        let mut outputs = Vec::new();
+        for tx_out in &unsigned_tx.vout {
+            let mut output = PsbtOutput::default();
+            // the change is marked, so the cosigners can verify that it returns to the account
+            let address = Address::from_str(&tx_out.scriptpubkey_address);
+            if let Some((change, index)) = address
+                .ok()
+                .and_then(|address| account.find_address(&address))
+            {
+                output.witness_script = Some(account.witness_script(change, index));
+                output.redeem_script = account.redeem_script(change, index);
+                output.bip32_derivation = account.key_sources(change, index).into_iter().collect();
+            }
+            outputs.push(output);
+        }
+        Psbt {
+            unsigned_tx,
+            inputs,
+            outputs,
+            unknown: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the previous transaction to the inputs that spend one of its outputs.
+    /// Returns false if no input spends the transaction or if the transaction ID
+    /// of the serialized transaction does not match.
+    pub fn add_previous_transaction(&mut self, prev_tx: &Transaction) -> bool {
+        let txid = prev_tx.compute_txid();
+        if txid != prev_tx.txid {
+            return false;
+        }
+        let mut added = false;
+        for (tx_in, input) in self.unsigned_tx.vin.iter().zip(self.inputs.iter_mut()) {
+            if tx_in.txid == txid {
+                input.non_witness_utxo = Some(prev_tx.clone());
+                added = true;
+            }
+        }
+        added
+    }
+
+    /// Parses a serialized PSBT.
+    pub fn from_bytes(bytes: &[u8], testnet: bool) -> Result<Psbt, PsbtError> {
+        if !bytes.starts_with(&MAGIC) {
+            return Err(PsbtError::new("invalid_psbt_error"));
+        }
+        let mut position = MAGIC.len();
+        let mut unsigned_tx = None;
+        let mut unknown = BTreeMap::new();
+        for (key, value) in read_map(bytes, &mut position)? {
+            if key == [PSBT_GLOBAL_UNSIGNED_TX] {
+                let tx = match Transaction::from_bytes(&value, testnet) {
+                    Ok(tx) => tx,
+                    Err(_) => return Err(PsbtError::new("invalid_psbt_error")),
+                };
+                if tx
+                    .vin
+                    .iter()
+                    .any(|tx_in| !tx_in.scriptsig.is_empty() || tx_in.witness.is_some())
+                {
+                    return Err(PsbtError::new("invalid_psbt_error"));
+                }
+                unsigned_tx = Some(tx);
+            } else {
+                unknown.insert(key, value);
+            }
+        }
+        let unsigned_tx = match unsigned_tx {
+            Some(unsigned_tx) => unsigned_tx,
+            None => return Err(PsbtError::new("invalid_psbt_error")),
+        };
+        let mut inputs = Vec::new();
+        for _ in 0..unsigned_tx.vin.len() {
+            inputs.push(PsbtInput::parse(read_map(bytes, &mut position)?, testnet)?);
+        }
+        let mut outputs = Vec::new();
+        for _ in 0..unsigned_tx.vout.len() {
+            outputs.push(PsbtOutput::parse(read_map(bytes, &mut position)?)?);
+        }
+        if position != bytes.len() {
+            return Err(PsbtError::new("invalid_psbt_error"));
+        }
+        Ok(Psbt {
+            unsigned_tx,
+            inputs,
+            outputs,
+            unknown,
+        })
+    }
+
+    /// Parses a Base64-encoded PSBT.
+    pub fn from_base64(s: &str, testnet: bool) -> Result<Psbt, PsbtError> {
+        match base64::decode(s.trim()) {
+            Some(bytes) => Psbt::from_bytes(&bytes, testnet),
+            None => Err(PsbtError::new("invalid_psbt_error")),
+        }
+    }
+
+    /// Adds signatures for all inputs that spend from keys of the key hierarchy and
+    /// returns the number of added signatures. The keys are derived at the paths of
+    /// the BIP-32 derivation fields whose fingerprint matches the master key.
+    /// Inputs are signed with the signature hash type of the input, if it is specified.
+    pub fn sign(&mut self, master_private_key: &MasterPrivateKey) -> Result<usize, PsbtError> {
+        let fingerprint = master_private_key.private_key.get_fingerprint();
+        let mut tx = self.unsigned_tx.clone();
+        let mut spent_outputs = Vec::new();
+        for (tx_in, input) in tx.vin.iter_mut().zip(self.inputs.iter()) {
+            let spent_output = input.spent_output(tx_in);
+            if let Some(output) = &spent_output {
+                tx_in.prevout = output.clone();
+            }
+            spent_outputs.push(spent_output);
+        }
+        let mut num_signatures = 0;
+        for index in 0..tx.vin.len() {
+            let output = match &spent_outputs[index] {
+                Some(output) => output.clone(),
+                None => continue,
+            };
+            let input = &self.inputs[index];
+            let witness_script = input.witness_script.clone();
+            let redeem_script = input.redeem_script.clone();
+            let taproot = output.scriptpubkey_type == script::P2TR_TYPE;
+            // the Taproot signature hash commits to the outputs spent by all inputs
+            if taproot && spent_outputs.iter().any(|output| output.is_none()) {
+                continue;
+            }
+            // the input is signed with the requested signature hash type, SIGHASH_DEFAULT
+            // only exists for Taproot inputs
+            let sighash_type = match input.sighash_type.map(SighashType::from_u32) {
+                None => SighashType::Default,
+                Some(Some(SighashType::Default)) if !taproot => continue,
+                Some(Some(sighash_type)) => sighash_type,
+                Some(None) => continue,
+            };
+            let key_sources: Vec<(Vec<u8>, KeySource)> = if taproot {
+                input
+                    .tap_bip32_derivation
+                    .iter()
+                    .map(|(key, (_, key_source))| (key.clone(), key_source.clone()))
+                    .collect()
+            } else {
+                input
+                    .bip32_derivation
+                    .iter()
+                    .map(|(key, key_source)| (key.clone(), key_source.clone()))
+                    .collect()
+            };
+            for (public_key, key_source) in key_sources {
+                if key_source.fingerprint != fingerprint {
+                    continue;
+                }
+                let private_key = match master_private_key.private_key.derive_path(&key_source.path)
+                {
+                    Ok(private_key) => private_key,
+                    Err(_) => continue,
+                };
+                let keypair = Keypair {
+                    public_key: private_key.derive_public_key(),
+                    private_key,
+                };
+                let derived_key = if taproot {
+                    keypair.public_key.key_data[1..].to_vec()
+                } else {
+                    keypair.public_key.key_data.to_vec()
+                };
+                let locked = match &witness_script {
+                    Some(witness_script) => locks_output_with_script(
+                        &output,
+                        witness_script,
+                        redeem_script.as_deref(),
+                        &public_key,
+                    ),
+                    None => locks_output(&output, &keypair),
+                };
+                if derived_key != public_key || !locked {
+                    continue;
+                }
+                if let Some(witness_script) = &witness_script {
+                    let signature = match tx.create_witness_script_signature(
+                        index,
+                        witness_script,
+                        output.value,
+                        &keypair.private_key,
+                        sighash_type,
+                    ) {
+                        Ok(signature) => signature,
+                        Err(_) => continue,
+                    };
+                    self.inputs[index]
+                        .partial_sigs
+                        .insert(public_key, signature);
+                    num_signatures += 1;
+                    continue;
+                }
+                let utxo_box = UTXOBox {
+                    utxo: UTXO {
+                        txid: tx.vin[index].txid.clone(),
+                        vout: tx.vin[index].vout,
+                        value: output.value,
+                        status: UTXOStatus {
+                            confirmed: false,
+                            block_height: None,
+                            block_hash: None,
+                            block_time: None,
+                        },
+                    },
+                    output: output.clone(),
+                    keypair,
+                };
+                // the redeem script of a P2SH-P2WPKH input is part of the script code
+                if let Ok(tx_in) = TransactionInput::create(&utxo_box) {
+                    tx.vin[index].scriptsig_asm = tx_in.scriptsig_asm;
+                }
+                let signature = match tx.create_signature(index, &utxo_box, sighash_type) {
+                    Ok(signature) => signature,
+                    Err(_) => continue,
+                };
+                let input = &mut self.inputs[index];
+                if taproot {
+                    input.tap_key_sig = Some(signature);
+                } else {
+                    input.partial_sigs.insert(public_key, signature);
+                }
+                num_signatures += 1;
+            }
+        }
+        if num_signatures == 0 {
+            return Err(PsbtError::new("psbt_key_missing_error"));
+        }
+        Ok(num_signatures)
+    }
+
+    /// Merges a PSBT of the same transaction into this PSBT, e.g., the PSBT that was
+    /// signed by a cosigner. Fields that are already present are not overwritten.
+    pub fn merge(&mut self, other: &Psbt) -> Result<(), PsbtError> {
+        if self.unsigned_tx.compute_txid() != other.unsigned_tx.compute_txid() {
+            return Err(PsbtError::new("psbt_mismatch_error"));
+        }
+        for (input, other_input) in self.inputs.iter_mut().zip(other.inputs.iter()) {
+            input.merge(other_input);
+        }
+        for (output, other_output) in self.outputs.iter_mut().zip(other.outputs.iter()) {
+            output.merge(other_output);
+        }
+        merge_map(&mut self.unknown, &other.unknown);
+        Ok(())
+    }
+
+    /// Builds the final scriptSigs and witnesses of all inputs from their signatures.
+    /// The fields that are only needed for signing are removed from finalized inputs.
+    pub fn finalize(&mut self) -> Result<(), PsbtError> {
+        for (tx_in, input) in self.unsigned_tx.vin.iter().zip(self.inputs.iter_mut()) {
+            if input.is_finalized() {
+                continue;
+            }
+            match input.spent_output(tx_in) {
+                Some(output) => input.finalize(&output)?,
+                None => return Err(PsbtError::new("psbt_incomplete_error")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the signed network transaction of a finalized PSBT.
+    pub fn extract_transaction(&self) -> Result<Transaction, PsbtError> {
+        let mut tx = self.unsigned_tx.clone();
+        let mut input_value = 0;
+        for (tx_in, input) in tx.vin.iter_mut().zip(self.inputs.iter()) {
+            let output = match input.spent_output(tx_in) {
+                Some(output) if input.is_finalized() => output,
+                _ => return Err(PsbtError::new("psbt_incomplete_error")),
+            };
+            input_value += output.value;
+            tx_in.prevout = output;
+            if let Some(script_sig) = &input.final_script_sig {
+                tx_in.scriptsig = hex::bytes_to_hex(script_sig);
+                tx_in.scriptsig_asm = script::deserialize(script_sig).unwrap_or_default();
+            }
+            if let Some(witness) = &input.final_script_witness {
+                tx_in.witness = Some(witness.iter().map(|item| hex::bytes_to_hex(item)).collect());
+            }
+        }
+        let output_value: u64 = tx.vout.iter().map(|tx_out| tx_out.value).sum();
+        if output_value > input_value {
+            return Err(PsbtError::new("invalid_psbt_error"));
+        }
+        tx.fee = Some(input_value - output_value);
+        tx.txid = tx.compute_txid();
+        Ok(tx)
+    }
+
+    /// Returns the serialized PSBT.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        write_pair(
+            &mut bytes,
+            &[PSBT_GLOBAL_UNSIGNED_TX],
+            &self.unsigned_tx.serialize_without_witness(),
+        );
+        write_unknown(&mut bytes, &self.unknown);
+        bytes.push(SEPARATOR);
+        for input in &self.inputs {
+            bytes.extend(input.serialize());
+        }
+        for output in &self.outputs {
+            bytes.extend(output.serialize());
+        }
+        bytes
+    }
+
+    /// Returns the serialized PSBT in Base64, the format that is used to exchange PSBTs.
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.serialize())
+    }
+}
+
+impl PsbtInput {
+    /// Parses the key-value pairs of an input map.
+    fn parse(pairs: KeyValuePairs, testnet: bool) -> Result<PsbtInput, PsbtError> {
+        let invalid = || PsbtError::new("invalid_psbt_error");
+        let mut input = PsbtInput::default();
+        for (key, value) in pairs {
+            let key_type = key[0];
+            let key_data = &key[1..];
+            if key_type == PSBT_IN_NON_WITNESS_UTXO && key_data.is_empty() {
+                let tx = Transaction::from_bytes(&value, testnet).map_err(|_| invalid())?;
+                input.non_witness_utxo = Some(tx);
+            } else if key_type == PSBT_IN_WITNESS_UTXO && key_data.is_empty() {
+                let output =
+                    TransactionOutput::from_bytes(&value, testnet).map_err(|_| invalid())?;
+                input.witness_utxo = Some(output);
+            } else if key_type == PSBT_IN_PARTIAL_SIG {
+                if key_data.len() != 33 && key_data.len() != 65 {
+                    return Err(invalid());
+                }
+                input.partial_sigs.insert(key_data.to_vec(), value);
+            } else if key_type == PSBT_IN_SIGHASH_TYPE && key_data.is_empty() {
+                let bytes: [u8; 4] = value.try_into().map_err(|_| invalid())?;
+                input.sighash_type = Some(u32::from_le_bytes(bytes));
+            } else if key_type == PSBT_IN_REDEEM_SCRIPT && key_data.is_empty() {
+                input.redeem_script = Some(value);
+            } else if key_type == PSBT_IN_WITNESS_SCRIPT && key_data.is_empty() {
+                input.witness_script = Some(value);
+            } else if key_type == PSBT_IN_BIP32_DERIVATION {
+                if key_data.len() != 33 {
+                    return Err(invalid());
+                }
+                let key_source = KeySource::from_bytes(&value).ok_or_else(invalid)?;
+                input.bip32_derivation.insert(key_data.to_vec(), key_source);
+            } else if key_type == PSBT_IN_FINAL_SCRIPTSIG && key_data.is_empty() {
+                input.final_script_sig = Some(value);
+            } else if key_type == PSBT_IN_FINAL_SCRIPTWITNESS && key_data.is_empty() {
+                let mut position = 0;
+                let (num_items, prefix_length) = varint::decode(&value).ok_or_else(invalid)?;
+                position += prefix_length;
+                let mut witness = Vec::new();
+                for _ in 0..num_items {
+                    witness.push(read_bytes(&value, &mut position)?);
+                }
+                if position != value.len() {
+                    return Err(invalid());
+                }
+                input.final_script_witness = Some(witness);
+            } else if key_type == PSBT_IN_TAP_KEY_SIG && key_data.is_empty() {
+                if value.len() != 64 && value.len() != 65 {
+                    return Err(invalid());
+                }
+                input.tap_key_sig = Some(value);
+            } else if key_type == PSBT_IN_TAP_BIP32_DERIVATION {
+                if key_data.len() != 32 {
+                    return Err(invalid());
+                }
+                let (num_leaf_hashes, prefix_length) =
+                    varint::decode(&value).ok_or_else(invalid)?;
+                let leaf_hashes_end = (num_leaf_hashes as usize)
+                    .checked_mul(32)
+                    .and_then(|len| len.checked_add(prefix_length))
+                    .filter(|end| *end <= value.len())
+                    .ok_or_else(invalid)?;
+                let leaf_hashes = value[prefix_length..leaf_hashes_end]
+                    .chunks(32)
+                    .map(|leaf_hash| leaf_hash.to_vec())
+                    .collect();
+                let key_source =
+                    KeySource::from_bytes(&value[leaf_hashes_end..]).ok_or_else(invalid)?;
+                input
+                    .tap_bip32_derivation
+                    .insert(key_data.to_vec(), (leaf_hashes, key_source));
+            } else if key_type == PSBT_IN_TAP_INTERNAL_KEY && key_data.is_empty() {
+                if value.len() != 32 {
+                    return Err(invalid());
+                }
+                input.tap_internal_key = Some(value);
+            } else {
+                input.unknown.insert(key, value);
+            }
+        }
+        Ok(input)
+    }
+
+    /// Returns the output that is spent by the input or `None` if it is unknown.
+    fn spent_output(&self, tx_in: &TransactionInput) -> Option<TransactionOutput> {
+        if let Some(output) = &self.witness_utxo {
+            return Some(output.clone());
+        }
+        let prev_tx = self.non_witness_utxo.as_ref()?;
+        if prev_tx.txid != tx_in.txid {
+            return None;
+        }
+        prev_tx.vout.get(tx_in.vout as usize).cloned()
+    }
+
+    /// Returns true if the final scriptSig or witness of the input is known.
+    pub fn is_finalized(&self) -> bool {
+        self.final_script_sig.is_some() || self.final_script_witness.is_some()
+    }
+
+    /// Builds the final scriptSig and witness of a single-key or multisig input that
+    /// spends the output.
+    fn finalize(&mut self, output: &TransactionOutput) -> Result<(), PsbtError> {
+        let incomplete = || PsbtError::new("psbt_incomplete_error");
+        if output.scriptpubkey_type == script::P2TR_TYPE {
+            let signature = self.tap_key_sig.clone().ok_or_else(incomplete)?;
+            self.final_script_witness = Some(vec![signature]);
+        } else if let Some(witness_script) = self.witness_script.clone() {
+            let (threshold, public_keys) =
+                script::parse_multisig(&witness_script).ok_or_else(incomplete)?;
+            // the signatures have to be in the order of the keys in the script, and
+            // OP_CHECKMULTISIG pops an additional element from the stack
+            let mut witness = vec![Vec::new()];
+            witness.extend(
+                public_keys
+                    .iter()
+                    .filter_map(|public_key| self.partial_sigs.get(public_key).cloned())
+                    .take(threshold),
+            );
+            if witness.len() != threshold + 1 {
+                return Err(incomplete());
+            }
+            witness.push(witness_script);
+            if output.scriptpubkey_type == script::P2SH_TYPE {
+                let redeem_script = self.redeem_script.clone().ok_or_else(incomplete)?;
+                self.final_script_sig = Some(push_script(&[&redeem_script]));
+            }
+            self.final_script_witness = Some(witness);
+        } else {
+            let (public_key, signature) = match self.partial_sigs.iter().next() {
+                Some((public_key, signature)) => (public_key.clone(), signature.clone()),
+                None => return Err(incomplete()),
+            };
+            if output.scriptpubkey_type == script::P2PKH_TYPE {
+                self.final_script_sig = Some(push_script(&[&signature, &public_key]));
+            } else if output.scriptpubkey_type == script::P2WPKH_TYPE {
+                self.final_script_witness = Some(vec![signature, public_key]);
+            } else if output.scriptpubkey_type == script::P2SH_TYPE {
+                // the script signature of a P2SH-P2WPKH input pushes the redeem script
+                let redeem_script = self.redeem_script.clone().ok_or_else(incomplete)?;
+                self.final_script_sig = Some(push_script(&[&redeem_script]));
+                self.final_script_witness = Some(vec![signature, public_key]);
+            } else {
+                return Err(incomplete());
+            }
+        }
+        self.partial_sigs.clear();
+        self.sighash_type = None;
+        self.redeem_script = None;
+        self.witness_script = None;
+        self.bip32_derivation.clear();
+        self.tap_key_sig = None;
+        self.tap_bip32_derivation.clear();
+        self.tap_internal_key = None;
+        Ok(())
+    }
+
+    /// Adds the fields of the other input that are missing.
+    fn merge(&mut self, other: &PsbtInput) {
+        self.non_witness_utxo = self
+            .non_witness_utxo
+            .take()
+            .or_else(|| other.non_witness_utxo.clone());
+        self.witness_utxo = self
+            .witness_utxo
+            .take()
+            .or_else(|| other.witness_utxo.clone());
+        merge_map(&mut self.partial_sigs, &other.partial_sigs);
+        self.sighash_type = self.sighash_type.or(other.sighash_type);
+        self.redeem_script = self
+            .redeem_script
+            .take()
+            .or_else(|| other.redeem_script.clone());
+        self.witness_script = self
+            .witness_script
+            .take()
+            .or_else(|| other.witness_script.clone());
+        merge_map(&mut self.bip32_derivation, &other.bip32_derivation);
+        self.final_script_sig = self
+            .final_script_sig
+            .take()
+            .or_else(|| other.final_script_sig.clone());
+        self.final_script_witness = self
+            .final_script_witness
+            .take()
+            .or_else(|| other.final_script_witness.clone());
+        self.tap_key_sig = self
+            .tap_key_sig
+            .take()
+            .or_else(|| other.tap_key_sig.clone());
+        merge_map(&mut self.tap_bip32_derivation, &other.tap_bip32_derivation);
+        self.tap_internal_key = self
+            .tap_internal_key
+            .take()
+            .or_else(|| other.tap_internal_key.clone());
+        merge_map(&mut self.unknown, &other.unknown);
+    }
+
+    /// Returns the serialized input map including the separator.
+    fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if let Some(tx) = &self.non_witness_utxo {
+            write_pair(&mut bytes, &[PSBT_IN_NON_WITNESS_UTXO], &tx.serialize());
+        }
+        if let Some(output) = &self.witness_utxo {
+            write_pair(&mut bytes, &[PSBT_IN_WITNESS_UTXO], &output.serialize());
+        }
+        for (public_key, signature) in &self.partial_sigs {
+            write_pair(&mut bytes, &key(PSBT_IN_PARTIAL_SIG, public_key), signature);
+        }
+        if let Some(sighash_type) = self.sighash_type {
+            write_pair(
+                &mut bytes,
+                &[PSBT_IN_SIGHASH_TYPE],
+                &sighash_type.to_le_bytes()[..4],
+            );
+        }
+        if let Some(redeem_script) = &self.redeem_script {
+            write_pair(&mut bytes, &[PSBT_IN_REDEEM_SCRIPT], redeem_script);
+        }
+        if let Some(witness_script) = &self.witness_script {
+            write_pair(&mut bytes, &[PSBT_IN_WITNESS_SCRIPT], witness_script);
+        }
+        for (public_key, key_source) in &self.bip32_derivation {
+            write_pair(
+                &mut bytes,
+                &key(PSBT_IN_BIP32_DERIVATION, public_key),
+                &key_source.serialize(),
+            );
+        }
+        if let Some(script_sig) = &self.final_script_sig {
+            write_pair(&mut bytes, &[PSBT_IN_FINAL_SCRIPTSIG], script_sig);
+        }
+        if let Some(witness) = &self.final_script_witness {
+            let mut value = varint::encode(witness.len() as u64);
+            for item in witness {
+                value.extend(varint::encode(item.len() as u64));
+                value.extend(item);
+            }
+            write_pair(&mut bytes, &[PSBT_IN_FINAL_SCRIPTWITNESS], &value);
+        }
+        if let Some(signature) = &self.tap_key_sig {
+            write_pair(&mut bytes, &[PSBT_IN_TAP_KEY_SIG], signature);
+        }
+        for (x_only_key, (leaf_hashes, key_source)) in &self.tap_bip32_derivation {
+            let mut value = varint::encode(leaf_hashes.len() as u64);
+            leaf_hashes
+                .iter()
+                .for_each(|leaf_hash| value.extend(leaf_hash));
+            value.extend(key_source.serialize());
+            write_pair(
+                &mut bytes,
+                &key(PSBT_IN_TAP_BIP32_DERIVATION, x_only_key),
+                &value,
+            );
+        }
+        if let Some(internal_key) = &self.tap_internal_key {
+            write_pair(&mut bytes, &[PSBT_IN_TAP_INTERNAL_KEY], internal_key);
+        }
+        write_unknown(&mut bytes, &self.unknown);
+        bytes.push(SEPARATOR);
+        bytes
+    }
+}
+
+impl PsbtOutput {
+    /// Parses the key-value pairs of an output map.
+    fn parse(pairs: KeyValuePairs) -> Result<PsbtOutput, PsbtError> {
+        let mut output = PsbtOutput::default();
+        for (key, value) in pairs {
+            if key == [PSBT_OUT_REDEEM_SCRIPT] {
+                output.redeem_script = Some(value);
+            } else if key == [PSBT_OUT_WITNESS_SCRIPT] {
+                output.witness_script = Some(value);
+            } else if key[0] == PSBT_OUT_BIP32_DERIVATION {
+                let key_source = match KeySource::from_bytes(&value) {
+                    Some(key_source) if key.len() == 34 => key_source,
+                    _ => return Err(PsbtError::new("invalid_psbt_error")),
+                };
+                output
+                    .bip32_derivation
+                    .insert(key[1..].to_vec(), key_source);
+            } else {
+                output.unknown.insert(key, value);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Adds the fields of the other output that are missing.
+    fn merge(&mut self, other: &PsbtOutput) {
+        self.redeem_script = self
+            .redeem_script
+            .take()
+            .or_else(|| other.redeem_script.clone());
+        self.witness_script = self
+            .witness_script
+            .take()
+            .or_else(|| other.witness_script.clone());
+        merge_map(&mut self.bip32_derivation, &other.bip32_derivation);
+        merge_map(&mut self.unknown, &other.unknown);
+    }
+
+    /// Returns the serialized output map including the separator.
+    fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if let Some(redeem_script) = &self.redeem_script {
+            write_pair(&mut bytes, &[PSBT_OUT_REDEEM_SCRIPT], redeem_script);
+        }
+        if let Some(witness_script) = &self.witness_script {
+            write_pair(&mut bytes, &[PSBT_OUT_WITNESS_SCRIPT], witness_script);
+        }
+        for (public_key, key_source) in &self.bip32_derivation {
+            write_pair(
+                &mut bytes,
+                &key(PSBT_OUT_BIP32_DERIVATION, public_key),
+                &key_source.serialize(),
+            );
+        }
+        write_unknown(&mut bytes, &self.unknown);
+        bytes.push(SEPARATOR);
+        bytes
+    }
+}
+
+/// Returns true if the output is locked by the public key of the key pair.
+/// The scripts of P2SH-P2WPKH and Taproot outputs are checked when the input is created.
+fn locks_output(output: &TransactionOutput, keypair: &Keypair) -> bool {
+    if output.scriptpubkey_type == script::P2PKH_TYPE
+        || output.scriptpubkey_type == script::P2WPKH_TYPE
+    {
+        let pubkey_hash = keypair.public_key.get_address().get_hash();
+        return script::pushed_data(&output.scriptpubkey_asm) == Some(&pubkey_hash);
+    }
+    let utxo_box = UTXOBox {
+        utxo: UTXO {
+            txid: String::from(""),
+            vout: 0,
+            value: output.value,
+            status: UTXOStatus {
+                confirmed: false,
+                block_height: None,
+                block_hash: None,
+                block_time: None,
+            },
+        },
+        output: output.clone(),
+        keypair: keypair.clone(),
+    };
+    TransactionInput::create(&utxo_box).is_ok()
+}
+
+/// Returns true if the output is locked by the witness script and the witness script
+/// is a multisig script that contains the public key. The redeem script is required
+/// for P2SH-P2WSH outputs.
+fn locks_output_with_script(
+    output: &TransactionOutput,
+    witness_script: &[u8],
+    redeem_script: Option<&[u8]>,
+    public_key: &[u8],
+) -> bool {
+    match script::parse_multisig(witness_script) {
+        Some((_, public_keys)) if public_keys.iter().any(|key| key == public_key) => (),
+        _ => return false,
+    }
+    let address = if output.scriptpubkey_type == script::P2WSH_TYPE {
+        Address::create_p2wsh(witness_script, false)
+    } else if output.scriptpubkey_type == script::P2SH_TYPE {
+        match redeem_script {
+            Some(redeem_script)
+                if redeem_script == script::p2sh_p2wsh_redeem_script(witness_script) =>
+            {
+                Address::create_p2sh(redeem_script, false)
+            }
+            _ => return false,
+        }
+    } else {
+        return false;
+    };
+    script::pushed_data(&output.scriptpubkey_asm) == Some(&address.get_hash())
+}
+
+/// Returns the script that pushes the data elements, without the length prefix.
+fn push_script(elements: &[&[u8]]) -> Vec<u8> {
+    let asm: Vec<String> = elements
+        .iter()
+        .map(|element| hex::bytes_to_hex(element))
+        .collect();
+    let bytes = script::serialize(&asm.join(" ")).unwrap();
+    let (_, prefix_length) = varint::decode(&bytes).unwrap();
+    bytes[prefix_length..].to_vec()
+}
+
+/// Adds the entries of the other map whose keys are missing.
+fn merge_map<V: Clone>(map: &mut BTreeMap<Vec<u8>, V>, other: &BTreeMap<Vec<u8>, V>) {
+    for (key, value) in other {
+        map.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+}
+
+/// Reads the key-value pairs of a map up to the separator.
+/// Duplicate keys are rejected.
+fn read_map(bytes: &[u8], position: &mut usize) -> Result<KeyValuePairs, PsbtError> {
+    let mut pairs: KeyValuePairs = Vec::new();
+    loop {
+        let key = read_bytes(bytes, position)?;
+        if key.is_empty() {
+            return Ok(pairs);
+        }
+        let value = read_bytes(bytes, position)?;
+        if pairs.iter().any(|(other, _)| *other == key) {
+            return Err(PsbtError::new("invalid_psbt_error"));
+        }
+        pairs.push((key, value));
+    }
+}
+
+/// Reads data that is prefixed with its length.
+fn read_bytes(bytes: &[u8], position: &mut usize) -> Result<Vec<u8>, PsbtError> {
+    let invalid = || PsbtError::new("invalid_psbt_error");
+    let (len, prefix_length) =
+        varint::decode(bytes.get(*position..).unwrap_or(&[])).ok_or_else(invalid)?;
+    let start = *position + prefix_length;
+    let end = start.checked_add(len as usize).ok_or_else(invalid)?;
+    let data = bytes.get(start..end).ok_or_else(invalid)?;
+    *position = end;
+    Ok(data.to_vec())
+}
+
+/// Returns the key of the specified type followed by the key data.
+fn key(key_type: u8, key_data: &[u8]) -> Vec<u8> {
+    let mut key = vec![key_type];
+    key.extend(key_data);
+    key
+}
+
+/// Writes a key-value pair, each prefixed with its length.
+fn write_pair(bytes: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    bytes.extend(varint::encode(key.len() as u64));
+    bytes.extend(key);
+    bytes.extend(varint::encode(value.len() as u64));
+    bytes.extend(value);
+}
+
+/// Writes the key-value pairs that are passed through unchanged.
+fn write_unknown(bytes: &mut Vec<u8>, unknown: &BTreeMap<Vec<u8>, Vec<u8>>) {
+    for (key, value) in unknown {
+        write_pair(bytes, key, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeySource, Psbt, MAGIC};
+    use crate::keys::{
+        bip32::{DerivationPath, ExtendedPrivateKey},
+        bip44::{
+            multisig::{Cosigner, MultisigAccounts, MultisigScriptType},
+            private_hierarchy::MasterPrivateKey,
+            BITCOIN_INDEX,
+        },
+    };
+    use crate::transactions::{
+        script,
+        transaction::{SighashType, Transaction, TransactionInput, TransactionOutput},
+        utxo::{UTXOBox, UTXOStatus, UTXO},
+    };
+    use crate::utils::{base64, hex, varint};
+    use secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1};
+    use std::str::FromStr;
+
+    fn private_key() -> ExtendedPrivateKey {
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap()
+    }
+
+    /// Returns a key hierarchy with two receive keys and a change key in the first account.
+    fn key_hierarchy(private_key: ExtendedPrivateKey) -> MasterPrivateKey {
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        let account = master_private_key.add_account(BITCOIN_INDEX);
+        master_private_key.new_receive_keypair(BITCOIN_INDEX, account.index, None);
+        master_private_key.new_receive_keypair(BITCOIN_INDEX, account.index, None);
+        master_private_key.new_change_keypair(BITCOIN_INDEX, account.index, None);
+        master_private_key
+    }
+
+    fn boxed_utxo(
+        txid: &str,
+        vout: u32,
+        output: TransactionOutput,
+        keypair_index: usize,
+        master_private_key: &MasterPrivateKey,
+    ) -> UTXOBox {
+        let keypairs = master_private_key.get_all_receive_keypairs(BITCOIN_INDEX, 0);
+        UTXOBox {
+            utxo: UTXO {
+                txid: txid.to_string(),
+                vout,
+                value: output.value,
+                status: UTXOStatus {
+                    confirmed: true,
+                    block_height: None,
+                    block_hash: None,
+                    block_time: None,
+                },
+            },
+            output,
+            keypair: keypairs[keypair_index].clone(),
+        }
+    }
+
+    /// Returns an unsigned transaction that spends a legacy and a SegWit output of the
+    /// wallet together with the spent UTXOs and the previous transaction of the legacy output.
+    fn wallet_tx(
+        master_private_key: &MasterPrivateKey,
+    ) -> (Transaction, Vec<UTXOBox>, Transaction) {
+        let keypairs = master_private_key.get_all_receive_keypairs(BITCOIN_INDEX, 0);
+        let change_keypairs = master_private_key.get_all_change_keypairs(BITCOIN_INDEX, 0);
+        let legacy_address = keypairs[0].public_key.get_address();
+        let mut prev_tx = Transaction {
+            txid: String::from(""),
+            version: 1,
+            locktime: 0,
+            vin: vec![TransactionInput {
+                txid: String::from(
+                    "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+                ),
+                vout: 1,
+                prevout: TransactionOutput::default(),
+                scriptsig: String::from(""),
+                scriptsig_asm: String::from(""),
+                witness: None,
+                is_coinbase: false,
+                sequence: 0xffffffff,
+            }],
+            vout: vec![TransactionOutput::create(&legacy_address, 100000)],
+            size: None,
+            weight: None,
+            fee: None,
+            status: None,
+        };
+        prev_tx.txid = prev_tx.compute_txid();
+        let segwit_hash = keypairs[1].public_key.get_address().get_hash();
+        let segwit_output = TransactionOutput {
+            scriptpubkey: String::from(""),
+            scriptpubkey_asm: script::p2wpkh_script_pub_key(&segwit_hash),
+            scriptpubkey_type: String::from("v0_p2wpkh"),
+            scriptpubkey_address: String::from(""),
+            value: 50000,
+        };
+        let boxed_utxos = vec![
+            boxed_utxo(
+                &prev_tx.txid,
+                0,
+                prev_tx.vout[0].clone(),
+                0,
+                master_private_key,
+            ),
+            boxed_utxo(
+                "0dd7697dfc7f13978dcf44e7d82c0be4591dec423616510a46cdb6cfeddae02d",
+                3,
+                segwit_output,
+                1,
+                master_private_key,
+            ),
+        ];
+        let targets = vec![
+            (keypairs[1].public_key.get_address(), 120000),
+            (change_keypairs[0].public_key.get_address(), 29000),
+        ];
+        let tx = Transaction::create(&boxed_utxos, targets).unwrap();
+        (tx, boxed_utxos, prev_tx)
+    }
+
+    /// Returns the PSBT of the wallet transaction including the previous transaction.
+    fn wallet_psbt(master_private_key: &MasterPrivateKey) -> Psbt {
+        let (tx, boxed_utxos, prev_tx) = wallet_tx(master_private_key);
+        let mut psbt = Psbt::create(&tx, &boxed_utxos, master_private_key);
+        psbt.add_previous_transaction(&prev_tx);
+        psbt
+    }
+
+    #[test]
+    fn test_key_source() {
+        let key_source = KeySource {
+            fingerprint: [0xd9, 0x0c, 0x6a, 0x4f],
+            path: DerivationPath::from_str("m/44'/0'/0/1").unwrap(),
+        };
+        let bytes = key_source.serialize();
+        assert_eq!(
+            hex::bytes_to_hex(&bytes),
+            "d90c6a4f2c000080000000800000000001000000"
+        );
+        assert_eq!(KeySource::from_bytes(&bytes), Some(key_source));
+        assert_eq!(KeySource::from_bytes(&bytes[..7]), None);
+    }
+
+    #[test]
+    fn test_create_psbt() {
+        let master_private_key = key_hierarchy(private_key());
+        let keypairs = master_private_key.get_all_receive_keypairs(BITCOIN_INDEX, 0);
+        let change_keypair = &master_private_key.get_all_change_keypairs(BITCOIN_INDEX, 0)[0];
+        let (mut tx, boxed_utxos, prev_tx) = wallet_tx(&master_private_key);
+        let unsigned_txid = tx.compute_txid();
+        tx.sign_all_inputs(&boxed_utxos);
+
+        let mut psbt = Psbt::create(&tx, &boxed_utxos, &master_private_key);
+        assert!(psbt.add_previous_transaction(&prev_tx));
+        // the unsigned transaction does not contain the signatures
+        assert!(psbt
+            .unsigned_tx
+            .vin
+            .iter()
+            .all(|tx_in| tx_in.scriptsig_asm.is_empty() && tx_in.witness.is_none()));
+        assert_eq!(psbt.unsigned_tx.compute_txid(), unsigned_txid);
+
+        let fingerprint = master_private_key.private_key.get_fingerprint();
+        let legacy_input = &psbt.inputs[0];
+        assert_eq!(
+            legacy_input.non_witness_utxo.as_ref().unwrap().txid,
+            prev_tx.txid
+        );
+        assert!(legacy_input.witness_utxo.is_none());
+        let key_source = legacy_input
+            .bip32_derivation
+            .get(&keypairs[0].public_key.key_data.to_vec())
+            .unwrap();
+        assert_eq!(key_source.fingerprint, fingerprint);
+        let key = master_private_key
+            .private_key
+            .derive_path(&key_source.path)
+            .unwrap();
+        assert_eq!(
+            key.derive_public_key().key_data,
+            keypairs[0].public_key.key_data
+        );
+        let segwit_input = &psbt.inputs[1];
+        assert!(segwit_input.non_witness_utxo.is_none());
+        assert_eq!(
+            segwit_input.witness_utxo.as_ref().unwrap().serialize(),
+            boxed_utxos[1].output.serialize()
+        );
+        assert_eq!(segwit_input.bip32_derivation.len(), 1);
+        // outputs to the wallet are marked with the derivation paths of their keys
+        let change_source = psbt.outputs[1]
+            .bip32_derivation
+            .get(&change_keypair.public_key.key_data.to_vec())
+            .unwrap();
+        assert_eq!(change_source.path.to_string(), "m/44'/0'/1/0");
+
+        let bytes = psbt.serialize();
+        let unsigned_tx = psbt.unsigned_tx.serialize_without_witness();
+        let mut prefix = MAGIC.to_vec();
+        prefix.extend([0x01, 0x00]);
+        prefix.extend(varint::encode(unsigned_tx.len() as u64));
+        prefix.extend(&unsigned_tx);
+        prefix.push(0x00);
+        assert!(bytes.starts_with(&prefix));
+        assert_eq!(base64::decode(&psbt.to_base64()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_parse_psbt() {
+        let master_private_key = key_hierarchy(private_key());
+        let mut psbt = wallet_psbt(&master_private_key);
+        psbt.inputs[1].unknown.insert(vec![0xfc, 0x01], vec![0x02]);
+        let parsed = Psbt::from_base64(&psbt.to_base64(), false).unwrap();
+        assert_eq!(parsed.serialize(), psbt.serialize());
+        assert_eq!(parsed.unsigned_tx.txid, psbt.unsigned_tx.compute_txid());
+        assert_eq!(
+            parsed.inputs[0].non_witness_utxo.as_ref().unwrap().txid,
+            psbt.inputs[0].non_witness_utxo.as_ref().unwrap().txid
+        );
+        assert_eq!(
+            parsed.inputs[1]
+                .witness_utxo
+                .as_ref()
+                .unwrap()
+                .scriptpubkey_type,
+            "v0_p2wpkh"
+        );
+        assert_eq!(
+            parsed.inputs[1].unknown.get(&vec![0xfc, 0x01]),
+            Some(&vec![0x02])
+        );
+
+        let bytes = psbt.serialize();
+        let err = |bytes: &[u8]| Psbt::from_bytes(bytes, false).unwrap_err().to_string();
+        assert_eq!(err(&bytes[1..]), "invalid_psbt_error");
+        assert_eq!(err(&bytes[..bytes.len() - 1]), "invalid_psbt_error");
+        let mut trailing_data = bytes.clone();
+        trailing_data.push(0x00);
+        assert_eq!(err(&trailing_data), "invalid_psbt_error");
+        // the unsigned transaction is specified twice
+        let mut duplicate_key = MAGIC.to_vec();
+        let unsigned_tx = psbt.unsigned_tx.serialize_without_witness();
+        for _ in 0..2 {
+            duplicate_key.extend([0x01, 0x00]);
+            duplicate_key.extend(varint::encode(unsigned_tx.len() as u64));
+            duplicate_key.extend(&unsigned_tx);
+        }
+        assert_eq!(err(&duplicate_key), "invalid_psbt_error");
+        assert!(Psbt::from_base64("cHNidP8=!", false).is_err());
+    }
+
+    #[test]
+    fn test_sign_psbt() {
+        let master_private_key = key_hierarchy(private_key());
+        let (mut tx, boxed_utxos, _) = wallet_tx(&master_private_key);
+        let mut psbt = wallet_psbt(&master_private_key);
+        assert_eq!(psbt.sign(&master_private_key).unwrap(), 2);
+
+        // ECDSA signatures are deterministic, so they match the signatures of the wallet
+        tx.sign_all_inputs(&boxed_utxos);
+        let legacy_signature = tx.vin[0].scriptsig_asm.split(" ").next().unwrap();
+        let segwit_signature = &tx.vin[1].witness.as_ref().unwrap()[0];
+        let keypairs = master_private_key.get_all_receive_keypairs(BITCOIN_INDEX, 0);
+        let partial_sig = |index: usize| {
+            let public_key = keypairs[index].public_key.key_data.to_vec();
+            hex::bytes_to_hex(psbt.inputs[index].partial_sigs.get(&public_key).unwrap())
+        };
+        assert_eq!(partial_sig(0), legacy_signature);
+        assert_eq!(&partial_sig(1), segwit_signature);
+
+        // the keys of another wallet do not match the derivation paths
+        let other_key = ExtendedPrivateKey::create_master_key([0x01; 32], false);
+        let other_master_private_key = key_hierarchy(other_key);
+        let mut psbt = wallet_psbt(&master_private_key);
+        assert_eq!(
+            psbt.sign(&other_master_private_key)
+                .unwrap_err()
+                .to_string(),
+            "psbt_key_missing_error"
+        );
+    }
+
+    #[test]
+    fn test_finalize_psbt() {
+        let master_private_key = key_hierarchy(private_key());
+        let (mut tx, boxed_utxos, _) = wallet_tx(&master_private_key);
+        let mut psbt = wallet_psbt(&master_private_key);
+        assert_eq!(
+            psbt.finalize().unwrap_err().to_string(),
+            "psbt_incomplete_error"
+        );
+        assert_eq!(
+            psbt.extract_transaction().unwrap_err().to_string(),
+            "psbt_incomplete_error"
+        );
+
+        psbt.sign(&master_private_key).unwrap();
+        psbt.finalize().unwrap();
+        assert!(psbt.inputs.iter().all(|input| input.is_finalized()
+            && input.partial_sigs.is_empty()
+            && input.bip32_derivation.is_empty()));
+        // the finalized PSBT can be exchanged as well
+        let psbt = Psbt::from_base64(&psbt.to_base64(), false).unwrap();
+
+        // ECDSA signatures are deterministic, so the transaction matches the wallet's
+        tx.sign_all_inputs(&boxed_utxos);
+        let extracted = psbt.extract_transaction().unwrap();
+        assert_eq!(extracted.serialize_hex(), tx.serialize_hex());
+        assert_eq!(extracted.txid, tx.compute_txid());
+        assert_eq!(extracted.fee, Some(1000));
+    }
+
+    #[test]
+    fn test_merge_psbt() {
+        let master_private_key = key_hierarchy(private_key());
+        let psbt = wallet_psbt(&master_private_key);
+        // each cosigner only knows the derivation path of one input
+        let mut first = psbt.clone();
+        first.inputs[1].bip32_derivation.clear();
+        assert_eq!(first.sign(&master_private_key).unwrap(), 1);
+        let mut second = psbt.clone();
+        second.inputs[0].bip32_derivation.clear();
+        assert_eq!(second.sign(&master_private_key).unwrap(), 1);
+
+        first.merge(&second).unwrap();
+        assert_eq!(first.inputs[0].partial_sigs.len(), 1);
+        assert_eq!(first.inputs[1].partial_sigs.len(), 1);
+        assert_eq!(
+            first.inputs[1].bip32_derivation,
+            psbt.inputs[1].bip32_derivation
+        );
+
+        let mut other_tx = psbt.clone();
+        other_tx.unsigned_tx.locktime = 1;
+        assert_eq!(
+            first.merge(&other_tx).unwrap_err().to_string(),
+            "psbt_mismatch_error"
+        );
+    }
+
+    #[test]
+    fn test_multisig_psbt() {
+        let master_private_keys: Vec<MasterPrivateKey> = (1..=3)
+            .map(|seed| {
+                MasterPrivateKey::create_from_key(ExtendedPrivateKey::create_master_key(
+                    [seed; 32], false,
+                ))
+            })
+            .collect();
+        for script_type in [MultisigScriptType::P2WSH, MultisigScriptType::P2SHP2WSH] {
+            let cosigners = master_private_keys
+                .iter()
+                .map(|master_private_key| {
+                    Cosigner::derive_local(
+                        String::from(""),
+                        master_private_key,
+                        BITCOIN_INDEX,
+                        0,
+                        script_type,
+                    )
+                })
+                .collect();
+            let mut accounts = MultisigAccounts::default();
+            let mut account = accounts
+                .add(BITCOIN_INDEX, String::from(""), 2, script_type, cosigners)
+                .unwrap()
+                .clone();
+            let receive_address = account.new_receive_address();
+            let change_address = account.new_change_address();
+            let utxo_box = UTXOBox {
+                utxo: UTXO {
+                    txid: String::from(
+                        "0dd7697dfc7f13978dcf44e7d82c0be4591dec423616510a46cdb6cfeddae02d",
+                    ),
+                    vout: 0,
+                    value: 100000,
+                    status: UTXOStatus {
+                        confirmed: true,
+                        block_height: None,
+                        block_hash: None,
+                        block_time: None,
+                    },
+                },
+                output: TransactionOutput::create(&receive_address, 100000),
+                keypair: account
+                    .local_keypair(&master_private_keys[0], false, 0)
+                    .unwrap(),
+            };
+            let targets = vec![(change_address, 99000)];
+            let tx = Transaction::create_unsigned(std::slice::from_ref(&utxo_box), targets);
+            let psbt = Psbt::create_multisig(&tx, &[utxo_box], &account);
+            assert_eq!(psbt.inputs[0].bip32_derivation.len(), 3);
+            assert!(psbt.outputs[0].witness_script.is_some());
+            let psbt = Psbt::from_base64(&psbt.to_base64(), false).unwrap();
+
+            let mut first = psbt.clone();
+            assert_eq!(first.sign(&master_private_keys[2]).unwrap(), 1);
+            let mut incomplete = first.clone();
+            assert_eq!(
+                incomplete.finalize().unwrap_err().to_string(),
+                "psbt_incomplete_error"
+            );
+            let mut second = psbt.clone();
+            assert_eq!(second.sign(&master_private_keys[0]).unwrap(), 1);
+            first.merge(&second).unwrap();
+            first.finalize().unwrap();
+            let signed_tx = first.extract_transaction().unwrap();
+            assert_eq!(signed_tx.fee, Some(1000));
+
+            // the signatures are valid for the keys in the witness script
+            let witness_script = account.witness_script(false, 0);
+            let witness: Vec<Vec<u8>> = signed_tx.vin[0]
+                .witness
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|item| hex::hex_to_bytes(item).unwrap())
+                .collect();
+            assert_eq!(witness.len(), 4);
+            assert!(witness[0].is_empty());
+            assert_eq!(witness[3], witness_script);
+            let script_code = script::deserialize(&witness_script).unwrap();
+            let z = tx
+                .signature_hash_bip143(0, &script_code, 100000, SighashType::Default)
+                .unwrap();
+            let msg = Message::from_slice(&z).unwrap();
+            let (_, public_keys) = script::parse_multisig(&witness_script).unwrap();
+            let secp = Secp256k1::new();
+            let mut key_index = 0;
+            for signature in &witness[1..3] {
+                let signature = Signature::from_der(&signature[..signature.len() - 1]).unwrap();
+                // each signature belongs to a later key than the previous one
+                while secp
+                    .verify_ecdsa(
+                        &msg,
+                        &signature,
+                        &PublicKey::from_slice(&public_keys[key_index]).unwrap(),
+                    )
+                    .is_err()
+                {
+                    key_index += 1;
+                }
+                key_index += 1;
+            }
+            assert!(key_index <= 3);
+            assert_eq!(
+                signed_tx.vin[0].scriptsig.is_empty(),
+                script_type == MultisigScriptType::P2WSH
+            );
+        }
+    }
+}
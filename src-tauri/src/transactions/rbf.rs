@@ -0,0 +1,235 @@
+//! Implements fee bumping of unconfirmed transactions with replace-by-fee as specified in
+//! [BIP-125](https://github.com/bitcoin/bips/blob/master/bip-0125.mediawiki).
+//! The replacement spends the same coins and pays the same recipients.
+//! The additional fee is deducted from the change output.
+use super::error::FeeBumpError;
+use super::transaction::{Transaction, TransactionInput, DUST_LIMIT};
+use super::utxo::{UTXOBox, UTXOStatus, UTXO};
+use crate::coin_selection::fee_estimation;
+use crate::keys::bip44::Keypair;
+
+static INCREMENTAL_RELAY_FEE: u64 = 1; // in sat/B, the default of Bitcoin Core
+
+/// Returns the signed replacement of the transaction that pays the specified fee per byte.
+/// The key pairs must include the key pairs of all inputs. The change output is the first
+/// output that pays to one of the change key pairs.
+pub fn bump_fee(
+    tx: &Transaction,
+    keypairs: &[Keypair],
+    change_keypairs: &[Keypair],
+    fee_per_byte: u64,
+) -> Result<Transaction, FeeBumpError> {
+    if tx.status.as_ref().is_some_and(|status| status.confirmed) {
+        return Err(FeeBumpError::new("tx_confirmed_error"));
+    }
+    if !tx.signals_rbf() {
+        return Err(FeeBumpError::new("not_replaceable_error"));
+    }
+    let boxed_utxos = spent_utxos(tx, keypairs)?;
+    let change_addresses: Vec<String> = change_keypairs
+        .iter()
+        .map(|keypair| keypair.public_key.get_address().to_string())
+        .collect();
+    let change_index = match tx
+        .vout
+        .iter()
+        .position(|tx_out| change_addresses.contains(&tx_out.scriptpubkey_address))
+    {
+        Some(change_index) => change_index,
+        None => return Err(FeeBumpError::new("change_output_missing_error")),
+    };
+
+    let input_value: u64 = boxed_utxos.iter().map(|utxo| utxo.utxo.value).sum();
+    let output_value: u64 = tx.vout.iter().map(|tx_out| tx_out.value).sum();
+    let old_fee = input_value.saturating_sub(output_value);
+    let size =
+        fee_estimation::estimate_transaction_size(tx.vin.len() as u32, tx.vout.len() as u32) as u64;
+    // the replacement has to pay for its own relay in addition to the fee it replaces
+    let new_fee = std::cmp::max(size * fee_per_byte, old_fee + size * INCREMENTAL_RELAY_FEE);
+    let fee_increase = new_fee - old_fee;
+    let change = tx.vout[change_index].value;
+    if change < fee_increase + DUST_LIMIT {
+        return Err(FeeBumpError::new("balance_insufficient"));
+    }
+
+    let mut vin = Vec::new();
+    for (tx_in, utxo_box) in tx.vin.iter().zip(boxed_utxos.iter()) {
+        let mut new_tx_in = match TransactionInput::create(utxo_box) {
+            Ok(new_tx_in) => new_tx_in,
+            Err(_) => return Err(FeeBumpError::new("create_tx_error")),
+        };
+        new_tx_in.sequence = tx_in.sequence;
+        vin.push(new_tx_in);
+    }
+    let mut vout = tx.vout.clone();
+    vout[change_index].value = change - fee_increase;
+    let mut replacement = Transaction {
+        txid: String::from(""),
+        version: tx.version,
+        locktime: tx.locktime,
+        vin,
+        vout,
+        size: None,
+        weight: None,
+        fee: Some(new_fee),
+        status: None,
+    };
+    replacement.sign_all_inputs(&boxed_utxos);
+    replacement.txid = replacement.compute_txid();
+    Ok(replacement)
+}
+
+/// Returns the outputs that are spent by the transaction together with their key pairs.
+/// The prevouts of the inputs must be known.
+fn spent_utxos(tx: &Transaction, keypairs: &[Keypair]) -> Result<Vec<UTXOBox>, FeeBumpError> {
+    let mut boxed_utxos = Vec::new();
+    for tx_in in &tx.vin {
+        let keypair = keypairs.iter().find(|keypair| {
+            keypair.public_key.get_address().to_string() == tx_in.prevout.scriptpubkey_address
+        });
+        let keypair = match keypair {
+            Some(keypair) => keypair.clone(),
+            None => return Err(FeeBumpError::new("foreign_input_error")),
+        };
+        boxed_utxos.push(UTXOBox {
+            utxo: UTXO {
+                txid: tx_in.txid.clone(),
+                vout: tx_in.vout,
+                value: tx_in.prevout.value,
+                status: UTXOStatus {
+                    confirmed: false,
+                    block_height: None,
+                    block_hash: None,
+                    block_time: None,
+                },
+            },
+            output: tx_in.prevout.clone(),
+            keypair,
+        });
+    }
+    Ok(boxed_utxos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bump_fee;
+    use crate::keys::bip44::private_hierarchy::Keypair;
+    use crate::test_utils::keypair_from_hex;
+    use crate::transactions::{
+        script,
+        transaction::{Transaction, TransactionInput, TransactionOutput, TransactionStatus},
+    };
+
+    fn p2pkh_output(keypair: &Keypair, value: u64) -> TransactionOutput {
+        let address = keypair.public_key.get_address();
+        TransactionOutput {
+            scriptpubkey: String::from(""),
+            scriptpubkey_asm: script::p2pkh_script_pub_key(&address.get_hash()),
+            scriptpubkey_type: String::from("p2pkh"),
+            scriptpubkey_address: address.to_string(),
+            value,
+        }
+    }
+
+    /// Returns a transaction that spends 100000 sat of the first key pair,
+    /// pays 50000 sat to the second and the change to the third.
+    fn unconfirmed_tx(keypairs: &[Keypair], change: u64) -> Transaction {
+        let mut tx = Transaction {
+            txid: String::from(""),
+            version: 1,
+            locktime: 0,
+            vin: vec![TransactionInput {
+                txid: String::from(
+                    "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+                ),
+                vout: 0,
+                prevout: p2pkh_output(&keypairs[0], 100000),
+                scriptsig: String::from(""),
+                scriptsig_asm: String::from(""),
+                witness: None,
+                is_coinbase: false,
+                sequence: 0xffffffff,
+            }],
+            vout: vec![
+                p2pkh_output(&keypairs[1], 50000),
+                p2pkh_output(&keypairs[2], change),
+            ],
+            size: None,
+            weight: None,
+            fee: None,
+            status: Some(TransactionStatus {
+                confirmed: false,
+                block_height: None,
+                block_hash: None,
+                block_time: None,
+            }),
+        };
+        tx.signal_rbf();
+        tx
+    }
+
+    fn keypairs() -> Vec<Keypair> {
+        vec![
+            keypair_from_hex("eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf"),
+            keypair_from_hex("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9"),
+            keypair_from_hex("8e02b539b1500aa7c81cf3fed177448a546f19d2be416c0c61ff28e577d8d0cd"),
+        ]
+    }
+
+    #[test]
+    fn test_bump_fee() {
+        let keypairs = keypairs();
+        let tx = unconfirmed_tx(&keypairs, 49000);
+        let replacement = bump_fee(&tx, &keypairs[..1], &keypairs[2..], 10).unwrap();
+        // the estimated size is 225 bytes
+        assert_eq!(replacement.fee, Some(2250));
+        assert_eq!(replacement.vout[0].value, 50000);
+        assert_eq!(replacement.vout[1].value, 47750);
+        assert!(replacement.signals_rbf());
+        assert_ne!(replacement.vin[0].scriptsig_asm, "");
+        assert_eq!(replacement.txid, replacement.compute_txid());
+        assert_ne!(replacement.txid, tx.compute_txid());
+
+        // the fee increases by at least the relay fee of the replacement
+        let replacement = bump_fee(&tx, &keypairs[..1], &keypairs[2..], 1).unwrap();
+        assert_eq!(replacement.fee, Some(1225));
+        assert_eq!(replacement.vout[1].value, 48775);
+    }
+
+    #[test]
+    fn test_bump_fee_rejected() {
+        let keypairs = keypairs();
+        let mut tx = unconfirmed_tx(&keypairs, 49000);
+        let err = |tx: &Transaction, keypairs: &[Keypair], change_keypairs: &[Keypair]| {
+            bump_fee(tx, keypairs, change_keypairs, 10)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err(&tx, &keypairs[1..2], &keypairs[2..]),
+            "foreign_input_error"
+        );
+        assert_eq!(
+            err(&tx, &keypairs[..1], &keypairs[1..1]),
+            "change_output_missing_error"
+        );
+        let dust_change_tx = unconfirmed_tx(&keypairs, 1500);
+        assert_eq!(
+            bump_fee(&dust_change_tx, &keypairs[..1], &keypairs[2..], 300)
+                .unwrap_err()
+                .to_string(),
+            "balance_insufficient"
+        );
+        tx.vin[0].sequence = 0xfffffffe;
+        assert_eq!(
+            err(&tx, &keypairs[..1], &keypairs[2..]),
+            "not_replaceable_error"
+        );
+        tx.signal_rbf();
+        tx.status.as_mut().unwrap().confirmed = true;
+        assert_eq!(
+            err(&tx, &keypairs[..1], &keypairs[2..]),
+            "tx_confirmed_error"
+        );
+    }
+}
@@ -1,9 +1,26 @@
 use crate::transactions::error::ParseScriptTypeError;
 use crate::utils::{hex, varint};
+use bitcoin_hashes::{sha256, Hash};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 use std::str::FromStr;
 
+/// Script types of previous outputs as reported by the backend.
+pub static P2PKH_TYPE: &'static str = "p2pkh";
+pub static P2SH_TYPE: &'static str = "p2sh";
+pub static P2WPKH_TYPE: &'static str = "v0_p2wpkh";
+pub static P2WSH_TYPE: &'static str = "v0_p2wsh";
+pub static P2TR_TYPE: &'static str = "v1_p2tr";
+pub static OP_RETURN_TYPE: &'static str = "op_return";
+
+pub static MAX_OP_RETURN_SIZE: usize = 80; // the largest payload that is relayed by default
+
+static OP_1: u8 = 81;
+static OP_16: u8 = 96;
+static OP_CHECKMULTISIG: u8 = 174;
+static OP_RETURN: u8 = 106;
+static OP_PUSHDATA1: u8 = 76;
+
 lazy_static! {
     static ref OP_CODE_TO_WORD: HashMap<u8, &'static str> = {
         let mut m = HashMap::new();
@@ -97,6 +114,7 @@ lazy_static! {
         m.insert(183, "OP_NOP8");
         m.insert(184, "OP_NOP9");
         m.insert(185, "OP_NOP10");
+        m.insert(186, "OP_CHECKSIGADD");
         m
     };
 }
@@ -194,6 +212,7 @@ lazy_static! {
         m.insert("OP_NOP8", 183);
         m.insert("OP_NOP9", 184);
         m.insert("OP_NOP10", 185);
+        m.insert("OP_CHECKSIGADD", 186);
         m
     };
 }
@@ -210,11 +229,11 @@ pub fn serialize(s: &str) -> Option<Vec<u8>> {
         } else if token.starts_with("OP_PUSHBYTES") {
             // ignore
         } else {
-            let elem_bytes = hex::hex_to_bytes(token).unwrap();
+            let elem_bytes = hex::hex_to_bytes(token)?;
             let len = elem_bytes.len();
-            if len < 75 {
+            if len <= 75 {
                 bytes.extend(&len.to_le_bytes()[..1]);
-            } else if len >= 75 && len < 0x100 {
+            } else if len > 75 && len < 0x100 {
                 bytes.extend(76u8.to_le_bytes());
                 bytes.extend(&len.to_le_bytes()[..1]);
             } else if len >= 0x100 && len <= 520 {
@@ -231,6 +250,71 @@ pub fn serialize(s: &str) -> Option<Vec<u8>> {
     Some(total_len)
 }
 
+/// An element of a script: either an opcode or data that is pushed onto the stack.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptElement {
+    OpCode(u8),
+    Data(Vec<u8>),
+}
+
+/// Parses the script into its elements.
+/// The script must not be prefixed with its length.
+/// Returns `None` if a push exceeds the end of the script.
+pub fn parse(bytes: &[u8]) -> Option<Vec<ScriptElement>> {
+    let mut elements = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let op_code = bytes[i];
+        i += 1;
+        let len = match op_code {
+            1..=75 => op_code as usize,
+            76 => {
+                let len = *bytes.get(i)? as usize;
+                i += 1;
+                len
+            }
+            77 => {
+                let len = u16::from_le_bytes(bytes.get(i..i + 2)?.try_into().unwrap()) as usize;
+                i += 2;
+                len
+            }
+            78 => {
+                let len = u32::from_le_bytes(bytes.get(i..i + 4)?.try_into().unwrap()) as usize;
+                i += 4;
+                len
+            }
+            _ => {
+                elements.push(ScriptElement::OpCode(op_code));
+                continue;
+            }
+        };
+        let data = bytes.get(i..i.checked_add(len)?)?;
+        elements.push(ScriptElement::Data(data.to_vec()));
+        i += len;
+    }
+    Some(elements)
+}
+
+/// Returns the script in assembly format.
+/// The script must not be prefixed with its length.
+/// Returns `None` if a push exceeds the end of the script.
+pub fn deserialize(bytes: &[u8]) -> Option<String> {
+    let words: Vec<String> = parse(bytes)?
+        .iter()
+        .map(|element| match element {
+            ScriptElement::OpCode(op_code) => match OP_CODE_TO_WORD.get(op_code) {
+                Some(word) => word.to_string(),
+                None => format!("OP_UNKNOWN_{}", op_code),
+            },
+            ScriptElement::Data(data) if data.len() <= 75 => {
+                format!("OP_PUSHBYTES_{} {}", data.len(), hex::bytes_to_hex(data))
+            }
+            ScriptElement::Data(data) => hex::bytes_to_hex(data),
+        })
+        .collect();
+    Some(words.join(" "))
+}
+
 pub fn p2pkh_script_pub_key(pubkey_hash: &str) -> String {
     format!(
         "OP_DUP OP_HASH160 OP_PUSHBYTES_20 {} OP_EQUALVERIFY OP_CHECKSIG",
@@ -242,9 +326,128 @@ pub fn p2pkh_script_sig(signature: &str, pubkey: &str) -> String {
     format!("{} {}", signature, pubkey)
 }
 
+pub fn p2sh_script_pub_key(script_hash: &str) -> String {
+    format!("OP_HASH160 OP_PUSHBYTES_20 {} OP_EQUAL", script_hash)
+}
+
+pub fn p2wpkh_script_pub_key(pubkey_hash: &str) -> String {
+    format!("OP_0 OP_PUSHBYTES_20 {}", pubkey_hash)
+}
+
+pub fn p2wsh_script_pub_key(script_hash: &str) -> String {
+    format!("OP_0 OP_PUSHBYTES_32 {}", script_hash)
+}
+
+pub fn p2tr_script_pub_key(output_key: &str) -> String {
+    format!("OP_1 OP_PUSHBYTES_32 {}", output_key)
+}
+
+/// Returns the redeem script of a P2SH-P2WPKH output in hex format.
+pub fn p2sh_p2wpkh_redeem_script(pubkey_hash: &str) -> String {
+    format!("0014{}", pubkey_hash)
+}
+
+/// Returns the m-of-n multisig script of the public keys. The keys are sorted
+/// as specified in [BIP-67](https://github.com/bitcoin/bips/blob/master/bip-0067.mediawiki),
+/// so all cosigners derive the same script. At most 16 keys are supported.
+pub fn multisig_script(threshold: usize, public_keys: &[Vec<u8>]) -> Option<Vec<u8>> {
+    if threshold == 0 || threshold > public_keys.len() || public_keys.len() > 16 {
+        return None;
+    }
+    let mut public_keys = public_keys.to_vec();
+    public_keys.sort();
+    // OP_1 to OP_16 push the numbers 1 to 16
+    let mut bytes = vec![OP_1 + threshold as u8 - 1];
+    for public_key in &public_keys {
+        bytes.push(public_key.len() as u8);
+        bytes.extend(public_key);
+    }
+    bytes.push(OP_1 + public_keys.len() as u8 - 1);
+    bytes.push(OP_CHECKMULTISIG);
+    Some(bytes)
+}
+
+/// Returns the threshold and the public keys of an m-of-n multisig script,
+/// or `None` if the script is not a multisig script.
+pub fn parse_multisig(script: &[u8]) -> Option<(usize, Vec<Vec<u8>>)> {
+    let elements = parse(script)?;
+    let small_int = |element: &ScriptElement| match element {
+        ScriptElement::OpCode(op_code) if *op_code >= OP_1 && *op_code <= OP_16 => {
+            Some((op_code - OP_1 + 1) as usize)
+        }
+        _ => None,
+    };
+    if elements.len() < 4 || elements.last() != Some(&ScriptElement::OpCode(OP_CHECKMULTISIG)) {
+        return None;
+    }
+    let threshold = small_int(&elements[0])?;
+    let num_keys = small_int(&elements[elements.len() - 2])?;
+    let public_keys: Vec<Vec<u8>> = elements[1..elements.len() - 2]
+        .iter()
+        .filter_map(|element| match element {
+            ScriptElement::Data(data) if data.len() == 33 => Some(data.clone()),
+            _ => None,
+        })
+        .collect();
+    if public_keys.len() != num_keys || num_keys != elements.len() - 3 || threshold > num_keys {
+        return None;
+    }
+    Some((threshold, public_keys))
+}
+
+/// Returns the redeem script of a P2SH-P2WSH output, i.e., the version byte
+/// followed by the hash of the witness script.
+pub fn p2sh_p2wsh_redeem_script(witness_script: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0x00, 0x20];
+    bytes.extend(sha256::Hash::hash(witness_script).into_inner());
+    bytes
+}
+
+/// Returns the script of an unspendable output that carries the data,
+/// or `None` if the data exceeds the size that is relayed.
+pub fn op_return_script(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() > MAX_OP_RETURN_SIZE {
+        return None;
+    }
+    let mut bytes = vec![OP_RETURN];
+    if data.len() > 75 {
+        bytes.push(OP_PUSHDATA1);
+    }
+    bytes.push(data.len() as u8);
+    bytes.extend(data);
+    Some(bytes)
+}
+
+/// Returns the first data element that is pushed by the script.
+pub fn pushed_data(s: &str) -> Option<&str> {
+    s.split(" ")
+        .find(|token| !token.is_empty() && !token.starts_with("OP_"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ScriptType {
     P2PKH,
-    //P2WPKH,
+    P2SH,
+    P2WPKH,
+    P2WSH,
+    P2TR,
+    OpReturn,
+}
+
+impl ScriptType {
+    /// Returns the type of the scriptPubKey, or `None`
+    /// if the script does not match any of the supported templates.
+    pub fn from_script_pub_key(bytes: &[u8]) -> Option<ScriptType> {
+        match bytes {
+            [118, 169, 20, .., 136, 172] if bytes.len() == 25 => Some(ScriptType::P2PKH),
+            [169, 20, .., 135] if bytes.len() == 23 => Some(ScriptType::P2SH),
+            [0, 20, ..] if bytes.len() == 22 => Some(ScriptType::P2WPKH),
+            [0, 32, ..] if bytes.len() == 34 => Some(ScriptType::P2WSH),
+            [81, 32, ..] if bytes.len() == 34 => Some(ScriptType::P2TR),
+            [106, ..] => Some(ScriptType::OpReturn),
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for ScriptType {
@@ -253,6 +456,11 @@ impl FromStr for ScriptType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "p2pkh" => Ok(ScriptType::P2PKH),
+            "p2sh" => Ok(ScriptType::P2SH),
+            "v0_p2wpkh" => Ok(ScriptType::P2WPKH),
+            "v0_p2wsh" => Ok(ScriptType::P2WSH),
+            "v1_p2tr" => Ok(ScriptType::P2TR),
+            "op_return" => Ok(ScriptType::OpReturn),
             _ => Err(ParseScriptTypeError {}),
         }
     }
@@ -262,6 +470,141 @@ impl ToString for ScriptType {
     fn to_string(&self) -> String {
         match self {
             ScriptType::P2PKH => String::from("p2pkh"),
+            ScriptType::P2SH => String::from("p2sh"),
+            ScriptType::P2WPKH => String::from("v0_p2wpkh"),
+            ScriptType::P2WSH => String::from("v0_p2wsh"),
+            ScriptType::P2TR => String::from("v1_p2tr"),
+            ScriptType::OpReturn => String::from("op_return"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::transactions::script::{self, ScriptElement, ScriptType};
+    use crate::utils::hex;
+
+    #[test]
+    fn test_deserialize() {
+        let bytes =
+            hex::hex_to_bytes("76a914751e76e8199196d454941c45d1b3a323f1433bd688ac").unwrap();
+        let asm = script::deserialize(&bytes).unwrap();
+        assert_eq!(
+            asm,
+            script::p2pkh_script_pub_key("751e76e8199196d454941c45d1b3a323f1433bd6")
+        );
+        let mut serialized = vec![bytes.len() as u8];
+        serialized.extend(&bytes);
+        assert_eq!(script::serialize(&asm).unwrap(), serialized);
+
+        let bytes = hex::hex_to_bytes(
+            "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+        )
+        .unwrap();
+        assert_eq!(
+            script::deserialize(&bytes).unwrap(),
+            script::p2tr_script_pub_key(
+                "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+            )
+        );
+
+        // unknown opcode
+        assert_eq!(
+            script::deserialize(&[0xba, 0xff]).unwrap(),
+            "OP_CHECKSIGADD OP_UNKNOWN_255"
+        );
+        // push exceeds the end of the script
+        assert!(script::deserialize(&[0x6a, 0x04, 0x01, 0x02]).is_none());
+        assert!(script::deserialize(&[0x4d, 0x01]).is_none());
+    }
+
+    #[test]
+    fn test_parse_pushdata() {
+        let mut bytes = vec![0x6a, 0x4c, 80];
+        bytes.extend([0xab; 80]);
+        bytes.extend([0x4d, 0x00, 0x01]);
+        bytes.extend([0xcd; 256]);
+        let elements = script::parse(&bytes).unwrap();
+        assert_eq!(
+            elements,
+            vec![
+                ScriptElement::OpCode(0x6a),
+                ScriptElement::Data(vec![0xab; 80]),
+                ScriptElement::Data(vec![0xcd; 256]),
+            ]
+        );
+        let asm = script::deserialize(&bytes).unwrap();
+        assert!(asm.starts_with("OP_RETURN abab"));
+        assert_eq!(script::serialize(&asm).unwrap()[3..], bytes[..]);
+    }
+
+    #[test]
+    fn test_script_type() {
+        let script_pub_keys = [
+            (
+                "76a914751e76e8199196d454941c45d1b3a323f1433bd688ac",
+                Some(ScriptType::P2PKH),
+            ),
+            (
+                "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87",
+                Some(ScriptType::P2SH),
+            ),
+            (
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                Some(ScriptType::P2WPKH),
+            ),
+            (
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+                Some(ScriptType::P2WSH),
+            ),
+            (
+                "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+                Some(ScriptType::P2TR),
+            ),
+            ("6a0401020304", Some(ScriptType::OpReturn)),
+        ];
+        for (script_pub_key, script_type) in script_pub_keys {
+            let bytes = hex::hex_to_bytes(script_pub_key).unwrap();
+            assert_eq!(ScriptType::from_script_pub_key(&bytes), script_type);
+        }
+    }
+
+    #[test]
+    fn test_multisig_script() {
+        // test vector from BIP-67
+        let public_keys = vec![
+            hex::hex_to_bytes("02ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f8")
+                .unwrap(),
+            hex::hex_to_bytes("02fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f")
+                .unwrap(),
+        ];
+        let script = script::multisig_script(2, &public_keys).unwrap();
+        assert_eq!(
+            hex::bytes_to_hex(&script),
+            "522102fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f2102ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f852ae"
+        );
+        let (threshold, parsed_keys) = script::parse_multisig(&script).unwrap();
+        assert_eq!(threshold, 2);
+        assert_eq!(
+            parsed_keys,
+            vec![public_keys[1].clone(), public_keys[0].clone()]
+        );
+        assert!(script::multisig_script(3, &public_keys).is_none());
+        assert!(script::multisig_script(0, &public_keys).is_none());
+        assert!(script::parse_multisig(&script[..script.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn test_op_return_script() {
+        let script = script::op_return_script(b"hello").unwrap();
+        assert_eq!(hex::bytes_to_hex(&script), "6a0568656c6c6f");
+        assert_eq!(
+            ScriptType::from_script_pub_key(&script),
+            Some(ScriptType::OpReturn)
+        );
+        let script = script::op_return_script(&[0xab; 80]).unwrap();
+        assert_eq!(&script[..3], &[0x6a, 0x4c, 80]);
+        assert_eq!(script.len(), 83);
+        assert!(script::op_return_script(&[0xab; 81]).is_none());
+    }
+}
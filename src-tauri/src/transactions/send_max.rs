@@ -0,0 +1,153 @@
+//! Implements sending the entire balance of an account to a single address.
+//! All given UTXOs are spent without a change output. Instead of estimating
+//! the size of the transaction, the fee is computed from the signed transaction.
+use super::script::ScriptType;
+use super::transaction::{Transaction, DUST_LIMIT};
+use super::utxo::UTXOBox;
+use crate::coin_selection::{error::CoinSelectionError, fee_estimation, MAX_INPUTS_PER_TX};
+use crate::keys::address::Address;
+
+// a signature can be up to two bytes longer than the estimated signature
+static SIGNATURE_MARGIN: u64 = 2;
+
+/// Returns the signed transaction that spends all UTXOs to the target address.
+/// The fee is the virtual size of the signed transaction times the fee per byte.
+pub fn create_send_max_transaction(
+    boxed_utxos: &Vec<UTXOBox>,
+    target_address: &Address,
+    fee_per_byte: u64,
+    locktime: Option<u32>,
+    rbf: bool,
+) -> Result<Transaction, CoinSelectionError> {
+    if boxed_utxos.len() > MAX_INPUTS_PER_TX {
+        return Err(CoinSelectionError::new(
+            "max_input_count_exceeded".to_string(),
+        ));
+    }
+    let total: u64 = boxed_utxos.iter().map(|utxo_box| utxo_box.utxo.value).sum();
+    let mut fee = 0;
+    loop {
+        let amount = match total.checked_sub(fee) {
+            Some(amount) if amount >= DUST_LIMIT => amount,
+            _ => return Err(CoinSelectionError::new("balance_insufficient".to_string())),
+        };
+        let targets = vec![(target_address.clone(), amount)];
+        let mut tx = match Transaction::create_with_options(boxed_utxos, targets, None, locktime) {
+            Ok(tx) => tx,
+            Err(_) => return Err(CoinSelectionError::new("create_tx_error".to_string())),
+        };
+        if rbf {
+            tx.signal_rbf();
+        }
+        tx.sign_all_inputs(boxed_utxos);
+        // the length of a signature depends on the signed amount, so the
+        // transaction is signed again until the fee covers its size
+        let required_fee = tx.vsize() as u64 * fee_per_byte;
+        if required_fee <= fee {
+            tx.txid = tx.compute_txid();
+            return Ok(tx);
+        }
+        fee = required_fee;
+    }
+}
+
+/// Returns the amount that a transaction which spends all UTXOs to the target address
+/// sends at the fee per byte, i.e., the balance minus the fee. The UTXOs are given by
+/// their value and script type, so no keys are needed. The fee is estimated, and the fee
+/// for the longest signatures is reserved, so the amount is never more than
+/// `create_send_max_transaction` sends. Returns zero if the balance does not cover the fee.
+pub fn max_spendable_amount(
+    utxos: &[(u64, ScriptType)],
+    target_address: &Address,
+    fee_per_byte: u64,
+) -> Result<u64, CoinSelectionError> {
+    if utxos.len() > MAX_INPUTS_PER_TX {
+        return Err(CoinSelectionError::new(
+            "max_input_count_exceeded".to_string(),
+        ));
+    }
+    let total: u64 = utxos.iter().map(|(value, _)| value).sum();
+    let input_types: Vec<ScriptType> = utxos.iter().map(|(_, script_type)| *script_type).collect();
+    let fee = fee_estimation::estimate_fee_for_scripts(
+        &input_types,
+        &[target_address.script_type()],
+        fee_per_byte,
+    ) + utxos.len() as u64 * SIGNATURE_MARGIN * fee_per_byte;
+    match total.checked_sub(fee) {
+        Some(amount) if amount >= DUST_LIMIT => Ok(amount),
+        _ => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_send_max_transaction, max_spendable_amount};
+    use crate::coin_selection::MAX_INPUTS_PER_TX;
+    use crate::test_utils::boxed_utxos;
+    use crate::transactions::script::ScriptType;
+
+    #[test]
+    fn test_create_send_max_transaction() {
+        let boxed_utxos = boxed_utxos(&[50000, 20000, 10000]);
+        let target_address = boxed_utxos[0].keypair.public_key.get_address();
+        let tx = create_send_max_transaction(&boxed_utxos, &target_address, 10, Some(800000), true)
+            .unwrap();
+        assert_eq!(tx.vin.len(), 3);
+        assert!(tx.vin.iter().all(|tx_in| !tx_in.scriptsig_asm.is_empty()));
+        assert!(tx.signals_rbf());
+        assert_eq!(tx.locktime, 800000);
+        assert_eq!(tx.txid, tx.compute_txid());
+        // no change output
+        assert_eq!(tx.vout.len(), 1);
+        assert_eq!(tx.vout[0].scriptpubkey_address, target_address.to_string());
+        // the fee pays for the actual size, not the estimated size
+        let fee = 80000 - tx.vout[0].value;
+        let size = tx.serialize().len() as u64;
+        assert!(fee >= 10 * size);
+        assert!(fee <= 10 * (size + 3));
+    }
+
+    #[test]
+    fn test_create_send_max_transaction_errors() {
+        let boxed_utxos = boxed_utxos(&[5000]);
+        let target_address = boxed_utxos[0].keypair.public_key.get_address();
+        // the balance does not cover the fee
+        let err = create_send_max_transaction(&boxed_utxos, &target_address, 100, None, true)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "balance_insufficient");
+        let err =
+            create_send_max_transaction(&Vec::new(), &target_address, 1, None, true).unwrap_err();
+        assert_eq!(err.to_string(), "balance_insufficient");
+        let boxed_utxos = boxed_utxos
+            .iter()
+            .cycle()
+            .take(MAX_INPUTS_PER_TX + 1)
+            .cloned()
+            .collect();
+        let err =
+            create_send_max_transaction(&boxed_utxos, &target_address, 1, None, true).unwrap_err();
+        assert_eq!(err.to_string(), "max_input_count_exceeded");
+    }
+
+    #[test]
+    fn test_max_spendable_amount() {
+        let boxed_utxos = boxed_utxos(&[50000, 20000, 10000]);
+        let target_address = boxed_utxos[0].keypair.public_key.get_address();
+        let tx = create_send_max_transaction(&boxed_utxos, &target_address, 10, Some(800000), true)
+            .unwrap();
+        let utxos: Vec<(u64, ScriptType)> = boxed_utxos
+            .iter()
+            .map(|utxo_box| (utxo_box.utxo.value, ScriptType::P2PKH))
+            .collect();
+        let amount = max_spendable_amount(&utxos, &target_address, 10).unwrap();
+        assert!(amount <= tx.vout[0].value);
+        assert!(amount + 3 * 10 * 3 >= tx.vout[0].value);
+        assert_eq!(
+            max_spendable_amount(&utxos, &target_address, 1000).unwrap(),
+            0
+        );
+        assert_eq!(max_spendable_amount(&[], &target_address, 1).unwrap(), 0);
+        let utxos = vec![(10000, ScriptType::P2PKH); MAX_INPUTS_PER_TX + 1];
+        assert!(max_spendable_amount(&utxos, &target_address, 1).is_err());
+    }
+}
@@ -0,0 +1,22 @@
+//! Implements the request to send amounts from an account to a list of addresses.
+use serde::{Deserialize, Serialize};
+
+/// The transaction of `send_transaction` and `prepare_transaction`.
+/// Options that are not specified default to the settings.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SendRequest {
+    pub coin_type_index: u32,
+    pub account_index: u32,
+    pub targets: Vec<(String, u64)>, // (address, amount) pairs, in the order of the outputs
+    pub fee: u64,
+    pub rbf: Option<bool>,
+    pub locktime: Option<u32>,
+    pub data: Option<String>, // the data of an OP_RETURN output
+    pub data_hex: Option<bool>,
+    pub outpoints: Option<Vec<(String, u32)>>, // the UTXOs to spend, if selected manually
+    pub strategy: Option<String>,
+    pub min_confirmations: Option<u32>,
+    pub min_change_confirmations: Option<u32>,
+    pub memo: Option<String>,
+}
@@ -1,17 +1,35 @@
 use super::{
-    error::UnsupportedScriptError,
+    error::{InvalidTransactionError, ParseTransactionError, SigningError, UnsupportedScriptError},
     script::{self, ScriptType},
     utxo::UTXOBox,
 };
 use crate::{
-    keys::address::Address,
+    keys::{address::Address, bip32::ExtendedPrivateKey, taproot},
     utils::{hex, varint},
 };
-use bitcoin_hashes::{sha256, Hash};
+use bitcoin_hashes::{ripemd160, sha256, sha256d, Hash};
+use rand::{Rng, RngCore};
 use secp256k1::{Message, Secp256k1, SecretKey};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-static SIGHASH_ALL: u32 = 1;
+static SIGHASH_DEFAULT: u32 = 0x00; // Taproot only, signs everything like SIGHASH_ALL
+static SIGHASH_ALL: u32 = 0x01;
+static SIGHASH_NONE: u32 = 0x02;
+static SIGHASH_SINGLE: u32 = 0x03;
+static SIGHASH_ANYONECANPAY: u32 = 0x80;
+static ANNEX_TAG: u8 = 0x50;
+static DEFAULT_VERSION: u32 = 1;
+pub static DUST_LIMIT: u64 = 546; // the smallest P2PKH output that is relayed
+static SEQUENCE_FINAL: u32 = 0xffffffff;
+static SEQUENCE_ENABLE_LOCKTIME: u32 = 0xfffffffe; // enables the locktime without signaling RBF
+static SEQUENCE_RBF: u32 = 0xfffffffd; // the highest sequence number that signals RBF (BIP-125)
+static SEGWIT_MARKER: u8 = 0x00;
+static SEGWIT_FLAG: u8 = 0x01;
+static COINBASE_VOUT: u32 = 0xffffffff;
+static WITNESS_SCALE_FACTOR: u32 = 4;
+static MAX_MONEY: u64 = 21000000 * 100000000; // in satoshis
+static MAX_STANDARD_TX_WEIGHT: u32 = 400000; // the largest transaction that is relayed
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Transaction {
@@ -46,7 +64,7 @@ pub struct TransactionInput {
     pub sequence: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct TransactionOutput {
     pub scriptpubkey: String,
     pub scriptpubkey_asm: String,
@@ -57,10 +75,39 @@ pub struct TransactionOutput {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SimplifiedTransaction {
+    pub txid: String,
     pub transaction_type: TransactionType,
     pub value: u64,
     pub fee: u64,
     pub confirmed: bool,
+    pub memo: Option<String>, // set by the wallet, not part of the transaction
+    #[serde(default)]
+    pub block_time: Option<u64>, // UNIX timestamp in seconds, if the transaction is confirmed
+    #[serde(default)]
+    pub fiat_value: Option<f64>, // set by the wallet at the block time, if it is requested
+    #[serde(default)]
+    pub counterparties: Vec<String>, // the senders of incoming and recipients of outgoing transactions
+    #[serde(default)]
+    pub contact: Option<String>, // set by the wallet, the name of the counterparty's contact
+    #[serde(default)]
+    pub block_height: Option<u64>,
+    #[serde(default)]
+    pub confirmations: Option<u32>, // set by the wallet against the most recent block
+    #[serde(default)]
+    pub paths: Vec<String>, // the derivation paths of the addresses of the wallet that are affected
+}
+
+impl SimplifiedTransaction {
+    /// Sets the number of confirmations from the height of the most recent block.
+    /// Unconfirmed transactions have no confirmations. The number remains unknown for
+    /// a confirmed transaction without a block height, e.g., from an older cache.
+    pub fn set_confirmations(&mut self, tip_height: u32) {
+        self.confirmations = match self.block_height {
+            _ if !self.confirmed => Some(0),
+            Some(block_height) => Some((tip_height as u64 + 1).saturating_sub(block_height) as u32),
+            None => None,
+        };
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -70,25 +117,112 @@ pub enum TransactionType {
     Internal,
 }
 
+/// The signature hash type selects the parts of the transaction that a signature commits to.
+/// SIGHASH_NONE signs no output, SIGHASH_SINGLE signs the output with the index of the input
+/// and ANYONECANPAY signs only the input itself, so other inputs can be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SighashType {
+    Default, // SIGHASH_DEFAULT for Taproot inputs, SIGHASH_ALL otherwise
+    All,
+    None,
+    Single,
+    AllAnyoneCanPay,
+    NoneAnyoneCanPay,
+    SingleAnyoneCanPay,
+}
+
+impl SighashType {
+    /// Returns the signature hash type with the specified value.
+    pub fn from_u32(value: u32) -> Option<SighashType> {
+        [
+            SighashType::Default,
+            SighashType::All,
+            SighashType::None,
+            SighashType::Single,
+            SighashType::AllAnyoneCanPay,
+            SighashType::NoneAnyoneCanPay,
+            SighashType::SingleAnyoneCanPay,
+        ]
+        .into_iter()
+        .find(|sighash_type| sighash_type.to_u32() == value)
+    }
+
+    /// Returns the value of the signature hash type.
+    pub fn to_u32(self) -> u32 {
+        match self {
+            SighashType::Default => SIGHASH_DEFAULT,
+            SighashType::All => SIGHASH_ALL,
+            SighashType::None => SIGHASH_NONE,
+            SighashType::Single => SIGHASH_SINGLE,
+            SighashType::AllAnyoneCanPay => SIGHASH_ALL | SIGHASH_ANYONECANPAY,
+            SighashType::NoneAnyoneCanPay => SIGHASH_NONE | SIGHASH_ANYONECANPAY,
+            SighashType::SingleAnyoneCanPay => SIGHASH_SINGLE | SIGHASH_ANYONECANPAY,
+        }
+    }
+
+    /// Returns the value of the signature hash type of an ECDSA signature,
+    /// which has no SIGHASH_DEFAULT.
+    fn ecdsa(self) -> u32 {
+        match self {
+            SighashType::Default => SIGHASH_ALL,
+            sighash_type => sighash_type.to_u32(),
+        }
+    }
+
+    fn is_none(self) -> bool {
+        self.to_u32() & !SIGHASH_ANYONECANPAY == SIGHASH_NONE
+    }
+
+    fn is_single(self) -> bool {
+        self.to_u32() & !SIGHASH_ANYONECANPAY == SIGHASH_SINGLE
+    }
+
+    fn is_anyone_can_pay(self) -> bool {
+        self.to_u32() & SIGHASH_ANYONECANPAY != 0
+    }
+}
+
 impl Transaction {
     /// Creates a transaction.
     pub fn create(
         boxed_utxos: &Vec<UTXOBox>,
         targets: Vec<(Address, u64)>,
     ) -> Result<Transaction, UnsupportedScriptError> {
+        Transaction::create_with_options(boxed_utxos, targets, None, None)
+    }
+
+    /// Creates a transaction with the specified version and locktime.
+    /// If a locktime is specified, the sequence numbers of the inputs are set,
+    /// such that the locktime is enforced.
+    pub fn create_with_options(
+        boxed_utxos: &[UTXOBox],
+        targets: Vec<(Address, u64)>,
+        version: Option<u32>,
+        locktime: Option<u32>,
+    ) -> Result<Transaction, UnsupportedScriptError> {
+        let sequence = match locktime {
+            Some(locktime) if locktime > 0 => SEQUENCE_ENABLE_LOCKTIME,
+            _ => SEQUENCE_FINAL,
+        };
         let tx_ins: Result<Vec<TransactionInput>, UnsupportedScriptError> = boxed_utxos
             .iter()
             .map(|utxo| TransactionInput::create(&utxo))
+            .map(|tx_in| {
+                tx_in.map(|mut tx_in| {
+                    tx_in.sequence = sequence;
+                    tx_in
+                })
+            })
             .collect();
         let tx_outs: Vec<TransactionOutput> = targets
             .iter()
-            .map(|(address, amount)| TransactionOutput::create(address, *amount, ScriptType::P2PKH))
+            .map(|(address, amount)| TransactionOutput::create(address, *amount))
             .collect();
 
         Ok(Transaction {
             txid: String::from(""),
-            version: 1,
-            locktime: 0,
+            version: version.unwrap_or(DEFAULT_VERSION),
+            locktime: locktime.unwrap_or(0),
             vin: tx_ins?,
             vout: tx_outs,
             size: None,
@@ -98,57 +232,373 @@ impl Transaction {
         })
     }
 
-    /// Signs all transaction inputs.
-    pub fn sign_all_inputs(&mut self, boxed_utxos: &Vec<UTXOBox>) {
-        boxed_utxos
+    /// Creates a transaction whose inputs have no script signatures, e.g., to spend
+    /// multisig outputs, which are signed by the cosigners through a PSBT.
+    pub fn create_unsigned(boxed_utxos: &[UTXOBox], targets: Vec<(Address, u64)>) -> Transaction {
+        let tx_ins = boxed_utxos
             .iter()
-            .enumerate()
-            .for_each(|(index, utxo)| self.sign_input(index, utxo));
+            .map(|utxo_box| TransactionInput {
+                txid: utxo_box.utxo.txid.clone(),
+                vout: utxo_box.utxo.vout,
+                prevout: utxo_box.output.clone(),
+                scriptsig: String::from(""),
+                scriptsig_asm: String::from(""),
+                witness: None,
+                is_coinbase: false,
+                sequence: SEQUENCE_FINAL,
+            })
+            .collect();
+        let tx_outs = targets
+            .iter()
+            .map(|(address, amount)| TransactionOutput::create(address, *amount))
+            .collect();
+        Transaction {
+            txid: String::from(""),
+            version: DEFAULT_VERSION,
+            locktime: 0,
+            vin: tx_ins,
+            vout: tx_outs,
+            size: None,
+            weight: None,
+            fee: None,
+            status: None,
+        }
+    }
+
+    /// Sets the sequence numbers of all inputs, such that the transaction
+    /// signals that it can be replaced (BIP-125). The locktime remains enforced.
+    /// Must be called before signing.
+    pub fn signal_rbf(&mut self) {
+        self.vin
+            .iter_mut()
+            .for_each(|tx_in| tx_in.sequence = SEQUENCE_RBF);
     }
 
-    /// Signs the transaction input with the specified index.
-    pub fn sign_input(&mut self, input_index: usize, utxo_box: &UTXOBox) {
-        let z = self.signature_hash(input_index, &utxo_box.output);
+    /// Returns true if any input signals that the transaction can be replaced.
+    pub fn signals_rbf(&self) -> bool {
+        self.vin.iter().any(|tx_in| tx_in.sequence <= SEQUENCE_RBF)
+    }
+
+    /// Signs all transaction inputs with the default signature hash type.
+    pub fn sign_all_inputs(&mut self, boxed_utxos: &Vec<UTXOBox>) {
+        boxed_utxos.iter().enumerate().for_each(|(index, utxo)| {
+            // the default signature hash type commits to all outputs and cannot fail
+            self.sign_input(index, utxo, SighashType::Default).unwrap()
+        });
+    }
+
+    /// Signs the transaction input with the specified index and signature hash type.
+    /// The script signature and the witness are built for the script type of the output
+    /// that the input spends, so a transaction can combine legacy and SegWit inputs.
+    /// The signature of a legacy input is placed in the script signature and its witness
+    /// is left empty. The signature of a SegWit input is placed in the witness.
+    pub fn sign_input(
+        &mut self,
+        input_index: usize,
+        utxo_box: &UTXOBox,
+        sighash_type: SighashType,
+    ) -> Result<(), SigningError> {
+        let signature = self.create_signature(input_index, utxo_box, sighash_type)?;
+        let signature = hex::bytes_to_hex(&signature);
+        let pubkey = hex::bytes_to_hex(&utxo_box.keypair.public_key.key_data);
+        let tx_in = self.vin.get_mut(input_index).unwrap();
+        match ScriptType::from_str(&utxo_box.output.scriptpubkey_type) {
+            Ok(ScriptType::P2TR) => {
+                let mut witness = vec![signature];
+                if let Some(annex) = tx_in.annex() {
+                    witness.push(hex::bytes_to_hex(&annex));
+                }
+                tx_in.witness = Some(witness);
+            }
+            // the script signature of a P2SH-P2WPKH input pushes the redeem script
+            Ok(ScriptType::P2WPKH) | Ok(ScriptType::P2SH) => {
+                tx_in.witness = Some(vec![signature, pubkey]);
+            }
+            _ => {
+                tx_in.scriptsig = String::from("");
+                tx_in.scriptsig_asm = script::p2pkh_script_sig(&signature, &pubkey);
+                tx_in.witness = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the signature of the transaction input with the specified index.
+    /// ECDSA signatures are DER encoded and followed by the signature hash type,
+    /// Schnorr signatures omit it for SIGHASH_DEFAULT.
+    pub fn create_signature(
+        &mut self,
+        input_index: usize,
+        utxo_box: &UTXOBox,
+        sighash_type: SighashType,
+    ) -> Result<Vec<u8>, SigningError> {
+        let z = self.signature_hash(input_index, &utxo_box.output, sighash_type)?;
         let msg = Message::from_slice(&z).unwrap();
         let secp = Secp256k1::new();
+        if utxo_box.output.scriptpubkey_type == script::P2TR_TYPE {
+            let keypair = taproot::tweaked_keypair(&utxo_box.keypair.private_key);
+            let mut aux_rand = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut aux_rand);
+            let mut sig = secp
+                .sign_schnorr_with_aux_rand(&msg, &keypair, &aux_rand)
+                .as_ref()
+                .to_vec();
+            if sighash_type != SighashType::Default {
+                sig.push(sighash_type.to_u32() as u8);
+            }
+            return Ok(sig);
+        }
         let private_key = SecretKey::from_slice(&utxo_box.keypair.private_key.key_data).unwrap();
         let mut sig_der = secp.sign_ecdsa(&msg, &private_key).serialize_der().to_vec();
-        sig_der.extend(&SIGHASH_ALL.to_be_bytes()[3..]);
-        let pubkey_sec = &utxo_box.keypair.public_key.key_data;
-        let script_sig = script::p2pkh_script_sig(
-            &hex::bytes_to_hex(&sig_der),
-            &hex::bytes_to_hex(&pubkey_sec),
-        );
-        let tx_in = self.vin.get_mut(input_index).unwrap();
-        tx_in.scriptsig_asm = script_sig;
+        sig_der.push(sighash_type.ecdsa() as u8);
+        Ok(sig_der)
+    }
+
+    /// Returns the signature of a SegWit input that spends a P2WSH or P2SH-P2WSH output
+    /// with the specified witness script, e.g., a multisig script.
+    pub fn create_witness_script_signature(
+        &self,
+        input_index: usize,
+        witness_script: &[u8],
+        value: u64,
+        private_key: &ExtendedPrivateKey,
+        sighash_type: SighashType,
+    ) -> Result<Vec<u8>, SigningError> {
+        // the script code is the witness script (BIP-143)
+        let script_code = script::deserialize(witness_script).unwrap_or_default();
+        let z = self.signature_hash_bip143(input_index, &script_code, value, sighash_type)?;
+        let msg = Message::from_slice(&z).unwrap();
+        let secp = Secp256k1::new();
+        let private_key = SecretKey::from_slice(&private_key.key_data).unwrap();
+        let mut sig_der = secp.sign_ecdsa(&msg, &private_key).serialize_der().to_vec();
+        sig_der.push(sighash_type.ecdsa() as u8);
+        Ok(sig_der)
     }
 
     /// Returns the signature hash for signing the input with the specified index.
-    pub fn signature_hash(&mut self, input_index: usize, output: &TransactionOutput) -> [u8; 32] {
+    /// The algorithm is selected by the script type of the spent output.
+    pub fn signature_hash(
+        &mut self,
+        input_index: usize,
+        output: &TransactionOutput,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], SigningError> {
+        if output.scriptpubkey_type == script::P2TR_TYPE {
+            self.signature_hash_bip341(input_index, sighash_type)
+        } else if is_segwit(output) {
+            let script_code = self.vin[input_index].script_code();
+            self.signature_hash_bip143(input_index, &script_code, output.value, sighash_type)
+        } else {
+            self.signature_hash_legacy(input_index, output, sighash_type)
+        }
+    }
+
+    /// Returns an error if the signature hash type is SIGHASH_SINGLE and the transaction
+    /// has no output with the index of the input. Such a signature would commit to no
+    /// output at all (legacy inputs even sign the constant one), so it is never created.
+    fn check_sighash_single(
+        &self,
+        input_index: usize,
+        sighash_type: SighashType,
+    ) -> Result<(), SigningError> {
+        if sighash_type.is_single() && input_index >= self.vout.len() {
+            return Err(SigningError::new("sighash_single_error"));
+        }
+        Ok(())
+    }
+
+    /// Returns the signature hash of a SegWit version 0 input as specified in BIP-143.
+    pub fn signature_hash_bip143(
+        &self,
+        input_index: usize,
+        script_code: &str,
+        value: u64,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], SigningError> {
+        self.check_sighash_single(input_index, sighash_type)?;
+        let mut prevouts = Vec::new();
+        let mut sequences = Vec::new();
+        self.vin.iter().for_each(|tx_in| {
+            prevouts.extend(tx_in.serialize_outpoint());
+            sequences.extend(&tx_in.sequence.to_le_bytes()[..4]);
+        });
+        let mut outputs = Vec::new();
+        if sighash_type.is_single() {
+            outputs.extend(&self.vout[input_index].serialize());
+        } else if !sighash_type.is_none() {
+            self.vout
+                .iter()
+                .for_each(|tx_out| outputs.extend(&tx_out.serialize()));
+        }
+        // the hashes of the parts that are not signed are zero
+        let hash_or_zero = |signed: bool, bytes: &[u8]| match signed {
+            true => sha256d::Hash::hash(bytes).into_inner(),
+            false => [0u8; 32],
+        };
+        let anyone_can_pay = sighash_type.is_anyone_can_pay();
+        let signs_sequences =
+            !anyone_can_pay && !sighash_type.is_single() && !sighash_type.is_none();
+
+        let tx_in = &self.vin[input_index];
         let mut bytes = Vec::new();
         bytes.extend(&self.version.to_le_bytes()[..4]);
-        bytes.extend(varint::encode(self.vin.len() as u64));
-        self.vin.iter().enumerate().for_each(|(index, tx_in)| {
-            let mut tx_in_clone = tx_in.clone();
-            if index == input_index {
-                tx_in_clone.scriptsig_asm = output.scriptpubkey_asm.clone();
-            } else {
-                tx_in_clone.scriptsig_asm = String::from("");
-            }
-            bytes.extend(&tx_in_clone.serialize());
+        bytes.extend(hash_or_zero(!anyone_can_pay, &prevouts));
+        bytes.extend(hash_or_zero(signs_sequences, &sequences));
+        bytes.extend(tx_in.serialize_outpoint());
+        bytes.extend(script::serialize(script_code).unwrap());
+        bytes.extend(&value.to_le_bytes()[..8]);
+        bytes.extend(&tx_in.sequence.to_le_bytes()[..4]);
+        bytes.extend(hash_or_zero(!sighash_type.is_none(), &outputs));
+        bytes.extend(&self.locktime.to_le_bytes()[..4]);
+        bytes.extend(&sighash_type.ecdsa().to_le_bytes()[..4]);
+        Ok(sha256d::Hash::hash(&bytes).into_inner())
+    }
+
+    /// Returns the signature hash of a Taproot key path spend as specified in BIP-341.
+    /// The annex of the input is committed to if there is one.
+    pub fn signature_hash_bip341(
+        &self,
+        input_index: usize,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], SigningError> {
+        self.check_sighash_single(input_index, sighash_type)?;
+        let mut prevouts = Vec::new();
+        let mut amounts = Vec::new();
+        let mut script_pubkeys = Vec::new();
+        let mut sequences = Vec::new();
+        self.vin.iter().for_each(|tx_in| {
+            prevouts.extend(tx_in.serialize_outpoint());
+            amounts.extend(&tx_in.prevout.value.to_le_bytes()[..8]);
+            script_pubkeys.extend(tx_in.prevout.serialize_script_pubkey());
+            sequences.extend(&tx_in.sequence.to_le_bytes()[..4]);
         });
-        bytes.extend(varint::encode(self.vout.len() as u64));
+        let mut outputs = Vec::new();
         self.vout
             .iter()
-            .for_each(|tx_out| bytes.extend(&tx_out.serialize()));
+            .for_each(|tx_out| outputs.extend(&tx_out.serialize()));
+        let tx_in = &self.vin[input_index];
+        let annex = tx_in.annex();
+
+        let mut bytes = vec![0x00]; // epoch
+        bytes.push(sighash_type.to_u32() as u8);
+        bytes.extend(&self.version.to_le_bytes()[..4]);
         bytes.extend(&self.locktime.to_le_bytes()[..4]);
-        bytes.extend(&SIGHASH_ALL.to_le_bytes()[..4]);
+        if !sighash_type.is_anyone_can_pay() {
+            bytes.extend(sha256::Hash::hash(&prevouts).into_inner());
+            bytes.extend(sha256::Hash::hash(&amounts).into_inner());
+            bytes.extend(sha256::Hash::hash(&script_pubkeys).into_inner());
+            bytes.extend(sha256::Hash::hash(&sequences).into_inner());
+        }
+        if !sighash_type.is_single() && !sighash_type.is_none() {
+            bytes.extend(sha256::Hash::hash(&outputs).into_inner());
+        }
+        // the spend type of a key path spend only signals the presence of the annex
+        bytes.push(annex.is_some() as u8);
+        if sighash_type.is_anyone_can_pay() {
+            bytes.extend(tx_in.serialize_outpoint());
+            bytes.extend(&tx_in.prevout.value.to_le_bytes()[..8]);
+            bytes.extend(tx_in.prevout.serialize_script_pubkey());
+            bytes.extend(&tx_in.sequence.to_le_bytes()[..4]);
+        } else {
+            bytes.extend(&(input_index as u32).to_le_bytes()[..4]);
+        }
+        if let Some(annex) = annex {
+            let mut annex_bytes = varint::encode(annex.len() as u64);
+            annex_bytes.extend(annex);
+            bytes.extend(sha256::Hash::hash(&annex_bytes).into_inner());
+        }
+        if sighash_type.is_single() {
+            let output = self.vout[input_index].serialize();
+            bytes.extend(sha256::Hash::hash(&output).into_inner());
+        }
+        Ok(taproot::tagged_hash(taproot::TAP_SIGHASH_TAG, &bytes))
+    }
+
+    /// Returns the legacy signature hash for signing the input with the specified index.
+    /// With SIGHASH_NONE and SIGHASH_SINGLE, the sequence numbers of the other inputs
+    /// are not signed, with ANYONECANPAY, the other inputs are omitted.
+    fn signature_hash_legacy(
+        &mut self,
+        input_index: usize,
+        output: &TransactionOutput,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], SigningError> {
+        self.check_sighash_single(input_index, sighash_type)?;
+        let signs_sequences = !sighash_type.is_single() && !sighash_type.is_none();
+        let mut bytes = Vec::new();
+        bytes.extend(&self.version.to_le_bytes()[..4]);
+        let tx_ins: Vec<TransactionInput> = self
+            .vin
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !sighash_type.is_anyone_can_pay() || *index == input_index)
+            .map(|(index, tx_in)| {
+                let mut tx_in_clone = tx_in.clone();
+                tx_in_clone.scriptsig = String::from("");
+                if index == input_index {
+                    tx_in_clone.scriptsig_asm = output.scriptpubkey_asm.clone();
+                } else {
+                    tx_in_clone.scriptsig_asm = String::from("");
+                    if !signs_sequences {
+                        tx_in_clone.sequence = 0;
+                    }
+                }
+                tx_in_clone
+            })
+            .collect();
+        bytes.extend(varint::encode(tx_ins.len() as u64));
+        tx_ins
+            .iter()
+            .for_each(|tx_in| bytes.extend(&tx_in.serialize()));
+        if sighash_type.is_none() {
+            bytes.extend(varint::encode(0));
+        } else if sighash_type.is_single() {
+            // the outputs before the signed output are blank, i.e., their value is -1
+            bytes.extend(varint::encode(input_index as u64 + 1));
+            (0..input_index).for_each(|_| {
+                bytes.extend(&u64::MAX.to_le_bytes()[..8]);
+                bytes.extend(varint::encode(0));
+            });
+            bytes.extend(&self.vout[input_index].serialize());
+        } else {
+            bytes.extend(varint::encode(self.vout.len() as u64));
+            self.vout
+                .iter()
+                .for_each(|tx_out| bytes.extend(&tx_out.serialize()));
+        }
+        bytes.extend(&self.locktime.to_le_bytes()[..4]);
+        bytes.extend(&sighash_type.ecdsa().to_le_bytes()[..4]);
         let hash = sha256::Hash::hash(&bytes);
-        sha256::Hash::hash(&hash).into_inner()
+        Ok(sha256::Hash::hash(&hash).into_inner())
     }
 
     /// Returns the serialized transaction.
+    /// The witnesses are included if any input has one.
     pub fn serialize(&self) -> Vec<u8> {
+        if self.vin.iter().all(|tx_in| tx_in.witness.is_none()) {
+            return self.serialize_without_witness();
+        }
+        let mut bytes = Vec::new();
+        bytes.extend(&self.version.to_le_bytes()[..4]);
+        bytes.push(SEGWIT_MARKER);
+        bytes.push(SEGWIT_FLAG);
+        bytes.extend(varint::encode(self.vin.len() as u64));
+        self.vin
+            .iter()
+            .for_each(|tx_in| bytes.extend(&tx_in.serialize()));
+        bytes.extend(varint::encode(self.vout.len() as u64));
+        self.vout
+            .iter()
+            .for_each(|tx_out| bytes.extend(&tx_out.serialize()));
+        self.vin
+            .iter()
+            .for_each(|tx_in| bytes.extend(&tx_in.serialize_witness()));
+        bytes.extend(&self.locktime.to_le_bytes()[..4]);
+        bytes
+    }
+
+    /// Returns the serialized transaction without the witnesses.
+    pub fn serialize_without_witness(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.extend(&self.version.to_le_bytes()[..4]);
         bytes.extend(varint::encode(self.vin.len() as u64));
@@ -163,35 +613,224 @@ impl Transaction {
         bytes
     }
 
+    /// Checks a transaction that was signed elsewhere before it is broadcast, so obvious
+    /// mistakes are reported locally instead of as a rejection of the backend.
+    /// The transaction must spend and create outputs, every input must be signed
+    /// and spent only once, and the outputs must be valid amounts above the dust limit,
+    /// except for OP_RETURN outputs. Transactions that are too large are not relayed.
+    pub fn check_sanity(&self) -> Result<(), InvalidTransactionError> {
+        if self.vin.is_empty() || self.vout.is_empty() {
+            return Err(InvalidTransactionError::new("empty_tx_error"));
+        }
+        let mut outpoints: Vec<(&str, u32)> = Vec::with_capacity(self.vin.len());
+        for tx_in in &self.vin {
+            if tx_in.is_coinbase || tx_in.vout == COINBASE_VOUT {
+                return Err(InvalidTransactionError::new("invalid_tx_error"));
+            }
+            if tx_in.scriptsig.is_empty() && tx_in.witness.is_none() {
+                return Err(InvalidTransactionError::new("unsigned_tx_error"));
+            }
+            outpoints.push((&tx_in.txid, tx_in.vout));
+        }
+        outpoints.sort();
+        if outpoints.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(InvalidTransactionError::new("duplicate_input_error"));
+        }
+        let mut total: u64 = 0;
+        for tx_out in &self.vout {
+            total = total.saturating_add(tx_out.value);
+            if tx_out.value > MAX_MONEY || total > MAX_MONEY {
+                return Err(InvalidTransactionError::new("invalid_tx_error"));
+            }
+            if tx_out.value < DUST_LIMIT && tx_out.scriptpubkey_type != script::OP_RETURN_TYPE {
+                return Err(InvalidTransactionError::new("dust_output_error"));
+            }
+        }
+        if self.vsize() * WITNESS_SCALE_FACTOR > MAX_STANDARD_TX_WEIGHT {
+            return Err(InvalidTransactionError::new("tx_too_large_error"));
+        }
+        Ok(())
+    }
+
     /// Returns the serialized transaction in hex format.
     pub fn serialize_hex(&self) -> String {
         hex::bytes_to_hex(&self.serialize())
     }
+
+    /// Returns the virtual size of the transaction, i.e., its weight divided by four
+    /// and rounded up. The size of a transaction without witnesses equals its vsize.
+    pub fn vsize(&self) -> u32 {
+        let base_size = self.serialize_without_witness().len() as u32;
+        let weight = base_size * (WITNESS_SCALE_FACTOR - 1) + self.serialize().len() as u32;
+        weight.div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    /// Computes the transaction ID from the serialized transaction without the witnesses.
+    pub fn compute_txid(&self) -> String {
+        let hash = sha256::Hash::hash(&self.serialize_without_witness());
+        let hash = sha256::Hash::hash(&hash);
+        let txid_bytes: Vec<u8> = hash.into_inner().into_iter().rev().collect();
+        hex::bytes_to_hex(&txid_bytes)
+    }
+
+    /// Parses a serialized transaction in hex format.
+    pub fn from_hex(s: &str, testnet: bool) -> Result<Transaction, ParseTransactionError> {
+        match hex::hex_to_bytes(s) {
+            Some(bytes) => Transaction::from_bytes(&bytes, testnet),
+            None => Err(ParseTransactionError::new("Invalid hex")),
+        }
+    }
+
+    /// Parses a serialized transaction with or without witnesses.
+    /// The spent outputs are not part of the serialization, so the prevouts
+    /// of the inputs are empty. The network determines the output addresses.
+    pub fn from_bytes(bytes: &[u8], testnet: bool) -> Result<Transaction, ParseTransactionError> {
+        let mut reader = Reader::new(bytes);
+        let version = reader.read_u32()?;
+        let segwit = reader.peek(2) == Some(&[SEGWIT_MARKER, SEGWIT_FLAG]);
+        if segwit {
+            reader.read(2)?;
+        }
+        let mut vin = Vec::new();
+        for _ in 0..reader.read_varint()? {
+            vin.push(TransactionInput::read(&mut reader)?);
+        }
+        let mut vout = Vec::new();
+        for _ in 0..reader.read_varint()? {
+            vout.push(TransactionOutput::read(&mut reader, testnet)?);
+        }
+        if segwit {
+            for tx_in in vin.iter_mut() {
+                let mut items = Vec::new();
+                for _ in 0..reader.read_varint()? {
+                    let len = reader.read_varint()? as usize;
+                    items.push(hex::bytes_to_hex(reader.read(len)?));
+                }
+                if !items.is_empty() {
+                    tx_in.witness = Some(items);
+                }
+            }
+            // the serialization would not contain the marker and flag
+            if vin.iter().all(|tx_in| tx_in.witness.is_none()) {
+                return Err(ParseTransactionError::new("Superfluous witness"));
+            }
+        }
+        let locktime = reader.read_u32()?;
+        if !reader.is_empty() {
+            return Err(ParseTransactionError::new("Trailing data"));
+        }
+        let mut tx = Transaction {
+            txid: String::from(""),
+            version,
+            locktime,
+            vin,
+            vout,
+            size: Some(bytes.len() as u32),
+            weight: None,
+            fee: None,
+            status: None,
+        };
+        tx.txid = tx.compute_txid();
+        let base_size = tx.serialize_without_witness().len() as u32;
+        tx.weight = Some(base_size * (WITNESS_SCALE_FACTOR - 1) + bytes.len() as u32);
+        Ok(tx)
+    }
 }
 
 impl TransactionInput {
     /// Creates a transaction input.
+    /// P2SH outputs are only supported if they wrap the P2WPKH output of the key pair
+    /// and P2TR outputs only if their output key is the tweaked key of the key pair.
     pub fn create(utxo_box: &UTXOBox) -> Result<TransactionInput, UnsupportedScriptError> {
-        if utxo_box.output.scriptpubkey_type == "p2pkh" {
-            Ok(TransactionInput {
-                txid: utxo_box.utxo.txid.clone(),
-                vout: utxo_box.utxo.vout,
-                prevout: utxo_box.output.clone(),
-                scriptsig: String::from(""),
-                scriptsig_asm: String::from(""),
-                witness: None,
-                is_coinbase: false,
-                sequence: 0xffffffff,
-            })
+        let scriptpubkey_type = &utxo_box.output.scriptpubkey_type;
+        let scriptsig_asm = if scriptpubkey_type == script::P2PKH_TYPE
+            || scriptpubkey_type == script::P2WPKH_TYPE
+        {
+            String::from("")
+        } else if scriptpubkey_type == script::P2TR_TYPE {
+            let output_key = taproot::output_key(&utxo_box.keypair.private_key);
+            let script_output_key = script::pushed_data(&utxo_box.output.scriptpubkey_asm);
+            if script_output_key != Some(&hex::bytes_to_hex(&output_key.serialize())) {
+                return Err(UnsupportedScriptError::new(scriptpubkey_type.clone()));
+            }
+            String::from("")
+        } else if scriptpubkey_type == script::P2SH_TYPE {
+            // the redeem script is pushed by the script signature
+            let pubkey_hash = utxo_box.keypair.public_key.get_address().get_hash();
+            let redeem_script = script::p2sh_p2wpkh_redeem_script(&pubkey_hash);
+            let redeem_script_hash = ripemd160::Hash::hash(&sha256::Hash::hash(
+                &hex::hex_to_bytes(&redeem_script).unwrap(),
+            ));
+            let script_hash = script::pushed_data(&utxo_box.output.scriptpubkey_asm);
+            if script_hash != Some(&hex::bytes_to_hex(&redeem_script_hash)) {
+                return Err(UnsupportedScriptError::new(scriptpubkey_type.clone()));
+            }
+            redeem_script
         } else {
-            Err(UnsupportedScriptError::new(
-                utxo_box.output.scriptpubkey_type.clone(),
-            ))
-        }
+            return Err(UnsupportedScriptError::new(scriptpubkey_type.clone()));
+        };
+        Ok(TransactionInput {
+            txid: utxo_box.utxo.txid.clone(),
+            vout: utxo_box.utxo.vout,
+            prevout: utxo_box.output.clone(),
+            scriptsig: String::from(""),
+            scriptsig_asm,
+            witness: None,
+            is_coinbase: false,
+            sequence: SEQUENCE_FINAL,
+        })
+    }
+
+    /// Reads a transaction input without its witness.
+    fn read(reader: &mut Reader) -> Result<TransactionInput, ParseTransactionError> {
+        let txid_bytes: Vec<u8> = reader.read(32)?.iter().rev().cloned().collect();
+        let vout = reader.read_u32()?;
+        let script_sig = reader.read_script()?;
+        let sequence = reader.read_u32()?;
+        let is_coinbase = vout == COINBASE_VOUT && txid_bytes.iter().all(|byte| *byte == 0);
+        let scriptsig_asm = match script::deserialize(script_sig) {
+            Some(asm) => asm,
+            None if is_coinbase => hex::bytes_to_hex(script_sig),
+            None => return Err(ParseTransactionError::new("Invalid script")),
+        };
+        Ok(TransactionInput {
+            txid: hex::bytes_to_hex(&txid_bytes),
+            vout,
+            prevout: TransactionOutput::default(),
+            scriptsig: hex::bytes_to_hex(script_sig),
+            scriptsig_asm,
+            witness: None,
+            is_coinbase,
+            sequence,
+        })
     }
 
     /// Returns the serialized transaction input.
     pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = self.serialize_outpoint();
+        let script_sig = self.script_sig_bytes();
+        bytes.extend(varint::encode(script_sig.len() as u64));
+        bytes.extend(script_sig);
+        bytes.extend(&self.sequence.to_le_bytes()[..4]);
+        bytes
+    }
+
+    /// Returns the scriptSig without the length prefix.
+    /// The hex format is used if it is known, because the backend's assembly
+    /// may contain words that cannot be serialized.
+    fn script_sig_bytes(&self) -> Vec<u8> {
+        if let Some(bytes) = hex::hex_to_bytes(&self.scriptsig) {
+            if !bytes.is_empty() {
+                return bytes;
+            }
+        }
+        let bytes = script::serialize(&self.scriptsig_asm).unwrap();
+        let (_, prefix_length) = varint::decode(&bytes).unwrap();
+        bytes[prefix_length..].to_vec()
+    }
+
+    /// Returns the serialized outpoint, i.e., the transaction ID and the output index.
+    fn serialize_outpoint(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         let txid_le_bytes: Vec<u8> = hex::hex_to_bytes(&self.txid)
             .unwrap()
@@ -200,51 +839,243 @@ impl TransactionInput {
             .collect();
         bytes.extend(txid_le_bytes);
         bytes.extend(&self.vout.to_le_bytes()[..4]);
-        bytes.extend(script::serialize(&self.scriptsig_asm).unwrap());
-        bytes.extend(&self.sequence.to_le_bytes()[..4]);
         bytes
     }
+
+    /// Returns the serialized witness. An input without a witness has zero items.
+    fn serialize_witness(&self) -> Vec<u8> {
+        let items = self.witness.clone().unwrap_or_default();
+        let mut bytes = varint::encode(items.len() as u64);
+        items.iter().for_each(|item| {
+            let item_bytes = hex::hex_to_bytes(item).unwrap();
+            bytes.extend(varint::encode(item_bytes.len() as u64));
+            bytes.extend(item_bytes);
+        });
+        bytes
+    }
+
+    /// Returns the annex of the witness. As specified in BIP-341, the last item
+    /// is the annex if there are at least two items and it starts with 0x50.
+    pub fn annex(&self) -> Option<Vec<u8>> {
+        let witness = self.witness.as_ref()?;
+        if witness.len() < 2 {
+            return None;
+        }
+        let last_item = hex::hex_to_bytes(witness.last()?)?;
+        if last_item.first() != Some(&ANNEX_TAG) {
+            return None;
+        }
+        Some(last_item)
+    }
+
+    /// Returns the script code of a P2WPKH or P2SH-P2WPKH input,
+    /// which is the P2PKH script of the public key hash (BIP-143).
+    fn script_code(&self) -> String {
+        let pubkey_hash = if self.prevout.scriptpubkey_type == script::P2SH_TYPE {
+            // the redeem script is the version byte followed by the public key hash
+            self.scriptsig_asm[4..].to_string()
+        } else {
+            script::pushed_data(&self.prevout.scriptpubkey_asm)
+                .unwrap_or("")
+                .to_string()
+        };
+        script::p2pkh_script_pub_key(&pubkey_hash)
+    }
+}
+
+/// Returns the locktime of a new transaction that discourages fee sniping, i.e.,
+/// the transaction cannot be mined in a block that replaces the most recent block.
+/// Like Bitcoin Core, the locktime is occasionally set further back, so transactions
+/// that are broadcast with a delay do not stand out.
+pub fn anti_fee_sniping_locktime(tip_height: u32) -> u32 {
+    let mut rng = rand::thread_rng();
+    if rng.gen_range(0..10) == 0 {
+        tip_height.saturating_sub(rng.gen_range(0..100))
+    } else {
+        tip_height
+    }
+}
+
+/// Reads the fields of a serialized transaction.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, position: 0 }
+    }
+
+    /// Returns the next bytes without advancing the position.
+    fn peek(&self, len: usize) -> Option<&'a [u8]> {
+        self.bytes
+            .get(self.position..self.position.checked_add(len)?)
+    }
+
+    /// Returns the next bytes and advances the position.
+    fn read(&mut self, len: usize) -> Result<&'a [u8], ParseTransactionError> {
+        match self.peek(len) {
+            Some(bytes) => {
+                self.position += len;
+                Ok(bytes)
+            }
+            None => Err(ParseTransactionError::new("Unexpected end of data")),
+        }
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ParseTransactionError> {
+        Ok(u32::from_le_bytes(self.read(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, ParseTransactionError> {
+        Ok(u64::from_le_bytes(self.read(8)?.try_into().unwrap()))
+    }
+
+    fn read_varint(&mut self) -> Result<u64, ParseTransactionError> {
+        match varint::decode(&self.bytes[self.position..]) {
+            Some((num, len)) => {
+                self.position += len;
+                Ok(num)
+            }
+            None => Err(ParseTransactionError::new("Invalid VarInt")),
+        }
+    }
+
+    /// Reads a script that is prefixed with its length.
+    fn read_script(&mut self) -> Result<&'a [u8], ParseTransactionError> {
+        let len = self.read_varint()? as usize;
+        self.read(len)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+}
+
+/// Returns true if the output is spent with a witness.
+fn is_segwit(output: &TransactionOutput) -> bool {
+    output.scriptpubkey_type == script::P2WPKH_TYPE
+        || output.scriptpubkey_type == script::P2SH_TYPE
+        || output.scriptpubkey_type == script::P2TR_TYPE
 }
 
 impl TransactionOutput {
     /// Creates a transaction output.
-    pub fn create(
-        target_address: &Address,
-        amount: u64,
-        scriptpubkey_type: ScriptType,
-    ) -> TransactionOutput {
-        match scriptpubkey_type {
-            ScriptType::P2PKH => TransactionOutput {
-                scriptpubkey: String::from(""),
-                scriptpubkey_asm: script::p2pkh_script_pub_key(&target_address.get_h160()),
-                scriptpubkey_type: scriptpubkey_type.to_string(),
-                scriptpubkey_address: target_address.to_string(),
-                value: amount,
-            },
+    pub fn create(target_address: &Address, amount: u64) -> TransactionOutput {
+        TransactionOutput {
+            scriptpubkey: String::from(""),
+            scriptpubkey_asm: target_address.script_pub_key(),
+            scriptpubkey_type: target_address.script_type().to_string(),
+            scriptpubkey_address: target_address.to_string(),
+            value: amount,
+        }
+    }
+
+    /// Creates an unspendable output that carries the data, or `None`
+    /// if the data exceeds the size that is relayed.
+    pub fn create_op_return(data: &[u8]) -> Option<TransactionOutput> {
+        let script_pub_key = script::op_return_script(data)?;
+        Some(TransactionOutput {
+            scriptpubkey: hex::bytes_to_hex(&script_pub_key),
+            scriptpubkey_asm: script::deserialize(&script_pub_key).unwrap(),
+            scriptpubkey_type: String::from(script::OP_RETURN_TYPE),
+            scriptpubkey_address: String::from(""),
+            value: 0,
+        })
+    }
+
+    /// Parses a serialized transaction output.
+    pub fn from_bytes(
+        bytes: &[u8],
+        testnet: bool,
+    ) -> Result<TransactionOutput, ParseTransactionError> {
+        let mut reader = Reader::new(bytes);
+        let tx_out = TransactionOutput::read(&mut reader, testnet)?;
+        if !reader.is_empty() {
+            return Err(ParseTransactionError::new("Trailing data"));
         }
+        Ok(tx_out)
+    }
+
+    /// Reads a transaction output.
+    fn read(
+        reader: &mut Reader,
+        testnet: bool,
+    ) -> Result<TransactionOutput, ParseTransactionError> {
+        let value = reader.read_u64()?;
+        let script_pubkey = reader.read_script()?;
+        let scriptpubkey_asm = match script::deserialize(script_pubkey) {
+            Some(asm) => asm,
+            None => return Err(ParseTransactionError::new("Invalid script")),
+        };
+        let scriptpubkey_type = match ScriptType::from_script_pub_key(script_pubkey) {
+            Some(script_type) => script_type.to_string(),
+            None => String::from("unknown"),
+        };
+        let scriptpubkey_address = match Address::from_script_pub_key(script_pubkey, testnet) {
+            Some(address) => address.to_string(),
+            None => String::from(""),
+        };
+        Ok(TransactionOutput {
+            scriptpubkey: hex::bytes_to_hex(script_pubkey),
+            scriptpubkey_asm,
+            scriptpubkey_type,
+            scriptpubkey_address,
+            value,
+        })
     }
 
     /// Returns the serialized transaction output.
     pub fn serialize(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.extend(&self.value.to_le_bytes()[..8]);
-        bytes.extend(script::serialize(&self.scriptpubkey_asm).unwrap());
+        let script_pubkey = self.script_pubkey_bytes();
+        bytes.extend(varint::encode(script_pubkey.len() as u64));
+        bytes.extend(script_pubkey);
+        bytes
+    }
+
+    /// Returns the scriptPubKey with the length prefix.
+    fn serialize_script_pubkey(&self) -> Vec<u8> {
+        let script_pubkey = self.script_pubkey_bytes();
+        let mut bytes = varint::encode(script_pubkey.len() as u64);
+        bytes.extend(script_pubkey);
         bytes
     }
+
+    /// Returns the scriptPubKey without the length prefix.
+    /// The hex format is used if it is known, because the backend's assembly
+    /// may contain words that cannot be serialized.
+    fn script_pubkey_bytes(&self) -> Vec<u8> {
+        if let Some(bytes) = hex::hex_to_bytes(&self.scriptpubkey) {
+            if !bytes.is_empty() {
+                return bytes;
+            }
+        }
+        let bytes = script::serialize(&self.scriptpubkey_asm).unwrap();
+        let (_, prefix_length) = varint::decode(&bytes).unwrap();
+        bytes[prefix_length..].to_vec()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{TransactionInput, TransactionOutput};
+    use super::{anti_fee_sniping_locktime, SighashType, TransactionInput, TransactionOutput};
+    use crate::test_utils::keypair_from_hex;
     use crate::{
-        keys::{bip32::ExtendedPrivateKey, bip44::private_hierarchy::Keypair},
+        keys::{
+            address::Address, bip32::ExtendedPrivateKey, bip44::private_hierarchy::Keypair, taproot,
+        },
         transactions::{
+            script,
             transaction::Transaction,
             utxo::{UTXOBox, UTXOStatus, UTXO},
         },
         utils::hex,
     };
     use num_bigint::BigUint;
+    use secp256k1::{ecdsa, schnorr, Message, PublicKey, Secp256k1};
     use std::str::FromStr;
 
     #[test]
@@ -414,7 +1245,7 @@ mod tests {
             output,
             keypair,
         };
-        tx.sign_input(0, &utxo_box);
+        tx.sign_input(0, &utxo_box, SighashType::Default).unwrap();
 
         let target = String::from("01000000015a3e58d4e5ce5f7dab2a64ad6d9e644fd7c445635674e449e5dd83c6811acb\
                                    d8000000006b48304502210082d5afc04466b7566bcc44a4670980393edbfa88d0daf02c\
@@ -424,5 +1255,702 @@ mod tests {
                                    4a584e364a6588ac60220000000000001976a9146bd18c889da9d66610354ccdc4676f05\
                                    5bae298088ac00000000");
         assert_eq!(tx.serialize_hex(), target);
+        assert_eq!(
+            tx.compute_txid(),
+            "0dd7697dfc7f13978dcf44e7d82c0be4591dec423616510a46cdb6cfeddae02d"
+        );
+    }
+
+    #[test]
+    fn test_create_with_options() {
+        let utxo = UTXO {
+            txid: String::from("d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a"),
+            vout: 0,
+            value: 10000,
+            status: UTXOStatus {
+                confirmed: true,
+                block_height: None,
+                block_hash: None,
+                block_time: None,
+            },
+        };
+        let output = TransactionOutput {
+            scriptpubkey: String::from("76a9146bd18c889da9d66610354ccdc4676f055bae298088ac"),
+            scriptpubkey_asm: String::from("OP_DUP OP_HASH160 OP_PUSHBYTES_20 6bd18c889da9d66610354ccdc4676f055bae2980 OP_EQUALVERIFY OP_CHECKSIG"),
+            scriptpubkey_type: String::from("p2pkh"),
+            scriptpubkey_address: String::from("mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1"),
+            value: 10000,
+        };
+        let private_key = ExtendedPrivateKey::create_master_key([0u8; 32], true);
+        let public_key = private_key.derive_public_key();
+        let keypair = Keypair {
+            private_key,
+            public_key,
+        };
+        let address = keypair.public_key.get_address();
+        let boxed_utxos = vec![UTXOBox {
+            utxo,
+            output,
+            keypair,
+        }];
+
+        let tx = Transaction::create(&boxed_utxos, vec![(address.clone(), 9000)]).unwrap();
+        assert_eq!(tx.version, 1);
+        assert_eq!(tx.locktime, 0);
+        assert_eq!(tx.vin[0].sequence, 0xffffffff);
+
+        let mut tx = Transaction::create_with_options(
+            &boxed_utxos,
+            vec![(address, 9000)],
+            Some(2),
+            Some(2_400_000),
+        )
+        .unwrap();
+        tx.sign_all_inputs(&boxed_utxos);
+        assert_eq!(tx.vin[0].sequence, 0xfffffffe);
+        let tx_hex = tx.serialize_hex();
+        assert!(tx_hex.starts_with("02000000"));
+        // the locktime is serialized in little endian
+        assert!(tx_hex.ends_with("009f2400"));
+    }
+
+    #[test]
+    fn test_anti_fee_sniping_locktime() {
+        for _ in 0..100 {
+            let locktime = anti_fee_sniping_locktime(2_400_000);
+            assert!(locktime <= 2_400_000 && locktime > 2_400_000 - 100);
+        }
+        assert_eq!(anti_fee_sniping_locktime(0), 0);
+    }
+
+    fn p2pkh_output(pubkey_hash: &str, value: u64) -> TransactionOutput {
+        TransactionOutput {
+            scriptpubkey: String::from(""),
+            scriptpubkey_asm: format!(
+                "OP_DUP OP_HASH160 {} OP_EQUALVERIFY OP_CHECKSIG",
+                pubkey_hash
+            ),
+            scriptpubkey_type: String::from("p2pkh"),
+            scriptpubkey_address: String::from(""),
+            value,
+        }
+    }
+
+    fn utxo_box(txid: &str, vout: u32, output: TransactionOutput, keypair: Keypair) -> UTXOBox {
+        UTXOBox {
+            utxo: UTXO {
+                txid: String::from(txid),
+                vout,
+                value: output.value,
+                status: UTXOStatus {
+                    confirmed: true,
+                    block_height: None,
+                    block_hash: None,
+                    block_time: None,
+                },
+            },
+            output,
+            keypair,
+        }
+    }
+
+    #[test]
+    fn test_sign_p2wpkh() {
+        // native P2WPKH example of BIP-143
+        let legacy_utxo_box = utxo_box(
+            "9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff",
+            0,
+            p2pkh_output("", 625000000),
+            keypair_from_hex("bbc27228ddcb9209d7fd6f36b02f7dfa6252af40bb2f1cbc7a557da8027ff866"),
+        );
+        let output = TransactionOutput {
+            scriptpubkey: String::from("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1"),
+            scriptpubkey_asm: String::from(
+                "OP_0 OP_PUSHBYTES_20 1d0f172a0ecb48aee1be1f2687d2963ae33f71a1",
+            ),
+            scriptpubkey_type: String::from("v0_p2wpkh"),
+            scriptpubkey_address: String::from(""),
+            value: 600000000,
+        };
+        let segwit_utxo_box = utxo_box(
+            "8ac60eb9575db5b2d987e29f301b5b819ea83a5c6579d282d189cc04b8e151ef",
+            1,
+            output.clone(),
+            keypair_from_hex("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9"),
+        );
+        let mut tx_in0 = TransactionInput::create(&legacy_utxo_box).unwrap();
+        tx_in0.sequence = 0xffffffee;
+        let tx_in1 = TransactionInput::create(&segwit_utxo_box).unwrap();
+        let mut tx = Transaction {
+            txid: String::from(""),
+            version: 1,
+            locktime: 17,
+            vin: vec![tx_in0, tx_in1],
+            vout: vec![
+                p2pkh_output("8280b37df378db99f66f85c95a783a76ac7a6d59", 112340000),
+                p2pkh_output("3bde42dbee7e4dbe6a21b2d50ce2f0167faa8159", 223450000),
+            ],
+            size: None,
+            weight: None,
+            fee: None,
+            status: None,
+        };
+        assert_eq!(
+            hex::bytes_to_hex(&tx.signature_hash(1, &output, SighashType::Default).unwrap()),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+        tx.sign_input(1, &segwit_utxo_box, SighashType::Default)
+            .unwrap();
+        // the first input spends a P2PK output, which is signed as in the example
+        tx.vin[0].scriptsig_asm = String::from("30450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01");
+
+        let target = String::from("01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f000000\
+                                   00494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be0220\
+                                   40529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804\
+                                   cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb20600\
+                                   0000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143b\
+                                   de42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5\
+                                   b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c\
+                                   212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07ae\
+                                   ee635711000000");
+        assert_eq!(tx.serialize_hex(), target);
+    }
+
+    #[test]
+    fn test_sign_p2sh_p2wpkh() {
+        // P2SH-P2WPKH example of BIP-143
+        let output = TransactionOutput {
+            scriptpubkey: String::from("a9144733f37cf4db86fbc2efed2500b4f4e49f31202387"),
+            scriptpubkey_asm: String::from(
+                "OP_HASH160 OP_PUSHBYTES_20 4733f37cf4db86fbc2efed2500b4f4e49f312023 OP_EQUAL",
+            ),
+            scriptpubkey_type: String::from("p2sh"),
+            scriptpubkey_address: String::from(""),
+            value: 1000000000,
+        };
+        let utxo_box = utxo_box(
+            "77541aeb3c4dac9260b68f74f44c973081a9d4cb2ebe8038b2d70faa201b6bdb",
+            1,
+            output.clone(),
+            keypair_from_hex("eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf"),
+        );
+        let mut tx_in = TransactionInput::create(&utxo_box).unwrap();
+        tx_in.sequence = 0xfffffffe;
+        let mut tx = Transaction {
+            txid: String::from(""),
+            version: 1,
+            locktime: 1170,
+            vin: vec![tx_in],
+            vout: vec![
+                p2pkh_output("a457b684d7f0d539a46a45bbc043f35b59d0d963", 199996600),
+                p2pkh_output("fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c", 800000000),
+            ],
+            size: None,
+            weight: None,
+            fee: None,
+            status: None,
+        };
+        assert_eq!(
+            hex::bytes_to_hex(&tx.signature_hash(0, &output, SighashType::Default).unwrap()),
+            "64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"
+        );
+        tx.sign_input(0, &utxo_box, SighashType::Default).unwrap();
+
+        let target = String::from("01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000\
+                                   001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914\
+                                   a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea\
+                                   97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c2417574711\
+                                   6f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe\
+                                   9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000");
+        assert_eq!(tx.serialize_hex(), target);
+        // the witness is not part of the transaction ID
+        assert_eq!(
+            tx.compute_txid(),
+            Transaction {
+                vin: vec![TransactionInput {
+                    witness: None,
+                    ..tx.vin[0].clone()
+                }],
+                ..tx.clone()
+            }
+            .compute_txid()
+        );
+
+        // a P2SH output that does not wrap the P2WPKH output of the key pair is not supported
+        let other_keypair =
+            keypair_from_hex("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9");
+        let other_utxo_box = UTXOBox {
+            keypair: other_keypair,
+            ..utxo_box.clone()
+        };
+        assert!(TransactionInput::create(&other_utxo_box).is_err());
+    }
+
+    #[test]
+    fn test_sign_p2tr() {
+        let keypair =
+            keypair_from_hex("6b973d88838f27366ed61c9ad6367663045cb456e28335c109e30717ae0c6baa");
+        let output_key = hex::bytes_to_hex(&taproot::output_key(&keypair.private_key).serialize());
+        let output = TransactionOutput {
+            scriptpubkey: format!("5120{}", output_key),
+            scriptpubkey_asm: format!("OP_PUSHNUM_1 OP_PUSHBYTES_32 {}", output_key),
+            scriptpubkey_type: String::from("v1_p2tr"),
+            scriptpubkey_address: String::from(""),
+            value: 420000,
+        };
+        let utxo_box = utxo_box(
+            "d1c40446c65456a9b11a9dddede31ee34b8d3df83788d98f690225d2958bfe3c",
+            0,
+            output.clone(),
+            keypair,
+        );
+        let tx_in = TransactionInput::create(&utxo_box).unwrap();
+        let mut tx = Transaction {
+            txid: String::from(""),
+            version: 2,
+            locktime: 0,
+            vin: vec![tx_in],
+            vout: vec![p2pkh_output(
+                "8280b37df378db99f66f85c95a783a76ac7a6d59",
+                400000,
+            )],
+            size: None,
+            weight: None,
+            fee: None,
+            status: None,
+        };
+        let secp = Secp256k1::new();
+        let output_key = taproot::output_key(&utxo_box.keypair.private_key);
+        let verify = |tx: &Transaction| {
+            let witness = tx.vin[0].witness.as_ref().unwrap();
+            let sig =
+                schnorr::Signature::from_slice(&hex::hex_to_bytes(&witness[0]).unwrap()).unwrap();
+            let msg =
+                Message::from_slice(&tx.signature_hash_bip341(0, SighashType::Default).unwrap())
+                    .unwrap();
+            secp.verify_schnorr(&sig, &msg, &output_key).is_ok()
+        };
+
+        tx.sign_input(0, &utxo_box, SighashType::Default).unwrap();
+        assert_eq!(tx.vin[0].witness.as_ref().unwrap().len(), 1);
+        assert!(verify(&tx));
+        assert!(tx.serialize_hex().starts_with("020000000001"));
+        let sighash = tx.signature_hash_bip341(0, SighashType::Default).unwrap();
+
+        // the annex is kept and committed to
+        tx.vin[0].witness = Some(vec![String::from(""), String::from("50aa")]);
+        assert_eq!(tx.vin[0].annex(), Some(vec![0x50, 0xaa]));
+        assert_ne!(
+            tx.signature_hash_bip341(0, SighashType::Default).unwrap(),
+            sighash
+        );
+        tx.sign_input(0, &utxo_box, SighashType::Default).unwrap();
+        assert_eq!(tx.vin[0].witness.as_ref().unwrap()[1], "50aa");
+        assert!(verify(&tx));
+        // a single item is never an annex
+        tx.vin[0].witness = Some(vec![String::from("50aa")]);
+        assert_eq!(tx.vin[0].annex(), None);
+
+        // signature hash types other than SIGHASH_DEFAULT are appended to the signature
+        tx.sign_input(0, &utxo_box, SighashType::All).unwrap();
+        let sig = hex::hex_to_bytes(&tx.vin[0].witness.as_ref().unwrap()[0]).unwrap();
+        assert_eq!(sig.len(), 65);
+        assert_eq!(sig[64], 0x01);
+        assert_ne!(
+            tx.signature_hash_bip341(0, SighashType::All).unwrap(),
+            tx.signature_hash_bip341(0, SighashType::Default).unwrap()
+        );
+        let single = tx
+            .signature_hash_bip341(0, SighashType::SingleAnyoneCanPay)
+            .unwrap();
+        let mut tx_in = tx.vin[0].clone();
+        tx_in.vout = 1;
+        tx.vin.push(tx_in);
+        tx.vout.push(tx.vout[0].clone());
+        assert_eq!(
+            tx.signature_hash_bip341(0, SighashType::SingleAnyoneCanPay)
+                .unwrap(),
+            single
+        );
+        assert!(tx.signature_hash_bip341(0, SighashType::All).is_ok());
+
+        // the output key must belong to the key pair
+        let other_utxo_box = UTXOBox {
+            keypair: keypair_from_hex(
+                "619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9",
+            ),
+            ..utxo_box.clone()
+        };
+        assert!(TransactionInput::create(&other_utxo_box).is_err());
+    }
+
+    #[test]
+    fn test_create_segwit_outputs() {
+        let address = Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        let tx_out = TransactionOutput::create(&address, 1000);
+        assert_eq!(tx_out.scriptpubkey_type, "v0_p2wpkh");
+        assert_eq!(
+            hex::bytes_to_hex(&tx_out.serialize()),
+            "e803000000000000160014751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+
+        let address =
+            Address::from_str("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7")
+                .unwrap();
+        let tx_out = TransactionOutput::create(&address, 1000);
+        assert_eq!(
+            hex::bytes_to_hex(&tx_out.serialize()),
+            "e8030000000000002200201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"
+        );
+    }
+
+    #[test]
+    fn test_create_p2tr_output() {
+        let address =
+            Address::from_str("bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr")
+                .unwrap();
+        let tx_out = TransactionOutput::create(&address, 1000);
+        assert_eq!(tx_out.scriptpubkey_type, "v1_p2tr");
+        assert_eq!(
+            hex::bytes_to_hex(&tx_out.serialize()),
+            "e803000000000000225120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+        );
+    }
+
+    #[test]
+    fn test_create_p2sh_output() {
+        let address = Address::from_str("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy").unwrap();
+        let tx_out = TransactionOutput::create(&address, 1000);
+        assert_eq!(tx_out.scriptpubkey_type, "p2sh");
+        assert_eq!(
+            hex::bytes_to_hex(&tx_out.serialize()),
+            "e80300000000000017a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87"
+        );
+    }
+
+    #[test]
+    fn test_create_op_return_output() {
+        let tx_out = TransactionOutput::create_op_return(b"hello").unwrap();
+        assert_eq!(tx_out.scriptpubkey_type, "op_return");
+        let bytes = tx_out.serialize();
+        assert_eq!(
+            hex::bytes_to_hex(&bytes),
+            "0000000000000000076a0568656c6c6f"
+        );
+        let parsed = TransactionOutput::from_bytes(&bytes, false).unwrap();
+        assert_eq!(parsed.scriptpubkey_type, "op_return");
+        assert!(parsed.scriptpubkey_address.is_empty());
+        assert!(TransactionOutput::create_op_return(&[0; 81]).is_none());
+    }
+
+    #[test]
+    fn test_sign_mixed_inputs() {
+        let keypairs: Vec<Keypair> = (1..=4)
+            .map(|seed| {
+                let private_key = ExtendedPrivateKey::create_master_key([seed; 32], true);
+                let public_key = private_key.derive_public_key();
+                Keypair {
+                    private_key,
+                    public_key,
+                }
+            })
+            .collect();
+        let pubkey_hash = keypairs[2].public_key.get_address().get_hash();
+        let redeem_script = script::p2sh_p2wpkh_redeem_script(&pubkey_hash);
+        let addresses = [
+            keypairs[0].public_key.get_address(),
+            Address::create_p2wpkh(&keypairs[1].public_key),
+            Address::create_p2sh(&hex::hex_to_bytes(&redeem_script).unwrap(), true),
+            Address::create_p2tr(&keypairs[3].public_key),
+        ];
+        let boxed_utxos: Vec<UTXOBox> = addresses
+            .iter()
+            .zip(keypairs.iter())
+            .enumerate()
+            .map(|(vout, (address, keypair))| {
+                utxo_box(
+                    "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a",
+                    vout as u32,
+                    TransactionOutput::create(address, 100000),
+                    keypair.clone(),
+                )
+            })
+            .collect();
+        let targets = vec![(addresses[1].clone(), 390000)];
+        let mut tx = Transaction::create(&boxed_utxos, targets).unwrap();
+        tx.sign_all_inputs(&boxed_utxos);
+
+        let secp = Secp256k1::new();
+        for (index, utxo_box) in boxed_utxos.iter().enumerate() {
+            let z = tx
+                .signature_hash(index, &utxo_box.output, SighashType::Default)
+                .unwrap();
+            let msg = Message::from_slice(&z).unwrap();
+            let tx_in = &tx.vin[index];
+            let witness = tx_in.witness.clone().unwrap_or_default();
+            let signature = match index {
+                0 => {
+                    // the legacy input is signed in the script signature
+                    assert!(witness.is_empty());
+                    tx_in.scriptsig_asm.split(' ').next().unwrap().to_string()
+                }
+                3 => {
+                    assert_eq!(witness.len(), 1);
+                    let sig =
+                        schnorr::Signature::from_slice(&hex::hex_to_bytes(&witness[0]).unwrap())
+                            .unwrap();
+                    let output_key = taproot::output_key(&utxo_box.keypair.private_key);
+                    assert!(secp.verify_schnorr(&sig, &msg, &output_key).is_ok());
+                    continue;
+                }
+                _ => {
+                    // the SegWit inputs are signed in the witness
+                    assert_eq!(witness.len(), 2);
+                    witness[0].clone()
+                }
+            };
+            let sig_bytes = hex::hex_to_bytes(&signature).unwrap();
+            let sig = ecdsa::Signature::from_der(&sig_bytes[..sig_bytes.len() - 1]).unwrap();
+            let public_key = PublicKey::from_slice(&utxo_box.keypair.public_key.key_data).unwrap();
+            assert!(secp.verify_ecdsa(&msg, &sig, &public_key).is_ok());
+        }
+        assert_eq!(tx.vin[1].scriptsig_asm, "");
+        assert_eq!(tx.vin[2].scriptsig_asm, redeem_script);
+
+        // the legacy input has an empty witness in the serialized transaction
+        let parsed = Transaction::from_hex(&tx.serialize_hex(), true).unwrap();
+        assert_eq!(parsed.serialize_hex(), tx.serialize_hex());
+        assert_eq!(parsed.txid, tx.compute_txid());
+        assert!(parsed.vin[0].witness.clone().unwrap_or_default().is_empty());
+        assert_eq!(parsed.vin[2].witness, tx.vin[2].witness);
+        assert!(tx.vsize() < tx.serialize().len() as u32);
+    }
+
+    #[test]
+    fn test_check_sanity() {
+        let tx_hex = "010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000\
+                      ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f\
+                      05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000";
+        let mut tx = Transaction::from_hex(tx_hex, false).unwrap();
+        assert_eq!(
+            tx.check_sanity().unwrap_err().to_string(),
+            "unsigned_tx_error"
+        );
+        tx.vin[0].witness = Some(vec!["00".to_string()]);
+        assert!(tx.check_sanity().is_ok());
+
+        let mut duplicate_input_tx = tx.clone();
+        duplicate_input_tx.vin.push(tx.vin[0].clone());
+        let err = duplicate_input_tx.check_sanity().unwrap_err();
+        assert_eq!(err.to_string(), "duplicate_input_error");
+
+        let mut dust_tx = tx.clone();
+        dust_tx.vout[1].value = 545;
+        let err = dust_tx.check_sanity().unwrap_err();
+        assert_eq!(err.to_string(), "dust_output_error");
+        dust_tx.vout[1] = TransactionOutput::create_op_return(b"hello").unwrap();
+        assert!(dust_tx.check_sanity().is_ok());
+
+        let mut invalid_amount_tx = tx.clone();
+        invalid_amount_tx.vout[0].value = 21000000 * 100000000;
+        let err = invalid_amount_tx.check_sanity().unwrap_err();
+        assert_eq!(err.to_string(), "invalid_tx_error");
+
+        tx.vout.clear();
+        assert_eq!(tx.check_sanity().unwrap_err().to_string(), "empty_tx_error");
+    }
+
+    #[test]
+    fn test_sighash_types() {
+        // P2SH-P2WSH example of BIP-143, which is signed with all signature hash types
+        let tx_hex = "010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000\
+                      ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f\
+                      05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000";
+        let witness_script = "56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103\
+                              b28f0c28bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d7\
+                              03413d57761b8b9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb83309\
+                              2a9a21cfdf1ed1376e58c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d6\
+                              1acdaa1322008af9602b3b14862c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5\
+                              dfbe673a9f01d9f0c19617681024306b56ae";
+        let tx = Transaction::from_hex(tx_hex, false).unwrap();
+        let script_code = script::deserialize(&hex::hex_to_bytes(witness_script).unwrap()).unwrap();
+        let sighashes = [
+            (
+                SighashType::All,
+                "185c0be5263dce5b4bb50a047973c1b6272bfbd0103a89444597dc40b248ee7c",
+            ),
+            (
+                SighashType::None,
+                "e9733bc60ea13c95c6527066bb975a2ff29a925e80aa14c213f686cbae5d2f36",
+            ),
+            (
+                SighashType::Single,
+                "1e1f1c303dc025bd664acb72e583e933fae4cff9148bf78c157d1e8f78530aea",
+            ),
+            (
+                SighashType::AllAnyoneCanPay,
+                "2a67f03e63a6a422125878b40b82da593be8d4efaafe88ee528af6e5a9955c6e",
+            ),
+            (
+                SighashType::NoneAnyoneCanPay,
+                "781ba15f3779d5542ce8ecb5c18716733a5ee42a6f51488ec96154934e2c890a",
+            ),
+            (
+                SighashType::SingleAnyoneCanPay,
+                "511e8e52ed574121fc1b654970395502128263f62662e076dc6baf05c2e6a99b",
+            ),
+        ];
+        for (sighash_type, sighash) in sighashes {
+            let z = tx
+                .signature_hash_bip143(0, &script_code, 987654321, sighash_type)
+                .unwrap();
+            assert_eq!(hex::bytes_to_hex(&z), sighash);
+            assert_eq!(
+                SighashType::from_u32(sighash_type.to_u32()),
+                Some(sighash_type)
+            );
+        }
+        assert_eq!(
+            tx.signature_hash_bip143(0, &script_code, 987654321, SighashType::Default)
+                .unwrap(),
+            tx.signature_hash_bip143(0, &script_code, 987654321, SighashType::All)
+                .unwrap()
+        );
+        assert_eq!(SighashType::from_u32(0x04), None);
+
+        // a legacy signature with SIGHASH_NONE does not commit to the outputs and one with
+        // ANYONECANPAY does not commit to the other inputs
+        let keypair =
+            keypair_from_hex("bbc27228ddcb9209d7fd6f36b02f7dfa6252af40bb2f1cbc7a557da8027ff866");
+        let utxo_box = utxo_box(
+            "9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff",
+            0,
+            TransactionOutput::create(&keypair.public_key.get_address(), 100000),
+            keypair,
+        );
+        let mut tx = Transaction::create(
+            &vec![utxo_box.clone()],
+            vec![(utxo_box.keypair.public_key.get_address(), 90000)],
+        )
+        .unwrap();
+        let output = utxo_box.output.clone();
+        let none = tx.signature_hash(0, &output, SighashType::None).unwrap();
+        let all_anyone_can_pay = tx
+            .signature_hash(0, &output, SighashType::AllAnyoneCanPay)
+            .unwrap();
+        tx.vout[0].value = 80000;
+        assert_eq!(
+            tx.signature_hash(0, &output, SighashType::None).unwrap(),
+            none
+        );
+        let mut tx_in = tx.vin[0].clone();
+        tx_in.vout = 1;
+        tx.vin.push(tx_in);
+        tx.vout[0].value = 90000;
+        assert_eq!(
+            tx.signature_hash(0, &output, SighashType::AllAnyoneCanPay)
+                .unwrap(),
+            all_anyone_can_pay
+        );
+        assert_ne!(
+            tx.signature_hash(0, &output, SighashType::All).unwrap(),
+            all_anyone_can_pay
+        );
+        let signature = tx
+            .create_signature(0, &utxo_box, SighashType::SingleAnyoneCanPay)
+            .unwrap();
+        assert_eq!(signature.last(), Some(&0x83));
+        // the second input has no output with the same index
+        let err = tx
+            .sign_input(1, &utxo_box, SighashType::Single)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "sighash_single_error");
+    }
+
+    #[test]
+    fn test_from_hex_legacy() {
+        let tx_hex = "01000000015a3e58d4e5ce5f7dab2a64ad6d9e644fd7c445635674e449e5dd83c6811acb\
+                      d8000000006b48304502210082d5afc04466b7566bcc44a4670980393edbfa88d0daf02c\
+                      163372fdcb5a1dc902203aa732322fd0cfca0d7fef4889779471d832dc0fa73ff5518a30\
+                      f92054b02d51012103597f57b176a4fd0bbf9b163ad341ed002101572b595485c537c367\
+                      3281a83ebcffffffff02e8030000000000001976a914fd158402792612f4d87a9f5f37e1\
+                      4a584e364a6588ac60220000000000001976a9146bd18c889da9d66610354ccdc4676f05\
+                      5bae298088ac00000000";
+        let tx = Transaction::from_hex(tx_hex, true).unwrap();
+        assert_eq!(
+            tx.txid,
+            "0dd7697dfc7f13978dcf44e7d82c0be4591dec423616510a46cdb6cfeddae02d"
+        );
+        assert_eq!(tx.version, 1);
+        assert_eq!(tx.locktime, 0);
+        assert_eq!(tx.vin.len(), 1);
+        assert_eq!(
+            tx.vin[0].txid,
+            "d8cb1a81c683dde549e474566345c4d74f649e6dad642aab7d5fcee5d4583e5a"
+        );
+        assert!(tx.vin[0].witness.is_none());
+        assert!(!tx.vin[0].is_coinbase);
+        assert_eq!(tx.vout.len(), 2);
+        assert_eq!(tx.vout[0].value, 1000);
+        assert_eq!(tx.vout[0].scriptpubkey_type, "p2pkh");
+        assert!(tx.vout[0].scriptpubkey_address.starts_with(['m', 'n']));
+        assert_eq!(tx.vout[1].value, 8800);
+        assert_eq!(tx.size, Some(226));
+        assert_eq!(tx.weight, Some(904));
+        assert_eq!(tx.vsize(), 226);
+        assert_eq!(tx.serialize_hex(), tx_hex);
+    }
+
+    #[test]
+    fn test_from_hex_segwit() {
+        // P2SH-P2WPKH example of BIP-143
+        let tx_hex = "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000\
+                      001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914\
+                      a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea\
+                      97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c2417574711\
+                      6f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe\
+                      9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000";
+        let tx = Transaction::from_hex(tx_hex, false).unwrap();
+        assert_eq!(tx.locktime, 1170);
+        assert_eq!(tx.vin[0].sequence, 0xfffffffe);
+        assert_eq!(
+            tx.vin[0].scriptsig,
+            "16001479091972186c449eb1ded22b78e40d009bdf0089"
+        );
+        let witness = tx.vin[0].witness.as_ref().unwrap();
+        assert_eq!(witness.len(), 2);
+        assert_eq!(
+            witness[1],
+            "03ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a26873"
+        );
+        assert_eq!(tx.serialize_hex(), tx_hex);
+        assert_eq!(tx.txid, tx.compute_txid());
+        // the witness is discounted
+        let base_size = tx.serialize_without_witness().len() as u32;
+        assert_eq!(tx.weight, Some(base_size * 3 + tx.size.unwrap()));
+        assert_eq!(tx.vsize(), tx.weight.unwrap().div_ceil(4));
+        assert!(tx.vsize() < tx.size.unwrap());
+    }
+
+    #[test]
+    fn test_from_hex_invalid() {
+        let tx_hex = "0100000001654923b9dd40ed333cc32b028fd071d523f78b1725562b66831bf73820fb725c0000000000ffffffff\
+                      02e8030000000000001976a914fc20f7fc8b0a6785e02ebe93adbcc66f3065c99788ac581b0000000000001976a91\
+                      46bd18c889da9d66610354ccdc4676f055bae298088ac00000000";
+        assert!(Transaction::from_hex(tx_hex, false).is_ok());
+        // truncated
+        assert!(Transaction::from_hex(&tx_hex[..tx_hex.len() - 2], false).is_err());
+        // trailing data
+        assert!(Transaction::from_hex(&format!("{}00", tx_hex), false).is_err());
+        // not hex
+        assert!(Transaction::from_hex("0100000x", false).is_err());
+        // marker and flag without any witness
+        let tx_hex = format!(
+            "{}0001{}00{}",
+            &tx_hex[..8],
+            &tx_hex[8..tx_hex.len() - 8],
+            &tx_hex[tx_hex.len() - 8..]
+        );
+        assert!(Transaction::from_hex(&tx_hex, false).is_err());
     }
 }
@@ -26,3 +26,17 @@ pub struct UTXOBox {
     pub output: TransactionOutput,
     pub keypair: Keypair,
 }
+
+/// A UTXO of an account as listed for coin control.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountUTXO {
+    pub txid: String,
+    pub vout: u32,
+    pub value: u64,
+    pub address: String,
+    pub confirmed: bool,
+    pub block_height: Option<u64>,
+    pub reserved: bool, // true if a pending transaction spends the UTXO
+    pub frozen: bool,
+    pub label: Option<String>,
+}
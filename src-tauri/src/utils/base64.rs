@@ -0,0 +1,76 @@
+//! Implements the Base64 encoding as specified in
+//! [RFC 4648](https://datatracker.ietf.org/doc/html/rfc4648#section-4).
+static BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+static PADDING: u8 = b'=';
+
+/// Encodes the bytes as a Base64 string.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (bits >> (18 - 6 * i)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push(PADDING as char);
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes a Base64 string. Returns `None` if the string is not valid Base64.
+pub fn decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.as_bytes();
+    if s.len() % 4 != 0 {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(s.len() / 4 * 3);
+    for (chunk_index, chunk) in s.chunks(4).enumerate() {
+        let is_last_chunk = chunk_index == s.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == PADDING).count();
+        if padding > 2 || (padding > 0 && !is_last_chunk) {
+            return None;
+        }
+        let mut bits: u32 = 0;
+        for &c in &chunk[..4 - padding] {
+            let value = BASE64_ALPHABET.iter().position(|&other| other == c)?;
+            bits = (bits << 6) | value as u32;
+        }
+        bits <<= 6 * padding;
+        let group = bits.to_be_bytes();
+        bytes.extend(&group[1..4 - padding]);
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::base64;
+
+    #[test]
+    fn test_encode_decode() {
+        // test vectors from RFC 4648
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (plain, encoded) in vectors {
+            assert_eq!(base64::encode(plain.as_bytes()), encoded);
+            assert_eq!(base64::decode(encoded).unwrap(), plain.as_bytes());
+        }
+        assert!(base64::decode("Zm9").is_none());
+        assert!(base64::decode("Zm=v").is_none());
+        assert!(base64::decode("Zg==Zg==").is_none());
+        assert!(base64::decode("Z!==").is_none());
+    }
+}
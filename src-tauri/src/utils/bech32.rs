@@ -0,0 +1,222 @@
+//! Implements the Bech32 encoding as specified in
+//! [BIP-173](https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki)
+//! and its variant Bech32m as specified in
+//! [BIP-350](https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki).
+static CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+static GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+static SEPARATOR: char = '1';
+static CHECKSUM_LENGTH: usize = 6;
+static MAX_LENGTH: usize = 90;
+static BECH32_CONSTANT: u32 = 1;
+static BECH32M_CONSTANT: u32 = 0x2bc830a3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    /// Returns the constant that the checksum is XORed with.
+    fn constant(&self) -> u32 {
+        match self {
+            Variant::Bech32 => BECH32_CONSTANT,
+            Variant::Bech32m => BECH32M_CONSTANT,
+        }
+    }
+}
+
+/// Encodes the 5-bit values with the human-readable part and appends the checksum.
+/// Returns `None` if the human-readable part or a value is invalid,
+/// or if the result would exceed 90 characters.
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> Option<String> {
+    if !is_valid_hrp(hrp)
+        || data.iter().any(|value| *value >= 32)
+        || hrp.len() + 1 + data.len() + CHECKSUM_LENGTH > MAX_LENGTH
+    {
+        return None;
+    }
+    let hrp = hrp.to_lowercase();
+    let mut encoded = format!("{}{}", hrp, SEPARATOR);
+    for value in data
+        .iter()
+        .chain(create_checksum(&hrp, data, variant).iter())
+    {
+        encoded.push(CHARSET[*value as usize] as char);
+    }
+    Some(encoded)
+}
+
+/// Decodes the string into the human-readable part and the 5-bit values
+/// without the checksum. Also returns the variant whose checksum matches.
+/// Returns `None` if the string is not valid Bech32 or Bech32m.
+pub fn decode(s: &str) -> Option<(String, Vec<u8>, Variant)> {
+    if s.len() > MAX_LENGTH || (s.to_lowercase() != s && s.to_uppercase() != s) {
+        return None;
+    }
+    let s = s.to_lowercase();
+    let separator = s.rfind(SEPARATOR)?;
+    let hrp = &s[..separator];
+    if !is_valid_hrp(hrp) || separator + 1 + CHECKSUM_LENGTH > s.len() {
+        return None;
+    }
+    let mut data = Vec::with_capacity(s.len() - separator - 1);
+    for c in s[separator + 1..].bytes() {
+        let value = CHARSET.iter().position(|charset_c| *charset_c == c)?;
+        data.push(value as u8);
+    }
+    let mut values = hrp_expand(hrp);
+    values.extend(&data);
+    let polymod = polymod(&values);
+    let variant = if polymod == BECH32_CONSTANT {
+        Variant::Bech32
+    } else if polymod == BECH32M_CONSTANT {
+        Variant::Bech32m
+    } else {
+        return None;
+    };
+    data.truncate(data.len() - CHECKSUM_LENGTH);
+    Some((hrp.to_string(), data, variant))
+}
+
+/// Regroups the bits of the data from `from` bits to `to` bits per value.
+/// If `pad` is false, returns `None` if the data has invalid padding.
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let mut converted = Vec::new();
+    let max_value = (1 << to) - 1;
+    for value in data {
+        if (*value as u32) >> from != 0 {
+            return None;
+        }
+        acc = (acc << from) | *value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push(((acc >> bits) & max_value) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push(((acc << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max_value) != 0 {
+        return None;
+    }
+    Some(converted)
+}
+
+/// Returns true if the human-readable part is not empty
+/// and only contains ASCII characters in the range 33-126.
+fn is_valid_hrp(hrp: &str) -> bool {
+    !hrp.is_empty() && hrp.bytes().all(|c| (33..=126).contains(&c))
+}
+
+/// Computes the checksum over the expanded human-readable part and the values.
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = (chk & 0x1ffffff) << 5 ^ *value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+/// Expands the human-readable part for the checksum computation.
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut values: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|c| c & 31));
+    values
+}
+
+/// Returns the checksum of the human-readable part and the values.
+fn create_checksum(hrp: &str, data: &[u8], variant: Variant) -> Vec<u8> {
+    let mut values = hrp_expand(hrp);
+    values.extend(data);
+    values.extend(vec![0u8; CHECKSUM_LENGTH]);
+    let polymod = polymod(&values) ^ variant.constant();
+    (0..CHECKSUM_LENGTH)
+        .map(|i| ((polymod >> (5 * (5 - i))) & 31) as u8)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::bech32::{self, Variant};
+
+    #[test]
+    fn test_valid_bech32() {
+        let valid = [
+            "A12UEL5L",
+            "a12uel5l",
+            "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+            "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+            "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+            "?1ezyfcl",
+        ];
+        for s in valid {
+            let (hrp, data, variant) = bech32::decode(s).unwrap();
+            assert_eq!(variant, Variant::Bech32);
+            assert_eq!(
+                bech32::encode(&hrp, &data, variant).unwrap(),
+                s.to_lowercase()
+            );
+        }
+    }
+
+    #[test]
+    fn test_valid_bech32m() {
+        let valid = [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ];
+        for s in valid {
+            let (hrp, data, variant) = bech32::decode(s).unwrap();
+            assert_eq!(variant, Variant::Bech32m);
+            assert_eq!(
+                bech32::encode(&hrp, &data, variant).unwrap(),
+                s.to_lowercase()
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let invalid = [
+            "pzry9x0s0muk",  // no separator
+            "1pzry9x0s0muk", // empty human-readable part
+            "x1b4n0q5v",     // invalid character
+            "li1dgmt3",      // checksum too short
+            "A1G7SGD8",      // checksum computed with an uppercase human-readable part
+            "10a06t8",       // empty human-readable part
+            "1qzzfhee",      // empty human-readable part
+            "a12UEL5L",      // mixed case
+            "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+        ];
+        for s in invalid {
+            assert!(bech32::decode(s).is_none(), "{}", s);
+        }
+        assert!(bech32::encode("", &[0], Variant::Bech32).is_none());
+        assert!(bech32::encode("bc", &[32], Variant::Bech32).is_none());
+    }
+
+    #[test]
+    fn test_convert_bits() {
+        let data = vec![0x75, 0x1e, 0x76, 0xe8];
+        let converted = bech32::convert_bits(&data, 8, 5, true).unwrap();
+        assert_eq!(bech32::convert_bits(&converted, 5, 8, false).unwrap(), data);
+        // non-zero padding
+        assert!(bech32::convert_bits(&[0x1f, 0x1f], 5, 8, false).is_none());
+    }
+}
@@ -0,0 +1,101 @@
+//! Implements helper functions for files.
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes the bytes to the specified file. The bytes are first written
+/// to a temporary file, which then replaces the file. A crash during the
+/// write therefore never leaves a partially written file behind.
+pub fn write_atomic<P: AsRef<Path>>(path: P, bytes: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let tmp_path = tmp_path(path);
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    // persist the rename
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+/// Writes the bytes to the specified file like `write_atomic`, but keeps the
+/// previous versions of the file. The newest previous version is stored in
+/// `<path>.bak.1` and the oldest in `<path>.bak.<count>`.
+pub fn write_atomic_with_backups<P: AsRef<Path>>(
+    path: P,
+    bytes: &[u8],
+    count: usize,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    if count > 0 && path.exists() {
+        let backup_paths = backup_paths(path, count);
+        for i in (1..count).rev() {
+            if backup_paths[i - 1].exists() {
+                fs::rename(&backup_paths[i - 1], &backup_paths[i])?;
+            }
+        }
+        // the file is copied, so it exists at all times
+        fs::copy(path, &backup_paths[0])?;
+    }
+    write_atomic(path, bytes)
+}
+
+/// Returns the paths of the previous versions of the specified file, newest first.
+pub fn backup_paths(path: &Path, count: usize) -> Vec<PathBuf> {
+    (1..=count)
+        .map(|i| {
+            let mut backup_path: OsString = path.as_os_str().to_owned();
+            backup_path.push(format!(".bak.{}", i));
+            PathBuf::from(backup_path)
+        })
+        .collect()
+}
+
+/// Returns the path of the temporary file for the specified file.
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp_path: OsString = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    PathBuf::from(tmp_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backup_paths, write_atomic, write_atomic_with_backups};
+    use std::fs;
+
+    #[test]
+    fn test_write_atomic() {
+        let path = std::env::temp_dir().join("ghorbu_test_write_atomic");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        assert!(!std::path::Path::new(&tmp_path).exists());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic_with_backups() {
+        let path = std::env::temp_dir().join("ghorbu_test_write_atomic_with_backups");
+        let backup_paths = backup_paths(&path, 2);
+        for p in backup_paths.iter() {
+            let _ = fs::remove_file(p);
+        }
+        for bytes in [b"first", b"secon", b"third", b"forth"] {
+            write_atomic_with_backups(&path, bytes, 2).unwrap();
+        }
+        assert_eq!(fs::read(&path).unwrap(), b"forth");
+        assert_eq!(fs::read(&backup_paths[0]).unwrap(), b"third");
+        assert_eq!(fs::read(&backup_paths[1]).unwrap(), b"secon");
+        fs::remove_file(path).unwrap();
+        for p in backup_paths {
+            fs::remove_file(p).unwrap();
+        }
+    }
+}
@@ -1,4 +1,7 @@
 //! Some helper functions.
 pub mod base58;
+pub mod base64;
+pub mod bech32;
+pub mod file;
 pub mod hex;
 pub mod varint;
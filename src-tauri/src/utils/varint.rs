@@ -14,6 +14,32 @@ pub fn encode(num: u64) -> Vec<u8> {
     }
 }
 
+/// Decodes the VarInt at the start of the provided bytes.
+/// Returns the integer and the number of bytes that were read, or `None`
+/// if the bytes end before the VarInt or if it is not minimally encoded.
+pub fn decode(bytes: &[u8]) -> Option<(u64, usize)> {
+    let prefix = *bytes.first()?;
+    let (num, len, min) = match prefix {
+        0xfd => {
+            let num = u16::from_le_bytes(bytes.get(1..3)?.try_into().unwrap());
+            (num as u64, 3, 0xfd)
+        }
+        0xfe => {
+            let num = u32::from_le_bytes(bytes.get(1..5)?.try_into().unwrap());
+            (num as u64, 5, 0x10000)
+        }
+        0xff => {
+            let num = u64::from_le_bytes(bytes.get(1..9)?.try_into().unwrap());
+            (num, 9, 0x100000000)
+        }
+        _ => return Some((prefix as u64, 1)),
+    };
+    if num < min {
+        return None;
+    }
+    Some((num, len))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::utils::varint;
@@ -51,4 +77,29 @@ mod tests {
             vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
         );
     }
+
+    #[test]
+    fn test_decode() {
+        for num in [0, 252, 253, 65535, 65536, 4294967295, 4294967296, u64::MAX] {
+            let bytes = varint::encode(num);
+            assert_eq!(varint::decode(&bytes), Some((num, bytes.len())));
+        }
+        // trailing bytes are not read
+        assert_eq!(varint::decode(&[0xfd, 0x00, 0x01, 0xab]), Some((256, 3)));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(varint::decode(&[]), None);
+        assert_eq!(varint::decode(&[0xfd, 0xff]), None);
+        assert_eq!(varint::decode(&[0xfe, 0x00, 0x00, 0x01]), None);
+        assert_eq!(varint::decode(&[0xff, 0x00, 0x00, 0x00, 0x00, 0x01]), None);
+        // not minimally encoded
+        assert_eq!(varint::decode(&[0xfd, 0xfc, 0x00]), None);
+        assert_eq!(varint::decode(&[0xfe, 0xff, 0xff, 0x00, 0x00]), None);
+        assert_eq!(
+            varint::decode(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00]),
+            None
+        );
+    }
 }
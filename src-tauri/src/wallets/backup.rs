@@ -0,0 +1,201 @@
+//! Implements a portable backup of a wallet. The backup contains the key
+//! hierarchy, including the labels of the accounts, and every store of the
+//! wallet: the watch list, the labels, the settings, the contacts, the frozen
+//! UTXOs, the payment requests and the multisig accounts. It is encrypted with
+//! the wallet password, so it can be moved between machines safely.
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroize;
+
+use super::contacts::Contacts;
+use super::error::WalletError;
+use super::labels::Labels;
+use super::payment_requests::PaymentRequests;
+use super::settings::WalletSettings;
+use super::watch_list::WatchList;
+use crate::coin_selection::frozen::FrozenUTXOs;
+use crate::encryption::{self, KdfParams};
+use crate::keys::bip44::multisig::MultisigAccounts;
+use crate::keys::bip44::MasterPrivateKey;
+use crate::utils::file;
+
+static BACKUP_VERSION: u32 = 2;
+
+/// The stores of a wallet besides the key hierarchy.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct WalletStores {
+    pub watch_list: WatchList,
+    pub labels: Labels,
+    pub settings: WalletSettings,
+    pub contacts: Contacts,
+    pub frozen: FrozenUTXOs,
+    pub payment_requests: PaymentRequests,
+    pub multisig_accounts: MultisigAccounts,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WalletBackup {
+    version: u32,
+    pub master_private_key: MasterPrivateKey,
+    pub stores: WalletStores,
+    pub kdf_params: KdfParams,
+    pub wipe_after: Option<u32>, // see `security::PasswordAttempts`
+}
+
+impl WalletBackup {
+    /// Creates a backup of the specified wallet data.
+    pub fn create(
+        master_private_key: MasterPrivateKey,
+        stores: WalletStores,
+        kdf_params: KdfParams,
+        wipe_after: Option<u32>,
+    ) -> WalletBackup {
+        WalletBackup {
+            version: BACKUP_VERSION,
+            master_private_key,
+            stores,
+            kdf_params,
+            wipe_after,
+        }
+    }
+
+    /// Encrypts the backup with the password and saves it to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P, password: String) -> Result<(), Box<dyn Error>> {
+        let backup_encoded = serde_json::to_vec(self)?;
+        let backup_encrypted =
+            encryption::encrypt_with_params(backup_encoded, password, &self.kdf_params);
+        file::write_atomic(path, &backup_encrypted)?;
+        Ok(())
+    }
+
+    /// Loads the backup from the specified path and decrypts it with the password.
+    /// Returns an error if the file is not a backup of a supported version.
+    pub fn load<P: AsRef<Path>>(path: P, password: String) -> Result<WalletBackup, Box<dyn Error>> {
+        let backup_encrypted = fs::read(path)?;
+        let mut backup_encoded = encryption::decrypt(backup_encrypted, password)?;
+        let backup = serde_json::from_slice::<WalletBackup>(&backup_encoded);
+        backup_encoded.zeroize();
+        match backup {
+            Ok(backup) if backup.version == BACKUP_VERSION => Ok(backup),
+            _ => Err(Box::new(WalletError::new("invalid_backup_error"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WalletBackup, WalletStores};
+    use crate::coin_selection::strategy::CoinSelectionStrategy;
+    use crate::encryption::{KdfParams, SecurityLevel};
+    use crate::keys::bip32::ExtendedPrivateKey;
+    use crate::keys::bip44::multisig::{Cosigner, MultisigScriptType};
+    use crate::keys::bip44::{MasterPrivateKey, BITCOIN_INDEX};
+    use crate::wallets::labels::LabelType;
+    use std::fs;
+
+    #[test]
+    fn test_backup() {
+        let path = std::env::temp_dir().join("ghorbu_test_backup");
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let private_key =
+            ExtendedPrivateKey::import_key_from_base58_check(private_key_b58).unwrap();
+        let mut master_private_key = MasterPrivateKey::create_from_key(private_key);
+        let account = master_private_key.add_account(BITCOIN_INDEX);
+        master_private_key.rename_account(
+            BITCOIN_INDEX,
+            account.index,
+            "Savings".to_string(),
+            None,
+        );
+        let mut stores = WalletStores::default();
+        stores
+            .watch_list
+            .add(
+                "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1",
+                "Cold storage".to_string(),
+            )
+            .unwrap();
+        let txid = "8e3ed1a9c0e8a58ea3b1e4e0a5fb3d5a4ef83b9cc6b36bbd3e5ba72d1e6f5fb8";
+        stores.labels.set(LabelType::Tx, txid, "Rent").unwrap();
+        stores.settings.coin_selection_strategy = CoinSelectionStrategy::Privacy;
+        stores.settings.confirmation_policy.min_confirmations = 3;
+        stores
+            .contacts
+            .add("Alice", "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1", "")
+            .unwrap();
+        stores.frozen.freeze(txid, 1);
+        stores
+            .payment_requests
+            .add(
+                BITCOIN_INDEX,
+                account.index,
+                "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+                Some(50000),
+                Some("Invoice"),
+                1700000000,
+            )
+            .unwrap();
+        let cosigners = (0..2)
+            .map(|account_index| {
+                Cosigner::derive_local(
+                    format!("Cosigner {}", account_index),
+                    &master_private_key,
+                    BITCOIN_INDEX,
+                    account_index,
+                    MultisigScriptType::P2WSH,
+                )
+            })
+            .collect();
+        stores
+            .multisig_accounts
+            .add(
+                BITCOIN_INDEX,
+                "Family".to_string(),
+                2,
+                MultisigScriptType::P2WSH,
+                cosigners,
+            )
+            .unwrap();
+        let kdf_params = KdfParams {
+            memory: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let backup = WalletBackup::create(master_private_key, stores, kdf_params, Some(10));
+        backup.save(&path, "password1234".to_string()).unwrap();
+
+        assert!(WalletBackup::load(&path, "password1235".to_string()).is_err());
+        let restored = WalletBackup::load(&path, "password1234".to_string()).unwrap();
+        assert_eq!(
+            restored.master_private_key.private_key.to_base58_check(),
+            private_key_b58
+        );
+        let account = &restored.master_private_key.purpose.coin_types[&BITCOIN_INDEX].accounts
+            [&account.index];
+        assert_eq!(account.label, "Savings");
+        let stores = &restored.stores;
+        assert_eq!(
+            stores.watch_list.get_addresses(true)[0].label,
+            "Cold storage"
+        );
+        assert_eq!(stores.labels.get(LabelType::Tx, txid), Some("Rent"));
+        assert_eq!(
+            stores.settings.coin_selection_strategy,
+            CoinSelectionStrategy::Privacy
+        );
+        assert_eq!(stores.settings.confirmation_policy.min_confirmations, 3);
+        assert_eq!(stores.contacts.list()[0].name, "Alice");
+        assert!(stores.frozen.is_frozen(txid, 1));
+        let request = &stores.payment_requests.list()[0];
+        assert_eq!(request.amount, Some(50000));
+        let multisig_account = stores.multisig_accounts.get(BITCOIN_INDEX, 0).unwrap();
+        assert_eq!(multisig_account.label, "Family");
+        assert_eq!(multisig_account.cosigners.len(), 2);
+        assert_eq!(restored.kdf_params, kdf_params);
+        assert_ne!(restored.kdf_params, SecurityLevel::Low.kdf_params());
+        assert_eq!(restored.wipe_after, Some(10));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+//! Implements the address book of a wallet. A contact is paid either at a fixed
+//! address or at fresh addresses that are derived from its extended public key,
+//! so payments to the contact do not reuse addresses. The contacts are stored
+//! next to the key file of the wallet.
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::error::WalletError;
+use crate::keys::address::Address;
+use crate::keys::bip32::ExtendedPublicKey;
+use crate::utils::file;
+
+// the addresses of an extended public key beyond the next index that are recognized,
+// e.g., addresses that the contact handed out without the wallet
+pub static XPUB_LOOKAHEAD: u32 = 20;
+// the external chain of an account, whose addresses are used for receiving
+static EXTERNAL_CHAIN: u32 = 0;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Contact {
+    pub id: u64,
+    pub name: String,
+    pub address: String, // an address or an extended public key
+    pub notes: String,
+    pub testnet: bool,
+    #[serde(default)]
+    pub next_index: u32, // of the next address that is derived from the extended public key
+}
+
+impl Contact {
+    /// Returns true if the contact is paid at addresses derived from an extended public key.
+    pub fn is_xpub(&self) -> bool {
+        ExtendedPublicKey::import_key_from_base58_check(&self.address).is_ok()
+    }
+
+    /// Returns the address of the external chain of the extended public key at the index.
+    fn derive_address(&self, index: u32) -> Option<String> {
+        let public_key = ExtendedPublicKey::import_key_from_base58_check(&self.address).ok()?;
+        let child_key = public_key
+            .derive_child_key(EXTERNAL_CHAIN)
+            .and_then(|chain_key| chain_key.derive_child_key(index))
+            .ok()?;
+        Some(child_key.get_address().to_string())
+    }
+
+    /// Returns the addresses that belong to the contact, i.e., its address or the
+    /// addresses that were derived from its extended public key and the lookahead.
+    pub fn get_addresses(&self) -> Vec<String> {
+        if !self.is_xpub() {
+            return vec![self.address.clone()];
+        }
+        (0..self.next_index + XPUB_LOOKAHEAD)
+            .filter_map(|index| self.derive_address(index))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Contacts {
+    #[serde(default)]
+    next_id: u64,
+    #[serde(default)]
+    contacts: Vec<Contact>,
+}
+
+impl Contacts {
+    /// Adds a contact and returns its ID.
+    /// Returns an error if the name is empty, the address or extended public key is
+    /// invalid, or another contact has the same address.
+    pub fn add(&mut self, name: &str, address: &str, notes: &str) -> Result<u64, WalletError> {
+        let testnet = self.validate(None, name, address)?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.contacts.push(Contact {
+            id,
+            name: name.trim().to_string(),
+            address: address.trim().to_string(),
+            notes: notes.to_string(),
+            testnet,
+            next_index: 0,
+        });
+        Ok(id)
+    }
+
+    /// Updates the contact with the specified ID. The derivation of fresh addresses
+    /// starts over if the address changes.
+    pub fn update(
+        &mut self,
+        id: u64,
+        name: &str,
+        address: &str,
+        notes: &str,
+    ) -> Result<(), WalletError> {
+        let testnet = self.validate(Some(id), name, address)?;
+        let contact = match self.contacts.iter_mut().find(|contact| contact.id == id) {
+            Some(contact) => contact,
+            None => return Err(WalletError::new("contact_not_found_error")),
+        };
+        if contact.address != address.trim() {
+            contact.address = address.trim().to_string();
+            contact.next_index = 0;
+        }
+        contact.name = name.trim().to_string();
+        contact.notes = notes.to_string();
+        contact.testnet = testnet;
+        Ok(())
+    }
+
+    /// Removes the contact with the specified ID.
+    /// Returns false if there is no such contact.
+    pub fn remove(&mut self, id: u64) -> bool {
+        let count = self.contacts.len();
+        self.contacts.retain(|contact| contact.id != id);
+        self.contacts.len() != count
+    }
+
+    /// Returns the contacts in the order they were added.
+    pub fn list(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    /// Returns the address to pay the contact with the specified ID. For an extended
+    /// public key, a fresh address is derived, so the contact has to be saved afterwards.
+    pub fn next_address(&mut self, id: u64) -> Result<String, WalletError> {
+        let contact = match self.contacts.iter_mut().find(|contact| contact.id == id) {
+            Some(contact) => contact,
+            None => return Err(WalletError::new("contact_not_found_error")),
+        };
+        if !contact.is_xpub() {
+            return Ok(contact.address.clone());
+        }
+        match contact.derive_address(contact.next_index) {
+            Some(address) => {
+                contact.next_index += 1;
+                Ok(address)
+            }
+            None => Err(WalletError::new("invalid_address_error")),
+        }
+    }
+
+    /// Returns the contacts by their addresses, so the contacts of many transactions
+    /// can be resolved without deriving the addresses again.
+    pub fn get_address_map(&self) -> HashMap<String, &Contact> {
+        self.contacts
+            .iter()
+            .flat_map(|contact| {
+                contact
+                    .get_addresses()
+                    .into_iter()
+                    .map(move |address| (address, contact))
+            })
+            .collect()
+    }
+
+    /// Returns the contact that the address belongs to.
+    pub fn resolve(&self, address: &str) -> Option<&Contact> {
+        self.get_address_map().get(address).copied()
+    }
+
+    /// Checks the name and the address of a contact and returns whether the address
+    /// belongs to the test network. The contact with the ID is excluded from the
+    /// check for duplicates.
+    fn validate(&self, id: Option<u64>, name: &str, address: &str) -> Result<bool, WalletError> {
+        if name.trim().is_empty() {
+            return Err(WalletError::new("invalid_contact_name_error"));
+        }
+        let address = address.trim();
+        let testnet = match Address::from_str(address) {
+            Ok(parsed_address) => parsed_address.testnet,
+            Err(_) => match ExtendedPublicKey::import_key_from_base58_check(address) {
+                Ok(public_key) => public_key.testnet,
+                Err(_) => return Err(WalletError::new("invalid_address_error")),
+            },
+        };
+        if self
+            .contacts
+            .iter()
+            .any(|contact| Some(contact.id) != id && contact.address == address)
+        {
+            return Err(WalletError::new("contact_exists_error"));
+        }
+        Ok(testnet)
+    }
+
+    /// Saves the contacts to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let contacts_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &contacts_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the contacts from the specified path.
+    /// Returns no contacts if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Contacts, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Contacts::default());
+        }
+        let contacts_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&contacts_encoded)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Contacts, XPUB_LOOKAHEAD};
+    use crate::keys::bip32::ExtendedPrivateKey;
+    use std::fs;
+
+    #[test]
+    fn test_contacts() {
+        let path = std::env::temp_dir().join("ghorbu_test_contacts");
+        let _ = fs::remove_file(&path);
+        let mut contacts = Contacts::load(&path).unwrap();
+        let alice = contacts
+            .add("Alice", "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1", "")
+            .unwrap();
+        assert!(contacts
+            .add("Bob", "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1", "")
+            .is_err());
+        assert!(contacts
+            .add("Bob", "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ2", "")
+            .is_err());
+        assert!(contacts
+            .add(" ", "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1", "")
+            .is_err());
+
+        let private_key_b58 = "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U";
+        let xpub = ExtendedPrivateKey::import_key_from_base58_check(private_key_b58)
+            .unwrap()
+            .derive_public_key()
+            .to_base58_check();
+        let bob = contacts.add("Bob", &xpub, "Salary").unwrap();
+        contacts.save(&path).unwrap();
+
+        let mut contacts = Contacts::load(&path).unwrap();
+        assert_eq!(contacts.list().len(), 2);
+        assert!(!contacts.list()[1].testnet);
+        assert_eq!(
+            contacts.next_address(alice).unwrap(),
+            "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1"
+        );
+        let first_address = contacts.next_address(bob).unwrap();
+        let second_address = contacts.next_address(bob).unwrap();
+        assert_ne!(first_address, second_address);
+        assert_eq!(contacts.resolve(&second_address).unwrap().name, "Bob");
+        assert_eq!(
+            contacts.list()[1].get_addresses().len() as u32,
+            2 + XPUB_LOOKAHEAD
+        );
+
+        contacts
+            .update(
+                alice,
+                "Alice Smith",
+                "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1",
+                "",
+            )
+            .unwrap();
+        assert_eq!(
+            contacts
+                .resolve("mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1")
+                .unwrap()
+                .name,
+            "Alice Smith"
+        );
+        assert!(contacts.update(alice, "Alice", &xpub, "").is_err());
+        assert!(contacts.remove(alice));
+        assert!(!contacts.remove(alice));
+        assert!(contacts.next_address(alice).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
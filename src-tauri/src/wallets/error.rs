@@ -0,0 +1,59 @@
+//! Defines errors for the wallets module.
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+
+/// This error occurs when a wallet cannot be created or opened.
+pub struct WalletError {
+    message: String,
+}
+
+impl WalletError {
+    pub fn new(message: &str) -> WalletError {
+        WalletError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Debug for WalletError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for WalletError {}
+
+/// This error occurs when a step of the wallet setup is called out of order
+/// or the setup session is missing or expired.
+#[derive(PartialEq)]
+pub enum SetupError {
+    NoSession,
+    Expired,
+    OutOfOrder,
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let code = match self {
+            SetupError::NoSession => "setup_session_missing_error",
+            SetupError::Expired => "setup_session_expired_error",
+            SetupError::OutOfOrder => "setup_step_out_of_order_error",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+impl fmt::Debug for SetupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl Error for SetupError {}
@@ -0,0 +1,28 @@
+//! Stores the password of a wallet in the keychain of the operating system
+//! (macOS Keychain, Windows Credential Manager or the Secret Service on Linux),
+//! so that the user does not have to enter it for every transaction.
+//! Storing the password is optional and has to be requested by the user.
+use keyring::Entry;
+use std::error::Error;
+
+static SERVICE: &'static str = "ghorbu-wallet";
+
+/// Stores the password of the specified wallet in the keychain.
+pub fn remember_password(wallet_name: &str, password: &str) -> Result<(), Box<dyn Error>> {
+    Entry::new(SERVICE, wallet_name).set_password(password)?;
+    Ok(())
+}
+
+/// Returns the password of the specified wallet
+/// or `None` if it is not stored in the keychain.
+pub fn get_password(wallet_name: &str) -> Option<String> {
+    Entry::new(SERVICE, wallet_name).get_password().ok()
+}
+
+/// Removes the password of the specified wallet from the keychain.
+pub fn forget_password(wallet_name: &str) -> Result<(), Box<dyn Error>> {
+    match Entry::new(SERVICE, wallet_name).delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(Box::new(err)),
+    }
+}
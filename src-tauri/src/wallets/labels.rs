@@ -0,0 +1,254 @@
+//! Implements labels for addresses, transactions and outputs. The labels are
+//! stored next to the key file of the wallet and can be exported to and imported
+//! from the [BIP-329](https://github.com/bitcoin/bips/blob/master/bip-0329.mediawiki)
+//! format, so they can be moved to another wallet.
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::error::WalletError;
+use crate::utils::file;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelType {
+    Tx,
+    Addr,
+    Pubkey,
+    Input,
+    Output,
+    Xpub,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Label {
+    #[serde(rename = "type")]
+    pub label_type: LabelType,
+    #[serde(rename = "ref")]
+    pub reference: String, // the address, transaction ID or outpoint ("txid:vout")
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spendable: Option<bool>, // outputs only
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Labels {
+    labels: Vec<Label>,
+}
+
+impl Labels {
+    /// Sets the label of the specified reference. An empty label removes the label.
+    /// Returns an error if an outpoint is not of the form "txid:vout".
+    pub fn set(
+        &mut self,
+        label_type: LabelType,
+        reference: &str,
+        label: &str,
+    ) -> Result<(), WalletError> {
+        if matches!(label_type, LabelType::Input | LabelType::Output) {
+            let vout = reference.rsplit_once(':').map(|(_, vout)| vout);
+            if !vout.is_some_and(|vout| vout.parse::<u32>().is_ok()) {
+                return Err(WalletError::new("invalid_label_error"));
+            }
+        }
+        self.labels.retain(|existing| {
+            existing.label_type != label_type || existing.reference != reference
+        });
+        if !label.is_empty() {
+            self.labels.push(Label {
+                label_type,
+                reference: reference.to_string(),
+                label: label.to_string(),
+                origin: None,
+                spendable: None,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the label of the specified reference.
+    pub fn get(&self, label_type: LabelType, reference: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|label| label.label_type == label_type && label.reference == reference)
+            .map(|label| label.label.as_str())
+    }
+
+    /// Returns all labels.
+    pub fn get_labels(&self) -> Vec<Label> {
+        self.labels.clone()
+    }
+
+    /// Returns the labels in the BIP-329 format, i.e., one JSON object per line.
+    pub fn export_bip329(&self) -> String {
+        self.labels
+            .iter()
+            .map(|label| serde_json::to_string(label).unwrap() + "\n")
+            .collect()
+    }
+
+    /// Imports labels in the BIP-329 format. An imported label replaces the existing
+    /// label of the same reference. Returns the number of imported labels.
+    pub fn import_bip329(&mut self, s: &str) -> Result<usize, WalletError> {
+        let mut imported = Vec::new();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<Label>(line) {
+                Ok(label) => imported.push(label),
+                Err(_) => return Err(WalletError::new("invalid_labels_error")),
+            }
+        }
+        let count = imported.len();
+        for label in imported {
+            self.labels.retain(|existing| {
+                existing.label_type != label.label_type || existing.reference != label.reference
+            });
+            self.labels.push(label);
+        }
+        Ok(count)
+    }
+
+    /// Saves the labels to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let labels_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &labels_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the labels from the specified path.
+    /// Returns no labels if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Labels, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Labels::default());
+        }
+        let labels_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&labels_encoded)?)
+    }
+}
+
+impl FromStr for LabelType {
+    type Err = WalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tx" => Ok(LabelType::Tx),
+            "addr" => Ok(LabelType::Addr),
+            "pubkey" => Ok(LabelType::Pubkey),
+            "input" => Ok(LabelType::Input),
+            "output" => Ok(LabelType::Output),
+            "xpub" => Ok(LabelType::Xpub),
+            _ => Err(WalletError::new("invalid_label_error")),
+        }
+    }
+}
+
+impl fmt::Display for LabelType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            LabelType::Tx => "tx",
+            LabelType::Addr => "addr",
+            LabelType::Pubkey => "pubkey",
+            LabelType::Input => "input",
+            LabelType::Output => "output",
+            LabelType::Xpub => "xpub",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LabelType, Labels};
+    use std::fs;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_labels() {
+        let path = std::env::temp_dir().join("ghorbu_test_labels");
+        let _ = fs::remove_file(&path);
+        let mut labels = Labels::load(&path).unwrap();
+        assert!(labels.get_labels().is_empty());
+
+        let txid = "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd";
+        let outpoint = format!("{}:1", txid);
+        labels.set(LabelType::Tx, txid, "Rent").unwrap();
+        labels.set(LabelType::Tx, txid, "Rent for May").unwrap();
+        labels.set(LabelType::Output, &outpoint, "Change").unwrap();
+        labels
+            .set(
+                LabelType::Addr,
+                "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1",
+                "Savings",
+            )
+            .unwrap();
+        assert!(labels.set(LabelType::Output, txid, "Invalid").is_err());
+        labels.save(&path).unwrap();
+
+        let mut labels = Labels::load(&path).unwrap();
+        assert_eq!(labels.get_labels().len(), 3);
+        assert_eq!(labels.get(LabelType::Tx, txid), Some("Rent for May"));
+        assert_eq!(labels.get(LabelType::Output, &outpoint), Some("Change"));
+        assert_eq!(labels.get(LabelType::Input, &outpoint), None);
+        labels
+            .set(LabelType::Addr, "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1", "")
+            .unwrap();
+        assert_eq!(
+            labels.get(LabelType::Addr, "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1"),
+            None
+        );
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(LabelType::from_str("output").unwrap(), LabelType::Output);
+        assert_eq!(LabelType::Xpub.to_string(), "xpub");
+        assert!(LabelType::from_str("utxo").is_err());
+    }
+
+    #[test]
+    fn test_bip329() {
+        // examples of BIP-329
+        let jsonl = r#"{"type":"tx","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd","label":"Transaction","origin":"wpkh([d34db33f/84'/0'/0'])"}
+{"type":"addr","ref":"bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c","label":"Address"}
+{"type":"output","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0","label":"Output","spendable":false}
+"#;
+        let mut labels = Labels::default();
+        labels
+            .set(
+                LabelType::Addr,
+                "bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c",
+                "Old label",
+            )
+            .unwrap();
+        assert_eq!(labels.import_bip329(jsonl).unwrap(), 3);
+        assert_eq!(labels.get_labels().len(), 3);
+        assert_eq!(
+            labels.get(
+                LabelType::Addr,
+                "bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c"
+            ),
+            Some("Address")
+        );
+        let output = labels
+            .get_labels()
+            .into_iter()
+            .find(|label| label.label_type == LabelType::Output)
+            .unwrap();
+        assert_eq!(output.spendable, Some(false));
+
+        // the export round-trips
+        let mut imported = Labels::default();
+        imported.import_bip329(&labels.export_bip329()).unwrap();
+        assert_eq!(imported.get_labels(), labels.get_labels());
+        assert!(labels
+            .export_bip329()
+            .contains(r#""origin":"wpkh([d34db33f/84'/0'/0'])""#));
+
+        assert!(labels.import_bip329("{\"type\":\"tx\"}").is_err());
+        assert!(labels.import_bip329("not json").is_err());
+    }
+}
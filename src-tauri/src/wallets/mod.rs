@@ -0,0 +1,142 @@
+//! Manages multiple named wallets. Every wallet has its own key file.
+//! The default wallet is stored in `~/.bitcoinwallet`, so existing wallets
+//! keep working, while all other wallets are stored in `~/.bitcoinwallets/`.
+//! Auxiliary files of a wallet (e.g. the broadcast journal) are stored next
+//! to its key file, with the name of the auxiliary file as an extension.
+pub mod backup;
+pub mod contacts;
+pub mod error;
+pub mod keychain;
+pub mod labels;
+pub mod payment_requests;
+pub mod settings;
+pub mod setup;
+pub mod watch_list;
+
+use error::WalletError;
+use std::fs;
+use std::path::PathBuf;
+
+pub static DEFAULT_WALLET: &'static str = "default";
+static DEFAULT_KEY_PATH: &'static str = ".bitcoinwallet";
+static WALLETS_DIR: &'static str = ".bitcoinwallets";
+static MAX_NAME_LENGTH: usize = 64;
+
+pub struct WalletManager {
+    home: PathBuf,
+}
+
+impl WalletManager {
+    /// Creates a wallet manager for the wallets in the specified home directory.
+    pub fn new<P: Into<PathBuf>>(home: P) -> WalletManager {
+        WalletManager { home: home.into() }
+    }
+
+    /// Checks that the wallet name only consists of letters,
+    /// digits, dashes and underscores.
+    pub fn validate_name(name: &str) -> Result<(), WalletError> {
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+            return Err(WalletError::new("invalid_wallet_name_error"));
+        }
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(WalletError::new("invalid_wallet_name_error"));
+        }
+        Ok(())
+    }
+
+    /// Returns the path of the key file of the specified wallet.
+    pub fn key_path(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_WALLET {
+            self.home.join(DEFAULT_KEY_PATH)
+        } else {
+            self.home.join(WALLETS_DIR).join(name)
+        }
+    }
+
+    /// Returns the path of the specified auxiliary file of the specified wallet.
+    pub fn auxiliary_path(&self, name: &str, extension: &str) -> PathBuf {
+        let mut path = self.key_path(name).into_os_string();
+        path.push(".");
+        path.push(extension);
+        PathBuf::from(path)
+    }
+
+    /// Returns true if the key file of the specified wallet exists.
+    pub fn exists(&self, name: &str) -> bool {
+        self.key_path(name).exists()
+    }
+
+    /// Returns the names of all existing wallets in alphabetical order.
+    /// The default wallet comes first.
+    pub fn list_wallets(&self) -> Vec<String> {
+        let mut wallets = Vec::new();
+        if let Ok(entries) = fs::read_dir(self.home.join(WALLETS_DIR)) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                // auxiliary files contain a dot and are skipped
+                if entry.path().is_file() && WalletManager::validate_name(&name).is_ok() {
+                    wallets.push(name);
+                }
+            }
+        }
+        wallets.sort();
+        if self.exists(DEFAULT_WALLET) {
+            wallets.retain(|name| name != DEFAULT_WALLET);
+            wallets.insert(0, DEFAULT_WALLET.to_string());
+        }
+        wallets
+    }
+
+    /// Prepares the creation of the specified wallet.
+    /// Returns an error if the name is invalid or the wallet already exists.
+    pub fn create_wallet(&self, name: &str) -> Result<PathBuf, WalletError> {
+        WalletManager::validate_name(name)?;
+        if self.exists(name) {
+            return Err(WalletError::new("wallet_exists_error"));
+        }
+        if name != DEFAULT_WALLET && fs::create_dir_all(self.home.join(WALLETS_DIR)).is_err() {
+            return Err(WalletError::new("io_error"));
+        }
+        Ok(self.key_path(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WalletManager, DEFAULT_WALLET};
+    use std::fs;
+
+    #[test]
+    fn test_wallet_manager() {
+        let home = std::env::temp_dir().join("ghorbu_test_wallet_manager");
+        let _ = fs::remove_dir_all(&home);
+        fs::create_dir_all(&home).unwrap();
+        let manager = WalletManager::new(&home);
+        assert!(manager.list_wallets().is_empty());
+        assert!(manager.create_wallet("../savings").is_err());
+        assert!(manager.create_wallet("").is_err());
+
+        let path = manager.create_wallet(DEFAULT_WALLET).unwrap();
+        assert_eq!(path, home.join(".bitcoinwallet"));
+        fs::write(&path, b"key").unwrap();
+        let path = manager.create_wallet("savings").unwrap();
+        fs::write(&path, b"key").unwrap();
+        fs::write(manager.auxiliary_path("savings", "journal"), b"journal").unwrap();
+        let path = manager.create_wallet("cold_storage").unwrap();
+        fs::write(&path, b"key").unwrap();
+        assert!(manager.create_wallet("savings").is_err());
+
+        assert_eq!(
+            manager.auxiliary_path(DEFAULT_WALLET, "journal"),
+            home.join(".bitcoinwallet.journal")
+        );
+        assert_eq!(
+            manager.list_wallets(),
+            vec![DEFAULT_WALLET, "cold_storage", "savings"]
+        );
+        fs::remove_dir_all(home).unwrap();
+    }
+}
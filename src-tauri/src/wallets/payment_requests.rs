@@ -0,0 +1,262 @@
+//! Implements the payment requests of a wallet. Every request has a fresh receive
+//! address, so the payments to the request can be told apart from other payments.
+//! The amount that the address received is tracked, so the wallet can show whether
+//! the request has been paid. The requests are stored next to the key file of the wallet.
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use super::error::WalletError;
+use crate::transactions::payment_uri::PaymentUri;
+use crate::transactions::transaction::Transaction;
+use crate::utils::file;
+
+static MAX_AMOUNT: u64 = 21000000 * 100000000; // in satoshis
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PaymentRequest {
+    pub id: u64,
+    pub coin_type_index: u32,
+    pub account_index: u32,
+    pub address: String,
+    pub amount: Option<u64>, // in satoshis, any amount is requested if there is none
+    pub label: Option<String>,
+    pub created_at: u64, // UNIX timestamp in seconds
+    #[serde(default)]
+    pub received: u64, // by the address, including unconfirmed transactions
+    #[serde(default)]
+    pub received_confirmed: u64,
+}
+
+impl PaymentRequest {
+    /// Returns the payment URI of the request.
+    pub fn to_payment_uri(&self) -> PaymentUri {
+        PaymentUri {
+            address: self.address.clone(),
+            amount: self.amount,
+            label: self.label.clone(),
+            message: None,
+            payjoin_endpoint: None,
+        }
+    }
+
+    /// Returns true if the requested amount has been received, including unconfirmed
+    /// transactions. A request without an amount is paid by any payment.
+    pub fn is_paid(&self) -> bool {
+        self.received >= self.amount.unwrap_or(1)
+    }
+
+    /// Returns true if the requested amount has been received in confirmed transactions.
+    pub fn is_confirmed(&self) -> bool {
+        self.received_confirmed >= self.amount.unwrap_or(1)
+    }
+
+    /// Sets the amounts that the address of the request received in the transactions.
+    /// The transactions of the address are passed, so a transaction that is
+    /// passed twice is only counted once.
+    pub fn track(&mut self, txs: &[Transaction]) {
+        let mut txids = HashSet::new();
+        self.received = 0;
+        self.received_confirmed = 0;
+        for tx in txs {
+            if !txids.insert(&tx.txid) {
+                continue;
+            }
+            let confirmed = tx.status.as_ref().is_some_and(|status| status.confirmed);
+            let received: u64 = tx
+                .vout
+                .iter()
+                .filter(|txout| txout.scriptpubkey_address == self.address)
+                .map(|txout| txout.value)
+                .sum();
+            self.received += received;
+            if confirmed {
+                self.received_confirmed += received;
+            }
+        }
+    }
+
+    /// Returns the request with its payment URI and the payload of its QR code.
+    pub fn details(&self) -> PaymentRequestDetails {
+        let payment_uri = self.to_payment_uri();
+        PaymentRequestDetails {
+            request: self.clone(),
+            uri: payment_uri.to_uri(),
+            qr_payload: payment_uri.to_qr_payload(),
+            paid: self.is_paid(),
+            confirmed: self.is_confirmed(),
+        }
+    }
+}
+
+/// A payment request as it is shown to the user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PaymentRequestDetails {
+    pub request: PaymentRequest,
+    pub uri: String,
+    pub qr_payload: String, // the URI with the scheme and a Bech32 address in uppercase
+    pub paid: bool,
+    pub confirmed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PaymentRequests {
+    #[serde(default)]
+    next_id: u64,
+    #[serde(default)]
+    requests: Vec<PaymentRequest>,
+}
+
+impl PaymentRequests {
+    /// Adds a request for the amount to the fresh receive address and returns it.
+    /// Returns an error if the amount is zero or exceeds the supply of bitcoin.
+    pub fn add(
+        &mut self,
+        coin_type_index: u32,
+        account_index: u32,
+        address: &str,
+        amount: Option<u64>,
+        label: Option<&str>,
+        created_at: u64,
+    ) -> Result<PaymentRequest, WalletError> {
+        if let Some(amount) = amount {
+            if amount == 0 || amount > MAX_AMOUNT {
+                return Err(WalletError::new("invalid_amount_error"));
+            }
+        }
+        // an empty label is omitted from the URI
+        let label = label
+            .map(|label| label.trim())
+            .filter(|label| !label.is_empty())
+            .map(|label| label.to_string());
+        let request = PaymentRequest {
+            id: self.next_id,
+            coin_type_index,
+            account_index,
+            address: address.to_string(),
+            amount,
+            label,
+            created_at,
+            received: 0,
+            received_confirmed: 0,
+        };
+        self.next_id += 1;
+        self.requests.push(request.clone());
+        Ok(request)
+    }
+
+    /// Removes the request with the specified ID.
+    /// Returns false if there is no such request.
+    pub fn remove(&mut self, id: u64) -> bool {
+        let count = self.requests.len();
+        self.requests.retain(|request| request.id != id);
+        self.requests.len() != count
+    }
+
+    /// Returns the requests in the order they were created.
+    pub fn list(&self) -> &[PaymentRequest] {
+        &self.requests
+    }
+
+    /// Returns the requests in the order they were created, so the received
+    /// amounts can be tracked.
+    pub fn list_mut(&mut self) -> &mut [PaymentRequest] {
+        &mut self.requests
+    }
+
+    /// Saves the requests to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let requests_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &requests_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the requests from the specified path.
+    /// Returns no requests if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PaymentRequests, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(PaymentRequests::default());
+        }
+        let requests_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&requests_encoded)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaymentRequests;
+    use crate::transactions::transaction::{Transaction, TransactionOutput, TransactionStatus};
+    use std::fs;
+
+    fn transaction(txid: &str, address: &str, value: u64, confirmed: bool) -> Transaction {
+        Transaction {
+            txid: txid.to_string(),
+            version: 2,
+            locktime: 0,
+            vin: Vec::new(),
+            vout: vec![TransactionOutput {
+                scriptpubkey: String::new(),
+                scriptpubkey_asm: String::new(),
+                scriptpubkey_type: String::new(),
+                scriptpubkey_address: address.to_string(),
+                value,
+            }],
+            size: None,
+            weight: None,
+            fee: Some(0),
+            status: Some(TransactionStatus {
+                confirmed,
+                block_height: None,
+                block_hash: None,
+                block_time: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_payment_requests() {
+        let path = std::env::temp_dir().join("ghorbu_test_payment_requests");
+        let _ = fs::remove_file(&path);
+        let address = "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1";
+        let mut requests = PaymentRequests::load(&path).unwrap();
+        assert!(requests.add(1, 0, address, Some(0), None, 0).is_err());
+        let request = requests
+            .add(1, 0, address, Some(50000), Some("Invoice 42 "), 1700000000)
+            .unwrap();
+        assert_eq!(request.label, Some("Invoice 42".to_string()));
+        assert_eq!(
+            request.details().uri,
+            "bitcoin:mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1?amount=0.0005&label=Invoice%2042"
+        );
+        let any_amount = requests.add(1, 0, address, None, Some(""), 0).unwrap();
+        assert_eq!(any_amount.label, None);
+        requests.save(&path).unwrap();
+
+        let mut requests = PaymentRequests::load(&path).unwrap();
+        assert_eq!(requests.list().len(), 2);
+        let txs = vec![
+            transaction("a", address, 30000, true),
+            transaction("b", address, 20000, false),
+            transaction("b", address, 20000, false),
+            transaction("c", "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ2", 70000, true),
+        ];
+        for request in requests.list_mut() {
+            request.track(&txs);
+        }
+        let request = &requests.list()[0];
+        assert_eq!(request.received, 50000);
+        assert_eq!(request.received_confirmed, 30000);
+        assert!(request.is_paid());
+        assert!(!request.is_confirmed());
+        assert!(requests.list()[1].is_confirmed());
+
+        assert!(requests.remove(0));
+        assert!(!requests.remove(0));
+        assert_eq!(requests.list().len(), 1);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+//! Implements the settings of a wallet that are not part of the key hierarchy,
+//! e.g., the coin selection strategy and the minimum number of confirmations.
+//! The settings are stored next to the key file of the wallet.
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::coin_selection::confirmations::ConfirmationPolicy;
+use crate::coin_selection::strategy::CoinSelectionStrategy;
+use crate::utils::file;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct WalletSettings {
+    #[serde(default)]
+    pub coin_selection_strategy: CoinSelectionStrategy,
+    #[serde(default)]
+    pub confirmation_policy: ConfirmationPolicy,
+}
+
+impl WalletSettings {
+    /// Saves the settings to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let settings_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &settings_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the settings from the specified path.
+    /// Returns the default settings if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<WalletSettings, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(WalletSettings::default());
+        }
+        let settings_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&settings_encoded)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WalletSettings;
+    use crate::coin_selection::confirmations::ConfirmationPolicy;
+    use crate::coin_selection::strategy::CoinSelectionStrategy;
+    use std::fs;
+
+    #[test]
+    fn test_settings() {
+        let path = std::env::temp_dir().join("ghorbu_test_settings");
+        let _ = fs::remove_file(&path);
+        let mut settings = WalletSettings::load(&path).unwrap();
+        assert_eq!(
+            settings.coin_selection_strategy,
+            CoinSelectionStrategy::BranchAndBound
+        );
+        settings.coin_selection_strategy = CoinSelectionStrategy::Knapsack;
+        settings.save(&path).unwrap();
+        let settings = WalletSettings::load(&path).unwrap();
+        assert_eq!(
+            settings.coin_selection_strategy,
+            CoinSelectionStrategy::Knapsack
+        );
+        // settings that are missing in the file take their default values
+        fs::write(&path, b"{}").unwrap();
+        let settings = WalletSettings::load(&path).unwrap();
+        assert_eq!(
+            settings.coin_selection_strategy,
+            CoinSelectionStrategy::BranchAndBound
+        );
+        assert_eq!(settings.confirmation_policy, ConfirmationPolicy::default());
+        fs::remove_file(path).unwrap();
+    }
+}
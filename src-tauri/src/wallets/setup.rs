@@ -0,0 +1,150 @@
+//! Implements the state machine of the wallet creation. The steps have to be
+//! completed in order:
+//!
+//! ```text
+//! start (generate mnemonic) -> confirm backup -> set passphrase -> finalize
+//! ```
+//!
+//! A session that is inactive for longer than the timeout expires, so the
+//! mnemonic is not kept in memory indefinitely. The passphrase is wiped
+//! when the session is dropped.
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+use super::error::SetupError;
+use crate::keys::bip39;
+
+pub static SESSION_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetupStep {
+    Started,
+    BackupConfirmed,
+    PassphraseSet,
+}
+
+pub struct WalletSetupSession {
+    mnemonic: Vec<&'static str>,
+    passphrase: String,
+    step: SetupStep,
+    timeout: Duration,
+    last_activity: Instant,
+}
+
+impl WalletSetupSession {
+    /// Starts a session with a new mnemonic.
+    pub fn start() -> WalletSetupSession {
+        WalletSetupSession::start_with_timeout(SESSION_TIMEOUT)
+    }
+
+    /// Starts a session with a new mnemonic that expires after the specified timeout.
+    pub fn start_with_timeout(timeout: Duration) -> WalletSetupSession {
+        WalletSetupSession {
+            mnemonic: bip39::generate_mnemonic(256, None),
+            passphrase: String::new(),
+            step: SetupStep::Started,
+            timeout,
+            last_activity: Instant::now(),
+        }
+    }
+
+    /// Returns the mnemonic of the session.
+    pub fn get_mnemonic(&self) -> String {
+        self.mnemonic.join(" ")
+    }
+
+    /// Returns the current step of the session.
+    pub fn get_step(&self) -> SetupStep {
+        self.step
+    }
+
+    /// Returns true if the session was inactive for longer than the timeout.
+    pub fn is_expired(&self) -> bool {
+        self.last_activity.elapsed() >= self.timeout
+    }
+
+    /// Confirms that the user has backed up the mnemonic.
+    pub fn confirm_backup(&mut self) -> Result<(), SetupError> {
+        self.advance(SetupStep::Started, SetupStep::BackupConfirmed)
+    }
+
+    /// Sets the passphrase that is used to derive the seed.
+    pub fn set_passphrase(&mut self, passphrase: String) -> Result<(), SetupError> {
+        self.advance(SetupStep::BackupConfirmed, SetupStep::PassphraseSet)?;
+        self.passphrase = passphrase;
+        Ok(())
+    }
+
+    /// Completes the session and returns the seed.
+    pub fn finalize(self) -> Result<[u8; 64], SetupError> {
+        if self.is_expired() {
+            return Err(SetupError::Expired);
+        }
+        if self.step != SetupStep::PassphraseSet {
+            return Err(SetupError::OutOfOrder);
+        }
+        Ok(bip39::generate_seed(
+            self.mnemonic.clone(),
+            &self.passphrase,
+        ))
+    }
+
+    /// Moves the session from the expected step to the next step.
+    fn advance(&mut self, expected: SetupStep, next: SetupStep) -> Result<(), SetupError> {
+        if self.is_expired() {
+            return Err(SetupError::Expired);
+        }
+        if self.step != expected {
+            return Err(SetupError::OutOfOrder);
+        }
+        self.step = next;
+        self.last_activity = Instant::now();
+        Ok(())
+    }
+}
+
+impl Drop for WalletSetupSession {
+    fn drop(&mut self) {
+        self.passphrase.zeroize();
+        // the words point into the word list, so only the references are cleared
+        self.mnemonic.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SetupStep, WalletSetupSession};
+    use crate::keys::bip39;
+    use crate::wallets::error::SetupError;
+    use std::time::Duration;
+
+    #[test]
+    fn test_setup_session() {
+        let mut session = WalletSetupSession::start();
+        let mnemonic = session.get_mnemonic();
+        assert_eq!(mnemonic.split(' ').count(), 24);
+        assert_eq!(
+            session.set_passphrase("passphrase".to_string()),
+            Err(SetupError::OutOfOrder)
+        );
+        session.confirm_backup().unwrap();
+        assert_eq!(session.confirm_backup(), Err(SetupError::OutOfOrder));
+        session.set_passphrase("passphrase".to_string()).unwrap();
+        assert_eq!(session.get_step(), SetupStep::PassphraseSet);
+        let seed = session.finalize().unwrap();
+        assert_eq!(
+            seed,
+            bip39::generate_seed(mnemonic.split(' ').collect(), "passphrase")
+        );
+
+        let session = WalletSetupSession::start();
+        assert_eq!(session.finalize().err(), Some(SetupError::OutOfOrder));
+    }
+
+    #[test]
+    fn test_setup_session_expired() {
+        let mut session = WalletSetupSession::start_with_timeout(Duration::ZERO);
+        assert!(session.is_expired());
+        assert_eq!(session.confirm_backup(), Err(SetupError::Expired));
+    }
+}
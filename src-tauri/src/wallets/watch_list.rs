@@ -0,0 +1,139 @@
+//! Implements a watch list of external addresses, i.e., addresses that are
+//! not derived from the wallet. Their balances and transactions are shown,
+//! but they cannot be spent. The watch list is stored next to the key file
+//! of the wallet.
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::error::WalletError;
+use crate::keys::address::Address;
+use crate::utils::file;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchedAddress {
+    pub address: String,
+    pub label: String,
+    pub testnet: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WatchedAddressBalance {
+    #[serde(flatten)]
+    pub watched_address: WatchedAddress,
+    pub balance: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct WatchList {
+    addresses: Vec<WatchedAddress>,
+}
+
+impl WatchList {
+    /// Adds the specified address to the watch list.
+    /// Returns an error if the address is invalid or already watched.
+    pub fn add(&mut self, address: &str, label: String) -> Result<(), WalletError> {
+        let parsed_address = match Address::from_str(address) {
+            Ok(address) => address,
+            Err(_) => return Err(WalletError::new("invalid_address_error")),
+        };
+        if self
+            .addresses
+            .iter()
+            .any(|watched| watched.address == address)
+        {
+            return Err(WalletError::new("address_watched_error"));
+        }
+        self.addresses.push(WatchedAddress {
+            address: address.to_string(),
+            label,
+            testnet: parsed_address.testnet,
+        });
+        Ok(())
+    }
+
+    /// Removes the specified address from the watch list.
+    /// Returns false if the address was not watched.
+    pub fn remove(&mut self, address: &str) -> bool {
+        let count = self.addresses.len();
+        self.addresses.retain(|watched| watched.address != address);
+        self.addresses.len() != count
+    }
+
+    /// Returns the watched addresses for the specified network.
+    pub fn get_addresses(&self, testnet: bool) -> Vec<WatchedAddress> {
+        self.addresses
+            .iter()
+            .filter(|watched| watched.testnet == testnet)
+            .cloned()
+            .collect()
+    }
+
+    /// Saves the watch list to the specified path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let watch_list_encoded = serde_json::to_vec(self)?;
+        file::write_atomic(path, &watch_list_encoded)?;
+        Ok(())
+    }
+
+    /// Loads the watch list from the specified path.
+    /// Returns an empty watch list if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<WatchList, Box<dyn Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(WatchList::default());
+        }
+        let watch_list_encoded = fs::read(path)?;
+        Ok(serde_json::from_slice(&watch_list_encoded)?)
+    }
+}
+
+impl WatchedAddress {
+    /// Parses the watched address.
+    pub fn get_address(&self) -> Address {
+        Address::from_str(&self.address).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatchList;
+    use std::fs;
+
+    #[test]
+    fn test_watch_list() {
+        let path = std::env::temp_dir().join("ghorbu_test_watch_list");
+        let _ = fs::remove_file(&path);
+        let mut watch_list = WatchList::load(&path).unwrap();
+        assert!(watch_list.get_addresses(true).is_empty());
+
+        watch_list
+            .add(
+                "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1",
+                "Cold storage".to_string(),
+            )
+            .unwrap();
+        assert!(watch_list
+            .add(
+                "mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1",
+                "Duplicate".to_string()
+            )
+            .is_err());
+        assert!(watch_list
+            .add("mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ2", "Invalid".to_string())
+            .is_err());
+        watch_list.save(&path).unwrap();
+
+        let mut watch_list = WatchList::load(&path).unwrap();
+        let addresses = watch_list.get_addresses(true);
+        assert_eq!(addresses.len(), 1);
+        assert_eq!(addresses[0].label, "Cold storage");
+        assert!(watch_list.get_addresses(false).is_empty());
+        assert!(watch_list.remove("mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1"));
+        assert!(!watch_list.remove("mqM3dJApCknasvUkPEnALkVBCDjsFLmWQ1"));
+        fs::remove_file(path).unwrap();
+    }
+}
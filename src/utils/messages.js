@@ -0,0 +1,19 @@
+import { invoke } from '@tauri-apps/api';
+
+let catalog = null;
+
+// Loads the message catalog for the locale of the user.
+export const loadMessages = async () => {
+    if (catalog === null) {
+        catalog = await invoke('get_message_catalog', {locale: navigator.language});
+    }
+    return catalog;
+}
+
+// Returns the message for the specified error code.
+export const getMessage = (code) => {
+    if (catalog === null) {
+        return code;
+    }
+    return catalog[code] ?? catalog['other_error'];
+}